@@ -0,0 +1,121 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A frontend for [`EditorCore`] without a terminal UI: It reads one command per line from
+//! stdin and prints the document with its syntax colors.
+//!
+//! ```text
+//! $ cargo run --example headless -- src/main.rs
+//! > goto 3
+//! > insert // TODO\n
+//! > find TODO
+//! > print
+//! > save
+//! ```
+
+use std::io::{self, BufRead as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::{env, process};
+
+use edit::arena;
+use edit::buffer::SearchOptions;
+use edit::editor::EditorCore;
+use edit::helpers::{CoordType, Point};
+
+const HELP: &str = "\
+open PATH      open a file
+goto LINE      move the cursor to the start of the line
+insert TEXT    type the text, with \\n for newlines
+find TEXT      select the next hit
+undo, redo
+print          print the document
+save [PATH]    save the document
+quit";
+
+fn main() {
+    if let Err(err) = arena::init(128 * 1024 * 1024) {
+        eprintln!("{err:?}");
+        process::exit(1);
+    }
+
+    let mut editor = EditorCore::new();
+    let mut current = None;
+    for path in env::args_os().skip(1) {
+        current = Some(open(&mut editor, Path::new(&path)));
+    }
+    let mut current = match current {
+        Some(index) => index,
+        None => editor.new_document().expect("out of memory"),
+    };
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        _ = io::stdout().flush();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+
+        let mut tb = editor.document(current).buffer.borrow_mut();
+        match command {
+            "open" => {
+                drop(tb);
+                current = open(&mut editor, Path::new(arg));
+            }
+            "goto" => match arg.parse::<CoordType>() {
+                Ok(line) => tb.cursor_move_to_logical(Point { x: 0, y: line.max(1) - 1 }),
+                Err(err) => println!("{err}"),
+            },
+            "insert" => tb.write(arg.replace("\\n", "\n").as_bytes(), true),
+            "find" => {
+                if let Err(err) = tb.find_and_select(arg, SearchOptions::default()) {
+                    println!("{err:?}");
+                }
+            }
+            "undo" => tb.undo(),
+            "redo" => tb.redo(),
+            "print" => {
+                let lines = tb.logical_line_count();
+                drop(tb);
+                print(&mut editor, current, lines);
+            }
+            "save" => {
+                drop(tb);
+                let path = (!arg.is_empty()).then(|| PathBuf::from(arg));
+                if let Err(err) = editor.save(current, path) {
+                    println!("{err:?}");
+                }
+            }
+            "quit" => break,
+            _ => println!("{HELP}"),
+        }
+    }
+}
+
+fn open(editor: &mut EditorCore, path: &Path) -> usize {
+    match editor.open(path) {
+        Ok(index) => index,
+        Err(err) => {
+            eprintln!("{}: {err:?}", path.display());
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the document with 24-bit ANSI colors, with the cursor's line marked.
+fn print(editor: &mut EditorCore, index: usize, lines: CoordType) {
+    let cursor = editor.document(index).buffer.borrow().cursor_logical_pos();
+    let mut out = String::new();
+    for line in 0..lines {
+        out.push_str(if line == cursor.y { "> " } else { "  " });
+        for (style, text) in editor.highlight_line(index, line) {
+            let c = style.foreground;
+            out.push_str(&format!("\x1b[38;2;{};{};{}m{text}", c.r, c.g, c.b));
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    print!("{out}");
+}
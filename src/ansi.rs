@@ -0,0 +1,164 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Interprets the ANSI escape sequences in text like build logs or `script` output,
+//! so that it can be shown with its colors instead of as escape garbage.
+//!
+//! See [`crate::buffer::TextBuffer::set_ansi_view()`].
+
+use std::ops::Range;
+
+use crate::vt::{self, Token};
+
+/// A color set via SGR (`ESC [ ... m`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Color {
+    /// An entry of the 256-color palette. The first 16 are the terminal's theme colors.
+    Indexed(u8),
+    /// A 24-bit color in the framebuffer's 0xAABBGGRR layout.
+    Rgb(u32),
+}
+
+/// The SGR state of a piece of text. The default is the unstyled text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Self::default();
+            return;
+        }
+
+        let mut it = params.iter().copied();
+        while let Some(p) = it.next() {
+            match p {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.faint = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.reverse = true,
+                22 => {
+                    self.bold = false;
+                    self.faint = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.reverse = false,
+                30..=37 => self.fg = Some(Color::Indexed((p - 30) as u8)),
+                38 => self.fg = Self::parse_extended_color(&mut it),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(Color::Indexed((p - 40) as u8)),
+                48 => self.bg = Self::parse_extended_color(&mut it),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(Color::Indexed((p - 90 + 8) as u8)),
+                100..=107 => self.bg = Some(Color::Indexed((p - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Parses the arguments of SGR 38 and 48: `5;n` for the 256-color palette and `2;r;g;b`.
+    fn parse_extended_color(it: &mut impl Iterator<Item = u16>) -> Option<Color> {
+        let mut next = || it.next().unwrap_or(0).min(255) as u32;
+        match next() {
+            5 => Some(Color::Indexed(next() as u8)),
+            2 => {
+                let (r, g, b) = (next(), next(), next());
+                Some(Color::Rgb(0xff000000 | b << 16 | g << 8 | r))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Text with its escape sequences removed, see [`strip()`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stripped {
+    pub text: String,
+    /// The styled parts of `text` as byte ranges, sorted and non-overlapping.
+    /// They never span across lines and unstyled text has none.
+    pub spans: Vec<(Range<usize>, Style)>,
+}
+
+/// Removes all escape sequences from `input` and turns the SGR ones into style spans.
+///
+/// Newlines and tabs are kept. Other control characters, including a carriage
+/// return that isn't part of a CRLF, are dropped, as they'd only show up as garbage.
+pub fn strip(input: &str) -> Stripped {
+    let mut parser = vt::Parser::new();
+    let mut stream = parser.parse(input);
+    let mut result = Stripped { text: String::with_capacity(input.len()), spans: Vec::new() };
+    let mut style = Style::default();
+    let mut pending_cr = false;
+
+    while let Some(token) = stream.next() {
+        let cr = std::mem::take(&mut pending_cr);
+
+        match token {
+            Token::Text(text) => {
+                let beg = result.text.len();
+                result.text.push_str(text);
+                let end = result.text.len();
+
+                if style != Style::default() {
+                    match result.spans.last_mut() {
+                        Some((range, s)) if range.end == beg && *s == style => range.end = end,
+                        _ => result.spans.push((beg..end, style)),
+                    }
+                }
+            }
+            Token::Ctrl('\r') => pending_cr = true,
+            Token::Ctrl('\n') => result.text.push_str(if cr { "\r\n" } else { "\n" }),
+            Token::Ctrl('\t') => result.text.push('\t'),
+            Token::Csi(csi) if csi.final_byte == 'm' && csi.private_byte == '\0' => {
+                style.apply_sgr(&csi.params[..csi.param_count]);
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red() -> Style {
+        Style { fg: Some(Color::Indexed(1)), ..Default::default() }
+    }
+
+    #[test]
+    fn test_strip_sgr() {
+        let s = strip("\x1b[31mred\x1b[0m plain \x1b[1;31mbold\x1b[mtail");
+        assert_eq!(s.text, "red plain boldtail");
+        assert_eq!(s.spans, vec![(0..3, red()), (10..14, Style { bold: true, ..red() })]);
+    }
+
+    #[test]
+    fn test_strip_extended_colors() {
+        let s = strip("\x1b[38;5;208ma\x1b[48;2;1;2;3mb\x1b[39;49mc");
+        let orange = Style { fg: Some(Color::Indexed(208)), ..Default::default() };
+        let bg = Some(Color::Rgb(0xff030201));
+        assert_eq!(s.text, "abc");
+        assert_eq!(s.spans, vec![(0..1, orange), (1..2, Style { bg, ..orange })]);
+    }
+
+    #[test]
+    fn test_strip_lines() {
+        // The style carries over to the next line, but each line gets its own span.
+        let s = strip("\x1b[31mone\r\ntwo\x1b[K\rthree\x1b]0;title\x07\x1b[0m\n");
+        assert_eq!(s.text, "one\r\ntwothree\n");
+        assert_eq!(s.spans, vec![(0..3, red()), (5..13, red())]);
+    }
+}
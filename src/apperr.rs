@@ -8,6 +8,8 @@ use std::{io, result};
 use crate::sys;
 
 pub const APP_ICU_MISSING: Error = Error::new_app(0);
+/// See [`crate::buffer::TextBuffer::set_hex_view()`].
+pub const APP_HEX_INVALID: Error = Error::new_app(4);
 
 /// Edit's transparent `Result` type.
 pub type Result<T> = result::Result<T, Error>;
@@ -13,5 +13,7 @@ mod string;
 pub use self::debug::Arena;
 #[cfg(any(doc, not(debug_assertions)))]
 pub use self::release::Arena;
+#[cfg(test)]
+pub use self::scratch::init_for_test;
 pub use self::scratch::{ScratchArena, init, scratch_arena};
 pub use self::string::ArenaString;
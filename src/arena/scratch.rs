@@ -23,6 +23,17 @@ pub fn init(capacity: usize) -> apperr::Result<()> {
     Ok(())
 }
 
+/// Initializes the scratch arenas for tests. They're shared by all threads, so this also
+/// returns a guard that keeps other tests from using them at the same time.
+#[cfg(test)]
+pub fn init_for_test() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    static INIT: std::sync::Once = std::sync::Once::new();
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    INIT.call_once(|| init(128 * MEBI).unwrap());
+    guard
+}
+
 /// Need an arena for temporary allocations? [`scratch_arena`] got you covered.
 /// Call [`scratch_arena`] and it'll return an [`Arena`] that resets when it goes out of scope.
 ///
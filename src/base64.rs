@@ -77,9 +77,36 @@ pub fn encode(dst: &mut ArenaString, src: &[u8]) {
     }
 }
 
+/// Decodes base64, skipping the padding and anything else that's not part of it,
+/// like line breaks. A trailing incomplete byte is dropped.
+pub fn decode(src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(src.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for &c in src {
+        let val = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => continue,
+        };
+        acc = (acc << 6 | val as u32) & 0xffffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
-    use super::encode;
+    use super::{decode, encode};
     use crate::arena::{Arena, ArenaString};
 
     #[test]
@@ -118,4 +145,15 @@ mod tests {
         assert_eq!(enc(b"abcdefghijklmNOPQRSTUVWXY"), "YWJjZGVmZ2hpamtsbU5PUFFSU1RVVldYWQ==");
         assert_eq!(enc(b"abcdefghijklmNOPQRSTUVWXYZ"), "YWJjZGVmZ2hpamtsbU5PUFFSU1RVVldYWVo=");
     }
+
+    #[test]
+    fn test_decode() {
+        let arena = Arena::new(4 * 1024).unwrap();
+        for src in [&b""[..], b"a", b"ab", b"abc", b"abcd", b"\xff\x00\xfe\x80"] {
+            let mut dst = ArenaString::new_in(&arena);
+            encode(&mut dst, src);
+            assert_eq!(decode(dst.as_bytes()), src);
+        }
+        assert_eq!(decode(b"YWJj\r\nZA=="), b"abcd");
+    }
 }
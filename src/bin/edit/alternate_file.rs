@@ -0,0 +1,246 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Switching between a source file and its test file.
+//!
+//! The counterparts are described by pairs of path templates, in which `{dir}`
+//! stands for any directory and `{name}` for a file name without `/`. For instance,
+//! `{dir}/{name}.py` and `{dir}/test_{name}.py`. The built-in pairs can be replaced
+//! per file type in the config:
+//!
+//! ```toml
+//! [alternate.python]
+//! source = ["{dir}/src/{name}.py"]
+//! test = ["{dir}/tests/test_{name}.py"]
+//! template = "import {name}\n"
+//! ```
+
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use edit::apperr;
+use edit::config::Config;
+use edit::syntax::FileType;
+
+/// The source/test path templates of a file type.
+pub struct AlternateRules {
+    /// `(source, test)` template pairs, in order of preference.
+    pairs: Vec<(String, String)>,
+    /// The initial contents of a newly created test file. `{name}` is replaced.
+    template: String,
+}
+
+impl AlternateRules {
+    pub fn new(config: &Config, file_type: FileType) -> Self {
+        let (pairs, template): (&[(&str, &str)], &str) = match file_type {
+            FileType::Rust => (
+                &[
+                    ("{dir}/src/{name}.rs", "{dir}/tests/{name}.rs"),
+                    ("{dir}/{name}.rs", "{dir}/{name}_test.rs"),
+                ],
+                "#[test]\nfn {name}() {\n}\n",
+            ),
+            FileType::Python => (
+                &[("{dir}/{name}.py", "{dir}/test_{name}.py")],
+                "import {name}\n\n\ndef test_{name}():\n    pass\n",
+            ),
+            FileType::TypeScript => (
+                &[
+                    ("{dir}/{name}.ts", "{dir}/{name}.spec.ts"),
+                    ("{dir}/{name}.ts", "{dir}/{name}.test.ts"),
+                ],
+                "import {} from \"./{name}\";\n",
+            ),
+            FileType::JavaScript => (
+                &[
+                    ("{dir}/{name}.js", "{dir}/{name}.spec.js"),
+                    ("{dir}/{name}.js", "{dir}/{name}.test.js"),
+                ],
+                "import {} from \"./{name}\";\n",
+            ),
+            _ => (&[], ""),
+        };
+        let mut rules = Self {
+            pairs: pairs.iter().map(|&(s, t)| (s.to_string(), t.to_string())).collect(),
+            template: template.to_string(),
+        };
+
+        let section = format!("alternate.{}", file_type.config_name());
+        if let Some(sources) = config.get_str_array(&format!("{section}.source"))
+            && let Some(tests) = config.get_str_array(&format!("{section}.test"))
+        {
+            rules.pairs =
+                sources.iter().zip(&tests).map(|(s, t)| (s.to_string(), t.to_string())).collect();
+        }
+        if let Some(template) = config.get_str(&format!("{section}.template")) {
+            rules.template = template.to_string();
+        }
+        rules
+    }
+
+    /// Returns the counterparts of `path` in order of preference, and whether they're test files.
+    /// Test templates are checked first, because `{name}.py` also matches `test_foo.py`.
+    pub fn candidates(&self, path: &Path) -> (Vec<PathBuf>, bool) {
+        let path = template_path(path);
+
+        for to_test in [false, true] {
+            let mut result = Vec::new();
+            for (source, test) in &self.pairs {
+                let (from, to) = if to_test { (source, test) } else { (test, source) };
+                if let Some(caps) = match_template(from, &path) {
+                    let candidate = PathBuf::from(caps.substitute(to));
+                    if !result.contains(&candidate) {
+                        result.push(candidate);
+                    }
+                }
+            }
+            if !result.is_empty() {
+                return (result, to_test);
+            }
+        }
+
+        (Vec::new(), false)
+    }
+}
+
+/// Returns the counterpart of `path`, creating it if none of the candidates exist.
+/// New test files start out with the file type's template. Returns `None` if no rule applies.
+pub fn find_or_create(
+    config: &Config,
+    path: &Path,
+    file_type: FileType,
+) -> apperr::Result<Option<PathBuf>> {
+    let rules = AlternateRules::new(config, file_type);
+    let (candidates, is_test) = rules.candidates(path);
+
+    if let Some(existing) = candidates.iter().find(|p| p.exists()) {
+        return Ok(Some(existing.clone()));
+    }
+    let Some(path) = candidates.into_iter().next() else {
+        return Ok(None);
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+    if is_test {
+        let text = template_path(&path);
+        let name = rules
+            .pairs
+            .iter()
+            .find_map(|(_, test)| match_template(test, &text))
+            .map_or("", |caps| caps.name);
+        file.write_all(rules.template.replace("{name}", name).as_bytes())?;
+    }
+    Ok(Some(path))
+}
+
+/// Returns `path` as a string with `/` separators, as used by the templates.
+fn template_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) { path.replace('\\', "/") } else { path.into_owned() }
+}
+
+struct Captures<'a> {
+    dir: &'a str,
+    name: &'a str,
+}
+
+impl Captures<'_> {
+    fn substitute(&self, template: &str) -> String {
+        template.replace("{dir}", self.dir).replace("{name}", self.name)
+    }
+}
+
+/// Matches `text` against a template. `{dir}` matches anything, `{name}` anything but `/`.
+fn match_template<'a>(template: &str, text: &'a str) -> Option<Captures<'a>> {
+    let mut caps = Captures { dir: "", name: "" };
+    match_rest(template, text, &mut caps).then_some(caps)
+}
+
+fn match_rest<'a>(template: &str, text: &'a str, caps: &mut Captures<'a>) -> bool {
+    let Some(beg) = template.find('{') else {
+        return template == text;
+    };
+    let Some(end) = template[beg..].find('}').map(|i| beg + i + 1) else {
+        return template == text;
+    };
+    let Some(text) = text.strip_prefix(&template[..beg]) else {
+        return false;
+    };
+
+    let placeholder = &template[beg..end];
+    let rest = &template[end..];
+    // Try the longest capture first, so that `{dir}` takes up as much as possible.
+    for len in (1..=text.len()).rev() {
+        if !text.is_char_boundary(len) {
+            continue;
+        }
+        let value = &text[..len];
+        match placeholder {
+            "{dir}" => caps.dir = value,
+            "{name}" if !value.contains('/') => caps.name = value,
+            "{name}" => continue,
+            _ => return false,
+        }
+        if match_rest(rest, &text[len..], caps) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(config: &Config, path: &str, file_type: FileType) -> (Vec<String>, bool) {
+        let (paths, is_test) = AlternateRules::new(config, file_type).candidates(Path::new(path));
+        (paths.iter().map(|p| p.to_string_lossy().into_owned()).collect(), is_test)
+    }
+
+    #[test]
+    fn test_candidates() {
+        let config = Config::default();
+
+        assert_eq!(
+            candidates(&config, "/p/src/foo.rs", FileType::Rust),
+            (vec!["/p/tests/foo.rs".to_string(), "/p/src/foo_test.rs".to_string()], true)
+        );
+        assert_eq!(
+            candidates(&config, "/p/tests/foo.rs", FileType::Rust),
+            (vec!["/p/src/foo.rs".to_string()], false)
+        );
+        assert_eq!(
+            candidates(&config, "/p/foo_test.rs", FileType::Rust),
+            (vec!["/p/foo.rs".to_string()], false)
+        );
+        assert_eq!(
+            candidates(&config, "/p/test_x.py", FileType::Python),
+            (vec!["/p/x.py".to_string()], false)
+        );
+        assert_eq!(
+            candidates(&config, "/p/x.py", FileType::Python),
+            (vec!["/p/test_x.py".to_string()], true)
+        );
+        assert_eq!(
+            candidates(&config, "/p/x.spec.ts", FileType::TypeScript),
+            (vec!["/p/x.ts".to_string()], false)
+        );
+        assert_eq!(candidates(&config, "/p/x.txt", FileType::Plain), (vec![], false));
+
+        let config = Config::parse(
+            r#"
+            [alternate.python]
+            source = ["{dir}/src/{name}.py"]
+            test = ["{dir}/tests/test_{name}.py"]
+            "#,
+        );
+        assert_eq!(
+            candidates(&config, "/p/src/x.py", FileType::Python),
+            (vec!["/p/tests/test_x.py".to_string()], true)
+        );
+    }
+}
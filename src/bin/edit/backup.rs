@@ -0,0 +1,188 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Keeps a copy of a file before it gets saved over. Configured via the `[backup]` section:
+//!
+//! ```toml
+//! [backup]
+//! mode = "directory"  # or "tilde" for `file~`, "bak" for `file.bak`, "none" (the default)
+//! directory = "~/backups"  # The default is `backups` in the state directory.
+//! keep = 10  # How many backups of each file the directory keeps.
+//! ```
+//!
+//! `tilde` and `bak` back a file up before it's first saved in a session, so that the
+//! backup holds the version from before editing. `directory` makes a copy on every save,
+//! named after the file's full path and the time (in UTC), and deletes the oldest ones.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use edit::apperr;
+use edit::config::Config;
+
+enum BackupMode {
+    Tilde,
+    Bak,
+    Directory(PathBuf),
+}
+
+pub struct BackupSettings {
+    mode: BackupMode,
+    keep: usize,
+}
+
+impl BackupSettings {
+    /// Returns `None` if backups are off.
+    pub fn load(config: &Config) -> Option<Self> {
+        let mode = match config.get_str("backup.mode")? {
+            "tilde" => BackupMode::Tilde,
+            "bak" => BackupMode::Bak,
+            "directory" => {
+                let dir = match config.get_str("backup.directory") {
+                    Some(dir) => expand_home(dir),
+                    None => Config::state_dir()?.join("backups"),
+                };
+                BackupMode::Directory(dir)
+            }
+            _ => return None,
+        };
+        let keep = config.get_int("backup.keep").unwrap_or(10).max(1) as usize;
+        Some(Self { mode, keep })
+    }
+}
+
+/// Backs up the file at `path`, if it exists. `first_save` is true if the document
+/// wasn't saved to `path` yet in this session.
+pub fn create(settings: &BackupSettings, path: &Path, first_save: bool) -> apperr::Result<()> {
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    match &settings.mode {
+        BackupMode::Tilde | BackupMode::Bak => {
+            if first_save {
+                let suffix = if matches!(settings.mode, BackupMode::Tilde) { "~" } else { ".bak" };
+                let mut dst = path.as_os_str().to_owned();
+                dst.push(suffix);
+                fs::copy(path, dst)?;
+            }
+        }
+        BackupMode::Directory(dir) => {
+            fs::create_dir_all(dir)?;
+            let prefix = backup_prefix(path);
+            let name = format!("{prefix}{}", timestamp(SystemTime::now()));
+            fs::copy(path, dir.join(name))?;
+            prune(dir, &prefix, settings.keep);
+        }
+    }
+    Ok(())
+}
+
+/// Turns `/etc/hosts` into `%etc%hosts~`, which the timestamp gets appended to.
+/// Swap files append the process ID instead, see [`crate::swap`].
+pub fn backup_prefix(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut prefix: String =
+        path.chars().map(|c| if matches!(c, '/' | '\\' | ':') { '%' } else { c }).collect();
+    prefix.push('~');
+    prefix
+}
+
+/// Formats the time as `20261017-195100`, in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Days since 1970-01-01 to a date, see: https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Deletes the oldest backups with the given prefix, so that `keep` remain.
+fn prune(dir: &Path, prefix: &str, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut backups: Vec<String> = entries
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        // `file~` has backups of its own, which start with the same prefix.
+        .filter(|name| {
+            name.strip_prefix(prefix).is_some_and(|rest| {
+                rest.len() == 15 && rest.bytes().all(|b| b.is_ascii_digit() || b == b'-')
+            })
+        })
+        .collect();
+
+    // The timestamps sort chronologically.
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for name in &backups[..excess] {
+        _ = fs::remove_file(dir.join(name));
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/").zip(env::var_os("HOME")) {
+        Some((rest, home)) => PathBuf::from(home).join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_backup_names() {
+        assert_eq!(backup_prefix(Path::new("/etc/hosts")), "%etc%hosts~");
+        assert_eq!(timestamp(UNIX_EPOCH), "19700101-000000");
+        let time = UNIX_EPOCH + Duration::from_secs(1_791_000_000);
+        assert_eq!(timestamp(time), "20261003-040000");
+    }
+
+    #[test]
+    fn test_backup_directory() {
+        let root = env::temp_dir().join(format!("edit-backup-{}", std::process::id()));
+        let dir = root.join("backups");
+        let file = root.join("hosts");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "127.0.0.1 localhost\n").unwrap();
+
+        let prefix = backup_prefix(&file);
+        for name in ["20250101-000000", "20240101-000000", "~20200101-000000"] {
+            fs::write(dir.join(format!("{prefix}{name}")), "").unwrap();
+        }
+        let settings = BackupSettings { mode: BackupMode::Directory(dir.clone()), keep: 2 };
+        create(&settings, &file, false).unwrap();
+
+        let mut names: Vec<_> =
+            fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        // The oldest one went, but not the backup of `hosts~`.
+        assert_eq!(names[0].to_str().unwrap(), format!("{prefix}20250101-000000"));
+        assert_eq!(names[2].to_str().unwrap(), format!("{prefix}~20200101-000000"));
+        assert_eq!(fs::read_to_string(dir.join(&names[1])).unwrap(), "127.0.0.1 localhost\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Shares the text copied in edit with the rest of the system.
+//!
+//! That's normally done via OSC 52, which asks the terminal to set its clipboard and works
+//! over SSH. Large copies are piped to the system clipboard tools (`wl-copy`, `xclip`,
+//! `pbcopy`, ...) instead where possible, and OSC 52 is written a piece at a time, so that
+//! neither needs a second copy of the text in memory.
+//!
+//! The other way around, Ctrl+V first takes over what was copied elsewhere in the meantime.
+//! It's read with the system clipboard tools (`wl-paste`, `xclip`, `pbpaste`, ...), or over
+//! SSH via OSC 52 if `clipboard.osc52_read` is set. Few terminals answer that without asking
+//! the user first, which is why it's off by default.
+
+use std::env;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use edit::arena::{ArenaString, scratch_arena};
+use edit::helpers::KIBI;
+use edit::{base64, sys};
+
+/// The commands that set the system clipboard from their stdin, in order of preference.
+#[cfg(windows)]
+const COPY_COMMANDS: &[&[&str]] = &[&[
+    "powershell",
+    "-NoProfile",
+    "-Command",
+    "[Console]::InputEncoding = [Text.Encoding]::UTF8; Set-Clipboard ([Console]::In.ReadToEnd())",
+]];
+#[cfg(target_os = "macos")]
+const COPY_COMMANDS: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(all(unix, not(target_os = "macos")))]
+const COPY_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard", "-in"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// The commands that print the system clipboard, in order of preference.
+#[cfg(windows)]
+const PASTE_COMMANDS: &[&[&str]] =
+    &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]];
+#[cfg(target_os = "macos")]
+const PASTE_COMMANDS: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(all(unix, not(target_os = "macos")))]
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+];
+
+/// Pipes `data` to the first system clipboard tool that works. Returns false if none do,
+/// or if edit runs over SSH, where they'd set the clipboard of the wrong machine.
+pub fn write_system(data: &[u8]) -> bool {
+    if env::var_os("SSH_CONNECTION").is_some() {
+        return false;
+    }
+    COPY_COMMANDS.iter().any(|command| {
+        let Ok(mut child) = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        // Dropping stdin closes it, which tells the tool that it has everything.
+        let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(data).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
+
+/// Returns the system clipboard from the first tool that works. `None` if none do,
+/// or if edit runs over SSH, like for [`write_system()`].
+pub fn read_system() -> Option<Vec<u8>> {
+    if env::var_os("SSH_CONNECTION").is_some() {
+        return None;
+    }
+    PASTE_COMMANDS.iter().find_map(|command| {
+        let output = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output.status.success().then_some(output.stdout)
+    })
+}
+
+/// Asks the terminal for its clipboard via OSC 52 and waits up to `timeout` for the answer.
+/// Anything else that's read in the meantime, like keys pressed right after, is appended
+/// to `other`, to be processed as usual.
+pub fn read_osc52(timeout: Duration, other: &mut String) -> Option<Vec<u8>> {
+    sys::write_stdout("\x1b]52;c;?\x1b\\");
+
+    let deadline = Instant::now() + timeout;
+    let mut input = String::new();
+    let mut data = None;
+    while data.is_none() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        let scratch = scratch_arena(None);
+        let Some(read) = sys::read_stdin(&scratch, deadline - now) else {
+            break;
+        };
+        input.push_str(&read);
+        data = take_osc52_response(&mut input);
+    }
+
+    other.push_str(&input);
+    data
+}
+
+/// Removes the answer to an OSC 52 query from `input` and returns its decoded contents.
+fn take_osc52_response(input: &mut String) -> Option<Vec<u8>> {
+    let beg = input.find("\x1b]52;")?;
+    let payload_beg = beg + 5;
+    let (payload_end, end) = match input[payload_beg..].find(['\x07', '\x1b']) {
+        Some(i) if input[payload_beg + i..].starts_with('\x07') => {
+            (payload_beg + i, payload_beg + i + 1)
+        }
+        Some(i) if input[payload_beg + i..].starts_with("\x1b\\") => {
+            (payload_beg + i, payload_beg + i + 2)
+        }
+        // The rest hasn't arrived yet.
+        _ => return None,
+    };
+
+    // The payload is `c;<base64>`, with the selection(s) it's from.
+    let payload = &input[payload_beg..payload_end];
+    let data = payload.split_once(';').map(|(_, data)| base64::decode(data.as_bytes()));
+    input.replace_range(beg..end, "");
+    data
+}
+
+/// Writes `data` to the terminal as OSC 52.
+pub fn write_osc52(data: &[u8]) {
+    // A multiple of 3, so that the base64 of the pieces adds up to that of the whole.
+    const CHUNK_SIZE: usize = 48 * KIBI;
+
+    sys::write_stdout("\x1b]52;c;");
+    for chunk in data.chunks(CHUNK_SIZE) {
+        let scratch = scratch_arena(None);
+        let mut encoded =
+            ArenaString::with_capacity_in(base64::encode_len(chunk.len()), &scratch);
+        base64::encode(&mut encoded, chunk);
+        sys::write_stdout(&encoded);
+    }
+    sys::write_stdout("\x1b\\");
+}
+
+/// Returns how much of `data` fits into `limit` bytes without splitting a UTF-8 character.
+pub fn shareable_len(data: &[u8], limit: usize) -> usize {
+    if data.len() <= limit {
+        return data.len();
+    }
+    let mut len = limit;
+    while len > 0 && (data[len] & 0xC0) == 0x80 {
+        len -= 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_osc52_response() {
+        let mut input = "a\x1b]52;c;aGk=\x1b\\b".to_string();
+        assert_eq!(take_osc52_response(&mut input), Some(b"hi".to_vec()));
+        assert_eq!(input, "ab");
+
+        let mut input = "\x1b]52;c;aGk=\x07".to_string();
+        assert_eq!(take_osc52_response(&mut input), Some(b"hi".to_vec()));
+        assert_eq!(input, "");
+
+        let mut input = "\x1b]52;c;aG".to_string();
+        assert_eq!(take_osc52_response(&mut input), None);
+        assert_eq!(input, "\x1b]52;c;aG");
+    }
+
+    #[test]
+    fn test_shareable_len() {
+        assert_eq!(shareable_len(b"abc", 10), 3);
+        assert_eq!(shareable_len(b"abc", 2), 2);
+        // "aé€" is 1 + 2 + 3 bytes.
+        let text = "aé€".as_bytes();
+        assert_eq!(shareable_len(text, 2), 1);
+        assert_eq!(shareable_len(text, 3), 3);
+        assert_eq!(shareable_len(text, 5), 3);
+        assert_eq!(shareable_len(text, 0), 0);
+    }
+}
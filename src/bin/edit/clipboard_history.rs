@@ -0,0 +1,420 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The recent clipboard contents, with where each was copied from: the file, the lines
+//! and when. Edit > Clipboard History lists them to paste one again, or to go back to the
+//! lines it came from.
+//!
+//! Next to it are the named registers `a` to `z`, which keep what's put into them until it's
+//! replaced: `:yank x` copies the selection or the current line into register `x`, and
+//! `:put x` pastes it. An uppercase name like `:yank X` appends instead. The clipboard
+//! history lists the registers first.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use edit::apperr;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::tui::*;
+
+use crate::localization::*;
+use crate::state::*;
+
+pub const APP_REGISTER_EMPTY: apperr::Error = apperr::Error::new_app(9);
+
+/// How many entries are kept. The oldest ones are dropped first.
+const MAX_ENTRIES: usize = 20;
+/// How much of an entry the list shows.
+const PREVIEW_LEN: usize = 60;
+
+/// Where the contents of a [`ClipboardEntry`] were copied from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ClipboardSource {
+    pub path: PathBuf,
+    /// The 0-based first and last line.
+    pub lines: (CoordType, CoordType),
+}
+
+pub struct ClipboardEntry {
+    pub data: Vec<u8>,
+    /// `None` if it wasn't copied from a saved file, e.g. from a dialog's text field.
+    pub source: Option<ClipboardSource>,
+    pub time: SystemTime,
+}
+
+#[derive(Default)]
+pub struct ClipboardHistory {
+    /// The newest entry first.
+    entries: Vec<ClipboardEntry>,
+    /// The named registers that were filled, sorted by name.
+    registers: Vec<(char, ClipboardEntry)>,
+    /// The clipboard generation that was last recorded.
+    seen_generation: u32,
+    /// The entry that's selected in the list.
+    selected: usize,
+    pub wants_list: bool,
+}
+
+impl ClipboardHistory {
+    pub fn show_list(&mut self) {
+        self.wants_list = true;
+        self.selected = 0;
+    }
+
+    /// Adds an entry. Copying something that's in the history already moves it to the front,
+    /// with the new source if there is one.
+    fn push(&mut self, mut entry: ClipboardEntry) {
+        if let Some(i) = self.entries.iter().position(|e| e.data == entry.data) {
+            let old = self.entries.remove(i);
+            entry.source = entry.source.or(old.source);
+        }
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+    }
+
+    /// Stores `entry` in the register `name`, or appends it for an uppercase `name`.
+    fn set_register(&mut self, name: char, entry: ClipboardEntry) {
+        let append = name.is_ascii_uppercase();
+        let name = name.to_ascii_lowercase();
+        match self.registers.binary_search_by_key(&name, |(n, _)| *n) {
+            Ok(i) if append => {
+                let register = &mut self.registers[i].1;
+                register.data.extend_from_slice(&entry.data);
+                register.time = entry.time;
+            }
+            Ok(i) => self.registers[i].1 = entry,
+            Err(i) => self.registers.insert(i, (name, entry)),
+        }
+    }
+
+    fn register(&self, name: char) -> Option<&ClipboardEntry> {
+        let name = name.to_ascii_lowercase();
+        self.registers.iter().find(|(n, _)| *n == name).map(|(_, entry)| entry)
+    }
+
+    /// The registers, then the history entries, as they're listed.
+    fn items(&self) -> impl Iterator<Item = (Option<char>, &ClipboardEntry)> {
+        let registers = self.registers.iter().map(|(name, entry)| (Some(*name), entry));
+        registers.chain(self.entries.iter().map(|entry| (None, entry)))
+    }
+}
+
+/// Whether `name` is a register name for [`yank()`] and [`put()`].
+pub fn is_register_name(name: char) -> bool {
+    name.is_ascii_alphabetic()
+}
+
+/// Copies the active document's `lines` into the register `name`,
+/// or the selection if `None`, which falls back to the cursor's line.
+pub fn yank(state: &mut State, name: char, lines: Option<Range<CoordType>>) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let mut tb = doc.buffer.borrow_mut();
+    let (data, lines) = match lines {
+        Some(lines) => {
+            let mut data = tb.lines_text(lines.clone()).join("\n").into_bytes();
+            data.push(b'\n');
+            (data, (lines.start, lines.end - 1))
+        }
+        None => {
+            let data = tb.extract_selection(false);
+            let first = match tb.selection_range() {
+                Some((beg, _)) => beg.logical_pos.y,
+                None => tb.cursor_logical_pos().y,
+            };
+            let lines = (first, first + line_breaks(&data));
+            (data, lines)
+        }
+    };
+    let source = doc.path.clone().map(|path| ClipboardSource { path, lines });
+    drop(tb);
+    state.clipboard_history.set_register(name, ClipboardEntry {
+        data,
+        source,
+        time: SystemTime::now(),
+    });
+}
+
+/// Pastes the register `name` into the active document, like Ctrl+V.
+pub fn put(state: &mut State, name: char) -> apperr::Result<()> {
+    let entry = state.clipboard_history.register(name).ok_or(APP_REGISTER_EMPTY)?;
+    if let Some(doc) = state.documents.active() {
+        let mut tb = doc.buffer.borrow_mut();
+        tb.write(&entry.data, true);
+        tb.make_cursor_visible();
+    }
+    Ok(())
+}
+
+/// Adds the clipboard contents to the history if they changed since the last call.
+/// Called every frame, after the editor had a chance to copy.
+pub fn update(ctx: &Context, state: &mut State) {
+    let generation = ctx.clipboard_generation();
+    if state.clipboard_history.seen_generation == generation {
+        return;
+    }
+    state.clipboard_history.seen_generation = generation;
+
+    let data = ctx.clipboard();
+    if data.is_empty() {
+        return;
+    }
+    let source = if state.editor_active { source_of(state, data) } else { None };
+    state.clipboard_history.push(ClipboardEntry {
+        data: data.to_vec(),
+        source,
+        time: SystemTime::now(),
+    });
+}
+
+/// Adds what [`Tui::set_clipboard()`] took over from the system clipboard.
+/// It has no source, as it was copied outside of edit.
+pub fn record_external(state: &mut State, generation: u32, data: &[u8]) {
+    state.clipboard_history.seen_generation = generation;
+    state.clipboard_history.push(ClipboardEntry {
+        data: data.to_vec(),
+        source: None,
+        time: SystemTime::now(),
+    });
+}
+
+/// The lines of the active document that `data` was copied or cut from.
+fn source_of(state: &State, data: &[u8]) -> Option<ClipboardSource> {
+    let doc = state.documents.active()?;
+    let path = doc.path.clone()?;
+    let tb = doc.buffer.borrow();
+    // After a cut, the cursor is where the selection began.
+    let first = match tb.selection_range() {
+        Some((beg, _)) => beg.logical_pos.y,
+        None => tb.cursor_logical_pos().y,
+    };
+    Some(ClipboardSource { path, lines: (first, first + line_breaks(data)) })
+}
+
+/// The number of line breaks in `data`, not counting a trailing one,
+/// as a copied line ends with it.
+fn line_breaks(data: &[u8]) -> CoordType {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    data.iter().filter(|&&b| b == b'\n').count() as CoordType
+}
+
+/// Formats how long ago `time` was, like `5s`, `3m` or `2h`.
+fn format_age(time: SystemTime, now: SystemTime) -> String {
+    let secs = now.duration_since(time).unwrap_or(Duration::ZERO).as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Formats a source as `main.rs:3` or `main.rs:3-5`, with 1-based lines.
+fn format_source(source: &ClipboardSource) -> String {
+    let name = source.path.file_name().map_or(source.path.as_os_str(), |n| n).to_string_lossy();
+    let (first, last) = source.lines;
+    if first == last {
+        format!("{name}:{}", first + 1)
+    } else {
+        format!("{name}:{}-{}", first + 1, last + 1)
+    }
+}
+
+/// The first line of `data`, shortened to [`PREVIEW_LEN`] characters.
+fn preview(data: &[u8]) -> String {
+    let text = String::from_utf8_lossy(data);
+    let line = text.trim_start().lines().next().unwrap_or("");
+    let mut preview: String = line.chars().take(PREVIEW_LEN).collect();
+    if preview.len() < line.len() || line.len() < text.trim().len() {
+        preview.push('…');
+    }
+    preview
+}
+
+pub fn draw_list(ctx: &mut Context, state: &mut State) {
+    enum Action {
+        None,
+        Paste,
+        GoToSource,
+    }
+    let mut action = Action::None;
+    let history = &mut state.clipboard_history;
+
+    ctx.modal_begin("clipboard-history", loc(LocId::EditClipboardHistory));
+    {
+        if history.entries.is_empty() && history.registers.is_empty() {
+            ctx.label("empty", loc(LocId::ClipboardHistoryEmpty));
+            ctx.attr_padding(Rect::three(1, 2, 1));
+        } else {
+            let width = (ctx.size().width - 20).clamp(10, 100);
+            let count = history.registers.len() + history.entries.len();
+            let height = (ctx.size().height - 12).clamp(1, count as _);
+            let now = SystemTime::now();
+
+            ctx.scrollarea_begin("scrollarea", Size { width, height });
+            ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+            ctx.inherit_focus();
+            {
+                ctx.list_begin("entries");
+                ctx.inherit_focus();
+                let mut selected = history.selected;
+                for (i, (register, entry)) in history.items().enumerate() {
+                    let source = entry.source.as_ref().map(format_source).unwrap_or_default();
+                    let label = match register {
+                        Some(name) => format!("\"{name}"),
+                        None => format_age(entry.time, now),
+                    };
+                    ctx.styled_list_item_begin();
+                    ctx.attr_overflow(Overflow::TruncateTail);
+                    ctx.styled_label_add_text(&format!("{label:>3}  "));
+                    ctx.styled_label_set_foreground(ctx.indexed(IndexedColor::BrightBlue));
+                    ctx.styled_label_add_text(&format!("{source:<20} "));
+                    ctx.styled_label_set_foreground(ctx.indexed(IndexedColor::Foreground));
+                    ctx.styled_label_add_text(&preview(&entry.data));
+                    match ctx.styled_list_item_end(i == 0) {
+                        ListSelection::Unchanged => {}
+                        ListSelection::Selected => selected = i,
+                        ListSelection::Activated => {
+                            selected = i;
+                            action = Action::Paste;
+                        }
+                    }
+                }
+                history.selected = selected;
+                ctx.list_end();
+            }
+            ctx.scrollarea_end();
+
+            ctx.table_begin("choices");
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                if ctx.button("paste", loc(LocId::EditPaste), ButtonStyle::default()) {
+                    action = Action::Paste;
+                }
+                let has_source =
+                    history.items().nth(history.selected).is_some_and(|(_, e)| e.source.is_some());
+                if has_source
+                    && ctx.button(
+                        "source",
+                        loc(LocId::ClipboardHistoryGoToSource),
+                        ButtonStyle::default(),
+                    )
+                {
+                    action = Action::GoToSource;
+                }
+            }
+            ctx.table_end();
+        }
+    }
+    let done = ctx.modal_end();
+
+    let history = &mut state.clipboard_history;
+    let entry = history.items().nth(history.selected).map(|(_, entry)| entry);
+    match action {
+        Action::None => {}
+        Action::Paste => {
+            if let Some(entry) = entry {
+                let data = entry.data.clone();
+                if let Some(doc) = state.documents.active() {
+                    doc.buffer.borrow_mut().write(&data, true);
+                }
+                // Makes it the clipboard contents, which moves it to the top of the list.
+                ctx.set_clipboard(data);
+            }
+        }
+        Action::GoToSource => {
+            if let Some(source) = entry.and_then(|e| e.source.clone())
+                && let Err(err) = go_to(state, &source.path, source.lines.0)
+            {
+                error_log_add(ctx, state, err);
+            }
+        }
+    }
+    if done || !matches!(action, Action::None) {
+        state.clipboard_history.wants_list = false;
+        ctx.needs_rerender();
+    }
+}
+
+/// Opens `path`, or switches to it, and moves the cursor to the 0-based `line`.
+fn go_to(state: &mut State, path: &Path, line: CoordType) -> edit::apperr::Result<()> {
+    let doc = state.documents.add_file_path(path)?;
+    let mut tb = doc.buffer.borrow_mut();
+    tb.cursor_move_to_logical(Point { x: 0, y: line });
+    tb.make_cursor_visible();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(data: &str, line: Option<CoordType>) -> ClipboardEntry {
+        ClipboardEntry {
+            data: data.as_bytes().to_vec(),
+            source: line.map(|y| ClipboardSource { path: PathBuf::from("/a.rs"), lines: (y, y) }),
+            time: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_push() {
+        let mut history = ClipboardHistory::default();
+        history.push(entry("one", Some(1)));
+        history.push(entry("two", Some(2)));
+        history.push(entry("one", None));
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(history.entries[0].data, b"one");
+        // Pasting it from the history again keeps where it came from.
+        assert_eq!(history.entries[0].source.as_ref().unwrap().lines, (1, 1));
+
+        for i in 0..30 {
+            history.push(entry(&i.to_string(), None));
+        }
+        assert_eq!(history.entries.len(), MAX_ENTRIES);
+        assert_eq!(history.entries[0].data, b"29");
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut history = ClipboardHistory::default();
+        history.set_register('b', entry("two", None));
+        history.set_register('a', entry("one", None));
+        history.set_register('B', entry("three", None));
+        assert_eq!(history.register('b').unwrap().data, b"twothree");
+        assert!(history.register('c').is_none());
+
+        history.push(entry("copied", None));
+        let items: Vec<_> = history.items().map(|(name, e)| (name, e.data.clone())).collect();
+        assert_eq!(
+            items,
+            [
+                (Some('a'), b"one".to_vec()),
+                (Some('b'), b"twothree".to_vec()),
+                (None, b"copied".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(line_breaks(b"fn main() {\n}\n"), 1);
+        assert_eq!(line_breaks(b"word"), 0);
+
+        let source = ClipboardSource { path: PathBuf::from("/src/main.rs"), lines: (2, 4) };
+        assert_eq!(format_source(&source), "main.rs:3-5");
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100_000);
+        assert_eq!(format_age(now - Duration::from_secs(5), now), "5s");
+        assert_eq!(format_age(now - Duration::from_secs(7200), now), "2h");
+
+        assert_eq!(preview(b"  let x = 1;\n"), "let x = 1;");
+        assert_eq!(preview(b"a\nb"), "a…");
+    }
+}
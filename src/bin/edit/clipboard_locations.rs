@@ -0,0 +1,159 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! File > Open from Clipboard: Opens the files mentioned in a copied compiler error,
+//! stack trace or list of paths, e.g. ` --> src/main.rs:12:5` or `File "app.py", line 3`.
+//!
+//! The system clipboard is read via the usual command line tools (`wl-paste`, `xclip`,
+//! `pbpaste`, ...). If none of them work, the text copied within edit is used instead.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use edit::apperr;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::tui::*;
+use regex::Regex;
+
+use crate::clipboard;
+use crate::localization::*;
+use crate::state::*;
+
+pub const APP_NO_CLIPBOARD_LOCATIONS: apperr::Error = apperr::Error::new_app(2);
+
+/// Finds the file locations in `text`, in the order they appear, and returns them as
+/// `path`, `path:line` or `path:line:column`, as understood by `add_file_path()`.
+/// Only paths for which `exists` returns true are included.
+pub fn find_locations(text: &str, exists: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+    // Python tracebacks: `File "app.py", line 3, in <module>`
+    let python = Regex::new(r#"File "([^"]+)", line (\d+)"#).unwrap();
+    // Most everything else: `src/main.rs:12:5`, `(/srv/app.js:10:7)`, `C:\src\a.c:3`, ...
+    let position =
+        Regex::new(r#"(?:file://)?((?:[A-Za-z]:[\\/])?[^\s:"'()\[\]<>,]+):(\d+)(?::(\d+))?"#)
+            .unwrap();
+    // Anything else that might be a path on its own.
+    let word = Regex::new(r#"[^\s:"'()\[\]<>,]*[./\\][^\s:"'()\[\]<>,]*"#).unwrap();
+
+    let mut found: Vec<(usize, String, String)> = Vec::new();
+    for caps in python.captures_iter(text) {
+        let path = caps[1].to_string();
+        found.push((caps.get(0).unwrap().start(), format!("{path}:{}", &caps[2]), path));
+    }
+    for caps in position.captures_iter(text) {
+        let path = caps[1].to_string();
+        let location = match caps.get(3) {
+            Some(column) => format!("{path}:{}:{}", &caps[2], column.as_str()),
+            None => format!("{path}:{}", &caps[2]),
+        };
+        found.push((caps.get(1).unwrap().start(), location, path));
+    }
+    for m in word.find_iter(text) {
+        let path = m.as_str().trim_end_matches('.');
+        if !found.iter().any(|(_, _, p)| p == path) {
+            found.push((m.start(), path.to_string(), path.to_string()));
+        }
+    }
+    found.sort_by_key(|(offset, _, _)| *offset);
+
+    let mut result: Vec<PathBuf> = Vec::new();
+    for (_, location, path) in found {
+        let location = PathBuf::from(location);
+        if !result.contains(&location) && exists(Path::new(&path)) {
+            result.push(location);
+        }
+    }
+    result
+}
+
+/// Opens the location on the clipboard, or lets the user pick one if there are several.
+pub fn open_from_clipboard(ctx: &mut Context, state: &mut State) {
+    let text = clipboard::read_system()
+        .map(|data| String::from_utf8_lossy(&data).into_owned())
+        .filter(|text| !text.trim().is_empty())
+        .unwrap_or_else(|| String::from_utf8_lossy(ctx.clipboard()).into_owned());
+    let mut locations = find_locations(&text, Path::is_file);
+
+    let result = match locations.len() {
+        0 => Err(APP_NO_CLIPBOARD_LOCATIONS),
+        1 => open(state, &locations.pop().unwrap()),
+        _ => {
+            state.wants_clipboard_locations = locations;
+            Ok(())
+        }
+    };
+    if let Err(err) = result {
+        error_log_add(ctx, state, err);
+    }
+    ctx.needs_rerender();
+}
+
+/// Opens a location found by [`find_locations()`], relative to the working directory.
+fn open(state: &mut State, location: &Path) -> apperr::Result<()> {
+    let path = env::current_dir()?.join(location);
+    state.documents.add_file_path(&path).map(|_| ())
+}
+
+pub fn draw_clipboard_locations(ctx: &mut Context, state: &mut State) {
+    let mut chosen = None;
+
+    ctx.modal_begin("clipboard-locations", loc(LocId::FileOpenFromClipboard));
+    {
+        let width = (ctx.size().width - 20).max(10);
+        let height = (ctx.size().height - 10).clamp(1, state.wants_clipboard_locations.len() as _);
+
+        ctx.scrollarea_begin("scrollarea", Size { width, height });
+        ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+        ctx.inherit_focus();
+        {
+            ctx.list_begin("locations");
+            ctx.inherit_focus();
+            for (i, location) in state.wants_clipboard_locations.iter().enumerate() {
+                ctx.styled_list_item_begin();
+                ctx.attr_overflow(Overflow::TruncateMiddle);
+                ctx.styled_label_add_text(&location.to_string_lossy());
+                if ctx.styled_list_item_end(false) == ListSelection::Activated {
+                    chosen = Some(i);
+                }
+            }
+            ctx.list_end();
+        }
+        ctx.scrollarea_end();
+    }
+    let done = ctx.modal_end();
+
+    if let Some(i) = chosen {
+        let location = state.wants_clipboard_locations.swap_remove(i);
+        if let Err(err) = open(state, &location) {
+            error_log_add(ctx, state, err);
+        }
+    }
+    if done || chosen.is_some() {
+        state.wants_clipboard_locations.clear();
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_locations() {
+        let text = r#"
+error[E0425]: cannot find value `x` in this scope
+ --> src/main.rs:12:5
+Traceback (most recent call last):
+  File "tools/app.py", line 3, in <module>
+    at render (/srv/web/app.js:10:7)
+see src/main.rs and missing.rs:1, or README.md.
+"#;
+        let existing = ["src/main.rs", "tools/app.py", "/srv/web/app.js", "README.md"];
+        let locations = find_locations(text, |p| existing.iter().any(|e| Path::new(e) == p));
+        assert_eq!(
+            locations,
+            ["src/main.rs:12:5", "tools/app.py:3", "/srv/web/app.js:10:7", "README.md"]
+                .map(PathBuf::from)
+        );
+    }
+}
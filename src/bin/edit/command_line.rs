@@ -0,0 +1,1111 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! An ex-style command line for those who'd rather type than use the menus.
+//!
+//! Supported are:
+//! * `:42`, `:$`: Go to a line.
+//! * `:[range]s/pattern/replacement/[flags]`: Replace the first hit per line, or all of them
+//!   with the `g` flag. `i` and `I` make the search case-insensitive and -sensitive, respectively.
+//!   With `c`, each replacement is confirmed first. The pattern is a regex, an empty one reuses
+//!   the search bar's, and `$1` in the replacement inserts its first capture group.
+//! * `:w [file]`, `:wq`, `:x`: Save, optionally under a new name, and close.
+//! * `:e file`: Open a file.
+//! * `:q`, `:q!`, `:qa`: Close the document, discarding its changes with `!`, or exit.
+//! * `:set wrap`, `nowrap`, `wrap!`, `wrapcolumn=80`, `number`, `expandtab`, `tabstop=4`,
+//!   `colorcolumn=80`, `density=compact`, `list`, `whitespace=boundary`, `filetype=rust`.
+//! * `:help [topic]`, `:man [section] name`: Open the help or a man page. See [`crate::help`].
+//! * `:review`, `:review import file`: Export or import a review bundle. See [`crate::review`].
+//! * `:run [command]`, `:cn`, `:cp`, `:cc [N]`: Run a task and go to the errors in its output.
+//!   See [`crate::tasks`].
+//! * `:scratch [filetype]`: Open a scratch document, which isn't backed by a file.
+//! * `:grep [pattern]`: Search the files below the current directory.
+//!   See [`crate::project_search`].
+//! * `:[range]y[ank] x`, `:pu[t] x`: Copy the selection or lines into the register `x`, or
+//!   paste it. See [`crate::clipboard_history`].
+//! * `:surround x`, `:surround x y`, `:unsurround x`: Put `x` around the selection, or change
+//!   the innermost `x` around it to `y`, or remove it. `x` is a bracket, a quote, Markdown
+//!   emphasis like `**` or an HTML tag like `<a href="#">`.
+//! * `:case x`: Convert the selection or the word at the cursor to the case `x`, one of
+//!   `upper`, `lower`, `title`, `camel`, `snake` and `kebab`.
+//! * `:[range]sort[!] [n][u]`, `:uniq`, `:reverse`, `:shuffle`: Sort the lines, descending
+//!   with `!`, by their first number with `n` and without repeats with `u`. Or remove
+//!   duplicates, reverse or shuffle them. Without a range, these apply to the selected lines,
+//!   or the whole document.
+//! * `:fold`, `:unfold`, `:fold all`, `:unfold all`: Fold or unfold the region at the cursor,
+//!   or all of them.
+//!
+//! A range is `%` for the whole document, `'<,'>` for the selected lines, or one or two
+//! addresses separated by a comma. An address is a line number, `.` for the current line
+//! or `$` for the last one. Without a range, commands apply to the current line.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::hash::{BuildHasher as _, RandomState};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use edit::apperr;
+use edit::buffer::{ReplaceStep, SearchOptions, TextBuffer};
+use edit::case::Case;
+use edit::config::{Density, RenderWhitespace};
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::icu;
+use edit::input::vk;
+use edit::surround::Surrounding;
+use edit::syntax::FileType;
+use edit::tui::*;
+
+use crate::clipboard_history;
+use crate::help::{self, HelpPage};
+use crate::review;
+use crate::tasks::{self, ErrorJump};
+use crate::localization::*;
+use crate::state::*;
+
+pub const APP_SURROUNDING_NOT_FOUND: apperr::Error = apperr::Error::new_app(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// A 0-based line number.
+    Line(CoordType),
+    Current,
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineRange {
+    Current,
+    Whole,
+    Selection,
+    Span(Address, Address),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    /// `None` toggles the current state.
+    Wrap(Option<bool>),
+    Number(Option<bool>),
+    ExpandTab(Option<bool>),
+    Ansi(Option<bool>),
+    Hex(Option<bool>),
+    ReadOnly(Option<bool>),
+    /// The byte order mark of UTF-8 documents. Named after Vim's `bomb`.
+    Bom(Option<bool>),
+    /// Renders all whitespace, like Vim's `list`, or none.
+    List(Option<bool>),
+    TabStop(CoordType),
+    ColorColumn(CoordType),
+    /// The column word wrap breaks lines at, or 0 for the width of the editor.
+    WrapColumn(CoordType),
+    Density(Density),
+    Whitespace(RenderWhitespace),
+    FileType(FileType),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Goto(Address),
+    Substitute {
+        range: LineRange,
+        pattern: String,
+        replacement: String,
+        every: bool,
+        match_case: bool,
+        /// Asks before each replacement, see [`SubstituteConfirm`].
+        confirm: bool,
+    },
+    Write {
+        path: Option<PathBuf>,
+        close: bool,
+    },
+    Edit(PathBuf),
+    Quit {
+        all: bool,
+        force: bool,
+    },
+    Set(Vec<Setting>),
+    Help(String),
+    Man {
+        name: String,
+        section: Option<String>,
+    },
+    /// Prints the document to the terminal's regular screen, see `print_to_scrollback()`.
+    Print,
+    /// Plays a saved macro, or saves the last recorded one under `name`.
+    Macro {
+        name: String,
+        save: bool,
+    },
+    /// Exports the open files' notes and changes, or imports someone else's.
+    Review {
+        import: Option<PathBuf>,
+    },
+    /// Runs a shell command, or picks one of the previous ones if `None`.
+    Run(Option<String>),
+    /// Goes to an error in the output of the last task.
+    GotoError(ErrorJump),
+    /// Opens a new scratch document, see [`crate::documents::DocumentManager::add_scratch()`].
+    Scratch(FileType),
+    /// Searches the files below the current directory, or just opens the results if empty.
+    Grep(String),
+    /// Copies the lines into a named register, or the selection if there's no range.
+    Yank {
+        range: Option<LineRange>,
+        register: char,
+    },
+    /// Pastes a named register.
+    Put(char),
+    /// Puts `new` around the selection, or replaces the `old` around it, or removes it.
+    Surround {
+        old: Option<Surrounding>,
+        new: Option<Surrounding>,
+    },
+    /// Converts the selection, or the word at the cursor, to another case.
+    Case(Case),
+    /// Sorts, dedups, reverses or shuffles the lines, or the selected ones if there's no range.
+    Lines {
+        range: Option<LineRange>,
+        op: LineOp,
+    },
+    /// Folds the region at the cursor, or all of them, or unfolds them.
+    Fold { unfold: bool, all: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOp {
+    /// Sorts by the first number in each line if `numeric`, and drops repeats if `unique`.
+    Sort { descending: bool, numeric: bool, unique: bool },
+    /// Keeps the first of each line that occurs more than once.
+    Unique,
+    Reverse,
+    Shuffle,
+}
+
+/// A `:s` with the `c` flag, which steps through the hits and asks before replacing each.
+pub struct SubstituteConfirm {
+    pattern: String,
+    options: SearchOptions,
+    replacement: String,
+    /// The lines to replace in. The end moves as replacements add or remove lines.
+    lines: Range<CoordType>,
+    every: bool,
+}
+
+impl LineRange {
+    /// Resolves the range into 0-based logical lines, given the cursor's line,
+    /// the number of lines, and the lines of the selection, if any.
+    pub fn resolve(
+        self,
+        current: CoordType,
+        line_count: CoordType,
+        selection: Option<Range<CoordType>>,
+    ) -> Range<CoordType> {
+        let address = |a: Address| match a {
+            Address::Line(line) => line.min(line_count - 1),
+            Address::Current => current,
+            Address::Last => line_count - 1,
+        };
+        match self {
+            LineRange::Current => current..current + 1,
+            LineRange::Whole => 0..line_count,
+            LineRange::Selection => selection.unwrap_or(current..current + 1),
+            LineRange::Span(beg, end) => {
+                let (beg, end) = (address(beg), address(end));
+                beg.min(end)..beg.max(end) + 1
+            }
+        }
+    }
+}
+
+/// Parses a command line, with or without the leading `:`. Returns `None` if it's invalid.
+pub fn parse(text: &str) -> Option<Command> {
+    let text = text.trim();
+    let text = text.strip_prefix(':').unwrap_or(text).trim_start();
+    let (range, rest) = parse_range(text)?;
+    let rest = rest.trim_start();
+
+    if rest.is_empty() {
+        return match range {
+            Some(LineRange::Span(_, end)) => Some(Command::Goto(end)),
+            _ => None,
+        };
+    }
+
+    let name_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+    let (name, args) = rest.split_at(name_len);
+    let (force, args) = match args.strip_prefix('!') {
+        Some(args) => (true, args),
+        None => (false, args),
+    };
+    let arg = args.trim();
+    let path = (!arg.is_empty()).then(|| PathBuf::from(arg));
+
+    // Only `:s`, `:y` and the line tools take a range.
+    let takes_range = matches!(
+        name,
+        "s" | "substitute" | "y" | "yank" | "sor" | "sort" | "uniq" | "reverse" | "shuffle"
+    );
+    if range.is_some() && !takes_range {
+        return None;
+    }
+    let register = {
+        let mut chars = arg.chars();
+        chars.next().filter(|&c| chars.next().is_none() && clipboard_history::is_register_name(c))
+    };
+
+    match name {
+        "s" | "substitute" => parse_substitute(range.unwrap_or(LineRange::Current), args),
+        "w" | "write" if !force => Some(Command::Write { path, close: false }),
+        "wq" | "x" | "xit" if !force => Some(Command::Write { path, close: true }),
+        "e" | "edit" if !force => path.map(Command::Edit),
+        "q" | "quit" | "close" if path.is_none() => Some(Command::Quit { all: false, force }),
+        "qa" | "qall" | "quitall" if path.is_none() => Some(Command::Quit { all: true, force }),
+        "set" | "se" if !force => arg
+            .split_whitespace()
+            .map(parse_setting)
+            .collect::<Option<Vec<_>>>()
+            .filter(|s| !s.is_empty())
+            .map(Command::Set),
+        "h" | "help" if !force => Some(Command::Help(arg.to_string())),
+        "p" | "print" if !force && arg.is_empty() => Some(Command::Print),
+        "man" if !force => {
+            let mut words = arg.split_whitespace().map(str::to_string);
+            match (words.next(), words.next(), words.next()) {
+                (Some(name), None, None) => Some(Command::Man { name, section: None }),
+                (Some(section), Some(name), None) => {
+                    Some(Command::Man { name, section: Some(section) })
+                }
+                _ => None,
+            }
+        }
+        "macro" if !force => {
+            let mut words = arg.split_whitespace().map(str::to_string);
+            match (words.next(), words.next(), words.next()) {
+                (Some(name), None, None) => Some(Command::Macro { name, save: false }),
+                (Some(save), Some(name), None) if save == "save" => {
+                    Some(Command::Macro { name, save: true })
+                }
+                _ => None,
+            }
+        }
+        "review" if !force => match arg.split_once(char::is_whitespace) {
+            _ if arg.is_empty() => Some(Command::Review { import: None }),
+            Some(("import", path)) => {
+                Some(Command::Review { import: Some(PathBuf::from(path.trim_start())) })
+            }
+            _ => None,
+        },
+        "run" if !force => Some(Command::Run((!arg.is_empty()).then(|| arg.to_string()))),
+        "cn" | "cnext" if !force && arg.is_empty() => Some(Command::GotoError(ErrorJump::Next)),
+        "cp" | "cprevious" if !force && arg.is_empty() => {
+            Some(Command::GotoError(ErrorJump::Previous))
+        }
+        "cc" if !force => match arg {
+            "" => Some(Command::GotoError(ErrorJump::Current)),
+            n => n.parse().ok().map(|n| Command::GotoError(ErrorJump::Nth(n))),
+        },
+        "scratch" if !force => match arg {
+            "" => Some(Command::Scratch(FileType::Plain)),
+            name => FileType::from_config_name(name).map(Command::Scratch),
+        },
+        "grep" if !force => Some(Command::Grep(arg.to_string())),
+        "y" | "yank" if !force => register.map(|register| Command::Yank { range, register }),
+        "pu" | "put" if !force => register.map(Command::Put),
+        "surround" | "unsurround" if !force => {
+            let mut words = parse_surroundings(arg)?.into_iter();
+            match (name, words.next(), words.next(), words.next()) {
+                ("surround", Some(new), None, None) => {
+                    Some(Command::Surround { old: None, new: Some(new) })
+                }
+                ("surround", Some(old), Some(new), None) => {
+                    Some(Command::Surround { old: Some(old), new: Some(new) })
+                }
+                ("unsurround", Some(old), None, None) => {
+                    Some(Command::Surround { old: Some(old), new: None })
+                }
+                _ => None,
+            }
+        }
+        "case" if !force => Case::from_name(arg).map(Command::Case),
+        "sor" | "sort" => {
+            let flags: String = arg.split_whitespace().collect();
+            if !flags.chars().all(|c| matches!(c, 'n' | 'u')) {
+                return None;
+            }
+            let (numeric, unique) = (flags.contains('n'), flags.contains('u'));
+            let op = LineOp::Sort { descending: force, numeric, unique };
+            Some(Command::Lines { range, op })
+        }
+        "uniq" if !force && arg.is_empty() => Some(Command::Lines { range, op: LineOp::Unique }),
+        "reverse" if !force && arg.is_empty() => {
+            Some(Command::Lines { range, op: LineOp::Reverse })
+        }
+        "shuffle" if !force && arg.is_empty() => {
+            Some(Command::Lines { range, op: LineOp::Shuffle })
+        }
+        "fold" | "unfold" if !force && matches!(arg, "" | "all") => {
+            Some(Command::Fold { unfold: name == "unfold", all: arg == "all" })
+        }
+        _ => None,
+    }
+}
+
+/// Splits the arguments of `:surround` into what they name. Tags may contain spaces.
+fn parse_surroundings(arg: &str) -> Option<Vec<Surrounding>> {
+    let mut surroundings = Vec::new();
+    let mut rest = arg.trim_start();
+    while !rest.is_empty() {
+        let len = match rest.find('>') {
+            Some(i) if rest.starts_with('<') => i + 1,
+            _ => rest.find(char::is_whitespace).unwrap_or(rest.len()),
+        };
+        surroundings.push(Surrounding::parse(&rest[..len])?);
+        rest = rest[len..].trim_start();
+    }
+    Some(surroundings)
+}
+
+fn parse_range(text: &str) -> Option<(Option<LineRange>, &str)> {
+    if let Some(rest) = text.strip_prefix('%') {
+        return Some((Some(LineRange::Whole), rest));
+    }
+    if let Some(rest) = text.strip_prefix("'<,'>") {
+        return Some((Some(LineRange::Selection), rest));
+    }
+
+    let Some((beg, rest)) = parse_address(text) else {
+        return Some((None, text));
+    };
+    let Some(rest) = rest.strip_prefix(',') else {
+        return Some((Some(LineRange::Span(beg, beg)), rest));
+    };
+    let (end, rest) = parse_address(rest)?;
+    Some((Some(LineRange::Span(beg, end)), rest))
+}
+
+fn parse_address(text: &str) -> Option<(Address, &str)> {
+    if let Some(rest) = text.strip_prefix('.') {
+        return Some((Address::Current, rest));
+    }
+    if let Some(rest) = text.strip_prefix('$') {
+        return Some((Address::Last, rest));
+    }
+    let len = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let line = text[..len].parse::<CoordType>().ok()?;
+    Some((Address::Line(line.saturating_sub(1)), &text[len..]))
+}
+
+fn parse_substitute(range: LineRange, args: &str) -> Option<Command> {
+    let mut chars = args.chars();
+    let delimiter = chars.next()?;
+    if delimiter.is_alphanumeric() || delimiter.is_whitespace() || delimiter == '\\' {
+        return None;
+    }
+
+    // The trailing delimiters are optional, like in Vim.
+    let mut parts = [String::new(), String::new()];
+    let mut rest = chars.as_str();
+    for part in &mut parts {
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            if c == delimiter {
+                break;
+            }
+            if c == '\\' {
+                match chars.next() {
+                    // An escaped delimiter stands for itself. Other escapes are left to the regex.
+                    Some(next) if next == delimiter => part.push(next),
+                    Some(next) => {
+                        part.push(c);
+                        part.push(next);
+                    }
+                    None => part.push(c),
+                }
+                continue;
+            }
+            part.push(c);
+        }
+        rest = chars.as_str();
+    }
+
+    let mut every = false;
+    let mut match_case = true;
+    let mut confirm = false;
+    for flag in rest.trim().chars() {
+        match flag {
+            'g' => every = true,
+            'c' => confirm = true,
+            'i' => match_case = false,
+            'I' => match_case = true,
+            _ => return None,
+        }
+    }
+
+    let [pattern, replacement] = parts;
+    Some(Command::Substitute { range, pattern, replacement, every, match_case, confirm })
+}
+
+fn parse_setting(arg: &str) -> Option<Setting> {
+    if let Some((name, value)) = arg.split_once('=') {
+        if name == "density" {
+            return Density::parse(value).map(Setting::Density);
+        }
+        if name == "whitespace" {
+            return RenderWhitespace::parse(value).map(Setting::Whitespace);
+        }
+        if matches!(name, "filetype" | "ft") {
+            return FileType::from_config_name(value).map(Setting::FileType);
+        }
+        let value = value.parse::<CoordType>().ok()?;
+        return match name {
+            "tabstop" | "ts" | "shiftwidth" | "sw" if value > 0 => Some(Setting::TabStop(value)),
+            "colorcolumn" | "cc" if value >= 0 => Some(Setting::ColorColumn(value)),
+            "wrapcolumn" | "wc" if value >= 0 => Some(Setting::WrapColumn(value)),
+            _ => None,
+        };
+    }
+
+    let (name, value) = if let Some(name) = arg.strip_suffix('!') {
+        (name, None)
+    } else if let Some(name) = arg.strip_prefix("no") {
+        (name, Some(false))
+    } else if let Some(name) = arg.strip_prefix("inv") {
+        (name, None)
+    } else {
+        (arg, Some(true))
+    };
+    match name {
+        "wrap" => Some(Setting::Wrap(value)),
+        "number" | "nu" => Some(Setting::Number(value)),
+        "expandtab" | "et" => Some(Setting::ExpandTab(value)),
+        "ansi" => Some(Setting::Ansi(value)),
+        "hex" => Some(Setting::Hex(value)),
+        "readonly" | "ro" => Some(Setting::ReadOnly(value)),
+        "bomb" | "bom" => Some(Setting::Bom(value)),
+        "list" => Some(Setting::List(value)),
+        _ => None,
+    }
+}
+
+pub fn draw_command_line(ctx: &mut Context, state: &mut State) {
+    let mut done = false;
+
+    if state.documents.active().is_some() {
+        ctx.modal_begin("command-line", loc(LocId::ViewCommandLine));
+        {
+            if ctx.editline("command", &mut state.command_line) {
+                state.command_line_invalid = false;
+            }
+            if state.command_line_invalid {
+                ctx.attr_background_rgba(ctx.indexed(IndexedColor::Red));
+                ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightWhite));
+            }
+
+            ctx.attr_intrinsic_size(Size { width: 40, height: 1 });
+            ctx.steal_focus();
+
+            if ctx.consume_shortcut(vk::RETURN) {
+                match parse(&state.command_line) {
+                    Some(command) => {
+                        execute(ctx, state, command);
+                        done = true;
+                    }
+                    None => state.command_line_invalid = true,
+                }
+                ctx.needs_rerender();
+            }
+        }
+        done |= ctx.modal_end();
+    } else {
+        done = true;
+    }
+
+    if done {
+        state.wants_command_line = false;
+        state.command_line.clear();
+        state.command_line_invalid = false;
+        ctx.needs_rerender();
+    }
+}
+
+/// Asks whether to replace the selected hit of a `:s` with the `c` flag,
+/// see [`TextBuffer::find_and_replace_step()`](edit::buffer::TextBuffer::find_and_replace_step).
+pub fn draw_substitute_confirm(ctx: &mut Context, state: &mut State) {
+    let mut step = None;
+
+    ctx.modal_begin("substitute-confirm", loc(LocId::SearchReplaceConfirm));
+    {
+        let contains_focus = ctx.contains_focus();
+
+        ctx.table_begin("choices");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        ctx.attr_position(Position::Center);
+        ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+        {
+            ctx.table_next_row();
+            ctx.inherit_focus();
+
+            if ctx.button("yes", loc(LocId::Yes), ButtonStyle::default()) {
+                step = Some(ReplaceStep::Replace);
+            }
+            ctx.inherit_focus();
+            if ctx.button("no", loc(LocId::No), ButtonStyle::default()) {
+                step = Some(ReplaceStep::Skip);
+            }
+            if ctx.button("all", loc(LocId::SearchReplaceAll), ButtonStyle::default()) {
+                step = Some(ReplaceStep::ReplaceAll);
+            }
+            if ctx.button("cancel", loc(LocId::Cancel), ButtonStyle::default()) {
+                state.substitute_confirm = None;
+            }
+        }
+        ctx.table_end();
+
+        // The same keys as in Vim.
+        if contains_focus {
+            if ctx.consume_shortcut(vk::Y) {
+                step = Some(ReplaceStep::Replace);
+            } else if ctx.consume_shortcut(vk::N) {
+                step = Some(ReplaceStep::Skip);
+            } else if ctx.consume_shortcut(vk::A) {
+                step = Some(ReplaceStep::ReplaceAll);
+            } else if ctx.consume_shortcut(vk::Q) {
+                state.substitute_confirm = None;
+            }
+        }
+    }
+    if ctx.modal_end() {
+        state.substitute_confirm = None;
+    }
+
+    if let Some(step) = step
+        && let Some(confirm) = &mut state.substitute_confirm
+        && let Some(doc) = state.documents.active()
+    {
+        let result = doc.buffer.borrow_mut().find_and_replace_step(
+            &confirm.pattern,
+            confirm.options,
+            &confirm.replacement,
+            &mut confirm.lines,
+            confirm.every,
+            step,
+        );
+        match result {
+            Ok(true) => {}
+            Ok(false) => state.substitute_confirm = None,
+            Err(err) => {
+                state.substitute_confirm = None;
+                error_log_add(ctx, state, err);
+            }
+        }
+    }
+    if state.substitute_confirm.is_none() {
+        ctx.needs_rerender();
+    }
+}
+
+fn execute(ctx: &mut Context, state: &mut State, command: Command) {
+    let Some(doc) = state.documents.active_mut() else {
+        return;
+    };
+
+    match command {
+        Command::Goto(address) => {
+            state.jump_list.push(&doc.buffer);
+            let mut tb = doc.buffer.borrow_mut();
+            let lines = LineRange::Span(address, address).resolve(
+                tb.cursor_logical_pos().y,
+                tb.logical_line_count(),
+                None,
+            );
+            tb.cursor_move_to_logical(Point { x: 0, y: lines.start });
+            tb.make_cursor_visible();
+        }
+        Command::Substitute { range, pattern, replacement, every, match_case, confirm } => {
+            if let Err(err) = icu::init() {
+                error_log_add(ctx, state, err);
+                return;
+            }
+            let pattern = if pattern.is_empty() { state.search_needle.clone() } else { pattern };
+            let result = {
+                let mut tb = doc.buffer.borrow_mut();
+                let lines = resolve_range(&tb, range);
+                let options = SearchOptions { match_case, whole_word: false, use_regex: true };
+                let result = if confirm {
+                    // Select the first hit and ask.
+                    tb.cursor_move_to_logical(Point { x: 0, y: lines.start });
+                    tb.clear_selection();
+                    let mut confirm =
+                        SubstituteConfirm { pattern, options, replacement, lines, every };
+                    tb.find_and_replace_step(
+                        &confirm.pattern,
+                        options,
+                        &confirm.replacement,
+                        &mut confirm.lines,
+                        every,
+                        ReplaceStep::Skip,
+                    )
+                    .map(|found| {
+                        if found {
+                            state.substitute_confirm = Some(confirm);
+                        }
+                    })
+                } else {
+                    tb.find_and_replace_in_lines(&pattern, options, &replacement, lines, every)
+                        .map(|_| ())
+                };
+                tb.make_cursor_visible();
+                result
+            };
+            if let Err(err) = result {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Write { path, close } => {
+            let dir = doc.dir.as_ref().unwrap_or(&state.file_picker_pending_dir);
+            let path = path.map(|p| dir.as_path().join(p));
+            if path.is_none() && doc.path.is_none() {
+                // Nowhere to save to? Ask.
+                state.wants_file_picker = StateFilePicker::SaveAs;
+                return;
+            }
+            if let Err(err) = doc.save(path, &state.trust) {
+                error_log_add(ctx, state, err);
+            } else if close {
+                state.documents.remove_active();
+            }
+        }
+        Command::Edit(path) => {
+            let dir = doc.dir.as_ref().unwrap_or(&state.file_picker_pending_dir);
+            let path = dir.as_path().join(path);
+            if let Err(err) = state.documents.add_file_path(&path) {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Quit { all, force } => {
+            if all {
+                state.wants_exit = true;
+            } else if force {
+                state.documents.remove_active();
+            } else {
+                state.wants_close = true;
+            }
+        }
+        Command::Set(settings) => {
+            let mut tb = doc.buffer.borrow_mut();
+            let mut result = Ok(());
+            for setting in settings {
+                match setting {
+                    Setting::Wrap(on) => {
+                        let on = on.unwrap_or(!tb.is_word_wrap_enabled());
+                        tb.set_word_wrap(on);
+                    }
+                    Setting::Number(on) => {
+                        let on = on.unwrap_or(tb.margin_width() == 0);
+                        tb.set_margin_enabled(on);
+                    }
+                    Setting::ExpandTab(on) => {
+                        let on = on.unwrap_or(tb.indent_with_tabs());
+                        tb.set_indent_with_tabs(!on);
+                    }
+                    Setting::Ansi(on) => {
+                        let on = on.unwrap_or(!tb.is_ansi_view());
+                        tb.set_ansi_view(on);
+                    }
+                    Setting::Hex(on) => {
+                        let on = on.unwrap_or(!tb.is_hex_view());
+                        result = result.and(tb.set_hex_view(on));
+                    }
+                    Setting::ReadOnly(on) => {
+                        let on = on.unwrap_or(!tb.is_read_only());
+                        tb.set_read_only(on);
+                    }
+                    Setting::Bom(on) => {
+                        let on = on.unwrap_or(!tb.has_bom());
+                        // UTF-16 and the like always have one.
+                        _ = tb.set_bom(on);
+                    }
+                    Setting::List(on) => {
+                        let on = on.unwrap_or(tb.render_whitespace() == RenderWhitespace::None);
+                        let mode = if on { RenderWhitespace::All } else { RenderWhitespace::None };
+                        tb.set_render_whitespace(mode);
+                    }
+                    Setting::TabStop(width) => {
+                        tb.set_tab_size(width);
+                    }
+                    Setting::ColorColumn(column) => tb.set_ruler(column),
+                    Setting::WrapColumn(column) => tb.set_word_wrap_limit(column),
+                    // Applied to the buffer by `update_density()` in main.rs.
+                    Setting::Density(density) => doc.density = density,
+                    Setting::Whitespace(mode) => tb.set_render_whitespace(mode),
+                    Setting::FileType(file_type) => {
+                        drop(tb);
+                        doc.set_file_type(file_type);
+                        tb = doc.buffer.borrow_mut();
+                    }
+                }
+            }
+            drop(tb);
+            if let Err(err) = result {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Help(query) => {
+            if let Err(err) = help::open_topic(state, &query) {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Man { name, section } => {
+            if let Err(err) = help::open(state, HelpPage::Man { name, section }) {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Print => crate::print_to_scrollback(ctx, state),
+        Command::Macro { name, save } => {
+            let result = if save {
+                state.macros.save_last(&name)
+            } else {
+                state.macros.play_saved(&name)
+            };
+            if let Err(err) = result {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Review { import } => {
+            let result = match import {
+                Some(path) => {
+                    let dir = doc.dir.as_ref().unwrap_or(&state.file_picker_pending_dir);
+                    let path = dir.as_path().join(path);
+                    review::import(state, &path)
+                }
+                None => review::export(state),
+            };
+            if let Err(err) = result {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Run(Some(command)) => state.tasks.start(&command),
+        Command::Run(None) => state.wants_task_history = true,
+        Command::GotoError(jump) => {
+            if let Err(err) = tasks::goto_error(state, jump) {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Scratch(file_type) => draw_add_scratch_document(ctx, state, file_type),
+        Command::Grep(needle) => {
+            state.wants_project_search = true;
+            if !needle.is_empty() {
+                state.project_search.needle = needle;
+                state.project_search.start(state.search_options);
+            }
+        }
+        Command::Yank { range, register } => {
+            let lines = range.map(|range| resolve_range(&doc.buffer.borrow(), range));
+            clipboard_history::yank(state, register, lines);
+        }
+        Command::Put(register) => {
+            if let Err(err) = clipboard_history::put(state, register) {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Command::Case(case) => {
+            let mut tb = doc.buffer.borrow_mut();
+            tb.convert_case(case);
+            tb.make_cursor_visible();
+        }
+        Command::Lines { range, op } => {
+            let mut tb = doc.buffer.borrow_mut();
+            let range = range.unwrap_or(if tb.has_selection() {
+                LineRange::Selection
+            } else {
+                LineRange::Whole
+            });
+            let lines = resolve_range(&tb, range);
+            tb.transform_lines(lines, |lines| op.apply(lines));
+            tb.make_cursor_visible();
+        }
+        Command::Fold { unfold, all } => {
+            let mut tb = doc.buffer.borrow_mut();
+            match (unfold, all) {
+                (false, false) => _ = tb.fold_at_cursor(),
+                (false, true) => _ = tb.fold_all(),
+                (true, false) => _ = tb.unfold_at_cursor(),
+                (true, true) => _ = tb.unfold_all(),
+            }
+            tb.make_cursor_visible();
+        }
+        Command::Surround { old, new } => {
+            let found = {
+                let mut tb = doc.buffer.borrow_mut();
+                let found = match (&old, &new) {
+                    (Some(old), new) => tb.change_surrounding(old, new.as_ref()),
+                    (None, Some(new)) => {
+                        tb.surround(new.open().as_bytes(), new.close().as_bytes());
+                        true
+                    }
+                    (None, None) => true,
+                };
+                tb.make_cursor_visible();
+                found
+            };
+            if !found {
+                error_log_add(ctx, state, APP_SURROUNDING_NOT_FOUND);
+            }
+        }
+    }
+}
+
+impl LineOp {
+    pub fn apply(self, lines: &mut Vec<&[u8]>) {
+        match self {
+            LineOp::Sort { descending, numeric, unique } => {
+                if numeric {
+                    // Like in Vim, lines without a number go first.
+                    lines.sort_by(|a, b| {
+                        let (a, b) = (first_number(a), first_number(b));
+                        a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                    });
+                } else {
+                    lines.sort();
+                }
+                if descending {
+                    lines.reverse();
+                }
+                if unique {
+                    lines.dedup();
+                }
+            }
+            LineOp::Unique => {
+                let mut seen = HashSet::new();
+                lines.retain(|line| seen.insert(*line));
+            }
+            LineOp::Reverse => lines.reverse(),
+            LineOp::Shuffle => {
+                // A xorshift generator seeded from the std's random hasher keys is plenty here.
+                let mut state = RandomState::new().hash_one(lines.len()) | 1;
+                for i in (1..lines.len()).rev() {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    lines.swap(i, (state % (i as u64 + 1)) as usize);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the first decimal number in `line`, if any.
+fn first_number(line: &[u8]) -> Option<f64> {
+    let beg = line.iter().position(u8::is_ascii_digit)?;
+    let beg = if beg > 0 && line[beg - 1] == b'-' { beg - 1 } else { beg };
+    let mut end = beg + 1;
+    while end < line.len() && line[end].is_ascii_digit() {
+        end += 1;
+    }
+    if end + 1 < line.len() && line[end] == b'.' && line[end + 1].is_ascii_digit() {
+        end += 1;
+        while end < line.len() && line[end].is_ascii_digit() {
+            end += 1;
+        }
+    }
+    str::from_utf8(&line[beg..end]).ok()?.parse().ok()
+}
+
+/// Resolves `range` into 0-based logical lines of `tb`.
+fn resolve_range(tb: &TextBuffer, range: LineRange) -> Range<CoordType> {
+    range.resolve(tb.cursor_logical_pos().y, tb.logical_line_count(), tb.selected_lines())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(":42"), Some(Command::Goto(Address::Line(41))));
+        assert_eq!(parse("$"), Some(Command::Goto(Address::Last)));
+        assert_eq!(
+            parse(":%s/foo/bar/g"),
+            Some(Command::Substitute {
+                range: LineRange::Whole,
+                pattern: "foo".to_string(),
+                replacement: "bar".to_string(),
+                every: true,
+                match_case: true,
+                confirm: false,
+            })
+        );
+        assert_eq!(
+            parse(r":3,$s#a\#b#c"),
+            Some(Command::Substitute {
+                range: LineRange::Span(Address::Line(2), Address::Last),
+                pattern: "a#b".to_string(),
+                replacement: "c".to_string(),
+                every: false,
+                match_case: true,
+                confirm: false,
+            })
+        );
+        assert_eq!(
+            parse(r":%s/(\w+)=(\w+)/$2=$1/gc"),
+            Some(Command::Substitute {
+                range: LineRange::Whole,
+                pattern: r"(\w+)=(\w+)".to_string(),
+                replacement: "$2=$1".to_string(),
+                every: true,
+                match_case: true,
+                confirm: true,
+            })
+        );
+        assert_eq!(
+            parse(":w new name.txt"),
+            Some(Command::Write { path: Some(PathBuf::from("new name.txt")), close: false })
+        );
+        assert_eq!(parse(":x"), Some(Command::Write { path: None, close: true }));
+        assert_eq!(parse(":q!"), Some(Command::Quit { all: false, force: true }));
+        assert_eq!(
+            parse(":set nowrap ts=4 wc=72 et! density=compact"),
+            Some(Command::Set(vec![
+                Setting::Wrap(Some(false)),
+                Setting::TabStop(4),
+                Setting::WrapColumn(72),
+                Setting::ExpandTab(None),
+                Setting::Density(Density::Compact),
+            ]))
+        );
+
+        assert_eq!(
+            parse(":set ansi invansi"),
+            Some(Command::Set(vec![Setting::Ansi(Some(true)), Setting::Ansi(None)]))
+        );
+        assert_eq!(
+            parse(":set hex nohex"),
+            Some(Command::Set(vec![Setting::Hex(Some(true)), Setting::Hex(Some(false))]))
+        );
+        assert_eq!(
+            parse(":set ro noreadonly"),
+            Some(Command::Set(vec![Setting::ReadOnly(Some(true)), Setting::ReadOnly(Some(false))]))
+        );
+        assert_eq!(
+            parse(":set nobomb bom!"),
+            Some(Command::Set(vec![Setting::Bom(Some(false)), Setting::Bom(None)]))
+        );
+        assert_eq!(
+            parse(":set list! whitespace=boundary"),
+            Some(Command::Set(vec![
+                Setting::List(None),
+                Setting::Whitespace(RenderWhitespace::Boundary),
+            ]))
+        );
+
+        assert_eq!(parse(":help search"), Some(Command::Help("search".to_string())));
+        assert_eq!(parse(":print"), Some(Command::Print));
+        assert_eq!(parse(":p foo"), None);
+        assert_eq!(
+            parse(":man 3 printf"),
+            Some(Command::Man { name: "printf".to_string(), section: Some("3".to_string()) })
+        );
+
+        assert_eq!(
+            parse(":macro save wrap-div"),
+            Some(Command::Macro { name: "wrap-div".to_string(), save: true })
+        );
+        assert_eq!(parse(":macro x y"), None);
+        assert_eq!(parse(":review"), Some(Command::Review { import: None }));
+        assert_eq!(
+            parse(":review import ../review.md"),
+            Some(Command::Review { import: Some(PathBuf::from("../review.md")) })
+        );
+        assert_eq!(parse(":review export"), None);
+        assert_eq!(parse(":run cargo build"), Some(Command::Run(Some("cargo build".to_string()))));
+        assert_eq!(parse(":run"), Some(Command::Run(None)));
+        assert_eq!(parse(":cn"), Some(Command::GotoError(ErrorJump::Next)));
+        assert_eq!(parse(":cc 3"), Some(Command::GotoError(ErrorJump::Nth(3))));
+        assert_eq!(parse(":cc x"), None);
+        assert_eq!(parse(":scratch"), Some(Command::Scratch(FileType::Plain)));
+        assert_eq!(parse(":scratch rust"), Some(Command::Scratch(FileType::Rust)));
+        assert_eq!(parse(":scratch cobol"), None);
+        assert_eq!(parse(":grep fn main"), Some(Command::Grep("fn main".to_string())));
+        assert_eq!(parse(":y a"), Some(Command::Yank { range: None, register: 'a' }));
+        assert_eq!(
+            parse(":'<,'>yank B"),
+            Some(Command::Yank { range: Some(LineRange::Selection), register: 'B' })
+        );
+        assert_eq!(parse(":put a"), Some(Command::Put('a')));
+        let tag = Surrounding::Tag { name: "a".to_string(), attributes: "href=\"#\"".to_string() };
+        assert_eq!(
+            parse(":surround ( <a href=\"#\">"),
+            Some(Command::Surround { old: Surrounding::parse("("), new: Some(tag) })
+        );
+        assert_eq!(
+            parse(":unsurround **"),
+            Some(Command::Surround { old: Surrounding::parse("**"), new: None })
+        );
+        assert_eq!(
+            parse(":'<,'>sort! n u"),
+            Some(Command::Lines {
+                range: Some(LineRange::Selection),
+                op: LineOp::Sort { descending: true, numeric: true, unique: true },
+            })
+        );
+        assert_eq!(parse(":case kebab"), Some(Command::Case(Case::Kebab)));
+        assert_eq!(parse(":fold"), Some(Command::Fold { unfold: false, all: false }));
+        assert_eq!(parse(":unfold all"), Some(Command::Fold { unfold: true, all: true }));
+        assert_eq!(parse(":fold 3"), None);
+        assert_eq!(parse(":shuffle"), Some(Command::Lines { range: None, op: LineOp::Shuffle }));
+        assert_eq!(
+            parse(":set ft=markdown"),
+            Some(Command::Set(vec![Setting::FileType(FileType::Markdown)]))
+        );
+
+        assert_eq!(parse(":"), None);
+        assert_eq!(parse(":%w"), None);
+        assert_eq!(parse(":s/a/b/z"), None);
+        assert_eq!(parse(":set frobnicate"), None);
+        assert_eq!(parse(":set density=tiny"), None);
+        assert_eq!(parse(":set whitespace=some"), None);
+        assert_eq!(parse(":e"), None);
+        assert_eq!(parse(":y"), None);
+        assert_eq!(parse(":pu ab"), None);
+        assert_eq!(parse(":%put a"), None);
+        assert_eq!(parse(":man"), None);
+        assert_eq!(parse(":surround"), None);
+        assert_eq!(parse(":surround ab"), None);
+        assert_eq!(parse(":unsurround ( ["), None);
+        assert_eq!(parse(":sort x"), None);
+        assert_eq!(parse(":case"), None);
+        assert_eq!(parse(":uniq!"), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let span = LineRange::Span(Address::Line(7), Address::Current);
+        assert_eq!(span.resolve(2, 10, None), 2..8);
+        assert_eq!(LineRange::Whole.resolve(2, 10, None), 0..10);
+        assert_eq!(LineRange::Selection.resolve(2, 10, Some(4..6)), 4..6);
+        assert_eq!(LineRange::Selection.resolve(2, 10, None), 2..3);
+        assert_eq!(LineRange::Span(Address::Line(99), Address::Last).resolve(0, 10, None), 9..10);
+    }
+
+    #[test]
+    fn test_line_ops() {
+        let apply = |op: LineOp, text: &str| {
+            let mut lines: Vec<&[u8]> = text.split(' ').map(str::as_bytes).collect();
+            op.apply(&mut lines);
+            lines.iter().map(|l| str::from_utf8(l).unwrap()).collect::<Vec<_>>().join(" ")
+        };
+        let sort = |descending, numeric, unique| LineOp::Sort { descending, numeric, unique };
+        assert_eq!(apply(sort(false, false, false), "b10 a b2 a"), "a a b10 b2");
+        assert_eq!(apply(sort(false, true, false), "b10 a b2 x-1.5"), "a x-1.5 b2 b10");
+        assert_eq!(apply(sort(true, false, true), "b a c a"), "c b a");
+        assert_eq!(apply(LineOp::Unique, "b a b c a"), "b a c");
+        assert_eq!(apply(LineOp::Reverse, "a b c"), "c b a");
+        let shuffled = apply(LineOp::Shuffle, "a b c d e f g h");
+        let mut sorted: Vec<_> = shuffled.split(' ').collect();
+        sorted.sort();
+        assert_eq!(sorted.join(" "), "a b c d e f g h");
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Helpers for writing git commit messages.
+
+use std::path::Path;
+use std::process::Command;
+
+use edit::buffer::TextBuffer;
+
+const CO_AUTHOR_TRAILER: &str = "Co-authored-by:";
+
+/// Returns whether git opened the given file for editing a commit message.
+pub fn is_commit_message(filename: &str) -> bool {
+    matches!(filename, "COMMIT_EDITMSG" | "MERGE_MSG")
+}
+
+/// Authors and issue references seen in the recent history of a repository,
+/// most recent first.
+#[derive(Default)]
+pub struct CommitHistory {
+    pub co_authors: Vec<String>,
+    pub issues: Vec<String>,
+}
+
+impl CommitHistory {
+    /// Reads the last few hundred commits of the repository that contains `dir`.
+    /// Errors, like git not being installed, result in an empty history.
+    pub fn load(dir: &Path) -> Self {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["log", "-n", "300", "--format=%an <%ae>%n%B%x00"])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                Self::parse(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => Self::default(),
+        }
+    }
+
+    fn parse(log: &str) -> Self {
+        let mut history = Self::default();
+
+        for commit in log.split('\0') {
+            let mut lines = commit.trim_start_matches('\n').lines();
+            if let Some(author) = lines.next() {
+                push_unique(&mut history.co_authors, author.trim());
+            }
+
+            for line in lines {
+                if let Some(co_author) = strip_prefix_ignore_case(line, CO_AUTHOR_TRAILER) {
+                    push_unique(&mut history.co_authors, co_author.trim());
+                }
+
+                let mut rest = line;
+                while let Some(idx) = rest.find('#') {
+                    rest = &rest[idx + 1..];
+                    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+                    if digits > 0 {
+                        push_unique(&mut history.issues, &rest[..digits]);
+                    }
+                }
+            }
+        }
+
+        history
+    }
+}
+
+/// Completes the text in front of the cursor: Inside a `Co-authored-by:` trailer with a
+/// previous author, and after a `#` with an issue number. Returns whether anything changed.
+pub fn complete(tb: &mut TextBuffer, history: &CommitHistory) -> bool {
+    let line = tb.line_text_before_cursor();
+
+    let (partial, candidates) =
+        if let Some(partial) = strip_prefix_ignore_case(&line, CO_AUTHOR_TRAILER) {
+            (partial.trim_start(), &history.co_authors)
+        } else if let Some(idx) = line.rfind('#')
+            && line[idx + 1..].bytes().all(|b| b.is_ascii_digit())
+        {
+            (&line[idx + 1..], &history.issues)
+        } else {
+            return false;
+        };
+
+    let Some(candidate) = candidates.iter().find(|c| {
+        c.len() > partial.len()
+            && c.get(..partial.len()).is_some_and(|p| p.eq_ignore_ascii_case(partial))
+    }) else {
+        return false;
+    };
+
+    // `partial` ends at the cursor. Select it by its byte length,
+    // since it may span fewer graphemes than chars.
+    let pos = tb.cursor_logical_pos();
+    tb.clear_selection();
+    tb.cursor_move_to_offset(tb.cursor_offset() - partial.len());
+    tb.start_selection();
+    tb.selection_update_logical(pos);
+    tb.write(candidate.as_bytes(), true);
+    true
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    if head.eq_ignore_ascii_case(prefix) { Some(&s[prefix.len()..]) } else { None }
+}
+
+fn push_unique(list: &mut Vec<String>, item: &str) {
+    if !item.is_empty() && !list.iter().any(|i| i == item) {
+        list.push(item.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edit::helpers::{CoordType, Point};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_history() {
+        let log = "Jane Doe <jane@example.com>\nFix crash (#12)\n\nCo-authored-by: Kim <kim@example.com>\n\0\n\
+                   Kim <kim@example.com>\nAdd feature\n\nCloses #7, see #12\n\0\n";
+        let history = CommitHistory::parse(log);
+        assert_eq!(
+            history.co_authors,
+            vec!["Jane Doe <jane@example.com>", "Kim <kim@example.com>"]
+        );
+        assert_eq!(history.issues, vec!["12", "7"]);
+    }
+
+    #[test]
+    fn test_complete() {
+        let history = CommitHistory {
+            co_authors: vec![
+                "Jane Doe <jane@example.com>".to_string(),
+                "Jose\u{301} Roe <jose@example.com>".to_string(),
+            ],
+            issues: vec!["123".to_string(), "7".to_string()],
+        };
+        // Completes the end of `text`, returning the result, if it changed.
+        let complete = |text: &str| {
+            let mut tb = TextBuffer::new(false).unwrap();
+            tb.set_text(text);
+            tb.cursor_move_to_logical(Point { x: CoordType::MAX, y: CoordType::MAX });
+            complete(&mut tb, &history).then(|| {
+                let mut text = String::new();
+                tb.save_as_string(&mut text);
+                text
+            })
+        };
+
+        assert_eq!(
+            complete("Fix\n\nco-authored-by: ja").as_deref(),
+            Some("Fix\n\nco-authored-by: Jane Doe <jane@example.com>")
+        );
+        assert_eq!(
+            complete("Fix\n\nCo-authored-by: jose\u{301}").as_deref(),
+            Some("Fix\n\nCo-authored-by: Jose\u{301} Roe <jose@example.com>")
+        );
+        assert_eq!(complete("Fix #1").as_deref(), Some("Fix #123"));
+        assert_eq!(complete("Fix #").as_deref(), Some("Fix #123"));
+        assert_eq!(complete("Fix #123"), None);
+        assert_eq!(complete("Fix #9"), None);
+        assert_eq!(complete("Co-authored-by: Kim"), None);
+        assert_eq!(complete("Fix crash"), None);
+    }
+}
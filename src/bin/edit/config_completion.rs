@@ -0,0 +1,138 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Ctrl+Space in the editor's own configs completes what [`config::completions()`] knows:
+//! section names, the keys of the current section, and their values, like `true`, theme
+//! names, file types and shortcuts. A single candidate is inserted right away, several
+//! are offered in a list.
+
+use edit::buffer::TextBuffer;
+use edit::config;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::syntax::SyntaxHighlighter;
+use edit::tui::*;
+
+use crate::localization::*;
+use crate::state::*;
+
+#[derive(Default)]
+pub struct ConfigCompletions {
+    /// The candidates the user is asked to pick from.
+    candidates: Vec<String>,
+    /// How many characters in front of the cursor the candidate replaces.
+    partial_len: CoordType,
+}
+
+impl ConfigCompletions {
+    pub fn is_open(&self) -> bool {
+        !self.candidates.is_empty()
+    }
+}
+
+/// Completes the text in front of the cursor of the active document, which is a config.
+pub fn complete(state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let contents = doc.contents();
+    let mut tb = doc.buffer.borrow_mut();
+
+    let y = tb.cursor_logical_pos().y;
+    let mut text: String =
+        String::from_utf8_lossy(&contents).split_inclusive('\n').take(y as usize).collect();
+    text.push_str(&tb.line_text_before_cursor());
+
+    let themes = SyntaxHighlighter::new().available_themes();
+    let (len, mut candidates) = config::completions(&text, &themes);
+    let partial_len = text[text.len() - len..].chars().count() as CoordType;
+
+    match candidates.len() {
+        0 => {}
+        1 => insert(&mut tb, partial_len, &candidates[0]),
+        _ => {
+            // Whatever the candidates have in common is typed right away.
+            let prefix = common_prefix(&candidates);
+            if prefix != &text[text.len() - len..] {
+                insert(&mut tb, partial_len, prefix);
+            }
+            let partial_len = prefix.chars().count() as CoordType;
+            candidates.sort();
+            state.config_completions = ConfigCompletions { candidates, partial_len };
+        }
+    }
+}
+
+/// Replaces the `partial_len` characters in front of the cursor with `text`.
+fn insert(tb: &mut TextBuffer, partial_len: CoordType, text: &str) {
+    let pos = tb.cursor_logical_pos();
+    tb.clear_selection();
+    if partial_len > 0 {
+        tb.selection_update_logical(Point { x: pos.x - partial_len, y: pos.y });
+    }
+    tb.write(text.as_bytes(), true);
+}
+
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = &candidates[0];
+    let len = candidates[1..].iter().fold(first.len(), |len, c| {
+        first.bytes().zip(c.bytes()).take(len).take_while(|(a, b)| a == b).count()
+    });
+    // The candidates are ASCII, but it mustn't split a character either way.
+    let len = (0..=len).rev().find(|&i| first.is_char_boundary(i)).unwrap_or(0);
+    &first[..len]
+}
+
+pub fn draw_list(ctx: &mut Context, state: &mut State) {
+    let completions = &state.config_completions;
+    let mut chosen = None;
+
+    ctx.modal_begin("config-completions", loc(LocId::ConfigCompletions));
+    {
+        let width = completions.candidates.iter().map(|c| c.len()).max().unwrap_or(0) + 4;
+        let width = (width as CoordType).clamp(20, (ctx.size().width - 20).max(20));
+        let height = (ctx.size().height - 10).clamp(1, completions.candidates.len() as _);
+
+        ctx.scrollarea_begin("scrollarea", Size { width, height });
+        ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+        ctx.inherit_focus();
+        {
+            ctx.list_begin("candidates");
+            ctx.inherit_focus();
+            for (i, candidate) in completions.candidates.iter().enumerate() {
+                if ctx.list_item(i == 0, candidate) == ListSelection::Activated {
+                    chosen = Some(i);
+                }
+            }
+            ctx.list_end();
+        }
+        ctx.scrollarea_end();
+    }
+    let done = ctx.modal_end();
+
+    if let Some(i) = chosen
+        && let Some(doc) = state.documents.active()
+    {
+        let completions = &state.config_completions;
+        let mut tb = doc.buffer.borrow_mut();
+        insert(&mut tb, completions.partial_len, &completions.candidates[i]);
+        tb.make_cursor_visible();
+    }
+    if done || chosen.is_some() {
+        state.config_completions = Default::default();
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_prefix() {
+        let candidates = ["scrollbars".to_string(), "scroll_indicators".to_string()];
+        assert_eq!(common_prefix(&candidates), "scroll");
+        assert_eq!(common_prefix(&["\"python\"".to_string(), "\"plain\"".to_string()]), "\"p");
+        assert_eq!(common_prefix(&["ctrl+".to_string(), "f1".to_string()]), "");
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Reports the problems [`Config::validate()`] finds, instead of ignoring bad settings.
+//!
+//! At startup, the problems of the user's config and the current directory's project config
+//! are listed in the "config problems" document, one `path:line: message` per line. Enter
+//! opens the config at that line. The problems are also shown as diagnostics in the configs
+//! themselves, and updated as they're edited.
+
+use std::env;
+use std::path::Path;
+
+use edit::apperr;
+use edit::config::{self, Config};
+use edit::diagnostics::{Diagnostic, Severity};
+use edit::helpers::*;
+use edit::input::vk;
+use edit::stack_trace;
+use edit::syntax::FileType;
+use edit::tui::*;
+
+use crate::documents::Document;
+use crate::state::*;
+
+/// The name of the document the problems are listed in.
+const NAME: &str = "config problems";
+/// The diagnostics source the problems are shown as.
+const SOURCE: &str = "config";
+
+/// Lists the problems of the configs in effect, if there are any.
+pub fn report(state: &mut State) -> apperr::Result<()> {
+    let cwd = env::current_dir().unwrap_or_default();
+    let project = Config::load_project(&cwd).map(|(_, config)| config);
+
+    let mut text = String::new();
+    for config in [Some(config::get()), project.as_ref()].into_iter().flatten() {
+        let Some(path) = config.path() else {
+            continue;
+        };
+        for problem in config.validate() {
+            text.push_str(&format!("{}:{}: {}\n", path.display(), problem.line, problem.message));
+        }
+    }
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let doc = state.documents.add_scratch(Some(NAME), FileType::Plain, &text)?;
+    doc.buffer.borrow_mut().set_read_only(true);
+    Ok(())
+}
+
+/// Opens the config at the problem under the cursor on Enter.
+pub fn handle_input(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    if doc.path.is_some() || doc.filename != NAME || !ctx.consume_shortcut(vk::RETURN) {
+        return;
+    }
+
+    let (line, _) = doc.buffer.borrow().line_text_at_cursor();
+    let Some((path, line, _)) =
+        line.split_once(": ").and_then(|(location, _)| stack_trace::split_location(location))
+    else {
+        return;
+    };
+    match state.documents.add_file_path(Path::new(path)) {
+        Ok(doc) => {
+            let mut tb = doc.buffer.borrow_mut();
+            tb.cursor_move_to_logical(Point { x: 0, y: line - 1 });
+            tb.make_cursor_visible();
+        }
+        Err(err) => error_log_add(ctx, state, err),
+    }
+    ctx.needs_rerender();
+}
+
+/// Returns whether `doc` is the user's config or a project config.
+pub fn is_config(doc: &Document) -> bool {
+    doc.path.as_ref().is_some_and(|path| {
+        Config::default_path().is_some_and(|p| p == *path)
+            || path.ends_with(Path::new(".edit").join("config.toml"))
+    })
+}
+
+/// Updates the diagnostics of the active document while it's edited, if it's a config.
+pub fn update(state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let (Some(path), true) = (&doc.path, is_config(doc)) else {
+        return;
+    };
+    let generation = doc.buffer.borrow().generation();
+    if state.config_validated.as_ref().is_some_and(|(p, g)| p == path && *g == generation) {
+        return;
+    }
+    state.config_validated = Some((path.clone(), generation));
+    show_diagnostics(doc);
+}
+
+/// Shows the problems of `doc` as diagnostics, if it's a config.
+pub fn show_diagnostics(doc: &Document) {
+    if !is_config(doc) {
+        return;
+    }
+
+    let text = String::from_utf8_lossy(&doc.contents()).into_owned();
+    let diagnostics = Config::parse(&text)
+        .validate()
+        .into_iter()
+        .map(|problem| Diagnostic {
+            line: problem.line as CoordType - 1,
+            column: 0,
+            severity: Severity::Error,
+            message: problem.message,
+        })
+        .collect();
+    doc.buffer.borrow_mut().set_diagnostics(SOURCE, diagnostics);
+}
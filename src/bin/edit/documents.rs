@@ -2,17 +2,44 @@
 // Licensed under the MIT License.
 
 use std::collections::LinkedList;
-use std::ffi::OsStr;
-use std::fs::File;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::Write as _;
 use std::path::{Path, PathBuf};
+use std::{mem, process};
 
-use edit::buffer::{RcTextBuffer, TextBuffer};
+use edit::buffer::{DEFAULT_UNDO_LIMIT, RcTextBuffer, TextBuffer};
+use edit::config::{BomPolicy, Density};
 use edit::helpers::{CoordType, Point};
 use edit::simd::memrchr2;
-use edit::{apperr, path, sys};
+use edit::git::Repo;
+use edit::{apperr, config, path, stack_trace, sys};
 use edit::syntax::{SyntaxHighlighter, FileType};
 
+use crate::backup::{self, BackupSettings};
+use crate::{commit_message, config_problems};
+use crate::file_watch::{self, DiskStamp};
+use crate::formatter;
+use crate::help::HelpPage;
+use crate::notes::Notes;
+use crate::remote::RemotePath;
+use crate::save_transforms::SaveTransforms;
 use crate::state::DisplayablePathBuf;
+use crate::sudo;
+use crate::workspace_trust::TrustStore;
+
+/// How much of a file [`Document::detect_stack_trace()`] looks at.
+const STACK_TRACE_SNIFF_LEN: usize = 64 * 1024;
+/// How much of the start of a file [`Document::detect_generated()`] looks at.
+const GENERATED_SNIFF_LEN: usize = 64 * 1024;
+/// How much of the end of a file [`Document::detect_generated()`] looks at for a source map.
+const SOURCE_MAP_SNIFF_LEN: usize = 1024;
+/// How many lines at the start of a file may say that it's generated.
+const GENERATED_MARKER_LINES: usize = 10;
+/// Code with lines at least this long on average is considered minified.
+const MINIFIED_LINE_LEN: usize = 1000;
+/// How many edits can be undone in the large-file mode. Each one may hold a lot of text.
+const LARGE_FILE_UNDO_LIMIT: usize = 100;
 
 pub struct Document {
     pub buffer: RcTextBuffer,
@@ -21,53 +48,270 @@ pub struct Document {
     pub filename: String,
     pub file_id: Option<sys::FileId>,
     pub new_file_counter: usize,
-    pub syntax_highlighter: Option<SyntaxHighlighter>,
     pub file_type: FileType,
+    /// Applied before saving, see [`crate::save_transforms`].
+    pub save_transforms: SaveTransforms,
+    /// Set after saving, so that the linters get to look at the new contents.
+    pub wants_lint: bool,
+    /// The pages a help document has shown, the current one last. Each one remembers
+    /// the cursor position to return to. Empty for all other documents. See [`crate::help`].
+    pub help: Vec<(HelpPage, Point)>,
+    /// The density from the config or `:set density=...`. The one in effect
+    /// may be more compact in narrow terminals, see [`crate::state::State::density`].
+    pub density: Density,
+    /// Applied to the byte order mark when the file is read, see [`Document::apply_bom_policy()`].
+    pub bom_policy: BomPolicy,
+    /// Whether the file was backed up in this session, see [`crate::backup`].
+    pub backed_up: bool,
+    /// The file as it was last read or saved, see [`crate::file_watch`].
+    pub disk_stamp: Option<DiskStamp>,
+    /// Whether the file changed since then, which the user is asked about.
+    pub changed_on_disk: bool,
+    /// See [`crate::notes`].
+    pub notes: Notes,
+    /// Whether the file is too large for all features, see [`Document::set_large_file()`].
+    pub large_file: bool,
+    /// Whether the file looks generated or minified, see [`Document::detect_generated()`].
+    pub generated: bool,
+    /// Where the file is if it's on another machine, see [`crate::remote`].
+    /// The `path` is `None` then.
+    pub remote: Option<RemotePath>,
+    /// The git repository the file belongs to, which may be a worktree or a submodule.
+    pub repo: Option<Repo>,
+    /// Whether it's a scratch document, see [`DocumentManager::add_scratch()`].
+    pub scratch: bool,
 }
 
 impl Document {
-    pub fn save(&mut self, new_path: Option<PathBuf>) -> apperr::Result<()> {
+    pub fn save(&mut self, new_path: Option<PathBuf>, trust: &TrustStore) -> apperr::Result<()> {
+        let path = new_path.as_deref().unwrap_or_else(|| self.path.as_ref().unwrap().as_path());
+
+        if let Some(settings) = BackupSettings::load(config::get()) {
+            backup::create(&settings, path, new_path.is_some() || !self.backed_up)?;
+            self.backed_up = true;
+        }
+
+        self.save_with(new_path, trust, |tb, path| {
+            if !Self::write_via_temp_file(tb, path)? {
+                // Truncating the file would pull the rug out from under a mapping of it.
+                tb.unmap()?;
+                let mut file = DocumentManager::open_for_writing(path)?;
+                tb.write_file(&mut file)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Saves the file as root after [`Document::save()`] was denied access to it,
+    /// see [`crate::sudo`]. No backup is made.
+    pub fn save_elevated(&mut self, trust: &TrustStore) -> apperr::Result<()> {
+        self.save_with(None, trust, sudo::write)
+    }
+
+    /// Applies the save transformations and the formatter, and then has `write` write the
+    /// document to the file. `trust` decides whether a project's formatter may run.
+    fn save_with(
+        &mut self,
+        new_path: Option<PathBuf>,
+        trust: &TrustStore,
+        write: impl FnOnce(&mut TextBuffer, &Path) -> apperr::Result<()>,
+    ) -> apperr::Result<()> {
         let path = new_path.as_deref().unwrap_or_else(|| self.path.as_ref().unwrap().as_path());
-        let mut file = DocumentManager::open_for_writing(path)?;
 
         {
             let mut tb = self.buffer.borrow_mut();
-            tb.write_file(&mut file)?;
+            self.save_transforms.apply(&mut tb);
+            if !self.generated {
+                let old_path = self.path.as_deref();
+                formatter::format_before_save(&mut tb, old_path, path, self.file_type, trust);
+            }
+            // Rewriting the same contents would only retrigger the build tools watching the file.
+            if new_path.is_none()
+                && !tb.is_modified_since_save()
+                && self.disk_stamp.is_some()
+                && file_watch::stamp(path) == self.disk_stamp
+            {
+                tb.mark_as_clean();
+                return Ok(());
+            }
+            write(&mut tb, path)?;
         }
 
         if let Ok(id) = sys::file_id(None, path) {
             self.file_id = Some(id);
         }
+        self.disk_stamp = file_watch::stamp(path);
+        self.changed_on_disk = false;
 
         if let Some(path) = new_path {
             self.set_path(path);
         }
+        // Where the notes' lines are now is only worth keeping along with the file.
+        // Losing that is no reason to fail the save, though.
+        if let Some(path) = &self.path {
+            _ = self.notes.save(path);
+        }
 
+        config_problems::show_diagnostics(self);
+        self.wants_lint = true;
         Ok(())
     }
 
+    /// Writes `tb` to a temporary file next to `path` and renames it over `path`,
+    /// so that a crash or a full disk never leaves a half-written file behind.
+    /// The permissions, owner and extended attributes of the file are kept.
+    ///
+    /// Returns false if that's not an option, e.g. because the directory isn't writable
+    /// or the file belongs to someone else,
+    /// and the file must be overwritten in place instead. Nothing was written then.
+    fn write_via_temp_file(tb: &mut TextBuffer, path: &Path) -> apperr::Result<bool> {
+        // Replace the file a symlink points to, not the symlink.
+        let path = sys::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Ok(false);
+        };
+        // Renaming over a read-only file works on Unix, but it shouldn't.
+        if sys::is_read_only(&path) || sys::has_hard_links(&path) {
+            return Ok(false);
+        }
+
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", process::id()));
+        let temp_path = dir.join(temp_name);
+        let mut options = File::options();
+        options.write(true).create_new(true);
+        // Nobody else may read the text before it has the permissions of the original file.
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let Ok(mut file) = options.open(&temp_path) else {
+            return Ok(false);
+        };
+        // A file that belongs to someone else would become ours.
+        if !sys::copy_file_attributes(&path, &file) {
+            drop(file);
+            _ = fs::remove_file(&temp_path);
+            return Ok(false);
+        }
+
+        let was_dirty = tb.is_dirty();
+        let result = tb.write_file(&mut file).and_then(|_| {
+            file.sync_all()?;
+            drop(file);
+            Ok(fs::rename(&temp_path, &path).is_ok())
+        });
+
+        if !matches!(result, Ok(true)) {
+            _ = fs::remove_file(&temp_path);
+            if was_dirty {
+                tb.mark_as_dirty();
+            }
+        }
+        result
+    }
+
     pub fn reread(&mut self, encoding: Option<&'static str>) -> apperr::Result<()> {
         let path = self.path.as_ref().unwrap().as_path();
         let mut file = DocumentManager::open_for_reading(path)?;
 
         {
             let mut tb = self.buffer.borrow_mut();
-            tb.read_file_with_path(&mut file, path, encoding)?;
+            // Reading the file turns the ANSI and hex views off, but a reload shouldn't.
+            let ansi_view = tb.is_ansi_view();
+            let hex_view = tb.is_hex_view();
+            tb.reload_file_with_path(&mut file, path, encoding)?;
+            tb.set_ansi_view(ansi_view);
+            if hex_view {
+                tb.set_hex_view(true)?;
+            }
         }
 
         if let Ok(id) = sys::file_id(None, path) {
             self.file_id = Some(id);
         }
+        self.disk_stamp = file_watch::stamp(path);
+        self.changed_on_disk = false;
 
+        // Reopening with an explicit encoding should give exactly that.
+        if encoding.is_none() {
+            self.apply_bom_policy();
+        }
         Ok(())
     }
 
+    /// Adds or removes the byte order mark of a UTF-8 file that was just read, as configured
+    /// via `bom`. The document is then modified, so that saving it applies the change.
+    pub fn apply_bom_policy(&mut self) {
+        let mut tb = self.buffer.borrow_mut();
+        match self.bom_policy {
+            BomPolicy::Keep => {}
+            BomPolicy::Remove => _ = tb.set_bom(false),
+            BomPolicy::Add => _ = tb.set_bom(true),
+        }
+    }
+
+    /// Writes the document as highlighted HTML next to it, e.g. `main.rs` to `main.rs.html`.
+    /// Returns the path of the written file.
+    pub fn export_html(&self) -> apperr::Result<PathBuf> {
+        // A remote file's HTML goes into the current directory.
+        let path = self.path.clone().unwrap_or_else(|| PathBuf::from(&self.filename));
+        let (text, mut highlighter) = self.text_and_highlighter();
+        let tab_size = self.buffer.borrow().tab_size();
+        let html = highlighter.export_html(&text, self.file_type, tab_size, &self.filename);
+
+        let mut dst = path.into_os_string();
+        dst.push(".html");
+        let dst = PathBuf::from(dst);
+        DocumentManager::open_for_writing(&dst)?.write_all(html.as_bytes())?;
+        Ok(dst)
+    }
+
+    /// Returns the document highlighted with ANSI colors, for printing it to the terminal.
+    pub fn export_ansi(&self) -> String {
+        let (text, mut highlighter) = self.text_and_highlighter();
+        highlighter.export_ansi(&text, self.file_type)
+    }
+
+    /// Returns the contents of the document.
+    pub fn contents(&self) -> Vec<u8> {
+        Self::buffer_contents(&self.buffer.borrow())
+    }
+
+    /// Returns the contents of `tb`, e.g. of a document.
+    pub fn buffer_contents(tb: &TextBuffer) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(tb.text_length());
+        loop {
+            let chunk = tb.read_forward(bytes.len());
+            if chunk.is_empty() {
+                break;
+            }
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    /// Returns the contents and a highlighter with the document's theme,
+    /// for rendering the document outside of the editor.
+    fn text_and_highlighter(&self) -> (String, SyntaxHighlighter) {
+        let tb = self.buffer.borrow();
+        let bytes = Self::buffer_contents(&tb);
+
+        let mut highlighter = SyntaxHighlighter::new();
+        if let Some(theme) = tb.theme() {
+            highlighter.set_theme(theme);
+        }
+        (String::from_utf8_lossy_owned(bytes), highlighter)
+    }
+
     fn set_path(&mut self, path: PathBuf) {
         let filename = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
         let dir = path.parent().map(ToOwned::to_owned).unwrap_or_default();
         self.filename = filename.clone();
         self.dir = Some(DisplayablePathBuf::from_path(dir));
+        self.repo = Repo::find(&path);
         self.path = Some(path.clone());
+        // Once it's saved, it's a file like any other.
+        self.scratch = false;
         
         // Detect file type and initialize syntax highlighting
         self.file_type = SyntaxHighlighter::detect_file_type(
@@ -82,18 +326,120 @@ impl Document {
             buffer.set_file_type(self.file_type);
         }
         
-        // Create syntax highlighter for all file types
-        let mut highlighter = SyntaxHighlighter::new();
-        // Try to set Studio Ghibli theme
-        highlighter.set_ghibli_theme();
-        self.syntax_highlighter = Some(highlighter);
-        
+        self.update_file_mode();
+        self.detect_stack_trace();
+    }
+
+    /// Highlights and indents the document as `file_type`, and applies its settings.
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.file_type = file_type;
+        self.buffer.borrow_mut().set_file_type(file_type);
         self.update_file_mode();
     }
 
+    /// The name Save As suggests. Scratch documents get the extension of their file type.
+    pub fn save_as_name(&self) -> String {
+        match self.file_type.extension() {
+            Some(ext) if self.scratch && Path::new(&self.filename).extension().is_none() => {
+                format!("{}.{ext}", self.filename)
+            }
+            _ => self.filename.clone(),
+        }
+    }
+
+    /// Makes this the document of a file on another machine, see [`crate::remote`].
+    pub fn set_remote(&mut self, target: RemotePath) {
+        self.filename = target.file_name().to_string();
+        self.file_type = SyntaxHighlighter::detect_file_type(&self.filename);
+        self.buffer.borrow_mut().set_file_type(self.file_type);
+        self.remote = Some(target);
+        self.update_file_mode();
+    }
+
+    /// Recognizes stack traces, which don't have a file name of their own.
+    /// Does nothing if the file type is already known.
+    pub fn detect_stack_trace(&mut self) {
+        if self.file_type != FileType::Plain {
+            return;
+        }
+
+        let head = self.head(STACK_TRACE_SNIFF_LEN);
+        if stack_trace::is_stack_trace(&String::from_utf8_lossy(&head)) {
+            self.file_type = FileType::StackTrace;
+            self.buffer.borrow_mut().set_file_type(self.file_type);
+            self.update_file_mode();
+        }
+    }
+
+    /// Turns the large-file mode on or off. It leaves out what makes editing very large files
+    /// sluggish: syntax highlighting, smart indentation, word wrap and most of the undo history.
+    pub fn set_large_file(&mut self, large_file: bool) {
+        self.large_file = large_file;
+        self.update_file_mode();
+    }
+
+    /// The file type to highlight the document as.
+    pub fn highlighted_file_type(&self) -> FileType {
+        if self.large_file { FileType::Plain } else { self.file_type }
+    }
+
+    /// Recognizes generated and minified files, see [`is_generated()`]. They're opened
+    /// read-only, as changes to them would likely be overwritten, and never formatted.
+    /// `editor.guard_generated = false` turns this off.
+    pub fn detect_generated(&mut self) {
+        if !config::get().get_bool("editor.guard_generated").unwrap_or(true) {
+            return;
+        }
+        let head = self.head(GENERATED_SNIFF_LEN);
+        let mut tb = self.buffer.borrow_mut();
+        let tail = tb.read_backward(tb.text_length());
+        let tail = &tail[tail.len().saturating_sub(SOURCE_MAP_SNIFF_LEN)..];
+
+        self.generated = is_generated(&head, tail, self.file_type);
+        if self.generated {
+            tb.set_read_only(true);
+        }
+    }
+
+    /// Returns up to the first `len` bytes of the document.
+    fn head(&self, len: usize) -> Vec<u8> {
+        let tb = self.buffer.borrow();
+        let mut head = Vec::new();
+        while head.len() < len {
+            let chunk = tb.read_forward(head.len());
+            if chunk.is_empty() {
+                break;
+            }
+            head.extend_from_slice(chunk);
+        }
+        head
+    }
+
+    /// Applies the per-file-type settings from the config.
     fn update_file_mode(&mut self) {
+        let is_commit_message = commit_message::is_commit_message(&self.filename);
+        let name = if is_commit_message {
+            "commit"
+        } else {
+            self.file_type.config_name()
+        };
+        let settings = config::get().file_type_settings(name);
+        self.save_transforms = SaveTransforms::from_settings(&settings);
+        self.density = settings.density;
+        self.bom_policy = settings.bom;
+
         let mut tb = self.buffer.borrow_mut();
-        tb.set_ruler(if self.filename == "COMMIT_EDITMSG" { 72 } else { 0 });
+        tb.set_ruler(settings.ruler);
+        tb.set_word_wrap(settings.word_wrap && !self.large_file);
+        tb.set_word_wrap_limit(settings.wrap_column);
+        tb.set_smart_indent_enabled(!self.large_file);
+        tb.set_undo_limit(if self.large_file { LARGE_FILE_UNDO_LIMIT } else { DEFAULT_UNDO_LIMIT });
+        tb.set_commit_message_mode(is_commit_message);
+        tb.set_whitespace_lint(settings.lint_whitespace);
+        tb.set_render_whitespace(settings.render_whitespace);
+        tb.set_theme(settings.theme);
+        tb.set_subword_navigation(settings.subword_navigation);
+        tb.set_auto_pairs(settings.auto_pairs);
     }
 }
 
@@ -141,6 +487,21 @@ impl DocumentManager {
         self.list.iter().collect()
     }
 
+    pub fn all_documents_mut(&mut self) -> Vec<&mut Document> {
+        self.list.iter_mut().collect()
+    }
+
+    /// Returns the documents that were saved since the last call, for running the linters.
+    pub fn take_lint_requests(&mut self) -> Vec<(PathBuf, FileType)> {
+        self.list
+            .iter_mut()
+            .filter_map(|doc| {
+                let wants = mem::take(&mut doc.wants_lint);
+                Some((doc.path.clone().filter(|_| wants)?, doc.file_type))
+            })
+            .collect()
+    }
+
     /// Get the index of the currently active document
     pub fn active_index(&self) -> Option<usize> {
         if self.list.is_empty() {
@@ -213,8 +574,21 @@ impl DocumentManager {
             filename: Default::default(),
             file_id: None,
             new_file_counter: 0,
-            syntax_highlighter: None,
             file_type: FileType::Plain,
+            save_transforms: SaveTransforms::default(),
+            wants_lint: false,
+            help: Vec::new(),
+            density: config::get().file_type_settings(FileType::Plain.config_name()).density,
+            bom_policy: BomPolicy::Keep,
+            backed_up: false,
+            disk_stamp: None,
+            changed_on_disk: false,
+            notes: Default::default(),
+            large_file: false,
+            generated: false,
+            remote: None,
+            repo: None,
+            scratch: false,
         };
         self.gen_untitled_name(&mut doc);
 
@@ -222,6 +596,26 @@ impl DocumentManager {
         Ok(self.list.front_mut().unwrap())
     }
 
+    /// Adds a scratch document: one that isn't backed by a file, like a task's output, a diff
+    /// or notes to self. Closing it doesn't ask to save its changes. Saving it asks where to,
+    /// after which it's a regular file. Without a `name`, it's called `Scratch-N`.
+    pub fn add_scratch(
+        &mut self,
+        name: Option<&str>,
+        file_type: FileType,
+        text: &str,
+    ) -> apperr::Result<&mut Document> {
+        let doc = self.add_untitled()?;
+        doc.filename = match name {
+            Some(name) => name.to_string(),
+            None => format!("Scratch-{}", doc.new_file_counter),
+        };
+        doc.scratch = true;
+        doc.set_file_type(file_type);
+        doc.buffer.borrow_mut().set_text(text);
+        Ok(doc)
+    }
+
     pub fn gen_untitled_name(&self, doc: &mut Document) {
         let mut new_file_counter = 0;
         for doc in &self.list {
@@ -275,18 +669,47 @@ impl DocumentManager {
             filename: Default::default(),
             file_id,
             new_file_counter: 0,
-            syntax_highlighter: None,
             file_type: FileType::Plain,
+            save_transforms: SaveTransforms::default(),
+            wants_lint: false,
+            help: Vec::new(),
+            density: Density::Normal,
+            bom_policy: BomPolicy::Keep,
+            backed_up: false,
+            disk_stamp: None,
+            changed_on_disk: false,
+            notes: Default::default(),
+            large_file: false,
+            generated: false,
+            remote: None,
+            repo: None,
+            scratch: false,
         };
+        if doc.file_id.is_some() {
+            doc.large_file = doc.buffer.borrow().text_length() >= config::get().large_file_size();
+            doc.disk_stamp = file_watch::stamp(&path);
+            doc.notes = Notes::load(&path);
+            // Edits to a file that can't be saved would only get lost.
+            if sys::is_read_only(&path) {
+                doc.buffer.borrow_mut().set_read_only(true);
+            }
+        }
         doc.set_path(path);
+        if doc.file_id.is_some() {
+            doc.apply_bom_policy();
+            doc.detect_generated();
+            config_problems::show_diagnostics(&doc);
+        }
 
         if let Some(active) = self.active()
             && active.path.is_none()
             && active.file_id.is_none()
             && !active.buffer.borrow().is_dirty()
+            && active.buffer.borrow().text_length() == 0
         {
             // If the current document is a pristine Untitled document with no
-            // name and no ID, replace it with the new document.
+            // name and no ID, replace it with the new document. Read-only ones
+            // with contents, like the task output, aren't pristine.
             self.remove_active();
         }
 
@@ -309,6 +732,9 @@ impl DocumentManager {
             tb.set_insert_final_newline(!cfg!(windows)); // As mandated by POSIX.
             tb.set_margin_enabled(true);
             tb.set_line_highlight_enabled(true);
+            tb.set_scroll_indicators_enabled(
+                config::get().get_bool("ui.scroll_indicators").unwrap_or(true),
+            );
         }
         Ok(buffer)
     }
@@ -367,6 +793,25 @@ impl DocumentManager {
     }
 }
 
+/// Whether a file that starts with `head` and ends with `tail` looks generated: It says so
+/// near the top, refers to a source map like bundled code does, or is code with lines too long
+/// to be written by hand.
+fn is_generated(head: &[u8], tail: &[u8], file_type: FileType) -> bool {
+    const MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "<auto-generated"];
+
+    let top = head.split(|&b| b == b'\n').take(GENERATED_MARKER_LINES);
+    if top.map(String::from_utf8_lossy).any(|line| MARKERS.iter().any(|m| line.contains(m))) {
+        return true;
+    }
+    if String::from_utf8_lossy(tail).contains("sourceMappingURL=") {
+        return true;
+    }
+    // Prose may well have long lines.
+    let lines = head.split(|&b| b == b'\n').count();
+    !matches!(file_type, FileType::Plain | FileType::Markdown)
+        && head.len() / lines >= MINIFIED_LINE_LEN
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -397,4 +842,17 @@ mod tests {
         assert_eq!(parse("file.txt:10"), ("file.txt", Some(Point { x: 0, y: 9 })));
         assert_eq!(parse("file.txt:10:5"), ("file.txt", Some(Point { x: 4, y: 9 })));
     }
+
+    #[test]
+    fn test_is_generated() {
+        let js = FileType::JavaScript;
+        assert!(is_generated(b"// Code generated by protoc. DO NOT EDIT.\n", b"", js));
+        assert!(is_generated(b"# @generated by uv\n", b"", FileType::Plain));
+        assert!(is_generated(b"", b"f();\n//# sourceMappingURL=app.js.map\n", js));
+        assert!(!is_generated(b"function f() {}\n", b"function f() {}\n", js));
+
+        let minified = format!("!function(){{{}}}();", "a=1;".repeat(500));
+        assert!(is_generated(minified.as_bytes(), b"", js));
+        assert!(!is_generated(minified.as_bytes(), b"", FileType::Markdown));
+    }
 }
@@ -1,14 +1,22 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
-use std::num::ParseIntError;
+use std::path::Path;
+use std::{env, io};
 
 use edit::framebuffer::IndexedColor;
 use edit::helpers::*;
-use edit::icu;
+use edit::syntax::FileType;
+use edit::{apperr, icu, stack_trace};
 use edit::input::{kbmod, vk};
 use edit::tui::*;
 
+use crate::{config_problems, file_watch, notes, sudo};
+use crate::help::handle_help_input;
+use crate::jump::handle_jump_input;
+use crate::line_search::{LINE_SEARCH_HEIGHT, draw_line_search};
+use crate::pager::handle_pager_input;
+use crate::project_search::{PROJECT_SEARCH_HEIGHT, draw_project_search};
 use crate::localization::*;
 use crate::state::*;
 
@@ -17,7 +25,7 @@ pub fn draw_editor(ctx: &mut Context, state: &mut State) {
     if ctx.contains_focus() {
         // Ctrl+T: New tab
         if ctx.consume_shortcut(kbmod::CTRL | vk::T) {
-            if let Ok(_) = state.documents.add_untitled() {
+            if state.documents.add_untitled().is_ok() {
                 ctx.needs_rerender();
             }
         }
@@ -29,42 +37,168 @@ pub fn draw_editor(ctx: &mut Context, state: &mut State) {
             } else {
                 // If it's the last tab, just create a new untitled one
                 state.wants_close = true;
-                if let Ok(_) = state.documents.add_untitled() {
+                if state.documents.add_untitled().is_ok() {
                     ctx.needs_rerender();
                 }
             }
         }
     }
 
-    if !matches!(state.wants_search.kind, StateSearchKind::Hidden | StateSearchKind::Disabled) {
+    state.editor_active = false;
+    if state.wants_project_search {
+        draw_project_search(ctx, state);
+    } else if state.wants_line_search {
+        draw_line_search(ctx, state);
+    } else if !matches!(state.wants_search.kind, StateSearchKind::Hidden | StateSearchKind::Disabled) {
         draw_search(ctx, state);
     }
+    if state.wants_search.kind == StateSearchKind::Hidden {
+        state.wants_search.origin = None;
+    }
+
+    // Highlight all hits of the search term while the search bar is open.
+    if let Some(doc) = state.documents.active() {
+        let needle = match state.wants_search.kind {
+            StateSearchKind::Hidden | StateSearchKind::Disabled => "",
+            _ => &state.search_needle,
+        };
+        doc.buffer.borrow_mut().set_search_highlight(needle, state.search_options);
+    }
+    if let Some(doc) = state.documents.active_mut() {
+        notes::update(doc);
+    }
 
     let size = ctx.size();
     let height_reduction = match state.wants_search.kind {
+        // Panels float over the editor, see `attr_panel_overlay()`.
+        _ if state.breakpoint == StateBreakpoint::Small => 3,
+        _ if state.wants_project_search => 3 + PROJECT_SEARCH_HEIGHT,
+        _ if state.wants_line_search => 3 + LINE_SEARCH_HEIGHT,
         StateSearchKind::Search => 5,    // +1 for tab bar
         StateSearchKind::Replace => 6,   // +1 for tab bar
         _ => 3,                         // +1 for tab bar (2 original + 1 for tabs)
     };
 
+    if state.wants_jump {
+        handle_jump_input(ctx, state);
+    }
+
+    let guarded = state
+        .documents
+        .active()
+        .is_some_and(|doc| doc.generated && doc.buffer.borrow().is_read_only());
+    if guarded {
+        draw_generated_banner(ctx, state);
+    }
+    let height_reduction = height_reduction + guarded as CoordType;
+
     if let Some(doc) = state.documents.active_mut() {
         // Use consistent background for all file types
-        ctx.textarea_with_file_type("editor", doc.buffer.clone(), doc.file_type);
+        ctx.textarea_with_file_type("editor", doc.buffer.clone(), doc.highlighted_file_type());
+        state.editor_active |= ctx.is_focused();
+        if !state.editor_active {
+            ctx.attr_inactive();
+        }
         // Set the proper size for the editor area
         ctx.attr_intrinsic_size(Size { width: size.width, height: size.height - height_reduction });
         ctx.inherit_focus();
+        if ctx.is_focused() {
+            handle_help_input(ctx, state);
+            handle_stack_frame_input(ctx, state);
+            config_problems::handle_input(ctx, state);
+        }
+        if state.pager.enabled && ctx.is_focused() {
+            handle_pager_input(ctx, state, size.height - height_reduction - 1);
+        }
     } else {
         ctx.block_begin("empty_editor");
         ctx.block_end();
     }
 }
 
+/// Explains why a generated file is read-only and offers to edit it anyway.
+/// See [`crate::documents::Document::detect_generated()`].
+fn draw_generated_banner(ctx: &mut Context, state: &mut State) {
+    ctx.table_begin("generated");
+    ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+    ctx.attr_intrinsic_size(Size { width: COORD_TYPE_SAFE_MAX, height: 1 });
+    ctx.attr_padding(Rect::two(0, 1));
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::Yellow));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        ctx.table_next_row();
+        ctx.label("message", loc(LocId::GeneratedFileBanner));
+        ctx.attr_overflow(Overflow::TruncateTail);
+        if ctx.button("edit-anyway", loc(LocId::GeneratedFileEditAnyway), ButtonStyle::default())
+            && let Some(doc) = state.documents.active()
+        {
+            doc.buffer.borrow_mut().set_read_only(false);
+            ctx.needs_rerender();
+        }
+    }
+    ctx.table_end();
+}
+
+/// Opens the file of the stack frame at the cursor on Enter.
+/// The textarea leaves Enter alone on frames, see [`FileType::StackTrace`].
+fn handle_stack_frame_input(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    if doc.file_type != FileType::StackTrace || !ctx.consume_shortcut(vk::RETURN) {
+        return;
+    }
+
+    let (line, _) = doc.buffer.borrow().line_text_at_cursor();
+    let Some(frame) = stack_trace::parse_frame(&line) else {
+        return;
+    };
+
+    // Relative paths are usually relative to where the program ran, which is
+    // most likely where the trace got saved or where edit got started.
+    let path = Path::new(frame.path);
+    let dir = doc.dir.as_ref().map(|d| d.as_path().to_path_buf());
+    let cwd = env::current_dir().ok();
+    let result = match [dir, cwd].into_iter().flatten().map(|d| d.join(path)).find(|p| p.is_file())
+    {
+        Some(path) => state.documents.add_file_path(&path).map(|doc| {
+            let x = frame.column.unwrap_or(1) - 1;
+            let mut tb = doc.buffer.borrow_mut();
+            tb.cursor_move_to_logical(Point { x, y: frame.line - 1 });
+            tb.make_cursor_visible();
+        }),
+        None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+    };
+    if let Err(err) = result {
+        error_log_add(ctx, state, err);
+    }
+    ctx.needs_rerender();
+}
+
+/// On small terminals, panels float over the top of the editor instead of taking
+/// `height` lines away from it. Call this right after beginning the panel's block,
+/// with the result of [`State::panel_overlay_top()`].
+pub fn attr_panel_overlay(ctx: &mut Context, top: Option<CoordType>, height: CoordType) {
+    let Some(top) = top else {
+        return;
+    };
+    ctx.attr_float(FloatSpec {
+        anchor: Anchor::Root,
+        gravity_x: 0.0,
+        gravity_y: 0.0,
+        offset_x: 0.0,
+        offset_y: top as f32,
+    });
+    ctx.attr_intrinsic_size(Size { width: ctx.size().width, height });
+}
+
 fn draw_search(ctx: &mut Context, state: &mut State) {
     enum SearchAction {
         None,
         Search,
         Replace,
         ReplaceAll,
+        ReplaceInTabs,
     }
 
     if let Err(err) = icu::init() {
@@ -83,6 +217,7 @@ fn draw_search(ctx: &mut Context, state: &mut State) {
 
     if state.wants_search.focus {
         state.wants_search.focus = false;
+        state.wants_search.origin.get_or_insert_with(|| doc.buffer.borrow().cursor_logical_pos());
         focus = StateSearchKind::Search;
 
         // If the selection is empty, focus the search input field.
@@ -94,12 +229,26 @@ fn draw_search(ctx: &mut Context, state: &mut State) {
     }
 
     ctx.block_begin("search");
+    let height = if state.wants_search.kind == StateSearchKind::Replace { 3 } else { 2 };
+    attr_panel_overlay(ctx, state.panel_overlay_top(), height);
     ctx.attr_focus_well();
     ctx.attr_background_rgba(ctx.indexed(IndexedColor::White));
     ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
     {
-        if ctx.contains_focus() && ctx.consume_shortcut(vk::ESCAPE) {
+        state.editor_active = ctx.contains_focus();
+        if state.editor_active && ctx.consume_shortcut(vk::ESCAPE) {
             state.wants_search.kind = StateSearchKind::Hidden;
+
+            // Canceling the search returns to where it started.
+            if let Some(origin) = state.wants_search.origin.take() {
+                let mut tb = doc.buffer.borrow_mut();
+                tb.clear_selection();
+                tb.cursor_move_to_logical(origin);
+                tb.make_cursor_visible();
+            }
+        } else if !state.editor_active && focus == StateSearchKind::Hidden {
+            // Going back to the editor keeps the cursor where the search left it.
+            state.wants_search.origin = None;
         }
 
         ctx.table_begin("needle");
@@ -174,6 +323,18 @@ fn draw_search(ctx: &mut Context, state: &mut State) {
                 change = true;
                 change_action = SearchAction::ReplaceAll;
             }
+            if state.wants_search.kind == StateSearchKind::Replace
+                && state.documents.len() > 1
+                && ctx.button(
+                    "replace-in-tabs",
+                    loc(LocId::SearchReplaceInTabs),
+                    ButtonStyle::default(),
+                )
+            {
+                // Unlike the other buttons, this one doesn't focus the search field,
+                // because the dialog it opens needs the focus.
+                action = SearchAction::ReplaceInTabs;
+            }
             if ctx.button("close", loc(LocId::SearchClose), ButtonStyle::default()) {
                 state.wants_search.kind = StateSearchKind::Hidden;
             }
@@ -188,9 +349,25 @@ fn draw_search(ctx: &mut Context, state: &mut State) {
     }
     ctx.block_end();
 
+    if matches!(action, SearchAction::Replace | SearchAction::ReplaceAll) {
+        // After changing the document, there's no going back with Escape.
+        state.wants_search.origin = None;
+    }
+
+    if let SearchAction::ReplaceInTabs = action {
+        // The replacements are made once the user confirms them.
+        state.search_success = count_in_tabs(state).is_ok_and(|found| found);
+        ctx.needs_rerender();
+        return;
+    }
+
     state.search_success = match action {
-        SearchAction::None => return,
+        SearchAction::None | SearchAction::ReplaceInTabs => return,
         SearchAction::Search => {
+            match state.wants_search.origin {
+                Some(origin) => state.jump_list.push_at(&doc.buffer, origin),
+                None => state.jump_list.push(&doc.buffer),
+            }
             doc.buffer.borrow_mut().find_and_select(&state.search_needle, state.search_options)
         }
         SearchAction::Replace => doc.buffer.borrow_mut().find_and_replace(
@@ -198,23 +375,128 @@ fn draw_search(ctx: &mut Context, state: &mut State) {
             state.search_options,
             &state.search_replacement,
         ),
-        SearchAction::ReplaceAll => doc.buffer.borrow_mut().find_and_replace_all(
-            &state.search_needle,
-            state.search_options,
-            &state.search_replacement,
-        ),
+        SearchAction::ReplaceAll => doc
+            .buffer
+            .borrow_mut()
+            .find_and_replace_all(
+                &state.search_needle,
+                state.search_options,
+                &state.search_replacement,
+            )
+            .map(drop),
     }
     .is_ok();
 
     ctx.needs_rerender();
 }
 
+/// Counts the search hits in each open file, so that the user can confirm replacing them.
+/// Returns whether there are any.
+fn count_in_tabs(state: &mut State) -> apperr::Result<bool> {
+    let mut files = Vec::new();
+    for doc in state.documents.all_documents() {
+        let tb = doc.buffer.borrow();
+        if tb.is_read_only() {
+            continue;
+        }
+        let count = tb.find_count(&state.search_needle, state.search_options)?;
+        if count != 0 {
+            files.push((doc.filename.clone(), count));
+        }
+    }
+    state.wants_replace_in_tabs = files;
+    Ok(!state.wants_replace_in_tabs.is_empty())
+}
+
+pub fn draw_dialog_replace_in_tabs(ctx: &mut Context, state: &mut State) {
+    let mut done = false;
+    let mut replace = false;
+
+    ctx.modal_begin("replace-in-tabs", loc(LocId::SearchReplaceInTabs));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            ctx.label("description", loc(LocId::SearchReplaceInTabsDescription));
+            for (i, (name, count)) in state.wants_replace_in_tabs.iter().enumerate() {
+                ctx.next_block_id_mixin(i as u64);
+                ctx.label("file", &format!("  {name}: {count}"));
+                ctx.attr_overflow(Overflow::TruncateTail);
+            }
+
+            ctx.table_begin("choices");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+
+                if ctx.button("replace", loc(LocId::SearchReplaceAll), ButtonStyle::default()) {
+                    done = true;
+                    replace = true;
+                }
+                ctx.inherit_focus();
+                if ctx.button("cancel", loc(LocId::Cancel), ButtonStyle::default()) {
+                    done = true;
+                }
+            }
+            ctx.table_end();
+        }
+        ctx.block_end();
+    }
+    if ctx.modal_end() {
+        done = true;
+    }
+
+    if !done {
+        return;
+    }
+
+    state.wants_replace_in_tabs.clear();
+    ctx.needs_rerender();
+
+    if !replace {
+        return;
+    }
+
+    // Each file gets its own undo step.
+    let mut result = Ok(0);
+    for doc in state.documents.all_documents() {
+        result = doc.buffer.borrow_mut().find_and_replace_all(
+            &state.search_needle,
+            state.search_options,
+            &state.search_replacement,
+        );
+        if result.is_err() {
+            break;
+        }
+    }
+    state.wants_search.origin = None;
+    if let Err(err) = result {
+        error_log_add(ctx, state, err);
+    }
+}
+
 pub fn draw_handle_save(ctx: &mut Context, state: &mut State) {
     if let Some(doc) = state.documents.active_mut() {
-        if doc.path.is_some() {
-            if let Err(err) = doc.save(None) {
+        if doc.remote.is_some() {
+            if let Err(err) = state.remote.save(doc) {
                 error_log_add(ctx, state, err);
             }
+        } else if doc.path.is_some() {
+            // Don't overwrite changes made elsewhere without asking.
+            if file_watch::check(doc) {
+                file_watch::interrupt_save(state);
+            } else if let Err(err) = doc.save(None, &state.trust) {
+                if sudo::can_retry(err) {
+                    state.wants_sudo_save = true;
+                } else {
+                    error_log_add(ctx, state, err);
+                }
+            }
         } else {
             // No path? Show the file picker.
             state.wants_file_picker = StateFilePicker::SaveAs;
@@ -232,7 +514,7 @@ pub fn draw_handle_wants_close(ctx: &mut Context, state: &mut State) {
         return;
     };
 
-    if !doc.buffer.borrow().is_dirty() {
+    if !doc.buffer.borrow().is_dirty() || doc.scratch || state.remote.is_saving(doc) {
         state.documents.remove_active();
         state.wants_close = false;
         ctx.needs_rerender();
@@ -328,14 +610,20 @@ pub fn draw_goto_menu(ctx: &mut Context, state: &mut State) {
             ctx.steal_focus();
 
             if ctx.consume_shortcut(vk::RETURN) {
-                match validate_goto_point(&state.goto_target) {
-                    Ok(point) => {
+                let point = {
+                    let tb = doc.buffer.borrow();
+                    let current = tb.cursor_logical_pos().y;
+                    parse_goto(&state.goto_target, current, tb.logical_line_count())
+                };
+                match point {
+                    Some(point) => {
+                        state.jump_list.push(&doc.buffer);
                         let mut buf = doc.buffer.borrow_mut();
                         buf.cursor_move_to_logical(point);
                         buf.make_cursor_visible();
                         done = true;
                     }
-                    Err(_) => state.goto_invalid = true,
+                    None => state.goto_invalid = true,
                 }
                 ctx.needs_rerender();
             }
@@ -353,13 +641,59 @@ pub fn draw_goto_menu(ctx: &mut Context, state: &mut State) {
     }
 }
 
-fn validate_goto_point(line: &str) -> Result<Point, ParseIntError> {
-    let mut coords = [0; 2];
-    let (y, x) = line.split_once(':').unwrap_or((line, "0"));
-    // Using a loop here avoids 2 copies of the str->int code.
-    // This makes the binary more compact.
-    for (i, s) in [x, y].iter().enumerate() {
-        coords[i] = s.parse::<CoordType>()?.saturating_sub(1);
+/// Parses `42` or `42:7` as a line and column, `+10` and `-10` as lines relative to the
+/// `current` one, and `50%` or `+10%` as a percentage of the `line_count` lines.
+fn parse_goto(text: &str, current: CoordType, line_count: CoordType) -> Option<Point> {
+    let text = text.trim();
+    let (line, column) = text.split_once(':').unwrap_or((text, "1"));
+    let x = column.parse::<CoordType>().ok()?.max(1) - 1;
+
+    let (sign, line) = match line.as_bytes().first() {
+        Some(b'+') => (1, &line[1..]),
+        Some(b'-') => (-1, &line[1..]),
+        _ => (0, line),
+    };
+    let (line, percent) = match line.strip_suffix('%') {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    let n = line.parse::<CoordType>().ok()?;
+    let n = if percent { n.saturating_mul(line_count) / 100 } else { n };
+
+    let y = match (sign, percent) {
+        (0, false) => n.saturating_sub(1),
+        (0, true) => n,
+        _ => current.saturating_add(n.saturating_mul(sign)),
+    };
+    Some(Point { x, y: y.clamp(0, (line_count - 1).max(0)) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_goto() {
+        let goto = |text| parse_goto(text, 20, 100).map(|p| (p.x, p.y));
+
+        assert_eq!(goto("42"), Some((0, 41)));
+        assert_eq!(goto(" 42:7 "), Some((6, 41)));
+        assert_eq!(goto("+10"), Some((0, 30)));
+        assert_eq!(goto("-10"), Some((0, 10)));
+        assert_eq!(goto("50%"), Some((0, 50)));
+        assert_eq!(goto("+10%"), Some((0, 30)));
+        assert_eq!(goto(""), None);
+        assert_eq!(goto("abc"), None);
+        assert_eq!(goto("42:x"), None);
+        assert_eq!(goto("%"), None);
+
+        // Out of range positions are clamped to the document.
+        assert_eq!(goto("0"), Some((0, 0)));
+        assert_eq!(goto("500"), Some((0, 99)));
+        assert_eq!(goto("-50"), Some((0, 0)));
+        assert_eq!(goto("200%"), Some((0, 99)));
+        assert_eq!(goto("--9223372036854775808"), Some((0, 99)));
+        assert_eq!(goto("+-9223372036854775808"), Some((0, 0)));
+        assert_eq!(parse_goto("7", 0, 0).map(|p| p.y), Some(0));
     }
-    Ok(Point { x: coords[0], y: coords[1] })
 }
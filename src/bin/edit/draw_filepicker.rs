@@ -20,8 +20,11 @@ pub fn draw_file_picker(ctx: &mut Context, state: &mut State) {
         state.wants_file_picker = StateFilePicker::SaveAsShown;
 
         if state.file_picker_pending_name.as_os_str().is_empty() {
-            state.file_picker_pending_name =
-                state.documents.active().map_or("Untitled.txt", |doc| doc.filename.as_str()).into();
+            state.file_picker_pending_name = state
+                .documents
+                .active()
+                .map_or_else(|| "Untitled.txt".to_string(), |doc| doc.save_as_name())
+                .into();
         }
     }
 
@@ -173,7 +176,7 @@ pub fn draw_file_picker(ctx: &mut Context, state: &mut State) {
         let res = if state.wants_file_picker == StateFilePicker::Open {
             state.documents.add_file_path(&path).map(|_| ())
         } else if let Some(doc) = state.documents.active_mut() {
-            doc.save(Some(path))
+            doc.save(Some(path), &state.trust)
         } else {
             Ok(())
         };
@@ -2,10 +2,14 @@
 // Licensed under the MIT License.
 
 use edit::arena_format;
+use edit::config::RenderWhitespace;
 use edit::helpers::*;
 use edit::input::{kbmod, vk};
+use edit::syntax::FileType;
 use edit::tui::*;
 
+use crate::{clipboard_locations, help, notes, save_transforms};
+use crate::keymap;
 use crate::localization::*;
 use crate::state::*;
 
@@ -39,9 +43,15 @@ fn draw_menu_file(ctx: &mut Context, state: &mut State) {
     if ctx.menubar_menu_button(loc(LocId::FileNew), 'N', kbmod::CTRL | vk::N) {
         draw_add_untitled_document(ctx, state);
     }
+    if ctx.menubar_menu_button(loc(LocId::FileNewScratch), 'W', vk::NULL) {
+        draw_add_scratch_document(ctx, state, FileType::Plain);
+    }
     if ctx.menubar_menu_button(loc(LocId::FileOpen), 'O', kbmod::CTRL | vk::O) {
         state.wants_file_picker = StateFilePicker::Open;
     }
+    if ctx.menubar_menu_button(loc(LocId::FileOpenFromClipboard), 'L', vk::NULL) {
+        clipboard_locations::open_from_clipboard(ctx, state);
+    }
     if state.documents.active().is_some() {
         if ctx.menubar_menu_button(loc(LocId::FileSave), 'S', kbmod::CTRL | vk::S) {
             state.wants_save = true;
@@ -49,10 +59,33 @@ fn draw_menu_file(ctx: &mut Context, state: &mut State) {
         if ctx.menubar_menu_button(loc(LocId::FileSaveAs), 'A', vk::NULL) {
             state.wants_file_picker = StateFilePicker::SaveAs;
         }
+        if state.documents.active().is_some_and(|doc| !doc.save_transforms.is_empty())
+            && ctx.menubar_menu_button(loc(LocId::FilePreviewSave), 'V', vk::NULL)
+            && let Err(err) = save_transforms::preview(state)
+        {
+            error_log_add(ctx, state, err);
+        }
+        if let Some(doc) = state.documents.active()
+            && doc.path.is_some()
+            && ctx.menubar_menu_button(loc(LocId::FileExportHtml), 'E', vk::NULL)
+            && let Err(err) = doc.export_html()
+        {
+            error_log_add(ctx, state, err);
+        }
         if ctx.menubar_menu_button(loc(LocId::FileClose), 'C', kbmod::CTRL | vk::W) {
             state.wants_close = true;
         }
     }
+    if let Some(command) = state.tasks.last()
+        && ctx.menubar_menu_button(loc(LocId::FileRunLastTask), 'R', kbmod::CTRL_SHIFT | vk::B)
+    {
+        let command = command.to_string();
+        state.tasks.start(&command);
+        ctx.needs_rerender();
+    }
+    if ctx.menubar_menu_button(loc(LocId::FileTaskHistory), 'T', vk::NULL) {
+        state.wants_task_history = true;
+    }
     if ctx.menubar_menu_button(loc(LocId::FileExit), 'X', kbmod::CTRL | vk::Q) {
         state.wants_exit = true;
     }
@@ -81,6 +114,9 @@ fn draw_menu_edit(ctx: &mut Context, state: &mut State) {
         tb.write(ctx.clipboard(), true);
         ctx.needs_rerender();
     }
+    if ctx.menubar_menu_button(loc(LocId::EditClipboardHistory), 'H', vk::NULL) {
+        state.clipboard_history.show_list();
+    }
     if state.wants_search.kind != StateSearchKind::Disabled {
         if ctx.menubar_menu_button(loc(LocId::EditFind), 'F', kbmod::CTRL | vk::F) {
             state.wants_search.kind = StateSearchKind::Search;
@@ -91,11 +127,93 @@ fn draw_menu_edit(ctx: &mut Context, state: &mut State) {
             state.wants_search.focus = true;
         }
     }
+    if ctx.menubar_menu_button(loc(LocId::EditFindLines), 'N', kbmod::ALT | vk::L) {
+        state.wants_line_search = true;
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditFindInFiles), 'K', kbmod::CTRL_SHIFT | vk::F) {
+        state.wants_project_search = true;
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditJump), 'J', kbmod::ALT | vk::J) {
+        state.wants_jump = true;
+    }
+    if tb.current_file_type().has_tags()
+        && ctx.menubar_menu_button(loc(LocId::EditMatchingTag), 'M', kbmod::ALT | vk::T)
+    {
+        let pos = tb.cursor_logical_pos();
+        if tb.jump_to_matching_tag() {
+            state.jump_list.push_at(&doc.buffer, pos);
+            tb.make_cursor_visible();
+            ctx.needs_rerender();
+        }
+    }
     if ctx.menubar_menu_button(loc(LocId::EditSelectAll), 'A', kbmod::CTRL | vk::A) {
         tb.select_all();
         ctx.needs_rerender();
     }
+    if ctx.menubar_menu_button(loc(LocId::EditReindent), 'I', kbmod::CTRL_SHIFT | vk::I) {
+        tb.reindent();
+        ctx.needs_rerender();
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditDuplicate), 'Q', kbmod::CTRL_SHIFT | vk::D) {
+        tb.duplicate();
+        ctx.needs_rerender();
+    }
+    let comments = tb.current_file_type().comment_style();
+    if comments.line.is_some() || comments.block.is_some() {
+        if ctx.menubar_menu_button(loc(LocId::EditToggleLineComment), 'G', kbmod::CTRL | vk::OEM_2)
+        {
+            tb.toggle_line_comment();
+            ctx.needs_rerender();
+        }
+        if ctx.menubar_menu_button(
+            loc(LocId::EditToggleBlockComment),
+            'B',
+            kbmod::ALT_SHIFT | vk::A,
+        ) {
+            tb.toggle_block_comment();
+            ctx.needs_rerender();
+        }
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditFixWhitespace), 'W', vk::NULL) {
+        tb.fix_whitespace();
+        ctx.needs_rerender();
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditSurround), 'X', vk::NULL) {
+        state.command_line = "surround ".to_string();
+        state.wants_command_line = true;
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditConvertCase), 'V', vk::NULL) {
+        state.command_line = "case ".to_string();
+        state.wants_command_line = true;
+    }
+    let record = if state.macros.is_recording() {
+        LocId::EditStopRecording
+    } else {
+        LocId::EditRecordMacro
+    };
+    if ctx.menubar_menu_button(loc(record), 'E', kbmod::ALT | vk::Q) {
+        state.macros.toggle_recording();
+        ctx.needs_rerender();
+    }
+    if state.macros.has_last()
+        && !state.macros.is_recording()
+        && ctx.menubar_menu_button(loc(LocId::EditPlayMacro), 'Y', kbmod::ALT | vk::P)
+    {
+        state.macros.play_last();
+    }
+    if ctx.menubar_menu_button(loc(LocId::EditMacros), 'O', vk::NULL) {
+        state.macros.show_list();
+    }
+    let add_note = ctx.menubar_menu_button(loc(LocId::EditNote), 'D', kbmod::ALT | vk::N);
+    if ctx.menubar_menu_button(loc(LocId::EditNotes), 'S', vk::NULL) {
+        state.wants_note_list = true;
+    }
     ctx.menubar_menu_end();
+
+    drop(tb);
+    if add_note {
+        notes::open_dialog(state);
+    }
 }
 
 fn draw_menu_view(ctx: &mut Context, state: &mut State) {
@@ -113,16 +231,60 @@ fn draw_menu_view(ctx: &mut Context, state: &mut State) {
         if ctx.menubar_menu_button(loc(LocId::FileGoto), 'G', kbmod::CTRL | vk::G) {
             state.wants_goto = true;
         }
+        if ctx.menubar_menu_button(loc(LocId::ViewCommandLine), 'C', kbmod::CTRL_SHIFT | vk::P) {
+            state.wants_command_line = true;
+        }
         if ctx.menubar_menu_checkbox(loc(LocId::ViewWordWrap), 'W', kbmod::ALT | vk::Z, word_wrap) {
             tb.set_word_wrap(!word_wrap);
             ctx.needs_rerender();
         }
+        let whitespace = tb.render_whitespace() != RenderWhitespace::None;
+        if ctx.menubar_menu_checkbox(loc(LocId::ViewRenderWhitespace), 'E', vk::NULL, whitespace) {
+            // Turning it on shows all of it, even if the config picked `boundary`.
+            let mode = if whitespace { RenderWhitespace::None } else { RenderWhitespace::All };
+            tb.set_render_whitespace(mode);
+            ctx.needs_rerender();
+        }
+        let ansi_view = tb.is_ansi_view();
+        if ctx.menubar_menu_checkbox(loc(LocId::ViewAnsiColors), 'N', vk::NULL, ansi_view) {
+            tb.set_ansi_view(!ansi_view);
+            ctx.needs_rerender();
+        }
+        let read_only = tb.is_read_only();
+        if ctx.menubar_menu_checkbox(loc(LocId::ViewReadOnly), 'R', vk::NULL, read_only) {
+            tb.set_read_only(!read_only);
+            ctx.needs_rerender();
+        }
+        let hex_view = tb.is_hex_view();
+        if ctx.menubar_menu_checkbox(loc(LocId::ViewHexView), 'H', vk::NULL, hex_view) {
+            if let Err(err) = tb.set_hex_view(!hex_view) {
+                drop(tb);
+                error_log_add(ctx, state, err);
+            }
+            ctx.needs_rerender();
+        }
+    }
+    if state.documents.active().is_some_and(|doc| doc.path.is_some())
+        && ctx.menubar_menu_button(loc(LocId::FileAlternate), 'A', kbmod::ALT | vk::O)
+    {
+        draw_switch_to_alternate_file(ctx, state);
+    }
+    if ctx.menubar_menu_button(loc(LocId::ViewWorkspaceTrust), 'T', vk::NULL) {
+        state.wants_workspace_trust = true;
     }
 
     ctx.menubar_menu_end();
 }
 
 fn draw_menu_help(ctx: &mut Context, state: &mut State) {
+    if ctx.menubar_menu_button(loc(LocId::HelpContents), 'C', vk::NULL)
+        && let Err(err) = help::open_topic(state, "")
+    {
+        error_log_add(ctx, state, err);
+    }
+    if ctx.menubar_menu_button(loc(LocId::HelpKeyboardShortcuts), 'K', keymap::CHEAT_SHEET_KEY) {
+        state.wants_cheat_sheet = true;
+    }
     if ctx.menubar_menu_button(loc(LocId::HelpAbout), 'A', vk::NULL) {
         state.wants_about = true;
     }
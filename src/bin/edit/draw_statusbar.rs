@@ -6,6 +6,7 @@ use edit::helpers::*;
 use edit::input::vk;
 use edit::tui::*;
 use edit::{arena_format, icu};
+use edit::config::{self, Density};
 use edit::syntax::FileType;
 
 use crate::localization::*;
@@ -15,32 +16,69 @@ pub fn draw_statusbar(ctx: &mut Context, state: &mut State) {
     ctx.table_begin("statusbar");
     ctx.attr_focus_well();
     ctx.attr_background_rgba(state.menubar_color_bg);
-    ctx.attr_foreground_rgba(state.menubar_color_fg);
-    ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+    ctx.attr_foreground_rgba(if state.editor_active {
+        state.menubar_color_fg
+    } else {
+        state.statusbar_color_fg_inactive
+    });
+    let (gap, padding) = match state.density {
+        Density::Compact => (1, 0),
+        Density::Normal => (2, 1),
+        Density::Comfortable => (3, 2),
+    };
+    ctx.table_set_cell_gap(Size { width: gap, height: 0 });
     ctx.attr_intrinsic_size(Size { width: COORD_TYPE_SAFE_MAX, height: 1 });
-    ctx.attr_padding(Rect::two(0, 1));
+    ctx.attr_padding(Rect::two(0, padding));
 
+    // Compact status bars leave out the newline and encoding buttons while they show
+    // the defaults, unless the status bar has the focus (see `ViewFocusStatusbar`).
+    let expanded =
+        state.density != Density::Compact || state.wants_statusbar_focus || ctx.contains_focus();
+
+    let mut leave_large_file = false;
     if let Some(doc) = state.documents.active() {
         // Get values before borrowing the buffer mutably
         let file_type = doc.file_type;
         let filename = doc.filename.clone();
         let has_path = doc.path.is_some();
+        let large_file = doc.large_file;
+        let repo = doc.repo.as_ref().and_then(|repo| {
+            let branch = repo.branch()?;
+            // The files may come from several repositories, like a submodule and its parent.
+            let ambiguous = state
+                .documents
+                .all_documents()
+                .iter()
+                .filter_map(|doc| doc.repo.as_ref())
+                .any(|other| other.work_tree != repo.work_tree);
+            Some(if ambiguous { format!("{} ({branch})", repo.name()) } else { branch })
+        });
         
         let mut tb = doc.buffer.borrow_mut();
 
         ctx.table_next_row();
 
-        if ctx.button("newline", if tb.is_crlf() { "CRLF" } else { "LF" }, ButtonStyle::default()) {
-            let is_crlf = tb.is_crlf();
-            tb.normalize_newlines(!is_crlf);
+        if state.macros.is_recording() {
+            ctx.label("recording", loc(LocId::MacroRecording));
+            ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightRed));
         }
-        if state.wants_statusbar_focus {
-            state.wants_statusbar_focus = false;
-            ctx.steal_focus();
+
+        if expanded || tb.is_crlf() {
+            let newline = if tb.is_crlf() { "CRLF" } else { "LF" };
+            if ctx.button("newline", newline, ButtonStyle::default()) {
+                let is_crlf = tb.is_crlf();
+                tb.normalize_newlines(!is_crlf);
+            }
+            if state.wants_statusbar_focus {
+                state.wants_statusbar_focus = false;
+                ctx.steal_focus();
+            }
         }
 
-        state.wants_encoding_picker |=
-            ctx.button("encoding", tb.encoding(), ButtonStyle::default());
+        if expanded || state.wants_encoding_picker || tb.encoding() != "UTF-8" {
+            state.wants_encoding_picker |=
+                ctx.button("encoding", tb.encoding(), ButtonStyle::default());
+        }
         if state.wants_encoding_picker {
             if has_path {
                 ctx.block_begin("frame");
@@ -61,6 +99,19 @@ pub fn draw_statusbar(ctx: &mut Context, state: &mut State) {
                     if ctx.button("convert", loc(LocId::EncodingConvert), ButtonStyle::default()) {
                         state.wants_encoding_change = StateEncodingChange::Convert;
                     }
+                    if tb.encoding().starts_with("UTF-8") {
+                        let has_bom = tb.has_bom();
+                        let label = if has_bom {
+                            LocId::EncodingRemoveBom
+                        } else {
+                            LocId::EncodingAddBom
+                        };
+                        if ctx.button("bom", loc(label), ButtonStyle::default()) {
+                            tb.set_bom(!has_bom);
+                            state.wants_encoding_picker = false;
+                            ctx.needs_rerender();
+                        }
+                    }
                 }
                 ctx.block_end();
             } else {
@@ -151,16 +202,60 @@ pub fn draw_statusbar(ctx: &mut Context, state: &mut State) {
             }
         }
 
+        let location = tb.cursor_logical_pos();
         ctx.label(
             "location",
-            &arena_format!(
-                ctx.arena(),
-                "{}:{}",
-                tb.cursor_logical_pos().y + 1,
-                tb.cursor_logical_pos().x + 1
-            ),
+            &if state.density == Density::Comfortable {
+                // Comfortable status bars have room for the line count.
+                arena_format!(
+                    ctx.arena(),
+                    "{}:{} / {}",
+                    location.y + 1,
+                    location.x + 1,
+                    tb.logical_line_count()
+                )
+            } else {
+                arena_format!(ctx.arena(), "{}:{}", location.y + 1, location.x + 1)
+            },
         );
 
+        if let Some(repo) = &repo {
+            ctx.label("repo", repo);
+        }
+
+        if let Some((source, diagnostic)) =
+            tb.diagnostics().on_line(tb.cursor_logical_pos().y).first()
+        {
+            const MAX_CHARS: usize = 60;
+            let message = diagnostic.message.lines().next().unwrap_or_default();
+            let text = if message.chars().count() > MAX_CHARS {
+                let cut: String = message.chars().take(MAX_CHARS - 1).collect();
+                arena_format!(ctx.arena(), "{source}: {cut}…")
+            } else {
+                arena_format!(ctx.arena(), "{source}: {message}")
+            };
+            ctx.label("diagnostic", &text);
+        }
+
+        if state.wants_jump {
+            ctx.label(
+                "jump",
+                &arena_format!(ctx.arena(), "{} {}", loc(LocId::JumpStatusLabel), state.jump.needle),
+            );
+        }
+
+        if state.pager.follow {
+            ctx.label("follow", loc(LocId::PagerFollowing));
+        }
+
+        if state.tasks.is_running() {
+            ctx.label("task", loc(LocId::TaskRunning));
+        }
+
+        if config::is_safe_mode() {
+            ctx.label("safe-mode", loc(LocId::SafeMode));
+        }
+
         #[cfg(feature = "debug-latency")]
         ctx.label(
             "stats",
@@ -172,6 +267,16 @@ pub fn draw_statusbar(ctx: &mut Context, state: &mut State) {
             ctx.needs_rerender();
         }
 
+        if tb.is_read_only() {
+            ctx.label("read-only", loc(LocId::ViewReadOnly));
+        }
+
+        // Clicking it brings back the features the large-file mode leaves out.
+        if large_file && ctx.button("large-file", loc(LocId::ViewLargeFile), ButtonStyle::default())
+        {
+            leave_large_file = true;
+        }
+
         if tb.is_dirty() {
             ctx.label("dirty", "*");
         }
@@ -184,7 +289,14 @@ pub fn draw_statusbar(ctx: &mut Context, state: &mut State) {
             let mut display_filename = filename.as_str();
             let filename_buf;
 
-            if total > 1 {
+            if state.density == Density::Compact {
+                // No room for the icon.
+                if total > 1 {
+                    filename_buf =
+                        arena_format!(ctx.arena(), "{} + {}", display_filename, total - 1);
+                    display_filename = &filename_buf;
+                }
+            } else if total > 1 {
                 filename_buf = arena_format!(ctx.arena(), "{} {} + {}", file_icon, display_filename, total - 1);
                 display_filename = &filename_buf;
             } else {
@@ -202,6 +314,11 @@ pub fn draw_statusbar(ctx: &mut Context, state: &mut State) {
     }
 
     ctx.table_end();
+
+    if leave_large_file && let Some(doc) = state.documents.active_mut() {
+        doc.set_large_file(false);
+        ctx.needs_rerender();
+    }
 }
 
 pub fn draw_dialog_encoding_change(ctx: &mut Context, state: &mut State) {
@@ -244,7 +361,7 @@ pub fn draw_dialog_encoding_change(ctx: &mut Context, state: &mut State) {
         if reopen && doc.path.is_some() {
             let mut res = Ok(());
             if doc.buffer.borrow().is_dirty() {
-                res = doc.save(None);
+                res = doc.save(None, &state.trust);
             }
             if res.is_ok() {
                 res = doc.reread(Some(encoding));
@@ -313,6 +430,9 @@ fn get_file_type_icon(file_type: FileType) -> &'static str {
         FileType::HTML => "🌐",
         FileType::CSS => "🎨",
         FileType::YAML => "⚙️",
+        FileType::Diff => "🩹",
+        FileType::Markdown => "📝",
+        FileType::Shell => "🐚",
         _ => "📄",
     }
 }
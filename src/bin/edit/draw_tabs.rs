@@ -1,12 +1,16 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
+use edit::config::Density;
 use edit::framebuffer::IndexedColor;
+use edit::helpers::Rect;
 use edit::input::{kbmod, vk};
 use edit::tui::*;
 use edit::syntax::FileType;
 
 use crate::documents::Document;
+use crate::keymap;
+use crate::localization::*;
 use crate::state::*;
 
 /// Studio Ghibli themed tab bar with magical touches
@@ -66,32 +70,28 @@ pub fn draw_ghibli_tab_bar(ctx: &mut Context, state: &mut State) {
     // Warm background like tree bark - using available colors
     ctx.attr_background_rgba(ctx.indexed(IndexedColor::Black));
     ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Yellow)); // Warm text
-    
+    // Small terminals only have room for the active tab and a counter.
+    if state.breakpoint == StateBreakpoint::Small {
+        if let Some(doc) = documents.get(active_index) {
+            let tab_display =
+                format!("[{}] {}/{}", get_tab_text(doc), active_index + 1, documents.len());
+            ctx.label("tabs_display", &tab_display);
+            ctx.attr_overflow(Overflow::TruncateMiddle);
+        }
+        ctx.block_end();
+        return;
+    }
+
+    if state.density == Density::Comfortable {
+        ctx.attr_padding(Rect { left: 0, top: 0, right: 0, bottom: 1 });
+    }
+
     // Create a single label with all tab information
     let mut tab_display = String::new();
     for (index, doc) in documents.iter().enumerate() {
         let is_active = index == active_index;
-        let is_dirty = doc.buffer.borrow().is_dirty();
-        
-        // Magical tab content with emoji based on file type
-        let file_icon = match doc.file_type {
-            FileType::Rust => "🦀",
-            FileType::JavaScript => "⚡",
-            FileType::TypeScript => "💙", 
-            FileType::Python => "🐍",
-            FileType::HTML => "🌐",
-            FileType::CSS => "🎨",
-            FileType::YAML => "⚙️",
-            _ => "📄",
-        };
-        
-        let display_name = get_display_name(doc);
-        let tab_text = if is_dirty {
-            format!("{} ● {}", file_icon, display_name)
-        } else {
-            format!("{} {}", file_icon, display_name)
-        };
-        
+        let tab_text = get_tab_text(doc);
+
         // Mark active tab
         if is_active {
             tab_display.push_str(&format!("[{}]", tab_text));
@@ -108,13 +108,56 @@ pub fn draw_ghibli_tab_bar(ctx: &mut Context, state: &mut State) {
     // Display the tabs as a single label
     ctx.label("tabs_display", &tab_display);
     
-    // Add navigation hint with the correct shortcuts
-    ctx.label("tab_hint", " [Alt+← →: Navigate | F1-F9: Jump | Ctrl+PgUp/PgDn: Switch]");
+    // Add navigation hint, generated from the keymap so that it stays in sync.
+    // Compact tab bars skip it to save a line.
+    if state.density == Density::Compact {
+        ctx.block_end();
+        return;
+    }
+    let mut hint = String::from(" [");
+    let tab_bindings = keymap::EDITOR.iter().filter(|b| b.category == LocId::KeymapTabs);
+    for (i, binding) in tab_bindings.enumerate() {
+        if i > 0 {
+            hint.push_str(" | ");
+        }
+        hint.push_str(&keymap::keys_text(ctx, binding));
+        hint.push_str(": ");
+        hint.push_str(loc(binding.description));
+    }
+    hint.push(']');
+    ctx.label("tab_hint", &hint);
     ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightBlack)); // Dimmed text
     
     ctx.block_end();
 }
 
+/// The file type icon, a dirty marker, a lock for read-only documents and the name of `doc`.
+fn get_tab_text(doc: &Document) -> String {
+    // Magical tab content with emoji based on file type
+    let file_icon = match doc.file_type {
+        FileType::Rust => "🦀",
+        FileType::JavaScript => "⚡",
+        FileType::TypeScript => "💙",
+        FileType::Python => "🐍",
+        FileType::HTML => "🌐",
+        FileType::CSS => "🎨",
+        FileType::YAML => "⚙️",
+        FileType::Diff => "🩹",
+        FileType::Markdown => "📝",
+        FileType::Shell => "🐚",
+        _ => "📄",
+    };
+
+    let display_name = get_display_name(doc);
+    let tb = doc.buffer.borrow();
+    let lock = if tb.is_read_only() { "🔒 " } else { "" };
+    if tb.is_dirty() {
+        format!("{} ● {}{}", file_icon, lock, display_name)
+    } else {
+        format!("{} {}{}", file_icon, lock, display_name)
+    }
+}
+
 fn get_display_name(doc: &Document) -> String {
     if doc.filename.is_empty() {
         "Untitled".to_string()
@@ -0,0 +1,198 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Notices when open files change on disk, e.g. after a `git checkout` or because another
+//! program saved them. The modification time and size of each file are checked once a
+//! second, which works the same on every platform and is cheap for the few files that are open.
+//!
+//! Once a changed file's document is active, a dialog offers to reload it, keep the document
+//! as it is, or show a diff of the two. Saving over a changed file asks first, too.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use edit::buffer::TextBuffer;
+use edit::diff;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::syntax::FileType;
+use edit::tui::*;
+
+use crate::documents::{Document, DocumentManager};
+use crate::localization::*;
+use crate::state::*;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What's compared to tell whether a file changed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DiskStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Returns the stamp of the file at `path`, or `None` if it doesn't exist.
+pub fn stamp(path: &Path) -> Option<DiskStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(DiskStamp { modified: metadata.modified().ok(), len: metadata.len() })
+}
+
+#[derive(Default)]
+pub struct FileWatch {
+    last_check: Option<Instant>,
+    /// Set if saving was interrupted by the dialog, to save once the user chose to keep
+    /// the document.
+    save_on_keep: bool,
+}
+
+impl FileWatch {
+    pub fn read_timeout(&self, documents: &DocumentManager) -> Duration {
+        if documents.all_documents().iter().any(|doc| doc.disk_stamp.is_some()) {
+            POLL_INTERVAL
+        } else {
+            Duration::MAX
+        }
+    }
+}
+
+/// Checks the open files for changes, at most once per [`POLL_INTERVAL`].
+pub fn update(state: &mut State) {
+    // The pager follows changes on its own, see `pager::update()`.
+    if state.pager.enabled {
+        return;
+    }
+    let now = Instant::now();
+    if state.file_watch.last_check.is_some_and(|last| now - last < POLL_INTERVAL) {
+        return;
+    }
+    state.file_watch.last_check = Some(now);
+
+    for doc in state.documents.all_documents_mut() {
+        check(doc);
+    }
+}
+
+/// Flags `doc` if its file changed since it was last read or saved, and returns whether
+/// it's flagged. Files that were deleted aren't flagged, as saving will simply recreate them.
+pub fn check(doc: &mut Document) -> bool {
+    if let Some(path) = &doc.path
+        && let Some(old) = doc.disk_stamp
+        && let Some(new) = stamp(path)
+        && new != old
+    {
+        doc.disk_stamp = Some(new);
+        doc.changed_on_disk = true;
+    }
+    doc.changed_on_disk
+}
+
+/// Called instead of saving if [`check()`] found that the file changed.
+pub fn interrupt_save(state: &mut State) {
+    state.file_watch.save_on_keep = true;
+}
+
+pub fn draw_dialog_file_changed(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active_mut() else {
+        return;
+    };
+
+    enum Action {
+        None,
+        Reload,
+        Keep,
+        Diff,
+    }
+    let mut action = Action::None;
+
+    ctx.modal_begin("file-changed", loc(LocId::FileChangedDialogTitle));
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::Yellow));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            ctx.label("filename", &doc.filename);
+            ctx.label("description", loc(LocId::FileChangedDialogDescription));
+            if doc.buffer.borrow().is_dirty() {
+                ctx.label("unsaved", loc(LocId::FileChangedDialogUnsaved));
+            }
+
+            ctx.table_begin("choices");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+
+                if ctx.button("reload", loc(LocId::FileChangedDialogReload), ButtonStyle::default())
+                {
+                    action = Action::Reload;
+                }
+                if ctx.button("keep", loc(LocId::FileChangedDialogKeep), ButtonStyle::default()) {
+                    action = Action::Keep;
+                }
+                // Keeping the document is the choice that loses nothing.
+                ctx.inherit_focus();
+                if ctx.button("diff", loc(LocId::FileChangedDialogDiff), ButtonStyle::default()) {
+                    action = Action::Diff;
+                }
+            }
+            ctx.table_end();
+        }
+        ctx.block_end();
+    }
+    if ctx.modal_end() {
+        action = Action::Keep;
+    }
+
+    match action {
+        Action::None => return,
+        Action::Reload => {
+            doc.changed_on_disk = false;
+            state.file_watch.save_on_keep = false;
+            if let Err(err) = doc.reread(None) {
+                error_log_add(ctx, state, err);
+            }
+        }
+        Action::Keep => {
+            // The document no longer matches the file, so it's unsaved either way.
+            doc.changed_on_disk = false;
+            doc.buffer.borrow_mut().mark_as_dirty();
+            state.wants_save |= state.file_watch.save_on_keep;
+            state.file_watch.save_on_keep = false;
+        }
+        // The dialog comes back when the user returns to the document.
+        Action::Diff => {
+            if let Err(err) = show_diff(state) {
+                error_log_add(ctx, state, err);
+            }
+        }
+    }
+    ctx.needs_rerender();
+}
+
+/// Opens a diff from the file on disk to the active document in a new, read-only document.
+fn show_diff(state: &mut State) -> edit::apperr::Result<()> {
+    let doc = state.documents.active().unwrap();
+    let path = doc.path.as_deref().unwrap();
+
+    // Decode the file like the document was, so that only actual changes show up.
+    let mut disk = TextBuffer::new(false)?;
+    disk.read_file_with_path(&mut DocumentManager::open_for_reading(path)?, path, None)?;
+    let disk = Document::buffer_contents(&disk);
+    let mine = doc.contents();
+
+    let (old, new) = (diff::lines(&disk), diff::lines(&mine));
+    let hunks = diff::diff(&old, &new);
+    let name = doc.filename.clone();
+    let text = diff::unified(&old, &new, &hunks, &format!("{name} (disk)"), &name, 3);
+
+    let name = format!("{name}.diff");
+    let doc = state.documents.add_scratch(Some(&name), FileType::Diff, &text)?;
+    doc.buffer.borrow_mut().set_read_only(true);
+    Ok(())
+}
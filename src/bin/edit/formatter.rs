@@ -0,0 +1,298 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Runs an external formatter before saving. By default only the lines that changed since
+//! the file was last saved are formatted, so that old code doesn't get reformatted along with
+//! every edit.
+//!
+//! Formatters are described by `[formatter.<name>]` config sections:
+//!
+//! ```toml
+//! [formatter.rustfmt]
+//! enabled = true
+//!
+//! [formatter.myfmt]
+//! command = ["myfmt", "--stdin-name", "{file}"]
+//! filetypes = ["python"]
+//! scope = "file"
+//! timeout = 500
+//! ```
+//!
+//! The command gets the document on stdin and writes the formatted version to stdout.
+//! Any formatter works that way, since the changes it makes are diffed against the document
+//! and only those touching modified lines are kept. `scope = "file"` keeps all of them.
+//! A formatter that takes longer than `timeout` milliseconds (1000 by default) is stopped,
+//! and the file is saved as it is.
+//!
+//! The built-in formatters (rustfmt, ruff, prettier, shfmt) only run once `enabled`. A project's
+//! `.edit/config.toml` may define formatters, but only trusted workspaces get to run them.
+
+use std::io::{Read as _, Write as _};
+use std::ops::Range;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use edit::buffer::TextBuffer;
+use edit::config::Config;
+use edit::diff;
+use edit::syntax::FileType;
+
+use crate::documents::{Document, DocumentManager};
+use crate::workspace_trust::TrustStore;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct Formatter {
+    command: Vec<String>,
+    file_types: Vec<FileType>,
+    /// Whether to keep the changes to all lines, not just the modified ones.
+    whole_file: bool,
+    timeout: Duration,
+}
+
+impl Formatter {
+    /// Returns the first enabled formatter for `file_type`.
+    fn load(config: &Config, file_type: FileType) -> Option<Formatter> {
+        let mut names: Vec<&str> = vec!["rustfmt", "ruff", "prettier", "shfmt"];
+        for name in config.subsections("formatter") {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+            .into_iter()
+            .filter_map(|name| Self::load_one(config, name))
+            .find(|f| f.file_types.contains(&file_type))
+    }
+
+    fn load_one(config: &Config, name: &str) -> Option<Formatter> {
+        let key = |k: &str| format!("formatter.{name}.{k}");
+        let builtin = builtin(name);
+
+        if !config.get_bool(&key("enabled")).unwrap_or(builtin.is_none()) {
+            return None;
+        }
+        let (command, file_types) = builtin.unwrap_or_default();
+
+        let command: Vec<String> = match config.get_str_array(&key("command")) {
+            Some(command) => command.into_iter().map(str::to_string).collect(),
+            None => command.iter().map(|s| s.to_string()).collect(),
+        };
+        let file_types = match config.get_str_array(&key("filetypes")) {
+            Some(names) => names.into_iter().filter_map(FileType::from_config_name).collect(),
+            None => file_types.to_vec(),
+        };
+        let whole_file = config.get_str(&key("scope")) == Some("file");
+        let timeout = config
+            .get_int(&key("timeout"))
+            .map_or(DEFAULT_TIMEOUT, |ms| Duration::from_millis(ms.max(0) as u64));
+
+        if command.is_empty() {
+            return None;
+        }
+        Some(Formatter { command, file_types, whole_file, timeout })
+    }
+
+    /// Runs the formatter on `text`. Returns `None` if it failed or took too long.
+    fn run(&self, text: &[u8], path: &Path) -> Option<Vec<u8>> {
+        let file = path.to_string_lossy();
+        let mut command = Command::new(&self.command[0]);
+        command
+            .args(self.command[1..].iter().map(|a| a.replace("{file}", &file)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        if let Some(dir) = path.parent() {
+            command.current_dir(dir);
+        }
+        let mut child = command.spawn().ok()?;
+
+        // Writing and reading happen in the background, so that a formatter that
+        // doesn't read its input, or never finishes, can't block the editor.
+        let mut stdin = child.stdin.take()?;
+        let input = text.to_vec();
+        thread::spawn(move || stdin.write_all(&input));
+        let mut stdout = child.stdout.take()?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            _ = sender.send(stdout.read_to_end(&mut output).map(|_| output));
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait().ok()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                _ = child.kill();
+                _ = child.wait();
+                return None;
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        let output = receiver.recv_timeout(self.timeout).ok()?.ok()?;
+        (status.success() && !output.is_empty()).then_some(output)
+    }
+}
+
+type Builtin = (&'static [&'static str], &'static [FileType]);
+
+fn builtin(name: &str) -> Option<Builtin> {
+    Some(match name {
+        "rustfmt" => (&["rustfmt", "--edition", "2021"], &[FileType::Rust]),
+        "ruff" => (&["ruff", "format", "--stdin-filename", "{file}", "-"], &[FileType::Python]),
+        "prettier" => (
+            &["prettier", "--stdin-filepath", "{file}"],
+            &[
+                FileType::JavaScript,
+                FileType::TypeScript,
+                FileType::HTML,
+                FileType::CSS,
+                FileType::YAML,
+                FileType::Markdown,
+            ],
+        ),
+        "shfmt" => (&["shfmt", "-filename", "{file}"], &[FileType::Shell]),
+        _ => return None,
+    })
+}
+
+/// Formats `tb` before it's saved to `path`, as a single undo step. `old_path` is where the
+/// document was saved before, if anywhere, to tell which lines were modified since.
+pub fn format_before_save(
+    tb: &mut TextBuffer,
+    old_path: Option<&Path>,
+    path: &Path,
+    file_type: FileType,
+    trust: &TrustStore,
+) {
+    if tb.is_read_only() || tb.is_hex_view() || tb.is_ansi_view() {
+        return;
+    }
+    let (config, _) = trust.command_config(path);
+    let Some(formatter) = Formatter::load(&config, file_type) else {
+        return;
+    };
+
+    let text = Document::buffer_contents(tb);
+    // Decode the file like the document was, so that only actual edits count.
+    let saved = old_path.and_then(|old_path| {
+        let mut saved = TextBuffer::new(false).ok()?;
+        let mut file = DocumentManager::open_for_reading(old_path).ok()?;
+        saved.read_file_with_path(&mut file, old_path, None).ok()?;
+        Some(Document::buffer_contents(&saved))
+    });
+    if saved.as_ref() == Some(&text) && !formatter.whole_file {
+        return;
+    }
+
+    let Some(formatted) = formatter.run(&text, path) else {
+        return;
+    };
+    let result = match &saved {
+        Some(saved) if !formatter.whole_file => {
+            let lines = diff::lines(&text);
+            let modified = modified_lines(&diff::lines(saved), &lines);
+            apply_within(&lines, &diff::lines(&formatted), &modified)
+        }
+        // All lines of a new file are new.
+        _ => formatted,
+    };
+    if result != text {
+        tb.set_bytes_by_diff(&result);
+    }
+}
+
+/// Returns the ranges of lines in `new` that differ from `old`. Where lines were only deleted,
+/// the line after them counts as modified, since it's now next to different code.
+fn modified_lines(old: &[&[u8]], new: &[&[u8]]) -> Vec<Range<usize>> {
+    diff::diff(old, new)
+        .into_iter()
+        .map(|hunk| if hunk.new.is_empty() { hunk.new.start..hunk.new.start + 1 } else { hunk.new })
+        .collect()
+}
+
+/// Applies the changes from `lines` to `formatted` that touch one of the `modified` ranges.
+fn apply_within(lines: &[&[u8]], formatted: &[&[u8]], modified: &[Range<usize>]) -> Vec<u8> {
+    let touches = |range: &Range<usize>| {
+        modified.iter().any(|m| {
+            if range.is_empty() {
+                // Lines the formatter inserted: next to or within a modified range.
+                m.start <= range.start && range.start <= m.end
+            } else {
+                m.start < range.end && range.start < m.end
+            }
+        })
+    };
+
+    let mut result = Vec::new();
+    let mut line = 0;
+    for hunk in diff::diff(lines, formatted) {
+        result.extend(lines[line..hunk.old.start].concat());
+        if hunk.old.len() == hunk.new.len() {
+            // Most formatters change lines in place. Each of them is its own change then,
+            // as they'd otherwise drag their unmodified neighbors along.
+            for (old, new) in hunk.old.clone().zip(hunk.new) {
+                result.extend(if touches(&(old..old + 1)) { formatted[new] } else { lines[old] });
+            }
+        } else if touches(&hunk.old) {
+            result.extend(formatted[hunk.new].concat());
+        } else {
+            result.extend(lines[hunk.old.clone()].concat());
+        }
+        line = hunk.old.end;
+    }
+    result.extend(lines[line..].concat());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_within() {
+        let saved = b"fn  a() {}\nfn  b() {}\nfn  c() {}\n";
+        let text = b"fn  a() {}\nfn  b() {  }\nfn  c() {}\n";
+        let formatted = b"fn a() {}\nfn b() {}\nfn c() {}\n";
+
+        let lines = diff::lines(text);
+        let modified = modified_lines(&diff::lines(saved), &lines);
+        assert_eq!(modified, vec![(1..2)]);
+        let result = apply_within(&lines, &diff::lines(formatted), &modified);
+        assert_eq!(result, b"fn  a() {}\nfn b() {}\nfn  c() {}\n");
+
+        // Deleting a line makes the following one count as modified.
+        let text = b"fn  a() {}\nfn  c() {}\n";
+        let modified = modified_lines(&diff::lines(saved), &diff::lines(text));
+        assert_eq!(modified, vec![(1..2)]);
+    }
+
+    #[test]
+    fn test_load() {
+        let config = Config::parse(
+            r#"
+[formatter.ruff]
+enabled = true
+scope = "file"
+
+[formatter.myfmt]
+command = ["myfmt", "{file}"]
+filetypes = ["rust"]
+timeout = 50
+"#,
+        );
+        let ruff = Formatter::load(&config, FileType::Python).unwrap();
+        assert!(ruff.whole_file);
+        assert_eq!(ruff.timeout, DEFAULT_TIMEOUT);
+        let myfmt = Formatter::load(&config, FileType::Rust).unwrap();
+        assert_eq!(myfmt.command, ["myfmt", "{file}"]);
+        assert_eq!(myfmt.timeout, Duration::from_millis(50));
+        assert!(Formatter::load(&config, FileType::Shell).is_none());
+    }
+}
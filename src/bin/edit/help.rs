@@ -0,0 +1,299 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The built-in documentation and a man page reader, shown in a read-only help document.
+//!
+//! The topics are the Markdown files in `assets/help`, which link to each other via
+//! `[text](topic)`. In man pages, references like `ls(1)` are links instead.
+//! Enter follows the link under the cursor and Backspace returns to the previous page.
+
+use std::process::Command;
+
+use edit::apperr;
+use edit::helpers::*;
+use edit::input::vk;
+use edit::syntax::FileType;
+use edit::tui::*;
+
+use crate::documents::Document;
+use crate::state::*;
+
+pub const APP_HELP_NOT_FOUND: apperr::Error = apperr::Error::new_app(1);
+
+/// The built-in topics. The first one is the index.
+const TOPICS: [(&str, &str); 6] = [
+    ("index", include_str!("../../../assets/help/index.md")),
+    ("keys", include_str!("../../../assets/help/keys.md")),
+    ("search", include_str!("../../../assets/help/search.md")),
+    ("commands", include_str!("../../../assets/help/commands.md")),
+    ("cli", include_str!("../../../assets/help/cli.md")),
+    ("config", include_str!("../../../assets/help/config.md")),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelpPage {
+    Topic(&'static str),
+    /// A man page, optionally from a specific section.
+    Man { name: String, section: Option<String> },
+}
+
+impl HelpPage {
+    /// The name of the help document while it shows this page.
+    fn title(&self) -> String {
+        match self {
+            HelpPage::Topic(name) => format!("help:{name}"),
+            HelpPage::Man { name, section: Some(section) } => format!("{name}({section})"),
+            HelpPage::Man { name, section: None } => name.clone(),
+        }
+    }
+
+    fn render(&self) -> apperr::Result<(String, FileType)> {
+        match self {
+            HelpPage::Topic(name) => {
+                let (_, text) = TOPICS.iter().find(|(n, _)| n == name).ok_or(APP_HELP_NOT_FOUND)?;
+                Ok((text.to_string(), FileType::Markdown))
+            }
+            HelpPage::Man { name, section } => {
+                // Neither may be mistaken for an option, like `-P` which runs a command.
+                if name.starts_with('-') || section.as_ref().is_some_and(|s| s.starts_with('-')) {
+                    return Err(APP_HELP_NOT_FOUND);
+                }
+                let output = Command::new("man")
+                    .arg("--")
+                    .args(section)
+                    .arg(name)
+                    .env("MANPAGER", "cat")
+                    .env("PAGER", "cat")
+                    .env("MANWIDTH", "80")
+                    .env("GROFF_NO_SGR", "1")
+                    .output()?;
+                if !output.status.success() || output.stdout.is_empty() {
+                    return Err(APP_HELP_NOT_FOUND);
+                }
+                let text = strip_formatting(&String::from_utf8_lossy(&output.stdout));
+                Ok((text, FileType::Plain))
+            }
+        }
+    }
+}
+
+/// Finds the topic for `:help query`: The one of that name, or else the first one whose name
+/// starts with it, or else the first one with a heading and then any line that mentions it.
+/// Returns the topic and the offset to place the cursor at.
+pub fn lookup(query: &str) -> Option<(&'static str, usize)> {
+    let query = query.trim().to_ascii_lowercase();
+    if query.is_empty() {
+        return Some((TOPICS[0].0, 0));
+    }
+    if let Some(&(name, _)) = TOPICS
+        .iter()
+        .find(|(name, _)| *name == query)
+        .or_else(|| TOPICS.iter().find(|(name, _)| name.starts_with(&query)))
+    {
+        return Some((name, 0));
+    }
+
+    let mention = |headings: bool| {
+        TOPICS.iter().find_map(|&(name, text)| {
+            let mut offset = 0;
+            for line in text.split_inclusive('\n') {
+                if line.starts_with('#') == headings
+                    && let Some(i) = line.to_ascii_lowercase().find(&query)
+                {
+                    return Some((name, offset + i));
+                }
+                offset += line.len();
+            }
+            None
+        })
+    };
+    mention(true).or_else(|| mention(false))
+}
+
+/// Returns the page that the link at byte `offset` in `line` points to, if there's one.
+fn link_at(line: &str, offset: usize) -> Option<HelpPage> {
+    // `[text](topic)`
+    let mut i = 0;
+    while let Some(beg) = line[i..].find('[').map(|p| i + p)
+        && let Some(mid) = line[beg..].find("](").map(|p| beg + p)
+        && let Some(end) = line[mid..].find(')').map(|p| mid + p + 1)
+    {
+        if (beg..end).contains(&offset) {
+            let target = &line[mid + 2..end - 1];
+            let topic = TOPICS.iter().find(|(name, _)| *name == target);
+            return topic.map(|&(name, _)| HelpPage::Topic(name));
+        }
+        i = end;
+    }
+
+    // `name(section)`
+    let is_name = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '+');
+    for (open, _) in line.match_indices('(') {
+        let beg = line[..open].trim_end_matches(is_name).len();
+        let Some(close) = line[open..].find(')').map(|p| open + p) else {
+            break;
+        };
+        let section = &line[open + 1..close];
+        if beg < open
+            && !line[beg..].starts_with('-')
+            && (beg..=close).contains(&offset)
+            && section.starts_with(|c: char| c.is_ascii_digit())
+            && section.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            let name = line[beg..open].to_string();
+            return Some(HelpPage::Man { name, section: Some(section.to_string()) });
+        }
+    }
+
+    None
+}
+
+/// Removes the overstrike bold and underline (`x\bx`, `_\bx`) and SGR sequences from man's output.
+fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x08' => _ = out.pop(),
+            '\x1b' => {
+                if chars.clone().next() == Some('[') {
+                    chars.next();
+                    chars.find(|c| ('@'..='~').contains(c));
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Shows `page` in the help document, which gets created if there's none yet.
+pub fn open(state: &mut State, page: HelpPage) -> apperr::Result<()> {
+    let (text, file_type) = page.render()?;
+
+    if !state.documents.update_active(|doc| !doc.help.is_empty()) {
+        let doc = state.documents.add_scratch(None, FileType::Plain, "")?;
+        doc.buffer.borrow_mut().set_read_only(true);
+    }
+    let doc = state.documents.active_mut().unwrap();
+    if let Some((_, pos)) = doc.help.last_mut() {
+        *pos = doc.buffer.borrow().cursor_logical_pos();
+    }
+    show(doc, &page, &text, file_type);
+    doc.help.push((page, Point::default()));
+    Ok(())
+}
+
+fn show(doc: &mut Document, page: &HelpPage, text: &str, file_type: FileType) {
+    doc.filename = page.title();
+    doc.file_type = file_type;
+
+    let mut tb = doc.buffer.borrow_mut();
+    tb.set_text(text);
+    tb.set_file_type(file_type);
+}
+
+/// `:help query`, see [`lookup()`].
+pub fn open_topic(state: &mut State, query: &str) -> apperr::Result<()> {
+    let (name, offset) = lookup(query).ok_or(APP_HELP_NOT_FOUND)?;
+    open(state, HelpPage::Topic(name))?;
+
+    let mut tb = state.documents.active().unwrap().buffer.borrow_mut();
+    tb.cursor_move_to_offset(offset);
+    tb.make_cursor_visible();
+    Ok(())
+}
+
+/// Returns to the previous page of the active help document, if there's one.
+fn back(state: &mut State) -> apperr::Result<()> {
+    let Some(doc) = state.documents.active_mut() else {
+        return Ok(());
+    };
+    if doc.help.len() < 2 {
+        return Ok(());
+    }
+
+    let (page, pos) = doc.help[doc.help.len() - 2].clone();
+    let (text, file_type) = page.render()?;
+    doc.help.pop();
+    show(doc, &page, &text, file_type);
+
+    let mut tb = doc.buffer.borrow_mut();
+    tb.cursor_move_to_logical(pos);
+    tb.make_cursor_visible();
+    Ok(())
+}
+
+/// Handles Enter and Backspace in help documents. Read-only textareas leave both alone,
+/// so this is called right after the editor.
+pub fn handle_help_input(ctx: &mut Context, state: &mut State) {
+    if !state.documents.active().is_some_and(|doc| !doc.help.is_empty()) {
+        return;
+    }
+
+    let result = if ctx.consume_shortcut(vk::RETURN) {
+        let doc = state.documents.active().unwrap();
+        let (line, offset) = doc.buffer.borrow().line_text_at_cursor();
+        match link_at(&line, offset) {
+            Some(page) => open(state, page),
+            None => Ok(()),
+        }
+    } else if ctx.consume_shortcut(vk::BACK) {
+        back(state)
+    } else {
+        return;
+    };
+
+    if let Err(err) = result {
+        error_log_add(ctx, state, err);
+    }
+    ctx.needs_rerender();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup(""), Some(("index", 0)));
+        assert_eq!(lookup("keys"), Some(("keys", 0)));
+        assert_eq!(lookup("Comm"), Some(("commands", 0)));
+
+        // Headings win over the body text of earlier topics.
+        let (name, offset) = lookup("pager keys").unwrap();
+        assert_eq!(name, "cli");
+        assert!(TOPICS[4].1[offset..].starts_with("Pager keys"));
+
+        let (name, offset) = lookup("colorcolumn").unwrap();
+        assert_eq!(name, "commands");
+        assert!(TOPICS[3].1[offset..].starts_with("colorcolumn"));
+
+        assert_eq!(lookup("frobnicate"), None);
+    }
+
+    #[test]
+    fn test_link_at() {
+        let line = "* [Keyboard shortcuts](keys), [nope](nope) and ls(1), printf(3p).";
+        assert_eq!(link_at(line, 2), Some(HelpPage::Topic("keys")));
+        assert_eq!(link_at(line, 27), Some(HelpPage::Topic("keys")));
+        assert_eq!(link_at(line, 28), None);
+        assert_eq!(link_at(line, 33), None);
+        let man = |name: &str, section: &str| {
+            Some(HelpPage::Man { name: name.to_string(), section: Some(section.to_string()) })
+        };
+        assert_eq!(link_at(line, 47), man("ls", "1"));
+        assert_eq!(link_at(line, 51), man("ls", "1"));
+        assert_eq!(link_at(line, 52), None);
+        assert_eq!(link_at(line, 60), man("printf", "3p"));
+        assert_eq!(link_at("f(x) and (1)", 1), None);
+        assert_eq!(link_at("f(x) and (1)", 10), None);
+        assert_eq!(link_at("see -Pcmd(1)", 6), None);
+    }
+
+    #[test]
+    fn test_strip_formatting() {
+        assert_eq!(strip_formatting("N\x08NA\x08AM\x08ME\x08E _\x08l_\x08s"), "NAME ls");
+        assert_eq!(strip_formatting("\x1b[1mbold\x1b[0m text"), "bold text");
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A two-character jump motion, easymotion/leap style.
+//!
+//! After the shortcut, the next two typed characters form the needle.
+//! All of its occurrences within the viewport get a short label drawn over them
+//! and typing one of the labels moves the cursor there.
+
+use edit::input::vk;
+use edit::tui::*;
+
+use crate::state::*;
+
+#[derive(Default)]
+pub struct Jump {
+    pub needle: String,
+    label: String,
+}
+
+/// Captures the keyboard input while the jump motion is active.
+/// Must be called before the editor, so that it doesn't see the input.
+pub fn handle_jump_input(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        state.wants_jump = false;
+        state.jump = Jump::default();
+        return;
+    };
+    let jump = &mut state.jump;
+    let mut tb = doc.buffer.borrow_mut();
+    let mut done = false;
+
+    if ctx.consume_shortcut(vk::ESCAPE) {
+        done = true;
+    } else if ctx.consume_shortcut(vk::BACK) {
+        if jump.label.pop().is_none() {
+            jump.needle.pop();
+            tb.set_jump_needle(None);
+        }
+    } else if let Some(text) = ctx.text_input() {
+        let text = text.to_string();
+        ctx.set_input_consumed();
+
+        for ch in text.chars() {
+            if jump.needle.chars().count() < 2 {
+                jump.needle.push(ch);
+                if jump.needle.chars().count() == 2 {
+                    tb.set_jump_needle(Some(&jump.needle));
+                }
+                continue;
+            }
+
+            jump.label.push(ch);
+            if tb.jump_to_label(&jump.label) {
+                tb.make_cursor_visible();
+                done = true;
+            } else if !tb.has_jump_label(&jump.label) {
+                done = true;
+            }
+            if done {
+                break;
+            }
+        }
+    } else if ctx.keyboard_input().is_some() {
+        // Any other key cancels the jump and then does whatever it usually does.
+        done = true;
+    } else {
+        return;
+    }
+
+    if done {
+        tb.set_jump_needle(None);
+        state.wants_jump = false;
+        state.jump = Jump::default();
+    }
+    ctx.needs_rerender();
+}
@@ -0,0 +1,234 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Where the cursor jumped from, to go back and forth like with Vim's Ctrl+O and Ctrl+I.
+//!
+//! Going to a line, searching and jumping to a matching tag remember the position they
+//! left with [`JumpList::push()`]. Switching tabs is noticed by [`JumpList::track()`],
+//! which is called once per frame, so that all the ways of doing so are covered.
+
+use std::rc::{Rc, Weak};
+
+use edit::buffer::{RcTextBuffer, TextBufferCell};
+use edit::helpers::*;
+
+use crate::documents::DocumentManager;
+
+/// Older jumps are forgotten.
+const MAX_JUMPS: usize = 100;
+
+struct Jump {
+    buffer: Weak<TextBufferCell>,
+    pos: Point,
+}
+
+impl Jump {
+    fn new(buffer: &RcTextBuffer) -> Self {
+        Self { buffer: Rc::downgrade(buffer), pos: buffer.borrow().cursor_logical_pos() }
+    }
+
+    /// Whether both are on the same line of the same document.
+    fn same_line(&self, other: &Jump) -> bool {
+        Weak::ptr_eq(&self.buffer, &other.buffer) && self.pos.y == other.pos.y
+    }
+}
+
+#[derive(Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    /// The jump that going back returns to is the one before it.
+    /// Equal to the length unless we went back.
+    index: usize,
+    /// The document that was active during the last [`JumpList::track()`].
+    active: Weak<TextBufferCell>,
+}
+
+impl JumpList {
+    /// Remembers the cursor position in `buffer`, before the cursor jumps elsewhere.
+    /// Forgets the jumps that could have been gone forward to.
+    pub fn push(&mut self, buffer: &RcTextBuffer) {
+        self.push_jump(Jump::new(buffer));
+    }
+
+    /// Like [`JumpList::push()`], but for a position the cursor already left, like where
+    /// a search started.
+    pub fn push_at(&mut self, buffer: &RcTextBuffer, pos: Point) {
+        self.push_jump(Jump { buffer: Rc::downgrade(buffer), pos });
+    }
+
+    fn push_jump(&mut self, jump: Jump) {
+        self.jumps.truncate(self.index);
+        self.jumps.retain(|j| !j.same_line(&jump) && j.buffer.strong_count() > 0);
+        self.jumps.push(jump);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.index = self.jumps.len();
+    }
+
+    /// Remembers where the previously active document was left, if another one is active now.
+    pub fn track(&mut self, documents: &DocumentManager) {
+        let active = documents.active().map_or_else(Weak::new, |doc| Rc::downgrade(&doc.buffer));
+        if !Weak::ptr_eq(&active, &self.active) {
+            if let Some(previous) = self.active.upgrade() {
+                self.push(&previous);
+            }
+            self.active = active;
+        }
+    }
+
+    /// Goes back to where the cursor was before the last jump. Returns `false` if there's
+    /// nowhere to go back to.
+    pub fn back(&mut self, documents: &mut DocumentManager) -> bool {
+        let Some(doc) = documents.active() else {
+            return false;
+        };
+        let current = Jump::new(&doc.buffer);
+        if self.index == self.jumps.len() {
+            // So that going forward returns here.
+            self.push(&doc.buffer);
+            self.index -= 1;
+        }
+        while self.index > 0 {
+            self.index -= 1;
+            if !self.jumps[self.index].same_line(&current) && self.go(documents) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Undoes [`JumpList::back()`].
+    pub fn forward(&mut self, documents: &mut DocumentManager) -> bool {
+        while self.index + 1 < self.jumps.len() {
+            self.index += 1;
+            if self.go(documents) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Moves to the jump at `self.index`, unless its document was closed.
+    fn go(&mut self, documents: &mut DocumentManager) -> bool {
+        let jump = &self.jumps[self.index];
+        let Some(buffer) = jump.buffer.upgrade() else {
+            return false;
+        };
+        if !documents.update_active(|doc| Rc::ptr_eq(&doc.buffer, &buffer)) {
+            return false;
+        }
+        let mut tb = buffer.borrow_mut();
+        tb.cursor_move_to_logical(jump.pos);
+        tb.make_cursor_visible();
+        // Returning to another tab isn't a jump of its own.
+        self.active = jump.buffer.clone();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edit::syntax::FileType;
+
+    use super::*;
+
+    const TEXT: &str = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+
+    fn add(documents: &mut DocumentManager) -> RcTextBuffer {
+        let doc = documents.add_scratch(None, FileType::Plain, TEXT).unwrap();
+        doc.buffer.clone()
+    }
+
+    fn goto(buffer: &RcTextBuffer, y: CoordType) {
+        buffer.borrow_mut().cursor_move_to_logical(Point { x: 0, y });
+    }
+
+    fn line(buffer: &RcTextBuffer) -> CoordType {
+        buffer.borrow().cursor_logical_pos().y
+    }
+
+    #[test]
+    fn test_back_forward() {
+        let _arena = crate::init_arena_for_test();
+        let mut documents = DocumentManager::default();
+        let buffer = add(&mut documents);
+        let mut jumps = JumpList::default();
+
+        jumps.push(&buffer);
+        goto(&buffer, 5);
+        jumps.push(&buffer);
+        goto(&buffer, 8);
+
+        assert!(jumps.back(&mut documents));
+        assert_eq!(line(&buffer), 5);
+        assert!(jumps.back(&mut documents));
+        assert_eq!(line(&buffer), 0);
+        assert!(!jumps.back(&mut documents));
+        assert_eq!(line(&buffer), 0);
+
+        assert!(jumps.forward(&mut documents));
+        assert_eq!(line(&buffer), 5);
+        assert!(jumps.forward(&mut documents));
+        assert_eq!(line(&buffer), 8);
+        assert!(!jumps.forward(&mut documents));
+        assert_eq!(line(&buffer), 8);
+    }
+
+    #[test]
+    fn test_push_truncates_forward() {
+        let _arena = crate::init_arena_for_test();
+        let mut documents = DocumentManager::default();
+        let buffer = add(&mut documents);
+        let mut jumps = JumpList::default();
+
+        goto(&buffer, 2);
+        jumps.push(&buffer);
+        goto(&buffer, 5);
+        jumps.push(&buffer);
+        goto(&buffer, 8);
+        assert!(jumps.back(&mut documents));
+        assert!(jumps.back(&mut documents));
+        assert_eq!(line(&buffer), 2);
+
+        // A new jump from here forgets the 5 and the 8.
+        jumps.push(&buffer);
+        goto(&buffer, 3);
+        assert!(!jumps.forward(&mut documents));
+        assert!(jumps.back(&mut documents));
+        assert_eq!(line(&buffer), 2);
+        assert!(!jumps.back(&mut documents));
+        assert!(jumps.forward(&mut documents));
+        assert_eq!(line(&buffer), 3);
+        assert!(!jumps.forward(&mut documents));
+    }
+
+    #[test]
+    fn test_closed_documents() {
+        let _arena = crate::init_arena_for_test();
+        let mut documents = DocumentManager::default();
+        let mut jumps = JumpList::default();
+
+        let first = add(&mut documents);
+        goto(&first, 2);
+        jumps.track(&documents);
+        let second = add(&mut documents);
+        jumps.track(&documents);
+        goto(&second, 4);
+        jumps.push(&second);
+        goto(&second, 7);
+
+        // Switching tabs was tracked as a jump.
+        assert_eq!(jumps.jumps.len(), 2);
+
+        // Closing the first document drops its jumps.
+        documents.switch_to_index(1);
+        documents.remove_active();
+        drop(first);
+        assert!(jumps.back(&mut documents));
+        assert_eq!(line(&second), 4);
+        assert_eq!(jumps.jumps.len(), 2);
+        assert!(!jumps.back(&mut documents));
+        assert_eq!(line(&second), 4);
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The table of keyboard shortcuts and the cheat sheet (Alt+K) that's generated from it.
+//!
+//! The shortcuts themselves are still handled where they always were (the menubar,
+//! the textarea, the tab bar, ...), so a new one needs an entry here to show up.
+
+use edit::framebuffer::{Attributes, IndexedColor};
+use edit::helpers::*;
+use edit::input::{InputKey, InputKeyMod, kbmod, vk};
+use edit::tui::*;
+
+use crate::localization::*;
+use crate::state::*;
+
+/// Toggles the cheat sheet.
+pub const CHEAT_SHEET_KEY: InputKey = vk::K.with_modifiers(kbmod::ALT);
+
+#[derive(Clone, Copy)]
+pub enum Key {
+    Chord(InputKey),
+    /// Text input, as used by the pager, or a description like "F1-F9".
+    Text(&'static str),
+}
+
+pub struct Binding {
+    pub category: LocId,
+    pub description: LocId,
+    pub keys: &'static [Key],
+}
+
+const fn binding(category: LocId, description: LocId, keys: &'static [Key]) -> Binding {
+    Binding { category, description, keys }
+}
+
+const fn chord(modifiers: InputKeyMod, key: InputKey) -> Key {
+    Key::Chord(key.with_modifiers(modifiers))
+}
+
+use Key::*;
+use LocId::*;
+
+/// Shortcuts that work anywhere, unless they're also listed in [`PAGER`].
+pub const EDITOR: &[Binding] = &[
+    binding(File, FileNew, &[chord(kbmod::CTRL, vk::N)]),
+    binding(File, FileOpen, &[chord(kbmod::CTRL, vk::O)]),
+    binding(File, FileSave, &[chord(kbmod::CTRL, vk::S)]),
+    binding(File, FileSaveAs, &[chord(kbmod::CTRL_SHIFT, vk::S)]),
+    binding(File, FileClose, &[chord(kbmod::CTRL, vk::W)]),
+    binding(File, FileAlternate, &[chord(kbmod::ALT, vk::O)]),
+    binding(File, FileRunLastTask, &[chord(kbmod::CTRL_SHIFT, vk::B)]),
+    binding(File, FileExit, &[chord(kbmod::CTRL, vk::Q)]),
+    binding(Edit, EditUndo, &[chord(kbmod::CTRL, vk::Z)]),
+    binding(Edit, EditRedo, &[chord(kbmod::CTRL, vk::Y)]),
+    binding(Edit, EditCut, &[chord(kbmod::CTRL, vk::X)]),
+    binding(Edit, EditCopy, &[chord(kbmod::CTRL, vk::C)]),
+    binding(Edit, EditPaste, &[chord(kbmod::CTRL, vk::V)]),
+    binding(Edit, EditSelectAll, &[chord(kbmod::CTRL, vk::A)]),
+    binding(Edit, EditFind, &[chord(kbmod::CTRL, vk::F)]),
+    binding(Edit, EditReplace, &[chord(kbmod::CTRL, vk::R)]),
+    binding(Edit, EditFindLines, &[chord(kbmod::ALT, vk::L)]),
+    binding(Edit, EditFindInFiles, &[chord(kbmod::CTRL_SHIFT, vk::F)]),
+    binding(Edit, EditReindent, &[chord(kbmod::CTRL_SHIFT, vk::I)]),
+    binding(Edit, EditToggleLineComment, &[chord(kbmod::CTRL, vk::OEM_2)]),
+    binding(Edit, EditToggleBlockComment, &[chord(kbmod::ALT_SHIFT, vk::A)]),
+    binding(Edit, KeymapMoveLines, &[chord(kbmod::ALT, vk::UP), chord(kbmod::ALT, vk::DOWN)]),
+    binding(Edit, EditDuplicate, &[chord(kbmod::CTRL_SHIFT, vk::D)]),
+    binding(Edit, KeymapToggleFold, &[chord(kbmod::ALT_SHIFT, vk::F)]),
+    binding(
+        Edit,
+        KeymapCursorAboveBelow,
+        &[chord(kbmod::CTRL_ALT, vk::UP), chord(kbmod::CTRL_ALT, vk::DOWN)],
+    ),
+    binding(Edit, KeymapCursorNextMatch, &[chord(kbmod::CTRL, vk::D)]),
+    binding(
+        Edit,
+        KeymapBlockSelection,
+        &[chord(kbmod::ALT_SHIFT, vk::UP), chord(kbmod::ALT_SHIFT, vk::DOWN)],
+    ),
+    binding(Edit, EditRecordMacro, &[chord(kbmod::ALT, vk::Q)]),
+    binding(Edit, EditPlayMacro, &[chord(kbmod::ALT, vk::P)]),
+    binding(Edit, EditNote, &[chord(kbmod::ALT, vk::N)]),
+    binding(KeymapNavigation, FileGoto, &[chord(kbmod::CTRL, vk::G)]),
+    binding(KeymapNavigation, EditJump, &[chord(kbmod::ALT, vk::J)]),
+    binding(KeymapNavigation, EditMatchingTag, &[chord(kbmod::ALT, vk::T)]),
+    binding(KeymapNavigation, KeymapJumpBack, &[chord(kbmod::ALT, vk::OEM_COMMA)]),
+    binding(KeymapNavigation, KeymapJumpForward, &[chord(kbmod::ALT, vk::OEM_PERIOD)]),
+    binding(
+        KeymapNavigation,
+        KeymapWord,
+        &[chord(kbmod::CTRL, vk::LEFT), chord(kbmod::CTRL, vk::RIGHT)],
+    ),
+    binding(
+        KeymapNavigation,
+        KeymapSubword,
+        &[chord(kbmod::CTRL_ALT, vk::LEFT), chord(kbmod::CTRL_ALT, vk::RIGHT)],
+    ),
+    binding(View, ViewDocumentPicker, &[chord(kbmod::CTRL, vk::P)]),
+    binding(View, ViewCommandLine, &[chord(kbmod::CTRL_SHIFT, vk::P)]),
+    binding(View, ViewWordWrap, &[chord(kbmod::ALT, vk::Z)]),
+    binding(
+        KeymapTabs,
+        KeymapNextTab,
+        &[chord(kbmod::ALT, vk::RIGHT), chord(kbmod::CTRL, vk::NEXT)],
+    ),
+    binding(
+        KeymapTabs,
+        KeymapPreviousTab,
+        &[chord(kbmod::ALT, vk::LEFT), chord(kbmod::CTRL, vk::PRIOR)],
+    ),
+    binding(KeymapTabs, KeymapGoToTab, &[Text("F1-F9")]),
+    binding(Help, HelpKeyboardShortcuts, &[Chord(CHEAT_SHEET_KEY)]),
+];
+
+/// The less-like keys of `--pager`, see [`crate::pager`].
+pub const PAGER: &[Binding] = &[
+    binding(KeymapPager, FileExit, &[Text("q")]),
+    binding(KeymapPager, EditFind, &[Text("/")]),
+    binding(KeymapPager, KeymapFindNext, &[Text("n")]),
+    binding(KeymapPager, KeymapPageDownUp, &[Chord(vk::SPACE), Text("f"), Text("b")]),
+    binding(KeymapPager, KeymapLineDownUp, &[Text("j"), Text("k")]),
+    binding(KeymapPager, KeymapStartEnd, &[Text("g"), Text("G")]),
+    binding(KeymapPager, KeymapFollow, &[Text("F")]),
+];
+
+/// The keys of help documents, see [`crate::help`].
+pub const HELP: &[Binding] = &[
+    binding(Help, KeymapFollowLink, &[Chord(vk::RETURN)]),
+    binding(Help, KeymapBack, &[Chord(vk::BACK)]),
+];
+
+/// Formats the keys of `binding` for display, e.g. "Alt+→, Ctrl+PgDn".
+pub fn keys_text(ctx: &Context, binding: &Binding) -> String {
+    let mut text = String::new();
+    for (i, key) in binding.keys.iter().enumerate() {
+        if i > 0 {
+            text.push_str(", ");
+        }
+        match *key {
+            Chord(key) => text.push_str(&ctx.shortcut_text(key)),
+            Text(key) => text.push_str(key),
+        }
+    }
+    text
+}
+
+/// The bindings that apply right now, in display order: The ones specific to
+/// the active document's mode come first, followed by the general ones.
+fn active_bindings(state: &State) -> Vec<&'static Binding> {
+    let mut bindings = Vec::new();
+    if state.pager.enabled {
+        bindings.extend(PAGER);
+    }
+    if state.documents.active().is_some_and(|doc| !doc.help.is_empty()) {
+        bindings.extend(HELP);
+    }
+    for binding in EDITOR {
+        // The pager shadows shortcuts for the same action, e.g. Ctrl+Q with q.
+        if !bindings.iter().any(|b| b.description == binding.description) {
+            bindings.push(binding);
+        }
+    }
+    bindings
+}
+
+pub fn draw_cheat_sheet(ctx: &mut Context, state: &mut State) {
+    let bindings = active_bindings(state);
+    let mut categories: Vec<LocId> = Vec::new();
+    for binding in &bindings {
+        if !categories.contains(&binding.category) {
+            categories.push(binding.category);
+        }
+    }
+
+    ctx.modal_begin("cheat-sheet", loc(HelpKeyboardShortcuts));
+    {
+        let (width, height) = if ctx.is_modal_fullscreen() {
+            (ctx.size().width - 3, ctx.size().height - 2)
+        } else {
+            ((ctx.size().width - 20).clamp(10, 60), (ctx.size().height - 10).max(10))
+        };
+
+        ctx.scrollarea_begin("scrollarea", Size { width, height });
+        ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+        ctx.inherit_focus();
+        {
+            ctx.table_begin("bindings");
+            ctx.inherit_focus();
+            ctx.table_set_columns(&[0, COORD_TYPE_SAFE_MAX]);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            ctx.attr_padding(Rect::two(0, 1));
+
+            let mut row = 0;
+            for &category in &categories {
+                ctx.next_block_id_mixin(row);
+                ctx.table_next_row();
+                if row == 0 {
+                    // Focus the first row, so that the sheet starts scrolled to the top.
+                    ctx.inherit_focus();
+                }
+                row += 1;
+                ctx.styled_label_begin("category");
+                ctx.styled_label_set_attributes(Attributes::Bold);
+                ctx.styled_label_add_text(loc(category));
+                ctx.styled_label_end();
+                ctx.block_begin("spacer");
+                ctx.block_end();
+
+                for binding in bindings.iter().filter(|b| b.category == category) {
+                    ctx.next_block_id_mixin(row);
+                    ctx.table_next_row();
+                    row += 1;
+                    ctx.label("keys", &keys_text(ctx, binding));
+                    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightBlue));
+                    ctx.label("description", loc(binding.description));
+                    ctx.attr_overflow(Overflow::TruncateTail);
+                }
+            }
+
+            ctx.table_end();
+        }
+        ctx.scrollarea_end();
+    }
+    // The modal swallows all input, so the toggle key needs to be checked before it does.
+    let toggled = ctx.consume_shortcut(CHEAT_SHEET_KEY);
+    if ctx.modal_end() || toggled {
+        state.wants_cheat_sheet = false;
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_duplicate_chords() {
+        for table in [EDITOR, PAGER, HELP] {
+            let chords: Vec<InputKey> = table
+                .iter()
+                .flat_map(|b| b.keys)
+                .filter_map(|&key| match key {
+                    Chord(key) => Some(key),
+                    Text(_) => None,
+                })
+                .collect();
+            for (i, key) in chords.iter().enumerate() {
+                assert!(!chords[i + 1..].contains(key));
+            }
+        }
+    }
+}
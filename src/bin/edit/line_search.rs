@@ -0,0 +1,291 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Lists all lines of the active document that match a fuzzy query, swiper-style.
+//!
+//! The query is split at whitespace and each term must match the line on its own, in any order.
+//! Moving through the list jumps the editor to the selected line right away.
+//! Escape returns to where the cursor was before.
+
+use edit::arena::{Arena, scratch_arena};
+use edit::buffer::TextBuffer;
+use edit::framebuffer::IndexedColor;
+use edit::fuzzy::score_fuzzy;
+use edit::helpers::*;
+use edit::input::vk;
+use edit::tui::*;
+
+use crate::draw_editor::attr_panel_overlay;
+use crate::localization::*;
+use crate::state::*;
+
+/// The number of lines shown in the list.
+const LIST_HEIGHT: CoordType = 8;
+/// The height of the whole panel: The query and the list.
+pub const LINE_SEARCH_HEIGHT: CoordType = LIST_HEIGHT + 1;
+/// Lines are only matched and shown up to this many characters.
+const MAX_LINE_CHARS: usize = 256;
+
+pub struct LineHit {
+    /// 0-based logical line.
+    pub line: CoordType,
+    /// The line without its indentation.
+    pub text: String,
+    /// The width of the indentation in characters.
+    pub indent: CoordType,
+    /// The matched characters in `text`, as sorted character indices.
+    pub positions: Vec<usize>,
+}
+
+#[derive(Default)]
+pub struct LineSearch {
+    pub needle: String,
+    hits: Vec<LineHit>,
+    /// The needle and buffer generation that `hits` were collected for.
+    hits_key: Option<(String, u32)>,
+    selected: usize,
+    scroll: usize,
+    /// Where the cursor was before the search started. It returns there if the search is canceled.
+    origin: Option<Point>,
+}
+
+impl LineSearch {
+    fn update(&mut self, tb: &TextBuffer) {
+        let key = (self.needle.clone(), tb.generation());
+        if self.hits_key.as_ref() != Some(&key) {
+            self.hits = collect_hits(tb, &self.needle);
+            self.hits_key = Some(key);
+            self.selected = 0;
+            self.scroll = 0;
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.hits.len().saturating_sub(1));
+        let height = LIST_HEIGHT as usize;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Returns the matched character indices, if all whitespace-separated terms of `needle` match `text`.
+pub fn match_line(arena: &Arena, text: &str, needle: &str) -> Option<Vec<usize>> {
+    let mut positions = Vec::new();
+    for term in needle.split_whitespace() {
+        let (score, term_positions) = score_fuzzy(arena, text, term, true);
+        if score <= 0 {
+            return None;
+        }
+        positions.extend_from_slice(&term_positions);
+    }
+    if positions.is_empty() {
+        return None;
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    Some(positions)
+}
+
+fn collect_hits(tb: &TextBuffer, needle: &str) -> Vec<LineHit> {
+    let mut hits = Vec::new();
+    if needle.trim().is_empty() {
+        return hits;
+    }
+
+    let mut line = Vec::new();
+    let mut line_no = 0;
+    let mut off = 0;
+    let mut process = |line: &[u8], line_no: CoordType| {
+        let scratch = scratch_arena(None);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = String::from_utf8_lossy(line);
+        let text = line.trim_start();
+        let indent = line[..line.len() - text.len()].chars().count() as CoordType;
+        let text: String = text.chars().take(MAX_LINE_CHARS).collect();
+        if let Some(positions) = match_line(&scratch, &text, needle) {
+            hits.push(LineHit { line: line_no, text, indent, positions });
+        }
+    };
+
+    loop {
+        let chunk = tb.read_forward(off);
+        if chunk.is_empty() {
+            process(&line, line_no);
+            break;
+        }
+        off += chunk.len();
+
+        let mut rest = chunk;
+        while let Some(i) = rest.iter().position(|&c| c == b'\n') {
+            line.extend_from_slice(&rest[..i]);
+            process(&line, line_no);
+            line.clear();
+            line_no += 1;
+            rest = &rest[i + 1..];
+        }
+        line.extend_from_slice(rest);
+    }
+
+    hits
+}
+
+pub fn draw_line_search(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        state.wants_line_search = false;
+        state.line_search.reset();
+        return;
+    };
+    let overlay_top = state.panel_overlay_top();
+    let search = &mut state.line_search;
+    let mut tb = doc.buffer.borrow_mut();
+    let mut jump = false;
+    let mut close = None;
+
+    search.origin.get_or_insert_with(|| tb.cursor_logical_pos());
+
+    ctx.block_begin("line-search");
+    attr_panel_overlay(ctx, overlay_top, LINE_SEARCH_HEIGHT);
+    ctx.attr_focus_well();
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::White));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        state.editor_active = ctx.contains_focus();
+
+        ctx.table_begin("needle");
+        ctx.table_set_columns(&[0, COORD_TYPE_SAFE_MAX, 0]);
+        ctx.table_set_cell_gap(Size { width: 1, height: 0 });
+        {
+            ctx.table_next_row();
+            ctx.label("label", loc(LocId::LineSearchLabel));
+
+            if ctx.editline("needle", &mut search.needle) {
+                jump = true;
+            }
+            ctx.focus_on_first_present();
+            search.update(&tb);
+            if search.hits.is_empty() && !search.needle.trim().is_empty() {
+                ctx.attr_background_rgba(ctx.indexed(IndexedColor::Red));
+                ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightWhite));
+            }
+
+            if ctx.is_focused() {
+                let page = LIST_HEIGHT as usize;
+                let selected = search.selected;
+                let target = if ctx.consume_shortcut(vk::UP) {
+                    Some(selected.saturating_sub(1))
+                } else if ctx.consume_shortcut(vk::DOWN) {
+                    Some(selected + 1)
+                } else if ctx.consume_shortcut(vk::PRIOR) {
+                    Some(selected.saturating_sub(page))
+                } else if ctx.consume_shortcut(vk::NEXT) {
+                    Some(selected + page)
+                } else {
+                    None
+                };
+                if let Some(target) = target {
+                    search.select(target);
+                    jump = true;
+                } else if ctx.consume_shortcut(vk::RETURN) {
+                    close = Some(true);
+                } else if ctx.consume_shortcut(vk::ESCAPE) {
+                    close = Some(false);
+                }
+            }
+
+            let count = if search.hits.is_empty() {
+                String::new()
+            } else {
+                format!("{}/{}", search.selected + 1, search.hits.len())
+            };
+            ctx.label("count", &count);
+        }
+        ctx.table_end();
+
+        ctx.block_begin("lines");
+        ctx.attr_intrinsic_size(Size { width: COORD_TYPE_SAFE_MAX, height: LIST_HEIGHT });
+        {
+            let number_width = tb.logical_line_count().max(1).ilog10() as usize + 1;
+            let hl = ctx.indexed(IndexedColor::Blue);
+            let dim = ctx.indexed_alpha(IndexedColor::Black, 1, 2);
+            let end = (search.scroll + LIST_HEIGHT as usize).min(search.hits.len());
+
+            for i in search.scroll..end {
+                let hit = &search.hits[i];
+                ctx.next_block_id_mixin(i as u64);
+                ctx.styled_label_begin("line");
+                ctx.styled_label_set_foreground(dim);
+                ctx.styled_label_add_text(&format!("{:>number_width$} ", hit.line + 1));
+
+                let mut positions = hit.positions.iter().peekable();
+                let mut run = String::new();
+                let mut run_matched = false;
+                for (ci, ch) in hit.text.chars().enumerate() {
+                    let matched = positions.next_if(|&&p| p == ci).is_some();
+                    if matched != run_matched && !run.is_empty() {
+                        ctx.styled_label_set_foreground(if run_matched { hl } else { 0 });
+                        ctx.styled_label_add_text(&run);
+                        run.clear();
+                    }
+                    run_matched = matched;
+                    run.push(ch);
+                }
+                ctx.styled_label_set_foreground(if run_matched { hl } else { 0 });
+                ctx.styled_label_add_text(&run);
+                ctx.styled_label_end();
+                ctx.attr_overflow(Overflow::TruncateTail);
+
+                if i == search.selected {
+                    ctx.attr_reverse();
+                }
+                if ctx.was_mouse_down() {
+                    search.select(i);
+                    jump = true;
+                }
+            }
+        }
+        ctx.block_end();
+    }
+    ctx.block_end();
+
+    if jump && let Some(hit) = search.hits.get(search.selected) {
+        let x = hit.indent + hit.positions.first().copied().unwrap_or(0) as CoordType;
+        tb.cursor_move_to_logical(Point { x, y: hit.line });
+        tb.make_cursor_visible();
+        ctx.needs_rerender();
+    }
+
+    if let Some(accept) = close {
+        if !accept && let Some(origin) = search.origin {
+            tb.cursor_move_to_logical(origin);
+            tb.make_cursor_visible();
+        }
+        search.reset();
+        state.wants_line_search = false;
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_line() {
+        let _arena = crate::init_arena_for_test();
+        let scratch = scratch_arena(None);
+        assert_eq!(match_line(&scratch, "fn draw_editor()", "drw"), Some(vec![3, 4, 6]));
+        assert_eq!(
+            match_line(&scratch, "fn draw_editor()", "edit fn"),
+            Some(vec![0, 1, 8, 9, 10, 11])
+        );
+        assert_eq!(match_line(&scratch, "fn draw_editor()", "edit xyz"), None);
+        assert_eq!(match_line(&scratch, "fn draw_editor()", "  "), None);
+    }
+}
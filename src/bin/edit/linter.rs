@@ -0,0 +1,442 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Runs external linters after saving and feeds their output into the document's diagnostics.
+//!
+//! Linters are described by `[linter.<name>]` config sections:
+//!
+//! ```toml
+//! [linter.ruff]
+//! enabled = true
+//!
+//! [linter.mylint]
+//! command = ["mylint", "--quiet", "{file}"]
+//! filetypes = ["python"]
+//! format = "regex"
+//! pattern = '^[^:]+:(?P<line>\d+):(?P<column>\d+): (?P<message>.*)$'
+//! ```
+//!
+//! The `regex` format matches each output line against `pattern`, which may use the named
+//! groups `line`, `column`, `severity` and `message`. The `json` format reads the array at
+//! the `items` path of the output and the `line`, `column`, `severity` and `message` paths
+//! inside each item. Lines and columns are 1-based.
+//!
+//! The built-in linters (ruff, eslint, shellcheck, yamllint) only run once `enabled`.
+//! Linters defined in the config run unless they're disabled. A project's `.edit/config.toml`
+//! may define linters, too, but they only run in trusted workspaces. See [`crate::workspace_trust`].
+//! The linters of a directory are looked up once, until a trust decision changes
+//! or a project config is saved.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use edit::config::Config;
+use edit::diagnostics::{Diagnostic, Severity};
+use edit::helpers::CoordType;
+use edit::json;
+use edit::syntax::FileType;
+use regex::Regex;
+
+use crate::state::State;
+use crate::workspace_trust::TrustStore;
+
+/// Saving in quick succession only results in a single run.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// How often finished runs are checked for while any are outstanding.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+enum OutputFormat {
+    Regex(Regex),
+    Json { items: String, line: String, column: String, severity: String, message: String },
+}
+
+pub struct Linter {
+    pub name: String,
+    command: Vec<String>,
+    file_types: Vec<FileType>,
+    format: OutputFormat,
+}
+
+impl Linter {
+    /// Returns all enabled linters from the config, along with the built-in ones.
+    pub fn load_all(config: &Config) -> Vec<Linter> {
+        let mut names: Vec<&str> = vec!["ruff", "eslint", "shellcheck", "yamllint"];
+        for name in config.subsections("linter") {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names.into_iter().filter_map(|name| Self::load(config, name)).collect()
+    }
+
+    fn load(config: &Config, name: &str) -> Option<Linter> {
+        let section = format!("linter.{name}");
+        let key = |k: &str| format!("{section}.{k}");
+        let builtin = builtin(name);
+
+        let enabled = config.get_bool(&key("enabled")).unwrap_or(builtin.is_none());
+        if !enabled {
+            return None;
+        }
+        let (command, file_types, format, pattern) = builtin.unwrap_or_default();
+
+        let command: Vec<String> = match config.get_str_array(&key("command")) {
+            Some(command) => command.into_iter().map(str::to_string).collect(),
+            None => command.iter().map(|s| s.to_string()).collect(),
+        };
+        let file_types: Vec<FileType> = match config.get_str_array(&key("filetypes")) {
+            Some(names) => names.into_iter().filter_map(FileType::from_config_name).collect(),
+            None => file_types.to_vec(),
+        };
+        let format = match config.get_str(&key("format")).unwrap_or(format) {
+            "json" => {
+                let path = |k: &str, default: &str| {
+                    config
+                        .get_str(&key(k))
+                        .unwrap_or(json_default(name, k).unwrap_or(default))
+                        .to_string()
+                };
+                OutputFormat::Json {
+                    items: path("items", ""),
+                    line: path("line", "line"),
+                    column: path("column", "column"),
+                    severity: path("severity", "severity"),
+                    message: path("message", "message"),
+                }
+            }
+            _ => OutputFormat::Regex(
+                Regex::new(config.get_str(&key("pattern")).unwrap_or(pattern)).ok()?,
+            ),
+        };
+
+        if command.is_empty() {
+            return None;
+        }
+        Some(Linter { name: name.to_string(), command, file_types, format })
+    }
+
+    pub fn applies_to(&self, file_type: FileType) -> bool {
+        self.file_types.contains(&file_type)
+    }
+
+    /// Runs the linter on `path` and returns its findings.
+    /// Failing to start the command results in no diagnostics.
+    fn run(&self, path: &Path) -> Vec<Diagnostic> {
+        let file = path.to_string_lossy();
+        let args: Vec<String> =
+            self.command[1..].iter().map(|a| a.replace("{file}", &file)).collect();
+        let mut command = Command::new(&self.command[0]);
+        command.args(&args);
+        if let Some(dir) = path.parent() {
+            command.current_dir(dir);
+        }
+        // Linters exit with a non-zero status when they found something, so it's ignored.
+        match command.output() {
+            Ok(output) => self.parse(&String::from_utf8_lossy(&output.stdout)),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn parse(&self, output: &str) -> Vec<Diagnostic> {
+        let position = |s: Option<&str>| {
+            s.and_then(|s| s.trim().parse::<f64>().ok()).map_or(0, |n| (n as CoordType - 1).max(0))
+        };
+
+        match &self.format {
+            OutputFormat::Regex(re) => output
+                .lines()
+                .filter_map(|line| {
+                    let caps = re.captures(line)?;
+                    let group = |name: &str| caps.name(name).map(|m| m.as_str());
+                    Some(Diagnostic {
+                        line: position(Some(group("line")?)),
+                        column: position(group("column")),
+                        severity: group("severity").map_or(Severity::Warning, Severity::parse),
+                        message: group("message").unwrap_or_default().trim().to_string(),
+                    })
+                })
+                .collect(),
+            OutputFormat::Json { items, line, column, severity, message } => {
+                let Some(value) = json::parse(output) else {
+                    return Vec::new();
+                };
+                let Some(list) = value.pointer(items).and_then(json::Value::as_array) else {
+                    return Vec::new();
+                };
+                list.iter()
+                    .filter_map(|item| {
+                        let text = |path: &str| item.pointer(path).and_then(json::Value::to_text);
+                        Some(Diagnostic {
+                            line: position(Some(&text(line)?)),
+                            column: position(text(column).as_deref()),
+                            severity: text(severity)
+                                .map_or(Severity::Warning, |s| Severity::parse(&s)),
+                            message: text(message).unwrap_or_default(),
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+type Builtin = (&'static [&'static str], &'static [FileType], &'static str, &'static str);
+
+fn builtin(name: &str) -> Option<Builtin> {
+    Some(match name {
+        "ruff" => (
+            &["ruff", "check", "--quiet", "--output-format=concise", "{file}"],
+            &[FileType::Python],
+            "regex",
+            r"^.+?:(?P<line>\d+):(?P<column>\d+): (?P<message>.*)$",
+        ),
+        "eslint" => (
+            &["eslint", "--format", "json", "{file}"],
+            &[FileType::JavaScript, FileType::TypeScript],
+            "json",
+            "",
+        ),
+        "shellcheck" => {
+            (&["shellcheck", "--format", "json1", "{file}"], &[FileType::Shell], "json", "")
+        }
+        "yamllint" => (
+            &["yamllint", "--format", "parsable", "{file}"],
+            &[FileType::YAML],
+            "regex",
+            r"^.+?:(?P<line>\d+):(?P<column>\d+): \[(?P<severity>\w+)\] (?P<message>.*)$",
+        ),
+        _ => return None,
+    })
+}
+
+/// The JSON paths of the built-in linters, where they differ from the defaults.
+fn json_default(name: &str, key: &str) -> Option<&'static str> {
+    Some(match (name, key) {
+        ("eslint", "items") => "0/messages",
+        ("shellcheck", "items") => "comments",
+        ("shellcheck", "severity") => "level",
+        _ => return None,
+    })
+}
+
+/// The diagnostics of each linter that ran on a file.
+type LintResult = (PathBuf, Vec<(String, Vec<Diagnostic>)>);
+
+struct PendingRun {
+    /// The linters that apply to the file.
+    linters: Arc<[Linter]>,
+    due: Instant,
+}
+
+/// Schedules linter runs in the background, at most one at a time per file.
+pub struct LinterRunner {
+    pending: HashMap<PathBuf, PendingRun>,
+    running: Vec<PathBuf>,
+    sender: mpsc::Sender<LintResult>,
+    receiver: mpsc::Receiver<LintResult>,
+    /// The linters that apply to the files of a directory and type, as of `cache_generation`.
+    /// Saves of the same file don't need to look for its project config every time.
+    cache: HashMap<(PathBuf, FileType), Arc<[Linter]>>,
+    /// The [`TrustStore::generation()`] the `cache` belongs to.
+    cache_generation: u32,
+}
+
+impl Default for LinterRunner {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            pending: HashMap::new(),
+            running: Vec::new(),
+            sender,
+            receiver,
+            cache: HashMap::new(),
+            cache_generation: 0,
+        }
+    }
+}
+
+impl LinterRunner {
+    /// Requests a run of the linters for the given file, e.g. after it was saved. They're
+    /// defined by `trust`'s [`TrustStore::command_config()`]. Returns the workspace root
+    /// if its commands await the user's decision, like `command_config()` does.
+    pub fn schedule(
+        &mut self,
+        trust: &TrustStore,
+        path: &Path,
+        file_type: FileType,
+    ) -> Option<PathBuf> {
+        if self.cache_generation != trust.generation() {
+            self.cache.clear();
+            self.cache_generation = trust.generation();
+        }
+
+        let dir = path.parent().unwrap_or(path).to_path_buf();
+        let mut undecided = None;
+        let linters = match self.cache.get(&(dir.clone(), file_type)) {
+            Some(linters) => linters.clone(),
+            None => {
+                let config;
+                (config, undecided) = trust.command_config(path);
+                let linters: Arc<[Linter]> = Linter::load_all(&config)
+                    .into_iter()
+                    .filter(|l| l.applies_to(file_type))
+                    .collect();
+                // Once the user decides, the generation changes anyway.
+                if undecided.is_none() {
+                    self.cache.insert((dir, file_type), linters.clone());
+                }
+                linters
+            }
+        };
+
+        if !linters.is_empty() {
+            self.pending
+                .insert(path.to_path_buf(), PendingRun { linters, due: Instant::now() + DEBOUNCE });
+        }
+        undecided
+    }
+
+    /// Forgets the cached linters, e.g. because a project config changed.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// How long the main loop may wait for input before [`LinterRunner::poll()`] needs to be called.
+    pub fn read_timeout(&self) -> Duration {
+        if self.pending.is_empty() && self.running.is_empty() {
+            Duration::MAX
+        } else {
+            POLL_INTERVAL
+        }
+    }
+
+    /// Starts the runs that are due and returns the results of finished ones,
+    /// as a list of diagnostics per linter for each file.
+    pub fn poll(&mut self) -> Vec<LintResult> {
+        let mut finished = Vec::new();
+        while let Ok(result) = self.receiver.try_recv() {
+            self.running.retain(|p| *p != result.0);
+            finished.push(result);
+        }
+
+        let now = Instant::now();
+        let due: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(path, run)| run.due <= now && !self.running.contains(path))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        for path in due {
+            let Some(run) = self.pending.remove(&path) else {
+                continue;
+            };
+            let sender = self.sender.clone();
+            self.running.push(path.clone());
+
+            thread::spawn(move || {
+                let results = run.linters.iter().map(|l| (l.name.clone(), l.run(&path))).collect();
+                _ = sender.send((path, results));
+            });
+        }
+
+        finished
+    }
+}
+
+/// Schedules runs for freshly saved documents and applies the results of finished runs.
+pub fn update(state: &mut State) {
+    for (path, file_type) in state.documents.take_lint_requests() {
+        if path.ends_with(Path::new(".edit").join("config.toml")) {
+            state.linters.clear_cache();
+        }
+        let undecided = state.linters.schedule(&state.trust, &path, file_type);
+        if undecided.is_some() {
+            state.wants_trust_prompt = undecided;
+        }
+    }
+
+    for (path, results) in state.linters.poll() {
+        for doc in state.documents.all_documents() {
+            if doc.path.as_deref() == Some(path.as_path()) {
+                let mut tb = doc.buffer.borrow_mut();
+                for (name, diagnostics) in &results {
+                    tb.set_diagnostics(name, diagnostics.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        let config = Config::parse(
+            r#"
+            [linter.ruff]
+            enabled = true
+            [linter.shellcheck]
+            enabled = true
+            [linter.mylint]
+            command = ["mylint", "{file}"]
+            filetypes = ["rust"]
+            format = "json"
+            items = "results"
+            message = "text/short"
+            "#,
+        );
+        let linters = Linter::load_all(&config);
+        let linter = |name: &str| linters.iter().find(|l| l.name == name).unwrap();
+        assert!(!linters.iter().any(|l| l.name == "eslint"));
+
+        let diagnostics =
+            linter("ruff").parse("a.py:3:5: F401 [*] `os` imported but unused\nFound 1 error.\n");
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                line: 2,
+                column: 4,
+                severity: Severity::Warning,
+                message: "F401 [*] `os` imported but unused".to_string()
+            }]
+        );
+
+        let diagnostics = linter("shellcheck").parse(
+            r#"{"comments":[{"file":"a.sh","line":2,"column":1,"level":"error","code":1,"message":"Bad"}]}"#,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].severity), (1, Severity::Error));
+
+        let mylint = linter("mylint");
+        assert!(mylint.applies_to(FileType::Rust));
+        let diagnostics = mylint.parse(r#"{"results":[{"line":7,"text":{"short":"hm"}}]}"#);
+        assert_eq!((diagnostics[0].line, diagnostics[0].message.as_str()), (6, "hm"));
+    }
+
+    #[test]
+    fn test_schedule_caches_linters() {
+        let mut trust = TrustStore::default();
+        let mut runner = LinterRunner::default();
+        let dir = Path::new("/nonexistent");
+
+        assert_eq!(runner.schedule(&trust, &dir.join("a.sh"), FileType::Shell), None);
+        assert_eq!(runner.schedule(&trust, &dir.join("b.sh"), FileType::Shell), None);
+        assert_eq!(runner.schedule(&trust, &dir.join("c.py"), FileType::Python), None);
+        assert_eq!(runner.cache.len(), 2);
+        // The built-in linters aren't enabled.
+        assert!(runner.pending.is_empty());
+
+        trust.decide(dir, false, false).unwrap();
+        runner.schedule(&trust, &dir.join("a.sh"), FileType::Shell);
+        assert_eq!(runner.cache.len(), 1);
+        runner.clear_cache();
+        assert!(runner.cache.is_empty());
+    }
+}
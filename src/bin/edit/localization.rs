@@ -16,16 +16,26 @@ pub enum LocId {
     No,
     Cancel,
     Always,
+    WizardBack,
+    WizardNext,
+    WizardFinish,
 
     // File menu
     File,
     FileNew,
+    FileNewScratch,
     FileOpen,
+    FileOpenFromClipboard,
     FileSave,
     FileSaveAs,
+    FilePreviewSave,
+    FileExportHtml,
     FileClose,
+    FileRunLastTask,
+    FileTaskHistory,
     FileExit,
     FileGoto,
+    FileAlternate,
 
     // Edit menu
     Edit,
@@ -34,19 +44,79 @@ pub enum LocId {
     EditCut,
     EditCopy,
     EditPaste,
+    EditClipboardHistory,
+    ClipboardHistoryGoToSource,
+    ClipboardHistoryEmpty,
+    ConfigCompletions,
     EditFind,
     EditReplace,
+    EditFindLines,
+    EditFindInFiles,
+    EditJump,
+    EditMatchingTag,
     EditSelectAll,
+    EditReindent,
+    EditFixWhitespace,
+    EditRecordMacro,
+    EditStopRecording,
+    EditPlayMacro,
+    EditMacros,
+    EditNote,
+    EditNotes,
+    EditSurround,
+    EditConvertCase,
+    EditToggleLineComment,
+    EditToggleBlockComment,
+    EditDuplicate,
+    NoteDialogTitle,
+    NoteDialogHint,
+    MacroRecording,
+    MacroParameters,
 
     // View menu
     View,
     ViewFocusStatusbar,
     ViewWordWrap,
+    ViewRenderWhitespace,
+    ViewAnsiColors,
+    ViewReadOnly,
+    ViewLargeFile,
+    GeneratedFileBanner,
+    GeneratedFileEditAnyway,
+    ViewHexView,
     ViewDocumentPicker,
+    ViewWorkspaceTrust,
+    ViewCommandLine,
 
     // Help menu
     Help,
     HelpAbout,
+    HelpContents,
+    HelpKeyboardShortcuts,
+
+    // Keyboard shortcuts cheat sheet
+    KeymapNavigation,
+    KeymapWord,
+    KeymapSubword,
+    KeymapCursorAboveBelow,
+    KeymapCursorNextMatch,
+    KeymapBlockSelection,
+    KeymapMoveLines,
+    KeymapJumpBack,
+    KeymapJumpForward,
+    KeymapToggleFold,
+    KeymapTabs,
+    KeymapNextTab,
+    KeymapPreviousTab,
+    KeymapGoToTab,
+    KeymapPager,
+    KeymapFindNext,
+    KeymapPageDownUp,
+    KeymapLineDownUp,
+    KeymapStartEnd,
+    KeymapFollow,
+    KeymapFollowLink,
+    KeymapBack,
 
     // Exit dialog
     UnsavedChangesDialogTitle,
@@ -63,6 +133,30 @@ pub enum LocId {
     LargeClipboardWarningLine2,
     LargeClipboardWarningLine3,
     SuperLargeClipboardWarning,
+    LargeClipboardTruncate,
+    FileChangedDialogTitle,
+    FileChangedDialogDescription,
+    FileChangedDialogUnsaved,
+    FileChangedDialogReload,
+    FileChangedDialogKeep,
+    FileChangedDialogDiff,
+    SwapRecoverTitle,
+    SwapRecoverDescription,
+    SwapRecover,
+    SwapDiscard,
+    SwapLater,
+    SwapInUseTitle,
+    SwapInUseDescription,
+    SwapReadOnly,
+    SwapEditAnyway,
+    StagedUnsavedTitle,
+    StagedUnsavedDescription,
+    StagedSaveAndStage,
+    StagedSave,
+    StagedIgnore,
+    SudoDialogTitle,
+    SudoDialogDescription,
+    SudoDialogSave,
 
     // Warning dialog
     WarningDialogTitle,
@@ -70,17 +164,38 @@ pub enum LocId {
     // Error dialog
     ErrorDialogTitle,
     ErrorIcuMissing,
+    ErrorHexInvalid,
+    ErrorHelpNotFound,
+    ErrorNoClipboardLocations,
+    ErrorMacroNotFound,
+    ErrorRegisterEmpty,
+    ErrorSurroundingNotFound,
+    ErrorSudoNotFound,
+    ErrorSudoFailed,
+    ErrorRemoteFailed,
+    ErrorStageFailed,
 
     SearchNeedleLabel,
+    LineSearchLabel,
+    ProjectSearchLabel,
+    JumpStatusLabel,
+    PagerFollowing,
+    TaskRunning,
+    SafeMode,
     SearchReplacementLabel,
     SearchMatchCase,
     SearchWholeWord,
     SearchUseRegex,
     SearchReplaceAll,
     SearchClose,
+    SearchReplaceConfirm,
+    SearchReplaceInTabs,
+    SearchReplaceInTabsDescription,
 
     EncodingReopen,
     EncodingConvert,
+    EncodingAddBom,
+    EncodingRemoveBom,
 
     IndentationTabs,
     IndentationSpaces,
@@ -90,6 +205,14 @@ pub enum LocId {
 
     FileOverwriteWarning,
     FileOverwriteWarningDescription,
+    TrustDialogTitle,
+    TrustDialogDescription,
+    TrustDialogTrust,
+    TrustDialogRestricted,
+    WorkspaceTrustTrusted,
+    WorkspaceTrustRestricted,
+    WorkspaceTrustRevoke,
+    WorkspaceTrustEmpty,
 
     Count,
 }
@@ -116,7 +239,7 @@ enum LangId {
 }
 
 #[rustfmt::skip]
-const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
+static S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
     // Ctrl (the keyboard key)
     [
         /* en      */ "Ctrl",
@@ -230,6 +353,48 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "总是",
         /* zh_hant */ "總是",
     ],
+    // WizardBack
+    [
+        /* en      */ "Back",
+        /* de      */ "Zurück",
+        /* es      */ "Atrás",
+        /* fr      */ "Précédent",
+        /* it      */ "Indietro",
+        /* ja      */ "戻る",
+        /* ko      */ "뒤로",
+        /* pt_br   */ "Voltar",
+        /* ru      */ "Назад",
+        /* zh_hans */ "上一步",
+        /* zh_hant */ "上一步",
+    ],
+    // WizardNext
+    [
+        /* en      */ "Next",
+        /* de      */ "Weiter",
+        /* es      */ "Siguiente",
+        /* fr      */ "Suivant",
+        /* it      */ "Avanti",
+        /* ja      */ "次へ",
+        /* ko      */ "다음",
+        /* pt_br   */ "Avançar",
+        /* ru      */ "Далее",
+        /* zh_hans */ "下一步",
+        /* zh_hant */ "下一步",
+    ],
+    // WizardFinish
+    [
+        /* en      */ "Finish",
+        /* de      */ "Fertig stellen",
+        /* es      */ "Finalizar",
+        /* fr      */ "Terminer",
+        /* it      */ "Fine",
+        /* ja      */ "完了",
+        /* ko      */ "마침",
+        /* pt_br   */ "Concluir",
+        /* ru      */ "Готово",
+        /* zh_hans */ "完成",
+        /* zh_hant */ "完成",
+    ],
 
     // File (a menu bar item)
     [
@@ -259,6 +424,20 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "新建文件",
         /* zh_hant */ "新增檔案",
     ],
+    // FileNewScratch
+    [
+        /* en      */ "New Scratch",
+        /* de      */ "Neues Notizblatt",
+        /* es      */ "Nuevo borrador",
+        /* fr      */ "Nouveau brouillon",
+        /* it      */ "Nuovo foglio temporaneo",
+        /* ja      */ "新しいスクラッチ",
+        /* ko      */ "새 스크래치",
+        /* pt_br   */ "Novo rascunho",
+        /* ru      */ "Новый черновик",
+        /* zh_hans */ "新建草稿",
+        /* zh_hant */ "新增草稿",
+    ],
     // FileOpen
     [
         /* en      */ "Open File…",
@@ -273,6 +452,20 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "打开文件…",
         /* zh_hant */ "開啟檔案…",
     ],
+    // FileOpenFromClipboard
+    [
+        /* en      */ "Open from Clipboard",
+        /* de      */ "Aus Zwischenablage öffnen",
+        /* es      */ "Abrir desde el portapapeles",
+        /* fr      */ "Ouvrir depuis le presse-papiers",
+        /* it      */ "Apri dagli appunti",
+        /* ja      */ "クリップボードから開く",
+        /* ko      */ "클립보드에서 열기",
+        /* pt_br   */ "Abrir da área de transferência",
+        /* ru      */ "Открыть из буфера обмена",
+        /* zh_hans */ "从剪贴板打开",
+        /* zh_hant */ "從剪貼簿開啟",
+    ],
     // FileSave
     [
         /* en      */ "Save",
@@ -301,6 +494,34 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "另存为…",
         /* zh_hant */ "另存新檔…",
     ],
+    // FilePreviewSave
+    [
+        /* en      */ "Preview Save Changes",
+        /* de      */ "Vorschau der Änderungen beim Speichern",
+        /* es      */ "Vista previa de los cambios al guardar",
+        /* fr      */ "Aperçu des modifications à l'enregistrement",
+        /* it      */ "Anteprima delle modifiche al salvataggio",
+        /* ja      */ "保存時の変更をプレビュー",
+        /* ko      */ "저장 시 변경 사항 미리 보기",
+        /* pt_br   */ "Visualizar alterações ao salvar",
+        /* ru      */ "Предпросмотр изменений при сохранении",
+        /* zh_hans */ "预览保存时的更改",
+        /* zh_hant */ "預覽儲存時的變更",
+    ],
+    // FileExportHtml
+    [
+        /* en      */ "Export as HTML",
+        /* de      */ "Als HTML exportieren",
+        /* es      */ "Exportar como HTML",
+        /* fr      */ "Exporter en HTML",
+        /* it      */ "Esporta come HTML",
+        /* ja      */ "HTML としてエクスポート",
+        /* ko      */ "HTML로 내보내기",
+        /* pt_br   */ "Exportar como HTML",
+        /* ru      */ "Экспорт в HTML",
+        /* zh_hans */ "导出为 HTML",
+        /* zh_hant */ "匯出為 HTML",
+    ],
     // FileClose
     [
         /* en      */ "Close Editor",
@@ -315,6 +536,34 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "关闭编辑器",
         /* zh_hant */ "關閉編輯器",
     ],
+    // FileRunLastTask
+    [
+        /* en      */ "Run Last Task",
+        /* de      */ "Letzte Aufgabe ausführen",
+        /* es      */ "Ejecutar última tarea",
+        /* fr      */ "Relancer la dernière tâche",
+        /* it      */ "Esegui ultima attività",
+        /* ja      */ "最後のタスクを実行",
+        /* ko      */ "마지막 작업 실행",
+        /* pt_br   */ "Executar última tarefa",
+        /* ru      */ "Запустить последнюю задачу",
+        /* zh_hans */ "运行上一个任务",
+        /* zh_hant */ "執行上一個工作",
+    ],
+    // FileTaskHistory
+    [
+        /* en      */ "Task History…",
+        /* de      */ "Aufgabenverlauf…",
+        /* es      */ "Historial de tareas…",
+        /* fr      */ "Historique des tâches…",
+        /* it      */ "Cronologia attività…",
+        /* ja      */ "タスク履歴…",
+        /* ko      */ "작업 기록…",
+        /* pt_br   */ "Histórico de tarefas…",
+        /* ru      */ "История задач…",
+        /* zh_hans */ "任务历史记录…",
+        /* zh_hant */ "工作歷程記錄…",
+    ],
     // FileExit
     [
         /* en      */ "Exit",
@@ -343,6 +592,20 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "转到行/列…",
         /* zh_hant */ "跳至行/列…",
     ],
+    // FileAlternate
+    [
+        /* en      */ "Go to Source/Test File",
+        /* de      */ "Zu Quell-/Testdatei wechseln",
+        /* es      */ "Ir al archivo de código/prueba",
+        /* fr      */ "Aller au fichier source/de test",
+        /* it      */ "Vai al file sorgente/di test",
+        /* ja      */ "ソース/テスト ファイルに移動",
+        /* ko      */ "소스/테스트 파일로 이동",
+        /* pt_br   */ "Ir para arquivo de código/teste",
+        /* ru      */ "Перейти к исходному/тестовому файлу",
+        /* zh_hans */ "转到源文件/测试文件",
+        /* zh_hant */ "移至原始檔/測試檔",
+    ],
 
     // Edit (a menu bar item)
     [
@@ -428,6 +691,62 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "粘贴",
         /* zh_hant */ "貼上",
     ],
+    // EditClipboardHistory
+    [
+        /* en      */ "Clipboard History…",
+        /* de      */ "Zwischenablageverlauf…",
+        /* es      */ "Historial del portapapeles…",
+        /* fr      */ "Historique du presse-papiers…",
+        /* it      */ "Cronologia appunti…",
+        /* ja      */ "クリップボードの履歴…",
+        /* ko      */ "클립보드 기록…",
+        /* pt_br   */ "Histórico da área de transferência…",
+        /* ru      */ "Журнал буфера обмена…",
+        /* zh_hans */ "剪贴板历史记录…",
+        /* zh_hant */ "剪貼簿歷程記錄…",
+    ],
+    // ClipboardHistoryGoToSource
+    [
+        /* en      */ "Go to Source",
+        /* de      */ "Zur Quelle",
+        /* es      */ "Ir al origen",
+        /* fr      */ "Aller à la source",
+        /* it      */ "Vai all'origine",
+        /* ja      */ "コピー元へ移動",
+        /* ko      */ "원본으로 이동",
+        /* pt_br   */ "Ir para a origem",
+        /* ru      */ "Перейти к источнику",
+        /* zh_hans */ "转到来源",
+        /* zh_hant */ "移至來源",
+    ],
+    // ClipboardHistoryEmpty
+    [
+        /* en      */ "Nothing was copied yet.",
+        /* de      */ "Noch nichts kopiert.",
+        /* es      */ "Todavía no se ha copiado nada.",
+        /* fr      */ "Rien n'a encore été copié.",
+        /* it      */ "Non è stato ancora copiato nulla.",
+        /* ja      */ "まだ何もコピーされていません。",
+        /* ko      */ "아직 복사한 항목이 없습니다.",
+        /* pt_br   */ "Nada foi copiado ainda.",
+        /* ru      */ "Ещё ничего не скопировано.",
+        /* zh_hans */ "尚未复制任何内容。",
+        /* zh_hant */ "尚未複製任何內容。",
+    ],
+    // ConfigCompletions
+    [
+        /* en      */ "Completions",
+        /* de      */ "Vervollständigungen",
+        /* es      */ "Sugerencias",
+        /* fr      */ "Suggestions",
+        /* it      */ "Suggerimenti",
+        /* ja      */ "補完候補",
+        /* ko      */ "완성 후보",
+        /* pt_br   */ "Sugestões",
+        /* ru      */ "Варианты дополнения",
+        /* zh_hans */ "补全建议",
+        /* zh_hant */ "完成建議",
+    ],
     // EditFind
     [
         /* en      */ "Find",
@@ -456,6 +775,62 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "替换",
         /* zh_hant */ "取代",
     ],
+    // EditFindLines
+    [
+        /* en      */ "Find Lines…",
+        /* de      */ "Zeilen suchen…",
+        /* es      */ "Buscar líneas…",
+        /* fr      */ "Rechercher des lignes…",
+        /* it      */ "Trova righe…",
+        /* ja      */ "行を検索…",
+        /* ko      */ "줄 찾기…",
+        /* pt_br   */ "Localizar linhas…",
+        /* ru      */ "Найти строки…",
+        /* zh_hans */ "查找行…",
+        /* zh_hant */ "尋找行…",
+    ],
+    // EditFindInFiles
+    [
+        /* en      */ "Find in Files",
+        /* de      */ "In Dateien suchen",
+        /* es      */ "Buscar en archivos",
+        /* fr      */ "Rechercher dans les fichiers",
+        /* it      */ "Trova nei file",
+        /* ja      */ "ファイル内を検索",
+        /* ko      */ "파일에서 찾기",
+        /* pt_br   */ "Localizar nos arquivos",
+        /* ru      */ "Найти в файлах",
+        /* zh_hans */ "在文件中查找",
+        /* zh_hant */ "在檔案中尋找",
+    ],
+    // EditJump
+    [
+        /* en      */ "Jump to Characters",
+        /* de      */ "Zu Zeichen springen",
+        /* es      */ "Saltar a caracteres",
+        /* fr      */ "Aller aux caractères",
+        /* it      */ "Salta ai caratteri",
+        /* ja      */ "文字へジャンプ",
+        /* ko      */ "문자로 이동",
+        /* pt_br   */ "Saltar para caracteres",
+        /* ru      */ "Перейти к символам",
+        /* zh_hans */ "跳转到字符",
+        /* zh_hant */ "跳至字元",
+    ],
+    // EditMatchingTag
+    [
+        /* en      */ "Go to Matching Tag",
+        /* de      */ "Zum passenden Tag",
+        /* es      */ "Ir a la etiqueta correspondiente",
+        /* fr      */ "Aller à la balise correspondante",
+        /* it      */ "Vai al tag corrispondente",
+        /* ja      */ "対応するタグへ移動",
+        /* ko      */ "일치하는 태그로 이동",
+        /* pt_br   */ "Ir para a tag correspondente",
+        /* ru      */ "К парному тегу",
+        /* zh_hans */ "转到匹配的标记",
+        /* zh_hant */ "移至對應的標記",
+    ],
     // EditSelectAll
     [
         /* en      */ "Select All",
@@ -470,6 +845,244 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "全选",
         /* zh_hant */ "全選"
     ],
+    // EditReindent
+    [
+        /* en      */ "Re-indent",
+        /* de      */ "Neu einrücken",
+        /* es      */ "Volver a sangrar",
+        /* fr      */ "Réindenter",
+        /* it      */ "Reindenta",
+        /* ja      */ "インデントを再調整",
+        /* ko      */ "들여쓰기 다시 적용",
+        /* pt_br   */ "Reindentar",
+        /* ru      */ "Переформатировать отступы",
+        /* zh_hans */ "重新缩进",
+        /* zh_hant */ "重新縮排"
+    ],
+    // EditFixWhitespace
+    [
+        /* en      */ "Fix Whitespace",
+        /* de      */ "Leerraum korrigieren",
+        /* es      */ "Corregir espacios en blanco",
+        /* fr      */ "Corriger les espaces",
+        /* it      */ "Correggi spazi vuoti",
+        /* ja      */ "空白を修正",
+        /* ko      */ "공백 수정",
+        /* pt_br   */ "Corrigir espaços em branco",
+        /* ru      */ "Исправить пробелы",
+        /* zh_hans */ "修正空白",
+        /* zh_hant */ "修正空白"
+    ],
+    // EditRecordMacro
+    [
+        /* en      */ "Record Macro",
+        /* de      */ "Makro aufzeichnen",
+        /* es      */ "Grabar macro",
+        /* fr      */ "Enregistrer une macro",
+        /* it      */ "Registra macro",
+        /* ja      */ "マクロの記録",
+        /* ko      */ "매크로 기록",
+        /* pt_br   */ "Gravar macro",
+        /* ru      */ "Записать макрос",
+        /* zh_hans */ "录制宏",
+        /* zh_hant */ "錄製巨集",
+    ],
+    // EditStopRecording
+    [
+        /* en      */ "Stop Recording",
+        /* de      */ "Aufzeichnung beenden",
+        /* es      */ "Detener grabación",
+        /* fr      */ "Arrêter l'enregistrement",
+        /* it      */ "Interrompi registrazione",
+        /* ja      */ "記録の停止",
+        /* ko      */ "기록 중지",
+        /* pt_br   */ "Parar gravação",
+        /* ru      */ "Остановить запись",
+        /* zh_hans */ "停止录制",
+        /* zh_hant */ "停止錄製",
+    ],
+    // EditPlayMacro
+    [
+        /* en      */ "Play Macro",
+        /* de      */ "Makro abspielen",
+        /* es      */ "Reproducir macro",
+        /* fr      */ "Exécuter la macro",
+        /* it      */ "Esegui macro",
+        /* ja      */ "マクロの再生",
+        /* ko      */ "매크로 재생",
+        /* pt_br   */ "Reproduzir macro",
+        /* ru      */ "Воспроизвести макрос",
+        /* zh_hans */ "播放宏",
+        /* zh_hant */ "播放巨集",
+    ],
+    // EditMacros
+    [
+        /* en      */ "Macros…",
+        /* de      */ "Makros…",
+        /* es      */ "Macros…",
+        /* fr      */ "Macros…",
+        /* it      */ "Macro…",
+        /* ja      */ "マクロ…",
+        /* ko      */ "매크로…",
+        /* pt_br   */ "Macros…",
+        /* ru      */ "Макросы…",
+        /* zh_hans */ "宏…",
+        /* zh_hant */ "巨集…",
+    ],
+    // EditNote
+    [
+        /* en      */ "Add or Edit Note…",
+        /* de      */ "Notiz hinzufügen oder bearbeiten…",
+        /* es      */ "Añadir o editar nota…",
+        /* fr      */ "Ajouter ou modifier une note…",
+        /* it      */ "Aggiungi o modifica nota…",
+        /* ja      */ "メモを追加/編集…",
+        /* ko      */ "메모 추가/편집…",
+        /* pt_br   */ "Adicionar ou editar nota…",
+        /* ru      */ "Добавить или изменить заметку…",
+        /* zh_hans */ "添加或编辑备注…",
+        /* zh_hant */ "新增或編輯備註…",
+    ],
+    // EditNotes
+    [
+        /* en      */ "Notes…",
+        /* de      */ "Notizen…",
+        /* es      */ "Notas…",
+        /* fr      */ "Notes…",
+        /* it      */ "Note…",
+        /* ja      */ "メモ…",
+        /* ko      */ "메모…",
+        /* pt_br   */ "Notas…",
+        /* ru      */ "Заметки…",
+        /* zh_hans */ "备注…",
+        /* zh_hant */ "備註…",
+    ],
+    // EditSurround
+    [
+        /* en      */ "Surround With…",
+        /* de      */ "Umschließen mit…",
+        /* es      */ "Rodear con…",
+        /* fr      */ "Entourer de…",
+        /* it      */ "Racchiudi con…",
+        /* ja      */ "囲む…",
+        /* ko      */ "둘러싸기…",
+        /* pt_br   */ "Envolver com…",
+        /* ru      */ "Обрамить…",
+        /* zh_hans */ "包围…",
+        /* zh_hant */ "包圍…",
+    ],
+    // EditConvertCase
+    [
+        /* en      */ "Convert Case…",
+        /* de      */ "Groß-/Kleinschreibung ändern…",
+        /* es      */ "Convertir mayúsculas/minúsculas…",
+        /* fr      */ "Changer la casse…",
+        /* it      */ "Converti maiuscole/minuscole…",
+        /* ja      */ "大文字/小文字の変換…",
+        /* ko      */ "대/소문자 변환…",
+        /* pt_br   */ "Converter maiúsculas/minúsculas…",
+        /* ru      */ "Изменить регистр…",
+        /* zh_hans */ "转换大小写…",
+        /* zh_hant */ "轉換大小寫…",
+    ],
+    // EditToggleLineComment
+    [
+        /* en      */ "Toggle Line Comment",
+        /* de      */ "Zeilenkommentar umschalten",
+        /* es      */ "Alternar comentario de línea",
+        /* fr      */ "Basculer le commentaire de ligne",
+        /* it      */ "Attiva/disattiva commento di riga",
+        /* ja      */ "行コメントの切り替え",
+        /* ko      */ "줄 주석 전환",
+        /* pt_br   */ "Alternar comentário de linha",
+        /* ru      */ "Переключить строчный комментарий",
+        /* zh_hans */ "切换行注释",
+        /* zh_hant */ "切換行註解",
+    ],
+    // EditToggleBlockComment
+    [
+        /* en      */ "Toggle Block Comment",
+        /* de      */ "Blockkommentar umschalten",
+        /* es      */ "Alternar comentario de bloque",
+        /* fr      */ "Basculer le commentaire de bloc",
+        /* it      */ "Attiva/disattiva commento di blocco",
+        /* ja      */ "ブロックコメントの切り替え",
+        /* ko      */ "블록 주석 전환",
+        /* pt_br   */ "Alternar comentário de bloco",
+        /* ru      */ "Переключить блочный комментарий",
+        /* zh_hans */ "切换块注释",
+        /* zh_hant */ "切換區塊註解",
+    ],
+    // EditDuplicate
+    [
+        /* en      */ "Duplicate Selection or Line",
+        /* de      */ "Auswahl oder Zeile duplizieren",
+        /* es      */ "Duplicar selección o línea",
+        /* fr      */ "Dupliquer la sélection ou la ligne",
+        /* it      */ "Duplica selezione o riga",
+        /* ja      */ "選択範囲または行を複製",
+        /* ko      */ "선택 영역 또는 줄 복제",
+        /* pt_br   */ "Duplicar seleção ou linha",
+        /* ru      */ "Дублировать выделение или строку",
+        /* zh_hans */ "复制所选内容或行",
+        /* zh_hant */ "複製選取範圍或行",
+    ],
+    // NoteDialogTitle
+    [
+        /* en      */ "Note",
+        /* de      */ "Notiz",
+        /* es      */ "Nota",
+        /* fr      */ "Note",
+        /* it      */ "Nota",
+        /* ja      */ "メモ",
+        /* ko      */ "메모",
+        /* pt_br   */ "Nota",
+        /* ru      */ "Заметка",
+        /* zh_hans */ "备注",
+        /* zh_hant */ "備註",
+    ],
+    // NoteDialogHint
+    [
+        /* en      */ "Enter saves it, an empty note is deleted.",
+        /* de      */ "Enter speichert, eine leere Notiz wird gelöscht.",
+        /* es      */ "Intro la guarda, una nota vacía se elimina.",
+        /* fr      */ "Entrée l'enregistre, une note vide est supprimée.",
+        /* it      */ "Invio la salva, una nota vuota viene eliminata.",
+        /* ja      */ "Enter で保存、空のメモは削除されます。",
+        /* ko      */ "Enter로 저장, 빈 메모는 삭제됩니다.",
+        /* pt_br   */ "Enter salva, uma nota vazia é excluída.",
+        /* ru      */ "Enter сохраняет, пустая заметка удаляется.",
+        /* zh_hans */ "按 Enter 保存，空备注将被删除。",
+        /* zh_hant */ "按 Enter 儲存，空白備註將被刪除。",
+    ],
+    // MacroRecording
+    [
+        /* en      */ "Recording",
+        /* de      */ "Aufzeichnung",
+        /* es      */ "Grabando",
+        /* fr      */ "Enregistrement",
+        /* it      */ "Registrazione",
+        /* ja      */ "記録中",
+        /* ko      */ "기록 중",
+        /* pt_br   */ "Gravando",
+        /* ru      */ "Запись",
+        /* zh_hans */ "正在录制",
+        /* zh_hant */ "正在錄製",
+    ],
+    // MacroParameters
+    [
+        /* en      */ "Macro Parameters",
+        /* de      */ "Makroparameter",
+        /* es      */ "Parámetros de la macro",
+        /* fr      */ "Paramètres de la macro",
+        /* it      */ "Parametri della macro",
+        /* ja      */ "マクロのパラメーター",
+        /* ko      */ "매크로 매개 변수",
+        /* pt_br   */ "Parâmetros da macro",
+        /* ru      */ "Параметры макроса",
+        /* zh_hans */ "宏参数",
+        /* zh_hant */ "巨集參數",
+    ],
 
     // View (a menu bar item)
     [
@@ -513,6 +1126,104 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "自动换行",
         /* zh_hant */ "自動換行",
     ],
+    // ViewRenderWhitespace
+    [
+        /* en      */ "Render Whitespace",
+        /* de      */ "Leerzeichen anzeigen",
+        /* es      */ "Mostrar espacios en blanco",
+        /* fr      */ "Afficher les espaces",
+        /* it      */ "Mostra spazi",
+        /* ja      */ "空白文字を表示",
+        /* ko      */ "공백 표시",
+        /* pt_br   */ "Mostrar espaços em branco",
+        /* ru      */ "Показывать пробелы",
+        /* zh_hans */ "显示空白字符",
+        /* zh_hant */ "顯示空白字元",
+    ],
+    // ViewAnsiColors
+    [
+        /* en      */ "ANSI Colors",
+        /* de      */ "ANSI-Farben",
+        /* es      */ "Colores ANSI",
+        /* fr      */ "Couleurs ANSI",
+        /* it      */ "Colori ANSI",
+        /* ja      */ "ANSI カラー",
+        /* ko      */ "ANSI 색",
+        /* pt_br   */ "Cores ANSI",
+        /* ru      */ "Цвета ANSI",
+        /* zh_hans */ "ANSI 颜色",
+        /* zh_hant */ "ANSI 色彩",
+    ],
+    // ViewReadOnly
+    [
+        /* en      */ "Read-Only",
+        /* de      */ "Schreibgeschützt",
+        /* es      */ "Solo lectura",
+        /* fr      */ "Lecture seule",
+        /* it      */ "Sola lettura",
+        /* ja      */ "読み取り専用",
+        /* ko      */ "읽기 전용",
+        /* pt_br   */ "Somente leitura",
+        /* ru      */ "Только чтение",
+        /* zh_hans */ "只读",
+        /* zh_hant */ "唯讀",
+    ],
+    // ViewLargeFile
+    [
+        /* en      */ "Large File",
+        /* de      */ "Große Datei",
+        /* es      */ "Archivo grande",
+        /* fr      */ "Gros fichier",
+        /* it      */ "File grande",
+        /* ja      */ "大きなファイル",
+        /* ko      */ "대용량 파일",
+        /* pt_br   */ "Arquivo grande",
+        /* ru      */ "Большой файл",
+        /* zh_hans */ "大文件",
+        /* zh_hant */ "大型檔案",
+    ],
+    // GeneratedFileBanner
+    [
+        /* en      */ "This file looks generated. Changes to it would likely be overwritten.",
+        /* de      */ "Diese Datei scheint generiert zu sein. Änderungen würden wahrscheinlich überschrieben.",
+        /* es      */ "Este archivo parece generado. Es probable que los cambios se sobrescriban.",
+        /* fr      */ "Ce fichier semble généré. Les modifications seraient probablement écrasées.",
+        /* it      */ "Questo file sembra generato. Le modifiche verrebbero probabilmente sovrascritte.",
+        /* ja      */ "このファイルは生成されたもののようです。変更は上書きされる可能性があります。",
+        /* ko      */ "이 파일은 생성된 파일로 보입니다. 변경 내용은 덮어쓰일 수 있습니다.",
+        /* pt_br   */ "Este arquivo parece gerado. As alterações provavelmente seriam sobrescritas.",
+        /* ru      */ "Похоже, этот файл сгенерирован. Изменения, скорее всего, будут перезаписаны.",
+        /* zh_hans */ "此文件似乎是生成的。对它的更改可能会被覆盖。",
+        /* zh_hant */ "此檔案似乎是產生的。對它的變更可能會被覆寫。",
+    ],
+    // GeneratedFileEditAnyway
+    [
+        /* en      */ "Edit Anyway",
+        /* de      */ "Trotzdem bearbeiten",
+        /* es      */ "Editar de todos modos",
+        /* fr      */ "Modifier quand même",
+        /* it      */ "Modifica comunque",
+        /* ja      */ "それでも編集",
+        /* ko      */ "그래도 편집",
+        /* pt_br   */ "Editar mesmo assim",
+        /* ru      */ "Всё равно редактировать",
+        /* zh_hans */ "仍然编辑",
+        /* zh_hant */ "仍要編輯",
+    ],
+    // ViewHexView
+    [
+        /* en      */ "Hex View",
+        /* de      */ "Hexansicht",
+        /* es      */ "Vista hexadecimal",
+        /* fr      */ "Vue hexadécimale",
+        /* it      */ "Vista esadecimale",
+        /* ja      */ "16 進表示",
+        /* ko      */ "16진수 보기",
+        /* pt_br   */ "Visualização hexadecimal",
+        /* ru      */ "Шестнадцатеричный вид",
+        /* zh_hans */ "十六进制视图",
+        /* zh_hant */ "十六進位檢視",
+    ],
     // ViewDocumentPicker
     [
         /* en      */ "Document Picker…",
@@ -527,6 +1238,34 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "文档选择器…",
         /* zh_hant */ "文件選擇器…",
     ],
+    // ViewWorkspaceTrust
+    [
+        /* en      */ "Workspace Trust…",
+        /* de      */ "Arbeitsbereich-Vertrauen…",
+        /* es      */ "Confianza del área de trabajo…",
+        /* fr      */ "Confiance de l’espace de travail…",
+        /* it      */ "Attendibilità dell’area di lavoro…",
+        /* ja      */ "ワークスペースの信頼…",
+        /* ko      */ "작업 영역 신뢰…",
+        /* pt_br   */ "Confiança do espaço de trabalho…",
+        /* ru      */ "Доверие к рабочей области…",
+        /* zh_hans */ "工作区信任…",
+        /* zh_hant */ "工作區信任…",
+    ],
+    // ViewCommandLine
+    [
+        /* en      */ "Command Line…",
+        /* de      */ "Befehlszeile…",
+        /* es      */ "Línea de comandos…",
+        /* fr      */ "Ligne de commande…",
+        /* it      */ "Riga di comando…",
+        /* ja      */ "コマンド ライン…",
+        /* ko      */ "명령줄…",
+        /* pt_br   */ "Linha de comando…",
+        /* ru      */ "Командная строка…",
+        /* zh_hans */ "命令行…",
+        /* zh_hant */ "命令列…",
+    ],
 
     // Help (a menu bar item)
     [
@@ -556,6 +1295,342 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "关于",
         /* zh_hant */ "關於",
     ],
+    // HelpContents
+    [
+        /* en      */ "Contents",
+        /* de      */ "Inhalt",
+        /* es      */ "Contenido",
+        /* fr      */ "Sommaire",
+        /* it      */ "Sommario",
+        /* ja      */ "目次",
+        /* ko      */ "목차",
+        /* pt_br   */ "Conteúdo",
+        /* ru      */ "Содержание",
+        /* zh_hans */ "目录",
+        /* zh_hant */ "目錄",
+    ],
+    // HelpKeyboardShortcuts
+    [
+        /* en      */ "Keyboard Shortcuts",
+        /* de      */ "Tastenkombinationen",
+        /* es      */ "Atajos de teclado",
+        /* fr      */ "Raccourcis clavier",
+        /* it      */ "Scorciatoie da tastiera",
+        /* ja      */ "キーボード ショートカット",
+        /* ko      */ "바로 가기 키",
+        /* pt_br   */ "Atalhos de teclado",
+        /* ru      */ "Сочетания клавиш",
+        /* zh_hans */ "键盘快捷方式",
+        /* zh_hant */ "鍵盤快速鍵",
+    ],
+    // KeymapNavigation
+    [
+        /* en      */ "Navigation",
+        /* de      */ "Navigation",
+        /* es      */ "Navegación",
+        /* fr      */ "Navigation",
+        /* it      */ "Navigazione",
+        /* ja      */ "移動",
+        /* ko      */ "탐색",
+        /* pt_br   */ "Navegação",
+        /* ru      */ "Навигация",
+        /* zh_hans */ "导航",
+        /* zh_hant */ "瀏覽",
+    ],
+    // KeymapWord
+    [
+        /* en      */ "Previous / next word",
+        /* de      */ "Vorheriges / nächstes Wort",
+        /* es      */ "Palabra anterior / siguiente",
+        /* fr      */ "Mot précédent / suivant",
+        /* it      */ "Parola precedente / successiva",
+        /* ja      */ "前 / 次の単語",
+        /* ko      */ "이전 / 다음 단어",
+        /* pt_br   */ "Palavra anterior / próxima",
+        /* ru      */ "Предыдущее / следующее слово",
+        /* zh_hans */ "上一个 / 下一个单词",
+        /* zh_hant */ "上一個 / 下一個單字",
+    ],
+    // KeymapSubword
+    [
+        /* en      */ "Previous / next sub-word",
+        /* de      */ "Vorheriger / nächster Wortteil",
+        /* es      */ "Subpalabra anterior / siguiente",
+        /* fr      */ "Sous-mot précédent / suivant",
+        /* it      */ "Sottoparola precedente / successiva",
+        /* ja      */ "前 / 次の単語の一部",
+        /* ko      */ "이전 / 다음 하위 단어",
+        /* pt_br   */ "Subpalavra anterior / próxima",
+        /* ru      */ "Предыдущая / следующая часть слова",
+        /* zh_hans */ "上一个 / 下一个子词",
+        /* zh_hant */ "上一個 / 下一個子字",
+    ],
+    // KeymapCursorAboveBelow
+    [
+        /* en      */ "Add a cursor above / below",
+        /* de      */ "Cursor darüber / darunter hinzufügen",
+        /* es      */ "Agregar cursor arriba / abajo",
+        /* fr      */ "Ajouter un curseur au-dessus / en dessous",
+        /* it      */ "Aggiungi cursore sopra / sotto",
+        /* ja      */ "上 / 下にカーソルを追加",
+        /* ko      */ "위 / 아래에 커서 추가",
+        /* pt_br   */ "Adicionar cursor acima / abaixo",
+        /* ru      */ "Добавить курсор выше / ниже",
+        /* zh_hans */ "在上方 / 下方添加光标",
+        /* zh_hant */ "在上方 / 下方新增游標",
+    ],
+    // KeymapCursorNextMatch
+    [
+        /* en      */ "Add a cursor at the next match",
+        /* de      */ "Cursor beim nächsten Treffer hinzufügen",
+        /* es      */ "Agregar cursor en la siguiente coincidencia",
+        /* fr      */ "Ajouter un curseur à l'occurrence suivante",
+        /* it      */ "Aggiungi cursore alla corrispondenza successiva",
+        /* ja      */ "次の一致にカーソルを追加",
+        /* ko      */ "다음 일치 항목에 커서 추가",
+        /* pt_br   */ "Adicionar cursor na próxima correspondência",
+        /* ru      */ "Добавить курсор на следующем совпадении",
+        /* zh_hans */ "在下一个匹配项处添加光标",
+        /* zh_hant */ "在下一個相符項目新增游標",
+    ],
+    // KeymapBlockSelection
+    [
+        /* en      */ "Select a block of columns",
+        /* de      */ "Spaltenblock auswählen",
+        /* es      */ "Seleccionar un bloque de columnas",
+        /* fr      */ "Sélectionner un bloc de colonnes",
+        /* it      */ "Seleziona un blocco di colonne",
+        /* ja      */ "矩形選択",
+        /* ko      */ "열 블록 선택",
+        /* pt_br   */ "Selecionar um bloco de colunas",
+        /* ru      */ "Выделить блок столбцов",
+        /* zh_hans */ "选择列块",
+        /* zh_hant */ "選取欄區塊",
+    ],
+    // KeymapMoveLines
+    [
+        /* en      */ "Move lines up or down",
+        /* de      */ "Zeilen nach oben oder unten verschieben",
+        /* es      */ "Mover líneas arriba o abajo",
+        /* fr      */ "Déplacer les lignes vers le haut ou le bas",
+        /* it      */ "Sposta le righe su o giù",
+        /* ja      */ "行を上下に移動",
+        /* ko      */ "줄을 위나 아래로 이동",
+        /* pt_br   */ "Mover linhas para cima ou para baixo",
+        /* ru      */ "Переместить строки вверх или вниз",
+        /* zh_hans */ "上移或下移行",
+        /* zh_hant */ "上移或下移行",
+    ],
+    // KeymapJumpBack
+    [
+        /* en      */ "Go back to where the cursor jumped from",
+        /* de      */ "Zurück zur Position vor dem Sprung",
+        /* es      */ "Volver a donde saltó el cursor",
+        /* fr      */ "Revenir là d'où le curseur a sauté",
+        /* it      */ "Torna al punto da cui è saltato il cursore",
+        /* ja      */ "カーソルのジャンプ元に戻る",
+        /* ko      */ "커서가 이동하기 전 위치로 돌아가기",
+        /* pt_br   */ "Voltar para onde o cursor saltou",
+        /* ru      */ "Вернуться туда, откуда перескочил курсор",
+        /* zh_hans */ "返回光标跳转前的位置",
+        /* zh_hant */ "返回游標跳轉前的位置",
+    ],
+    // KeymapJumpForward
+    [
+        /* en      */ "Go forward again",
+        /* de      */ "Wieder vorwärts",
+        /* es      */ "Volver a avanzar",
+        /* fr      */ "Revenir en avant",
+        /* it      */ "Vai di nuovo avanti",
+        /* ja      */ "再び進む",
+        /* ko      */ "다시 앞으로 이동",
+        /* pt_br   */ "Avançar novamente",
+        /* ru      */ "Снова вперёд",
+        /* zh_hans */ "再次前进",
+        /* zh_hant */ "再次前進",
+    ],
+    // KeymapToggleFold
+    [
+        /* en      */ "Fold or unfold",
+        /* de      */ "Ein- oder ausklappen",
+        /* es      */ "Plegar o desplegar",
+        /* fr      */ "Replier ou déplier",
+        /* it      */ "Comprimi o espandi",
+        /* ja      */ "折りたたみ/展開",
+        /* ko      */ "접기/펼치기",
+        /* pt_br   */ "Recolher ou expandir",
+        /* ru      */ "Свернуть или развернуть",
+        /* zh_hans */ "折叠或展开",
+        /* zh_hant */ "摺疊或展開",
+    ],
+    // KeymapTabs
+    [
+        /* en      */ "Tabs",
+        /* de      */ "Tabs",
+        /* es      */ "Pestañas",
+        /* fr      */ "Onglets",
+        /* it      */ "Schede",
+        /* ja      */ "タブ",
+        /* ko      */ "탭",
+        /* pt_br   */ "Abas",
+        /* ru      */ "Вкладки",
+        /* zh_hans */ "标签页",
+        /* zh_hant */ "索引標籤",
+    ],
+    // KeymapNextTab
+    [
+        /* en      */ "Next tab",
+        /* de      */ "Nächster Tab",
+        /* es      */ "Pestaña siguiente",
+        /* fr      */ "Onglet suivant",
+        /* it      */ "Scheda successiva",
+        /* ja      */ "次のタブ",
+        /* ko      */ "다음 탭",
+        /* pt_br   */ "Próxima aba",
+        /* ru      */ "Следующая вкладка",
+        /* zh_hans */ "下一个标签页",
+        /* zh_hant */ "下一個索引標籤",
+    ],
+    // KeymapPreviousTab
+    [
+        /* en      */ "Previous tab",
+        /* de      */ "Vorheriger Tab",
+        /* es      */ "Pestaña anterior",
+        /* fr      */ "Onglet précédent",
+        /* it      */ "Scheda precedente",
+        /* ja      */ "前のタブ",
+        /* ko      */ "이전 탭",
+        /* pt_br   */ "Aba anterior",
+        /* ru      */ "Предыдущая вкладка",
+        /* zh_hans */ "上一个标签页",
+        /* zh_hant */ "上一個索引標籤",
+    ],
+    // KeymapGoToTab
+    [
+        /* en      */ "Go to tab",
+        /* de      */ "Gehe zu Tab",
+        /* es      */ "Ir a la pestaña",
+        /* fr      */ "Aller à l'onglet",
+        /* it      */ "Vai alla scheda",
+        /* ja      */ "タブに移動",
+        /* ko      */ "탭으로 이동",
+        /* pt_br   */ "Ir para a aba",
+        /* ru      */ "Перейти к вкладке",
+        /* zh_hans */ "转到标签页",
+        /* zh_hant */ "移至索引標籤",
+    ],
+    // KeymapPager
+    [
+        /* en      */ "Pager",
+        /* de      */ "Pager",
+        /* es      */ "Paginador",
+        /* fr      */ "Pager",
+        /* it      */ "Pager",
+        /* ja      */ "ページャー",
+        /* ko      */ "페이저",
+        /* pt_br   */ "Paginador",
+        /* ru      */ "Пейджер",
+        /* zh_hans */ "分页器",
+        /* zh_hant */ "分頁程式",
+    ],
+    // KeymapFindNext
+    [
+        /* en      */ "Find next",
+        /* de      */ "Weitersuchen",
+        /* es      */ "Buscar siguiente",
+        /* fr      */ "Rechercher le suivant",
+        /* it      */ "Trova successivo",
+        /* ja      */ "次を検索",
+        /* ko      */ "다음 찾기",
+        /* pt_br   */ "Localizar próxima",
+        /* ru      */ "Найти далее",
+        /* zh_hans */ "查找下一个",
+        /* zh_hant */ "尋找下一個",
+    ],
+    // KeymapPageDownUp
+    [
+        /* en      */ "Page down / up",
+        /* de      */ "Seite ab / auf",
+        /* es      */ "Página abajo / arriba",
+        /* fr      */ "Page suivante / précédente",
+        /* it      */ "Pagina giù / su",
+        /* ja      */ "ページ ダウン / アップ",
+        /* ko      */ "페이지 아래로 / 위로",
+        /* pt_br   */ "Página abaixo / acima",
+        /* ru      */ "Страница вниз / вверх",
+        /* zh_hans */ "向下 / 向上翻页",
+        /* zh_hant */ "向下 / 向上翻頁",
+    ],
+    // KeymapLineDownUp
+    [
+        /* en      */ "Line down / up",
+        /* de      */ "Zeile ab / auf",
+        /* es      */ "Línea abajo / arriba",
+        /* fr      */ "Ligne suivante / précédente",
+        /* it      */ "Riga giù / su",
+        /* ja      */ "1 行下 / 上",
+        /* ko      */ "한 줄 아래로 / 위로",
+        /* pt_br   */ "Linha abaixo / acima",
+        /* ru      */ "Строка вниз / вверх",
+        /* zh_hans */ "向下 / 向上一行",
+        /* zh_hant */ "向下 / 向上一行",
+    ],
+    // KeymapStartEnd
+    [
+        /* en      */ "Start / end of file",
+        /* de      */ "Dateianfang / -ende",
+        /* es      */ "Inicio / fin del archivo",
+        /* fr      */ "Début / fin du fichier",
+        /* it      */ "Inizio / fine del file",
+        /* ja      */ "ファイルの先頭 / 末尾",
+        /* ko      */ "파일 시작 / 끝",
+        /* pt_br   */ "Início / fim do arquivo",
+        /* ru      */ "Начало / конец файла",
+        /* zh_hans */ "文件开头 / 结尾",
+        /* zh_hant */ "檔案開頭 / 結尾",
+    ],
+    // KeymapFollow
+    [
+        /* en      */ "Follow the file as it grows",
+        /* de      */ "Der wachsenden Datei folgen",
+        /* es      */ "Seguir el archivo mientras crece",
+        /* fr      */ "Suivre le fichier à mesure qu'il grandit",
+        /* it      */ "Segui il file mentre cresce",
+        /* ja      */ "増えていくファイルを追跡",
+        /* ko      */ "파일이 커지는 대로 따라가기",
+        /* pt_br   */ "Acompanhar o arquivo conforme cresce",
+        /* ru      */ "Следить за ростом файла",
+        /* zh_hans */ "随文件增长跟踪",
+        /* zh_hant */ "隨檔案增長追蹤",
+    ],
+    // KeymapFollowLink
+    [
+        /* en      */ "Follow link",
+        /* de      */ "Link folgen",
+        /* es      */ "Seguir vínculo",
+        /* fr      */ "Suivre le lien",
+        /* it      */ "Segui collegamento",
+        /* ja      */ "リンクをたどる",
+        /* ko      */ "링크 따라가기",
+        /* pt_br   */ "Seguir link",
+        /* ru      */ "Перейти по ссылке",
+        /* zh_hans */ "跟随链接",
+        /* zh_hant */ "追蹤連結",
+    ],
+    // KeymapBack
+    [
+        /* en      */ "Back",
+        /* de      */ "Zurück",
+        /* es      */ "Atrás",
+        /* fr      */ "Précédent",
+        /* it      */ "Indietro",
+        /* ja      */ "戻る",
+        /* ko      */ "뒤로",
+        /* pt_br   */ "Voltar",
+        /* ru      */ "Назад",
+        /* zh_hans */ "后退",
+        /* zh_hant */ "上一頁",
+    ],
 
     // UnsavedChangesDialogTitle
     [
@@ -700,6 +1775,342 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "你复制的文本过大，无法共享。",
         /* zh_hant */ "您複製的文字過大，無法分享。",
     ],
+    // LargeClipboardTruncate
+    [
+        /* en      */ "Do you want to share only the first {size}?",
+        /* de      */ "Möchten Sie nur die ersten {size} teilen?",
+        /* es      */ "¿Desea compartir solo los primeros {size}?",
+        /* fr      */ "Voulez-vous partager uniquement les premiers {size} ?",
+        /* it      */ "Condividere solo i primi {size}?",
+        /* ja      */ "最初の {size} だけを共有しますか?",
+        /* ko      */ "처음 {size}만 공유하시겠습니까?",
+        /* pt_br   */ "Deseja compartilhar apenas os primeiros {size}?",
+        /* ru      */ "Поделиться только первыми {size}?",
+        /* zh_hans */ "是否只共享前 {size}?",
+        /* zh_hant */ "是否只共用前 {size}?",
+    ],
+    // FileChangedDialogTitle
+    [
+        /* en      */ "File Changed",
+        /* de      */ "Datei geändert",
+        /* es      */ "Archivo modificado",
+        /* fr      */ "Fichier modifié",
+        /* it      */ "File modificato",
+        /* ja      */ "ファイルが変更されました",
+        /* ko      */ "파일이 변경됨",
+        /* pt_br   */ "Arquivo alterado",
+        /* ru      */ "Файл изменён",
+        /* zh_hans */ "文件已更改",
+        /* zh_hant */ "檔案已變更",
+    ],
+    // FileChangedDialogDescription
+    [
+        /* en      */ "This file was changed by another program.",
+        /* de      */ "Diese Datei wurde von einem anderen Programm geändert.",
+        /* es      */ "Otro programa modificó este archivo.",
+        /* fr      */ "Ce fichier a été modifié par un autre programme.",
+        /* it      */ "Questo file è stato modificato da un altro programma.",
+        /* ja      */ "このファイルは別のプログラムによって変更されました。",
+        /* ko      */ "이 파일이 다른 프로그램에 의해 변경되었습니다.",
+        /* pt_br   */ "Este arquivo foi alterado por outro programa.",
+        /* ru      */ "Этот файл был изменён другой программой.",
+        /* zh_hans */ "此文件已被其他程序更改。",
+        /* zh_hant */ "此檔案已被其他程式變更。",
+    ],
+    // FileChangedDialogUnsaved
+    [
+        /* en      */ "Reloading discards your unsaved changes.",
+        /* de      */ "Beim Neuladen gehen Ihre ungespeicherten Änderungen verloren.",
+        /* es      */ "Al recargar se descartan los cambios no guardados.",
+        /* fr      */ "Le rechargement annule vos modifications non enregistrées.",
+        /* it      */ "Ricaricando si perdono le modifiche non salvate.",
+        /* ja      */ "再読み込みすると、保存されていない変更は破棄されます。",
+        /* ko      */ "다시 로드하면 저장하지 않은 변경 내용이 삭제됩니다.",
+        /* pt_br   */ "Recarregar descarta as alterações não salvas.",
+        /* ru      */ "При перезагрузке несохранённые изменения будут потеряны.",
+        /* zh_hans */ "重新加载将丢弃未保存的更改。",
+        /* zh_hant */ "重新載入將捨棄未儲存的變更。",
+    ],
+    // FileChangedDialogReload
+    [
+        /* en      */ "Reload",
+        /* de      */ "Neu laden",
+        /* es      */ "Recargar",
+        /* fr      */ "Recharger",
+        /* it      */ "Ricarica",
+        /* ja      */ "再読み込み",
+        /* ko      */ "다시 로드",
+        /* pt_br   */ "Recarregar",
+        /* ru      */ "Перезагрузить",
+        /* zh_hans */ "重新加载",
+        /* zh_hant */ "重新載入",
+    ],
+    // FileChangedDialogKeep
+    [
+        /* en      */ "Keep Mine",
+        /* de      */ "Meine behalten",
+        /* es      */ "Conservar la mía",
+        /* fr      */ "Garder la mienne",
+        /* it      */ "Mantieni la mia",
+        /* ja      */ "自分の変更を保持",
+        /* ko      */ "내 것 유지",
+        /* pt_br   */ "Manter a minha",
+        /* ru      */ "Оставить мою",
+        /* zh_hans */ "保留我的",
+        /* zh_hant */ "保留我的",
+    ],
+    // FileChangedDialogDiff
+    [
+        /* en      */ "Show Diff",
+        /* de      */ "Unterschiede zeigen",
+        /* es      */ "Mostrar diferencias",
+        /* fr      */ "Afficher les différences",
+        /* it      */ "Mostra differenze",
+        /* ja      */ "差分を表示",
+        /* ko      */ "차이 보기",
+        /* pt_br   */ "Mostrar diferenças",
+        /* ru      */ "Показать различия",
+        /* zh_hans */ "显示差异",
+        /* zh_hant */ "顯示差異",
+    ],
+    // SwapRecoverTitle
+    [
+        /* en      */ "Unsaved Changes Found",
+        /* de      */ "Nicht gespeicherte Änderungen gefunden",
+        /* es      */ "Cambios sin guardar encontrados",
+        /* fr      */ "Modifications non enregistrées trouvées",
+        /* it      */ "Modifiche non salvate trovate",
+        /* ja      */ "保存されていない変更が見つかりました",
+        /* ko      */ "저장되지 않은 변경 내용이 있습니다",
+        /* pt_br   */ "Alterações não salvas encontradas",
+        /* ru      */ "Найдены несохранённые изменения",
+        /* zh_hans */ "发现未保存的更改",
+        /* zh_hant */ "發現未儲存的變更",
+    ],
+    // SwapRecoverDescription
+    [
+        /* en      */ "An editor that was closed unexpectedly had changes to this file.",
+        /* de      */ "Ein unerwartet beendeter Editor hatte Änderungen an dieser Datei.",
+        /* es      */ "Un editor que se cerró inesperadamente tenía cambios en este archivo.",
+        /* fr      */ "Un éditeur fermé de manière inattendue avait des modifications de ce fichier.",
+        /* it      */ "Un editor chiuso inaspettatamente aveva modifiche a questo file.",
+        /* ja      */ "予期せず終了したエディターに、このファイルへの変更がありました。",
+        /* ko      */ "예기치 않게 종료된 편집기에 이 파일의 변경 내용이 있었습니다.",
+        /* pt_br   */ "Um editor fechado inesperadamente tinha alterações neste arquivo.",
+        /* ru      */ "В неожиданно закрытом редакторе были изменения этого файла.",
+        /* zh_hans */ "意外关闭的编辑器中有对此文件的更改。",
+        /* zh_hant */ "意外關閉的編輯器中有對此檔案的變更。",
+    ],
+    // SwapRecover
+    [
+        /* en      */ "Recover",
+        /* de      */ "Wiederherstellen",
+        /* es      */ "Recuperar",
+        /* fr      */ "Récupérer",
+        /* it      */ "Recupera",
+        /* ja      */ "復元",
+        /* ko      */ "복구",
+        /* pt_br   */ "Recuperar",
+        /* ru      */ "Восстановить",
+        /* zh_hans */ "恢复",
+        /* zh_hant */ "復原",
+    ],
+    // SwapDiscard
+    [
+        /* en      */ "Discard",
+        /* de      */ "Verwerfen",
+        /* es      */ "Descartar",
+        /* fr      */ "Abandonner",
+        /* it      */ "Scarta",
+        /* ja      */ "破棄",
+        /* ko      */ "삭제",
+        /* pt_br   */ "Descartar",
+        /* ru      */ "Отбросить",
+        /* zh_hans */ "丢弃",
+        /* zh_hant */ "捨棄",
+    ],
+    // SwapLater
+    [
+        /* en      */ "Later",
+        /* de      */ "Später",
+        /* es      */ "Más tarde",
+        /* fr      */ "Plus tard",
+        /* it      */ "Più tardi",
+        /* ja      */ "後で",
+        /* ko      */ "나중에",
+        /* pt_br   */ "Mais tarde",
+        /* ru      */ "Позже",
+        /* zh_hans */ "稍后",
+        /* zh_hant */ "稍後",
+    ],
+    // SwapInUseTitle
+    [
+        /* en      */ "File Already Open",
+        /* de      */ "Datei bereits geöffnet",
+        /* es      */ "Archivo ya abierto",
+        /* fr      */ "Fichier déjà ouvert",
+        /* it      */ "File già aperto",
+        /* ja      */ "ファイルは既に開かれています",
+        /* ko      */ "파일이 이미 열려 있음",
+        /* pt_br   */ "Arquivo já aberto",
+        /* ru      */ "Файл уже открыт",
+        /* zh_hans */ "文件已打开",
+        /* zh_hant */ "檔案已開啟",
+    ],
+    // SwapInUseDescription
+    [
+        /* en      */ "Another editor has unsaved changes to this file.",
+        /* de      */ "Ein anderer Editor hat ungespeicherte Änderungen an dieser Datei.",
+        /* es      */ "Otro editor tiene cambios sin guardar en este archivo.",
+        /* fr      */ "Un autre éditeur a des modifications non enregistrées de ce fichier.",
+        /* it      */ "Un altro editor ha modifiche non salvate a questo file.",
+        /* ja      */ "別のエディターに、このファイルへの未保存の変更があります。",
+        /* ko      */ "다른 편집기에 이 파일의 저장되지 않은 변경 내용이 있습니다.",
+        /* pt_br   */ "Outro editor tem alterações não salvas neste arquivo.",
+        /* ru      */ "В другом редакторе есть несохранённые изменения этого файла.",
+        /* zh_hans */ "另一个编辑器中有此文件未保存的更改。",
+        /* zh_hant */ "另一個編輯器中有此檔案未儲存的變更。",
+    ],
+    // SwapReadOnly
+    [
+        /* en      */ "Open Read-Only",
+        /* de      */ "Schreibgeschützt öffnen",
+        /* es      */ "Abrir como solo lectura",
+        /* fr      */ "Ouvrir en lecture seule",
+        /* it      */ "Apri in sola lettura",
+        /* ja      */ "読み取り専用で開く",
+        /* ko      */ "읽기 전용으로 열기",
+        /* pt_br   */ "Abrir somente leitura",
+        /* ru      */ "Открыть только для чтения",
+        /* zh_hans */ "以只读方式打开",
+        /* zh_hant */ "以唯讀方式開啟",
+    ],
+    // SwapEditAnyway
+    [
+        /* en      */ "Edit Anyway",
+        /* de      */ "Trotzdem bearbeiten",
+        /* es      */ "Editar de todos modos",
+        /* fr      */ "Modifier quand même",
+        /* it      */ "Modifica comunque",
+        /* ja      */ "編集を続ける",
+        /* ko      */ "그래도 편집",
+        /* pt_br   */ "Editar mesmo assim",
+        /* ru      */ "Всё равно редактировать",
+        /* zh_hans */ "仍然编辑",
+        /* zh_hant */ "仍要編輯",
+    ],
+    // StagedUnsavedTitle
+    [
+        /* en      */ "Unsaved Staged Files",
+        /* de      */ "Nicht gespeicherte vorgemerkte Dateien",
+        /* es      */ "Archivos preparados sin guardar",
+        /* fr      */ "Fichiers indexés non enregistrés",
+        /* it      */ "File in stage non salvati",
+        /* ja      */ "未保存のステージ済みファイル",
+        /* ko      */ "저장되지 않은 스테이징된 파일",
+        /* pt_br   */ "Arquivos preparados não salvos",
+        /* ru      */ "Несохранённые проиндексированные файлы",
+        /* zh_hans */ "未保存的已暂存文件",
+        /* zh_hant */ "未儲存的已暫存檔案",
+    ],
+    // StagedUnsavedDescription
+    [
+        /* en      */ "These staged files have unsaved changes, which a commit wouldn't include:",
+        /* de      */ "Diese Dateien sind vorgemerkt, aber ein Commit würde ihre ungespeicherten Änderungen nicht enthalten:",
+        /* es      */ "Estos archivos están preparados, pero un commit no incluiría sus cambios sin guardar:",
+        /* fr      */ "Ces fichiers sont indexés, mais un commit n'inclurait pas leurs modifications non enregistrées :",
+        /* it      */ "Questi file sono in stage, ma un commit non includerebbe le modifiche non salvate:",
+        /* ja      */ "これらのファイルはステージ済みですが、コミットには未保存の変更が含まれません:",
+        /* ko      */ "이 파일은 스테이징되었지만 커밋에 저장되지 않은 변경 내용이 포함되지 않습니다.",
+        /* pt_br   */ "Estes arquivos estão preparados, mas um commit não incluiria as alterações não salvas:",
+        /* ru      */ "Эти файлы проиндексированы, но коммит не включит их несохранённые изменения:",
+        /* zh_hans */ "这些文件已暂存，但提交不会包含其未保存的更改：",
+        /* zh_hant */ "這些檔案已暫存，但認可不會包含其未儲存的變更：",
+    ],
+    // StagedSaveAndStage
+    [
+        /* en      */ "Save and Stage",
+        /* de      */ "Speichern und vormerken",
+        /* es      */ "Guardar y preparar",
+        /* fr      */ "Enregistrer et indexer",
+        /* it      */ "Salva e metti in stage",
+        /* ja      */ "保存してステージ",
+        /* ko      */ "저장 후 스테이징",
+        /* pt_br   */ "Salvar e preparar",
+        /* ru      */ "Сохранить и проиндексировать",
+        /* zh_hans */ "保存并暂存",
+        /* zh_hant */ "儲存並暫存",
+    ],
+    // StagedSave
+    [
+        /* en      */ "Save",
+        /* de      */ "Speichern",
+        /* es      */ "Guardar",
+        /* fr      */ "Enregistrer",
+        /* it      */ "Salva",
+        /* ja      */ "保存",
+        /* ko      */ "저장",
+        /* pt_br   */ "Salvar",
+        /* ru      */ "Сохранить",
+        /* zh_hans */ "保存",
+        /* zh_hant */ "儲存",
+    ],
+    // StagedIgnore
+    [
+        /* en      */ "Ignore",
+        /* de      */ "Ignorieren",
+        /* es      */ "Ignorar",
+        /* fr      */ "Ignorer",
+        /* it      */ "Ignora",
+        /* ja      */ "無視",
+        /* ko      */ "무시",
+        /* pt_br   */ "Ignorar",
+        /* ru      */ "Игнорировать",
+        /* zh_hans */ "忽略",
+        /* zh_hant */ "略過",
+    ],
+    // SudoDialogTitle
+    [
+        /* en      */ "Permission Denied",
+        /* de      */ "Zugriff verweigert",
+        /* es      */ "Permiso denegado",
+        /* fr      */ "Permission refusée",
+        /* it      */ "Permesso negato",
+        /* ja      */ "アクセスが拒否されました",
+        /* ko      */ "권한 거부됨",
+        /* pt_br   */ "Permissão negada",
+        /* ru      */ "Доступ запрещён",
+        /* zh_hans */ "权限被拒绝",
+        /* zh_hant */ "權限遭拒",
+    ],
+    // SudoDialogDescription
+    [
+        /* en      */ "You don't have permission to write this file. Save it as root instead?",
+        /* de      */ "Sie dürfen diese Datei nicht schreiben. Stattdessen als root speichern?",
+        /* es      */ "No tiene permiso para escribir este archivo. ¿Guardarlo como root?",
+        /* fr      */ "Vous n'avez pas le droit d'écrire ce fichier. L'enregistrer en tant que root ?",
+        /* it      */ "Non hai il permesso di scrivere questo file. Salvarlo come root?",
+        /* ja      */ "このファイルに書き込む権限がありません。root として保存しますか?",
+        /* ko      */ "이 파일을 쓸 권한이 없습니다. root로 저장하시겠습니까?",
+        /* pt_br   */ "Você não tem permissão para gravar este arquivo. Salvar como root?",
+        /* ru      */ "У вас нет прав на запись этого файла. Сохранить от имени root?",
+        /* zh_hans */ "你没有写入此文件的权限。改为以 root 身份保存?",
+        /* zh_hant */ "你沒有寫入此檔案的權限。改以 root 身分儲存?",
+    ],
+    // SudoDialogSave
+    [
+        /* en      */ "Save as Root",
+        /* de      */ "Als root speichern",
+        /* es      */ "Guardar como root",
+        /* fr      */ "Enregistrer en tant que root",
+        /* it      */ "Salva come root",
+        /* ja      */ "root として保存",
+        /* ko      */ "root로 저장",
+        /* pt_br   */ "Salvar como root",
+        /* ru      */ "Сохранить от имени root",
+        /* zh_hans */ "以 root 身份保存",
+        /* zh_hant */ "以 root 身分儲存",
+    ],
 
     // WarningDialogTitle
     [
@@ -744,6 +2155,146 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "此操作需要 ICU 库",
         /* zh_hant */ "此操作需要 ICU 庫",
     ],
+    // ErrorHexInvalid
+    [
+        /* en      */ "The hex view has bytes that aren't two hex digits.",
+        /* de      */ "Die Hexansicht enthält Bytes, die nicht aus zwei Hexziffern bestehen.",
+        /* es      */ "La vista hexadecimal contiene bytes que no son dos dígitos hexadecimales.",
+        /* fr      */ "La vue hexadécimale contient des octets qui ne sont pas deux chiffres hexadécimaux.",
+        /* it      */ "La vista esadecimale contiene byte che non sono due cifre esadecimali.",
+        /* ja      */ "16 進表示に 2 桁の 16 進数ではないバイトがあります。",
+        /* ko      */ "16진수 보기에 두 자리 16진수가 아닌 바이트가 있습니다.",
+        /* pt_br   */ "A visualização hexadecimal tem bytes que não são dois dígitos hexadecimais.",
+        /* ru      */ "В шестнадцатеричном виде есть байты, не состоящие из двух шестнадцатеричных цифр.",
+        /* zh_hans */ "十六进制视图中有不是两位十六进制数字的字节。",
+        /* zh_hant */ "十六進位檢視中有不是兩位十六進位數字的位元組。",
+    ],
+    // ErrorHelpNotFound
+    [
+        /* en      */ "No help found for this topic",
+        /* de      */ "Keine Hilfe zu diesem Thema gefunden",
+        /* es      */ "No se encontró ayuda sobre este tema",
+        /* fr      */ "Aucune aide trouvée pour ce sujet",
+        /* it      */ "Nessuna guida trovata per questo argomento",
+        /* ja      */ "このトピックのヘルプが見つかりません",
+        /* ko      */ "이 항목에 대한 도움말을 찾을 수 없습니다",
+        /* pt_br   */ "Nenhuma ajuda encontrada para este tópico",
+        /* ru      */ "Справка по этой теме не найдена",
+        /* zh_hans */ "未找到此主题的帮助",
+        /* zh_hant */ "找不到此主題的說明",
+    ],
+    // ErrorNoClipboardLocations
+    [
+        /* en      */ "The clipboard doesn't mention any existing file",
+        /* de      */ "Die Zwischenablage nennt keine vorhandene Datei",
+        /* es      */ "El portapapeles no menciona ningún archivo existente",
+        /* fr      */ "Le presse-papiers ne mentionne aucun fichier existant",
+        /* it      */ "Gli appunti non menzionano alcun file esistente",
+        /* ja      */ "クリップボードに既存のファイルが見つかりません",
+        /* ko      */ "클립보드에 기존 파일이 없습니다",
+        /* pt_br   */ "A área de transferência não menciona nenhum arquivo existente",
+        /* ru      */ "В буфере обмена нет существующих файлов",
+        /* zh_hans */ "剪贴板中没有现有文件",
+        /* zh_hant */ "剪貼簿中沒有現有檔案",
+    ],
+    // ErrorMacroNotFound
+    [
+        /* en      */ "No macro with that name was found",
+        /* de      */ "Kein Makro mit diesem Namen gefunden",
+        /* es      */ "No se encontró ninguna macro con ese nombre",
+        /* fr      */ "Aucune macro portant ce nom n'a été trouvée",
+        /* it      */ "Nessuna macro trovata con questo nome",
+        /* ja      */ "その名前のマクロが見つかりません",
+        /* ko      */ "해당 이름의 매크로를 찾을 수 없습니다",
+        /* pt_br   */ "Nenhuma macro com esse nome foi encontrada",
+        /* ru      */ "Макрос с таким именем не найден",
+        /* zh_hans */ "未找到具有该名称的宏",
+        /* zh_hant */ "找不到具有該名稱的巨集",
+    ],
+    // ErrorRegisterEmpty
+    [
+        /* en      */ "That register is empty",
+        /* de      */ "Dieses Register ist leer",
+        /* es      */ "Ese registro está vacío",
+        /* fr      */ "Ce registre est vide",
+        /* it      */ "Questo registro è vuoto",
+        /* ja      */ "そのレジスタは空です",
+        /* ko      */ "해당 레지스터가 비어 있습니다",
+        /* pt_br   */ "Esse registro está vazio",
+        /* ru      */ "Этот регистр пуст",
+        /* zh_hans */ "该寄存器为空",
+        /* zh_hant */ "該暫存器是空的",
+    ],
+    // ErrorSurroundingNotFound
+    [
+        /* en      */ "Nothing like that surrounds the cursor",
+        /* de      */ "Nichts dergleichen umgibt den Cursor",
+        /* es      */ "No hay nada así alrededor del cursor",
+        /* fr      */ "Rien de tel n'entoure le curseur",
+        /* it      */ "Non c'è niente del genere attorno al cursore",
+        /* ja      */ "カーソルの周囲に該当するものがありません",
+        /* ko      */ "커서 주위에 해당하는 것이 없습니다",
+        /* pt_br   */ "Não há nada assim ao redor do cursor",
+        /* ru      */ "Вокруг курсора нет ничего такого",
+        /* zh_hans */ "光标周围没有这样的内容",
+        /* zh_hant */ "游標周圍沒有這樣的內容",
+    ],
+    // ErrorSudoNotFound
+    [
+        /* en      */ "Neither sudo, doas nor pkexec was found.",
+        /* de      */ "Weder sudo, doas noch pkexec wurde gefunden.",
+        /* es      */ "No se encontró sudo, doas ni pkexec.",
+        /* fr      */ "Ni sudo, ni doas, ni pkexec n'a été trouvé.",
+        /* it      */ "Non è stato trovato né sudo, né doas, né pkexec.",
+        /* ja      */ "sudo、doas、pkexec のいずれも見つかりません。",
+        /* ko      */ "sudo, doas, pkexec 중 어느 것도 찾을 수 없습니다.",
+        /* pt_br   */ "Nem sudo, nem doas, nem pkexec foi encontrado.",
+        /* ru      */ "Не найдено ни sudo, ни doas, ни pkexec.",
+        /* zh_hans */ "未找到 sudo、doas 或 pkexec。",
+        /* zh_hant */ "找不到 sudo、doas 或 pkexec。",
+    ],
+    // ErrorSudoFailed
+    [
+        /* en      */ "Saving as root failed.",
+        /* de      */ "Speichern als root fehlgeschlagen.",
+        /* es      */ "No se pudo guardar como root.",
+        /* fr      */ "L'enregistrement en tant que root a échoué.",
+        /* it      */ "Salvataggio come root non riuscito.",
+        /* ja      */ "root として保存できませんでした。",
+        /* ko      */ "root로 저장하지 못했습니다.",
+        /* pt_br   */ "Falha ao salvar como root.",
+        /* ru      */ "Не удалось сохранить от имени root.",
+        /* zh_hans */ "以 root 身份保存失败。",
+        /* zh_hant */ "以 root 身分儲存失敗。",
+    ],
+    // ErrorRemoteFailed
+    [
+        /* en      */ "Transferring the remote file failed.",
+        /* de      */ "Die Übertragung der entfernten Datei ist fehlgeschlagen.",
+        /* es      */ "No se pudo transferir el archivo remoto.",
+        /* fr      */ "Le transfert du fichier distant a échoué.",
+        /* it      */ "Trasferimento del file remoto non riuscito.",
+        /* ja      */ "リモート ファイルを転送できませんでした。",
+        /* ko      */ "원격 파일을 전송하지 못했습니다.",
+        /* pt_br   */ "Falha ao transferir o arquivo remoto.",
+        /* ru      */ "Не удалось передать удалённый файл.",
+        /* zh_hans */ "传输远程文件失败。",
+        /* zh_hant */ "傳輸遠端檔案失敗。",
+    ],
+    // ErrorStageFailed
+    [
+        /* en      */ "Staging the files with git failed.",
+        /* de      */ "Das Vormerken der Dateien mit git ist fehlgeschlagen.",
+        /* es      */ "No se pudieron preparar los archivos con git.",
+        /* fr      */ "L'indexation des fichiers avec git a échoué.",
+        /* it      */ "Impossibile mettere in stage i file con git.",
+        /* ja      */ "git でファイルをステージできませんでした。",
+        /* ko      */ "git으로 파일을 스테이징하지 못했습니다.",
+        /* pt_br   */ "Falha ao preparar os arquivos com o git.",
+        /* ru      */ "Не удалось проиндексировать файлы с помощью git.",
+        /* zh_hans */ "使用 git 暂存文件失败。",
+        /* zh_hant */ "使用 git 暫存檔案失敗。",
+    ],
 
     // SearchNeedleLabel (for input field)
     [
@@ -759,6 +2310,90 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "查找:",
         /* zh_hant */ "尋找:",
     ],
+    // LineSearchLabel
+    [
+        /* en      */ "Lines:",
+        /* de      */ "Zeilen:",
+        /* es      */ "Líneas:",
+        /* fr      */ "Lignes :",
+        /* it      */ "Righe:",
+        /* ja      */ "行:",
+        /* ko      */ "줄:",
+        /* pt_br   */ "Linhas:",
+        /* ru      */ "Строки:",
+        /* zh_hans */ "行:",
+        /* zh_hant */ "行:",
+    ],
+    // ProjectSearchLabel
+    [
+        /* en      */ "In files:",
+        /* de      */ "In Dateien:",
+        /* es      */ "En archivos:",
+        /* fr      */ "Dans les fichiers :",
+        /* it      */ "Nei file:",
+        /* ja      */ "ファイル内:",
+        /* ko      */ "파일에서:",
+        /* pt_br   */ "Nos arquivos:",
+        /* ru      */ "В файлах:",
+        /* zh_hans */ "在文件中:",
+        /* zh_hant */ "在檔案中:",
+    ],
+    // JumpStatusLabel
+    [
+        /* en      */ "Jump:",
+        /* de      */ "Springen:",
+        /* es      */ "Saltar:",
+        /* fr      */ "Aller à :",
+        /* it      */ "Salta:",
+        /* ja      */ "ジャンプ:",
+        /* ko      */ "이동:",
+        /* pt_br   */ "Saltar:",
+        /* ru      */ "Переход:",
+        /* zh_hans */ "跳转：",
+        /* zh_hant */ "跳至：",
+    ],
+    // PagerFollowing
+    [
+        /* en      */ "Following",
+        /* de      */ "Folgen",
+        /* es      */ "Siguiendo",
+        /* fr      */ "Suivi",
+        /* it      */ "In ascolto",
+        /* ja      */ "追従中",
+        /* ko      */ "따라가는 중",
+        /* pt_br   */ "Acompanhando",
+        /* ru      */ "Слежение",
+        /* zh_hans */ "跟随中",
+        /* zh_hant */ "跟隨中",
+    ],
+    // TaskRunning
+    [
+        /* en      */ "Running task…",
+        /* de      */ "Aufgabe läuft…",
+        /* es      */ "Ejecutando tarea…",
+        /* fr      */ "Tâche en cours…",
+        /* it      */ "Attività in corso…",
+        /* ja      */ "タスク実行中…",
+        /* ko      */ "작업 실행 중…",
+        /* pt_br   */ "Executando tarefa…",
+        /* ru      */ "Выполняется задача…",
+        /* zh_hans */ "正在运行任务…",
+        /* zh_hant */ "正在執行工作…",
+    ],
+    // SafeMode
+    [
+        /* en      */ "Safe Mode",
+        /* de      */ "Abgesicherter Modus",
+        /* es      */ "Modo seguro",
+        /* fr      */ "Mode sans échec",
+        /* it      */ "Modalità provvisoria",
+        /* ja      */ "セーフ モード",
+        /* ko      */ "안전 모드",
+        /* pt_br   */ "Modo de segurança",
+        /* ru      */ "Безопасный режим",
+        /* zh_hans */ "安全模式",
+        /* zh_hant */ "安全模式",
+    ],
     // SearchReplacementLabel (for input field)
     [
         /* en      */ "Replace:",
@@ -843,6 +2478,48 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "关闭",
         /* zh_hant */ "關閉",
     ],
+    // SearchReplaceConfirm
+    [
+        /* en      */ "Replace this match?",
+        /* de      */ "Diesen Treffer ersetzen?",
+        /* es      */ "¿Reemplazar esta coincidencia?",
+        /* fr      */ "Remplacer cette occurrence ?",
+        /* it      */ "Sostituire questa corrispondenza?",
+        /* ja      */ "この一致を置換しますか?",
+        /* ko      */ "이 일치 항목을 바꾸시겠습니까?",
+        /* pt_br   */ "Substituir esta correspondência?",
+        /* ru      */ "Заменить это совпадение?",
+        /* zh_hans */ "替换此匹配项?",
+        /* zh_hant */ "取代此相符項目?",
+    ],
+    // SearchReplaceInTabs
+    [
+        /* en      */ "Replace in All Tabs",
+        /* de      */ "In allen Tabs ersetzen",
+        /* es      */ "Reemplazar en todas las pestañas",
+        /* fr      */ "Remplacer dans tous les onglets",
+        /* it      */ "Sostituisci in tutte le schede",
+        /* ja      */ "すべてのタブで置換",
+        /* ko      */ "모든 탭에서 바꾸기",
+        /* pt_br   */ "Substituir em todas as abas",
+        /* ru      */ "Заменить во всех вкладках",
+        /* zh_hans */ "在所有标签页中替换",
+        /* zh_hant */ "在所有索引標籤中取代",
+    ],
+    // SearchReplaceInTabsDescription
+    [
+        /* en      */ "Replace the matches in these files?",
+        /* de      */ "Die Treffer in diesen Dateien ersetzen?",
+        /* es      */ "¿Reemplazar las coincidencias en estos archivos?",
+        /* fr      */ "Remplacer les occurrences dans ces fichiers ?",
+        /* it      */ "Sostituire le corrispondenze in questi file?",
+        /* ja      */ "これらのファイル内の一致を置換しますか?",
+        /* ko      */ "이 파일들의 일치 항목을 바꾸시겠습니까?",
+        /* pt_br   */ "Substituir as correspondências nestes arquivos?",
+        /* ru      */ "Заменить совпадения в этих файлах?",
+        /* zh_hans */ "替换这些文件中的匹配项?",
+        /* zh_hant */ "取代這些檔案中的相符項目?",
+    ],
 
     // EncodingReopen
     [
@@ -872,6 +2549,34 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "转换为编码…",
         /* zh_hant */ "轉換為編碼…",
     ],
+    // EncodingAddBom
+    [
+        /* en      */ "Add byte order mark",
+        /* de      */ "Byte Order Mark hinzufügen",
+        /* es      */ "Agregar marca de orden de bytes",
+        /* fr      */ "Ajouter une marque d’ordre des octets",
+        /* it      */ "Aggiungi byte order mark",
+        /* ja      */ "BOM を追加",
+        /* ko      */ "BOM 추가",
+        /* pt_br   */ "Adicionar marca de ordem de byte",
+        /* ru      */ "Добавить метку порядка байтов",
+        /* zh_hans */ "添加字节顺序标记",
+        /* zh_hant */ "新增位元組順序標記",
+    ],
+    // EncodingRemoveBom
+    [
+        /* en      */ "Remove byte order mark",
+        /* de      */ "Byte Order Mark entfernen",
+        /* es      */ "Quitar marca de orden de bytes",
+        /* fr      */ "Supprimer la marque d’ordre des octets",
+        /* it      */ "Rimuovi byte order mark",
+        /* ja      */ "BOM を削除",
+        /* ko      */ "BOM 제거",
+        /* pt_br   */ "Remover marca de ordem de byte",
+        /* ru      */ "Удалить метку порядка байтов",
+        /* zh_hans */ "删除字节顺序标记",
+        /* zh_hant */ "移除位元組順序標記",
+    ],
 
     // IndentationTabs
     [
@@ -959,6 +2664,118 @@ const S_LANG_LUT: [[&str; LangId::Count as usize]; LocId::Count as usize] = [
         /* zh_hans */ "文件已存在。要覆盖它吗？",
         /* zh_hant */ "檔案已存在。要覆蓋它嗎？",
     ],
+    // TrustDialogTitle
+    [
+        /* en      */ "Trust this workspace?",
+        /* de      */ "Diesem Arbeitsbereich vertrauen?",
+        /* es      */ "¿Confiar en esta área de trabajo?",
+        /* fr      */ "Faire confiance à cet espace de travail ?",
+        /* it      */ "Considerare attendibile quest’area di lavoro?",
+        /* ja      */ "このワークスペースを信頼しますか?",
+        /* ko      */ "이 작업 영역을 신뢰하시겠습니까?",
+        /* pt_br   */ "Confiar neste espaço de trabalho?",
+        /* ru      */ "Доверять этой рабочей области?",
+        /* zh_hans */ "信任此工作区?",
+        /* zh_hant */ "信任此工作區?",
+    ],
+    // TrustDialogDescription
+    [
+        /* en      */ "Its .edit/config.toml defines commands, like linters, that would run on your computer.",
+        /* de      */ "Seine .edit/config.toml definiert Befehle wie Linter, die auf Ihrem Computer ausgeführt würden.",
+        /* es      */ "Su .edit/config.toml define comandos, como linters, que se ejecutarían en su equipo.",
+        /* fr      */ "Son .edit/config.toml définit des commandes, comme des linters, qui s’exécuteraient sur votre ordinateur.",
+        /* it      */ "Il suo .edit/config.toml definisce comandi, come i linter, che verrebbero eseguiti sul computer.",
+        /* ja      */ "この .edit/config.toml は、リンターなど、お使いのコンピューターで実行されるコマンドを定義しています。",
+        /* ko      */ "이 .edit/config.toml은 린터 등 컴퓨터에서 실행될 명령을 정의합니다.",
+        /* pt_br   */ "Seu .edit/config.toml define comandos, como linters, que seriam executados no seu computador.",
+        /* ru      */ "Его .edit/config.toml задаёт команды, например линтеры, которые будут запущены на вашем компьютере.",
+        /* zh_hans */ "其 .edit/config.toml 定义了将在你的计算机上运行的命令，例如 linter。",
+        /* zh_hant */ "其 .edit/config.toml 定義了將在您的電腦上執行的命令，例如 linter。",
+    ],
+    // TrustDialogTrust
+    [
+        /* en      */ "Trust",
+        /* de      */ "Vertrauen",
+        /* es      */ "Confiar",
+        /* fr      */ "Faire confiance",
+        /* it      */ "Considera attendibile",
+        /* ja      */ "信頼する",
+        /* ko      */ "신뢰",
+        /* pt_br   */ "Confiar",
+        /* ru      */ "Доверять",
+        /* zh_hans */ "信任",
+        /* zh_hant */ "信任",
+    ],
+    // TrustDialogRestricted
+    [
+        /* en      */ "Restricted Mode",
+        /* de      */ "Eingeschränkter Modus",
+        /* es      */ "Modo restringido",
+        /* fr      */ "Mode restreint",
+        /* it      */ "Modalità con restrizioni",
+        /* ja      */ "制限モード",
+        /* ko      */ "제한 모드",
+        /* pt_br   */ "Modo restrito",
+        /* ru      */ "Ограниченный режим",
+        /* zh_hans */ "受限模式",
+        /* zh_hant */ "受限模式",
+    ],
+    // WorkspaceTrustTrusted
+    [
+        /* en      */ "Trusted",
+        /* de      */ "Vertrauenswürdig",
+        /* es      */ "De confianza",
+        /* fr      */ "Approuvé",
+        /* it      */ "Attendibile",
+        /* ja      */ "信頼済み",
+        /* ko      */ "신뢰함",
+        /* pt_br   */ "Confiável",
+        /* ru      */ "Доверенная",
+        /* zh_hans */ "已信任",
+        /* zh_hant */ "已信任",
+    ],
+    // WorkspaceTrustRestricted
+    [
+        /* en      */ "Restricted",
+        /* de      */ "Eingeschränkt",
+        /* es      */ "Restringido",
+        /* fr      */ "Restreint",
+        /* it      */ "Con restrizioni",
+        /* ja      */ "制限付き",
+        /* ko      */ "제한됨",
+        /* pt_br   */ "Restrito",
+        /* ru      */ "Ограничена",
+        /* zh_hans */ "受限",
+        /* zh_hant */ "受限",
+    ],
+    // WorkspaceTrustRevoke
+    [
+        /* en      */ "Forget",
+        /* de      */ "Vergessen",
+        /* es      */ "Olvidar",
+        /* fr      */ "Oublier",
+        /* it      */ "Dimentica",
+        /* ja      */ "削除",
+        /* ko      */ "삭제",
+        /* pt_br   */ "Esquecer",
+        /* ru      */ "Забыть",
+        /* zh_hans */ "忘记",
+        /* zh_hant */ "忘記",
+    ],
+    // WorkspaceTrustEmpty
+    [
+        /* en      */ "No workspace has been trusted or restricted yet.",
+        /* de      */ "Bisher wurde keinem Arbeitsbereich vertraut oder eingeschränkt.",
+        /* es      */ "Aún no se ha confiado ni restringido ningún área de trabajo.",
+        /* fr      */ "Aucun espace de travail n’a encore été approuvé ou restreint.",
+        /* it      */ "Nessuna area di lavoro è stata ancora considerata attendibile o limitata.",
+        /* ja      */ "信頼または制限されたワークスペースはまだありません。",
+        /* ko      */ "아직 신뢰하거나 제한한 작업 영역이 없습니다.",
+        /* pt_br   */ "Nenhum espaço de trabalho foi confiado ou restringido ainda.",
+        /* ru      */ "Пока нет доверенных или ограниченных рабочих областей.",
+        /* zh_hans */ "尚未信任或限制任何工作区。",
+        /* zh_hant */ "尚未信任或限制任何工作區。",
+    ],
 ];
 
 static mut S_LANG: LangId = LangId::en;
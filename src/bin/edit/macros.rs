@@ -0,0 +1,354 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Keyboard macros: Edit > Record Macro (Alt+Q) records what's typed until it's pressed
+//! again, and Edit > Play Macro (Alt+P) plays it back. `:macro save NAME` keeps the last one
+//! in the `macros` folder of the state directory, and Edit > Macros lists those.
+//!
+//! A macro is the input as the terminal sent it, so it replays exactly what happened.
+//! Text like `{{name}}` typed while recording becomes a parameter, which is asked for
+//! when the macro is played. Saved macros can be bound to keys:
+//!
+//! ```toml
+//! [macro.wrap-div]
+//! key = "ctrl+alt+d"
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+
+use edit::apperr;
+use edit::config::Config;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::input::InputKey;
+use edit::tui::*;
+use regex::Regex;
+
+use crate::localization::*;
+use crate::state::*;
+use crate::wizard::{Wizard, WizardResult};
+
+pub const APP_MACRO_NOT_FOUND: apperr::Error = apperr::Error::new_app(3);
+
+/// The parameters that are asked for before a macro plays, one per wizard step.
+struct Prompt {
+    chunks: Vec<String>,
+    params: Vec<String>,
+    values: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Macros {
+    /// The input read while recording, one chunk per read.
+    recording: Option<Vec<String>>,
+    last: Vec<String>,
+    /// Waiting to be fed into the input loop by `main`.
+    playback: Vec<String>,
+    prompt: Option<Wizard<Prompt>>,
+    pub wants_list: bool,
+    /// The saved macros, listed while `wants_list` is set.
+    list: Vec<String>,
+    /// The keys bound to saved macros via the config.
+    keys: Vec<(InputKey, String)>,
+}
+
+impl Macros {
+    pub fn load_key_bindings(&mut self, config: &Config) {
+        self.keys = config
+            .subsections("macro")
+            .into_iter()
+            .filter_map(|name| {
+                let key = InputKey::parse(config.get_str(&format!("macro.{name}.key"))?)?;
+                Some((key, name.to_string()))
+            })
+            .collect();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn has_last(&self) -> bool {
+        !self.last.is_empty()
+    }
+
+    /// Starts recording, or stops and keeps the recording as the last macro.
+    pub fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(chunks) => self.last = merge_text(chunks),
+            None => self.recording = Some(Vec::new()),
+        }
+    }
+
+    /// Called by `main` with each batch of input that was processed while recording.
+    pub fn record(&mut self, input: &str) {
+        // Window size reports are injected on resize. They don't belong in a macro.
+        static WINDOW_SIZE: std::sync::LazyLock<Regex> =
+            std::sync::LazyLock::new(|| Regex::new(r"\x1b\[8;\d+;\d+t").unwrap());
+        if let Some(chunks) = &mut self.recording {
+            let input = WINDOW_SIZE.replace_all(input, "");
+            if !input.is_empty() {
+                chunks.push(input.into_owned());
+            }
+        }
+    }
+
+    /// Returns the input of the macro to play, if there's one. Called by `main` after each
+    /// batch of input. Each chunk must be parsed like a separate read from the terminal.
+    pub fn take_playback(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.playback)
+    }
+
+    pub fn play_last(&mut self) {
+        if !self.is_recording() {
+            self.play(self.last.clone());
+        }
+    }
+
+    pub fn play_saved(&mut self, name: &str) -> apperr::Result<()> {
+        let path = macro_path(name).ok_or(APP_MACRO_NOT_FOUND)?;
+        let text = fs::read_to_string(path).map_err(|_| APP_MACRO_NOT_FOUND)?;
+        self.play(text.lines().map(unescape).collect());
+        Ok(())
+    }
+
+    /// Plays a saved macro, if `key` is bound to one.
+    pub fn play_for_key(&mut self, key: InputKey) -> Option<apperr::Result<()>> {
+        let name = self.keys.iter().find(|(k, _)| *k == key)?.1.clone();
+        Some(self.play_saved(&name))
+    }
+
+    fn play(&mut self, chunks: Vec<String>) {
+        let params = parameters(&chunks);
+        if params.is_empty() {
+            self.playback = chunks;
+        } else {
+            let steps = params.len();
+            let values = vec![String::new(); steps];
+            self.prompt = Some(Wizard::new(Prompt { chunks, params, values }, steps));
+        }
+    }
+
+    /// Saves the last macro under `name`.
+    pub fn save_last(&self, name: &str) -> apperr::Result<()> {
+        let path = macro_path(name).ok_or(APP_MACRO_NOT_FOUND)?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        let text: String = self.last.iter().map(|chunk| escape(chunk) + "\n").collect();
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn show_list(&mut self) {
+        self.list = macros_dir()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| {
+                e.file_name().into_string().ok()?.strip_suffix(".macro").map(Into::into)
+            })
+            .collect();
+        self.list.sort();
+        self.wants_list = true;
+    }
+}
+
+fn macros_dir() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("macros"))
+}
+
+/// Returns `None` if `name` isn't a valid macro name.
+fn macro_path(name: &str) -> Option<PathBuf> {
+    let valid =
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| macros_dir().map(|dir| dir.join(format!("{name}.macro"))))?
+}
+
+/// Joins consecutive plain text, so that `{{name}}` is found even if it was typed one
+/// character at a time. Chunks with escape sequences stay as they are, since splitting
+/// them would change how they're parsed, but control characters like Enter get split off.
+fn merge_text(chunks: Vec<String>) -> Vec<String> {
+    let is_text = |s: &str| !s.chars().any(char::is_control);
+    let mut result: Vec<String> = Vec::new();
+    let mut push = |piece: &str| match result.last_mut() {
+        Some(last) if is_text(last) && is_text(piece) => last.push_str(piece),
+        _ => result.push(piece.to_string()),
+    };
+    for chunk in &chunks {
+        if chunk.contains('\x1b') {
+            push(chunk);
+            continue;
+        }
+        let mut rest = chunk.as_str();
+        while let Some(i) = rest.find(char::is_control) {
+            let len = rest[i..].chars().next().unwrap().len_utf8();
+            push(&rest[..i]);
+            push(&rest[i..i + len]);
+            rest = &rest[i + len..];
+        }
+        push(rest);
+    }
+    result.retain(|chunk| !chunk.is_empty());
+    result
+}
+
+/// Returns the distinct `{{name}}` parameters in the order they first appear.
+fn parameters(chunks: &[String]) -> Vec<String> {
+    let re = Regex::new(r"\{\{(\w+)\}\}").unwrap();
+    let mut result: Vec<String> = Vec::new();
+    for caps in chunks.iter().flat_map(|c| re.captures_iter(c)) {
+        if !result.iter().any(|p| *p == caps[1]) {
+            result.push(caps[1].to_string());
+        }
+    }
+    result
+}
+
+fn substitute(chunks: &[String], params: &[String], values: &[String]) -> Vec<String> {
+    let mut result = chunks.to_vec();
+    for chunk in &mut result {
+        for (param, value) in params.iter().zip(values) {
+            *chunk = chunk.replace(&format!("{{{{{param}}}}}"), value);
+        }
+    }
+    result
+}
+
+/// Saved macros have one chunk per line, with control characters escaped.
+pub fn escape(chunk: &str) -> String {
+    let mut result = String::with_capacity(chunk.len());
+    for c in chunk.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\x1b' => result.push_str("\\e"),
+            '\r' => result.push_str("\\r"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_control() => result.push_str(&format!("\\x{:02x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+pub fn unescape(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('e') => result.push('\x1b'),
+            Some('r') => result.push('\r'),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    result.push(c);
+                }
+            }
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+pub fn draw_macro_list(ctx: &mut Context, state: &mut State) {
+    let mut chosen = None;
+
+    ctx.modal_begin("macros", loc(LocId::EditMacros));
+    {
+        let width = (ctx.size().width - 20).clamp(10, 60);
+        let height = (ctx.size().height - 10).clamp(1, state.macros.list.len().max(1) as _);
+
+        ctx.scrollarea_begin("scrollarea", Size { width, height });
+        ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+        ctx.inherit_focus();
+        {
+            ctx.list_begin("names");
+            ctx.inherit_focus();
+            for (i, name) in state.macros.list.iter().enumerate() {
+                if ctx.list_item(false, name) == ListSelection::Activated {
+                    chosen = Some(i);
+                }
+            }
+            ctx.list_end();
+        }
+        ctx.scrollarea_end();
+    }
+    let done = ctx.modal_end();
+
+    if let Some(i) = chosen {
+        let name = state.macros.list[i].clone();
+        if let Err(err) = state.macros.play_saved(&name) {
+            error_log_add(ctx, state, err);
+        }
+    }
+    if done || chosen.is_some() {
+        state.macros.wants_list = false;
+        ctx.needs_rerender();
+    }
+}
+
+pub fn draw_macro_prompt(ctx: &mut Context, state: &mut State) {
+    let Some(wizard) = &mut state.macros.prompt else {
+        return;
+    };
+
+    let result =
+        wizard.draw(ctx, "macro-prompt", loc(LocId::MacroParameters), |ctx, prompt, step| {
+            ctx.table_begin("fields");
+            ctx.inherit_focus();
+            ctx.table_set_cell_gap(Size { width: 1, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+                ctx.label("name", &prompt.params[step]);
+                ctx.editline("value", &mut prompt.values[step]);
+                ctx.attr_intrinsic_size(Size { width: 32, height: 1 });
+                ctx.focus_on_first_present();
+            }
+            ctx.table_end();
+            true
+        });
+
+    match result {
+        WizardResult::Running => {}
+        WizardResult::Finished => {
+            let prompt = state.macros.prompt.take().unwrap().data;
+            state.macros.playback = substitute(&prompt.chunks, &prompt.params, &prompt.values);
+        }
+        WizardResult::Cancelled => state.macros.prompt = None,
+    }
+}
+
+pub fn is_prompting(state: &State) -> bool {
+    state.macros.prompt.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macro_parameters() {
+        let typed = ["<", "{", "{", "tag", "}}>\r</", "{{tag}}>", "\x1b[D"];
+        let chunks = merge_text(typed.map(String::from).to_vec());
+        assert_eq!(chunks, ["<{{tag}}>", "\r", "</{{tag}}>", "\x1b[D"]);
+        assert_eq!(parameters(&chunks), ["tag"]);
+        assert_eq!(
+            substitute(&chunks, &["tag".to_string()], &["div".to_string()]),
+            ["<div>", "\r", "</div>", "\x1b[D"]
+        );
+
+        for chunk in ["a\\b", "\x1b[1;5D", "\r\n\t", "\x7f"] {
+            assert!(!escape(chunk).contains(['\n', '\x1b']));
+            assert_eq!(unescape(&escape(chunk)), chunk);
+        }
+    }
+}
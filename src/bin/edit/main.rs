@@ -5,51 +5,95 @@
     allocator_api,
     let_chains,
     linked_list_cursors,
-    os_string_truncate,
     string_from_utf8_lossy_owned
 )]
-
+#![allow(stable_features)]
+
+mod alternate_file;
+mod backup;
+mod clipboard;
+mod clipboard_history;
+mod clipboard_locations;
+mod command_line;
+mod commit_message;
+mod config_completion;
+mod config_problems;
 mod documents;
 mod draw_editor;
 mod draw_filepicker;
 mod draw_menubar;
 mod draw_statusbar;
 mod draw_tabs;
+mod file_watch;
+mod formatter;
+mod help;
+mod jump;
+mod jump_list;
+mod keymap;
+mod line_search;
+mod linter;
 mod localization;
+mod macros;
+mod notes;
+mod pager;
+mod project_search;
+mod review;
+mod remote;
+mod save_transforms;
+mod staged;
 mod state;
+mod sudo;
+mod swap;
+mod tasks;
+mod wizard;
+mod workspace_trust;
 
 use std::borrow::Cow;
 #[cfg(feature = "debug-latency")]
 use std::fmt::Write;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use std::{env, process};
 
+use command_line::*;
+use commit_message::CommitHistory;
+use documents::DocumentManager;
 use draw_editor::*;
 use draw_filepicker::*;
 use draw_menubar::*;
 use draw_statusbar::*;
 use draw_tabs::*;
 use edit::arena::{self, Arena, ArenaString, scratch_arena};
-use edit::framebuffer::{self, IndexedColor};
-use edit::helpers::{KIBI, MEBI, MetricFormatter, Rect, Size};
+use edit::config::{self, Config, Density};
+use edit::framebuffer::{self, Attributes, IndexedColor};
+use edit::helpers::{CoordType, KIBI, MEBI, MetricFormatter, Point, Rect, Size};
 use edit::input::{self, kbmod, vk};
 use edit::oklab::oklab_blend;
 use edit::tui::*;
 use edit::vt::{self, Token};
-use edit::{apperr, arena_format, base64, path, sys};
+use edit::{apperr, arena_format, path, sys};
+use help::HelpPage;
+use remote::RemotePath;
 use localization::*;
 use state::*;
+use workspace_trust::*;
 
 #[cfg(target_pointer_width = "32")]
 const SCRATCH_ARENA_CAPACITY: usize = 128 * MEBI;
 #[cfg(target_pointer_width = "64")]
 const SCRATCH_ARENA_CAPACITY: usize = 512 * MEBI;
 
+/// A round trip to the terminal that takes this long means a slow connection, e.g. over SSH.
+/// Terminals that take this long to answer at startup get the low-bandwidth mode.
+const HIGH_LATENCY: Duration = Duration::from_millis(100);
+/// With `--low-bandwidth`, frames are sent at most this often.
+const LOW_BANDWIDTH_FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
 fn main() -> process::ExitCode {
     if cfg!(debug_assertions) {
         let hook = std::panic::take_hook();
         std::panic::set_hook(Box::new(move |info| {
-            drop(RestoreModes);
+            drop(RestoreModes { alternate_screen: true });
             drop(sys::Deinit);
             hook(info);
         }));
@@ -71,11 +115,22 @@ fn run() -> apperr::Result<()> {
     arena::init(SCRATCH_ARENA_CAPACITY)?;
     // Init the `loc` module, so that error messages are localized.
     localization::init();
+    // Load the user's config.toml (if any) before anything reads settings from it.
+    // `--safe` must be known before that, so it's looked for ahead of the other arguments.
+    if env::args_os().skip(1).any(|arg| arg == "--safe") {
+        config::enable_safe_mode();
+    }
+    config::init(Config::load());
 
     let mut state = State::new()?;
+    state.macros.load_key_bindings(config::get());
     if handle_args(&mut state)? {
         return Ok(());
     }
+    if !state.pager.enabled {
+        config_problems::report(&mut state)?;
+        swap::scan(&mut state);
+    }
 
     // sys::init() will switch the terminal to raw mode which prevents the user from pressing Ctrl+C.
     // Since the `read_file` call may hang for some reason, we must only call this afterwards.
@@ -87,13 +142,40 @@ fn run() -> apperr::Result<()> {
     let mut input_parser = input::Parser::new();
     let mut tui = Tui::new()?;
 
-    let _restore = setup_terminal(&mut tui, &mut vt_parser);
+    state.inline_mode = !config::get().get_bool("ui.alternate_screen").unwrap_or(true);
+    let setup_beg = Instant::now();
+    let _restore = setup_terminal(&mut tui, &mut vt_parser, !state.inline_mode);
+    // The setup waits for the terminal's answer to a DA1 query, which makes for a round trip.
+    // `ui.low_bandwidth = false` turns the detection off.
+    state.low_bandwidth |= config::get()
+        .get_bool("ui.low_bandwidth")
+        .unwrap_or(setup_beg.elapsed() >= HIGH_LATENCY);
+    tui.set_low_bandwidth(state.low_bandwidth);
+    let term = env::var("TERM").unwrap_or_default();
+    tui.setup_supported_attributes(supported_attributes(&term, config::get()));
 
     state.menubar_color_bg = oklab_blend(
         tui.indexed(IndexedColor::Background),
         tui.indexed_alpha(IndexedColor::BrightBlue, 1, 2),
     );
     state.menubar_color_fg = tui.contrasted(state.menubar_color_bg);
+    state.statusbar_color_fg_inactive = oklab_blend(
+        state.menubar_color_bg,
+        (state.menubar_color_fg & 0x00ffffff) | 0x99000000,
+    );
+    if let Some(limit) = config::get().get_int("clipboard.osc52_limit") {
+        state.osc_clipboard_limit = limit.max(0) as usize;
+    }
+    state.osc_clipboard_read = config::get().get_bool("clipboard.osc52_read").unwrap_or(false);
+    if let Some(percent) = config::get().get_int("ui.dim_inactive") {
+        tui.set_inactive_dim(percent.clamp(0, 100) as u32);
+    }
+    if let Some(scrollbars) = config::get().get_str("ui.scrollbars") {
+        tui.set_textarea_scrollbars(Scrollbars {
+            vertical: matches!(scrollbars, "vertical" | "both"),
+            horizontal: matches!(scrollbars, "horizontal" | "both"),
+        });
+    }
     let floater_bg = oklab_blend(
         tui.indexed_alpha(IndexedColor::Background, 2, 3),
         tui.indexed_alpha(IndexedColor::Foreground, 1, 3),
@@ -108,11 +190,15 @@ fn run() -> apperr::Result<()> {
     tui.set_floater_default_fg(floater_fg);
     tui.set_modal_default_bg(floater_bg);
     tui.set_modal_default_fg(floater_fg);
+    tui.set_modal_fullscreen_below(StateBreakpoint::SMALL_BELOW);
 
     sys::inject_window_size_into_stdin();
 
     #[cfg(feature = "debug-latency")]
     let mut last_latency_width = 0;
+    let mut last_frame = Instant::now();
+    let mut frame_pending = false;
+    let mut late_input = String::new();
 
     loop {
         #[cfg(feature = "debug-latency")]
@@ -123,23 +209,56 @@ fn run() -> apperr::Result<()> {
         // Process a batch of input.
         {
             let scratch = scratch_arena(None);
-            let read_timeout = vt_parser.read_timeout().min(tui.read_timeout());
+            let read_timeout = vt_parser
+                .read_timeout()
+                .min(tui.read_timeout())
+                .min(state.linters.read_timeout())
+                .min(state.tasks.read_timeout())
+                .min(state.remote.read_timeout())
+                .min(state.swap.read_timeout(&state))
+                .min(state.staged.read_timeout(&state))
+                .min(state.pager.read_timeout())
+                .min(state.project_search.read_timeout())
+                .min(state.file_watch.read_timeout(&state.documents))
+                .min(if frame_pending {
+                    LOW_BANDWIDTH_FRAME_INTERVAL.saturating_sub(last_frame.elapsed())
+                } else {
+                    Duration::MAX
+                });
             let Some(input) = sys::read_stdin(&scratch, read_timeout) else {
                 break;
             };
 
+            linter::update(&mut state);
+            tasks::update(&mut state);
+            remote::update(&mut state);
+            pager::update(&mut state);
+            project_search::update(&mut state);
+            file_watch::update(&mut state);
+            if !state.pager.enabled {
+                swap::update(&mut state);
+                staged::update(&mut state);
+            }
+
             #[cfg(feature = "debug-latency")]
             {
                 time_beg = std::time::Instant::now();
                 passes = 0usize;
             }
 
+            // Input that starts or stops the recording isn't part of the macro.
+            let recording = state.macros.is_recording();
             let vt_iter = vt_parser.parse(&input);
             let mut input_iter = input_parser.parse(vt_iter);
 
             while {
                 let input = input_iter.next();
                 let more = input.is_some();
+                if let Some(input::Input::Keyboard(key)) = input
+                    && key == kbmod::CTRL | vk::V
+                {
+                    refresh_clipboard(&mut tui, &mut state, &mut late_input);
+                }
                 let mut ctx = tui.create_context(input);
 
                 draw(&mut ctx, &mut state);
@@ -151,8 +270,20 @@ fn run() -> apperr::Result<()> {
 
                 more
             } {}
+
+            if recording && state.macros.is_recording() {
+                state.macros.record(&input);
+            }
+        }
+
+        // Input that arrived while waiting for the terminal's clipboard.
+        if !late_input.is_empty() {
+            let chunk = std::mem::take(&mut late_input);
+            process_input(&mut tui, &mut state, &chunk);
         }
 
+        play_macro(&mut tui, &mut state);
+
         // Continue rendering until the layout has settled.
         // This can take >1 frame, if the input focus is tossed between different controls.
         while tui.needs_settling() {
@@ -170,6 +301,15 @@ fn run() -> apperr::Result<()> {
             break;
         }
 
+        // On slow connections, input that arrives in quick succession, like a held down key,
+        // is shown in one frame instead of sending all the ones in between.
+        if state.low_bandwidth && last_frame.elapsed() < LOW_BANDWIDTH_FRAME_INTERVAL {
+            frame_pending = true;
+            continue;
+        }
+        frame_pending = false;
+        last_frame = Instant::now();
+
         // Render the UI and write it to the terminal.
         {
             let scratch = scratch_arena(None);
@@ -183,10 +323,6 @@ fn run() -> apperr::Result<()> {
                 }
             }
 
-            if state.osc_clipboard_send_generation == tui.clipboard_generation() {
-                write_osc_clipboard(&mut output, &mut state, &tui);
-            }
-
             #[cfg(feature = "debug-latency")]
             {
                 // Print the number of passes and latency in the top right corner.
@@ -228,67 +364,234 @@ fn run() -> apperr::Result<()> {
 
             sys::write_stdout(&output);
         }
+
+        if state.osc_clipboard_send_generation == tui.clipboard_generation() {
+            write_osc_clipboard(&mut state, &tui);
+        }
+    }
+
+    // Uploads that are still running would be cut off.
+    remote::wait(&mut state)?;
+    swap::remove_all(&mut state);
+
+    if let Some(text) = &state.exit_output {
+        // Clear the editor's last frame and leave the document in its place.
+        sys::write_stdout("\x1b[0m\x1b[H\x1b[2J");
+        print_raw(text);
     }
 
     Ok(())
 }
 
+/// Prints `text` to the terminal, which is in raw mode and doesn't translate LF to CRLF.
+fn print_raw(text: &str) {
+    sys::write_stdout(&text.replace('\n', "\r\n"));
+}
+
+/// Prints the active document to the regular screen, so that it's in the terminal's scrollback
+/// after the editor quits. The editor's screen gets redrawn afterwards.
+fn print_to_scrollback(ctx: &mut Context, state: &State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let text = doc.export_ansi();
+    if state.inline_mode {
+        // Print it below the editor and then scroll it off the screen.
+        sys::write_stdout(&format!("\x1b[0m\x1b[{}H\r\n", ctx.size().height));
+        print_raw(&text);
+        print_raw(&"\n".repeat(ctx.size().height as usize));
+    } else {
+        // The regular screen has its cursor where it was when the editor was started.
+        sys::write_stdout("\x1b[?1049l");
+        print_raw(&text);
+        sys::write_stdout("\x1b[?1049h");
+    }
+    ctx.invalidate_screen();
+}
+
 // Returns true if the application should exit early.
 fn handle_args(state: &mut State) -> apperr::Result<bool> {
     let scratch = scratch_arena(None);
-    let mut paths: Vec<PathBuf, &Arena> = Vec::new_in(&*scratch);
+    let mut paths: Vec<(PathBuf, Option<Point>), &Arena> = Vec::new_in(&*scratch);
+    // `+42` or `+42:7` before a file, like Vim and Emacs take it.
+    let mut goto = None;
+    let mut remotes = Vec::new();
     let mut cwd = env::current_dir()?;
+    let mut export_html = false;
+    let mut cat = false;
+    let mut theme = None;
+    let mut man = None;
+    let mut read_only = false;
 
     // The best CLI argument parser in the world.
-    for arg in env::args_os().skip(1) {
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
         if arg == "-h" || arg == "--help" || (cfg!(windows) && arg == "/?") {
             print_help();
             return Ok(true);
         } else if arg == "-v" || arg == "--version" {
             print_version();
             return Ok(true);
+        } else if arg == "--export-html" {
+            export_html = true;
+            continue;
+        } else if arg == "--cat" {
+            cat = true;
+            continue;
+        } else if arg == "--pager" {
+            state.pager.enabled = true;
+            continue;
+        } else if arg == "--readonly" {
+            read_only = true;
+            continue;
+        } else if arg == "--low-bandwidth" {
+            state.low_bandwidth = true;
+            continue;
+        } else if arg == "--safe" {
+            // Handled in `run()`.
+            continue;
+        } else if arg == "--man" {
+            // `--man ls` or `--man 1 ls`, like `man` itself.
+            let mut words = args.by_ref().map(|a| a.to_string_lossy().into_owned());
+            let page = match (words.next(), words.next()) {
+                (Some(section), Some(name)) => HelpPage::Man { name, section: Some(section) },
+                (Some(name), None) => HelpPage::Man { name, section: None },
+                (None, _) => HelpPage::Topic("index"),
+            };
+            man = Some(page);
+            break;
+        } else if arg == "--theme" {
+            theme = args.next().map(|t| t.to_string_lossy().into_owned());
+            continue;
+        } else if let Some(t) = arg.to_str().and_then(|a| a.strip_prefix("--theme=")) {
+            theme = Some(t.to_string());
+            continue;
         } else if arg == "-" {
             paths.clear();
             break;
+        } else if let Some(point) = arg.to_str().and_then(parse_plus_goto) {
+            goto = Some(point);
+            continue;
+        }
+        if let Some(target) = arg.to_str().and_then(RemotePath::parse)
+            && !Path::new(&arg).exists()
+        {
+            remotes.push(target);
+            continue;
         }
         let p = cwd.join(Path::new(&arg));
         let p = path::normalize(&p);
         if !p.is_dir() {
-            paths.push(p);
+            paths.push((p, goto.take()));
         }
     }
 
-    for p in &paths {
-        state.documents.add_file_path(p)?;
+    for (p, goto) in &paths {
+        let doc = state.documents.add_file_path(p)?;
+        if let Some(goto) = *goto {
+            doc.buffer.borrow_mut().cursor_move_to_logical(goto);
+        }
+        if (cat || export_html) && doc.file_id.is_none() {
+            // The editor would offer to create a missing file. Here we report why it's missing.
+            DocumentManager::open_for_reading(doc.path.as_deref().unwrap())?;
+        }
+        if let Some(theme) = &theme {
+            doc.buffer.borrow_mut().set_theme(Some(theme.clone()));
+        }
+        if read_only {
+            doc.buffer.borrow_mut().set_read_only(true);
+        }
+    }
+    for target in remotes.drain(..) {
+        remote::open(state, target, read_only)?;
+        if let Some(theme) = &theme {
+            state.documents.active().unwrap().buffer.borrow_mut().set_theme(Some(theme.clone()));
+        }
+    }
+    if cat || export_html {
+        // Both need the remote files' contents.
+        remote::wait(state)?;
     }
-    if let Some(parent) = paths.first().and_then(|p| p.parent()) {
+    if cat {
+        // The most recently opened document is the active one, so we go backwards.
+        for doc in state.documents.all_documents().iter().rev() {
+            sys::write_stdout(&doc.export_ansi());
+        }
+        return Ok(true);
+    }
+    if export_html {
+        for doc in state.documents.all_documents() {
+            let dst = doc.export_html()?;
+            sys::write_stdout(&format!("{}\r\n", dst.display()));
+        }
+        return Ok(true);
+    }
+    if let Some(parent) = paths.first().and_then(|(p, _)| p.parent()) {
         cwd = parent.to_path_buf();
     }
 
     if let Some(mut file) = sys::open_stdin_if_redirected() {
         let doc = state.documents.add_untitled()?;
-        let mut tb = doc.buffer.borrow_mut();
-        tb.read_file(&mut file, None)?;
-        tb.mark_as_dirty();
-    } else if paths.is_empty() {
+        {
+            let mut tb = doc.buffer.borrow_mut();
+            tb.read_file(&mut file, None)?;
+            if !state.pager.enabled {
+                tb.mark_as_dirty();
+            }
+        }
+        doc.detect_stack_trace();
+    } else if let Some(page) = man {
+        help::open(state, page)?;
+        state.pager.enabled = true;
+    } else if paths.is_empty() && state.documents.len() == 0 {
         // No files were passed, and stdin is not redirected.
         state.documents.add_untitled()?;
     }
 
+    if state.pager.enabled {
+        for doc in state.documents.all_documents() {
+            let mut tb = doc.buffer.borrow_mut();
+            tb.set_read_only(true);
+            // Like `less -R`, so that colored output from `git log` and friends looks right.
+            if doc.help.is_empty() {
+                tb.set_ansi_view(true);
+            }
+        }
+    }
+
     state.file_picker_pending_dir = DisplayablePathBuf::from_path(cwd);
     state.file_picker_pending_dir_revision = state.file_picker_pending_dir_revision.wrapping_add(1);
     Ok(false)
 }
 
+/// Parses `+LINE` and `+LINE:COLUMN` into a 0-based position.
+fn parse_plus_goto(arg: &str) -> Option<Point> {
+    let arg = arg.strip_prefix('+')?;
+    let (line, column) = arg.split_once(':').unwrap_or((arg, "1"));
+    let y = line.parse::<CoordType>().ok()?;
+    let x = column.parse::<CoordType>().ok()?;
+    Some(Point { x: x.max(1) - 1, y: y.max(1) - 1 })
+}
+
 fn print_help() {
     sys::write_stdout(concat!(
-        "Usage: edit [OPTIONS] [FILE[:LINE[:COLUMN]]]\r\n",
+        "Usage: edit [OPTIONS] [+LINE[:COLUMN]] [FILE[:LINE[:COLUMN]]]\r\n",
         "Options:\r\n",
         "    -h, --help       Print this help message\r\n",
         "    -v, --version    Print the version number\r\n",
+        "    --cat            Print each FILE highlighted with ANSI colors and exit\r\n",
+        "    --export-html    Write each FILE as highlighted HTML to FILE.html and exit\r\n",
+        "    --pager          View FILE or stdin read-only, with less-like keys (q, /, n, F, ...)\r\n",
+        "    --readonly       Open each FILE read-only, so that it isn't changed by accident\r\n",
+        "    --low-bandwidth  Send fewer updates to the terminal, for slow connections\r\n",
+        "    --safe           Ignore all config files, to rule them out as the cause of a problem\r\n",
+        "    --theme NAME     Highlight FILE with the given syntax theme\r\n",
+        "    --man [SECTION] NAME  Read a man page with less-like keys\r\n",
         "\r\n",
         "Arguments:\r\n",
         "    FILE[:LINE[:COLUMN]]    The file to open, optionally with line and column (e.g., foo.txt:123:45)\r\n",
+        "    +LINE[:COLUMN]          Where to put the cursor in the FILE after it (e.g., +123 foo.txt)\r\n",
+        "    [USER@]HOST:PATH        A file to open over SSH (e.g., root@example.com:/etc/hosts)\r\n",
     ));
 }
 
@@ -296,7 +599,36 @@ fn print_version() {
     sys::write_stdout(concat!("edit version ", env!("CARGO_PKG_VERSION"), "\r\n"));
 }
 
+/// Feeds the input of a macro that was asked to play through the UI, as if it was typed.
+fn play_macro(tui: &mut Tui, state: &mut State) {
+    for chunk in state.macros.take_playback() {
+        // The focus must be back where it was, e.g. after the parameter prompt closed.
+        while tui.needs_settling() {
+            let mut ctx = tui.create_context(None);
+            draw(&mut ctx, state);
+        }
+        process_input(tui, state, &chunk);
+    }
+}
+
+/// Processes `chunk` as a separate read from the terminal.
+fn process_input(tui: &mut Tui, state: &mut State, chunk: &str) {
+    // Each chunk was a separate read, so a trailing escape is the Escape key.
+    let mut vt_parser = vt::Parser::new();
+    let mut input_parser = input::Parser::new();
+    for text in [chunk, ""] {
+        for input in input_parser.parse(vt_parser.parse(text)) {
+            let mut ctx = tui.create_context(Some(input));
+            draw(&mut ctx, state);
+        }
+    }
+}
+
 fn draw(ctx: &mut Context, state: &mut State) {
+    state.jump_list.track(&state.documents);
+    update_layout(ctx, state);
+    // Edits rerender the frame, which shows their problems right away.
+    config_problems::update(state);
     draw_menubar(ctx, state);
     draw_ghibli_tab_bar(ctx, state); // Add our magical Ghibli-themed tab bar
     draw_editor(ctx, state);
@@ -311,21 +643,76 @@ fn draw(ctx: &mut Context, state: &mut State) {
     if state.wants_goto {
         draw_goto_menu(ctx, state);
     }
+    if state.wants_command_line {
+        draw_command_line(ctx, state);
+    }
+    if state.substitute_confirm.is_some() {
+        draw_substitute_confirm(ctx, state);
+    }
+    if !state.wants_replace_in_tabs.is_empty() {
+        draw_dialog_replace_in_tabs(ctx, state);
+    }
+    if macros::is_prompting(state) {
+        macros::draw_macro_prompt(ctx, state);
+    }
+    if state.macros.wants_list {
+        macros::draw_macro_list(ctx, state);
+    }
+    if state.note_dialog.is_some() {
+        notes::draw_dialog(ctx, state);
+    }
+    if state.wants_note_list {
+        notes::draw_list(ctx, state);
+    }
+    if state.wants_task_history {
+        tasks::draw_history(ctx, state);
+    }
+    if state.clipboard_history.wants_list {
+        clipboard_history::draw_list(ctx, state);
+    }
     if state.wants_file_picker != StateFilePicker::None {
         draw_file_picker(ctx, state);
     }
     if state.wants_save {
         draw_handle_save(ctx, state);
     }
+    if state.wants_sudo_save {
+        sudo::draw_dialog(ctx, state);
+    }
     if state.wants_encoding_change != StateEncodingChange::None {
         draw_dialog_encoding_change(ctx, state);
     }
     if state.wants_document_picker {
         draw_document_picker(ctx, state);
     }
+    if state.config_completions.is_open() {
+        config_completion::draw_list(ctx, state);
+    }
+    if !state.wants_clipboard_locations.is_empty() {
+        clipboard_locations::draw_clipboard_locations(ctx, state);
+    }
     if state.wants_about {
         draw_dialog_about(ctx, state);
     }
+    if state.wants_cheat_sheet {
+        keymap::draw_cheat_sheet(ctx, state);
+    }
+    if state.wants_trust_prompt.is_some() {
+        draw_dialog_trust_prompt(ctx, state);
+    }
+    if state.wants_workspace_trust {
+        draw_dialog_workspace_trust(ctx, state);
+    }
+    if state.documents.active().is_some_and(|doc| doc.changed_on_disk) {
+        file_watch::draw_dialog_file_changed(ctx, state);
+    }
+    if state.swap.has_pending() {
+        swap::draw_dialog(ctx, state);
+    }
+    if state.staged.has_unsaved() {
+        staged::draw_dialog(ctx, state);
+    }
+    clipboard_history::update(ctx, state);
     if state.osc_clipboard_seen_generation != ctx.clipboard_generation() {
         draw_handle_clipboard_change(ctx, state);
     }
@@ -352,6 +739,63 @@ fn draw(ctx: &mut Context, state: &mut State) {
             state.wants_exit = true;
         } else if key == kbmod::CTRL | vk::G {
             state.wants_goto = true;
+        } else if key == kbmod::CTRL_SHIFT | vk::P {
+            state.wants_command_line = true;
+        } else if key == kbmod::ALT | vk::O {
+            draw_switch_to_alternate_file(ctx, state);
+        } else if key == kbmod::CTRL_SHIFT | vk::B
+            && let Some(command) = state.tasks.last()
+        {
+            let command = command.to_string();
+            state.tasks.start(&command);
+        } else if key == kbmod::CTRL_SHIFT | vk::I
+            && let Some(doc) = state.documents.active()
+        {
+            doc.buffer.borrow_mut().reindent();
+        } else if key == kbmod::CTRL | vk::OEM_2
+            && let Some(doc) = state.documents.active()
+        {
+            doc.buffer.borrow_mut().toggle_line_comment();
+        } else if key == kbmod::ALT_SHIFT | vk::A
+            && let Some(doc) = state.documents.active()
+        {
+            doc.buffer.borrow_mut().toggle_block_comment();
+        } else if key == kbmod::ALT_SHIFT | vk::F
+            && let Some(doc) = state.documents.active()
+        {
+            let mut tb = doc.buffer.borrow_mut();
+            if tb.toggle_fold() {
+                tb.make_cursor_visible();
+            }
+        } else if key == kbmod::CTRL_SHIFT | vk::D
+            && let Some(doc) = state.documents.active()
+        {
+            doc.buffer.borrow_mut().duplicate();
+        } else if (key == kbmod::CTRL | vk::SPACE || key == vk::NULL)
+            && let Some(doc) = state.documents.active()
+            && commit_message::is_commit_message(&doc.filename)
+        {
+            // Terminals send Ctrl+Space as NUL.
+            let history = state.commit_history.get_or_insert_with(|| {
+                let dir = doc.dir.as_ref().map_or(Path::new("."), |d| d.as_path());
+                CommitHistory::load(dir)
+            });
+            commit_message::complete(&mut doc.buffer.borrow_mut(), history);
+        } else if (key == kbmod::CTRL | vk::SPACE || key == vk::NULL)
+            && state.documents.active().is_some_and(config_problems::is_config)
+        {
+            config_completion::complete(state);
+        } else if key == kbmod::CTRL | vk::F
+            && state.breakpoint == StateBreakpoint::Small
+            && state.wants_search.kind == StateSearchKind::Search
+        {
+            // On small terminals the search bar is an overlay, so its shortcut toggles it.
+            state.wants_search.kind = StateSearchKind::Hidden;
+        } else if key == kbmod::CTRL | vk::R
+            && state.breakpoint == StateBreakpoint::Small
+            && state.wants_search.kind == StateSearchKind::Replace
+        {
+            state.wants_search.kind = StateSearchKind::Hidden;
         } else if key == kbmod::CTRL | vk::F && state.wants_search.kind != StateSearchKind::Disabled
         {
             state.wants_search.kind = StateSearchKind::Search;
@@ -360,6 +804,37 @@ fn draw(ctx: &mut Context, state: &mut State) {
         {
             state.wants_search.kind = StateSearchKind::Replace;
             state.wants_search.focus = true;
+        } else if key == kbmod::ALT | vk::L && state.documents.active().is_some() {
+            state.wants_line_search = true;
+        } else if key == kbmod::CTRL_SHIFT | vk::F {
+            state.wants_project_search = true;
+        } else if key == kbmod::ALT | vk::J && state.documents.active().is_some() {
+            state.wants_jump = true;
+        } else if key == kbmod::ALT | vk::T
+            && let Some(doc) = state.documents.active()
+        {
+            let mut tb = doc.buffer.borrow_mut();
+            let pos = tb.cursor_logical_pos();
+            if tb.jump_to_matching_tag() {
+                state.jump_list.push_at(&doc.buffer, pos);
+                tb.make_cursor_visible();
+            }
+        } else if key == kbmod::ALT | vk::OEM_COMMA {
+            state.jump_list.back(&mut state.documents);
+        } else if key == kbmod::ALT | vk::OEM_PERIOD {
+            state.jump_list.forward(&mut state.documents);
+        } else if key == kbmod::ALT | vk::Q {
+            state.macros.toggle_recording();
+        } else if key == kbmod::ALT | vk::P {
+            state.macros.play_last();
+        } else if key == kbmod::ALT | vk::N && state.documents.active().is_some() {
+            notes::open_dialog(state);
+        } else if let Some(result) = state.macros.play_for_key(key) {
+            if let Err(err) = result {
+                error_log_add(ctx, state, err);
+            }
+        } else if key == keymap::CHEAT_SHEET_KEY {
+            state.wants_cheat_sheet = !state.wants_cheat_sheet;
         } else {
             return;
         }
@@ -370,12 +845,29 @@ fn draw(ctx: &mut Context, state: &mut State) {
     }
 }
 
+/// Picks the active document's density, unless the terminal is too narrow for it.
+fn update_layout(ctx: &Context, state: &mut State) {
+    state.breakpoint = StateBreakpoint::for_size(ctx.size());
+    let auto_compact_width = config::get().auto_compact_width();
+    state.density = match state.documents.active() {
+        _ if ctx.size().width < auto_compact_width => Density::Compact,
+        Some(doc) => doc.density,
+        None => Density::Normal,
+    };
+    if let Some(doc) = state.documents.active() {
+        doc.buffer.borrow_mut().set_density(state.density);
+    }
+}
+
 fn draw_handle_wants_exit(_ctx: &mut Context, state: &mut State) {
     while let Some(doc) = state.documents.active() {
-        if doc.buffer.borrow().is_dirty() {
+        if doc.buffer.borrow().is_dirty() && !doc.scratch && !state.remote.is_saving(doc) {
             state.wants_close = true;
             return;
         }
+        if state.inline_mode && state.exit_output.is_none() {
+            state.exit_output = Some(doc.export_ansi());
+        }
         state.documents.remove_active();
     }
 
@@ -400,39 +892,51 @@ const LARGE_CLIPBOARD_THRESHOLD: usize = 4 * KIBI;
 
 fn draw_handle_clipboard_change(ctx: &mut Context, state: &mut State) {
     let generation = ctx.clipboard_generation();
+    let len = ctx.clipboard().len();
+    let over_limit = len > state.osc_clipboard_limit;
 
-    if state.osc_clipboard_always_send || ctx.clipboard().len() < LARGE_CLIPBOARD_THRESHOLD {
+    if !over_limit && (state.osc_clipboard_always_send || len < LARGE_CLIPBOARD_THRESHOLD) {
         state.osc_clipboard_seen_generation = generation;
         state.osc_clipboard_send_generation = generation;
+        state.osc_clipboard_shared_generation = generation;
         return;
     }
 
-    let over_limit = ctx.clipboard().len() >= SCRATCH_ARENA_CAPACITY / 4;
+    // Large copies go to the system clipboard directly where possible.
+    // It's quicker than OSC 52 and doesn't have the terminal's size limit.
+    if state.osc_clipboard_system_generation != generation {
+        state.osc_clipboard_system_generation = generation;
+        if clipboard::write_system(ctx.clipboard()) {
+            state.osc_clipboard_seen_generation = generation;
+            state.osc_clipboard_shared_generation = generation;
+            return;
+        }
+    }
+
+    fn with_size<'a>(ctx: &Context<'a, '_>, template: &str, size: usize) -> ArenaString<'a> {
+        let size = arena_format!(ctx.arena(), "{}", MetricFormatter(size));
+        let mut label = ArenaString::with_capacity_in(template.len() + size.len(), ctx.arena());
+        label.push_str(template);
+        label.replace_once_in_place("{size}", &size);
+        label
+    }
 
     ctx.modal_begin("warning", loc(LocId::WarningDialogTitle));
     {
         ctx.block_begin("description");
         ctx.attr_padding(Rect::three(1, 2, 1));
 
+        ctx.label("line1", loc(LocId::LargeClipboardWarningLine1));
+        ctx.attr_position(Position::Center);
         if over_limit {
-            ctx.label("line1", loc(LocId::LargeClipboardWarningLine1));
-            ctx.attr_position(Position::Center);
+            let label3 =
+                with_size(ctx, loc(LocId::LargeClipboardTruncate), state.osc_clipboard_limit);
             ctx.label("line2", loc(LocId::SuperLargeClipboardWarning));
             ctx.attr_position(Position::Center);
-        } else {
-            let label2 = {
-                let template = loc(LocId::LargeClipboardWarningLine2);
-                let size = arena_format!(ctx.arena(), "{}", MetricFormatter(ctx.clipboard().len()));
-
-                let mut label =
-                    ArenaString::with_capacity_in(template.len() + size.len(), ctx.arena());
-                label.push_str(template);
-                label.replace_once_in_place("{size}", &size);
-                label
-            };
-
-            ctx.label("line1", loc(LocId::LargeClipboardWarningLine1));
+            ctx.label("line3", &label3);
             ctx.attr_position(Position::Center);
+        } else {
+            let label2 = with_size(ctx, loc(LocId::LargeClipboardWarningLine2), len);
             ctx.label("line2", &label2);
             ctx.attr_position(Position::Center);
             ctx.label("line3", loc(LocId::LargeClipboardWarningLine3));
@@ -449,32 +953,26 @@ fn draw_handle_clipboard_change(ctx: &mut Context, state: &mut State) {
             ctx.table_next_row();
             ctx.inherit_focus();
 
-            if over_limit {
-                if ctx.button("ok", loc(LocId::Ok), ButtonStyle::default()) {
-                    state.osc_clipboard_seen_generation = generation;
-                }
-                ctx.inherit_focus();
-            } else {
-                if ctx.button("always", loc(LocId::Always), ButtonStyle::default()) {
-                    state.osc_clipboard_always_send = true;
-                    state.osc_clipboard_seen_generation = generation;
-                    state.osc_clipboard_send_generation = generation;
-                }
+            // There's no "always" for text that's too large, as it would always be truncated.
+            if !over_limit && ctx.button("always", loc(LocId::Always), ButtonStyle::default()) {
+                state.osc_clipboard_always_send = true;
+                state.osc_clipboard_seen_generation = generation;
+                state.osc_clipboard_send_generation = generation;
+            }
 
-                if ctx.button("yes", loc(LocId::Yes), ButtonStyle::default()) {
-                    state.osc_clipboard_seen_generation = generation;
-                    state.osc_clipboard_send_generation = generation;
-                }
-                if ctx.clipboard().len() < 10 * LARGE_CLIPBOARD_THRESHOLD {
-                    ctx.inherit_focus();
-                }
+            if ctx.button("yes", loc(LocId::Yes), ButtonStyle::default()) {
+                state.osc_clipboard_seen_generation = generation;
+                state.osc_clipboard_send_generation = generation;
+            }
+            if !over_limit && len < 10 * LARGE_CLIPBOARD_THRESHOLD {
+                ctx.inherit_focus();
+            }
 
-                if ctx.button("no", loc(LocId::No), ButtonStyle::default()) {
-                    state.osc_clipboard_seen_generation = generation;
-                }
-                if ctx.clipboard().len() >= 10 * LARGE_CLIPBOARD_THRESHOLD {
-                    ctx.inherit_focus();
-                }
+            if ctx.button("no", loc(LocId::No), ButtonStyle::default()) {
+                state.osc_clipboard_seen_generation = generation;
+            }
+            if over_limit || len >= 10 * LARGE_CLIPBOARD_THRESHOLD {
+                ctx.inherit_focus();
             }
         }
         ctx.table_end();
@@ -484,44 +982,84 @@ fn draw_handle_clipboard_change(ctx: &mut Context, state: &mut State) {
     }
 }
 
+/// Sends the clipboard to the terminal, truncated to `osc_clipboard_limit`.
+/// It's written directly, after the frame, so that it isn't copied into the frame's output.
 #[cold]
-fn write_osc_clipboard(output: &mut ArenaString, state: &mut State, tui: &Tui) {
+fn write_osc_clipboard(state: &mut State, tui: &Tui) {
     let clipboard = tui.clipboard();
     if !clipboard.is_empty() {
-        // Rust doubles the size of a string when it needs to grow it.
-        // If `clipboard` is *really* large, this may then double
-        // the size of the `output` from e.g. 100MB to 200MB. Not good.
-        // We can avoid that by reserving the needed size in advance.
-        output.reserve_exact(base64::encode_len(clipboard.len()) + 16);
-        output.push_str("\x1b]52;c;");
-        base64::encode(output, clipboard);
-        output.push_str("\x1b\\");
+        let len = clipboard::shareable_len(clipboard, state.osc_clipboard_limit);
+        clipboard::write_osc52(&clipboard[..len]);
+        if len == clipboard.len() {
+            state.osc_clipboard_shared_generation = tui.clipboard_generation();
+        }
     }
     state.osc_clipboard_send_generation = tui.clipboard_generation().wrapping_sub(1);
 }
 
-struct RestoreModes;
+/// How long Ctrl+V waits for the terminal to answer with its clipboard.
+const OSC52_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Called before Ctrl+V: Takes over what was copied outside of edit since, so that it's
+/// what gets pasted. If the system clipboard didn't get edit's last copy in full, e.g. as
+/// it was too large, that copy is newer and stays. Input read while waiting for the
+/// terminal is appended to `late_input`.
+#[cold]
+fn refresh_clipboard(tui: &mut Tui, state: &mut State, late_input: &mut String) {
+    if state.osc_clipboard_shared_generation != tui.clipboard_generation() {
+        return;
+    }
+
+    let data = match clipboard::read_system() {
+        Some(data) => Some(data),
+        None if state.osc_clipboard_read => clipboard::read_osc52(OSC52_READ_TIMEOUT, late_input),
+        None => None,
+    };
+    let Some(data) = data.filter(|data| !data.is_empty() && data != tui.clipboard()) else {
+        return;
+    };
+
+    tui.set_clipboard(data);
+    let generation = tui.clipboard_generation();
+    clipboard_history::record_external(state, generation, tui.clipboard());
+    // It's from the system clipboard, so there's no need to share it.
+    state.osc_clipboard_seen_generation = generation;
+    state.osc_clipboard_send_generation = generation.wrapping_sub(1);
+    state.osc_clipboard_shared_generation = generation;
+}
+
+struct RestoreModes {
+    alternate_screen: bool,
+}
 
 impl Drop for RestoreModes {
     fn drop(&mut self) {
         // Same as in the beginning but in the reverse order.
         // It also includes DECSCUSR 0 to reset the cursor style and DECTCEM to show the cursor.
-        sys::write_stdout(
-            "\x1b[0 q\x1b[?25h\x1b]0;\x07\x1b[?1036l\x1b[?1002;1006;2004l\x1b[?1049l",
-        );
+        sys::write_stdout("\x1b[0 q\x1b[?25h\x1b]0;\x07\x1b[?1036l\x1b[?1002;1006;2004l");
+        if self.alternate_screen {
+            sys::write_stdout("\x1b[?1049l");
+        }
     }
 }
 
-fn setup_terminal(tui: &mut Tui, vt_parser: &mut vt::Parser) -> RestoreModes {
+fn setup_terminal(
+    tui: &mut Tui,
+    vt_parser: &mut vt::Parser,
+    alternate_screen: bool,
+) -> RestoreModes {
+    // 1049: Alternative Screen Buffer
+    //   I put the ASB switch in the beginning, just in case the terminal performs
+    //   some additional state tracking beyond the modes we enable/disable.
+    if alternate_screen {
+        sys::write_stdout("\x1b[?1049h");
+    }
     sys::write_stdout(concat!(
-        // 1049: Alternative Screen Buffer
-        //   I put the ASB switch in the beginning, just in case the terminal performs
-        //   some additional state tracking beyond the modes we enable/disable.
         // 1002: Cell Motion Mouse Tracking
         // 1006: SGR Mouse Mode
         // 2004: Bracketed Paste Mode
         // 1036: Xterm: "meta sends escape" (Alt keypresses should be encoded with ESC + char)
-        "\x1b[?1049h\x1b[?1002;1006;2004h\x1b[?1036h",
+        "\x1b[?1002;1006;2004h\x1b[?1036h",
         // OSC 4 color table requests for indices 0 through 15 (base colors).
         "\x1b]4;0;?;1;?;2;?;3;?;4;?;5;?;6;?;7;?\x07",
         "\x1b]4;8;?;9;?;10;?;11;?;12;?;13;?;14;?;15;?\x07",
@@ -608,7 +1146,37 @@ fn setup_terminal(tui: &mut Tui, vt_parser: &mut vt::Parser) -> RestoreModes {
         tui.setup_indexed_colors(indexed_colors);
     }
 
-    RestoreModes
+    RestoreModes { alternate_screen }
+}
+
+/// Guesses which text attributes the `$TERM` terminal can render legibly,
+/// and lets the `[terminal]` section of the config override the guess.
+fn supported_attributes(term: &str, config: &Config) -> Attributes {
+    let (mut bold, mut italic, mut underline) = (true, true, true);
+
+    match term {
+        "dumb" => (bold, italic, underline) = (false, false, false),
+        // The Linux console & co. render italics as a color, if at all,
+        // and GNU screen without a proper terminfo uses reverse video.
+        "linux" | "cons25" | "vt100" | "vt220" | "ansi" | "screen" => italic = false,
+        _ => {}
+    }
+
+    bold = config.get_bool("terminal.bold").unwrap_or(bold);
+    italic = config.get_bool("terminal.italic").unwrap_or(italic);
+    underline = config.get_bool("terminal.underline").unwrap_or(underline);
+
+    let mut attr = Attributes::None;
+    if bold {
+        attr = attr | Attributes::Bold;
+    }
+    if italic {
+        attr = attr | Attributes::Italic;
+    }
+    if underline {
+        attr = attr | Attributes::Underlined;
+    }
+    attr
 }
 
 /// Strips all C0 control characters from the string and replaces them with "_".
@@ -630,3 +1198,47 @@ fn sanitize_control_chars(text: &str) -> Cow<'_, str> {
         Cow::Borrowed(text)
     }
 }
+
+/// Initializes the scratch arenas for tests, like `edit::arena::init_for_test()` does for
+/// the library's. They're shared by all threads, so this also returns a guard that keeps
+/// other tests from using them at the same time.
+#[cfg(test)]
+fn init_arena_for_test() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    static INIT: std::sync::Once = std::sync::Once::new();
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    INIT.call_once(|| arena::init(16 * MEBI).unwrap());
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_attributes() {
+        let none = Config::parse("");
+        assert!(supported_attributes("xterm-256color", &none) == Attributes::All);
+        assert!(supported_attributes("dumb", &none) == Attributes::None);
+        let no_italic = Attributes::Bold | Attributes::Underlined;
+        assert!(supported_attributes("linux", &none) == no_italic);
+
+        let config = Config::parse("[terminal]\nbold = false\nitalic = true\n");
+        let expected = Attributes::Italic | Attributes::Underlined;
+        assert!(supported_attributes("linux", &config) == expected);
+        assert!(supported_attributes("dumb", &config) == Attributes::Italic);
+    }
+
+    #[test]
+    fn test_parse_plus_goto() {
+        let goto = |arg| parse_plus_goto(arg).map(|p| (p.x, p.y));
+
+        assert_eq!(goto("+42"), Some((0, 41)));
+        assert_eq!(goto("+42:7"), Some((6, 41)));
+        assert_eq!(goto("+0:0"), Some((0, 0)));
+        assert_eq!(goto("42"), None);
+        assert_eq!(goto("+"), None);
+        assert_eq!(goto("+abc"), None);
+        assert_eq!(goto("+42:"), None);
+    }
+}
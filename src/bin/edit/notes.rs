@@ -0,0 +1,347 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Notes attached to lines of a file, e.g. to review one's own changes before sending them
+//! off. They're never written into the file, but kept in the `notes` folder of the state
+//! directory, one file per annotated file.
+//!
+//! A note remembers the text of its first line. After edits, and when the file is opened
+//! again, it moves to the nearest line with that text, so that it survives lines being added
+//! or removed above it. If the line itself was changed, the note stays and takes on its text.
+//!
+//! Annotated lines get their line number tinted like diagnostics, and the status bar shows the
+//! note on the cursor's line.
+//!
+//! Notes imported from someone else's review, see [`crate::review`], are shown the same way,
+//! but never saved.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use edit::apperr;
+use edit::arena_format;
+use edit::buffer::TextBuffer;
+use edit::config::Config;
+use edit::diagnostics::{Diagnostic, Severity};
+use edit::framebuffer::IndexedColor;
+use edit::hash::hash_str;
+use edit::helpers::*;
+use edit::input::vk;
+use edit::tui::*;
+
+use crate::documents::Document;
+use crate::localization::*;
+use crate::macros::{escape, unescape};
+use crate::state::*;
+
+/// The diagnostics sources the notes are shown as: one's own, and imported ones.
+const SOURCE: &str = "note";
+const SOURCE_IMPORTED: &str = "review";
+/// How far [`update()`] looks for a note's line.
+const SEARCH_LINES: CoordType = 500;
+
+pub struct Note {
+    /// The first annotated line, 0-based.
+    pub line: CoordType,
+    /// The number of annotated lines.
+    pub count: CoordType,
+    /// The text of the first annotated line.
+    pub anchor: String,
+    pub text: String,
+    /// Whether the note comes from a review bundle.
+    pub imported: bool,
+}
+
+impl Note {
+    pub fn new(line: CoordType, count: CoordType, anchor: String, text: String) -> Self {
+        Self { line, count, anchor, text, imported: false }
+    }
+}
+
+#[derive(Default)]
+pub struct Notes {
+    list: Vec<Note>,
+    /// The buffer generation at which the notes were last moved along with their lines.
+    generation: Option<u32>,
+}
+
+impl Notes {
+    /// Loads the notes of the file at `path`. There are none if they can't be read.
+    pub fn load(path: &Path) -> Self {
+        let text = notes_path(path).and_then(|p| fs::read_to_string(p).ok()).unwrap_or_default();
+        Self::parse(&text)
+    }
+
+    /// The first line is the path of the annotated file, for anyone looking at the folder.
+    /// Each following one is a note: its line, the number of lines, the anchor and the text.
+    fn parse(text: &str) -> Self {
+        let list = text
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                Some(Note::new(
+                    fields.next()?.parse().ok()?,
+                    fields.next()?.parse().ok()?,
+                    unescape(fields.next()?),
+                    unescape(fields.next()?),
+                ))
+            })
+            .collect();
+        Self { list, generation: None }
+    }
+
+    /// Saves the notes of the file at `path`, or deletes them if there are none.
+    /// Imported notes are left out.
+    pub fn save(&self, path: &Path) -> apperr::Result<()> {
+        let Some(notes_path) = notes_path(path) else {
+            return Ok(());
+        };
+        if self.list.iter().all(|note| note.imported) {
+            _ = fs::remove_file(&notes_path);
+            return Ok(());
+        }
+
+        let mut text = format!("{}\n", path.display());
+        for note in self.list.iter().filter(|note| !note.imported) {
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                note.line,
+                note.count,
+                escape(&note.anchor),
+                escape(&note.text)
+            ));
+        }
+        fs::create_dir_all(notes_path.parent().unwrap())?;
+        fs::write(notes_path, text)?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> &[Note] {
+        &self.list
+    }
+
+    /// Adds the notes of someone else's review. Notes imported before are replaced.
+    pub fn import(&mut self, notes: impl IntoIterator<Item = Note>) {
+        self.list.retain(|note| !note.imported);
+        self.list.extend(notes.into_iter().map(|note| Note { imported: true, ..note }));
+        self.generation = None;
+    }
+
+    /// Returns the index of the note that covers line `y`.
+    pub fn at_line(&self, y: CoordType) -> Option<usize> {
+        self.list.iter().position(|note| (note.line..note.line + note.count).contains(&y))
+    }
+}
+
+fn notes_path(path: &Path) -> Option<PathBuf> {
+    let hash = hash_str(0, &path.to_string_lossy());
+    Config::state_dir().map(|dir| dir.join("notes").join(format!("{hash:016x}")))
+}
+
+/// Moves the notes of `doc` along with their lines if the document changed,
+/// and shows them in its margin.
+pub fn update(doc: &mut Document) {
+    let mut tb = doc.buffer.borrow_mut();
+    let notes = &mut doc.notes;
+    if notes.generation == Some(tb.generation()) {
+        return;
+    }
+    notes.generation = Some(tb.generation());
+
+    for note in &mut notes.list {
+        follow_anchor(note, &tb);
+    }
+    notes.list.sort_by_key(|note| note.line);
+
+    for (source, imported) in [(SOURCE, false), (SOURCE_IMPORTED, true)] {
+        let diagnostics = notes
+            .list
+            .iter()
+            .filter(|note| note.imported == imported)
+            .flat_map(|note| {
+                (note.line..note.line + note.count).map(|line| Diagnostic {
+                    line,
+                    column: 0,
+                    severity: Severity::Info,
+                    message: note.text.clone(),
+                })
+            })
+            .collect();
+        tb.set_diagnostics(source, diagnostics);
+    }
+}
+
+fn follow_anchor(note: &mut Note, tb: &TextBuffer) {
+    let last_line = tb.logical_line_count() - 1;
+    let line = note.line.min(last_line);
+    let beg = (line - SEARCH_LINES).max(0);
+    let lines = tb.lines_text(beg..line + SEARCH_LINES + 1);
+    let text_at = |y: CoordType| lines.get((y - beg) as usize);
+
+    // Look closest to where the note was first.
+    let found = (0..=SEARCH_LINES)
+        .flat_map(|distance| [line - distance, line + distance])
+        .find(|&y| y >= 0 && text_at(y) == Some(&note.anchor));
+
+    match found {
+        Some(y) => note.line = y,
+        None => {
+            note.line = line;
+            note.anchor = text_at(line).cloned().unwrap_or_default();
+        }
+    }
+    note.count = note.count.clamp(1, last_line - note.line + 1);
+}
+
+/// What the note dialog edits.
+pub struct NoteDialog {
+    /// The index of the edited note, or `None` for a new one.
+    index: Option<usize>,
+    line: CoordType,
+    count: CoordType,
+    text: String,
+}
+
+/// Opens the dialog for the note at the cursor, or for a new one on the selected lines.
+pub fn open_dialog(state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let tb = doc.buffer.borrow();
+    let y = tb.cursor_logical_pos().y;
+
+    state.note_dialog = Some(match doc.notes.at_line(y) {
+        Some(i) => {
+            let note = &doc.notes.list[i];
+            NoteDialog { index: Some(i), line: note.line, count: note.count, text: note.text.clone() }
+        }
+        None => {
+            let (line, count) = match tb.selected_lines() {
+                Some(lines) => (lines.start, lines.end - lines.start),
+                None => (y, 1),
+            };
+            NoteDialog { index: None, line, count, text: String::new() }
+        }
+    });
+}
+
+pub fn draw_dialog(ctx: &mut Context, state: &mut State) {
+    let (Some(dialog), Some(doc)) = (&mut state.note_dialog, state.documents.active_mut()) else {
+        state.note_dialog = None;
+        return;
+    };
+    let mut done = false;
+    let mut save = false;
+
+    let title = if dialog.count > 1 {
+        let last = dialog.line + dialog.count;
+        arena_format!(ctx.arena(), "{} ({}–{})", loc(LocId::NoteDialogTitle), dialog.line + 1, last)
+    } else {
+        arena_format!(ctx.arena(), "{} ({})", loc(LocId::NoteDialogTitle), dialog.line + 1)
+    };
+    ctx.modal_begin("note", &title);
+    {
+        ctx.editline("text", &mut dialog.text);
+        ctx.attr_intrinsic_size(Size { width: 50, height: 1 });
+        ctx.steal_focus();
+
+        ctx.label("hint", loc(LocId::NoteDialogHint));
+        ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightBlack));
+
+        if ctx.consume_shortcut(vk::RETURN) {
+            save = true;
+            done = true;
+        }
+    }
+    done |= ctx.modal_end();
+
+    if save {
+        let text = dialog.text.trim().to_string();
+        let notes = &mut doc.notes.list;
+        match dialog.index {
+            Some(i) if text.is_empty() => _ = notes.remove(i),
+            // Editing an imported note makes it one's own.
+            Some(i) => {
+                notes[i].text = text;
+                notes[i].imported = false;
+            }
+            None if text.is_empty() => {}
+            None => {
+                let anchor = doc.buffer.borrow().lines_text(dialog.line..dialog.line + 1);
+                let anchor = anchor.into_iter().next().unwrap_or_default();
+                notes.push(Note::new(dialog.line, dialog.count, anchor, text));
+            }
+        }
+        // Shows the change right away.
+        doc.notes.generation = None;
+        update(doc);
+        if let Some(path) = &doc.path
+            && let Err(err) = doc.notes.save(path)
+        {
+            error_log_add(ctx, state, err);
+        }
+    }
+    if done {
+        state.note_dialog = None;
+        ctx.needs_rerender();
+    }
+}
+
+/// Lists the notes of the active document. Picking one goes to its line.
+pub fn draw_list(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        state.wants_note_list = false;
+        return;
+    };
+    let mut chosen = None;
+
+    ctx.modal_begin("notes", loc(LocId::EditNotes));
+    {
+        let notes = doc.notes.list();
+        let width = (ctx.size().width - 20).clamp(10, 80);
+        let height = (ctx.size().height - 10).clamp(1, notes.len().max(1) as _);
+
+        ctx.scrollarea_begin("scrollarea", Size { width, height });
+        ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+        ctx.inherit_focus();
+        {
+            ctx.list_begin("notes");
+            ctx.inherit_focus();
+            for note in notes {
+                let text = arena_format!(ctx.arena(), "{}: {}", note.line + 1, note.text);
+                if ctx.list_item(false, &text) == ListSelection::Activated {
+                    chosen = Some(note.line);
+                }
+            }
+            ctx.list_end();
+        }
+        ctx.scrollarea_end();
+    }
+    let done = ctx.modal_end();
+
+    if let Some(line) = chosen {
+        let mut tb = doc.buffer.borrow_mut();
+        tb.cursor_move_to_logical(Point { x: 0, y: line });
+        tb.make_cursor_visible();
+    }
+    if done || chosen.is_some() {
+        state.wants_note_list = false;
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let notes = Notes::parse("/src/main.rs\n3\t2\tfn main() {\tcheck this\\tlater\nbroken\n");
+        assert_eq!(notes.list.len(), 1);
+        let note = &notes.list[0];
+        assert_eq!((note.line, note.count), (3, 2));
+        assert_eq!(note.anchor, "fn main() {");
+        assert_eq!(note.text, "check this\tlater");
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! `edit --pager`: A read-only viewing mode, so that edit can serve as `$PAGER`.
+//!
+//! The keys follow `less`: `q` quits, `/` searches and `n` finds the next hit,
+//! `Space`/`b` page down/up, `j`/`k` move by lines and `g`/`G` go to the start/end.
+//! `F` toggles follow mode, which keeps rereading the file as it grows, like `tail -f`.
+
+use std::fs;
+use std::time::Duration;
+
+use edit::helpers::*;
+use edit::tui::*;
+
+use crate::state::*;
+
+/// How often the file is checked for new contents in follow mode.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+pub struct Pager {
+    pub enabled: bool,
+    pub follow: bool,
+    /// The file size at the last check in follow mode.
+    follow_len: Option<u64>,
+}
+
+impl Pager {
+    pub fn read_timeout(&self) -> Duration {
+        if self.follow { FOLLOW_INTERVAL } else { Duration::MAX }
+    }
+}
+
+/// Handles the pager keys. Read-only textareas leave text input alone, so this is called
+/// right after the editor. `page` is the number of lines to move for `Space` and `b`.
+pub fn handle_pager_input(ctx: &mut Context, state: &mut State, page: CoordType) {
+    let Some(text) = ctx.text_input() else {
+        return;
+    };
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let mut tb = doc.buffer.borrow_mut();
+    let y = tb.cursor_visual_pos().y;
+
+    match text {
+        "q" => state.exit = true,
+        "/" => {
+            state.wants_search.kind = StateSearchKind::Search;
+            state.wants_search.focus = true;
+        }
+        "n" => {
+            state.search_success =
+                tb.find_and_select(&state.search_needle, state.search_options).is_ok();
+        }
+        " " | "f" => tb.cursor_move_to_visual(Point { x: 0, y: y + page }),
+        "b" => tb.cursor_move_to_visual(Point { x: 0, y: y - page }),
+        "j" => tb.cursor_move_to_visual(Point { x: 0, y: y + 1 }),
+        "k" => tb.cursor_move_to_visual(Point { x: 0, y: y - 1 }),
+        "g" => tb.cursor_move_to_logical(Point::default()),
+        "G" => tb.cursor_move_to_logical(Point::MAX),
+        "F" => {
+            state.pager.follow = !state.pager.follow;
+            state.pager.follow_len = None;
+            tb.cursor_move_to_logical(Point::MAX);
+        }
+        // Everything else gets swallowed, as it would only attempt to edit the document.
+        _ => {}
+    }
+
+    tb.make_cursor_visible();
+    ctx.set_input_consumed();
+    ctx.needs_rerender();
+}
+
+/// Rereads the active document in follow mode if its file changed in size.
+pub fn update(state: &mut State) {
+    if !state.pager.follow {
+        return;
+    }
+    let Some(doc) = state.documents.active_mut() else {
+        return;
+    };
+    let Some(len) = doc.path.as_ref().and_then(|p| fs::metadata(p).ok()).map(|m| m.len()) else {
+        return;
+    };
+
+    if state.pager.follow_len.replace(len).is_some_and(|prev| prev != len) {
+        if let Err(err) = doc.reread(None) {
+            state.pager.follow = false;
+            state.pager.follow_len = None;
+            error_log_push(state, err);
+            return;
+        }
+        let mut tb = doc.buffer.borrow_mut();
+        tb.cursor_move_to_logical(Point::MAX);
+        tb.make_cursor_visible();
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Find in files: Searches all files below the current directory in the background
+//! and lists the hits in a panel. Enter opens the file at the selected hit.
+//!
+//! What a `.gitignore` excludes is skipped, like `.git` itself, binary files and large ones.
+//! The options of the search bar apply. The files are split between a few threads,
+//! and each line is listed once, at its first hit.
+//!
+//! Searches for plain text only read the files that a [`SearchIndex`] of the directory says
+//! may contain it. The index is kept between searches and in the state directory.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+use std::{env, fs, thread};
+
+use edit::buffer::SearchOptions;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::input::vk;
+use edit::search_index::{IndexLimits, SearchIndex};
+use edit::tui::*;
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::draw_editor::attr_panel_overlay;
+use crate::localization::*;
+use crate::state::*;
+
+/// The number of hits shown in the list.
+const LIST_HEIGHT: CoordType = 8;
+/// The height of the whole panel: The query, the options and the list.
+pub const PROJECT_SEARCH_HEIGHT: CoordType = LIST_HEIGHT + 2;
+/// How often a running search is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// The search stops after this many hits.
+const MAX_HITS: usize = 10_000;
+/// Larger files are skipped.
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Files with a NUL in this many bytes at their start are considered binary and skipped.
+const BINARY_CHECK_LEN: usize = 8000;
+/// Lines are only shown up to this many characters.
+const MAX_LINE_CHARS: usize = 256;
+
+pub struct ProjectHit {
+    pub path: PathBuf,
+    /// The path relative to the searched directory, for showing it.
+    pub name: String,
+    /// 0-based logical line.
+    pub line: CoordType,
+    /// 0-based column of the hit, in characters.
+    pub column: CoordType,
+    /// The line without its indentation.
+    pub text: String,
+    /// The hit in `text`, as character indices.
+    pub range: Range<usize>,
+}
+
+#[derive(Default)]
+pub struct ProjectSearch {
+    pub needle: String,
+    /// The needle and options that `hits` are for.
+    searched: Option<(String, SearchOptions)>,
+    hits: Vec<ProjectHit>,
+    running: Option<mpsc::Receiver<Vec<ProjectHit>>>,
+    /// The needle isn't a valid regex.
+    invalid: bool,
+    selected: usize,
+    scroll: usize,
+    /// The index of the last searched directory, see [`search()`].
+    index: Arc<Mutex<Option<SearchIndex>>>,
+}
+
+impl ProjectSearch {
+    /// Searches the files below the current directory for the needle, in the background.
+    /// A search that's still running is forgotten about.
+    pub fn start(&mut self, options: SearchOptions) {
+        self.hits.clear();
+        self.select(0);
+        self.running = None;
+        self.searched = Some((self.needle.clone(), options));
+
+        let regex = match build_regex(&self.needle, options) {
+            Ok(regex) => regex,
+            Err(_) => {
+                self.invalid = true;
+                return;
+            }
+        };
+        self.invalid = false;
+
+        let root = env::current_dir().unwrap_or_default();
+        let literal = (!options.use_regex).then(|| self.needle.clone());
+        let index = self.index.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            _ = sender.send(search(&root, &regex, literal.as_deref(), &index));
+        });
+        self.running = Some(receiver);
+    }
+
+    /// How long the main loop may wait for input before [`update()`] needs to be called.
+    pub fn read_timeout(&self) -> Duration {
+        if self.running.is_some() { POLL_INTERVAL } else { Duration::MAX }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.hits.len().saturating_sub(1));
+        let height = LIST_HEIGHT as usize;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+}
+
+/// Picks up the hits of a finished search.
+pub fn update(state: &mut State) {
+    let search = &mut state.project_search;
+    let Some(receiver) = &search.running else {
+        return;
+    };
+    search.hits = match receiver.try_recv() {
+        Ok(hits) => hits,
+        Err(mpsc::TryRecvError::Empty) => return,
+        Err(mpsc::TryRecvError::Disconnected) => Vec::new(),
+    };
+    search.running = None;
+    search.select(0);
+}
+
+fn build_regex(needle: &str, options: SearchOptions) -> Result<Regex, regex::Error> {
+    let mut pattern = if options.use_regex { needle.to_string() } else { regex::escape(needle) };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{pattern})\b");
+    }
+    RegexBuilder::new(&pattern).case_insensitive(!options.match_case).build()
+}
+
+/// Searches the files below `root` for `regex`. The hits are sorted by path and line.
+/// If it searches for the `literal` text, the `index` of `root` narrows down the files.
+fn search(
+    root: &Path,
+    regex: &Regex,
+    literal: Option<&str>,
+    index: &Mutex<Option<SearchIndex>>,
+) -> Vec<ProjectHit> {
+    let mut files = Vec::new();
+    walk(root, "", &mut Vec::new(), &mut files);
+    if files.is_empty() {
+        return Vec::new();
+    }
+
+    if let Some(literal) = literal {
+        let mut index = index.lock().unwrap_or_else(|err| err.into_inner());
+        match &mut *index {
+            Some(index) if index.root() == root => index.refresh(),
+            _ => *index = Some(SearchIndex::load_or_build(root, IndexLimits::default())),
+        }
+        let index = index.as_ref().unwrap();
+        // Files the index doesn't know about, like hidden ones, are searched anyway.
+        if let Some(candidates) = index.candidates(literal) {
+            files.retain(|(path, _)| {
+                !index.contains(path) || candidates.binary_search(path).is_ok()
+            });
+        }
+    }
+
+    let threads = thread::available_parallelism().map_or(4, |n| n.get()).min(8);
+    let chunk_len = files.len().div_ceil(threads);
+    let mut hits: Vec<ProjectHit> = thread::scope(|scope| {
+        let workers: Vec<_> = files
+            .chunks(chunk_len)
+            .map(|files| {
+                scope.spawn(move || {
+                    let mut hits = Vec::new();
+                    for (path, name) in files {
+                        if hits.len() >= MAX_HITS {
+                            break;
+                        }
+                        search_file(path, name, regex, &mut hits);
+                    }
+                    hits
+                })
+            })
+            .collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_default()).collect()
+    });
+
+    hits.truncate(MAX_HITS);
+    hits
+}
+
+/// Collects the files below `dir`, sorted by name, with their path relative to the root (`rel`).
+/// `rules` are those of the `.gitignore` files in the directories above.
+fn walk(dir: &Path, rel: &str, rules: &mut Vec<IgnoreRule>, files: &mut Vec<(PathBuf, String)>) {
+    let rules_len = rules.len();
+    if let Ok(text) = fs::read_to_string(dir.join(".gitignore")) {
+        rules.extend(parse_gitignore(rel, &text));
+    }
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            // Symlinks are skipped, as they could lead in circles.
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if name == ".git" || file_type.is_symlink() {
+                continue;
+            }
+
+            let path = if rel.is_empty() { name.to_string() } else { format!("{rel}/{name}") };
+            if is_ignored(rules, &path, file_type.is_dir()) {
+                continue;
+            }
+            if file_type.is_dir() {
+                walk(&entry.path(), &path, rules, files);
+            } else {
+                files.push((entry.path(), path));
+            }
+        }
+    }
+
+    rules.truncate(rules_len);
+}
+
+fn search_file(path: &Path, name: &str, regex: &Regex, hits: &mut Vec<ProjectHit>) {
+    if fs::metadata(path).map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+        return;
+    }
+    let Ok(data) = fs::read(path) else {
+        return;
+    };
+    if data[..data.len().min(BINARY_CHECK_LEN)].contains(&0) {
+        return;
+    }
+
+    for (line_no, line) in data.split(|&c| c == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let Some(m) = regex.find(line) else {
+            continue;
+        };
+
+        let column = String::from_utf8_lossy(&line[..m.start()]).chars().count();
+        let line = String::from_utf8_lossy(line);
+        let text = line.trim_start();
+        let indent = line[..line.len() - text.len()].chars().count();
+        let text: String = text.chars().take(MAX_LINE_CHARS).collect();
+        let len = String::from_utf8_lossy(m.as_bytes()).chars().count();
+        let start = column.saturating_sub(indent);
+
+        hits.push(ProjectHit {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+            line: line_no as CoordType,
+            column: column as CoordType,
+            text,
+            range: start..start + len,
+        });
+        if hits.len() >= MAX_HITS {
+            break;
+        }
+    }
+}
+
+/// A pattern from a `.gitignore` file.
+#[derive(Debug, PartialEq, Eq)]
+struct IgnoreRule {
+    /// The directory of the `.gitignore`, relative to the root. Empty for the root itself.
+    base: String,
+    glob: String,
+    /// Starts with `!`: Includes what an earlier pattern excluded.
+    negate: bool,
+    /// Ends with `/`: Only matches directories.
+    dir_only: bool,
+    /// Contains a `/`: Matches the path relative to `base`, instead of just the name.
+    anchored: bool,
+}
+
+fn parse_gitignore(base: &str, text: &str) -> Vec<IgnoreRule> {
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // `\!` and `\#` stand for themselves.
+        let line = line.strip_prefix('\\').filter(|l| l.starts_with(['!', '#'])).unwrap_or(line);
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let glob = line.strip_prefix('/').unwrap_or(line);
+        if glob.is_empty() {
+            continue;
+        }
+
+        rules.push(IgnoreRule {
+            base: base.to_string(),
+            glob: glob.to_string(),
+            negate,
+            dir_only,
+            anchored,
+        });
+    }
+    rules
+}
+
+/// Whether `path`, relative to the root and separated by `/`, is ignored.
+/// Like in Git, the last rule that matches decides.
+fn is_ignored(rules: &[IgnoreRule], path: &str, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in rules {
+        if rule.dir_only && !is_dir {
+            continue;
+        }
+        let rest = if rule.base.is_empty() {
+            path
+        } else {
+            match path.strip_prefix(rule.base.as_str()).and_then(|p| p.strip_prefix('/')) {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+        let text = if rule.anchored { rest } else { rest.rsplit('/').next().unwrap_or(rest) };
+        if glob_match(rule.glob.as_bytes(), text.as_bytes()) {
+            ignored = !rule.negate;
+        }
+    }
+    ignored
+}
+
+/// Matches `text` against a glob with `*`, `**`, `?` and `[...]`, like Git does.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` also matches no directory at all.
+            if let [b'/', after @ ..] = rest
+                && glob_match(after, text)
+            {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => matches!(text, [c, t @ ..] if *c != b'/' && glob_match(rest, t)),
+        [b'[', rest @ ..] if rest.iter().skip(1).any(|&c| c == b']') => {
+            let end = rest.iter().skip(1).position(|&c| c == b']').unwrap() + 1;
+            let (negate, class) = match &rest[..end] {
+                [b'!' | b'^', class @ ..] => (true, class),
+                class => (false, class),
+            };
+            let Some((&c, text)) = text.split_first() else {
+                return false;
+            };
+
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == b'-' {
+                    matched |= (class[i]..=class[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negate && c != b'/' && glob_match(&rest[end + 1..], text)
+        }
+        [b'\\', c, rest @ ..] | [c, rest @ ..] => {
+            text.first() == Some(c) && glob_match(rest, &text[1..])
+        }
+    }
+}
+
+pub fn draw_project_search(ctx: &mut Context, state: &mut State) {
+    let overlay_top = state.panel_overlay_top();
+    let search = &mut state.project_search;
+    let mut open = false;
+    let mut close = false;
+
+    ctx.block_begin("project-search");
+    attr_panel_overlay(ctx, overlay_top, PROJECT_SEARCH_HEIGHT);
+    ctx.attr_focus_well();
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::White));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        state.editor_active = ctx.contains_focus();
+
+        ctx.table_begin("needle");
+        ctx.table_set_columns(&[0, COORD_TYPE_SAFE_MAX, 0]);
+        ctx.table_set_cell_gap(Size { width: 1, height: 0 });
+        {
+            ctx.table_next_row();
+            ctx.label("label", loc(LocId::ProjectSearchLabel));
+
+            ctx.editline("needle", &mut search.needle);
+            ctx.focus_on_first_present();
+            let searched = search.searched.as_ref().is_some_and(|(needle, options)| {
+                *needle == search.needle && *options == state.search_options
+            });
+            if searched && search.running.is_none() && search.hits.is_empty() || search.invalid {
+                ctx.attr_background_rgba(ctx.indexed(IndexedColor::Red));
+                ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightWhite));
+            }
+
+            if ctx.is_focused() {
+                let page = LIST_HEIGHT as usize;
+                let selected = search.selected;
+                let target = if ctx.consume_shortcut(vk::UP) {
+                    Some(selected.saturating_sub(1))
+                } else if ctx.consume_shortcut(vk::DOWN) {
+                    Some(selected + 1)
+                } else if ctx.consume_shortcut(vk::PRIOR) {
+                    Some(selected.saturating_sub(page))
+                } else if ctx.consume_shortcut(vk::NEXT) {
+                    Some(selected + page)
+                } else {
+                    None
+                };
+                if let Some(target) = target {
+                    search.select(target);
+                } else if ctx.consume_shortcut(vk::RETURN) {
+                    // The first Enter searches, the next ones open the selected hit.
+                    if searched && !search.hits.is_empty() {
+                        open = true;
+                    } else if !search.needle.is_empty() {
+                        search.start(state.search_options);
+                    }
+                } else if ctx.consume_shortcut(vk::ESCAPE) {
+                    close = true;
+                }
+            }
+
+            let count = if search.running.is_some() {
+                "…".to_string()
+            } else if search.hits.is_empty() {
+                String::new()
+            } else {
+                format!("{}/{}", search.selected + 1, search.hits.len())
+            };
+            ctx.label("count", &count);
+        }
+        ctx.table_end();
+
+        ctx.table_begin("options");
+        ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+        {
+            ctx.table_next_row();
+            ctx.checkbox(
+                "match-case",
+                loc(LocId::SearchMatchCase),
+                &mut state.search_options.match_case,
+            );
+            ctx.checkbox(
+                "whole-word",
+                loc(LocId::SearchWholeWord),
+                &mut state.search_options.whole_word,
+            );
+            ctx.checkbox(
+                "use-regex",
+                loc(LocId::SearchUseRegex),
+                &mut state.search_options.use_regex,
+            );
+        }
+        ctx.table_end();
+
+        ctx.block_begin("hits");
+        ctx.attr_intrinsic_size(Size { width: COORD_TYPE_SAFE_MAX, height: LIST_HEIGHT });
+        {
+            let hl = ctx.indexed(IndexedColor::Blue);
+            let dim = ctx.indexed_alpha(IndexedColor::Black, 1, 2);
+            let end = (search.scroll + LIST_HEIGHT as usize).min(search.hits.len());
+
+            for i in search.scroll..end {
+                let hit = &search.hits[i];
+                ctx.next_block_id_mixin(i as u64);
+                ctx.styled_label_begin("hit");
+                ctx.styled_label_set_foreground(dim);
+                ctx.styled_label_add_text(&format!("{}:{}: ", hit.name, hit.line + 1));
+
+                let chars = || hit.text.chars();
+                let before: String = chars().take(hit.range.start).collect();
+                let matched: String = chars().skip(hit.range.start).take(hit.range.len()).collect();
+                let after: String = chars().skip(hit.range.end).collect();
+                ctx.styled_label_set_foreground(0);
+                ctx.styled_label_add_text(&before);
+                ctx.styled_label_set_foreground(hl);
+                ctx.styled_label_add_text(&matched);
+                ctx.styled_label_set_foreground(0);
+                ctx.styled_label_add_text(&after);
+                ctx.styled_label_end();
+                ctx.attr_overflow(Overflow::TruncateTail);
+
+                if i == search.selected {
+                    ctx.attr_reverse();
+                }
+                if ctx.was_mouse_down() {
+                    search.select(i);
+                    open = true;
+                }
+            }
+        }
+        ctx.block_end();
+    }
+    ctx.block_end();
+
+    if open && let Some(hit) = state.project_search.hits.get(state.project_search.selected) {
+        let (path, pos) = (hit.path.clone(), Point { x: hit.column, y: hit.line });
+        match state.documents.add_file_path(&path) {
+            Ok(doc) => {
+                let mut tb = doc.buffer.borrow_mut();
+                tb.cursor_move_to_logical(pos);
+                tb.make_cursor_visible();
+                close = true;
+            }
+            Err(err) => error_log_add(ctx, state, err),
+        }
+    }
+    if close {
+        state.wants_project_search = false;
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.rs", b"main.rs"));
+        assert!(!glob_match(b"*.rs", b"src/main.rs"));
+        assert!(glob_match(b"**/*.rs", b"main.rs"));
+        assert!(glob_match(b"**/*.rs", b"src/bin/main.rs"));
+        assert!(glob_match(b"src/**", b"src/bin/main.rs"));
+        assert!(glob_match(b"a/**/b", b"a/b"));
+        assert!(glob_match(b"a/**/b", b"a/x/y/b"));
+        assert!(glob_match(b"file?.[ch]", b"file1.c"));
+        assert!(!glob_match(b"file?.[!ch]", b"file1.c"));
+        assert!(glob_match(b"[a-c]x", b"bx"));
+        assert!(glob_match(br"\*", b"*"));
+        assert!(!glob_match(br"\*", b"x"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let root = "# build output\n/target\n*.log\n!keep.log\nnode_modules/\n";
+        let mut rules = parse_gitignore("", root);
+        rules.extend(parse_gitignore("web", "dist/\n/local.txt\n"));
+
+        assert!(is_ignored(&rules, "target", true));
+        assert!(!is_ignored(&rules, "src/target", true));
+        assert!(is_ignored(&rules, "src/debug.log", false));
+        assert!(!is_ignored(&rules, "src/keep.log", false));
+        assert!(is_ignored(&rules, "web/node_modules", true));
+        assert!(!is_ignored(&rules, "node_modules", false));
+        assert!(is_ignored(&rules, "web/a/dist", true));
+        assert!(!is_ignored(&rules, "dist", true));
+        assert!(is_ignored(&rules, "web/local.txt", false));
+        assert!(!is_ignored(&rules, "web/a/local.txt", false));
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Files on other machines, opened with `edit user@host:/etc/nginx/nginx.conf` like `scp`.
+//!
+//! The files are read and written with `ssh`, which shares one connection per host between
+//! all of them, see [`ssh()`]. That connection is made before the editor starts, so that
+//! `ssh` can ask for a password. The files are then fetched in the background, and saving
+//! uploads them in the background, too, one after the other per file. The editor waits for
+//! uploads that are still running when it exits.
+//!
+//! Like local files, they're uploaded to a temporary file next to them, which is then moved
+//! into place, so that a lost connection never leaves a half-written file behind. A copy of
+//! the file is overwritten for that, so that its permissions stay the same. If the directory
+//! isn't writable or the file belongs to someone else, `cat` writes it in place instead.
+//! A file that doesn't exist yet is opened empty and created by saving it.
+
+use std::fs::{self, File};
+use std::io::{Seek as _, Write as _};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+use std::{env, fmt, mem, process, thread};
+
+use edit::apperr;
+use edit::config::Config;
+
+use crate::documents::Document;
+use crate::state::*;
+
+pub const APP_REMOTE_FAILED: apperr::Error = apperr::Error::new_app(7);
+
+/// How often running transfers are checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A file on another machine, as in `[user@]host:path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePath {
+    pub host: String,
+    pub path: String,
+}
+
+impl RemotePath {
+    /// Parses `user@host:path` or `host:/path`. Without the user, the path must be absolute,
+    /// so that `file:12` (go to line 12) and `C:\file` aren't mistaken for a remote file.
+    pub fn parse(arg: &str) -> Option<Self> {
+        let (host, path) = arg.split_once(':')?;
+        let valid_host = host.len() > 1
+            && !host.starts_with(['-', '@'])
+            && !host.contains(['/', '\\'])
+            && !host.ends_with('@');
+        if !valid_host || path.is_empty() || !(host.contains('@') || path.starts_with('/')) {
+            return None;
+        }
+        Some(Self { host: host.to_string(), path: path.to_string() })
+    }
+
+    pub fn file_name(&self) -> &str {
+        let name = self.path.rsplit('/').next().unwrap_or_default();
+        if name.is_empty() { &self.path } else { name }
+    }
+
+    /// The path quoted for the remote shell. `~/` is left unquoted, so that it's expanded.
+    fn quoted_path(&self) -> String {
+        let (prefix, path) = match self.path.strip_prefix("~/") {
+            Some(path) => ("~/", path),
+            None => ("", self.path.as_str()),
+        };
+        format!("{prefix}{}", shell_quote(path))
+    }
+
+    /// A `sh` script that writes its stdin to the file, see the module docs.
+    fn save_script(&self) -> String {
+        format!(
+            r#"p={}
+d=$(dirname -- "$p") && t="$d/.$(basename -- "$p").edit-$$" || exit 1
+if [ -w "$d" ] && [ ! -L "$p" ] && {{ [ ! -e "$p" ] || [ -O "$p" ]; }}; then
+    {{ [ ! -e "$p" ] || cp -p -- "$p" "$t"; }} && cat > "$t" && mv -f -- "$t" "$p" && exit
+    rm -f -- "$t"
+    exit 1
+fi
+cat > "$p""#,
+            self.quoted_path()
+        )
+    }
+}
+
+/// Quotes `s` for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+impl fmt::Display for RemotePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.path)
+    }
+}
+
+enum TransferKind {
+    /// The file is read-only once it arrives, if `read_only` is set.
+    Fetch { read_only: bool },
+    /// The document is saved once this finishes, unless it changed since `generation`.
+    Save { generation: u32 },
+}
+
+struct Transfer {
+    target: RemotePath,
+    kind: TransferKind,
+    receiver: mpsc::Receiver<apperr::Result<Vec<u8>>>,
+}
+
+#[derive(Default)]
+pub struct Remote {
+    /// The hosts that [`Remote::connect()`] connected to.
+    hosts: Vec<String>,
+    transfers: Vec<Transfer>,
+    /// The contents and generation to upload once the running upload of the file is done.
+    queued_saves: Vec<(RemotePath, Vec<u8>, u32)>,
+}
+
+impl Remote {
+    /// Connects to `host`, unless that happened before. `ssh` may ask for a password
+    /// on the terminal, which must not be in raw mode.
+    fn connect(&mut self, host: &str) -> apperr::Result<()> {
+        if self.hosts.iter().any(|h| h == host) {
+            return Ok(());
+        }
+        // The first command starts the shared connection, which outlives it.
+        if !ssh(host, "true", false).status()?.success() {
+            return Err(APP_REMOTE_FAILED);
+        }
+        self.hosts.push(host.to_string());
+        Ok(())
+    }
+
+    /// Uploads `doc` in the background. It's marked as saved once the upload succeeded.
+    /// If the file is still being uploaded, it's uploaded again after that.
+    pub fn save(&mut self, doc: &mut Document) -> apperr::Result<()> {
+        let Some(target) = doc.remote.clone() else {
+            return Ok(());
+        };
+
+        let mut contents = Vec::new();
+        let generation = {
+            let mut tb = doc.buffer.borrow_mut();
+            doc.save_transforms.apply(&mut tb);
+            tb.write_file(&mut contents)?;
+            // It's not saved until the upload is done.
+            tb.mark_as_dirty();
+            tb.generation()
+        };
+
+        let uploading = self.transfers.iter().any(|transfer| {
+            transfer.target == target && matches!(transfer.kind, TransferKind::Save { .. })
+        });
+        if uploading {
+            // Only the latest contents are worth uploading.
+            self.queued_saves.retain(|(t, ..)| *t != target);
+            self.queued_saves.push((target, contents, generation));
+        } else {
+            self.start_save(target, contents, generation);
+        }
+        Ok(())
+    }
+
+    /// Whether the current contents of `doc` are being uploaded. It may be closed then,
+    /// as the upload goes on and reports if it fails.
+    pub fn is_saving(&self, doc: &Document) -> bool {
+        let Some(target) = &doc.remote else {
+            return false;
+        };
+        let current = doc.buffer.borrow().generation();
+        self.queued_saves.iter().any(|(t, _, g)| t == target && *g == current)
+            || self.transfers.iter().any(|transfer| {
+                transfer.target == *target
+                    && matches!(transfer.kind, TransferKind::Save { generation: g } if g == current)
+            })
+    }
+
+    fn start_save(&mut self, target: RemotePath, contents: Vec<u8>, generation: u32) {
+        let script = format!("exec sh -c {}", shell_quote(&target.save_script()));
+        let mut cmd = ssh(&target.host, &script, true);
+        self.start(target, TransferKind::Save { generation }, move || {
+            let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::null()).spawn()?;
+            // Closing stdin once it's written lets `cat` finish.
+            let written = child.stdin.take().unwrap().write_all(&contents);
+            if !child.wait()?.success() {
+                return Err(APP_REMOTE_FAILED);
+            }
+            written?;
+            Ok(Vec::new())
+        });
+    }
+
+    /// Starts the upload that waited for the one of `target` to finish, if any.
+    fn start_queued_save(&mut self, target: &RemotePath) {
+        if let Some(i) = self.queued_saves.iter().position(|(t, ..)| t == target) {
+            let (target, contents, generation) = self.queued_saves.remove(i);
+            self.start_save(target, contents, generation);
+        }
+    }
+
+    /// How long the main loop may wait for input before [`update()`] needs to be called.
+    pub fn read_timeout(&self) -> Duration {
+        if self.transfers.is_empty() { Duration::MAX } else { POLL_INTERVAL }
+    }
+
+    fn start(
+        &mut self,
+        target: RemotePath,
+        kind: TransferKind,
+        transfer: impl FnOnce() -> apperr::Result<Vec<u8>> + Send + 'static,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || _ = sender.send(transfer()));
+        self.transfers.push(Transfer { target, kind, receiver });
+    }
+}
+
+/// Opens `target` in a new document, which is filled in once the file arrives,
+/// see [`update()`]. Connects to its host first, if needed.
+pub fn open(state: &mut State, target: RemotePath, read_only: bool) -> apperr::Result<()> {
+    state.remote.connect(&target.host)?;
+
+    // Switch to the file if it's already open.
+    if state.documents.update_active(|doc| doc.remote.as_ref() == Some(&target)) {
+        return Ok(());
+    }
+
+    let doc = state.documents.add_untitled()?;
+    doc.set_remote(target.clone());
+    // Edits made before the file arrives would get lost.
+    doc.buffer.borrow_mut().set_read_only(true);
+
+    let quoted = target.quoted_path();
+    let mut cmd = ssh(&target.host, &format!("if [ -e {quoted} ]; then cat -- {quoted}; fi"), true);
+    state.remote.start(target, TransferKind::Fetch { read_only }, move || {
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(APP_REMOTE_FAILED);
+        }
+        Ok(output.stdout)
+    });
+    Ok(())
+}
+
+/// Fills in the files that arrived and reports the transfers that failed.
+pub fn update(state: &mut State) {
+    let mut i = 0;
+    while i < state.remote.transfers.len() {
+        let result = match state.remote.transfers[i].receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => {
+                i += 1;
+                continue;
+            }
+            Err(mpsc::TryRecvError::Disconnected) => Err(APP_REMOTE_FAILED),
+        };
+        let transfer = state.remote.transfers.remove(i);
+        if let Err(err) = finish(state, transfer, result) {
+            error_log_push(state, err);
+        }
+    }
+}
+
+/// Waits for all transfers, e.g. the uploads that are still running when the editor exits.
+/// Returns the first error.
+pub fn wait(state: &mut State) -> apperr::Result<()> {
+    let mut first_err = Ok(());
+    // Finishing an upload may start the next one of the same file.
+    while !state.remote.transfers.is_empty() {
+        for transfer in mem::take(&mut state.remote.transfers) {
+            let result = transfer.receiver.recv().unwrap_or(Err(APP_REMOTE_FAILED));
+            if let Err(err) = finish(state, transfer, result) {
+                error_log_push(state, err);
+                if first_err.is_ok() {
+                    first_err = Err(err);
+                }
+            }
+        }
+    }
+    first_err
+}
+
+fn finish(
+    state: &mut State,
+    transfer: Transfer,
+    result: apperr::Result<Vec<u8>>,
+) -> apperr::Result<()> {
+    if let TransferKind::Save { .. } = transfer.kind {
+        state.remote.start_queued_save(&transfer.target);
+    }
+
+    // The document may have been closed meanwhile.
+    let doc = state
+        .documents
+        .all_documents_mut()
+        .into_iter()
+        .find(|doc| doc.remote.as_ref() == Some(&transfer.target));
+
+    match (transfer.kind, doc) {
+        (TransferKind::Fetch { read_only }, Some(doc)) => {
+            load(doc, &result?)?;
+            doc.buffer.borrow_mut().set_read_only(read_only);
+            Ok(())
+        }
+        (TransferKind::Save { generation }, Some(doc)) => {
+            let mut tb = doc.buffer.borrow_mut();
+            // Edits made meanwhile are yet to be saved.
+            if result.is_ok() && tb.generation() == generation {
+                tb.mark_as_clean();
+            }
+            result.map(drop)
+        }
+        (_, None) => result.map(drop),
+    }
+}
+
+/// Reads `contents` into `doc` like a local file, with the encoding detected.
+fn load(doc: &mut Document, contents: &[u8]) -> apperr::Result<()> {
+    // Reading files is what the buffer knows how to do, so the contents take a detour through
+    // one. It's kept private to the user, as it may well be a file only root can read.
+    let dir = Config::state_dir().unwrap_or_else(env::temp_dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("remote-{}.tmp", process::id()));
+    let result = (|| {
+        let mut options = File::options();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        file.write_all(contents)?;
+        file.rewind()?;
+
+        let mut tb = doc.buffer.borrow_mut();
+        tb.read_file_with_path(&mut file, Path::new(doc.filename.as_str()), None)?;
+        // The file is about to be gone.
+        tb.unmap()
+    })();
+    _ = fs::remove_file(&path);
+    result?;
+
+    doc.apply_bom_policy();
+    doc.detect_generated();
+    Ok(())
+}
+
+/// Returns an `ssh` command that runs `command` on `host`, and shares its connection with
+/// all others to it. The shared connection closes itself a while after the last one.
+///
+/// In the `background`, it never asks for anything, as the editor owns the terminal.
+fn ssh(host: &str, command: &str, background: bool) -> Command {
+    let mut cmd = Command::new("ssh");
+    // Windows' OpenSSH can't share connections.
+    if cfg!(unix)
+        && let Some(dir) = Config::state_dir()
+        && fs::create_dir_all(&dir).is_ok()
+    {
+        cmd.arg("-o").arg(format!("ControlPath=\"{}\"", dir.join("ssh-%C").display()));
+        cmd.args(["-o", "ControlMaster=auto", "-o", "ControlPersist=10m"]);
+    }
+    if background {
+        cmd.args(["-o", "BatchMode=yes"]).stdin(Stdio::null()).stderr(Stdio::null());
+    }
+    cmd.args([host, command]);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let parse = |arg| RemotePath::parse(arg).map(|t| (t.host, t.path));
+        let remote = |host: &str, path: &str| Some((host.to_string(), path.to_string()));
+
+        assert_eq!(parse("root@example.com:/etc/hosts"), remote("root@example.com", "/etc/hosts"));
+        assert_eq!(parse("me@box:notes.txt"), remote("me@box", "notes.txt"));
+        assert_eq!(parse("box:/etc/hosts"), remote("box", "/etc/hosts"));
+        assert_eq!(parse("box:notes.txt"), None);
+        assert_eq!(parse("foo.txt:12:3"), None);
+        assert_eq!(parse(r"C:\foo.txt"), None);
+        assert_eq!(parse("C:/foo.txt"), None);
+        assert_eq!(parse("dir/foo:/bar"), None);
+        assert_eq!(parse("-oProxyCommand=x:/bar"), None);
+        assert_eq!(parse("me@box:"), None);
+    }
+
+    #[test]
+    fn test_quoted_path() {
+        let quoted = |path: &str| {
+            RemotePath { host: "box".to_string(), path: path.to_string() }.quoted_path()
+        };
+        assert_eq!(quoted("/etc/it's here"), r"'/etc/it'\''s here'");
+        assert_eq!(quoted("~/notes.txt"), "~/'notes.txt'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_script() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let dir = env::temp_dir().join(format!("edit-test-remote-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("it's here.txt");
+        let target = RemotePath { host: "box".to_string(), path: path.display().to_string() };
+        let save = |contents: &[u8]| {
+            let mut child = Command::new("sh")
+                .args(["-c", &target.save_script()])
+                .stdin(Stdio::piped())
+                .spawn()
+                .unwrap();
+            child.stdin.take().unwrap().write_all(contents).unwrap();
+            child.wait().unwrap().success()
+        };
+
+        // A new file is created.
+        assert!(save(b"one"));
+        assert_eq!(fs::read(&path).unwrap(), b"one");
+
+        // An existing one keeps its permissions, and no temporary file is left behind.
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        assert!(save(b"two"));
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Review bundles: the [notes](crate::notes) and `git diff` of the open files in one Markdown
+//! document, to send to someone else. Importing a bundle shows its notes in the files again.
+//!
+//! A bundle looks like this. Only the file headings and the notes matter for importing it.
+//!
+//! ````markdown
+//! # Review
+//!
+//! ## `src/main.rs`
+//!
+//! ```diff
+//! diff --git a/src/main.rs b/src/main.rs
+//! ...
+//! ```
+//!
+//! - Lines 12–14: Doesn't this leak the handle?
+//!   > let file = File::open(path)?;
+//! ````
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use edit::apperr;
+use edit::helpers::*;
+use edit::syntax::FileType;
+
+use crate::notes::Note;
+use crate::state::*;
+
+/// The file heading: the path in backticks.
+const FILE_PREFIX: &str = "## `";
+const ANCHOR_PREFIX: &str = "  > ";
+
+/// Opens a bundle of the open files with notes or changes in a new document.
+pub fn export(state: &mut State) -> apperr::Result<()> {
+    let cwd = env::current_dir().unwrap_or_default();
+    let mut text = String::from("# Review\n");
+
+    for doc in state.documents.all_documents() {
+        let Some(path) = &doc.path else {
+            continue;
+        };
+        let diff = git_diff(path);
+        if diff.is_empty() && doc.notes.list().is_empty() {
+            continue;
+        }
+        let name = path.strip_prefix(&cwd).unwrap_or(path);
+        text.push_str(&format!("\n{FILE_PREFIX}{}`\n", name.display()));
+        if !diff.is_empty() {
+            let fence = fence(&diff);
+            text.push_str(&format!("\n{fence}diff\n{diff}{fence}\n"));
+        }
+        if !doc.notes.list().is_empty() {
+            text.push('\n');
+            for note in doc.notes.list() {
+                write_note(&mut text, note);
+            }
+        }
+    }
+
+    state.documents.add_scratch(Some("review.md"), FileType::Markdown, &text)?;
+    Ok(())
+}
+
+/// Opens the files of the bundle at `path` and shows its notes in them.
+pub fn import(state: &mut State, path: &Path) -> apperr::Result<()> {
+    let text = fs::read_to_string(path)?;
+    let cwd = env::current_dir().unwrap_or_default();
+    for (file, mut notes) in parse(&text) {
+        let doc = state.documents.add_file_path(&cwd.join(file))?;
+        // Without the line's text, the note can only stay on the line it says.
+        let tb = doc.buffer.borrow();
+        for note in notes.iter_mut().filter(|note| note.anchor.is_empty()) {
+            let line = tb.lines_text(note.line..note.line + 1);
+            note.anchor = line.into_iter().next().unwrap_or_default();
+        }
+        drop(tb);
+        doc.notes.import(notes);
+    }
+    Ok(())
+}
+
+/// Returns the uncommitted changes of the file at `path`, or nothing if it's not in a
+/// repository or git isn't installed.
+fn git_diff(path: &Path) -> String {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return String::new();
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["diff", "--no-color", "HEAD", "--"])
+        .arg(name)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Returns a code fence that's longer than any line of backticks in `text`.
+fn fence(text: &str) -> String {
+    let longest = text
+        .lines()
+        .filter(|line| line.starts_with("```"))
+        .map(|line| line.len() - line.trim_start_matches('`').len())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn write_note(text: &mut String, note: &Note) {
+    let text_line = note.text.replace('\n', " ");
+    if note.count > 1 {
+        let last = note.line + note.count;
+        text.push_str(&format!("- Lines {}–{last}: {text_line}\n", note.line + 1));
+    } else {
+        text.push_str(&format!("- Line {}: {text_line}\n", note.line + 1));
+    }
+    text.push_str(&format!("{ANCHOR_PREFIX}{}\n", note.anchor));
+}
+
+/// Returns the notes of each file in the bundle `text`.
+fn parse(text: &str) -> Vec<(PathBuf, Vec<Note>)> {
+    let mut files: Vec<(PathBuf, Vec<Note>)> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in text.lines() {
+        // The diffs may contain anything.
+        if let Some(f) = fence {
+            if line.trim_end() == f {
+                fence = None;
+            }
+            continue;
+        }
+        if line.starts_with("```") {
+            fence = Some(&line[..line.len() - line.trim_start_matches('`').len()]);
+        } else if let Some(name) = line.strip_prefix(FILE_PREFIX).and_then(|l| l.strip_suffix('`'))
+        {
+            files.push((PathBuf::from(name), Vec::new()));
+        } else if let Some(anchor) = line.strip_prefix(ANCHOR_PREFIX)
+            && let Some((_, notes)) = files.last_mut()
+            && let Some(note) = notes.last_mut()
+        {
+            note.anchor = anchor.to_string();
+        } else if let Some((_, notes)) = files.last_mut()
+            && let Some(note) = parse_note(line)
+        {
+            notes.push(note);
+        }
+    }
+
+    files.retain(|(_, notes)| !notes.is_empty());
+    files
+}
+
+/// Parses `- Line 12: text` or `- Lines 12–14: text`.
+fn parse_note(line: &str) -> Option<Note> {
+    let rest = line.strip_prefix("- Lines ").or_else(|| line.strip_prefix("- Line "))?;
+    let (lines, text) = rest.split_once(": ")?;
+    let (first, last) = lines.split_once('–').unwrap_or((lines, lines));
+    let first: CoordType = first.parse().ok()?;
+    let last: CoordType = last.parse().ok()?;
+    if first < 1 || last < first {
+        return None;
+    }
+    Some(Note::new(first - 1, last - first + 1, String::new(), text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = concat!(
+            "# Review\n",
+            "\n",
+            "## `src/main.rs`\n",
+            "\n",
+            "````diff\n",
+            "- Line 1: not a note\n",
+            "```\n",
+            "````\n",
+            "\n",
+            "- Lines 12–14: Doesn't this leak?\n",
+            "  > let file = File::open(path)?;\n",
+            "- Line 3: Typo\n",
+            "\n",
+            "## `README.md`\n",
+        );
+        let files = parse(text);
+        assert_eq!(files.len(), 1);
+        let (path, notes) = &files[0];
+        assert_eq!(path, Path::new("src/main.rs"));
+        assert_eq!(notes.len(), 2);
+        assert_eq!((notes[0].line, notes[0].count), (11, 3));
+        assert_eq!(notes[0].anchor, "let file = File::open(path)?;");
+        assert_eq!(notes[0].text, "Doesn't this leak?");
+        assert_eq!((notes[1].line, notes[1].count, notes[1].anchor.as_str()), (2, 1, ""));
+
+        let mut exported = String::new();
+        write_note(&mut exported, &notes[0]);
+        assert_eq!(
+            exported,
+            "- Lines 12–14: Doesn't this leak?\n  > let file = File::open(path)?;\n"
+        );
+        assert_eq!(fence("```\ncode\n```\n"), "````");
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Whitespace fixes applied to a file whenever it's saved, per file type:
+//!
+//! ```toml
+//! [editor]
+//! trim_trailing_whitespace = true
+//!
+//! [filetype.python]
+//! insert_final_newline = true
+//! normalize_indentation = true
+//! ```
+//!
+//! They run before the formatter, each as its own undo step. File > Preview Save Changes
+//! opens a diff of what the next save would change, without changing anything.
+
+use edit::buffer::TextBuffer;
+use edit::config::FileTypeSettings;
+use edit::document::IndentStyle;
+use edit::syntax::FileType;
+use edit::{apperr, diagnostics, diff, unicode};
+
+use crate::state::*;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SaveTransforms {
+    pub trim_trailing_whitespace: bool,
+    pub insert_final_newline: bool,
+    pub normalize_indentation: bool,
+}
+
+impl SaveTransforms {
+    pub fn from_settings(settings: &FileTypeSettings) -> Self {
+        Self {
+            trim_trailing_whitespace: settings.trim_trailing_whitespace,
+            insert_final_newline: settings.insert_final_newline,
+            normalize_indentation: settings.normalize_indentation,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Applies the transformations to `tb`, unless it's read-only.
+    pub fn apply(&self, tb: &mut TextBuffer) {
+        if self.normalize_indentation {
+            tb.normalize_indentation();
+        }
+        if self.trim_trailing_whitespace {
+            tb.trim_trailing_whitespace();
+        }
+        if self.insert_final_newline {
+            tb.ensure_final_newline();
+        }
+    }
+
+    /// Returns `text` the way [`SaveTransforms::apply()`] would leave it.
+    fn transform(&self, text: &[u8], style: IndentStyle, crlf: bool) -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len() + 2);
+
+        for line in text.split_inclusive(|&b| b == b'\n') {
+            let (content, newline) = line.split_at(unicode::strip_newline(line).len());
+            let mut content = content.to_vec();
+            if self.normalize_indentation
+                && let Some(fixed) = diagnostics::normalize_indentation(&content, style)
+            {
+                content = fixed;
+            }
+            if self.trim_trailing_whitespace {
+                let end = content.iter().rposition(|&c| c != b' ' && c != b'\t');
+                content.truncate(end.map_or(0, |i| i + 1));
+            }
+            out.extend_from_slice(&content);
+            out.extend_from_slice(newline);
+        }
+
+        if self.insert_final_newline && !out.is_empty() && !out.ends_with(b"\n") {
+            out.extend_from_slice(if crlf { b"\r\n" } else { b"\n" });
+        }
+        out
+    }
+}
+
+/// Opens a diff of what saving the active document would change in a new, read-only document.
+pub fn preview(state: &mut State) -> apperr::Result<()> {
+    let Some(doc) = state.documents.active() else {
+        return Ok(());
+    };
+
+    let mine = doc.contents();
+    let saved = {
+        let tb = doc.buffer.borrow();
+        doc.save_transforms.transform(&mine, tb.indent_style(), tb.is_crlf())
+    };
+    let (old, new) = (diff::lines(&mine), diff::lines(&saved));
+    let hunks = diff::diff(&old, &new);
+    let name = doc.filename.clone();
+    let text = diff::unified(&old, &new, &hunks, &name, &format!("{name} (saved)"), 3);
+
+    let name = format!("{name}.diff");
+    let doc = state.documents.add_scratch(Some(&name), FileType::Diff, &text)?;
+    doc.buffer.borrow_mut().set_read_only(true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform() {
+        let spaces = IndentStyle { use_tabs: false, width: 4 };
+        let all = SaveTransforms {
+            trim_trailing_whitespace: true,
+            insert_final_newline: true,
+            normalize_indentation: true,
+        };
+        let text = b"fn main() {\r\n\tfoo(); \r\n}";
+
+        assert_eq!(all.transform(text, spaces, true), b"fn main() {\r\n    foo();\r\n}\r\n");
+        assert_eq!(SaveTransforms::default().transform(text, spaces, true), text);
+
+        let newline = SaveTransforms { insert_final_newline: true, ..Default::default() };
+        assert_eq!(newline.transform(b"a\n", spaces, false), b"a\n");
+        assert_eq!(newline.transform(b"", spaces, false), b"");
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Warns about unsaved changes to files that are staged for a commit, as the commit would
+//! contain them as they were last saved.
+//!
+//! Like [`crate::file_watch`] does with the open files, the `index` of their repositories
+//! is checked once a second. When it changed, e.g. after a `git add`, git is asked which
+//! files are staged. If some of them have unsaved changes, a dialog offers to save them
+//! and to stage them again.
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use edit::apperr;
+use edit::framebuffer::IndexedColor;
+use edit::git::Repo;
+use edit::helpers::*;
+use edit::tui::*;
+
+use crate::file_watch::{self, DiskStamp};
+use crate::localization::*;
+use crate::state::*;
+
+pub const APP_STAGE_FAILED: apperr::Error = apperr::Error::new_app(8);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct Staged {
+    last_check: Option<Instant>,
+    /// Each repository's `index` as it was last checked, by git directory.
+    indexes: Vec<(PathBuf, Option<DiskStamp>)>,
+    /// The staged files with unsaved changes that the user is asked about.
+    unsaved: Vec<PathBuf>,
+    /// The files the user chose to keep unsaved, with the buffer generation at the time.
+    ignored: Vec<(PathBuf, u32)>,
+}
+
+impl Staged {
+    pub fn has_unsaved(&self) -> bool {
+        !self.unsaved.is_empty()
+    }
+
+    pub fn read_timeout(&self, state: &State) -> Duration {
+        if state.documents.all_documents().iter().any(|doc| doc.repo.is_some()) {
+            POLL_INTERVAL
+        } else {
+            Duration::MAX
+        }
+    }
+}
+
+/// Checks the repositories of the open files for changes to what's staged,
+/// at most once per [`POLL_INTERVAL`].
+pub fn update(state: &mut State) {
+    let now = Instant::now();
+    if state.staged.last_check.is_some_and(|last| now - last < POLL_INTERVAL) {
+        return;
+    }
+    state.staged.last_check = Some(now);
+
+    // Files the user ignored are asked about again once they're edited.
+    let documents = &state.documents;
+    state.staged.ignored.retain(|(path, generation)| {
+        documents.all_documents().iter().any(|doc| {
+            doc.path.as_ref() == Some(path) && doc.buffer.borrow().generation() == *generation
+        })
+    });
+
+    let mut repos: Vec<&Repo> = Vec::new();
+    for doc in state.documents.all_documents() {
+        if let Some(repo) = &doc.repo
+            && !repos.contains(&repo)
+        {
+            repos.push(repo);
+        }
+    }
+
+    let staged = &mut state.staged;
+    let mut changed = Vec::new();
+    for repo in repos {
+        let stamp = file_watch::stamp(&repo.git_dir.join("index"));
+        match staged.indexes.iter_mut().find(|(dir, _)| *dir == repo.git_dir) {
+            Some((_, old)) if *old != stamp => {
+                *old = stamp;
+                changed.push(repo.clone());
+            }
+            Some(_) => {}
+            None => staged.indexes.push((repo.git_dir.clone(), stamp)),
+        }
+    }
+
+    for repo in changed {
+        let Some(files) = staged_files(&repo) else {
+            continue;
+        };
+        for doc in state.documents.all_documents() {
+            let Some(path) = &doc.path else {
+                continue;
+            };
+            let generation = {
+                let tb = doc.buffer.borrow();
+                if !tb.is_dirty() {
+                    continue;
+                }
+                tb.generation()
+            };
+            if files.contains(path)
+                && !staged.unsaved.contains(path)
+                && !staged.ignored.contains(&(path.clone(), generation))
+            {
+                staged.unsaved.push(path.clone());
+            }
+        }
+    }
+}
+
+/// Returns the files that are staged in `repo`, or `None` if git failed.
+fn staged_files(repo: &Repo) -> Option<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&repo.work_tree)
+        .args(["diff", "--cached", "--name-only", "-z"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let names = String::from_utf8_lossy(&output.stdout);
+    Some(
+        names
+            .split('\0')
+            .filter(|name| !name.is_empty())
+            .map(|name| repo.work_tree.join(name))
+            .collect(),
+    )
+}
+
+/// Stages `paths`, which all belong to `repo`.
+fn stage(repo: &Repo, paths: &[PathBuf]) -> apperr::Result<()> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(&repo.work_tree)
+        .args(["add", "--"])
+        .args(paths)
+        .status()?;
+    if status.success() { Ok(()) } else { Err(APP_STAGE_FAILED) }
+}
+
+pub fn draw_dialog(ctx: &mut Context, state: &mut State) {
+    enum Action {
+        None,
+        Save { stage: bool },
+        Ignore,
+    }
+    let mut action = Action::None;
+
+    ctx.modal_begin("staged-unsaved", loc(LocId::StagedUnsavedTitle));
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::Yellow));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            ctx.label("description", loc(LocId::StagedUnsavedDescription));
+            for (i, path) in state.staged.unsaved.iter().enumerate() {
+                ctx.next_block_id_mixin(i as u64);
+                ctx.label("file", &format!("  {}", path.display()));
+                ctx.attr_overflow(Overflow::TruncateTail);
+            }
+
+            ctx.table_begin("choices");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+
+                if ctx.button("stage", loc(LocId::StagedSaveAndStage), ButtonStyle::default()) {
+                    action = Action::Save { stage: true };
+                }
+                ctx.inherit_focus();
+                if ctx.button("save", loc(LocId::StagedSave), ButtonStyle::default()) {
+                    action = Action::Save { stage: false };
+                }
+                if ctx.button("ignore", loc(LocId::StagedIgnore), ButtonStyle::default()) {
+                    action = Action::Ignore;
+                }
+            }
+            ctx.table_end();
+        }
+        ctx.block_end();
+    }
+    if ctx.modal_end() {
+        action = Action::Ignore;
+    }
+
+    let unsaved = match action {
+        Action::None => return,
+        _ => std::mem::take(&mut state.staged.unsaved),
+    };
+    let mut errors = Vec::new();
+    // The files to stage again, grouped by repository.
+    let mut saved: Vec<(Repo, Vec<PathBuf>)> = Vec::new();
+
+    for doc in state.documents.all_documents_mut() {
+        let Some(path) = doc.path.clone().filter(|path| unsaved.contains(path)) else {
+            continue;
+        };
+        match action {
+            Action::Save { stage } => {
+                // A file that changed on disk meanwhile asks before it's saved over.
+                if file_watch::check(doc) {
+                    continue;
+                }
+                if let Err(err) = doc.save(None, &state.trust) {
+                    errors.push(err);
+                    continue;
+                }
+                if stage && let Some(repo) = &doc.repo {
+                    match saved.iter_mut().find(|(r, _)| r == repo) {
+                        Some((_, paths)) => paths.push(path),
+                        None => saved.push((repo.clone(), vec![path])),
+                    }
+                }
+            }
+            _ => {
+                let generation = doc.buffer.borrow().generation();
+                state.staged.ignored.push((path, generation));
+            }
+        }
+    }
+
+    for (repo, paths) in &saved {
+        if let Err(err) = stage(repo, paths) {
+            errors.push(err);
+        }
+    }
+    for err in errors {
+        error_log_add(ctx, state, err);
+    }
+    ctx.needs_rerender();
+}
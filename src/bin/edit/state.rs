@@ -8,10 +8,34 @@ use std::path::{Path, PathBuf};
 
 use edit::framebuffer::IndexedColor;
 use edit::helpers::*;
+use edit::syntax::FileType;
 use edit::tui::*;
-use edit::{apperr, buffer, icu, sys};
-
+use edit::{apperr, buffer, config, icu, sys};
+
+use crate::{
+    alternate_file, clipboard_history, clipboard_locations, command_line, macros, remote, staged,
+    sudo,
+};
+use crate::clipboard_history::ClipboardHistory;
+use crate::command_line::SubstituteConfirm;
+use crate::commit_message::CommitHistory;
+use crate::config_completion::ConfigCompletions;
 use crate::documents::DocumentManager;
+use crate::help;
+use crate::jump::Jump;
+use crate::jump_list::JumpList;
+use crate::line_search::LineSearch;
+use crate::linter::LinterRunner;
+use crate::file_watch::FileWatch;
+use crate::macros::Macros;
+use crate::notes::NoteDialog;
+use crate::pager::Pager;
+use crate::project_search::ProjectSearch;
+use crate::remote::Remote;
+use crate::staged::Staged;
+use crate::swap::Swap;
+use crate::tasks::Tasks;
+use crate::workspace_trust::TrustStore;
 use crate::localization::*;
 
 #[repr(transparent)]
@@ -27,6 +51,20 @@ impl std::fmt::Display for FormatApperr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.0 {
             apperr::APP_ICU_MISSING => f.write_str(loc(LocId::ErrorIcuMissing)),
+            apperr::APP_HEX_INVALID => f.write_str(loc(LocId::ErrorHexInvalid)),
+            help::APP_HELP_NOT_FOUND => f.write_str(loc(LocId::ErrorHelpNotFound)),
+            clipboard_locations::APP_NO_CLIPBOARD_LOCATIONS => {
+                f.write_str(loc(LocId::ErrorNoClipboardLocations))
+            }
+            macros::APP_MACRO_NOT_FOUND => f.write_str(loc(LocId::ErrorMacroNotFound)),
+            clipboard_history::APP_REGISTER_EMPTY => f.write_str(loc(LocId::ErrorRegisterEmpty)),
+            command_line::APP_SURROUNDING_NOT_FOUND => {
+                f.write_str(loc(LocId::ErrorSurroundingNotFound))
+            }
+            sudo::APP_SUDO_NOT_FOUND => f.write_str(loc(LocId::ErrorSudoNotFound)),
+            sudo::APP_SUDO_FAILED => f.write_str(loc(LocId::ErrorSudoFailed)),
+            remote::APP_REMOTE_FAILED => f.write_str(loc(LocId::ErrorRemoteFailed)),
+            staged::APP_STAGE_FAILED => f.write_str(loc(LocId::ErrorStageFailed)),
             apperr::Error::App(code) => write!(f, "Unknown app error code: {code}"),
             apperr::Error::Icu(code) => icu::apperr_format(f, code),
             apperr::Error::Sys(code) => sys::apperr_format(f, code),
@@ -92,6 +130,9 @@ impl<T: ?Sized + AsRef<OsStr>> From<&T> for DisplayablePathBuf {
 pub struct StateSearch {
     pub kind: StateSearchKind,
     pub focus: bool,
+    /// Where the cursor was when the search bar was opened.
+    /// Escape returns there, unless the user went back to the editor in the meantime.
+    pub origin: Option<Point>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -118,9 +159,33 @@ pub enum StateEncodingChange {
     Reopen,
 }
 
+/// How the UI is laid out, depending on the terminal size.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum StateBreakpoint {
+    /// Panels float over the editor, the tab bar only shows the active tab
+    /// and dialogs are full-screen, so that nothing overflows.
+    Small,
+    #[default]
+    Regular,
+}
+
+impl StateBreakpoint {
+    /// Terminals narrower or shorter than this are [`StateBreakpoint::Small`].
+    pub const SMALL_BELOW: Size = Size { width: 60, height: 16 };
+
+    pub fn for_size(size: Size) -> Self {
+        if size.width < Self::SMALL_BELOW.width || size.height < Self::SMALL_BELOW.height {
+            Self::Small
+        } else {
+            Self::Regular
+        }
+    }
+}
+
 pub struct State {
     pub menubar_color_bg: u32,
     pub menubar_color_fg: u32,
+    pub statusbar_color_fg_inactive: u32,
 
     pub documents: DocumentManager,
 
@@ -136,29 +201,92 @@ pub struct State {
     pub file_picker_entries: Option<Vec<DisplayablePathBuf>>,
     pub file_picker_overwrite_warning: Option<PathBuf>, // The path the warning is about.
 
+    // Whether the editor (or its search bar) has focus, as opposed to the menus or dialogs.
+    pub editor_active: bool,
+
+    // The recent history of the repository, for completions in git commit messages.
+    pub commit_history: Option<CommitHistory>,
+    pub linters: LinterRunner,
+    pub trust: TrustStore,
+    /// The config and its buffer generation that were last checked for problems.
+    pub config_validated: Option<(PathBuf, u32)>,
+    pub config_completions: ConfigCompletions,
+    pub wants_trust_prompt: Option<PathBuf>,
+    pub wants_workspace_trust: bool,
+
     pub wants_search: StateSearch,
     pub search_needle: String,
     pub search_replacement: String,
     pub search_options: buffer::SearchOptions,
     pub search_success: bool,
+    /// The open files with hits and their number, while asking whether to replace
+    /// them all. See the search bar's "All Tabs" button.
+    pub wants_replace_in_tabs: Vec<(String, usize)>,
+    pub wants_line_search: bool,
+    pub line_search: LineSearch,
+    pub wants_project_search: bool,
+    pub project_search: ProjectSearch,
+    pub wants_jump: bool,
+    pub jump: Jump,
+    pub jump_list: JumpList,
+    pub pager: Pager,
+    pub macros: Macros,
+    pub file_watch: FileWatch,
+    pub note_dialog: Option<NoteDialog>,
+    pub wants_note_list: bool,
+    pub tasks: Tasks,
+    pub remote: Remote,
+    pub swap: Swap,
+    pub staged: Staged,
+    pub wants_task_history: bool,
+    /// The density in effect for this frame. Narrow terminals force [`config::Density::Compact`].
+    pub density: config::Density,
+    /// The breakpoint in effect for this frame.
+    pub breakpoint: StateBreakpoint,
 
     pub wants_save: bool,
+    /// Set when saving was denied access to the file, see [`crate::sudo`].
+    pub wants_sudo_save: bool,
     pub wants_statusbar_focus: bool,
     pub wants_encoding_picker: bool,
     pub wants_encoding_change: StateEncodingChange,
     pub wants_indentation_picker: bool,
     pub wants_document_picker: bool,
+    /// The choices of File > Open from Clipboard, if it found several.
+    pub wants_clipboard_locations: Vec<PathBuf>,
+    pub clipboard_history: ClipboardHistory,
     pub wants_about: bool,
+    pub wants_cheat_sheet: bool,
     pub wants_close: bool,
     pub wants_exit: bool,
     pub wants_goto: bool,
     pub goto_target: String,
     pub goto_invalid: bool,
+    pub wants_command_line: bool,
+    pub command_line: String,
+    pub command_line_invalid: bool,
+    pub substitute_confirm: Option<SubstituteConfirm>,
 
     pub osc_title_filename: String,
     pub osc_clipboard_seen_generation: u32,
     pub osc_clipboard_send_generation: u32,
     pub osc_clipboard_always_send: bool,
+    /// The most OSC 52 sends at once. Terminals have limits of their own.
+    pub osc_clipboard_limit: usize,
+    /// The clipboard generation that was last tried with the system clipboard tools.
+    pub osc_clipboard_system_generation: u32,
+    /// The last clipboard generation that the system clipboard got in full.
+    /// Until the current one did, Ctrl+V doesn't take over the system clipboard.
+    pub osc_clipboard_shared_generation: u32,
+    /// Set by `clipboard.osc52_read`: Ctrl+V asks the terminal for its clipboard.
+    pub osc_clipboard_read: bool,
+    /// Set if `ui.alternate_screen = false`: The editor draws over the regular screen,
+    /// so that what it prints to it ends up in the terminal's scrollback.
+    pub inline_mode: bool,
+    /// Set by `--low-bandwidth`, or if the terminal is slow to respond. See `main()`.
+    pub low_bandwidth: bool,
+    /// In inline mode, the document that was active when exiting, printed after the editor quits.
+    pub exit_output: Option<String>,
     pub exit: bool,
 }
 
@@ -167,6 +295,7 @@ impl State {
         Ok(Self {
             menubar_color_bg: 0,
             menubar_color_fg: 0,
+            statusbar_color_fg_inactive: 0,
 
             documents: Default::default(),
 
@@ -181,32 +310,88 @@ impl State {
             file_picker_entries: None,
             file_picker_overwrite_warning: None,
 
-            wants_search: StateSearch { kind: StateSearchKind::Hidden, focus: false },
+            editor_active: true,
+
+            commit_history: None,
+            linters: Default::default(),
+            trust: TrustStore::load(),
+            config_validated: None,
+            config_completions: Default::default(),
+            wants_trust_prompt: None,
+            wants_workspace_trust: false,
+
+            wants_search: StateSearch {
+                kind: StateSearchKind::Hidden,
+                focus: false,
+                origin: None,
+            },
             search_needle: Default::default(),
             search_replacement: Default::default(),
             search_options: Default::default(),
             search_success: true,
+            wants_replace_in_tabs: Vec::new(),
+            wants_line_search: false,
+            wants_project_search: false,
+            project_search: Default::default(),
+            line_search: Default::default(),
+            wants_jump: false,
+            jump: Default::default(),
+            jump_list: Default::default(),
+            pager: Default::default(),
+            macros: Default::default(),
+            file_watch: Default::default(),
+            note_dialog: None,
+            wants_note_list: false,
+            tasks: Default::default(),
+            remote: Default::default(),
+            swap: Default::default(),
+            staged: Default::default(),
+            wants_task_history: false,
+            density: Default::default(),
+            breakpoint: Default::default(),
 
             wants_save: false,
+            wants_sudo_save: false,
             wants_statusbar_focus: false,
             wants_encoding_picker: false,
             wants_encoding_change: StateEncodingChange::None,
             wants_indentation_picker: false,
             wants_document_picker: false,
+            wants_clipboard_locations: Vec::new(),
+            clipboard_history: Default::default(),
             wants_about: false,
+            wants_cheat_sheet: false,
             wants_close: false,
             wants_exit: false,
             wants_goto: false,
             goto_target: Default::default(),
             goto_invalid: false,
+            wants_command_line: false,
+            command_line: Default::default(),
+            command_line_invalid: false,
+            substitute_confirm: None,
 
             osc_title_filename: Default::default(),
             osc_clipboard_seen_generation: 0,
             osc_clipboard_send_generation: 0,
             osc_clipboard_always_send: false,
+            osc_clipboard_limit: 32 * MEBI,
+            osc_clipboard_system_generation: 0,
+            osc_clipboard_shared_generation: 0,
+            osc_clipboard_read: false,
+            inline_mode: false,
+            low_bandwidth: false,
+            exit_output: None,
             exit: false,
         })
     }
+
+    /// The row at which panels float over the editor, if they do. See [`StateBreakpoint::Small`].
+    pub fn panel_overlay_top(&self) -> Option<CoordType> {
+        // Below the menubar and, if there's more than one document, the tab bar.
+        (self.breakpoint == StateBreakpoint::Small)
+            .then(|| 1 + (self.documents.len() > 1) as CoordType)
+    }
 }
 
 pub fn draw_add_untitled_document(ctx: &mut Context, state: &mut State) {
@@ -215,16 +400,48 @@ pub fn draw_add_untitled_document(ctx: &mut Context, state: &mut State) {
     }
 }
 
+pub fn draw_add_scratch_document(ctx: &mut Context, state: &mut State, file_type: FileType) {
+    if let Err(err) = state.documents.add_scratch(None, file_type, "") {
+        error_log_add(ctx, state, err);
+    }
+}
+
+/// Opens the test file of the active source file or vice versa. See [`alternate_file`].
+pub fn draw_switch_to_alternate_file(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        return;
+    };
+    let Some(path) = doc.path.clone() else {
+        return;
+    };
+    let result = alternate_file::find_or_create(config::get(), &path, doc.file_type)
+        .and_then(|alt| match alt {
+            Some(alt) => state.documents.add_file_path(&alt).map(|_| ()),
+            None => Ok(()),
+        });
+    if let Err(err) = result {
+        error_log_add(ctx, state, err);
+    }
+}
+
 pub fn error_log_add(ctx: &mut Context, state: &mut State, err: apperr::Error) {
-    let msg = format!("{}", FormatApperr::from(err));
-    if !msg.is_empty() {
-        state.error_log[state.error_log_index] = msg;
-        state.error_log_index = (state.error_log_index + 1) % state.error_log.len();
-        state.error_log_count = state.error_log.len().min(state.error_log_count + 1);
+    if error_log_push(state, err) {
         ctx.needs_rerender();
     }
 }
 
+/// Like [`error_log_add()`], for use outside of drawing. Returns true if the log changed.
+pub fn error_log_push(state: &mut State, err: apperr::Error) -> bool {
+    let msg = format!("{}", FormatApperr::from(err));
+    if msg.is_empty() {
+        return false;
+    }
+    state.error_log[state.error_log_index] = msg;
+    state.error_log_index = (state.error_log_index + 1) % state.error_log.len();
+    state.error_log_count = state.error_log.len().min(state.error_log_count + 1);
+    true
+}
+
 pub fn draw_error_log(ctx: &mut Context, state: &mut State) {
     ctx.modal_begin("error", loc(LocId::ErrorDialogTitle));
     ctx.attr_background_rgba(ctx.indexed(IndexedColor::Red));
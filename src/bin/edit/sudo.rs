@@ -0,0 +1,146 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Saves files the user isn't allowed to write, like one in `/etc` opened without `sudo`.
+//!
+//! When saving fails with a permission error, a dialog offers to save the file as root
+//! instead. The document is then piped to `sudo tee file`, or `doas` or `pkexec` if there's
+//! no `sudo`. They ask for the password on the terminal, which is switched back to its
+//! regular screen and modes meanwhile. `tee` writes the file in place, so that its owner
+//! and permissions stay the same.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use edit::apperr;
+use edit::buffer::TextBuffer;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::sys;
+use edit::tui::*;
+
+use crate::localization::*;
+use crate::state::*;
+
+pub const APP_SUDO_NOT_FOUND: apperr::Error = apperr::Error::new_app(5);
+pub const APP_SUDO_FAILED: apperr::Error = apperr::Error::new_app(6);
+
+/// The commands that run another one as root, in order of preference.
+const TOOLS: [&str; 3] = ["sudo", "doas", "pkexec"];
+
+/// Whether saving again as root might get around `err`.
+pub fn can_retry(err: apperr::Error) -> bool {
+    cfg!(unix) && sys::apperr_is_permission_denied(err)
+}
+
+/// Writes `tb` to `path` with the first of [`TOOLS`] that exists.
+pub fn write(tb: &mut TextBuffer, path: &Path) -> apperr::Result<()> {
+    // `tee` truncates the file, which must not be mapped then.
+    tb.unmap()?;
+
+    for tool in TOOLS {
+        let mut child = match Command::new(tool)
+            .args(["tee", "--"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        // Closing stdin once it's written lets `tee` finish.
+        let written = tb.write_file(&mut child.stdin.take().unwrap());
+        let status = child.wait()?;
+        if !status.success() {
+            tb.mark_as_dirty();
+            return Err(APP_SUDO_FAILED);
+        }
+        return written;
+    }
+
+    Err(APP_SUDO_NOT_FOUND)
+}
+
+pub fn draw_dialog(ctx: &mut Context, state: &mut State) {
+    let Some(doc) = state.documents.active() else {
+        state.wants_sudo_save = false;
+        return;
+    };
+
+    let mut save = false;
+    let mut done = false;
+
+    ctx.modal_begin("sudo", loc(LocId::SudoDialogTitle));
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::Red));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::BrightWhite));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            ctx.label("filename", &doc.filename);
+            ctx.label("description", loc(LocId::SudoDialogDescription));
+
+            ctx.table_begin("choices");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+
+                if ctx.button("save", loc(LocId::SudoDialogSave), ButtonStyle::default()) {
+                    save = true;
+                }
+                ctx.inherit_focus();
+                if ctx.button("cancel", loc(LocId::Cancel), ButtonStyle::default()) {
+                    done = true;
+                }
+            }
+            ctx.table_end();
+        }
+        ctx.block_end();
+    }
+    done |= ctx.modal_end();
+
+    if save {
+        if let Err(err) = save_elevated(ctx, state) {
+            error_log_add(ctx, state, err);
+        }
+        done = true;
+    }
+    if done {
+        state.wants_sudo_save = false;
+        ctx.needs_rerender();
+    }
+}
+
+/// Saves the active document as root, with the terminal handed over for the password prompt.
+fn save_elevated(ctx: &mut Context, state: &mut State) -> apperr::Result<()> {
+    let Some(doc) = state.documents.active_mut() else {
+        return Ok(());
+    };
+
+    if state.inline_mode {
+        // Prompt below the editor, like the shell would.
+        sys::write_stdout(&format!("\x1b[0m\x1b[{}H\r\n\x1b[?25h", ctx.size().height));
+    } else {
+        sys::write_stdout("\x1b[0m\x1b[?1049l\x1b[?25h");
+    }
+
+    let result = sys::with_initial_modes(|| doc.save_elevated(&state.trust));
+
+    if state.inline_mode {
+        // Make room for the editor again, below whatever was printed.
+        sys::write_stdout(&"\r\n".repeat(ctx.size().height as usize));
+    } else {
+        sys::write_stdout("\x1b[?1049h");
+    }
+    ctx.invalidate_screen();
+    result
+}
@@ -0,0 +1,367 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Swap files: a copy of each modified document, kept up to date while it's edited, so that
+//! the changes survive a crash, a `kill` or a lost SSH connection.
+//!
+//! They're written to `swap` in the state directory every few seconds, while the document
+//! changes. Each editor process has its own, named after the file and the process ID like
+//! `%etc%hosts~1234.swp`. They're deleted once the document is saved or closed, and when
+//! the editor exits. A swap file holds the document in UTF-8 after a short header:
+//!
+//! ```text
+//! edit swap 1
+//! pid: 1234
+//! path: /etc/hosts
+//!
+//! 127.0.0.1 localhost
+//! ```
+//!
+//! At startup, the swap files of editors that aren't running anymore are offered for
+//! recovery, which opens the file with the changes as a step that can be undone.
+//! Opening a file that another running editor has a swap file of warns about it, as one
+//! of the two would save over the other's changes.
+
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+use edit::apperr;
+use edit::config::Config;
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::sys;
+use edit::tui::*;
+
+use crate::backup::backup_prefix;
+use crate::localization::*;
+use crate::state::*;
+
+const MAGIC: &str = "edit swap 1\n";
+const EXTENSION: &str = ".swp";
+/// How often the swap files of documents that changed are rewritten.
+const WRITE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A swap file of another editor process.
+pub struct ForeignSwap {
+    swap_path: PathBuf,
+    path: PathBuf,
+    /// Whether the process is still running, or its changes are up for recovery.
+    running: bool,
+}
+
+#[derive(Default)]
+pub struct Swap {
+    last_write: Option<Instant>,
+    /// The documents that have a swap file of this process, with the buffer generation it holds.
+    written: Vec<(PathBuf, u32)>,
+    /// The files that were looked for foreign swap files.
+    checked: Vec<PathBuf>,
+    /// The foreign swap files the user is asked about, the first one first.
+    pending: Vec<ForeignSwap>,
+}
+
+impl Swap {
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// How long the main loop may wait for input before [`update()`] needs to be called.
+    pub fn read_timeout(&self, state: &State) -> Duration {
+        let dirty = state.documents.all_documents().iter().any(|doc| {
+            doc.path.is_some() && doc.buffer.borrow().is_dirty()
+        });
+        if dirty || !self.written.is_empty() { WRITE_INTERVAL } else { Duration::MAX }
+    }
+}
+
+fn swap_dir() -> Option<PathBuf> {
+    Some(Config::state_dir()?.join("swap"))
+}
+
+fn swap_path(dir: &Path, path: &Path, pid: u32) -> PathBuf {
+    dir.join(format!("{}{pid}{EXTENSION}", backup_prefix(path)))
+}
+
+/// Returns the process ID and the path in the header of a swap file, and its contents.
+fn parse(data: &[u8]) -> Option<(u32, PathBuf, &[u8])> {
+    let data = data.strip_prefix(MAGIC.as_bytes())?;
+    let end = data.windows(2).position(|w| w == b"\n\n")?;
+    let header = str::from_utf8(&data[..end]).ok()?;
+
+    let mut pid = None;
+    let mut path = None;
+    for line in header.lines() {
+        match line.split_once(": ") {
+            Some(("pid", value)) => pid = value.parse().ok(),
+            Some(("path", value)) => path = Some(PathBuf::from(value)),
+            _ => {}
+        }
+    }
+    Some((pid?, path?, &data[end + 2..]))
+}
+
+fn write(dir: &Path, path: &Path, contents: &[u8]) -> apperr::Result<()> {
+    let pid = process::id();
+    let dst = swap_path(dir, path, pid);
+    let mut temp = dst.clone().into_os_string();
+    temp.push(".tmp");
+
+    let result = (|| {
+        fs::create_dir_all(dir)?;
+        let mut options = fs::File::options();
+        options.write(true).create(true).truncate(true);
+        // It holds the text of a file that others may not be allowed to read.
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        write!(file, "{MAGIC}pid: {pid}\npath: {}\n\n", path.display())?;
+        file.write_all(contents)?;
+        drop(file);
+        // Replacing it at once means that there's always a whole swap file to recover from.
+        fs::rename(&temp, &dst)
+    })();
+
+    // The previous swap file is out of date now, and wouldn't be deleted anymore.
+    if result.is_err() {
+        _ = fs::remove_file(&temp);
+        _ = fs::remove_file(&dst);
+    }
+    Ok(result?)
+}
+
+/// Looks for the swap files of editors that aren't running anymore, to offer recovering them.
+pub fn scan(state: &mut State) {
+    let Some(dir) = swap_dir() else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let swap_path = entry.path();
+        if !swap_path.to_string_lossy().ends_with(EXTENSION) {
+            continue;
+        }
+        let Ok(data) = fs::read(&swap_path) else {
+            continue;
+        };
+        if let Some((pid, path, _)) = parse(&data)
+            && pid != process::id()
+            && !sys::process_exists(pid)
+        {
+            state.swap.pending.push(ForeignSwap { swap_path, path, running: false });
+        }
+    }
+}
+
+/// Looks for the swap files of other editors of the newly opened files, and writes or deletes
+/// this one's, at most every [`WRITE_INTERVAL`].
+pub fn update(state: &mut State) {
+    let Some(dir) = swap_dir() else {
+        return;
+    };
+    let swap = &mut state.swap;
+
+    for doc in state.documents.all_documents() {
+        if let Some(path) = &doc.path
+            && !swap.checked.contains(path)
+        {
+            swap.checked.push(path.clone());
+            check_foreign(&dir, path, &mut swap.pending);
+        }
+    }
+
+    let now = Instant::now();
+    if swap.last_write.is_some_and(|last| now - last < WRITE_INTERVAL) {
+        return;
+    }
+    swap.last_write = Some(now);
+
+    let mut previous = std::mem::take(&mut swap.written);
+    for doc in state.documents.all_documents() {
+        let Some(path) = &doc.path else {
+            continue;
+        };
+        let generation = {
+            let tb = doc.buffer.borrow();
+            if !tb.is_dirty() || doc.large_file {
+                continue;
+            }
+            tb.generation()
+        };
+        let old = previous.iter().position(|(p, _)| p == path).map(|i| previous.swap_remove(i));
+        if old.is_some_and(|(_, g)| g == generation) || write(&dir, path, &doc.contents()).is_ok()
+        {
+            swap.written.push((path.clone(), generation));
+        }
+    }
+
+    // The rest were saved or closed.
+    for (path, _) in previous {
+        _ = fs::remove_file(swap_path(&dir, &path, process::id()));
+    }
+}
+
+/// Deletes the swap files of this process, when the editor exits.
+pub fn remove_all(state: &mut State) {
+    let Some(dir) = swap_dir() else {
+        return;
+    };
+    for (path, _) in state.swap.written.drain(..) {
+        _ = fs::remove_file(swap_path(&dir, &path, process::id()));
+    }
+}
+
+/// Adds the swap files other editors have of `path` to `pending`.
+fn check_foreign(dir: &Path, path: &Path, pending: &mut Vec<ForeignSwap>) {
+    let prefix = backup_prefix(path);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix)?.strip_suffix(EXTENSION))
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let swap_path = entry.path();
+        if pid == process::id() || pending.iter().any(|p| p.swap_path == swap_path) {
+            continue;
+        }
+        let running = sys::process_exists(pid);
+        pending.push(ForeignSwap { swap_path, path: path.to_path_buf(), running });
+    }
+}
+
+/// Asks what to do about the first of the pending foreign swap files.
+pub fn draw_dialog(ctx: &mut Context, state: &mut State) {
+    let Some(foreign) = state.swap.pending.first() else {
+        return;
+    };
+
+    enum Action {
+        None,
+        Recover,
+        Discard,
+        ReadOnly,
+        Dismiss,
+    }
+    let mut action = Action::None;
+
+    let (title, description) = if foreign.running {
+        (LocId::SwapInUseTitle, LocId::SwapInUseDescription)
+    } else {
+        (LocId::SwapRecoverTitle, LocId::SwapRecoverDescription)
+    };
+    ctx.modal_begin("swap", loc(title));
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::Yellow));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            ctx.label("path", &foreign.path.to_string_lossy());
+            ctx.label("description", loc(description));
+
+            ctx.table_begin("choices");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+
+                if foreign.running {
+                    if ctx.button("read-only", loc(LocId::SwapReadOnly), ButtonStyle::default()) {
+                        action = Action::ReadOnly;
+                    }
+                    ctx.inherit_focus();
+                    if ctx.button("edit", loc(LocId::SwapEditAnyway), ButtonStyle::default()) {
+                        action = Action::Dismiss;
+                    }
+                } else {
+                    if ctx.button("recover", loc(LocId::SwapRecover), ButtonStyle::default()) {
+                        action = Action::Recover;
+                    }
+                    ctx.inherit_focus();
+                    if ctx.button("discard", loc(LocId::SwapDiscard), ButtonStyle::default()) {
+                        action = Action::Discard;
+                    }
+                    // Asked again at the next start.
+                    if ctx.button("later", loc(LocId::SwapLater), ButtonStyle::default()) {
+                        action = Action::Dismiss;
+                    }
+                }
+            }
+            ctx.table_end();
+        }
+        ctx.block_end();
+    }
+    if ctx.modal_end() {
+        action = Action::Dismiss;
+    }
+
+    let result = match action {
+        Action::None => return,
+        Action::Recover => recover(state),
+        Action::Discard => fs::remove_file(&state.swap.pending[0].swap_path).map_err(Into::into),
+        Action::ReadOnly => {
+            let path = &state.swap.pending[0].path;
+            if let Some(doc) = state
+                .documents
+                .all_documents_mut()
+                .into_iter()
+                .find(|doc| doc.path.as_ref() == Some(path))
+            {
+                doc.buffer.borrow_mut().set_read_only(true);
+            }
+            Ok(())
+        }
+        Action::Dismiss => Ok(()),
+    };
+    state.swap.pending.remove(0);
+    if let Err(err) = result {
+        error_log_add(ctx, state, err);
+    }
+    ctx.needs_rerender();
+}
+
+/// Opens the file of the first pending swap file with the changes it holds,
+/// and deletes the swap file.
+fn recover(state: &mut State) -> apperr::Result<()> {
+    let foreign = &state.swap.pending[0];
+    let data = fs::read(&foreign.swap_path)?;
+    let Some((_, _, contents)) = parse(&data) else {
+        return Ok(());
+    };
+
+    let doc = state.documents.add_file_path(&foreign.path)?;
+    doc.buffer.borrow_mut().set_bytes_by_diff(contents);
+    fs::remove_file(&foreign.swap_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let data = b"edit swap 1\npid: 1234\npath: /etc/hosts\n\n127.0.0.1 localhost\n\n";
+        let (pid, path, contents) = parse(data).unwrap();
+        assert_eq!(pid, 1234);
+        assert_eq!(path, Path::new("/etc/hosts"));
+        assert_eq!(contents, b"127.0.0.1 localhost\n\n");
+
+        assert!(parse(b"edit swap 1\npath: /etc/hosts\n\n").is_none());
+        assert!(parse(b"edit swap 2\npid: 1\npath: /etc/hosts\n\n").is_none());
+    }
+}
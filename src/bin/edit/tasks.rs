@@ -0,0 +1,326 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Tasks: shell commands like a build, run from the command line, with the errors in their
+//! output lined up for the build-edit-fix loop.
+//!
+//! * `:run command` runs the command in the current directory, in the background.
+//!   Its output replaces the contents of the "task output" document once it's done.
+//! * `:run` picks one of the commands run before, most recent first.
+//!   Ctrl+Shift+B runs the last one again.
+//! * `:cn`, `:cp` and `:cc N` go to the next, previous and N-th error. `:cc` goes back
+//!   to the current one.
+//!
+//! Errors are the lines of the output that start with `path:line:column: message` or
+//! `path:line: message`, like most compilers print them, and the `--> path:line:column`
+//! lines rustc prints below the message. Only paths of existing files count. In open files,
+//! the errors are shown like diagnostics, numbered so that `:cc` can go back to one.
+//!
+//! The history and the errors last until the editor exits.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use edit::apperr;
+use edit::diagnostics::{Diagnostic, Severity};
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::syntax::FileType;
+use edit::{path, stack_trace};
+use edit::tui::*;
+
+use crate::documents::Document;
+use crate::localization::*;
+use crate::state::*;
+
+/// The name of the document the output goes into.
+const OUTPUT_NAME: &str = "task output";
+/// The diagnostics source the errors are shown as.
+const SOURCE: &str = "task";
+/// How often a running task is checked on.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How many commands [`Tasks::history()`] remembers.
+const HISTORY_LEN: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorJump {
+    /// The error that was gone to last, or the first one.
+    Current,
+    Next,
+    Previous,
+    /// 1-based, like the numbers shown with the errors.
+    Nth(usize),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct TaskError {
+    path: PathBuf,
+    /// 0-based.
+    line: CoordType,
+    /// 0-based.
+    column: CoordType,
+    message: String,
+}
+
+#[derive(Default)]
+pub struct Tasks {
+    /// The commands run so far, the most recent one last.
+    history: Vec<String>,
+    running: Option<mpsc::Receiver<String>>,
+    errors: Vec<TaskError>,
+    /// The index of the error that was gone to last.
+    current: Option<usize>,
+}
+
+impl Tasks {
+    /// The commands run so far, the most recent one first.
+    pub fn history(&self) -> impl Iterator<Item = &str> {
+        self.history.iter().rev().map(String::as_str)
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.history.last().map(String::as_str)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Runs `command` in the background. A task that's still running is forgotten about.
+    pub fn start(&mut self, command: &str) {
+        self.history.retain(|c| c != command);
+        self.history.push(command.to_string());
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let command = command.to_string();
+        thread::spawn(move || _ = sender.send(run(&command)));
+        self.running = Some(receiver);
+    }
+
+    /// How long the main loop may wait for input before [`update()`] needs to be called.
+    pub fn read_timeout(&self) -> Duration {
+        if self.running.is_some() { POLL_INTERVAL } else { Duration::MAX }
+    }
+}
+
+/// Runs `command` with the shell and returns what it printed, followed by its exit status.
+fn run(command: &str) -> String {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(format!("{command} 2>&1"));
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(format!("exec 2>&1\n{command}"));
+        shell
+    };
+
+    let mut text = format!("$ {command}\n\n");
+    match shell.output() {
+        Ok(output) => {
+            text.push_str(&String::from_utf8_lossy(&output.stdout));
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&format!("\n[{}]\n", output.status));
+        }
+        Err(err) => text.push_str(&format!("{err}\n")),
+    }
+    text
+}
+
+/// Shows the output of a finished task, and its errors.
+pub fn update(state: &mut State) {
+    let Some(receiver) = &state.tasks.running else {
+        return;
+    };
+    let output = match receiver.try_recv() {
+        Ok(output) => output,
+        Err(mpsc::TryRecvError::Empty) => return,
+        Err(mpsc::TryRecvError::Disconnected) => String::new(),
+    };
+    state.tasks.running = None;
+
+    let cwd = env::current_dir().unwrap_or_default();
+    state.tasks.errors = parse_errors(&output, &cwd);
+    state.tasks.current = None;
+    for doc in state.documents.all_documents() {
+        show_errors(&state.tasks, doc);
+    }
+
+    let is_output = |doc: &Document| doc.path.is_none() && doc.filename == OUTPUT_NAME;
+    if state.documents.update_active(is_output)
+        && let Some(doc) = state.documents.active()
+    {
+        doc.buffer.borrow_mut().set_text(&output);
+    } else {
+        _ = state.documents.add_scratch(Some(OUTPUT_NAME), FileType::Plain, &output);
+    }
+}
+
+/// Opens the file of the error that `jump` leads to, at the error.
+pub fn goto_error(state: &mut State, jump: ErrorJump) -> apperr::Result<()> {
+    let tasks = &mut state.tasks;
+    let Some(last) = tasks.errors.len().checked_sub(1) else {
+        return Ok(());
+    };
+    let index = match (jump, tasks.current) {
+        (ErrorJump::Next, Some(i)) => (i + 1).min(last),
+        (ErrorJump::Previous, Some(i)) => i.saturating_sub(1),
+        (ErrorJump::Current, Some(i)) => i,
+        (ErrorJump::Current | ErrorJump::Next | ErrorJump::Previous, None) => 0,
+        (ErrorJump::Nth(n), _) => n.saturating_sub(1).min(last),
+    };
+    tasks.current = Some(index);
+
+    if let Some(doc) = state.documents.active() {
+        state.jump_list.push(&doc.buffer);
+    }
+    let error = &state.tasks.errors[index];
+    let doc = state.documents.add_file_path(&error.path)?;
+    show_errors(&state.tasks, doc);
+    let mut tb = doc.buffer.borrow_mut();
+    tb.cursor_move_to_logical(Point { x: error.column, y: error.line });
+    tb.make_cursor_visible();
+    Ok(())
+}
+
+/// Shows the errors in `doc` as diagnostics, or clears the ones of an earlier run.
+fn show_errors(tasks: &Tasks, doc: &Document) {
+    let Some(path) = &doc.path else {
+        return;
+    };
+    let total = tasks.errors.len();
+    let diagnostics = tasks
+        .errors
+        .iter()
+        .enumerate()
+        .filter(|(_, error)| error.path == *path)
+        .map(|(i, error)| Diagnostic {
+            line: error.line,
+            column: error.column,
+            severity: if error.message.starts_with("warning") {
+                Severity::Warning
+            } else {
+                Severity::Error
+            },
+            message: format!("[{}/{total}] {}", i + 1, error.message),
+        })
+        .collect();
+    doc.buffer.borrow_mut().set_diagnostics(SOURCE, diagnostics);
+}
+
+fn parse_errors(output: &str, dir: &Path) -> Vec<TaskError> {
+    let mut errors = Vec::new();
+    // rustc prints the message above the location.
+    let mut heading = "";
+
+    for line in output.lines() {
+        let (location, message) = if let Some(location) = line.trim_start().strip_prefix("--> ") {
+            (location.trim_end(), heading)
+        } else {
+            if !line.starts_with(char::is_whitespace) {
+                heading = line;
+            }
+            match line.split_once(": ") {
+                Some((location, message)) => (location, message),
+                None => continue,
+            }
+        };
+
+        let Some((path, line, column)) = stack_trace::split_location(location) else {
+            continue;
+        };
+        let path = path::normalize(&dir.join(path));
+        if line < 1 || !path.is_file() {
+            continue;
+        }
+        errors.push(TaskError {
+            path,
+            line: line - 1,
+            column: column.unwrap_or(1).max(1) - 1,
+            message: message.trim().to_string(),
+        });
+    }
+
+    errors
+}
+
+/// Lists the commands run before. Picking one runs it again.
+pub fn draw_history(ctx: &mut Context, state: &mut State) {
+    let mut chosen = None;
+
+    ctx.modal_begin("tasks", loc(LocId::FileTaskHistory));
+    {
+        let count = state.tasks.history.len();
+        let width = (ctx.size().width - 20).clamp(10, 80);
+        let height = (ctx.size().height - 10).clamp(1, count.max(1) as _);
+
+        ctx.scrollarea_begin("scrollarea", Size { width, height });
+        ctx.attr_background_rgba(ctx.indexed_alpha(IndexedColor::Black, 1, 4));
+        ctx.inherit_focus();
+        {
+            ctx.list_begin("history");
+            ctx.inherit_focus();
+            for command in state.tasks.history() {
+                if ctx.list_item(false, command) == ListSelection::Activated {
+                    chosen = Some(command.to_string());
+                }
+            }
+            ctx.list_end();
+        }
+        ctx.scrollarea_end();
+    }
+    let done = ctx.modal_end();
+
+    if let Some(command) = &chosen {
+        state.tasks.start(command);
+    }
+    if done || chosen.is_some() {
+        state.wants_task_history = false;
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let output = concat!(
+            "$ cargo build\n",
+            "error[E0308]: mismatched types\n",
+            "  --> src/lib.rs:3:5\n",
+            "src/helpers.rs:10: warning: unused\n",
+            "http://localhost:8080: not a file\n",
+            "src/missing.rs:1:1: gone\n",
+        );
+        let errors = parse_errors(output, dir);
+        assert_eq!(
+            errors,
+            [
+                TaskError {
+                    path: dir.join("src/lib.rs"),
+                    line: 2,
+                    column: 4,
+                    message: "error[E0308]: mismatched types".to_string(),
+                },
+                TaskError {
+                    path: dir.join("src/helpers.rs"),
+                    line: 9,
+                    column: 0,
+                    message: "warning: unused".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Dialogs that take several steps, like pattern → scope → preview → apply.
+//!
+//! A [`Wizard`] keeps the current step and the data that the steps fill in, so that going
+//! back shows what was entered before. It draws the Back, Next (or Finish) and Cancel
+//! buttons, while the feature only draws the content of each step.
+
+use edit::arena_format;
+use edit::helpers::*;
+use edit::input::vk;
+use edit::tui::*;
+
+use crate::localization::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardResult {
+    Running,
+    Finished,
+    Cancelled,
+}
+
+#[derive(Clone, Copy)]
+enum Navigation {
+    None,
+    Back,
+    Next,
+    Cancel,
+}
+
+pub struct Wizard<T> {
+    pub data: T,
+    step: usize,
+    steps: usize,
+}
+
+impl<T> Wizard<T> {
+    pub fn new(data: T, steps: usize) -> Self {
+        Self { data, step: 0, steps: steps.max(1) }
+    }
+
+    /// Draws the current step in a modal. `draw_step` draws its content and returns whether
+    /// it's complete, which Next and Finish require. Enter presses them, Escape cancels.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        classname: &'static str,
+        title: &str,
+        draw_step: impl FnOnce(&mut Context, &mut T, usize) -> bool,
+    ) -> WizardResult {
+        let mut navigation = Navigation::None;
+        let title = if self.steps > 1 {
+            arena_format!(ctx.arena(), "{title} ({}/{})", self.step + 1, self.steps)
+        } else {
+            arena_format!(ctx.arena(), "{title}")
+        };
+
+        ctx.modal_begin(classname, &title);
+        {
+            // Each step gets its own block, so that `focus_on_first_present` works per step.
+            ctx.next_block_id_mixin(self.step as u64);
+            ctx.block_begin("step");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            let complete = draw_step(ctx, &mut self.data, self.step);
+            ctx.block_end();
+
+            ctx.table_begin("buttons");
+            ctx.attr_padding(Rect::three(1, 2, 1));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+
+                if self.step > 0
+                    && ctx.button("back", loc(LocId::WizardBack), ButtonStyle::default())
+                {
+                    navigation = Navigation::Back;
+                }
+                let next = if self.step + 1 == self.steps {
+                    LocId::WizardFinish
+                } else {
+                    LocId::WizardNext
+                };
+                if ctx.button("next", loc(next), ButtonStyle::default()) && complete {
+                    navigation = Navigation::Next;
+                }
+                if ctx.button("cancel", loc(LocId::Cancel), ButtonStyle::default()) {
+                    navigation = Navigation::Cancel;
+                }
+            }
+            ctx.table_end();
+
+            if complete && ctx.consume_shortcut(vk::RETURN) {
+                navigation = Navigation::Next;
+            }
+        }
+        if ctx.modal_end() {
+            navigation = Navigation::Cancel;
+        }
+
+        if !matches!(navigation, Navigation::None) {
+            ctx.needs_rerender();
+        }
+        self.navigate(navigation)
+    }
+
+    fn navigate(&mut self, navigation: Navigation) -> WizardResult {
+        match navigation {
+            Navigation::None => WizardResult::Running,
+            Navigation::Back => {
+                self.step = self.step.saturating_sub(1);
+                WizardResult::Running
+            }
+            Navigation::Next if self.step + 1 < self.steps => {
+                self.step += 1;
+                WizardResult::Running
+            }
+            Navigation::Next => WizardResult::Finished,
+            Navigation::Cancel => WizardResult::Cancelled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wizard_navigation() {
+        let mut wizard = Wizard::new((), 3);
+        assert_eq!(wizard.navigate(Navigation::Back), WizardResult::Running);
+        assert_eq!(wizard.step, 0);
+        assert_eq!(wizard.navigate(Navigation::Next), WizardResult::Running);
+        assert_eq!(wizard.navigate(Navigation::Next), WizardResult::Running);
+        assert_eq!(wizard.step, 2);
+        assert_eq!(wizard.navigate(Navigation::Back), WizardResult::Running);
+        assert_eq!(wizard.step, 1);
+        assert_eq!(wizard.navigate(Navigation::Next), WizardResult::Running);
+        assert_eq!(wizard.navigate(Navigation::Next), WizardResult::Finished);
+        assert_eq!(wizard.navigate(Navigation::Cancel), WizardResult::Cancelled);
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Workspace trust: Commands declared in a project's `.edit/config.toml`, like linters,
+//! only run once the user has trusted that workspace. Until then, or if the user picks
+//! the restricted mode, only the commands from the user's own config run.
+//!
+//! Decisions are remembered in `trusted_workspaces` next to the user's config,
+//! one `trusted <path>` or `restricted <path>` line per workspace.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use edit::apperr;
+use edit::config::{self, Config};
+use edit::framebuffer::IndexedColor;
+use edit::helpers::*;
+use edit::tui::*;
+
+use crate::localization::*;
+use crate::state::*;
+
+/// Config sections that declare commands to be run.
+const COMMAND_SECTIONS: &[&str] = &["formatter", "linter"];
+
+#[derive(Default)]
+pub struct TrustStore {
+    path: Option<PathBuf>,
+    /// Remembered decisions: `true` for trusted, `false` for restricted.
+    entries: Vec<(PathBuf, bool)>,
+    /// Workspaces restricted by dismissing the prompt. Forgotten on exit.
+    session_restricted: Vec<PathBuf>,
+    /// Incremented by each decision. See [`TrustStore::generation()`].
+    generation: u32,
+}
+
+impl TrustStore {
+    /// Loads the remembered decisions. A missing or unreadable file results in an empty store.
+    pub fn load() -> Self {
+        let path = Config::config_dir().map(|dir| dir.join("trusted_workspaces"));
+        let text = path.as_ref().and_then(|p| fs::read_to_string(p).ok()).unwrap_or_default();
+        let mut store = Self::parse(&text);
+        store.path = path;
+        store
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut store = Self::default();
+        for line in text.lines() {
+            let (trusted, root) = match line.split_once(' ') {
+                Some(("trusted", root)) => (true, root),
+                Some(("restricted", root)) => (false, root),
+                _ => continue,
+            };
+            store.entries.push((PathBuf::from(root), trusted));
+        }
+        store
+    }
+
+    fn save(&self) -> apperr::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for (root, trusted) in &self.entries {
+            text.push_str(if *trusted { "trusted " } else { "restricted " });
+            text.push_str(&root.to_string_lossy());
+            text.push('\n');
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Changes with every decision, and with it what [`TrustStore::command_config()`] returns.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// The remembered decisions, as `(workspace, trusted)` pairs.
+    pub fn entries(&self) -> &[(PathBuf, bool)] {
+        &self.entries
+    }
+
+    /// Returns whether the workspace is trusted, or `None` if the user hasn't decided yet.
+    pub fn is_trusted(&self, root: &Path) -> Option<bool> {
+        if let Some((_, trusted)) = self.entries.iter().find(|(r, _)| r == root) {
+            return Some(*trusted);
+        }
+        self.session_restricted.iter().any(|r| r == root).then_some(false)
+    }
+
+    /// Records a decision. Unless `remember` is set, it only applies to restricting
+    /// the workspace until the editor exits.
+    pub fn decide(&mut self, root: &Path, trusted: bool, remember: bool) -> apperr::Result<()> {
+        self.generation = self.generation.wrapping_add(1);
+        if !remember {
+            if !trusted && !self.session_restricted.iter().any(|r| r == root) {
+                self.session_restricted.push(root.to_path_buf());
+            }
+            return Ok(());
+        }
+        self.entries.retain(|(r, _)| r != root);
+        self.entries.push((root.to_path_buf(), trusted));
+        self.save()
+    }
+
+    /// Forgets the decision for the workspace, so that the user gets asked again.
+    pub fn revoke(&mut self, root: &Path) -> apperr::Result<()> {
+        self.generation = self.generation.wrapping_add(1);
+        self.session_restricted.retain(|r| r != root);
+        self.entries.retain(|(r, _)| r != root);
+        self.save()
+    }
+
+    /// Returns the config to use for running commands on `path`: the user's config, plus the
+    /// project's if its workspace is trusted. The second return value is the workspace root
+    /// if it declares commands, but the user hasn't decided yet whether to trust it.
+    pub fn command_config(&self, path: &Path) -> (Config, Option<PathBuf>) {
+        let mut config = config::get().clone();
+        let Some((root, project)) = path.parent().and_then(Config::load_project) else {
+            return (config, None);
+        };
+        if !COMMAND_SECTIONS.iter().any(|s| !project.subsections(s).is_empty()) {
+            return (config, None);
+        }
+
+        match self.is_trusted(&root) {
+            Some(true) => {
+                config.merge(&project);
+                (config, None)
+            }
+            Some(false) => (config, None),
+            None => (config, Some(root)),
+        }
+    }
+}
+
+pub fn draw_dialog_trust_prompt(ctx: &mut Context, state: &mut State) {
+    let Some(root) = state.wants_trust_prompt.clone() else {
+        return;
+    };
+    let mut decision = None;
+
+    ctx.modal_begin("trust-prompt", loc(LocId::TrustDialogTitle));
+    ctx.attr_background_rgba(ctx.indexed(IndexedColor::Yellow));
+    ctx.attr_foreground_rgba(ctx.indexed(IndexedColor::Black));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            ctx.label("workspace", &root.to_string_lossy());
+            ctx.attr_overflow(Overflow::TruncateHead);
+            ctx.label("description", loc(LocId::TrustDialogDescription));
+
+            ctx.table_begin("choices");
+            ctx.inherit_focus();
+            ctx.attr_padding(Rect::three(1, 2, 0));
+            ctx.attr_position(Position::Center);
+            ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+            {
+                ctx.table_next_row();
+                ctx.inherit_focus();
+
+                if ctx.button("trust", loc(LocId::TrustDialogTrust), ButtonStyle::default()) {
+                    decision = Some((true, true));
+                }
+                if ctx.button(
+                    "restricted",
+                    loc(LocId::TrustDialogRestricted),
+                    ButtonStyle::default(),
+                ) {
+                    decision = Some((false, true));
+                }
+                ctx.inherit_focus();
+            }
+            ctx.table_end();
+        }
+        ctx.block_end();
+    }
+    if ctx.modal_end() {
+        // Dismissed? Ask again next time the editor runs.
+        decision = Some((false, false));
+    }
+
+    let Some((trusted, remember)) = decision else {
+        return;
+    };
+    state.wants_trust_prompt = None;
+    if let Err(err) = state.trust.decide(&root, trusted, remember) {
+        error_log_add(ctx, state, err);
+    }
+    if trusted && let Some(doc) = state.documents.active_mut() {
+        // Give the project's commands a chance to run right away.
+        doc.wants_lint = true;
+    }
+    ctx.needs_rerender();
+}
+
+pub fn draw_dialog_workspace_trust(ctx: &mut Context, state: &mut State) {
+    let mut revoke = None;
+
+    ctx.modal_begin("workspace-trust", loc(LocId::ViewWorkspaceTrust));
+    {
+        ctx.block_begin("content");
+        ctx.inherit_focus();
+        ctx.attr_padding(Rect::three(1, 2, 1));
+        {
+            if state.trust.entries().is_empty() {
+                ctx.label("empty", loc(LocId::WorkspaceTrustEmpty));
+            } else {
+                ctx.table_begin("workspaces");
+                ctx.inherit_focus();
+                ctx.table_set_cell_gap(Size { width: 2, height: 0 });
+                for (i, (root, trusted)) in state.trust.entries().iter().enumerate() {
+                    ctx.table_next_row();
+                    ctx.next_block_id_mixin(i as u64);
+                    ctx.label("path", &root.to_string_lossy());
+                    ctx.attr_overflow(Overflow::TruncateHead);
+                    ctx.label(
+                        "status",
+                        loc(if *trusted {
+                            LocId::WorkspaceTrustTrusted
+                        } else {
+                            LocId::WorkspaceTrustRestricted
+                        }),
+                    );
+                    if ctx.button(
+                        "revoke",
+                        loc(LocId::WorkspaceTrustRevoke),
+                        ButtonStyle::default(),
+                    ) {
+                        revoke = Some(root.clone());
+                    }
+                    if i == 0 {
+                        ctx.inherit_focus();
+                    }
+                }
+                ctx.table_end();
+            }
+
+            if ctx.button("ok", loc(LocId::Ok), ButtonStyle::default()) {
+                state.wants_workspace_trust = false;
+            }
+            ctx.attr_position(Position::Center);
+            if state.trust.entries().is_empty() {
+                ctx.inherit_focus();
+            }
+        }
+        ctx.block_end();
+    }
+    if ctx.modal_end() {
+        state.wants_workspace_trust = false;
+    }
+
+    if let Some(root) = revoke {
+        if let Err(err) = state.trust.revoke(&root) {
+            error_log_add(ctx, state, err);
+        }
+        ctx.needs_rerender();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decisions() {
+        let mut store = TrustStore::parse("trusted /a\nrestricted /b\ngarbage\n");
+        assert_eq!(store.is_trusted(Path::new("/a")), Some(true));
+        assert_eq!(store.is_trusted(Path::new("/b")), Some(false));
+        assert_eq!(store.is_trusted(Path::new("/c")), None);
+
+        // Without a path, nothing gets written.
+        store.decide(Path::new("/c"), false, false).unwrap();
+        assert_eq!(store.is_trusted(Path::new("/c")), Some(false));
+        assert_eq!(store.entries().len(), 2);
+
+        store.decide(Path::new("/b"), true, true).unwrap();
+        assert_eq!(store.is_trusted(Path::new("/b")), Some(true));
+        store.revoke(Path::new("/a")).unwrap();
+        assert_eq!(store.is_trusted(Path::new("/a")), None);
+    }
+}
@@ -1,11 +1,12 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT License.
 
+use std::fs::File;
 use std::ops::Range;
 use std::ptr::{self, NonNull};
 use std::slice;
 
-use crate::document::{ReadableDocument, WriteableDocument};
+use crate::document::{EditDelta, ReadableDocument, WriteableDocument};
 use crate::helpers::*;
 use crate::{apperr, sys};
 
@@ -25,13 +26,17 @@ const SMALL_GAP_CHUNK: usize = 16;
 enum BackingBuffer {
     VirtualMemory(NonNull<u8>, usize),
     Vec(Vec<u8>),
+    /// A read-only file mapping. See [`GapBuffer::map_file`].
+    Mapped(NonNull<u8>, usize),
 }
 
 impl Drop for BackingBuffer {
     fn drop(&mut self) {
         unsafe {
-            if let Self::VirtualMemory(ptr, reserve) = *self {
-                sys::virtual_release(ptr, reserve);
+            match *self {
+                Self::VirtualMemory(ptr, reserve) => sys::virtual_release(ptr, reserve),
+                Self::Mapped(ptr, len) => sys::file_unmap(ptr, len),
+                Self::Vec(_) => {}
             }
         }
     }
@@ -57,22 +62,24 @@ pub struct GapBuffer {
     /// Increments every time the buffer is modified.
     generation: u32,
     /// If `Vec(..)`, the buffer is optimized for small amounts of text
-    /// and uses the standard heap. Otherwise, it uses virtual memory.
+    /// and uses the standard heap. If `Mapped(..)`, it's a read-only view of a file.
+    /// Otherwise, it uses virtual memory.
     buffer: BackingBuffer,
 }
 
 impl GapBuffer {
     pub fn new(small: bool) -> apperr::Result<Self> {
-        let reserve;
+        Self::with_reserve(if small { SMALL_CAPACITY } else { LARGE_CAPACITY })
+    }
+
+    fn with_reserve(reserve: usize) -> apperr::Result<Self> {
         let buffer;
         let text;
 
-        if small {
-            reserve = SMALL_CAPACITY;
+        if reserve <= SMALL_CAPACITY {
             text = NonNull::dangling();
             buffer = BackingBuffer::Vec(Vec::new());
         } else {
-            reserve = LARGE_CAPACITY;
             text = unsafe { sys::virtual_reserve(reserve)? };
             buffer = BackingBuffer::VirtualMemory(text, reserve);
         }
@@ -89,6 +96,57 @@ impl GapBuffer {
         })
     }
 
+    /// Maps the first `len` bytes of `file` into memory, skipping the first `skip` of them
+    /// (for instance a BOM). Nothing is read until it's needed, which makes this a lot faster
+    /// than reading huge files. The first modification copies the contents into a regular
+    /// buffer. If the file gets truncated until then, the part that's gone reads as zeros.
+    pub fn map_file(file: &File, len: usize, skip: usize) -> apperr::Result<Self> {
+        let base = unsafe { sys::file_map(file, len)? };
+        let skip = skip.min(len);
+        let text_length = len - skip;
+
+        Ok(Self {
+            text: unsafe { base.add(skip) },
+            reserve: text_length,
+            commit: text_length,
+            text_length,
+            gap_off: text_length,
+            gap_len: 0,
+            generation: 0,
+            buffer: BackingBuffer::Mapped(base, len),
+        })
+    }
+
+    /// Whether the contents are still those of a file mapping. See [`GapBuffer::map_file`].
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.buffer, BackingBuffer::Mapped(..))
+    }
+
+    /// Turns a file mapping into a regular buffer by copying its contents.
+    /// Does nothing for regular buffers.
+    pub fn unmap(&mut self) -> apperr::Result<()> {
+        if !self.is_mapped() {
+            return Ok(());
+        }
+
+        // Files beyond the usual capacity get twice their size, leaving room for edits.
+        let reserve = LARGE_CAPACITY.max(self.text_length.saturating_mul(2));
+        let mut buffer = Self::with_reserve(reserve)?;
+        self.copy_into_gap_buffer(&mut buffer);
+        buffer.generation = self.generation;
+        *self = buffer;
+        Ok(())
+    }
+
+    fn copy_into_gap_buffer(&self, dst: &mut Self) {
+        let mut off = 0;
+        while off < self.text_length {
+            let chunk = self.read_forward(off);
+            dst.replace(off..off, chunk);
+            off += chunk.len();
+        }
+    }
+
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.text_length
@@ -104,6 +162,11 @@ impl GapBuffer {
 
     /// WARNING: The returned slice must not necessarily be the same length as `len` (due to OOM).
     pub fn allocate_gap(&mut self, off: usize, len: usize, delete: usize) -> &mut [u8] {
+        // The mapping is read-only. Copy it on the first write.
+        if self.unmap().is_err() {
+            return &mut [];
+        }
+
         // Sanitize parameters
         let off = off.min(self.text_length);
         let delete = delete.min(self.text_length - off);
@@ -203,6 +266,7 @@ impl GapBuffer {
                     v.resize(bytes_new, 0);
                     self.text = unsafe { NonNull::new_unchecked(v.as_mut_ptr()) };
                 }
+                BackingBuffer::Mapped(..) => unreachable!(),
             }
 
             self.commit = bytes_new;
@@ -336,6 +400,25 @@ impl GapBuffer {
     }
 }
 
+impl WriteableDocument for GapBuffer {
+    fn replace(&mut self, range: Range<usize>, replacement: &[u8]) {
+        GapBuffer::replace(self, range, replacement);
+    }
+
+    fn replace_ext(&mut self, range: Range<usize>, replacement: &[u8]) -> EditDelta<'static> {
+        let end = range.end.min(self.text_length);
+        let beg = range.start.min(end);
+        let mut old_text = Vec::new();
+        self.extract_raw(beg, end, &mut old_text, 0);
+        GapBuffer::replace(self, beg..end, replacement);
+        EditDelta {
+            removed_range: beg..end,
+            old_text: old_text.into(),
+            new_text: replacement.to_vec().into(),
+        }
+    }
+}
+
 impl ReadableDocument for GapBuffer {
     fn read_forward(&self, off: usize) -> &[u8] {
         let off = off.min(self.text_length);
@@ -375,3 +458,57 @@ impl ReadableDocument for GapBuffer {
         unsafe { slice::from_raw_parts(self.text.add(beg).as_ptr(), len) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    #[test]
+    fn test_map_file_copy_on_write() {
+        let path = std::env::temp_dir().join(format!("edit-gap-buffer-{}", std::process::id()));
+        File::create(&path).unwrap().write_all(b"\xEF\xBB\xBFhello world").unwrap();
+        let file = File::open(&path).unwrap();
+
+        let mut buffer = GapBuffer::map_file(&file, 14, 3).unwrap();
+        assert!(buffer.is_mapped());
+        assert_eq!(buffer.read_forward(0), b"hello world");
+
+        let delta = buffer.replace_ext(0..5, b"goodbye");
+        assert_eq!((&*delta.old_text, delta.inserted_len()), (&b"hello"[..], 7));
+        assert!(!buffer.is_mapped());
+
+        let mut text = String::new();
+        buffer.copy_into(&mut text);
+        assert_eq!(text, "goodbye world");
+
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_map_file_truncated() {
+        let path = std::env::temp_dir().join(format!("edit-gap-truncated-{}", std::process::id()));
+        let len = 256 * KIBI;
+        File::create(&path).unwrap().write_all(&vec![b'a'; len]).unwrap();
+        let file = File::options().read(true).write(true).open(&path).unwrap();
+
+        let buffer = GapBuffer::map_file(&file, len, 0).unwrap();
+        file.set_len(0).unwrap();
+
+        // Reading the part that's gone must not crash.
+        let mut text = Vec::new();
+        let mut off = 0;
+        while off < len {
+            let chunk = buffer.read_forward(off);
+            text.extend_from_slice(chunk);
+            off += chunk.len();
+        }
+        assert_eq!(text.len(), len);
+
+        drop(buffer);
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
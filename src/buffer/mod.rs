@@ -20,40 +20,58 @@
 //! The solution to the former is to keep line caches, which further complicates the architecture.
 //! There's no solution for the latter. However, there's a chance that the performance will still be sufficient.
 
+// Much of what's here only serves `TextBuffer::render()`, which needs the terminal UI.
+#![cfg_attr(not(feature = "tui"), allow(dead_code, unused_imports))]
+
 mod gap_buffer;
 mod navigation;
 
 use std::borrow::Cow;
 use std::cell::UnsafeCell;
+use std::cmp::Reverse;
 use std::collections::LinkedList;
 use std::fmt::Write as _;
 use std::fs::File;
-use std::io::{Read as _, Write as _};
+use std::io::{self, Read as _, Seek as _};
 use std::mem::{self, MaybeUninit};
 use std::ops::Range;
 use std::rc::Rc;
 use std::str;
 
-use gap_buffer::GapBuffer;
+pub(crate) use gap_buffer::GapBuffer;
+pub(crate) use navigation::{word_backward, word_forward};
 
+use crate::ansi;
 use crate::arena::{ArenaString, scratch_arena};
+use crate::case::Case;
 use crate::cell::SemiRefCell;
-use crate::document::{ReadableDocument, WriteableDocument};
+use crate::config::{Density, RenderWhitespace};
+use crate::diagnostics::{self, Diagnostic, Diagnostics, Severity};
+use crate::document::{IndentStyle, ReadableDocument, WriteableDocument};
+#[cfg(feature = "tui")]
 use crate::framebuffer::{Framebuffer, IndexedColor};
+use crate::hash::{hash, hash_str};
 use crate::helpers::*;
+#[cfg(feature = "tui")]
 use crate::oklab::oklab_blend;
 use crate::simd::memchr2;
-use crate::syntax::{SmartIndenter, FileType};
+use crate::stack_trace;
+use crate::surround::Surrounding;
+use crate::syntax::{FileType, OverlayKind, OverlaySpan, SmartIndenter, matching_tag};
 use crate::unicode::{self, Cursor, MeasurementConfig};
-use crate::{apperr, icu};
+use crate::{apperr, diff, hex, icu};
 
 /// The margin template is used for line numbers.
 /// The max. line number we should ever expect is probably 64-bit,
-/// and so this template fits 19 digits, followed by " │ ".
+/// and so this template fits 19 digits plus padding, followed by " │ ".
 const MARGIN_TEMPLATE: &str = "                    │ ";
 /// Just a bunch of whitespace you can use for turning tabs into spaces.
 /// Happens to reuse MARGIN_TEMPLATE, because it has sufficient whitespace.
 const TAB_WHITESPACE: &str = MARGIN_TEMPLATE;
+/// The conventional max. length of the summary line of a git commit message.
+const COMMIT_SUMMARY_LIMIT: CoordType = 50;
+/// The conventional max. length of the lines in the body of a git commit message.
+const COMMIT_BODY_LIMIT: CoordType = 72;
 
 /// Stores statistics about the whole document.
 #[derive(Copy, Clone)]
@@ -72,6 +90,16 @@ struct TextBufferSelection {
     end: Point,
 }
 
+impl TextBufferSelection {
+    /// The logical lines it touches.
+    /// A selection that ends at the start of a line doesn't include that line.
+    fn lines(&self) -> Range<CoordType> {
+        let [beg, end] = minmax(self.beg, self.end);
+        let last = if end.x == 0 && end.y > beg.y { end.y - 1 } else { end.y };
+        beg.y..last + 1
+    }
+}
+
 /// In order to group actions into a single undo step,
 /// we need to know the type of action that was performed.
 /// This stores the action type.
@@ -99,6 +127,9 @@ struct HistoryEntry {
     deleted: Vec<u8>,
     /// Text that was added to the buffer.
     added: Vec<u8>,
+    /// Whether it's undone and redone together with the entry before it.
+    /// See [`TextBuffer::for_each_cursor()`].
+    joined: bool,
 }
 
 /// Caches an ICU search operation.
@@ -121,10 +152,45 @@ struct ActiveSearch {
     selection_generation: u32,
     /// Stores the text buffer offset in between searches.
     next_search_offset: usize,
+    /// The offsets of the hit that was selected, while `selection_generation` is current.
+    /// Unlike the selection, this includes empty hits, like those of `^`.
+    hit: Option<Range<usize>>,
     /// If we know there were no hits, we can skip searching.
     no_matches: bool,
 }
 
+/// Tracks the search term whose hits get highlighted during [`TextBuffer::render()`].
+struct SearchHighlight {
+    /// The pattern that was passed to [`TextBuffer::set_search_highlight()`].
+    pattern: String,
+    /// The options that were passed to [`TextBuffer::set_search_highlight()`].
+    options: SearchOptions,
+    /// `None` if the pattern is invalid (e.g. a broken regex).
+    search: Option<ActiveSearch>,
+}
+
+/// The state of [`TextBuffer::set_ansi_view()`].
+struct AnsiView {
+    /// The contents including the escape sequences. They're written when saving.
+    original: Vec<u8>,
+    /// See [`ansi::Stripped::spans`].
+    spans: Vec<(Range<usize>, ansi::Style)>,
+    /// Whether the buffer was read-only before the view was enabled.
+    read_only: bool,
+}
+
+/// The state of [`TextBuffer::set_hex_view()`].
+struct HexView {
+    /// Whether overtype was on before the view was enabled.
+    overtype: bool,
+}
+
+/// A labeled occurrence of the needle passed to [`TextBuffer::set_jump_needle()`].
+struct JumpTarget {
+    label: String,
+    offset: usize,
+}
+
 /// Options for a search operation.
 #[derive(Default, Clone, Copy, Eq, PartialEq)]
 pub struct SearchOptions {
@@ -136,6 +202,17 @@ pub struct SearchOptions {
     pub use_regex: bool,
 }
 
+/// What [`TextBuffer::find_and_replace_step()`] does with the selected hit.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ReplaceStep {
+    /// Leaves it as is.
+    Skip,
+    /// Replaces it.
+    Replace,
+    /// Replaces it and all the following ones as a single undo step.
+    ReplaceAll,
+}
+
 /// Caches the start and length of the active edit line for a single edit.
 /// This helps us avoid having to remeasure the buffer after an edit.
 struct ActiveEditLineInfo {
@@ -150,15 +227,27 @@ struct ActiveEditLineInfo {
 }
 
 /// Char- or word-wise navigation? Your choice.
+#[derive(Copy, Clone)]
 pub enum CursorMovement {
     Grapheme,
     Word,
+    /// Stops within words at `camelCase`, `snake_case` and digit boundaries.
+    SubWord,
 }
 
 /// The result of a call to [`TextBuffer::render()`].
 pub struct RenderResult {
     /// The maximum visual X position we encountered during rendering.
     pub visual_pos_x_max: CoordType,
+    /// The width of the widest line in the viewport, including the parts scrolled out of view.
+    /// Only computed if word wrap is disabled and the caller asked for it.
+    pub line_width_max: CoordType,
+    /// Search hits and selected ranges within the viewport, sorted by line.
+    /// See [`TextBuffer::set_search_highlight()`].
+    pub overlays: Vec<OverlaySpan>,
+    /// The rows that word wrap broke off before the end of their line, and how many
+    /// columns of text they have, sorted. The next row continues the same line.
+    pub wrapped_rows: Vec<(CoordType, CoordType)>,
 }
 
 /// A [`TextBuffer`] with inner mutability.
@@ -177,7 +266,11 @@ pub struct TextBuffer {
     undo_stack: LinkedList<SemiRefCell<HistoryEntry>>,
     redo_stack: LinkedList<SemiRefCell<HistoryEntry>>,
     last_history_type: HistoryType,
+    undo_limit: usize,
     last_save_generation: u32,
+    /// The hash of the contents as of the last save or read, see
+    /// [`TextBuffer::is_modified_since_save()`]. `None` if it's unknown.
+    saved_hash: Option<u64>,
 
     active_edit_line_info: Option<ActiveEditLineInfo>,
     active_edit_depth: i32,
@@ -192,21 +285,59 @@ pub struct TextBuffer {
     cursor_for_rendering: Option<Cursor>,
     selection: Option<TextBufferSelection>,
     selection_generation: u32,
+    /// The cursors besides `cursor`, see [`TextBuffer::for_each_cursor()`].
+    /// `end` is where each one is and `beg` where its selection starts, if it has one.
+    extra_cursors: Vec<TextBufferSelection>,
+    /// While [`TextBuffer::for_each_cursor()`] runs, the number of undo entries it made so far.
+    each_cursor: Option<usize>,
+    /// The block selection's corners in visual coordinates, where it started and where
+    /// it's being extended to, and the `selection_generation` it's valid for.
+    block_selection: Option<(Point, Point, u32)>,
+    /// Where the closers are that [`TextBuffer::set_auto_pairs()`] inserted and that
+    /// typing the same character again steps over. Offsets, kept up to date by each edit.
+    auto_closers: Vec<usize>,
     search: Option<UnsafeCell<ActiveSearch>>,
+    search_highlight: Option<SearchHighlight>,
+    jump_needle: Option<String>,
+    jump_targets: Vec<JumpTarget>,
+    // The buffer generation and cursor offset `tag_pair` was computed for.
+    tag_pair_key: Option<(u32, usize)>,
+    tag_pair: Option<[Range<usize>; 2]>,
+    /// The folded regions as the logical lines they hide. The line before each one,
+    /// its header, stays visible. Sorted and not overlapping.
+    folds: Vec<Range<CoordType>>,
+    /// The visual rows hidden by each of `folds`, see [`TextBuffer::fold_rows_update()`].
+    fold_rows: Vec<Range<CoordType>>,
+    // The buffer generation and file type `fold_regions` was computed for.
+    fold_regions_key: Option<(u32, FileType)>,
+    fold_regions: Vec<Range<CoordType>>,
 
     width: CoordType,
     margin_width: CoordType,
     margin_enabled: bool,
+    density: Density,
     word_wrap_column: CoordType,
     word_wrap_enabled: bool,
+    /// The column word wrap breaks lines at, if it's narrower than the text area, or 0.
+    word_wrap_limit: CoordType,
     tab_size: CoordType,
     indent_with_tabs: bool,
     line_highlight_enabled: bool,
+    scroll_indicators_enabled: bool,
     ruler: CoordType,
+    commit_message_mode: bool,
+    diagnostics: Diagnostics,
+    whitespace_lint: bool,
+    render_whitespace: RenderWhitespace,
+    subword_navigation: bool,
+    auto_pairs: bool,
     encoding: &'static str,
     newlines_are_crlf: bool,
     insert_final_newline: bool,
     overtype: bool,
+    read_only: bool,
+    ansi_view: Option<AnsiView>,
+    hex_view: Option<HexView>,
 
     wants_cursor_visibility: bool,
     
@@ -214,6 +345,7 @@ pub struct TextBuffer {
     smart_indenter: SmartIndenter,
     current_file_type: FileType,
     smart_indent_enabled: bool,
+    theme: Option<String>,
 }
 
 impl TextBuffer {
@@ -233,7 +365,9 @@ impl TextBuffer {
             undo_stack: LinkedList::new(),
             redo_stack: LinkedList::new(),
             last_history_type: HistoryType::Other,
+            undo_limit: DEFAULT_UNDO_LIMIT,
             last_save_generation: 0,
+            saved_hash: None,
 
             active_edit_line_info: None,
             active_edit_depth: 0,
@@ -244,21 +378,46 @@ impl TextBuffer {
             cursor_for_rendering: None,
             selection: None,
             selection_generation: 0,
+            extra_cursors: Vec::new(),
+            each_cursor: None,
+            block_selection: None,
+            auto_closers: Vec::new(),
             search: None,
+            search_highlight: None,
+            jump_needle: None,
+            jump_targets: Vec::new(),
+            tag_pair_key: None,
+            tag_pair: None,
+            folds: Vec::new(),
+            fold_rows: Vec::new(),
+            fold_regions_key: None,
+            fold_regions: Vec::new(),
 
             width: 0,
             margin_width: 0,
             margin_enabled: false,
+            density: Density::Normal,
             word_wrap_column: 0,
             word_wrap_enabled: false,
+            word_wrap_limit: 0,
             tab_size: 4,
             indent_with_tabs: false,
             line_highlight_enabled: false,
+            scroll_indicators_enabled: false,
             ruler: 0,
+            commit_message_mode: false,
+            diagnostics: Diagnostics::default(),
+            whitespace_lint: false,
+            render_whitespace: RenderWhitespace::None,
+            subword_navigation: false,
+            auto_pairs: false,
             encoding: "UTF-8",
             newlines_are_crlf: cfg!(windows), // Windows users want CRLF
             insert_final_newline: false,
             overtype: false,
+            read_only: false,
+            ansi_view: None,
+            hex_view: None,
 
             wants_cursor_visibility: false,
             
@@ -266,6 +425,7 @@ impl TextBuffer {
             smart_indenter: SmartIndenter::new(),
             current_file_type: FileType::Plain,
             smart_indent_enabled: true,
+            theme: None,
         })
     }
 
@@ -281,9 +441,9 @@ impl TextBuffer {
     }
 
     /// Number of visual lines in the document,
-    /// that is, the number of lines after layout.
+    /// that is, the number of lines after layout, without those that are folded away.
     pub fn visual_line_count(&self) -> CoordType {
-        self.stats.visual_lines
+        self.stats.visual_lines - self.fold_rows.iter().map(|r| r.end - r.start).sum::<CoordType>()
     }
 
     /// Does the buffer need to be saved?
@@ -298,13 +458,56 @@ impl TextBuffer {
         self.buffer.generation()
     }
 
-    /// Force the buffer to be dirty.
+    /// Force the buffer to be dirty. The next save writes the file even if the contents
+    /// are the same as before, see [`TextBuffer::is_modified_since_save()`].
     pub fn mark_as_dirty(&mut self) {
         self.last_save_generation = self.buffer.generation().wrapping_sub(1);
+        self.saved_hash = None;
     }
 
-    fn mark_as_clean(&mut self) {
+    /// Marks the contents as saved, e.g. after they were written elsewhere.
+    pub fn mark_as_clean(&mut self) {
         self.last_save_generation = self.buffer.generation();
+        self.saved_hash = self.content_hash();
+    }
+
+    /// Whether the contents differ from the ones last saved or read. Unlike
+    /// [`TextBuffer::is_dirty()`], which tracks edits, this is false after edits that
+    /// were undone or typed back in by hand, so that saving again can be skipped.
+    pub fn is_modified_since_save(&self) -> bool {
+        self.is_dirty() && self.saved_hash.is_none_or(|saved| self.content_hash() != Some(saved))
+    }
+
+    /// Hashes the contents along with the encoding they're written in. Large files that
+    /// are still mapped aren't hashed, and neither are the ANSI and hex views.
+    fn content_hash(&self) -> Option<u64> {
+        if self.is_mapped() || self.ansi_view.is_some() || self.hex_view.is_some() {
+            return None;
+        }
+
+        // Where the gap splits the text mustn't matter, so it's hashed in blocks of its own.
+        let mut block = [0u8; 4096];
+        let mut filled = 0;
+        let mut h = hash_str(0, self.encoding);
+        let mut off = 0;
+        loop {
+            let mut chunk = self.read_forward(off);
+            if chunk.is_empty() {
+                break;
+            }
+            off += chunk.len();
+            while !chunk.is_empty() {
+                let n = chunk.len().min(block.len() - filled);
+                block[filled..filled + n].copy_from_slice(&chunk[..n]);
+                filled += n;
+                chunk = &chunk[n..];
+                if filled == block.len() {
+                    h = hash(h, &block);
+                    filled = 0;
+                }
+            }
+        }
+        Some(hash(h, &block[..filled]))
     }
 
     /// The encoding used during reading/writing. "UTF-8" is the default.
@@ -320,6 +523,23 @@ impl TextBuffer {
         }
     }
 
+    /// Whether the file starts with a byte order mark when saved.
+    /// The UTF-16, UTF-32 and GB18030 encodings always get one.
+    pub fn has_bom(&self) -> bool {
+        self.encoding != "UTF-8" && self.encoding.starts_with("UTF")
+            || self.encoding == "GB18030"
+    }
+
+    /// Adds or removes the byte order mark of a UTF-8 document.
+    /// Returns false for other encodings, where it can't be changed.
+    pub fn set_bom(&mut self, bom: bool) -> bool {
+        if !self.encoding.starts_with("UTF-8") {
+            return false;
+        }
+        self.set_encoding(if bom { "UTF-8 BOM" } else { "UTF-8" });
+        true
+    }
+
     /// The newline type used in the document. LF or CRLF.
     pub fn is_crlf(&self) -> bool {
         self.newlines_are_crlf
@@ -329,6 +549,10 @@ impl TextBuffer {
     ///
     /// NOTE: Cannot be undone.
     pub fn normalize_newlines(&mut self, crlf: bool) {
+        if self.read_only {
+            return;
+        }
+
         let newline: &[u8] = if crlf { b"\r\n" } else { b"\n" };
         let mut off = 0;
 
@@ -410,16 +634,131 @@ impl TextBuffer {
         self.overtype = overtype;
     }
 
+    /// Returns true if edits are blocked. See [`TextBuffer::set_read_only()`].
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Makes all editing functions (writing, deleting, undo, etc.) do nothing.
+    /// Loading new contents via [`TextBuffer::read_file()`] and friends still works.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        // The ANSI view stays read-only and applies this once it's turned off.
+        match &mut self.ansi_view {
+            Some(view) => view.read_only = read_only,
+            None => self.read_only = read_only,
+        }
+    }
+
+    /// Returns true if escape sequences are shown as colors. See [`TextBuffer::set_ansi_view()`].
+    pub fn is_ansi_view(&self) -> bool {
+        self.ansi_view.is_some()
+    }
+
+    /// Shows the text with its ANSI escape sequences interpreted as colors, like `less -R`.
+    ///
+    /// The buffer then contains the text without the escape sequences and is read-only,
+    /// while saving writes the original contents. Reading a file turns the view off.
+    pub fn set_ansi_view(&mut self, enabled: bool) {
+        if enabled == self.ansi_view.is_some() {
+            return;
+        }
+
+        let dirty = self.is_dirty();
+        let y = self.cursor.logical_pos.y;
+
+        if enabled {
+            let original = self.read_all();
+            let stripped = ansi::strip(&String::from_utf8_lossy(&original));
+            self.set_bytes(stripped.text.as_bytes());
+            self.ansi_view =
+                Some(AnsiView { original, spans: stripped.spans, read_only: self.read_only });
+            self.read_only = true;
+        } else if let Some(view) = self.ansi_view.take() {
+            self.set_bytes(&view.original);
+            self.read_only = view.read_only;
+        }
+
+        if dirty {
+            self.mark_as_dirty();
+        }
+        self.cursor_move_to_logical(Point { x: 0, y });
+    }
+
+    /// Returns true if the contents are shown as a hex dump. See [`TextBuffer::set_hex_view()`].
+    pub fn is_hex_view(&self) -> bool {
+        self.hex_view.is_some()
+    }
+
+    /// Shows the contents as an editable hex dump, see [`hex`]. Binary files are shown
+    /// like that when they're read.
+    ///
+    /// The buffer then contains the dump, in overtype mode, and saving writes the bytes
+    /// in its hex columns. Turning the view off fails with [`apperr::APP_HEX_INVALID`]
+    /// if they're broken, and moves the cursor to the first broken line.
+    pub fn set_hex_view(&mut self, enabled: bool) -> apperr::Result<()> {
+        if enabled == self.hex_view.is_some() {
+            return Ok(());
+        }
+
+        let dirty = self.is_dirty();
+        let y = self.cursor.logical_pos.y;
+
+        if enabled {
+            self.set_ansi_view(false);
+            self.set_bytes(hex::dump(&self.read_all()).as_bytes());
+            self.hex_view = Some(HexView { overtype: self.overtype });
+            self.overtype = true;
+        } else {
+            let data = self.parse_hex_view()?;
+            self.set_bytes(&data);
+            if let Some(view) = self.hex_view.take() {
+                self.overtype = view.overtype;
+            }
+        }
+
+        if dirty {
+            self.mark_as_dirty();
+        }
+        self.cursor_move_to_logical(Point { x: 0, y });
+        Ok(())
+    }
+
+    /// Returns the bytes of the hex dump in the buffer. See [`TextBuffer::set_hex_view()`].
+    fn parse_hex_view(&mut self) -> apperr::Result<Vec<u8>> {
+        hex::parse(&self.read_all()).map_err(|line| {
+            self.cursor_move_to_logical(Point { x: 0, y: line as CoordType });
+            apperr::APP_HEX_INVALID
+        })
+    }
+
+    /// Copies the entire contents of the buffer.
+    fn read_all(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.text_length());
+        loop {
+            let chunk = self.read_forward(bytes.len());
+            if chunk.is_empty() {
+                break;
+            }
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
     /// Gets the logical cursor position, that is,
     /// the position in lines and graphemes per line.
     pub fn cursor_logical_pos(&self) -> Point {
         self.cursor.logical_pos
     }
 
+    /// Gets the byte offset of the cursor in the document.
+    pub fn cursor_offset(&self) -> usize {
+        self.cursor.offset
+    }
+
     /// Gets the visual cursor position, that is,
     /// the position in laid out rows and columns.
     pub fn cursor_visual_pos(&self) -> Point {
-        self.cursor.visual_pos
+        Point { x: self.cursor.visual_pos.x, y: self.row_to_screen(self.cursor.visual_pos.y) }
     }
 
     /// Gets the width of the left margin.
@@ -438,6 +777,22 @@ impl TextBuffer {
         }
     }
 
+    /// Sets how much padding the margin gets around the line numbers.
+    pub fn set_density(&mut self, density: Density) -> bool {
+        if self.density == density {
+            false
+        } else {
+            self.density = density;
+            self.reflow(true);
+            true
+        }
+    }
+
+    /// The separator between the line numbers and the text.
+    fn margin_separator(&self) -> &'static str {
+        if self.density == Density::Compact { "│" } else { " │ " }
+    }
+
     /// Gets the width of the text contents for layout.
     pub fn text_width(&self) -> CoordType {
         self.width - self.margin_width
@@ -478,6 +833,25 @@ impl TextBuffer {
         }
     }
 
+    /// The column word wrap breaks lines at, or 0 for the width of the viewport.
+    pub fn word_wrap_limit(&self) -> CoordType {
+        self.word_wrap_limit
+    }
+
+    /// Makes word wrap break lines at `column`, unless the viewport is narrower.
+    /// 0 breaks them at the edge of the viewport.
+    ///
+    /// NOTE: Like with [`TextBuffer::set_word_wrap()`], the tui code is expected to call
+    /// `set_width()` after this.
+    pub fn set_word_wrap_limit(&mut self, column: CoordType) {
+        let column = column.max(0);
+        if self.word_wrap_limit != column {
+            self.word_wrap_limit = column;
+            self.width = 0; // Force a reflow.
+            self.make_cursor_visible();
+        }
+    }
+
     /// Set the width available for layout.
     ///
     /// Ideally this would be a pure UI concern, but the text buffer needs this
@@ -510,6 +884,11 @@ impl TextBuffer {
         }
     }
 
+    /// Returns the indentation style, as detected when the file was loaded or set by the user.
+    pub fn indent_style(&self) -> IndentStyle {
+        IndentStyle { use_tabs: self.indent_with_tabs, width: self.tab_size }
+    }
+
     /// Returns whether tabs are used for indentation.
     pub fn indent_with_tabs(&self) -> bool {
         self.indent_with_tabs
@@ -530,6 +909,12 @@ impl TextBuffer {
         self.smart_indent_enabled = enabled;
     }
 
+    /// Sets how many edits can be undone, [`DEFAULT_UNDO_LIMIT`] by default.
+    /// Lowering it drops the oldest ones on the next edit.
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.undo_limit = limit;
+    }
+
     /// Returns the current file type for smart indentation.
     pub fn current_file_type(&self) -> FileType {
         self.current_file_type
@@ -540,30 +925,109 @@ impl TextBuffer {
         self.current_file_type = file_type;
     }
 
+    /// The syntax highlighting theme of this buffer, or `None` for the default one.
+    pub fn theme(&self) -> Option<&str> {
+        self.theme.as_deref()
+    }
+
+    /// Sets the syntax highlighting theme, which gets applied whenever the buffer is rendered.
+    /// Unknown theme names fall back to the default theme.
+    pub fn set_theme(&mut self, theme: Option<String>) {
+        self.theme = theme;
+    }
+
     /// Sets whether the line the cursor is on should be highlighted.
     pub fn set_line_highlight_enabled(&mut self, enabled: bool) {
         self.line_highlight_enabled = enabled;
     }
 
-    /// Sets a ruler column, e.g. 80.
+    /// Sets whether lines that continue past the left or right edge of the viewport
+    /// get a "…" at that edge. With word wrap, rows that continue on the next one get
+    /// a "↩" past their end instead, which takes up the last column of the viewport.
+    pub fn set_scroll_indicators_enabled(&mut self, enabled: bool) {
+        if self.scroll_indicators_enabled != enabled {
+            self.scroll_indicators_enabled = enabled;
+            self.reflow(true);
+        }
+    }
+
+    /// Sets a ruler column, e.g. 80, or 0 to disable it.
+    /// Characters past the ruler get highlighted.
     pub fn set_ruler(&mut self, column: CoordType) {
         self.ruler = column;
     }
 
+    /// Enables the helpers for writing git commit messages: The summary line gets a guide
+    /// at column 50 and the body one at column 72, a non-blank 2nd line is flagged,
+    /// and `#` comments are dimmed.
+    pub fn set_commit_message_mode(&mut self, enabled: bool) {
+        self.commit_message_mode = enabled;
+    }
+
+    /// The diagnostics shown in the margin, e.g. from a linter.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Replaces the diagnostics of the given source. See [`Diagnostics::set()`].
+    pub fn set_diagnostics(&mut self, source: &str, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics.set(source, diagnostics);
+    }
+
+    /// Highlights trailing whitespace and indentation that doesn't match
+    /// [`TextBuffer::indent_style()`]. See [`diagnostics::whitespace_issues()`].
+    pub fn set_whitespace_lint(&mut self, enabled: bool) {
+        self.whitespace_lint = enabled;
+    }
+
+    /// Which whitespace is drawn visibly, see [`TextBuffer::set_render_whitespace()`].
+    pub fn render_whitespace(&self) -> RenderWhitespace {
+        self.render_whitespace
+    }
+
+    /// Draws spaces as `·`, tabs as `→` and non-breaking spaces as `⍽`, in a dimmed color.
+    /// [`RenderWhitespace::All`] also ends lines with a `¬`, or a `¤` for CRLF.
+    pub fn set_render_whitespace(&mut self, mode: RenderWhitespace) {
+        self.render_whitespace = mode;
+    }
+
+    /// Makes [`CursorMovement::Word`] stop at sub-word boundaries, like [`CursorMovement::SubWord`].
+    pub fn set_subword_navigation(&mut self, enabled: bool) {
+        self.subword_navigation = enabled;
+    }
+
+    /// Typing an opening bracket or quote of [`FileType::auto_pairs()`] also inserts
+    /// the closing one, or wraps the selection in both.
+    pub fn set_auto_pairs(&mut self, enabled: bool) {
+        self.auto_pairs = enabled;
+        self.auto_closers.clear();
+    }
+
     fn reflow(&mut self, force: bool) {
         // +1 onto logical_lines, because line numbers are 1-based.
         // +1 onto log10, because we want the digit width and not the actual log10.
         // +3 onto log10, because we append " | " to the line numbers to form the margin.
+        // Compact margins only append "|" and comfortable ones also prepend a space.
         self.margin_width = if self.margin_enabled {
-            self.stats.logical_lines.ilog10() as CoordType + 4
+            let padding = match self.density {
+                Density::Compact => 1,
+                Density::Normal => 3,
+                Density::Comfortable => 4,
+            };
+            self.stats.logical_lines.ilog10() as CoordType + 1 + padding
         } else {
             0
         };
 
         let text_width = self.text_width();
+        // The scroll indicators need the last column for the "↩" of rows that wrap.
+        let mut wrap_width = text_width - self.scroll_indicators_enabled as CoordType;
+        if self.word_wrap_limit > 0 {
+            wrap_width = wrap_width.min(self.word_wrap_limit);
+        }
         // 2 columns are required, because otherwise wide glyphs wouldn't ever fit.
         let word_wrap_column =
-            if self.word_wrap_enabled && text_width >= 2 { text_width } else { 0 };
+            if self.word_wrap_enabled && wrap_width >= 2 { wrap_width } else { 0 };
 
         if force || self.word_wrap_column > word_wrap_column {
             self.word_wrap_column = word_wrap_column;
@@ -582,9 +1046,204 @@ impl TextBuffer {
             }
         }
 
+        self.fold_rows_update();
         self.cursor_for_rendering = None;
     }
 
+    /// Unfolds the region if the cursor is on the header of a folded one, or folds the
+    /// innermost one at the cursor. Returns whether anything changed.
+    pub fn toggle_fold(&mut self) -> bool {
+        self.unfold_at_cursor() || self.fold_at_cursor()
+    }
+
+    /// Folds the innermost region at the cursor, or the one around it, if it's folded already.
+    /// A cursor inside the region moves to its header. Returns whether anything changed.
+    /// See [`TextBuffer::fold_all()`].
+    pub fn fold_at_cursor(&mut self) -> bool {
+        let y = self.cursor.logical_pos.y;
+        self.fold_regions_update();
+        // Nested regions start later than those around them.
+        let region = self.fold_regions.iter().rfind(|r| {
+            r.contains(&y) && self.folds.binary_search_by_key(&(r.start + 1), |f| f.start).is_err()
+        });
+        match region.cloned() {
+            Some(region) => {
+                self.fold(region);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Unfolds the region whose header the cursor is on, if it's folded.
+    pub fn unfold_at_cursor(&mut self) -> bool {
+        self.unfold_header(self.cursor.logical_pos.y)
+    }
+
+    /// Like [`TextBuffer::toggle_fold()`] for a click on the visual row `y`,
+    /// but only if it's the header of a region.
+    pub fn toggle_fold_at_visual(&mut self, y: CoordType) -> bool {
+        let row = self.screen_to_row(y);
+        if y < 0 || row >= self.stats.visual_lines {
+            return false;
+        }
+        let pos = self.cursor_move_to_visual_internal(self.cursor, Point { x: 0, y: row });
+        let y = pos.logical_pos.y;
+        if self.unfold_header(y) {
+            return true;
+        }
+        self.fold_regions_update();
+        match self.fold_regions.iter().find(|r| r.start == y).cloned() {
+            Some(region) => {
+                self.fold(region);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Folds all outermost regions.
+    pub fn fold_all(&mut self) -> bool {
+        self.fold_regions_update();
+        let mut folds = Vec::new();
+        for region in &self.fold_regions {
+            if folds.last().is_none_or(|f: &Range<CoordType>| f.end <= region.start) {
+                folds.push(region.start + 1..region.end);
+            }
+        }
+        if folds == self.folds {
+            return false;
+        }
+        self.folds = folds;
+        self.folds_changed();
+        true
+    }
+
+    /// Unfolds everything.
+    pub fn unfold_all(&mut self) -> bool {
+        if self.folds.is_empty() {
+            return false;
+        }
+        self.folds.clear();
+        self.folds_changed();
+        true
+    }
+
+    /// Folds the region, whose first line is its header, replacing the folds inside of it.
+    fn fold(&mut self, region: Range<CoordType>) {
+        self.folds.retain(|f| f.end <= region.start || f.start >= region.end);
+        let i = self.folds.partition_point(|f| f.start < region.start);
+        self.folds.insert(i, region.start + 1..region.end);
+        self.folds_changed();
+    }
+
+    /// Unfolds the region with the header line `y`, if it's folded.
+    fn unfold_header(&mut self, y: CoordType) -> bool {
+        match self.folds.binary_search_by_key(&(y + 1), |f| f.start) {
+            Ok(i) => {
+                self.folds.remove(i);
+                self.folds_changed();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn folds_changed(&mut self) {
+        let pos = self.cursor.logical_pos;
+        if let Some(fold) = self.folds.iter().find(|f| f.contains(&pos.y)) {
+            let header = Point { x: pos.x, y: fold.start - 1 };
+            self.cursor = self.cursor_move_to_logical_internal(self.cursor, header);
+            self.set_selection(None);
+        }
+        self.fold_rows_update();
+    }
+
+    /// Keeps `folds` on their lines after an edit of the logical lines `beg..=end`
+    /// that changed the line count by `delta`. The folds it touched get unfolded.
+    fn folds_after_edit(&mut self, beg: CoordType, end: CoordType, delta: CoordType) {
+        self.folds.retain_mut(|f| {
+            let header = f.start - 1;
+            if end < header {
+                *f = f.start + delta..f.end + delta;
+                true
+            } else {
+                (beg == header && end == header && delta == 0) || beg >= f.end
+            }
+        });
+    }
+
+    /// Recalculates `fold_rows`, since the layout or `folds` changed.
+    fn fold_rows_update(&mut self) {
+        self.fold_rows.clear();
+        if self.word_wrap_column <= 0 {
+            self.fold_rows.extend(self.folds.iter().cloned());
+            return;
+        }
+
+        let mut cursor = self.cursor;
+        for i in 0..self.folds.len() {
+            let lines = self.folds[i].clone();
+            cursor = self.cursor_move_to_logical_internal(cursor, Point { x: 0, y: lines.start });
+            let beg = cursor.visual_pos.y;
+            let end = if lines.end >= self.stats.logical_lines {
+                self.stats.visual_lines
+            } else {
+                cursor = self.cursor_move_to_logical_internal(cursor, Point { x: 0, y: lines.end });
+                cursor.visual_pos.y
+            };
+            self.fold_rows.push(beg..end);
+        }
+    }
+
+    /// Converts a visual row to the one it's shown at with the folds hidden.
+    /// Hidden rows are shown at the header of their fold.
+    fn row_to_screen(&self, row: CoordType) -> CoordType {
+        let mut y = row;
+        for r in &self.fold_rows {
+            if row < r.start {
+                break;
+            }
+            y -= row.min(r.end - 1) - r.start + 1;
+        }
+        y
+    }
+
+    /// The inverse of [`TextBuffer::row_to_screen()`].
+    fn screen_to_row(&self, y: CoordType) -> CoordType {
+        let mut row = y;
+        for r in &self.fold_rows {
+            if row < r.start {
+                break;
+            }
+            row += r.end - r.start;
+        }
+        row
+    }
+
+    /// Updates `fold_regions` to those from [`crate::syntax::IndentRule::fold_ranges()`]
+    /// as ranges of logical lines, starting with their header. There are none for files
+    /// without an indentation rule and for huge ones.
+    fn fold_regions_update(&mut self) {
+        let key = (self.buffer.generation(), self.current_file_type);
+        if self.fold_regions_key == Some(key) {
+            return;
+        }
+        self.fold_regions_key = Some(key);
+        self.fold_regions.clear();
+
+        if self.stats.logical_lines > FOLD_MAX_LINES {
+            return;
+        }
+        let Some(rule) = self.smart_indenter.rule(self.current_file_type) else {
+            return;
+        };
+        let lines = self.lines_text(0..self.stats.logical_lines);
+        let regions = rule.fold_ranges(&lines);
+        self.fold_regions =
+            regions.into_iter().map(|r| r.start as CoordType..r.end as CoordType).collect();
+    }
+
     /// Replaces the entire buffer contents with the given `text`.
     /// Assumes that the line count doesn't change.
     pub fn copy_from_str(&mut self, text: &dyn ReadableDocument) {
@@ -599,6 +1258,71 @@ impl TextBuffer {
         }
     }
 
+    /// Replaces the entire buffer contents with the given `text`
+    /// and forgets the undo history, as if `text` was read from a file.
+    pub fn set_text(&mut self, text: &str) {
+        self.set_bytes(text.as_bytes());
+    }
+
+    /// Replaces the range from the first to the last line that differs from `text` as a single
+    /// undo step, e.g. to reload the file, see [`TextBuffer::reload_file_with_path()`].
+    /// The cursor and selection stay on their lines, unless those changed.
+    pub fn set_bytes_by_diff(&mut self, text: &[u8]) {
+        let old = self.read_all();
+        let (old_lines, new_lines) = (diff::lines(&old), diff::lines(text));
+        let hunks = diff::diff(&old_lines, &new_lines);
+        let (Some(first), Some(last)) = (hunks.first(), hunks.last()) else {
+            return;
+        };
+
+        let offset_of = |lines: &[&[u8]], line: usize| -> usize {
+            lines[..line].iter().map(|l| l.len()).sum()
+        };
+        let old_range =
+            offset_of(&old_lines, first.old.start)..offset_of(&old_lines, last.old.end);
+        let new_range =
+            offset_of(&new_lines, first.new.start)..offset_of(&new_lines, last.new.end);
+
+        // Positions on unchanged lines move along with them,
+        // while those on changed lines go to the start of the change.
+        let map = |pos: Point| {
+            let y = pos.y as usize;
+            let mut new_y = y;
+            for hunk in &hunks {
+                if y < hunk.old.start {
+                    break;
+                }
+                if y < hunk.old.end {
+                    return Point { x: 0, y: hunk.new.start as CoordType };
+                }
+                new_y = y - hunk.old.end + hunk.new.end;
+            }
+            Point { x: pos.x, y: new_y as CoordType }
+        };
+        let pos = map(self.cursor.logical_pos);
+        let selection =
+            self.selection.map(|s| TextBufferSelection { beg: map(s.beg), end: map(s.end) });
+
+        let beg = self.cursor_move_to_offset_internal(self.cursor, old_range.start);
+        let end = self.cursor_move_to_offset_internal(beg, old_range.end);
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&text[new_range]);
+        self.edit_end();
+
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, pos));
+        self.set_selection(selection);
+    }
+
+    fn set_bytes(&mut self, text: &[u8]) {
+        self.buffer.clear();
+        self.buffer.replace(0..0, text);
+        let (_, lines) = unicode::newlines_forward(text, 0, 0, CoordType::MAX);
+        self.stats.logical_lines = lines + 1;
+        self.stats.visual_lines = self.stats.logical_lines;
+        self.recalc_after_content_swap();
+    }
+
     fn recalc_after_content_swap(&mut self) {
         // If the buffer was changed, nothing we previously saved can be relied upon.
         self.undo_stack.clear();
@@ -608,10 +1332,23 @@ impl TextBuffer {
         self.cursor_for_rendering = None;
         self.set_selection(None);
         self.search = None;
+        self.folds.clear();
+        self.fold_regions_key = None;
         self.mark_as_clean();
         self.reflow(true);
     }
 
+    /// Whether the contents are still backed by a memory-mapped file.
+    pub fn is_mapped(&self) -> bool {
+        self.buffer.is_mapped()
+    }
+
+    /// Copies a memory-mapped file into memory. Needs to be called before
+    /// the file gets overwritten, as reading from the mapping would crash otherwise.
+    pub fn unmap(&mut self) -> apperr::Result<()> {
+        self.buffer.unmap()
+    }
+
     /// Copies the contents of the buffer into a string.
     pub fn save_as_string(&mut self, dst: &mut dyn WriteableDocument) {
         self.buffer.copy_into(dst);
@@ -645,12 +1382,47 @@ impl TextBuffer {
         self.read_file(file, encoding)
     }
 
+    /// Reads the file again after it changed on disk. Unlike [`TextBuffer::read_file()`],
+    /// only the lines that changed are replaced, as a single undo step, so that the undo
+    /// history is kept and the cursor and selection stay on their lines.
+    ///
+    /// Falls back to reading the file as usual if `encoding` is given, the file is now in a
+    /// different encoding, or it's too large to diff, see [`TextBuffer::is_mapped()`].
+    pub fn reload_file_with_path(
+        &mut self,
+        file: &mut File,
+        file_path: &std::path::Path,
+        encoding: Option<&'static str>,
+    ) -> apperr::Result<()> {
+        if encoding.is_none() && self.ansi_view.is_none() && !self.is_mapped() {
+            let mut new = TextBuffer::new(false)?;
+            new.read_file_with_path(file, file_path, None)?;
+            if new.encoding == self.encoding
+                && new.hex_view.is_some() == self.hex_view.is_some()
+                && !new.is_mapped()
+            {
+                self.set_bytes_by_diff(&new.read_all());
+                self.mark_as_clean();
+                return Ok(());
+            }
+            file.rewind()?;
+        }
+        self.read_file_with_path(file, file_path, encoding)
+    }
+
     /// Internal implementation of file reading
     fn read_file_internal(
         &mut self,
         file: &mut File,
         encoding: Option<&'static str>,
     ) -> apperr::Result<()> {
+        if let Some(view) = self.ansi_view.take() {
+            self.read_only = view.read_only;
+        }
+        if let Some(view) = self.hex_view.take() {
+            self.overtype = view.overtype;
+        }
+
         let scratch = scratch_arena(None);
         let mut buf = scratch.alloc_uninit().transpose();
         let mut first_chunk_len = 0;
@@ -683,6 +1455,24 @@ impl TextBuffer {
             self.read_file_with_icu(file, &mut buf, first_chunk_len, done)?;
         }
 
+        // Binary files are shown as a hex dump, which keeps their bytes as they are.
+        // Files with a BOM are text, even UTF-16 ones full of NULs.
+        if encoding.is_none() && self.encoding == "UTF-8" && hex::is_binary(self.read_forward(0)) {
+            self.recalc_after_content_swap();
+            return self.set_hex_view(true);
+        }
+
+        // Files without a BOM that aren't valid UTF-8 are most likely in a legacy encoding.
+        // Pipes can't be read twice, so their contents stay as they are.
+        if encoding.is_none()
+            && self.encoding == "UTF-8"
+            && let Some(legacy) = detect_legacy_encoding(self.read_forward(0))
+            && icu::init().is_ok()
+            && file.rewind().is_ok()
+        {
+            return self.read_file_internal(file, Some(legacy));
+        }
+
         // Figure out
         // * the logical line count
         // * the newline type (LF or CRLF)
@@ -694,48 +1484,8 @@ impl TextBuffer {
             let mut lines = 0;
             // Number of lines ending in CRLF.
             let mut crlf_count = 0;
-            // Number of lines starting with a tab.
-            let mut tab_indentations = 0;
-            // Number of lines starting with a space.
-            let mut space_indentations = 0;
-            // Histogram of the indentation depth of lines starting with between 2 and 8 spaces.
-            // In other words, `space_indentation_sizes[0]` is the number of lines starting with 2 spaces.
-            let mut space_indentation_sizes = [0; 7];
 
             loop {
-                // Check if the line starts with a tab.
-                if offset < chunk.len() && chunk[offset] == b'\t' {
-                    tab_indentations += 1;
-                } else {
-                    // Otherwise, check how many spaces the line starts with. Searching for >8 spaces
-                    // allows us to reject lines that have more than 1 level of indentation.
-                    let space_indentation =
-                        chunk[offset..].iter().take(9).take_while(|&&c| c == b' ').count();
-
-                    // We'll also reject lines starting with 1 space, because that's too fickle as a heuristic.
-                    if (2..=8).contains(&space_indentation) {
-                        space_indentations += 1;
-
-                        // If we encounter an indentation depth of 6, it may either be a 6-space indentation,
-                        // two 3-space indentation or 3 2-space indentations. To make this work, we increment
-                        // all 3 possible histogram slots.
-                        //   2 -> 2
-                        //   3 -> 3
-                        //   4 -> 4 2
-                        //   5 -> 5
-                        //   6 -> 6 3 2
-                        //   7 -> 7
-                        //   8 -> 8 4 2
-                        space_indentation_sizes[space_indentation - 2] += 1;
-                        if space_indentation & 4 != 0 {
-                            space_indentation_sizes[0] += 1;
-                        }
-                        if space_indentation == 6 || space_indentation == 8 {
-                            space_indentation_sizes[space_indentation / 2 - 2] += 1;
-                        }
-                    }
-                }
-
                 (offset, lines) = unicode::newlines_forward(chunk, offset, lines, lines + 1);
 
                 // Check if the preceding line ended in CRLF.
@@ -753,25 +1503,7 @@ impl TextBuffer {
             // We'll assume CRLF if more than half of the lines end in CRLF.
             let newlines_are_crlf = crlf_count >= lines / 2;
 
-            // We'll assume tabs if there are more lines starting with tabs than with spaces.
-            let indent_with_tabs = tab_indentations > space_indentations;
-            let tab_size = if indent_with_tabs {
-                // Tabs will get a visual size of 4 spaces by default.
-                4
-            } else {
-                // Otherwise, we'll assume the most common indentation depth.
-                // If there are conflicting indentation depths, we'll prefer the maximum, because in the loop
-                // above we incremented the histogram slot for 2-spaces when encountering 4-spaces and so on.
-                let mut max = 1;
-                let mut tab_size = 4;
-                for (i, &count) in space_indentation_sizes.iter().enumerate() {
-                    if count >= max {
-                        max = count;
-                        tab_size = i as CoordType + 2;
-                    }
-                }
-                tab_size
-            };
+            let indent = IndentStyle::detect(chunk);
 
             // If the file has more than 1000 lines, figure out how many are remaining.
             if offset < chunk.len() {
@@ -785,8 +1517,8 @@ impl TextBuffer {
             self.stats.visual_lines = self.stats.logical_lines;
             self.newlines_are_crlf = newlines_are_crlf;
             self.insert_final_newline = final_newline;
-            self.indent_with_tabs = indent_with_tabs;
-            self.tab_size = tab_size;
+            self.indent_with_tabs = indent.use_tabs;
+            self.tab_size = indent.width;
         }
 
         self.recalc_after_content_swap();
@@ -800,7 +1532,11 @@ impl TextBuffer {
         first_chunk_len: usize,
         done: bool,
     ) -> apperr::Result<()> {
-        {
+        if !done && self.read_file_as_mapping(file, buf, first_chunk_len) {
+            return Ok(());
+        }
+
+        {
             let mut first_chunk = unsafe { buf[..first_chunk_len].assume_init_ref() };
             if first_chunk.starts_with(b"\xEF\xBB\xBF") {
                 first_chunk = &first_chunk[3..];
@@ -847,6 +1583,36 @@ impl TextBuffer {
         Ok(())
     }
 
+    /// Maps huge files into memory instead of reading them, see [`GapBuffer::map_file`].
+    /// Returns false if the file is too small or can't be mapped.
+    fn read_file_as_mapping(
+        &mut self,
+        file: &File,
+        buf: &[MaybeUninit<u8>; 4 * KIBI],
+        first_chunk_len: usize,
+    ) -> bool {
+        let Ok(m) = file.metadata() else {
+            return false;
+        };
+        if !m.is_file() || m.len() < MMAP_MIN_LEN as u64 {
+            return false;
+        }
+
+        let first_chunk = unsafe { buf[..first_chunk_len].assume_init_ref() };
+        let bom = first_chunk.starts_with(b"\xEF\xBB\xBF");
+        let Ok(mut buffer) = GapBuffer::map_file(file, m.len() as usize, if bom { 3 } else { 0 })
+        else {
+            return false;
+        };
+
+        buffer.set_generation(self.buffer.generation().wrapping_add(1));
+        self.buffer = buffer;
+        if bom {
+            self.encoding = "UTF-8 BOM";
+        }
+        true
+    }
+
     fn read_file_with_icu(
         &mut self,
         file: &mut File,
@@ -912,7 +1678,21 @@ impl TextBuffer {
     }
 
     /// Writes the text buffer contents to a file, handling BOM and encoding.
-    pub fn write_file(&mut self, file: &mut File) -> apperr::Result<()> {
+    pub fn write_file(&mut self, file: &mut impl io::Write) -> apperr::Result<()> {
+        if self.ansi_view.is_some() {
+            // Save the escape sequences, not just what's shown.
+            self.set_ansi_view(false);
+            let res = self.write_file(file);
+            self.set_ansi_view(true);
+            return res;
+        }
+        if self.hex_view.is_some() {
+            // Save the bytes, not the dump of them.
+            file.write_all(&self.parse_hex_view()?)?;
+            self.mark_as_clean();
+            return Ok(());
+        }
+
         let mut offset = 0;
 
         if self.encoding.starts_with("UTF-8") {
@@ -935,7 +1715,7 @@ impl TextBuffer {
         Ok(())
     }
 
-    fn write_file_with_icu(&mut self, file: &mut File) -> apperr::Result<()> {
+    fn write_file_with_icu(&mut self, file: &mut impl io::Write) -> apperr::Result<()> {
         let scratch = scratch_arena(None);
         let pivot_buffer = scratch.alloc_uninit_slice(4 * KIBI);
         let buf = scratch.alloc_uninit_slice(4 * KIBI);
@@ -980,7 +1760,8 @@ impl TextBuffer {
 
     /// Moves the cursor to `visual_pos` and updates the selection to contain it.
     pub fn selection_update_visual(&mut self, visual_pos: Point) {
-        self.set_cursor_for_selection(self.cursor_move_to_visual_internal(self.cursor, visual_pos));
+        let pos = Point { x: visual_pos.x, y: self.screen_to_row(visual_pos.y) };
+        self.set_cursor_for_selection(self.cursor_move_to_visual_internal(self.cursor, pos));
     }
 
     /// Moves the cursor to `logical_pos` and updates the selection to contain it.
@@ -1054,6 +1835,247 @@ impl TextBuffer {
         had_selection
     }
 
+    /// Returns whether there's more than one cursor.
+    pub fn has_extra_cursors(&self) -> bool {
+        !self.extra_cursors.is_empty()
+    }
+
+    /// Removes all cursors but the primary one. Returns whether there were any.
+    pub fn clear_extra_cursors(&mut self) -> bool {
+        let had_extra_cursors = !self.extra_cursors.is_empty();
+        self.extra_cursors.clear();
+        had_extra_cursors
+    }
+
+    /// Adds a cursor one visual line above (`delta` = -1) or below (1) the outermost cursor
+    /// in that direction, at the visual column `x`. It becomes the primary cursor.
+    /// Returns whether there was a line to add it to.
+    pub fn add_cursor_vertical(&mut self, x: CoordType, delta: CoordType) -> bool {
+        debug_assert!(delta == -1 || delta == 1);
+
+        let mut outermost = self.cursor;
+        for extra in &self.extra_cursors {
+            let pos = self.cursor_move_to_logical_internal(outermost, extra.end);
+            if (pos.visual_pos.y - outermost.visual_pos.y).signum() == delta {
+                outermost = pos;
+            }
+        }
+
+        let y = self.row_to_screen(outermost.visual_pos.y) + delta;
+        if y < 0 || y >= self.visual_line_count() {
+            return false;
+        }
+
+        let y = self.screen_to_row(y);
+        let target = self.cursor_move_to_visual_internal(outermost, Point { x, y });
+        self.extra_cursors.push(self.primary_cursor_selection());
+        self.extra_cursors.retain(|c| c.end != target.logical_pos);
+        self.set_cursor_internal(target);
+        self.last_history_type = HistoryType::Other;
+        self.set_selection(None);
+        true
+    }
+
+    /// Adds a cursor that selects the next occurrence of the selected text, searching
+    /// forward from the primary cursor and wrapping around. It becomes the primary cursor.
+    /// Without a selection, the word at the cursor gets selected first instead.
+    /// Returns whether anything changed.
+    pub fn add_cursor_at_next_match(&mut self) -> bool {
+        let Some((beg, end)) = self.selection_range_internal(false) else {
+            if !self.extra_cursors.is_empty() {
+                return false;
+            }
+            self.select_word();
+            return self.selection.is_some();
+        };
+
+        let mut needle = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut needle, 0);
+        let text = self.read_all();
+        let find = |from: usize, to: usize| {
+            text[from..to].windows(needle.len()).position(|w| w == needle).map(|i| from + i)
+        };
+
+        // Skip the occurrences that already have a cursor.
+        let taken: Vec<usize> = self
+            .extra_cursors
+            .iter()
+            .map(|c| self.cursor_move_to_logical_internal(self.cursor, c.beg.min(c.end)).offset)
+            .chain([beg.offset])
+            .collect();
+        let mut from = end.offset;
+        let mut wrapped = false;
+        let hit = loop {
+            let hit = match find(from, text.len()) {
+                None if !wrapped => {
+                    wrapped = true;
+                    find(0, text.len())
+                }
+                hit => hit,
+            };
+            match hit {
+                Some(hit) if taken.contains(&hit) => {
+                    if wrapped && hit >= beg.offset {
+                        return false;
+                    }
+                    from = hit + needle.len();
+                }
+                Some(hit) => break hit,
+                None => return false,
+            }
+        };
+
+        self.extra_cursors.push(self.primary_cursor_selection());
+        let hit_beg = self.cursor_move_to_offset_internal(self.cursor, hit);
+        let hit_end = self.cursor_move_to_offset_internal(hit_beg, hit + needle.len());
+        self.set_cursor_internal(hit_end);
+        self.last_history_type = HistoryType::Other;
+        self.set_selection(Some(TextBufferSelection {
+            beg: hit_beg.logical_pos,
+            end: hit_end.logical_pos,
+        }));
+        true
+    }
+
+    /// Starts or extends a block selection to the visual position `corner`. It's the rectangle
+    /// between `corner` and the cursor position at the start, and every line in it gets a cursor
+    /// that selects its part. Typing, deleting and pasting then work on the whole block.
+    pub fn block_selection_update_visual(&mut self, corner: Point) {
+        let anchor = match self.block_selection {
+            Some((anchor, ..)) if self.has_block_selection() => anchor,
+            _ => self.cursor_visual_pos(),
+        };
+        let corner =
+            Point { x: corner.x.max(0), y: corner.y.clamp(0, self.visual_line_count() - 1) };
+
+        // The cursors go on the `corner` side of each line, and the primary one on its line.
+        let mut primary = None;
+        self.extra_cursors.clear();
+        let [top, bottom] = minmax(anchor.y, corner.y);
+        let top_row = self.screen_to_row(top);
+        let mut pos = self.cursor_move_to_visual_internal(self.cursor, Point { x: 0, y: top_row });
+        for y in top..=bottom {
+            let row = self.screen_to_row(y);
+            let beg = self.cursor_move_to_visual_internal(pos, Point { x: anchor.x, y: row });
+            pos = self.cursor_move_to_visual_internal(beg, Point { x: corner.x, y: row });
+            let cursor = TextBufferSelection { beg: beg.logical_pos, end: pos.logical_pos };
+            if y == corner.y {
+                primary = Some((pos, cursor));
+            } else {
+                self.extra_cursors.push(cursor);
+            }
+        }
+
+        if let Some((pos, cursor)) = primary {
+            self.set_cursor_internal(pos);
+            self.last_history_type = HistoryType::Other;
+            let generation = self.set_selection(Some(cursor));
+            self.block_selection = Some((anchor, corner, generation));
+        }
+    }
+
+    /// Whether the selection is still the block selection made by the last
+    /// [`TextBuffer::block_selection_update_visual()`] call.
+    pub fn has_block_selection(&self) -> bool {
+        self.block_selection
+            .is_some_and(|(_, _, generation)| generation == self.selection_generation)
+    }
+
+    /// Like [`TextBuffer::block_selection_update_visual()`], but moves the corner by `delta`.
+    pub fn block_selection_update_delta(&mut self, delta: Point) {
+        let corner = match self.block_selection {
+            Some((_, corner, _)) if self.has_block_selection() => corner,
+            _ => self.cursor.visual_pos,
+        };
+        self.block_selection_update_visual(Point { x: corner.x + delta.x, y: corner.y + delta.y });
+    }
+
+    /// The primary cursor and its selection, in the form of [`TextBuffer::extra_cursors`].
+    fn primary_cursor_selection(&self) -> TextBufferSelection {
+        let end = self.cursor.logical_pos;
+        let beg = match self.selection {
+            // The cursor is at one end of the selection, usually at its `end`.
+            Some(selection) if selection.beg == end => selection.end,
+            Some(selection) => selection.beg,
+            None => end,
+        };
+        TextBufferSelection { beg, end }
+    }
+
+    /// Runs `func` once for each cursor, with that cursor and its selection made the primary
+    /// ones. The last cursor in the document goes first, so that the edits don't move the
+    /// cursors yet to come. All edits become a single undo step.
+    ///
+    /// [`TextBuffer::write()`] and [`TextBuffer::delete()`] do this on their own. Other edits
+    /// and movements only apply to the primary cursor, and the latter remove all others.
+    pub fn for_each_cursor(&mut self, mut func: impl FnMut(&mut Self)) {
+        if self.extra_cursors.is_empty() || self.each_cursor.is_some() {
+            func(self);
+            return;
+        }
+
+        let mut cursors: Vec<(TextBufferSelection, bool)> =
+            self.extra_cursors.drain(..).map(|c| (c, false)).collect();
+        cursors.push((self.primary_cursor_selection(), true));
+        cursors.sort_by_key(|(c, _)| Reverse(c.beg.min(c.end)));
+
+        // For each cursor: Its selection's anchor, its offset,
+        // how much the edit grew the text, and whether it's the primary one.
+        let mut results = Vec::with_capacity(cursors.len());
+        self.each_cursor = Some(0);
+
+        for &(cursor, primary) in &cursors {
+            let len_before = self.text_length();
+            let pos = self.cursor_move_to_logical_internal(self.cursor, cursor.end);
+            self.set_cursor_internal(pos);
+            self.set_selection(Some(cursor));
+            self.last_history_type = HistoryType::Other;
+
+            func(self);
+
+            let anchor = self.selection.map(|_| {
+                let anchor = self.primary_cursor_selection().beg;
+                self.cursor_move_to_logical_internal(self.cursor, anchor).offset
+            });
+            let growth = self.text_length() as isize - len_before as isize;
+            results.push((anchor, self.cursor.offset, growth, primary));
+        }
+
+        self.each_cursor = None;
+        self.last_history_type = HistoryType::Other;
+
+        // The edits at the earlier cursors, which came last, shifted the later ones.
+        let mut shift = 0;
+        let mut offsets = Vec::with_capacity(results.len());
+        for &(anchor, offset, growth, primary) in results.iter().rev() {
+            let clamp = |off: usize| off.saturating_add_signed(shift).min(self.text_length());
+            offsets.push((anchor.map(clamp), clamp(offset), primary));
+            shift += growth;
+        }
+        offsets.sort_by_key(|&(_, offset, primary)| (offset, !primary));
+        offsets.dedup_by_key(|(_, offset, _)| *offset);
+
+        let mut pos = Cursor::default();
+        let mut primary_cursor = None;
+        for (anchor, offset, primary) in offsets {
+            pos = self.cursor_move_to_offset_internal(pos, offset);
+            let beg = anchor.map_or(pos.logical_pos, |anchor| {
+                self.cursor_move_to_offset_internal(pos, anchor).logical_pos
+            });
+            let cursor = TextBufferSelection { beg, end: pos.logical_pos };
+            if primary {
+                primary_cursor = Some((pos, cursor));
+            } else {
+                self.extra_cursors.push(cursor);
+            }
+        }
+
+        if let Some((pos, cursor)) = primary_cursor {
+            self.set_cursor_internal(pos);
+            self.set_selection(Some(cursor));
+        }
+    }
+
     /// Find the next occurrence of the given `pattern` and select it.
     pub fn find_and_select(&mut self, pattern: &str, options: SearchOptions) -> apperr::Result<()> {
         if let Some(search) = &mut self.search {
@@ -1108,168 +2130,601 @@ impl TextBuffer {
     }
 
     /// Find the next occurrence of the given `pattern` and replace it with `replacement`.
+    ///
+    /// With [`SearchOptions::use_regex`], `$1` to `$9` or `${12}` in the `replacement`
+    /// stand for the capture groups of the hit, `$0` for the entire hit and `$$` for a `$`.
+    /// `\n`, `\t` and `\\` are escapes for a newline, a tab and a backslash.
     pub fn find_and_replace(
         &mut self,
         pattern: &str,
         options: SearchOptions,
         replacement: &str,
     ) -> apperr::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
         // Editors traditionally replace the previous search hit, not the next possible one.
-        if let (Some(search), Some(..)) = (&mut self.search, &self.selection) {
-            let search = search.get_mut();
-            if search.selection_generation == self.selection_generation {
-                self.write(replacement.as_bytes(), true);
+        if let Some(hit) = self.find_current_hit() {
+            let search = unsafe { &mut *self.search.as_ref().unwrap().get() };
+            let mut text = Vec::new();
+            self.find_expand_replacement(search, replacement.as_bytes(), &mut text);
+            self.find_replace_hits(&[(hit.clone(), 0..text.len())], &text);
+            // Nothing is behind the hit, so the search wraps around.
+            if hit.is_empty() && !self.find_skip_empty_hit() {
+                self.cursor_move_to_logical(Point::default());
             }
         }
 
         self.find_and_select(pattern, options)
     }
 
-    /// Find all occurrences of the given `pattern` and replace them with `replacement`.
+    /// Find all occurrences of the given `pattern` and replace them with `replacement`
+    /// as a single undo step. See [`TextBuffer::find_and_replace()`] for the `replacement`.
+    /// Returns the number of replacements.
     pub fn find_and_replace_all(
         &mut self,
         pattern: &str,
         options: SearchOptions,
         replacement: &str,
-    ) -> apperr::Result<()> {
-        let replacement = replacement.as_bytes();
+    ) -> apperr::Result<usize> {
+        if self.read_only {
+            return Ok(0);
+        }
+
         let mut search = self.find_construct_search(pattern, options)?;
-        let mut offset = 0;
+        let count =
+            self.find_replace_from(&mut search, 0, CoordType::MAX, true, replacement.as_bytes());
+        Ok(count)
+    }
 
-        loop {
-            self.find_select_next(&mut search, offset, false);
-            if !self.has_selection() {
-                break;
-            }
-            self.write(replacement, true);
-            offset = self.cursor.offset;
+    /// Counts the occurrences of the given `pattern`, which is what
+    /// [`TextBuffer::find_and_replace_all()`] would replace.
+    pub fn find_count(&self, pattern: &str, options: SearchOptions) -> apperr::Result<usize> {
+        let mut search = self.find_construct_search(pattern, options)?;
+        search.regex.reset(0);
+        Ok(search.regex.count())
+    }
+
+    /// Replaces the hits of `pattern` that start within the given logical `lines`
+    /// as a single undo step. See [`TextBuffer::find_and_replace()`] for the `replacement`.
+    /// Unless `every` is set, only the first hit on each line is replaced, like Vim's `:s`.
+    /// Returns the number of replacements.
+    pub fn find_and_replace_in_lines(
+        &mut self,
+        pattern: &str,
+        options: SearchOptions,
+        replacement: &str,
+        lines: Range<CoordType>,
+        every: bool,
+    ) -> apperr::Result<usize> {
+        if self.read_only {
+            return Ok(0);
         }
 
-        Ok(())
+        let mut search = self.find_construct_search(pattern, options)?;
+        let offset =
+            self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: lines.start }).offset;
+        let count =
+            self.find_replace_from(&mut search, offset, lines.end, every, replacement.as_bytes());
+        self.clear_selection();
+        Ok(count)
     }
 
-    fn find_construct_search(
-        &self,
+    /// Steps through the hits of `pattern` within the logical `lines`, to replace them one
+    /// by one after asking the user. Does the `step` to the selected hit, if any, and selects
+    /// the next one. Unless `every` is set, that's the first hit on one of the following lines.
+    ///
+    /// Start by placing the cursor at the start of the `lines` and taking a [`ReplaceStep::Skip`].
+    /// `lines` is updated as replacements add or remove lines.
+    /// Returns whether another hit was selected.
+    pub fn find_and_replace_step(
+        &mut self,
         pattern: &str,
         options: SearchOptions,
-    ) -> apperr::Result<ActiveSearch> {
-        if pattern.is_empty() {
-            return Err(apperr::Error::Icu(1)); // U_ILLEGAL_ARGUMENT_ERROR
+        replacement: &str,
+        lines: &mut Range<CoordType>,
+        every: bool,
+        step: ReplaceStep,
+    ) -> apperr::Result<bool> {
+        if let Some(search) = &mut self.search {
+            let search = search.get_mut();
+            if search.pattern != pattern || search.options != options {
+                self.search = None;
+            }
         }
 
-        let sanitized_pattern = if options.whole_word && options.use_regex {
-            Cow::Owned(format!(r"\b(?:{pattern})\b"))
-        } else if options.whole_word {
-            let mut p = String::with_capacity(pattern.len() + 16);
-            p.push_str(r"\b");
+        let mut offset = self.cursor.offset;
 
-            // Escape regex special characters.
-            let b = unsafe { p.as_mut_vec() };
-            for &byte in pattern.as_bytes() {
-                match byte {
-                    b'*' | b'?' | b'+' | b'[' | b'(' | b')' | b'{' | b'}' | b'^' | b'$' | b'|'
-                    | b'\\' | b'.' => {
-                        b.push(b'\\');
-                        b.push(byte);
+        if let Some(hit) = self.find_current_hit() {
+            let search = unsafe { &mut *self.search.as_ref().unwrap().get() };
+            let (beg, end) = (hit.start, hit.end);
+            let lines_before = self.stats.logical_lines;
+
+            match step {
+                ReplaceStep::Skip => {
+                    let end = self.cursor_move_to_offset_internal(self.cursor, end);
+                    self.set_selection(None);
+                    unsafe { self.set_cursor(end) };
+                }
+                ReplaceStep::Replace if !self.read_only => {
+                    let mut text = Vec::new();
+                    self.find_expand_replacement(search, replacement.as_bytes(), &mut text);
+                    self.find_replace_hits(&[(beg..end, 0..text.len())], &text);
+                    if hit.is_empty() && !self.find_skip_empty_hit() {
+                        return Ok(false);
                     }
-                    _ => b.push(byte),
+                }
+                ReplaceStep::Replace => return Ok(false),
+                ReplaceStep::ReplaceAll => {
+                    if !self.read_only {
+                        let replacement = replacement.as_bytes();
+                        self.find_replace_from(search, beg, lines.end, every, replacement);
+                    }
+                    self.clear_selection();
+                    return Ok(false);
                 }
             }
 
-            p.push_str(r"\b");
-            Cow::Owned(p)
-        } else {
-            Cow::Borrowed(pattern)
-        };
+            lines.end += self.stats.logical_lines - lines_before;
+            offset = self.cursor.offset;
 
-        let mut flags = icu::Regex::MULTILINE;
-        if !options.match_case {
-            flags |= icu::Regex::CASE_INSENSITIVE;
-        }
-        if !options.use_regex && !options.whole_word {
-            flags |= icu::Regex::LITERAL;
+            if !every {
+                let next = Point { x: 0, y: self.cursor.logical_pos.y + 1 };
+                let next = self.cursor_move_to_logical_internal(self.cursor, next);
+                if next.logical_pos.y == self.cursor.logical_pos.y {
+                    return Ok(false);
+                }
+                offset = next.offset;
+            }
         }
 
-        // Move the start of the search to the start of the selection,
-        // or otherwise to the current cursor position.
+        // Edits drop the search, see `TextBuffer::edit_end()`. That's why it's only created now.
+        let search = match &self.search {
+            Some(search) => unsafe { &mut *search.get() },
+            None => {
+                let search = self.find_construct_search(pattern, options)?;
+                self.search = Some(UnsafeCell::new(search));
+                unsafe { &mut *self.search.as_ref().unwrap().get() }
+            }
+        };
 
-        let text = unsafe { icu::Text::new(self)? };
-        let regex = unsafe { icu::Regex::new(&sanitized_pattern, flags, &text)? };
+        self.find_select_next(search, offset, false);
+        let found = search.hit.as_ref().is_some_and(|hit| {
+            self.cursor_move_to_offset_internal(self.cursor, hit.start).logical_pos.y < lines.end
+        });
+        if !found {
+            self.clear_selection();
+        }
+        Ok(found)
+    }
 
-        Ok(ActiveSearch {
-            pattern: pattern.to_string(),
-            options,
-            text,
-            regex,
-            buffer_generation: self.buffer.generation(),
-            selection_generation: 0,
-            next_search_offset: 0,
-            no_matches: false,
-        })
+    /// The offsets of the hit the last search selected, unless the selection changed since.
+    fn find_current_hit(&self) -> Option<Range<usize>> {
+        let search = unsafe { &*self.search.as_ref()?.get() };
+        if search.selection_generation != self.selection_generation {
+            return None;
+        }
+        search.hit.clone()
     }
 
-    fn find_select_next(&mut self, search: &mut ActiveSearch, offset: usize, wrap: bool) {
+    /// Moves the cursor past the character behind an empty hit that was just replaced.
+    /// It's where the hit was, and searching from there would find the same hit again.
+    /// Returns false if the hit was at the end of the text.
+    fn find_skip_empty_hit(&mut self) -> bool {
+        if self.cursor.offset >= self.text_length() {
+            return false;
+        }
+        let cursor = self.cursor_move_delta_internal(self.cursor, CursorMovement::Grapheme, 1);
+        unsafe { self.set_cursor(cursor) };
+        true
+    }
+
+    /// Replaces the hits of `search` from `offset` on, that start before the logical line `end`.
+    /// Unless `every` is set, only the first hit on each line is replaced.
+    /// Returns the number of replacements.
+    fn find_replace_from(
+        &mut self,
+        search: &mut ActiveSearch,
+        offset: usize,
+        end: CoordType,
+        every: bool,
+        replacement: &[u8],
+    ) -> usize {
+        let end = if end < self.stats.logical_lines {
+            self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: end }).offset
+        } else {
+            usize::MAX
+        };
+
         if search.buffer_generation != self.buffer.generation() {
             unsafe { search.regex.set_text(&mut search.text, offset) };
             search.buffer_generation = self.buffer.generation();
-            search.next_search_offset = offset;
-        } else if search.next_search_offset != offset {
-            search.next_search_offset = offset;
+        } else {
             search.regex.reset(offset);
         }
 
-        let mut hit = search.regex.next();
+        // All hits are collected first, because they're replaced in one go.
+        let mut hits = Vec::new();
+        let mut text = Vec::new();
+        let mut cursor = self.cursor;
 
-        // If we hit the end of the buffer, and we know that there's something to find,
-        // start the search again from the beginning (= wrap around).
-        if wrap && hit.is_none() && search.next_search_offset != 0 {
-            search.next_search_offset = 0;
-            search.regex.reset(0);
-            hit = search.regex.next();
-        }
+        while let Some(hit) = search.regex.next() {
+            if hit.start >= end {
+                break;
+            }
 
-        search.selection_generation = if let Some(range) = hit {
-            // Now the search offset is no more at the start of the buffer.
-            search.next_search_offset = range.end;
+            let beg = text.len();
+            self.find_expand_replacement(search, replacement, &mut text);
+            hits.push((hit.clone(), beg..text.len()));
 
-            let beg = self.cursor_move_to_offset_internal(self.cursor, range.start);
-            let end = self.cursor_move_to_offset_internal(beg, range.end);
+            if !every {
+                cursor = self.cursor_move_to_offset_internal(cursor, hit.end);
+                let next = Point { x: 0, y: cursor.logical_pos.y + 1 };
+                let next = self.cursor_move_to_logical_internal(cursor, next);
+                if next.logical_pos.y == cursor.logical_pos.y {
+                    break;
+                }
+                search.regex.reset(next.offset);
+            }
+        }
 
-            unsafe { self.set_cursor(end) };
-            self.make_cursor_visible();
+        self.find_replace_hits(&hits, &text);
+        hits.len()
+    }
 
-            self.set_selection(Some(TextBufferSelection {
-                beg: beg.logical_pos,
-                end: end.logical_pos,
-            }))
-        } else {
-            // Avoid searching through the entire document again if we know there's nothing to find.
-            search.no_matches = true;
-            self.set_selection(None)
+    /// Replaces each of the sorted `hits` with its range of `text` as a single undo step.
+    /// The cursor ends up behind the last replacement.
+    fn find_replace_hits(&mut self, hits: &[(Range<usize>, Range<usize>)], text: &[u8]) {
+        let (Some(first), Some(last)) = (hits.first(), hits.last()) else {
+            return;
         };
-    }
+        if first.0.start == last.0.end && text.is_empty() {
+            return;
+        }
 
-    fn measurement_config(&self) -> MeasurementConfig {
-        MeasurementConfig::new(&self.buffer)
-            .with_word_wrap_column(self.word_wrap_column)
-            .with_tab_size(self.tab_size)
+        let mut replaced = Vec::new();
+        let mut off = first.0.start;
+        for (hit, replacement) in hits {
+            self.buffer.extract_raw(off, hit.start, &mut replaced, usize::MAX);
+            replaced.extend_from_slice(&text[replacement.clone()]);
+            off = hit.end;
+        }
+
+        self.set_selection(None);
+        let beg = self.cursor_move_to_offset_internal(self.cursor, first.0.start);
+        let end = self.cursor_move_to_offset_internal(beg, last.0.end);
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&replaced);
+        self.edit_end();
     }
 
-    fn goto_line_start(&self, cursor: Cursor, y: CoordType) -> Cursor {
-        let mut result = cursor;
-        let mut seek_to_line_start = true;
+    /// Appends the `replacement` for the last hit of `search` to `out`.
+    /// See [`TextBuffer::find_and_replace()`] for what it expands.
+    fn find_expand_replacement(
+        &self,
+        search: &mut ActiveSearch,
+        replacement: &[u8],
+        out: &mut Vec<u8>,
+    ) {
+        if !search.options.use_regex {
+            out.extend_from_slice(replacement);
+            return;
+        }
 
-        if y > result.logical_pos.y {
-            while y > result.logical_pos.y {
-                let chunk = self.read_forward(result.offset);
-                if chunk.is_empty() {
-                    break;
+        let mut i = 0;
+        while i < replacement.len() {
+            let c = replacement[i];
+            let next = replacement.get(i + 1).copied();
+            i += 1;
+
+            match (c, next) {
+                (b'\\', Some(next)) => {
+                    out.push(match next {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        _ => next,
+                    });
+                    i += 1;
                 }
-
-                let (delta, line) = unicode::newlines_forward(chunk, 0, result.logical_pos.y, y);
-                result.offset += delta;
-                result.logical_pos.y = line;
-            }
+                (b'$', Some(b'$')) => {
+                    out.push(b'$');
+                    i += 1;
+                }
+                (b'$', Some(b'0'..=b'9')) => {
+                    self.find_expand_group(search, (replacement[i] - b'0') as i32, out);
+                    i += 1;
+                }
+                (b'$', Some(b'{')) => {
+                    let digits = &replacement[i + 1..];
+                    let len = digits.iter().take_while(|c| c.is_ascii_digit()).count();
+                    if len > 0 && digits.get(len) == Some(&b'}') {
+                        let group = str::from_utf8(&digits[..len]).unwrap().parse().unwrap_or(-1);
+                        self.find_expand_group(search, group, out);
+                        i += len + 2;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+    }
+
+    /// Appends the text of the capture `group` of the last hit of `search` to `out`.
+    /// Groups that don't exist or didn't participate in the hit are empty.
+    fn find_expand_group(&self, search: &mut ActiveSearch, group: i32, out: &mut Vec<u8>) {
+        if let Some(range) = search.regex.group(group) {
+            self.buffer.extract_raw(range.start, range.end, out, usize::MAX);
+        }
+    }
+
+    /// Highlights all hits of the given `pattern` within the viewport during
+    /// [`TextBuffer::render()`]. An empty `pattern` disables the highlighting.
+    ///
+    /// This is cheap to call every frame: The search is only rebuilt when
+    /// the `pattern` or `options` change.
+    pub fn set_search_highlight(&mut self, pattern: &str, options: SearchOptions) {
+        if pattern.is_empty() {
+            self.search_highlight = None;
+            return;
+        }
+
+        if let Some(h) = &self.search_highlight
+            && h.pattern == pattern
+            && h.options == options
+        {
+            return;
+        }
+
+        self.search_highlight = Some(SearchHighlight {
+            pattern: pattern.to_string(),
+            options,
+            search: self.find_construct_search(pattern, options).ok(),
+        });
+    }
+
+    /// Collects the search hits that intersect the `beg..end` offset range.
+    fn search_highlight_hits(&mut self, beg: usize, end: usize) -> Vec<Range<usize>> {
+        let mut hits = Vec::new();
+        let generation = self.buffer.generation();
+        let Some(search) = self.search_highlight.as_mut().and_then(|h| h.search.as_mut()) else {
+            return hits;
+        };
+
+        if search.buffer_generation != generation {
+            unsafe { search.regex.set_text(&mut search.text, beg) };
+            search.buffer_generation = generation;
+        } else {
+            search.regex.reset(beg);
+        }
+
+        for hit in &mut search.regex {
+            if hit.start >= end {
+                break;
+            }
+            if !hit.is_empty() {
+                hits.push(hit);
+            }
+        }
+
+        hits
+    }
+
+    /// Labels all occurrences of `needle` within the viewport during [`TextBuffer::render()`].
+    /// The labels closest to the cursor are the shortest ones. `None` removes the labels.
+    ///
+    /// A lowercase `needle` matches case-insensitively.
+    pub fn set_jump_needle(&mut self, needle: Option<&str>) {
+        self.jump_needle = needle.filter(|n| !n.is_empty()).map(str::to_string);
+        self.jump_targets.clear();
+    }
+
+    /// Returns true if any of the labels drawn by the last [`TextBuffer::render()`] starts with `prefix`.
+    pub fn has_jump_label(&self, prefix: &str) -> bool {
+        self.jump_targets.iter().any(|t| t.label.starts_with(prefix))
+    }
+
+    /// Moves the cursor to the occurrence labeled `label`, removes the labels and returns true.
+    /// Returns false if there's no such label.
+    pub fn jump_to_label(&mut self, label: &str) -> bool {
+        let Some(target) = self.jump_targets.iter().find(|t| t.label == label) else {
+            return false;
+        };
+        self.cursor_move_to_offset(target.offset);
+        self.set_jump_needle(None);
+        true
+    }
+
+    /// Finds the occurrences of the jump needle that start within `beg..end`
+    /// and labels them by their distance to the cursor.
+    fn jump_targets_collect(&mut self, beg: usize, end: usize) {
+        self.jump_targets.clear();
+        let Some(needle) = &self.jump_needle else {
+            return;
+        };
+        let ignore_case = !needle.bytes().any(|b| b.is_ascii_uppercase());
+        let needle = needle.as_bytes();
+
+        let mut text = Vec::new();
+        let end = (end + needle.len()).min(self.text_length());
+        while beg + text.len() < end {
+            let chunk = self.read_forward(beg + text.len());
+            text.extend_from_slice(&chunk[..chunk.len().min(end - beg - text.len())]);
+        }
+
+        let mut offsets = Vec::new();
+        let mut i = 0;
+        while i + needle.len() <= text.len() {
+            let hay = &text[i..i + needle.len()];
+            if if ignore_case { hay.eq_ignore_ascii_case(needle) } else { hay == needle } {
+                offsets.push(beg + i);
+                i += needle.len();
+            } else {
+                i += 1;
+            }
+        }
+
+        let cursor = self.cursor.offset;
+        offsets.sort_by_key(|&off| off.abs_diff(cursor));
+        self.jump_targets = jump_labels(offsets.len())
+            .into_iter()
+            .zip(offsets)
+            .map(|(label, offset)| JumpTarget { label, offset })
+            .collect();
+    }
+
+    /// Moves the cursor to the partner of the HTML/XML tag the cursor is in and returns true.
+    /// Returns false if the cursor isn't in a tag or the tag has no partner.
+    pub fn jump_to_matching_tag(&mut self) -> bool {
+        let Some([_, partner]) = self.tag_pair() else {
+            return false;
+        };
+        self.cursor_move_to_offset(partner.start);
+        true
+    }
+
+    /// Returns the name ranges of the tag at the cursor and its partner. See [`matching_tag()`].
+    fn tag_pair(&mut self) -> Option<[Range<usize>; 2]> {
+        if self.text_length() > TAG_PAIR_MAX_LEN {
+            return None;
+        }
+
+        let key = (self.buffer.generation(), self.cursor.offset);
+        if self.tag_pair_key != Some(key) {
+            let mut text = Vec::new();
+            self.buffer.extract_raw(0, self.text_length(), &mut text, 0);
+            self.tag_pair = matching_tag(&text, self.cursor.offset);
+            self.tag_pair_key = Some(key);
+        }
+        self.tag_pair.clone()
+    }
+
+    fn find_construct_search(
+        &self,
+        pattern: &str,
+        options: SearchOptions,
+    ) -> apperr::Result<ActiveSearch> {
+        if pattern.is_empty() {
+            return Err(apperr::Error::Icu(1)); // U_ILLEGAL_ARGUMENT_ERROR
+        }
+
+        let sanitized_pattern = if options.whole_word && options.use_regex {
+            Cow::Owned(format!(r"\b(?:{pattern})\b"))
+        } else if options.whole_word {
+            let mut p = String::with_capacity(pattern.len() + 16);
+            p.push_str(r"\b");
+
+            // Escape regex special characters.
+            let b = unsafe { p.as_mut_vec() };
+            for &byte in pattern.as_bytes() {
+                match byte {
+                    b'*' | b'?' | b'+' | b'[' | b'(' | b')' | b'{' | b'}' | b'^' | b'$' | b'|'
+                    | b'\\' | b'.' => {
+                        b.push(b'\\');
+                        b.push(byte);
+                    }
+                    _ => b.push(byte),
+                }
+            }
+
+            p.push_str(r"\b");
+            Cow::Owned(p)
+        } else {
+            Cow::Borrowed(pattern)
+        };
+
+        let mut flags = icu::Regex::MULTILINE;
+        if !options.match_case {
+            flags |= icu::Regex::CASE_INSENSITIVE;
+        }
+        if !options.use_regex && !options.whole_word {
+            flags |= icu::Regex::LITERAL;
+        }
+
+        // Move the start of the search to the start of the selection,
+        // or otherwise to the current cursor position.
+
+        let text = unsafe { icu::Text::new(self)? };
+        let regex = unsafe { icu::Regex::new(&sanitized_pattern, flags, &text)? };
+
+        Ok(ActiveSearch {
+            pattern: pattern.to_string(),
+            options,
+            text,
+            regex,
+            buffer_generation: self.buffer.generation(),
+            selection_generation: 0,
+            next_search_offset: 0,
+            hit: None,
+            no_matches: false,
+        })
+    }
+
+    fn find_select_next(&mut self, search: &mut ActiveSearch, offset: usize, wrap: bool) {
+        if search.buffer_generation != self.buffer.generation() {
+            unsafe { search.regex.set_text(&mut search.text, offset) };
+            search.buffer_generation = self.buffer.generation();
+            search.next_search_offset = offset;
+        } else if search.next_search_offset != offset {
+            search.next_search_offset = offset;
+            search.regex.reset(offset);
+        }
+
+        let mut hit = search.regex.next();
+
+        // If we hit the end of the buffer, and we know that there's something to find,
+        // start the search again from the beginning (= wrap around).
+        if wrap && hit.is_none() && search.next_search_offset != 0 {
+            search.next_search_offset = 0;
+            search.regex.reset(0);
+            hit = search.regex.next();
+        }
+
+        search.hit = hit.clone();
+        search.selection_generation = if let Some(range) = hit {
+            // Now the search offset is no more at the start of the buffer.
+            search.next_search_offset = range.end;
+
+            let beg = self.cursor_move_to_offset_internal(self.cursor, range.start);
+            let end = self.cursor_move_to_offset_internal(beg, range.end);
+
+            unsafe { self.set_cursor(end) };
+            self.make_cursor_visible();
+
+            self.set_selection(Some(TextBufferSelection {
+                beg: beg.logical_pos,
+                end: end.logical_pos,
+            }))
+        } else {
+            // Avoid searching through the entire document again if we know there's nothing to find.
+            search.no_matches = true;
+            self.set_selection(None)
+        };
+    }
+
+    fn measurement_config(&self) -> MeasurementConfig<'_> {
+        MeasurementConfig::new(&self.buffer)
+            .with_word_wrap_column(self.word_wrap_column)
+            .with_tab_size(self.tab_size)
+    }
+
+    fn goto_line_start(&self, cursor: Cursor, y: CoordType) -> Cursor {
+        let mut result = cursor;
+        let mut seek_to_line_start = true;
+
+        if y > result.logical_pos.y {
+            while y > result.logical_pos.y {
+                let chunk = self.read_forward(result.offset);
+                if chunk.is_empty() {
+                    break;
+                }
+
+                let (delta, line) = unicode::newlines_forward(chunk, 0, result.logical_pos.y, y);
+                result.offset += delta;
+                result.logical_pos.y = line;
+            }
 
             // If we're at the end of the buffer, we could either be there because the last
             // character in the buffer is genuinely a newline, or because the buffer ends in a
@@ -1464,16 +2919,19 @@ impl TextBuffer {
                     }
                 }
             }
-            CursorMovement::Word => {
+            CursorMovement::Word | CursorMovement::SubWord => {
                 let doc = &self.buffer as &dyn ReadableDocument;
                 let mut offset = self.cursor.offset;
+                let subword =
+                    matches!(granularity, CursorMovement::SubWord) || self.subword_navigation;
 
                 while delta != 0 {
-                    if delta < 0 {
-                        offset = navigation::word_backward(doc, offset);
-                    } else {
-                        offset = navigation::word_forward(doc, offset);
-                    }
+                    offset = match (delta < 0, subword) {
+                        (true, false) => doc.word_prev(offset),
+                        (false, false) => doc.word_next(offset),
+                        (true, true) => navigation::subword_backward(doc, offset),
+                        (false, true) => navigation::subword_forward(doc, offset),
+                    };
                     delta -= sign;
                 }
 
@@ -1496,6 +2954,7 @@ impl TextBuffer {
 
     /// Moves the cursor to the given visual position.
     pub fn cursor_move_to_visual(&mut self, pos: Point) {
+        let pos = Point { x: pos.x, y: self.screen_to_row(pos.y) };
         unsafe { self.set_cursor(self.cursor_move_to_visual_internal(self.cursor, pos)) }
     }
 
@@ -1511,12 +2970,19 @@ impl TextBuffer {
     /// This function performs no checks that the cursor is valid. "Valid" in this case means
     /// that the TextBuffer has not been modified since you received the cursor from this class.
     pub unsafe fn set_cursor(&mut self, cursor: Cursor) {
+        if self.each_cursor.is_none() {
+            self.extra_cursors.clear();
+            self.auto_closers.clear();
+        }
         self.set_cursor_internal(cursor);
         self.last_history_type = HistoryType::Other;
         self.set_selection(None);
     }
 
     fn set_cursor_for_selection(&mut self, cursor: Cursor) {
+        if self.each_cursor.is_none() {
+            self.extra_cursors.clear();
+        }
         let beg = match self.selection {
             Some(TextBufferSelection { beg, .. }) => beg,
             None => self.cursor.logical_pos,
@@ -1541,16 +3007,27 @@ impl TextBuffer {
                 && cursor.visual_pos.y <= self.stats.visual_lines
         );
         self.cursor = cursor;
+
+        // Whatever moves the cursor into a fold, like a search, unfolds it.
+        if let Some(i) = self.folds.iter().position(|f| f.contains(&cursor.logical_pos.y)) {
+            self.folds.remove(i);
+            self.fold_rows_update();
+        }
     }
 
     /// Extracts a rectangular region of the text buffer and writes it to the framebuffer.
     /// The `destination` rect is framebuffer coordinates. The extracted region within this
     /// text buffer has the given `origin` and the same size as the `destination` rect.
+    ///
+    /// Measuring the lines that continue past the right edge means walking them to their end,
+    /// so [`RenderResult::line_width_max`] is only computed if `measure_lines` is set.
+    #[cfg(feature = "tui")]
     pub fn render(
         &mut self,
         origin: Point,
         destination: Rect,
         focused: bool,
+        measure_lines: bool,
         fb: &mut Framebuffer,
     ) -> Option<RenderResult> {
         if destination.is_empty() {
@@ -1560,18 +3037,28 @@ impl TextBuffer {
         let scratch = scratch_arena(None);
         let width = destination.width();
         let height = destination.height();
-        let line_number_width = self.margin_width.max(3) as usize - 3;
+        let margin_separator = self.margin_separator();
+        let separator_width = margin_separator.chars().count();
+        let line_number_width = (self.margin_width as usize).max(separator_width) - separator_width;
         let text_width = width - self.margin_width;
         let mut visualizer_buf = [0xE2, 0x90, 0x80]; // U+2400 in UTF8
         let mut line = ArenaString::new_in(&scratch);
         let mut visual_pos_x_max = 0;
+        let mut line_width_max = 0;
+        let mut wrapped_rows = Vec::new();
+        // The offsets into `line` of the whitespace glyphs drawn on the current row.
+        let mut whitespace_glyphs = Vec::new();
 
-        // Pick the cursor closer to the `origin.y`.
+        // The rows hidden by folds are skipped, so `origin.y` isn't a visual row as such.
+        let first_row = self.screen_to_row(origin.y);
+        let last_row = self.screen_to_row(origin.y + height - 1);
+
+        // Pick the cursor closer to the `first_row`.
         let mut cursor = {
             let a = self.cursor;
             let b = self.cursor_for_rendering.unwrap_or_default();
-            let da = (a.visual_pos.y - origin.y).abs();
-            let db = (b.visual_pos.y - origin.y).abs();
+            let da = (a.visual_pos.y - first_row).abs();
+            let db = (b.visual_pos.y - first_row).abs();
             if da < db { a } else { b }
         };
 
@@ -1579,13 +3066,50 @@ impl TextBuffer {
             None => [Point::MIN, Point::MIN],
             Some(TextBufferSelection { beg, end }) => minmax(beg, end),
         };
+        let mut selections = vec![[selection_beg, selection_end]];
+        selections.extend(
+            self.extra_cursors.iter().filter(|c| c.beg != c.end).map(|c| minmax(c.beg, c.end)),
+        );
+
+        let mut overlays = Vec::new();
+        let search_hits = if self.search_highlight.is_some() {
+            let beg = self.cursor_move_to_visual_internal(cursor, Point { x: 0, y: first_row });
+            let end = self
+                .cursor_move_to_visual_internal(beg, Point { x: CoordType::MAX, y: last_row });
+            self.search_highlight_hits(beg.offset, end.offset)
+        } else {
+            Vec::new()
+        };
+
+        if self.jump_needle.is_some() {
+            let beg = self.cursor_move_to_visual_internal(cursor, Point { x: 0, y: first_row });
+            let end = self
+                .cursor_move_to_visual_internal(beg, Point { x: CoordType::MAX, y: last_row });
+            self.jump_targets_collect(beg.offset, end.offset);
+        }
+
+        // In markup the tag at the cursor and its partner get highlighted.
+        let tag_pair = if focused && self.current_file_type.has_tags() {
+            self.tag_pair()
+        } else {
+            None
+        };
+
+        // The hit selected by the last find operation gets emphasized.
+        let current_hit = self.find_current_hit();
+
+        // Foldable lines get a chevron in the margin.
+        if line_number_width != 0 {
+            self.fold_regions_update();
+        }
 
         line.reserve(width as usize * 2);
 
         for y in 0..height {
             line.clear();
+            whitespace_glyphs.clear();
 
-            let visual_line = origin.y + y;
+            let visual_line = self.screen_to_row(origin.y + y);
             let mut cursor_beg =
                 self.cursor_move_to_visual_internal(cursor, Point { x: origin.x, y: visual_line });
             let cursor_end = self.cursor_move_to_visual_internal(
@@ -1603,22 +3127,64 @@ impl TextBuffer {
                     // Past the end of the buffer? Place "    | " in the margin.
                     // Since we know that we won't see line numbers greater than i64::MAX (9223372036854775807)
                     // any time soon, we can use a static string as the template (`MARGIN`) and slice it,
-                    // because `line_number_width` can't possibly be larger than 19.
-                    let off = 19 - line_number_width;
-                    unsafe { std::hint::assert_unchecked(off < MARGIN_TEMPLATE.len()) };
-                    line.push_str(&MARGIN_TEMPLATE[off..]);
+                    // because `line_number_width` can't be larger than 20 (19 digits plus padding).
+                    let off = 20 - line_number_width;
+                    unsafe { std::hint::assert_unchecked(off <= 20) };
+                    line.push_str(&MARGIN_TEMPLATE[off..20]);
+                    line.push_str(margin_separator);
                 } else if self.word_wrap_column <= 0 || cursor_beg.logical_pos.x == 0 {
                     // Regular line? Place "123 | " in the margin.
-                    _ = write!(line, "{:1$} │ ", cursor_beg.logical_pos.y + 1, line_number_width);
+                    let logical_y = cursor_beg.logical_pos.y;
+                    _ = write!(line, "{:1$}", logical_y + 1, line_number_width);
+
+                    // Foldable ones get a "▾" in the separator, and folded ones a "▸".
+                    let folded = self.folds.binary_search_by_key(&(logical_y + 1), |f| f.start);
+                    let foldable = self.fold_regions.binary_search_by_key(&logical_y, |r| r.start);
+                    let chevron = if folded.is_ok() {
+                        Some('▸')
+                    } else if foldable.is_ok() {
+                        Some('▾')
+                    } else {
+                        None
+                    };
+                    if let Some(chevron) = chevron {
+                        let mut rest = margin_separator.chars();
+                        rest.next();
+                        line.push(chevron);
+                        line.push_str(rest.as_str());
+                    } else {
+                        line.push_str(margin_separator);
+                    }
+
+                    // Lines with diagnostics get their line number tinted by the worst severity.
+                    if let Some(severity) = self.diagnostics.severity_on_line(cursor_beg.logical_pos.y) {
+                        let color = match severity {
+                            Severity::Error => IndexedColor::Red,
+                            Severity::Warning => IndexedColor::Yellow,
+                            Severity::Info => IndexedColor::Blue,
+                        };
+                        let left = destination.left;
+                        let top = destination.top + y;
+                        fb.blend_bg(
+                            Rect {
+                                left,
+                                top,
+                                right: left + line_number_width as CoordType,
+                                bottom: top + 1,
+                            },
+                            fb.indexed_alpha(color, 1, 2),
+                        );
+                    }
                 } else {
                     // Wrapped line? Place " ... | " in the margin.
                     let number_width = (cursor_beg.logical_pos.y + 1).ilog10() as usize + 1;
                     _ = write!(
                         line,
-                        "{0:1$}{0:∙<2$} │ ",
+                        "{0:1$}{0:∙<2$}{3}",
                         "",
                         line_number_width - number_width,
-                        number_width
+                        number_width,
+                        margin_separator
                     );
                     // Blending in the background color will "dim" the indicator dots.
                     let left = destination.left;
@@ -1635,6 +3201,8 @@ impl TextBuffer {
                 }
             }
 
+            let text_beg = line.len();
+
             // Nothing to do if the entire line is empty.
             if cursor_beg.offset != cursor_end.offset {
                 // If we couldn't reach the left edge, we may have stopped short due to a wide glyph.
@@ -1661,6 +3229,17 @@ impl TextBuffer {
                     offset
                 }
 
+                // Which whitespace is drawn visibly, by its offset.
+                let content = match self.render_whitespace {
+                    RenderWhitespace::Boundary => self.line_content_range(cursor_beg),
+                    _ => 0..0,
+                };
+                let visible = |off: usize| match self.render_whitespace {
+                    RenderWhitespace::None => false,
+                    RenderWhitespace::Boundary => !content.contains(&off),
+                    RenderWhitespace::All => true,
+                };
+
                 let mut global_off = cursor_beg.offset;
                 let mut cursor_tab = cursor_beg;
 
@@ -1673,13 +3252,30 @@ impl TextBuffer {
                         let beg = chunk_off;
                         chunk_off = find_control_char(chunk, beg);
 
+                        let mut off = global_off + beg;
                         for chunk in chunk[beg..chunk_off].utf8_chunks() {
-                            if !chunk.valid().is_empty() {
-                                line.push_str(chunk.valid());
+                            let valid = chunk.valid();
+                            if self.render_whitespace == RenderWhitespace::None {
+                                line.push_str(valid);
+                            } else {
+                                for (i, c) in valid.char_indices() {
+                                    let glyph = match c {
+                                        ' ' => '·',
+                                        '\u{A0}' => '⍽',
+                                        _ => c,
+                                    };
+                                    if glyph != c && visible(off + i) {
+                                        whitespace_glyphs.push(line.len());
+                                        line.push(glyph);
+                                    } else {
+                                        line.push(c);
+                                    }
+                                }
                             }
                             if !chunk.invalid().is_empty() {
                                 line.push('\u{FFFD}');
                             }
+                            off += valid.len() + chunk.invalid().len();
                         }
 
                         while chunk_off < chunk.len()
@@ -1694,12 +3290,24 @@ impl TextBuffer {
                                     global_off + chunk_off - 1,
                                 );
                                 let tab_size = self.tab_size - (cursor_tab.column % self.tab_size);
-                                line.push_str(&TAB_WHITESPACE[..tab_size as usize]);
+                                let mut tab_size = tab_size as usize;
+                                let mut tab_off = global_off + chunk_off - 1;
 
                                 // Since we know that we just aligned ourselves to the next tab stop,
                                 // we can trivially process any successive tabs.
-                                while chunk_off < chunk.len() && chunk[chunk_off] == b'\t' {
-                                    line.push_str(&TAB_WHITESPACE[..self.tab_size as usize]);
+                                loop {
+                                    if visible(tab_off) {
+                                        whitespace_glyphs.push(line.len());
+                                        line.push('→');
+                                        line.push_str(&TAB_WHITESPACE[..tab_size - 1]);
+                                    } else {
+                                        line.push_str(&TAB_WHITESPACE[..tab_size]);
+                                    }
+                                    if chunk_off >= chunk.len() || chunk[chunk_off] != b'\t' {
+                                        break;
+                                    }
+                                    tab_size = self.tab_size as usize;
+                                    tab_off = global_off + chunk_off;
                                     chunk_off += 1;
                                 }
                                 continue;
@@ -1721,53 +3329,388 @@ impl TextBuffer {
                 visual_pos_x_max = visual_pos_x_max.max(cursor_end.visual_pos.x);
             }
 
+            // The last row of a folded header is followed by a summary of what's hidden.
+            if let Some(i) = self.fold_rows.iter().position(|r| r.start == visual_line + 1)
+                && cursor_end.visual_pos.x >= origin.x
+                && let None | Some(b'\n' | b'\r') = self.read_forward(cursor_end.offset).first()
+            {
+                let summary = self.fold_summary(self.folds[i].clone());
+                let beg = cursor_end.visual_pos.x - origin.x;
+                let end = (beg + summary.chars().count() as CoordType).min(text_width);
+                line.push_str(&summary);
+                if beg < end {
+                    let kind = OverlayKind::Dimmed;
+                    overlays.push(OverlaySpan { line: y, columns: beg..end, kind });
+                }
+            } else if self.render_whitespace == RenderWhitespace::All
+                && cursor_end.visual_pos.x >= origin.x
+                && let Some(&c @ (b'\n' | b'\r')) = self.read_forward(cursor_end.offset).first()
+            {
+                whitespace_glyphs.push(line.len());
+                line.push(if c == b'\r' { '¤' } else { '¬' });
+            }
+
+            // Dim the whitespace glyphs, merging those next to each other.
+            if !whitespace_glyphs.is_empty() {
+                let text = &line.as_bytes()[text_beg..];
+                let mut cfg = MeasurementConfig::new(&text);
+                let mut columns: Option<Range<CoordType>> = None;
+                for &off in &whitespace_glyphs {
+                    let x = cfg.goto_offset(off - text_beg).visual_pos.x;
+                    if x >= text_width {
+                        break;
+                    }
+                    match &mut columns {
+                        Some(c) if c.end == x => c.end = x + 1,
+                        _ => {
+                            if let Some(c) = columns.replace(x..x + 1) {
+                                let kind = OverlayKind::Whitespace;
+                                overlays.push(OverlaySpan { line: y, columns: c, kind });
+                            }
+                        }
+                    }
+                }
+                if let Some(c) = columns {
+                    let kind = OverlayKind::Whitespace;
+                    overlays.push(OverlaySpan { line: y, columns: c, kind });
+                }
+            }
+
             fb.replace_text(destination.top + y, destination.left, destination.right, &line);
 
-            // Draw the selection on this line, if any.
-            // FYI: `cursor_beg.visual_pos.y == visual_line` is necessary as the `visual_line`
-            // may be past the end of the document, and so it may not receive a highlight.
-            if cursor_beg.visual_pos.y == visual_line
-                && selection_beg <= cursor_end.logical_pos
-                && selection_end >= cursor_beg.logical_pos
-            {
-                // By default, we assume the entire line is selected.
-                let mut beg = 0;
-                let mut end = COORD_TYPE_SAFE_MAX;
-                let mut cursor = cursor_beg;
+            if self.word_wrap_column <= 0 && visual_line < self.stats.visual_lines {
+                let continues = self
+                    .read_forward(cursor_end.offset)
+                    .first()
+                    .is_some_and(|&c| c != b'\n' && c != b'\r');
+                let line_width = if continues && measure_lines {
+                    let y = cursor_end.logical_pos.y;
+                    let end = self.cursor_move_to_logical_internal(
+                        cursor_end,
+                        Point { x: CoordType::MAX, y },
+                    );
+                    end.visual_pos.x
+                } else {
+                    cursor_end.visual_pos.x
+                };
+                line_width_max = line_width_max.max(line_width);
 
-                // The start of the selection is within this line. We need to update selection_beg.
-                if selection_beg <= cursor_end.logical_pos
-                    && selection_beg >= cursor_beg.logical_pos
-                {
-                    cursor = self.cursor_move_to_logical_internal(cursor, selection_beg);
-                    beg = cursor.visual_pos.x;
+                if self.scroll_indicators_enabled && text_width > 1 {
+                    let left = destination.left + self.margin_width;
+                    let top = destination.top + y;
+                    let mut indicate = |column: CoordType| {
+                        fb.replace_text(top, left + column, left + column + 1, "…");
+                        overlays.push(OverlaySpan {
+                            line: y,
+                            columns: column..column + 1,
+                            kind: OverlayKind::Dimmed,
+                        });
+                    };
+                    if origin.x > 0 && cursor_beg.logical_pos.x > 0 {
+                        indicate(0);
+                    }
+                    if continues {
+                        indicate(text_width - 1);
+                    }
                 }
+            } else if self.word_wrap_column > 0
+                && visual_line < self.stats.visual_lines
+                && let Some(&c) = self.read_forward(cursor_end.offset).first()
+                && c != b'\n'
+                && c != b'\r'
+            {
+                wrapped_rows.push((y, cursor_end.visual_pos.x - origin.x));
 
-                // The end of the selection is within this line. We need to update selection_end.
-                if selection_end <= cursor_end.logical_pos
-                    && selection_end >= cursor_beg.logical_pos
-                {
-                    cursor = self.cursor_move_to_logical_internal(cursor, selection_end);
-                    end = cursor.visual_pos.x;
+                if self.scroll_indicators_enabled && self.word_wrap_column < text_width {
+                    let left = destination.left + self.margin_width + self.word_wrap_column;
+                    let top = destination.top + y;
+                    let rect = Rect { left, top, right: left + 1, bottom: top + 1 };
+                    fb.replace_text(top, left, left + 1, "↩");
+                    fb.blend_fg(rect, fb.indexed_alpha(IndexedColor::Background, 1, 2));
                 }
+            }
 
-                beg = beg.max(origin.x);
-                end = end.min(origin.x + text_width);
+            if self.current_file_type == FileType::StackTrace
+                && cursor_beg.visual_pos.y == visual_line
+                && self.is_external_frame(cursor_beg)
+            {
+                overlays.push(OverlaySpan {
+                    line: y,
+                    columns: 0..text_width,
+                    kind: OverlayKind::Dimmed,
+                });
+            }
 
+            if self.commit_message_mode && cursor_beg.visual_pos.y == visual_line {
                 let left = destination.left + self.margin_width - origin.x;
                 let top = destination.top + y;
-                let rect = Rect { left: left + beg, top, right: left + end, bottom: top + 1 };
-
-                let mut bg = oklab_blend(
-                    fb.indexed(IndexedColor::Foreground),
-                    fb.indexed_alpha(IndexedColor::BrightBlue, 1, 2),
-                );
-                if !focused {
-                    bg = oklab_blend(bg, fb.indexed_alpha(IndexedColor::Background, 1, 2))
-                };
-                let fg = fb.contrasted(bg);
-                fb.blend_bg(rect, bg);
-                fb.blend_fg(rect, fg);
+                let line_start = self.goto_line_start(cursor_beg, cursor_beg.logical_pos.y);
+
+                if self.read_forward(line_start.offset).first() == Some(&b'#') {
+                    overlays.push(OverlaySpan {
+                        line: y,
+                        columns: 0..text_width,
+                        kind: OverlayKind::Dimmed,
+                    });
+                } else if cursor_beg.logical_pos.y == 1 {
+                    // The summary must be followed by a blank line.
+                    if cursor_beg.offset != cursor_end.offset {
+                        fb.blend_bg(
+                            Rect { left: left + origin.x, top, right: destination.right, bottom: top + 1 },
+                            fb.indexed_alpha(IndexedColor::BrightRed, 1, 4),
+                        );
+                    }
+                } else {
+                    // The summary should fit into 50 columns and the lines of the body into 72.
+                    let y = cursor_beg.logical_pos.y;
+                    let limit = if y == 0 { COMMIT_SUMMARY_LIMIT } else { COMMIT_BODY_LIMIT };
+                    let guide =
+                        self.cursor_move_to_logical_internal(cursor_beg, Point { x: limit, y });
+                    let beg = if guide.visual_pos.y < visual_line { 0 } else { guide.visual_pos.x };
+                    let beg = beg.max(origin.x);
+                    if guide.visual_pos.y <= visual_line && beg < cursor_end.visual_pos.x {
+                        fb.blend_bg(
+                            Rect { left: left + beg, top, right: left + cursor_end.visual_pos.x, bottom: top + 1 },
+                            fb.indexed_alpha(IndexedColor::BrightYellow, 1, 4),
+                        );
+                    }
+                }
+            }
+
+            // Highlight the characters that go past the ruler.
+            if self.ruler > 0
+                && cursor_beg.visual_pos.y == visual_line
+                && cursor_end.visual_pos.x > self.ruler
+            {
+                let left = destination.left + self.margin_width - origin.x;
+                let top = destination.top + y;
+                let beg = self.ruler.max(origin.x);
+                fb.blend_bg(
+                    Rect { left: left + beg, top, right: left + cursor_end.visual_pos.x, bottom: top + 1 },
+                    fb.indexed_alpha(IndexedColor::BrightRed, 1, 3),
+                );
+            }
+
+            if self.whitespace_lint && cursor_beg.visual_pos.y == visual_line {
+                let line_start = self.goto_line_start(cursor_beg, cursor_beg.logical_pos.y);
+                let mut text = Vec::new();
+                loop {
+                    let chunk = self.read_forward(line_start.offset + text.len());
+                    if let Some(end) = chunk.iter().position(|&c| c == b'\n' || c == b'\r') {
+                        text.extend_from_slice(&chunk[..end]);
+                        break;
+                    }
+                    if chunk.is_empty() {
+                        break;
+                    }
+                    text.extend_from_slice(chunk);
+                }
+                let style = self.indent_style();
+
+                for issue in diagnostics::whitespace_issues(&text, style) {
+                    // Whitespace that is still being typed isn't worth a warning.
+                    if issue.kind == diagnostics::WhitespaceIssueKind::Trailing
+                        && focused
+                        && self.cursor.logical_pos.y == cursor_beg.logical_pos.y
+                    {
+                        continue;
+                    }
+
+                    let beg = line_start.offset + issue.range.start;
+                    let end = line_start.offset + issue.range.end;
+                    if end <= cursor_beg.offset || beg >= cursor_end.offset {
+                        continue;
+                    }
+                    let beg = self.cursor_move_to_offset_internal(cursor_beg, beg.max(cursor_beg.offset));
+                    let end = self.cursor_move_to_offset_internal(beg, end.min(cursor_end.offset));
+                    let left = destination.left + self.margin_width - origin.x;
+                    let top = destination.top + y;
+                    let right = (left + end.visual_pos.x).min(destination.right);
+                    let beg_x = (left + beg.visual_pos.x).max(destination.left + self.margin_width);
+                    if beg_x < right {
+                        fb.blend_bg(
+                            Rect { left: beg_x, top, right, bottom: top + 1 },
+                            fb.indexed_alpha(IndexedColor::BrightYellow, 1, 3),
+                        );
+                    }
+                }
+            }
+
+            // Collect the search hits on this line, if any.
+            if cursor_beg.visual_pos.y == visual_line {
+                for hit in &search_hits {
+                    if hit.end <= cursor_beg.offset || hit.start >= cursor_end.offset {
+                        continue;
+                    }
+
+                    let beg = self.cursor_move_to_offset_internal(
+                        cursor_beg,
+                        hit.start.max(cursor_beg.offset),
+                    );
+                    let end =
+                        self.cursor_move_to_offset_internal(beg, hit.end.min(cursor_end.offset));
+                    let kind = if current_hit.as_ref() == Some(hit) {
+                        OverlayKind::CurrentSearchMatch
+                    } else {
+                        OverlayKind::SearchMatch
+                    };
+
+                    overlays.push(OverlaySpan {
+                        line: y,
+                        columns: (beg.visual_pos.x - origin.x).max(0)
+                            ..(end.visual_pos.x - origin.x).min(text_width),
+                        kind,
+                    });
+                }
+            }
+
+            // Collect the escape sequence colors on this line, if any.
+            if cursor_beg.visual_pos.y == visual_line
+                && let Some(view) = &self.ansi_view
+            {
+                let first = view.spans.partition_point(|(r, _)| r.end <= cursor_beg.offset);
+                for (range, style) in &view.spans[first..] {
+                    if range.start >= cursor_end.offset {
+                        break;
+                    }
+
+                    let beg = self.cursor_move_to_offset_internal(
+                        cursor_beg,
+                        range.start.max(cursor_beg.offset),
+                    );
+                    let end =
+                        self.cursor_move_to_offset_internal(beg, range.end.min(cursor_end.offset));
+
+                    overlays.push(OverlaySpan {
+                        line: y,
+                        columns: (beg.visual_pos.x - origin.x).max(0)
+                            ..(end.visual_pos.x - origin.x).min(text_width),
+                        kind: OverlayKind::Styled(*style),
+                    });
+                }
+            }
+
+            // Collect the tag pair names on this line, if any.
+            if cursor_beg.visual_pos.y == visual_line {
+                for name in tag_pair.iter().flatten() {
+                    if name.start < cursor_beg.offset || name.end > cursor_end.offset {
+                        continue;
+                    }
+
+                    let beg = self.cursor_move_to_offset_internal(cursor_beg, name.start);
+                    let end = self.cursor_move_to_offset_internal(beg, name.end);
+                    overlays.push(OverlaySpan {
+                        line: y,
+                        columns: (beg.visual_pos.x - origin.x).max(0)
+                            ..(end.visual_pos.x - origin.x).min(text_width),
+                        kind: OverlayKind::MatchingTag,
+                    });
+                }
+            }
+
+            // Draw the jump labels on top of the text they point to.
+            if cursor_beg.visual_pos.y == visual_line {
+                for i in 0..self.jump_targets.len() {
+                    let offset = self.jump_targets[i].offset;
+                    if offset < cursor_beg.offset || offset >= cursor_end.offset {
+                        continue;
+                    }
+
+                    let pos = self.cursor_move_to_offset_internal(cursor_beg, offset);
+                    let label = &self.jump_targets[i].label;
+                    let beg = pos.visual_pos.x - origin.x;
+                    let end = (beg + label.len() as CoordType).min(text_width);
+                    if beg < 0 || beg >= end {
+                        continue;
+                    }
+
+                    let left = destination.left + self.margin_width;
+                    fb.replace_text(destination.top + y, left + beg, left + end, label);
+                    overlays.push(OverlaySpan { line: y, columns: beg..end, kind: OverlayKind::JumpLabel });
+                }
+            }
+
+            // Extra cursors are drawn as blocks, because the terminal only shows one.
+            if focused && cursor_beg.visual_pos.y == visual_line {
+                for extra in &self.extra_cursors {
+                    if extra.end.y != cursor_beg.logical_pos.y {
+                        continue;
+                    }
+                    let pos = self.cursor_move_to_logical_internal(cursor_beg, extra.end);
+                    let x = pos.visual_pos.x - origin.x;
+                    if pos.visual_pos.y != visual_line || x < 0 || x >= text_width {
+                        continue;
+                    }
+
+                    let left = destination.left + self.margin_width + x;
+                    let top = destination.top + y;
+                    let rect = Rect { left, top, right: left + 1, bottom: top + 1 };
+                    let bg = fb.indexed(IndexedColor::Foreground);
+                    fb.blend_bg(rect, bg);
+                    fb.blend_fg(rect, fb.contrasted(bg));
+                    let kind = OverlayKind::Caret;
+                    overlays.push(OverlaySpan { line: y, columns: x..x + 1, kind });
+                }
+            }
+
+            // Draw the selections on this line, if any.
+            // FYI: `cursor_beg.visual_pos.y == visual_line` is necessary as the `visual_line`
+            // may be past the end of the document, and so it may not receive a highlight.
+            for &[selection_beg, selection_end] in &selections {
+                if cursor_beg.visual_pos.y == visual_line
+                    && selection_beg <= cursor_end.logical_pos
+                    && selection_end >= cursor_beg.logical_pos
+                {
+                    // By default, we assume the entire line is selected.
+                    let mut beg = 0;
+                    let mut end = COORD_TYPE_SAFE_MAX;
+                    let mut cursor = cursor_beg;
+
+                    // The start of the selection is within this line.
+                    // We need to update selection_beg.
+                    if selection_beg <= cursor_end.logical_pos
+                        && selection_beg >= cursor_beg.logical_pos
+                    {
+                        cursor = self.cursor_move_to_logical_internal(cursor, selection_beg);
+                        beg = cursor.visual_pos.x;
+                    }
+
+                    // The end of the selection is within this line.
+                    // We need to update selection_end.
+                    if selection_end <= cursor_end.logical_pos
+                        && selection_end >= cursor_beg.logical_pos
+                    {
+                        cursor = self.cursor_move_to_logical_internal(cursor, selection_end);
+                        end = cursor.visual_pos.x;
+                    }
+
+                    beg = beg.max(origin.x);
+                    end = end.min(origin.x + text_width);
+
+                    // Let the caller re-tint the selection without losing the syntax colors.
+                    if beg < end {
+                        overlays.push(OverlaySpan {
+                            line: y,
+                            columns: beg - origin.x..end - origin.x,
+                            kind: OverlayKind::Selection,
+                        });
+                    }
+
+                    let left = destination.left + self.margin_width - origin.x;
+                    let top = destination.top + y;
+                    let rect = Rect { left: left + beg, top, right: left + end, bottom: top + 1 };
+
+                    let mut bg = oklab_blend(
+                        fb.indexed(IndexedColor::Foreground),
+                        fb.indexed_alpha(IndexedColor::BrightBlue, 1, 2),
+                    );
+                    if !focused {
+                        bg = oklab_blend(bg, fb.indexed_alpha(IndexedColor::Background, 1, 2))
+                    };
+                    let fg = fb.contrasted(bg);
+                    fb.blend_bg(rect, bg);
+                    fb.blend_fg(rect, fg);
+                }
             }
 
             cursor = cursor_end;
@@ -1784,13 +3727,13 @@ impl TextBuffer {
             fb.blend_fg(margin, 0x7f3f3f3f);
         }
 
-        if self.ruler > 0 {
-            let left = destination.left + self.margin_width + (self.ruler - origin.x).max(0);
-            let right = destination.right;
-            if left < right {
+        // The ruler is drawn as a single column just past the limit.
+        if self.ruler > 0 && self.ruler >= origin.x {
+            let left = destination.left + self.margin_width + self.ruler - origin.x;
+            if left < destination.right {
                 fb.blend_bg(
-                    Rect { left, top: destination.top, right, bottom: destination.bottom },
-                    fb.indexed_alpha(IndexedColor::BrightRed, 1, 4),
+                    Rect { left, top: destination.top, right: left + 1, bottom: destination.bottom },
+                    fb.indexed_alpha(IndexedColor::Foreground, 1, 8),
                 );
             }
         }
@@ -1805,6 +3748,7 @@ impl TextBuffer {
                 x = 0;
                 y += 1;
             }
+            y = self.row_to_screen(y);
 
             // Move the cursor into screen space.
             x += destination.left - origin.x + self.margin_width;
@@ -1835,7 +3779,65 @@ impl TextBuffer {
             }
         }
 
-        Some(RenderResult { visual_pos_x_max })
+        Some(RenderResult { visual_pos_x_max, line_width_max, overlays, wrapped_rows })
+    }
+
+    /// Whether the logical line at `cursor` is a stack frame outside of the user's code.
+    fn is_external_frame(&self, cursor: Cursor) -> bool {
+        let line_start = self.goto_line_start(cursor, cursor.logical_pos.y);
+        let line = self.get_line_content(line_start);
+        stack_trace::parse_frame(&line).is_some_and(|frame| frame.external)
+    }
+
+    /// The offsets of the line at `cursor` between its indentation and trailing whitespace.
+    /// Empty and at the start of the line if it's blank.
+    fn line_content_range(&self, cursor: Cursor) -> Range<usize> {
+        let y = cursor.logical_pos.y;
+        let beg = self.goto_line_start(cursor, y).offset;
+        let end = self.cursor_move_to_logical_internal(cursor, Point { x: CoordType::MAX, y });
+        let end = end.offset;
+        let is_blank = |c: &&u8| matches!(c, b' ' | b'\t');
+
+        let mut content_beg = beg;
+        while content_beg < end {
+            let chunk = self.read_forward(content_beg);
+            let chunk = &chunk[..chunk.len().min(end - content_beg)];
+            let blank = chunk.iter().take_while(is_blank).count();
+            content_beg += blank;
+            if chunk.is_empty() || blank < chunk.len() {
+                break;
+            }
+        }
+
+        let mut content_end = end;
+        while content_end > content_beg {
+            let chunk = self.read_backward(content_end);
+            let chunk = &chunk[chunk.len().saturating_sub(content_end - content_beg)..];
+            let blank = chunk.iter().rev().take_while(is_blank).count();
+            content_end -= blank;
+            if chunk.is_empty() || blank < chunk.len() {
+                break;
+            }
+        }
+
+        content_beg..content_end
+    }
+
+    /// The text shown after the header of a fold, like `…} (12 lines)`.
+    /// The closing line is included if it's indented like the header.
+    fn fold_summary(&self, fold: Range<CoordType>) -> String {
+        let lines = self.lines_text(fold.start - 1..fold.end);
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let mut summary = String::from("…");
+        if let (Some(header), Some(last)) = (lines.first(), lines.last())
+            && lines.len() > 2
+            && indent(header) == indent(last)
+        {
+            summary.push_str(last.trim());
+        }
+        let count = fold.end - fold.start;
+        _ = write!(summary, " ({count} {})", if count == 1 { "line" } else { "lines" });
+        summary
     }
 
     /// Inserts `text` at the current cursor position.
@@ -1843,7 +3845,37 @@ impl TextBuffer {
     /// If there's a current selection, it will be replaced.
     /// The selection is cleared after the call.
     pub fn write(&mut self, text: &[u8], raw: bool) {
-        if text.is_empty() {
+        if text.is_empty() || self.read_only {
+            return;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            // As many lines as there are cursors, like a copied block, go one to each.
+            // Bracketed paste uses CR as the newline, which is why it's split on both.
+            let mut lines = Vec::new();
+            let mut rest = text;
+            while let Some(i) = rest.iter().position(|&b| b == b'\r' || b == b'\n') {
+                lines.push(&rest[..i]);
+                let crlf = rest[i] == b'\r' && rest.get(i + 1) == Some(&b'\n');
+                rest = &rest[i + 1 + crlf as usize..];
+            }
+            if !rest.is_empty() {
+                lines.push(rest);
+            }
+            if lines.len() == self.extra_cursors.len() + 1 {
+                // The last cursor goes first.
+                let mut lines = lines.into_iter().rev();
+                self.for_each_cursor(|tb| tb.write(lines.next().unwrap_or_default(), raw));
+            } else {
+                self.for_each_cursor(|tb| tb.write(text, raw));
+            }
+            return;
+        }
+        if !raw
+            && self.auto_pairs
+            && !self.overtype
+            && let [c] = *text
+            && self.write_auto_pair(c)
+        {
             return;
         }
 
@@ -1951,10 +3983,105 @@ impl TextBuffer {
         {
             let cursor = self.cursor;
             self.edit_write(if self.newlines_are_crlf { b"\r\n" } else { b"\n" });
-            self.set_cursor_internal(cursor);
+            // Not `set_cursor_internal`, because the line stats are only updated by `edit_end`.
+            self.cursor = cursor;
+        }
+
+        self.edit_end();
+
+        if !raw
+            && self.smart_indent_enabled
+            && self.should_use_smart_indent()
+            && matches!(text, b"}" | b":" | b">")
+        {
+            self.auto_dedent_current_line();
+        }
+    }
+
+    /// Types `c` if it's one of the [`FileType::auto_pairs()`] and there's something special
+    /// to do about it. Returns `false` if it should be written like any other character.
+    fn write_auto_pair(&mut self, c: u8) -> bool {
+        let pairs = self.current_file_type.auto_pairs();
+        let Some(&(open, close)) = pairs.iter().find(|&&(o, cl)| c == o || c == cl) else {
+            return false;
+        };
+        let next = self.read_forward(self.cursor.offset).first().copied();
+
+        // Typing the closer that was inserted along with the opener steps over it,
+        // unless it's a quote that's escaped and so belongs inside the string.
+        if c == close
+            && self.selection.is_none()
+            && next == Some(close)
+            && (open != close || self.read_backward(self.cursor.offset).last() != Some(&b'\\'))
+            && let Some(i) = self.auto_closers.iter().position(|&off| off == self.cursor.offset)
+        {
+            self.auto_closers.remove(i);
+            self.set_cursor_internal(
+                self.cursor_move_to_offset_internal(self.cursor, self.cursor.offset + 1),
+            );
+            self.last_history_type = HistoryType::Other;
+            return true;
+        }
+        if c != open {
+            return false;
+        }
+
+        // With a selection the opener and closer go around it, which stays selected.
+        if let Some((beg, end)) = self.selection_range_internal(false) {
+            let range = beg.offset..end.offset;
+            self.replace_around(range.clone(), range, &[open], &[close]);
+            return true;
+        }
+
+        // Only pair up in front of whitespace or a closer, so that typing
+        // an opener right before a word doesn't leave a stray closer behind.
+        let pairs_before = |b: u8| {
+            b.is_ascii_whitespace()
+                || matches!(b, b';' | b',' | b':')
+                || pairs.iter().any(|&(o, cl)| b == cl && o != cl)
+        };
+        if !next.is_none_or(pairs_before) {
+            return false;
+        }
+        if open == close && !self.quote_opens_string(open) {
+            return false;
         }
 
+        self.edit_begin(HistoryType::Write, self.cursor);
+        self.edit_write(&[open, close]);
         self.edit_end();
+
+        let cursor = self.cursor_move_to_offset_internal(self.cursor, self.cursor.offset - 1);
+        self.set_cursor_internal(cursor);
+        self.auto_closers.push(cursor.offset);
+        self.last_history_type = HistoryType::Other;
+        true
+    }
+
+    /// Whether typing `quote` at the cursor starts a string, as opposed to ending one,
+    /// being escaped, or being an apostrophe like in "don't".
+    fn quote_opens_string(&self, quote: u8) -> bool {
+        let line_start = self.goto_line_start(self.cursor, self.cursor.logical_pos.y);
+        let mut line = Vec::new();
+        self.buffer.extract_raw(line_start.offset, self.cursor.offset, &mut line, 0);
+
+        if line.last().is_some_and(|&b| b.is_ascii_alphanumeric() || b == b'_' || b == b'\\') {
+            return false;
+        }
+
+        // An even number of unescaped quotes before the cursor means it's not inside a string.
+        let mut inside = false;
+        let mut escaped = false;
+        for &b in &line {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == quote {
+                inside = !inside;
+            }
+        }
+        !inside
     }
 
     /// Deletes 1 grapheme cluster from the buffer.
@@ -1964,6 +4091,13 @@ impl TextBuffer {
     /// Deletes characters from the buffer based on a delta from the cursor.
     pub fn delete(&mut self, granularity: CursorMovement, delta: CoordType) {
         debug_assert!(delta == -1 || delta == 1);
+        if self.read_only {
+            return;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            self.for_each_cursor(|tb| tb.delete(granularity, delta));
+            return;
+        }
 
         let mut beg;
         let mut end;
@@ -1986,6 +4120,19 @@ impl TextBuffer {
             if beg.offset > end.offset {
                 mem::swap(&mut beg, &mut end);
             }
+
+            // Backspace between an opener and the closer that was inserted with it removes both.
+            if delta == -1
+                && matches!(granularity, CursorMovement::Grapheme)
+                && self.auto_closers.contains(&end.offset)
+                && let (Some(&o), Some(&cl)) =
+                    (self.read_backward(end.offset).last(), self.read_forward(end.offset).first())
+                && self.current_file_type.auto_pairs().contains(&(o, cl))
+            {
+                end = self.cursor_move_to_offset_internal(end, end.offset + 1);
+                // Not contiguous with what an earlier backspace deleted, so it can't be merged.
+                self.last_history_type = HistoryType::Other;
+            }
         }
 
         self.edit_begin(HistoryType::Delete, beg);
@@ -2002,105 +4149,732 @@ impl TextBuffer {
         let mut chars = 0;
         let mut offset = cursor.offset;
 
-        'outer: loop {
-            let chunk = self.read_forward(offset);
-            if chunk.is_empty() {
-                break;
+        'outer: loop {
+            let chunk = self.read_forward(offset);
+            if chunk.is_empty() {
+                break;
+            }
+
+            for &c in chunk {
+                if c == b'\n' || c == b'\r' || (c != b' ' && c != b'\t') {
+                    break 'outer;
+                }
+                chars += 1;
+            }
+
+            offset += chunk.len();
+        }
+
+        Point { x: chars, y: cursor.logical_pos.y }
+    }
+
+    /// Unindents the current selection or line.
+    ///
+    /// TODO: This function is ripe for some optimizations:
+    /// * Instead of replacing the entire selection,
+    ///   it should unindent each line directly (as if multiple cursors had been used).
+    /// * The cursor movement at the end is rather costly, but at least without word wrap
+    ///   it should be possible to calculate it directly from the removed amount.
+    pub fn unindent(&mut self) {
+        if self.read_only {
+            return;
+        }
+
+        let mut selection_beg = self.cursor.logical_pos;
+        let mut selection_end = selection_beg;
+
+        if let Some(TextBufferSelection { beg, end }) = self.selection {
+            selection_beg = beg;
+            selection_end = end;
+        }
+
+        let [beg, end] = minmax(selection_beg, selection_end);
+        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: beg.y });
+        let end = self.cursor_move_to_logical_internal(beg, Point { x: CoordType::MAX, y: end.y });
+
+        let mut replacement = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut replacement, 0);
+
+        let initial_len = replacement.len();
+        let mut offset = 0;
+        let mut y = beg.logical_pos.y;
+
+        loop {
+            if offset >= replacement.len() {
+                break;
+            }
+
+            let mut remove = 0;
+
+            if replacement[offset] == b'\t' {
+                remove = 1;
+            } else {
+                while remove < self.tab_size as usize
+                    && offset + remove < replacement.len()
+                    && replacement[offset + remove] == b' '
+                {
+                    remove += 1;
+                }
+            }
+
+            if remove > 0 {
+                replacement.drain(offset..offset + remove);
+            }
+
+            if y == selection_beg.y {
+                selection_beg.x -= remove as CoordType;
+            }
+            if y == selection_end.y {
+                selection_end.x -= remove as CoordType;
+            }
+
+            (offset, y) = unicode::newlines_forward(&replacement, offset, y, y + 1);
+        }
+
+        if replacement.len() == initial_len {
+            // Nothing to do.
+            return;
+        }
+
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&replacement);
+        self.edit_end();
+
+        if let Some(TextBufferSelection { beg, end }) = &mut self.selection {
+            *beg = selection_beg;
+            *end = selection_end;
+        }
+
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, selection_end));
+    }
+
+    /// Recomputes the indentation of the selected lines, or the entire document if
+    /// there's no selection, using the smart indentation rules of the current file type.
+    ///
+    /// Does nothing for file types without rules, or for Python and YAML, where the
+    /// indentation carries meaning that can't be reconstructed from the text alone.
+    pub fn reindent(&mut self) {
+        let file_type = self.current_file_type;
+        if self.read_only
+            || matches!(file_type, FileType::Python | FileType::YAML)
+            || self.smart_indenter.rule(file_type).is_none()
+        {
+            return;
+        }
+
+        let (beg_y, end_y) = match self.selected_lines() {
+            Some(lines) => (lines.start, lines.end - 1),
+            None => (0, self.stats.logical_lines - 1),
+        };
+        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: beg_y });
+        let end = self.cursor_move_to_logical_internal(beg, Point { x: CoordType::MAX, y: end_y });
+
+        // The indentation of the closest non-blank line above the range serves as the anchor.
+        let mut context: Vec<String> = self.previous_nonblank_line(beg_y).into_iter().collect();
+
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+
+        let tab_size = self.tab_size as usize;
+        let mut replacement = Vec::with_capacity(text.len());
+        let mut line_beg = 0;
+
+        while line_beg <= text.len() {
+            let line_end = memchr2(b'\n', b'\n', &text, line_beg);
+            let line = &text[line_beg..line_end];
+            let content = &line[line.iter().take_while(|&&c| c == b' ' || c == b'\t').count()..];
+
+            if content.iter().any(|c| !c.is_ascii_whitespace()) {
+                let indent = self.smart_indenter.calculate_indent(
+                    &context,
+                    context.len(),
+                    &String::from_utf8_lossy(content),
+                    file_type,
+                    self.indent_style(),
+                );
+                let (tabs, spaces) =
+                    if self.indent_with_tabs { (indent / tab_size, indent % tab_size) } else { (0, indent) };
+                let start = replacement.len();
+                replacement.resize(start + tabs, b'\t');
+                replacement.resize(start + tabs + spaces, b' ');
+                replacement.extend_from_slice(content);
+
+                context.clear();
+                context.push(String::from_utf8_lossy(&replacement[start..]).into_owned());
+            } else {
+                // Blank lines lose their whitespace, but keep a trailing CR, if any.
+                replacement.extend_from_slice(content);
+            }
+
+            if line_end < text.len() {
+                replacement.push(b'\n');
+            }
+            line_beg = line_end + 1;
+        }
+
+        if replacement == text {
+            return;
+        }
+
+        let pos = self.cursor.logical_pos;
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&replacement);
+        self.edit_end();
+
+        let line_end = self.cursor_move_to_logical_internal(self.cursor, Point { x: CoordType::MAX, y: end_y });
+        if let Some(TextBufferSelection { beg, end }) = &mut self.selection {
+            *beg = Point { x: 0, y: beg_y };
+            *end = line_end.logical_pos;
+        }
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, pos));
+    }
+
+    /// Replaces the logical `lines` with what `f` turns them into, as a single undo step.
+    /// `f` gets them without their line endings. Afterwards the new lines are selected.
+    pub fn transform_lines(&mut self, lines: Range<CoordType>, f: impl FnOnce(&mut Vec<&[u8]>)) {
+        if self.read_only || lines.is_empty() {
+            return;
+        }
+
+        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: lines.start });
+        let end = self.cursor_move_to_logical_internal(
+            beg,
+            Point { x: CoordType::MAX, y: lines.end - 1 },
+        );
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+
+        let mut replacement: Vec<&[u8]> =
+            text.split(|&b| b == b'\n').map(unicode::strip_newline).collect();
+        // The empty line after a final newline isn't one to sort.
+        let final_newline = replacement.len() > 1 && replacement.last() == Some(&&b""[..]);
+        if final_newline {
+            replacement.pop();
+        }
+        f(&mut replacement);
+        let count = replacement.len() as CoordType;
+        if final_newline {
+            replacement.push(b"");
+        }
+        let newline: &[u8] = if self.newlines_are_crlf { b"\r\n" } else { b"\n" };
+        let replacement = replacement.join(newline);
+        if replacement == text {
+            return;
+        }
+
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&replacement);
+        self.edit_end();
+
+        // Removing duplicates leaves fewer lines.
+        let end = self.cursor_move_to_logical_internal(
+            self.cursor,
+            Point { x: CoordType::MAX, y: lines.start + count.max(1) - 1 },
+        );
+        self.set_cursor_internal(end);
+        self.set_selection(Some(TextBufferSelection {
+            beg: Point { x: 0, y: lines.start },
+            end: end.logical_pos,
+        }));
+    }
+
+    /// Returns the text between the start of the cursor's line and the cursor.
+    pub fn line_text_before_cursor(&self) -> String {
+        let beg = self.goto_line_start(self.cursor, self.cursor.logical_pos.y);
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, self.cursor.offset, &mut text, 0);
+        String::from_utf8_lossy(&text).into_owned()
+    }
+
+    /// Returns the text of the cursor's line without its line ending,
+    /// and the cursor's byte offset within it.
+    pub fn line_text_at_cursor(&self) -> (String, usize) {
+        let beg = self.goto_line_start(self.cursor, self.cursor.logical_pos.y);
+        let end = self.cursor_move_to_logical_internal(
+            beg,
+            Point { x: CoordType::MAX, y: beg.logical_pos.y },
+        );
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+        (String::from_utf8_lossy(&text).into_owned(), self.cursor.offset - beg.offset)
+    }
+
+    /// Returns the text of the logical lines in `lines` without their line endings.
+    /// Lines past the end of the buffer are left out.
+    pub fn lines_text(&self, lines: Range<CoordType>) -> Vec<String> {
+        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: lines.start });
+        let end = self.cursor_move_to_logical_internal(beg, Point { x: 0, y: lines.end });
+        let count = lines.end.min(self.stats.logical_lines) - lines.start;
+        if beg.logical_pos.y != lines.start || count <= 0 {
+            return Vec::new();
+        }
+
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+        text.split(|&b| b == b'\n')
+            .take(count as usize)
+            .map(|line| String::from_utf8_lossy(unicode::strip_newline(line)).into_owned())
+            .collect()
+    }
+
+    /// Removes trailing spaces and tabs from all lines.
+    pub fn trim_trailing_whitespace(&mut self) {
+        self.rewrite_lines(|line| {
+            let trimmed = line.iter().rposition(|&c| c != b' ' && c != b'\t').map_or(0, |i| i + 1);
+            (trimmed != line.len()).then(|| line[..trimmed].to_vec())
+        });
+    }
+
+    /// Removes trailing whitespace and converts mismatched indentation to the
+    /// document's indentation style, as flagged by [`TextBuffer::set_whitespace_lint()`].
+    pub fn fix_whitespace(&mut self) {
+        let style = self.indent_style();
+        self.rewrite_lines(|line| diagnostics::fix_whitespace(line, style));
+    }
+
+    /// Converts mismatched indentation to the document's indentation style,
+    /// leaving trailing whitespace alone.
+    pub fn normalize_indentation(&mut self) {
+        let style = self.indent_style();
+        self.rewrite_lines(|line| diagnostics::normalize_indentation(line, style));
+    }
+
+    /// Appends a newline if the document isn't empty and doesn't end in one already.
+    pub fn ensure_final_newline(&mut self) {
+        let len = self.text_length();
+        if self.read_only || len == 0 || self.buffer.read_backward(len).ends_with(b"\n") {
+            return;
+        }
+
+        let cursor = self.cursor;
+        let end = self.cursor_move_to_offset_internal(self.cursor, len);
+        self.edit_begin(HistoryType::Other, end);
+        self.edit_write(if self.newlines_are_crlf { b"\r\n" } else { b"\n" });
+        self.edit_end();
+        self.set_cursor_internal(cursor);
+    }
+
+    /// Moves the lines of the selection, or those of each cursor, up or down by one line
+    /// as a single undo step. The cursors and selections move along with them.
+    pub fn move_lines(&mut self, delta: CoordType) {
+        debug_assert!(delta == -1 || delta == 1);
+        if self.read_only {
+            return;
+        }
+
+        // The lines of each cursor, merged into blocks where they touch.
+        // Each block moves across its own neighbor line.
+        let mut blocks: Vec<(CoordType, CoordType)> = self
+            .extra_cursors
+            .iter()
+            .copied()
+            .chain([self.primary_cursor_selection()])
+            .map(|cursor| {
+                let lines = cursor.lines();
+                (lines.start, lines.end - 1)
+            })
+            .collect();
+        blocks.sort_unstable();
+        blocks.dedup_by(|block, prev| {
+            let touches = block.0 <= prev.1 + 1;
+            if touches {
+                prev.1 = prev.1.max(block.1);
+            }
+            touches
+        });
+
+        let (first, last) = (blocks[0].0, blocks[blocks.len() - 1].1);
+        if (delta < 0 && first == 0) || (delta > 0 && last + 1 >= self.stats.logical_lines) {
+            return;
+        }
+
+        // The moved lines and the ones they move across.
+        let (first, last) = (first.min(first + delta), last.max(last + delta));
+        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: first });
+        let end = self.cursor_move_to_logical_internal(beg, Point { x: CoordType::MAX, y: last });
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+
+        let mut lines: Vec<&[u8]> =
+            text.split(|&b| b == b'\n').map(unicode::strip_newline).collect();
+        for (beg, end) in blocks {
+            let beg = (beg.min(beg + delta) - first) as usize;
+            let end = (end.max(end + delta) - first) as usize;
+            if delta < 0 {
+                lines[beg..=end].rotate_left(1);
+            } else {
+                lines[beg..=end].rotate_right(1);
+            }
+        }
+        let newline: &[u8] = if self.newlines_are_crlf { b"\r\n" } else { b"\n" };
+        let replacement = lines.join(newline);
+
+        let pos = self.cursor.logical_pos;
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&replacement);
+        self.edit_end();
+
+        let shift = |pos: Point| Point { x: pos.x, y: pos.y + delta };
+        if let Some(TextBufferSelection { beg, end }) = &mut self.selection {
+            *beg = shift(*beg);
+            *end = shift(*end);
+        }
+        for TextBufferSelection { beg, end } in &mut self.extra_cursors {
+            *beg = shift(*beg);
+            *end = shift(*end);
+        }
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, shift(pos)));
+    }
+
+    /// Inserts a copy of the selection after it and selects the copy, or without
+    /// a selection, a copy of the current line below it and moves the cursor there.
+    /// Either way, repeating it makes more copies. Applies to each cursor.
+    pub fn duplicate(&mut self) {
+        if self.read_only {
+            return;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            self.for_each_cursor(|tb| tb.duplicate());
+            return;
+        }
+
+        if let Some((beg, end)) = self.selection_range_internal(false) {
+            let mut text = Vec::new();
+            self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+            self.edit_begin(HistoryType::Other, end);
+            self.edit_write(&text);
+            self.edit_end();
+
+            let copy_beg = self.cursor_move_to_offset_internal(self.cursor, end.offset);
+            self.set_selection(Some(TextBufferSelection {
+                beg: copy_beg.logical_pos,
+                end: self.cursor.logical_pos,
+            }));
+        } else {
+            let pos = self.cursor.logical_pos;
+            let beg = self.goto_line_start(self.cursor, pos.y);
+            let end =
+                self.cursor_move_to_logical_internal(beg, Point { x: CoordType::MAX, y: pos.y });
+            let mut text = Vec::from(if self.newlines_are_crlf { &b"\r\n"[..] } else { b"\n" });
+            self.buffer.extract_raw(beg.offset, end.offset, &mut text, usize::MAX);
+            self.edit_begin(HistoryType::Other, end);
+            self.edit_write(&text);
+            self.edit_end();
+
+            let pos = Point { x: pos.x, y: pos.y + 1 };
+            self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, pos));
+        }
+        self.last_history_type = HistoryType::Other;
+    }
+
+    /// Comments out the lines of the selection, or those of all cursors, or uncomments them
+    /// if they're all comments already. See [`crate::syntax::CommentStyle::toggle_lines()`].
+    pub fn toggle_line_comment(&mut self) {
+        if self.read_only {
+            return;
+        }
+
+        let mut ys = Vec::new();
+        for cursor in self.extra_cursors.iter().chain([&self.primary_cursor_selection()]) {
+            ys.extend(cursor.lines());
+        }
+        ys.sort_unstable();
+        ys.dedup();
+        let (Some(&first), Some(&last)) = (ys.first(), ys.last()) else {
+            return;
+        };
+
+        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: first });
+        let end = self.cursor_move_to_logical_internal(beg, Point { x: CoordType::MAX, y: last });
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+        let mut lines: Vec<Vec<u8>> = text.split(|&b| b == b'\n').map(<[u8]>::to_vec).collect();
+
+        // Lines between the cursors stay as they are.
+        let mut toggled: Vec<Vec<u8>> = ys
+            .iter()
+            .map(|&y| unicode::strip_newline(&lines[(y - first) as usize]).to_vec())
+            .collect();
+        let Some(edits) = self.current_file_type.comment_style().toggle_lines(&mut toggled) else {
+            return;
+        };
+        if edits.iter().all(|&(_, growth)| growth == 0) {
+            return;
+        }
+        for (&y, mut line) in ys.iter().zip(toggled) {
+            let old = &mut lines[(y - first) as usize];
+            if old.ends_with(b"\r") {
+                line.push(b'\r');
+            }
+            *old = line;
+        }
+
+        let pos = self.cursor.logical_pos;
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&lines.join(&b'\n'));
+        self.edit_end();
+
+        // Positions behind the change move along with the text.
+        let adjust = |pos: Point| match ys.binary_search(&pos.y) {
+            Ok(i) if pos.x >= edits[i].0 as CoordType => {
+                let (column, growth) = edits[i];
+                Point { x: (pos.x + growth).max(column as CoordType), y: pos.y }
+            }
+            _ => pos,
+        };
+        let pos = adjust(pos);
+        if let Some(TextBufferSelection { beg, end }) = &mut self.selection {
+            *beg = adjust(*beg);
+            *end = adjust(*end);
+        }
+        for TextBufferSelection { beg, end } in &mut self.extra_cursors {
+            *beg = adjust(*beg);
+            *end = adjust(*end);
+        }
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, pos));
+    }
+
+    /// Puts the selection, or the current line, into a block comment, or takes it out of one.
+    /// Falls back to [`TextBuffer::toggle_line_comment()`] for file types without
+    /// block comments, like Python.
+    pub fn toggle_block_comment(&mut self) {
+        let Some((open, close)) = self.current_file_type.comment_style().block else {
+            self.toggle_line_comment();
+            return;
+        };
+        if self.read_only {
+            return;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            self.for_each_cursor(|tb| tb.toggle_block_comment());
+            return;
+        }
+
+        let (beg, end) = self.selection_range_internal(false).unwrap_or_else(|| {
+            let y = self.cursor.logical_pos.y;
+            (
+                self.goto_line_start(self.cursor, y),
+                self.cursor_move_to_logical_internal(self.cursor, Point { x: CoordType::MAX, y }),
+            )
+        });
+        let mut text = Vec::new();
+        self.buffer.extract_raw(beg.offset, end.offset, &mut text, 0);
+
+        // The whitespace around the text stays outside of the comment.
+        let leading = text.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        let trailing = text[leading..].iter().rev().take_while(|b| b.is_ascii_whitespace()).count();
+        let outer = beg.offset + leading..end.offset - trailing;
+        let text = &text[leading..text.len() - trailing];
+        let (open, close) = (open.as_bytes(), close.as_bytes());
+
+        if text.len() >= open.len() + close.len() && text.starts_with(open) && text.ends_with(close)
+        {
+            let mut inner = outer.start + open.len()..outer.end - close.len();
+            if inner.start < inner.end && text[open.len()] == b' ' {
+                inner.start += 1;
             }
-
-            for &c in chunk {
-                if c == b'\n' || c == b'\r' || (c != b' ' && c != b'\t') {
-                    break 'outer;
-                }
-                chars += 1;
+            if inner.start < inner.end && text[text.len() - close.len() - 1] == b' ' {
+                inner.end -= 1;
             }
+            self.replace_around(outer, inner, b"", b"");
+        } else {
+            let outer =
+                if outer.is_empty() { self.cursor.offset..self.cursor.offset } else { outer };
+            let (open, close) = ([open, b" "].concat(), [b" ", close].concat());
+            self.replace_around(outer.clone(), outer, &open, &close);
+        }
+    }
 
-            offset += chunk.len();
+    /// Puts `open` and `close` around the selection, or the word at the cursor if there's
+    /// none, and selects what's between them. With several cursors, around each one's.
+    pub fn surround(&mut self, open: &[u8], close: &[u8]) {
+        if self.read_only {
+            return;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            self.for_each_cursor(|tb| tb.surround(open, close));
+            return;
         }
 
-        Point { x: chars, y: cursor.logical_pos.y }
+        let range = match self.selection_range_internal(false) {
+            Some((beg, end)) => beg.offset..end.offset,
+            None => navigation::word_select(&self.buffer, self.cursor.offset),
+        };
+        self.replace_around(range.clone(), range, open, close);
     }
 
-    /// Unindents the current selection or line.
-    ///
-    /// TODO: This function is ripe for some optimizations:
-    /// * Instead of replacing the entire selection,
-    ///   it should unindent each line directly (as if multiple cursors had been used).
-    /// * The cursor movement at the end is rather costly, but at least without word wrap
-    ///   it should be possible to calculate it directly from the removed amount.
-    pub fn unindent(&mut self) {
-        let mut selection_beg = self.cursor.logical_pos;
-        let mut selection_end = selection_beg;
+    /// Converts the selection, or the word at the cursor if there's none, to `case`
+    /// and selects the result. With several cursors, each one's.
+    pub fn convert_case(&mut self, case: Case) {
+        if self.read_only {
+            return;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            self.for_each_cursor(|tb| tb.convert_case(case));
+            return;
+        }
 
-        if let Some(TextBufferSelection { beg, end }) = self.selection {
-            selection_beg = beg;
-            selection_end = end;
+        let range = match self.selection_range_internal(false) {
+            Some((beg, end)) => beg.offset..end.offset,
+            None => navigation::word_select(&self.buffer, self.cursor.offset),
+        };
+        let mut text = Vec::new();
+        self.buffer.extract_raw(range.start, range.end, &mut text, 0);
+        let converted = case.convert(&String::from_utf8_lossy(&text));
+        if converted.as_bytes() == text {
+            return;
         }
 
-        let [beg, end] = minmax(selection_beg, selection_end);
-        let beg = self.cursor_move_to_logical_internal(self.cursor, Point { x: 0, y: beg.y });
-        let end = self.cursor_move_to_logical_internal(beg, Point { x: CoordType::MAX, y: end.y });
+        let beg = self.cursor_move_to_offset_internal(self.cursor, range.start);
+        let end = self.cursor_move_to_offset_internal(beg, range.end);
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(converted.as_bytes());
+        self.edit_end();
 
-        let mut replacement = Vec::new();
-        self.buffer.extract_raw(beg.offset, end.offset, &mut replacement, 0);
+        let beg = self.cursor_move_to_offset_internal(self.cursor, range.start);
+        let end = self.cursor_move_to_offset_internal(beg, range.start + converted.len());
+        self.set_cursor_internal(end);
+        self.set_selection(Some(TextBufferSelection {
+            beg: beg.logical_pos,
+            end: end.logical_pos,
+        }));
+        self.last_history_type = HistoryType::Other;
+    }
 
-        let initial_len = replacement.len();
-        let mut offset = 0;
-        let mut y = beg.logical_pos.y;
+    /// Replaces the innermost `old` around the selection or the cursor with `new`,
+    /// or removes it if that's `None`. Returns `false` if there's none to be found.
+    pub fn change_surrounding(&mut self, old: &Surrounding, new: Option<&Surrounding>) -> bool {
+        if self.read_only {
+            return false;
+        }
+        if !self.extra_cursors.is_empty() && self.each_cursor.is_none() {
+            let mut found = false;
+            self.for_each_cursor(|tb| found |= tb.change_surrounding(old, new));
+            return found;
+        }
 
-        loop {
-            if offset >= replacement.len() {
-                break;
-            }
+        let range = match self.selection_range_internal(false) {
+            Some((beg, end)) => beg.offset..end.offset,
+            None => self.cursor.offset..self.cursor.offset,
+        };
+        let Some([open, close]) = old.find(&self.read_all(), range) else {
+            return false;
+        };
+        let (new_open, new_close) = new.map(|n| (n.open(), n.close())).unwrap_or_default();
+        self.replace_around(
+            open.start..close.end,
+            open.end..close.start,
+            new_open.as_bytes(),
+            new_close.as_bytes(),
+        );
+        true
+    }
 
-            let mut remove = 0;
+    /// Replaces `outer` with `open`, the text in `inner` and `close` as a single undo step
+    /// and selects the text that came from `inner`.
+    fn replace_around(
+        &mut self,
+        outer: Range<usize>,
+        inner: Range<usize>,
+        open: &[u8],
+        close: &[u8],
+    ) {
+        let mut text = Vec::with_capacity(open.len() + inner.len() + close.len());
+        text.extend_from_slice(open);
+        self.buffer.extract_raw(inner.start, inner.end, &mut text, usize::MAX);
+        text.extend_from_slice(close);
+
+        let beg = self.cursor_move_to_offset_internal(self.cursor, outer.start);
+        let end = self.cursor_move_to_offset_internal(beg, outer.end);
+        self.edit_begin(HistoryType::Other, beg);
+        self.edit_delete(end);
+        self.edit_write(&text);
+        self.edit_end();
 
-            if replacement[offset] == b'\t' {
-                remove = 1;
-            } else {
-                while remove < self.tab_size as usize
-                    && offset + remove < replacement.len()
-                    && replacement[offset + remove] == b' '
-                {
-                    remove += 1;
-                }
-            }
+        // `edit_end` remeasured the cursor, so it's the one to start from.
+        let sel_beg = outer.start + open.len();
+        let beg = self.cursor_move_to_offset_internal(self.cursor, sel_beg);
+        let end = self.cursor_move_to_offset_internal(beg, sel_beg + inner.len());
+        self.set_cursor_internal(end);
+        self.set_selection((beg.offset != end.offset).then_some(TextBufferSelection {
+            beg: beg.logical_pos,
+            end: end.logical_pos,
+        }));
+        self.last_history_type = HistoryType::Other;
+    }
 
-            if remove > 0 {
-                replacement.drain(offset..offset + remove);
-            }
+    /// Replaces each line (without its line ending) for which `rewrite` returns `Some`,
+    /// as a single undo step.
+    ///
+    /// Only the range between the first and last affected line is replaced,
+    /// so that the undo entry stays small for typical documents.
+    fn rewrite_lines(&mut self, mut rewrite: impl FnMut(&[u8]) -> Option<Vec<u8>>) {
+        if self.read_only {
+            return;
+        }
 
-            if y == selection_beg.y {
-                selection_beg.x -= remove as CoordType;
+        let mut text = Vec::new();
+        self.buffer.extract_raw(0, self.text_length(), &mut text, 0);
+
+        let mut replacement = Vec::new();
+        let mut changed: Option<Range<usize>> = None;
+        let mut line_beg = 0;
+
+        while line_beg < text.len() {
+            let line_end = memchr2(b'\n', b'\n', &text, line_beg);
+            let mut content_end = line_end;
+            if content_end > line_beg && text[content_end - 1] == b'\r' {
+                content_end -= 1;
             }
-            if y == selection_end.y {
-                selection_end.x -= remove as CoordType;
+
+            if let Some(line) = rewrite(&text[line_beg..content_end]) {
+                let range = changed.get_or_insert(line_beg..line_beg);
+                // Include the unchanged lines in between.
+                replacement.extend_from_slice(&text[range.end..line_beg]);
+                replacement.extend_from_slice(&line);
+                replacement.extend_from_slice(&text[content_end..line_end]);
+                range.end = line_end;
             }
 
-            (offset, y) = unicode::newlines_forward(&replacement, offset, y, y + 1);
+            line_beg = line_end + 1;
         }
 
-        if replacement.len() == initial_len {
-            // Nothing to do.
+        let Some(range) = changed else {
             return;
-        }
+        };
 
+        let pos = self.cursor.logical_pos;
+        let beg = self.cursor_move_to_offset_internal(self.cursor, range.start);
+        let end = self.cursor_move_to_offset_internal(beg, range.end);
         self.edit_begin(HistoryType::Other, beg);
         self.edit_delete(end);
         self.edit_write(&replacement);
         self.edit_end();
-
-        if let Some(TextBufferSelection { beg, end }) = &mut self.selection {
-            *beg = selection_beg;
-            *end = selection_end;
-        }
-
-        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, selection_end));
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, pos));
     }
 
     /// Extracts the contents of the current selection.
     /// May optionally delete it, if requested. This is meant to be used for Ctrl+X.
     pub fn extract_selection(&mut self, delete: bool) -> Vec<u8> {
+        if self.each_cursor.is_none() && self.extra_cursors.iter().any(|c| c.beg != c.end) {
+            // With several cursors, like in a block selection, each one's selection is a line.
+            let mut lines = Vec::new();
+            self.for_each_cursor(|tb| {
+                if tb.has_selection() {
+                    lines.push(tb.extract_selection(delete));
+                }
+            });
+            lines.reverse();
+            let newline: &[u8] = if self.newlines_are_crlf { b"\r\n" } else { b"\n" };
+            return lines.join(newline);
+        }
+
         let Some((beg, end)) = self.selection_range_internal(true) else {
             return Vec::new();
         };
@@ -2108,7 +4882,7 @@ impl TextBuffer {
         let mut out = Vec::new();
         self.buffer.extract_raw(beg.offset, end.offset, &mut out, 0);
 
-        if delete && !out.is_empty() {
+        if delete && !out.is_empty() && !self.read_only {
             self.edit_begin(HistoryType::Delete, beg);
             self.edit_delete(end);
             self.edit_end();
@@ -2142,6 +4916,12 @@ impl TextBuffer {
         self.selection_range_internal(false)
     }
 
+    /// Returns the logical lines of the selection, or `None` if there is no selection.
+    /// A selection that ends at the start of a line doesn't include that line.
+    pub fn selected_lines(&self) -> Option<Range<CoordType>> {
+        self.selection.map(|s| s.lines())
+    }
+
     /// Returns the current selection anchors.
     ///
     /// If there's no selection and `line_fallback` is `true`,
@@ -2179,7 +4959,7 @@ impl TextBuffer {
             || !matches!(history_type, HistoryType::Write | HistoryType::Delete)
         {
             self.redo_stack.clear();
-            while self.undo_stack.len() > 1000 {
+            while self.undo_stack.len() > self.undo_limit {
                 self.undo_stack.pop_front();
             }
 
@@ -2192,7 +4972,11 @@ impl TextBuffer {
                 cursor: cursor.logical_pos,
                 deleted: Vec::new(),
                 added: Vec::new(),
+                joined: self.each_cursor.is_some_and(|entries| entries > 0),
             }));
+            if let Some(entries) = &mut self.each_cursor {
+                *entries += 1;
+            }
         }
 
         self.active_edit_off = cursor.offset;
@@ -2228,12 +5012,19 @@ impl TextBuffer {
 
         // Write!
         self.buffer.replace(self.active_edit_off..self.active_edit_off, text);
+        for off in &mut self.auto_closers {
+            if *off >= self.active_edit_off {
+                *off += text.len();
+            }
+        }
 
         // Move self.cursor to the end of the newly written text. Can't use `self.set_cursor_internal`,
         // because we're still in the progress of recalculating the line stats.
         self.active_edit_off += text.len();
         self.cursor = self.cursor_move_to_offset_internal(self.cursor, self.active_edit_off);
-        self.stats.logical_lines += self.cursor.logical_pos.y - logical_y_before;
+        let added = self.cursor.logical_pos.y - logical_y_before;
+        self.stats.logical_lines += added;
+        self.folds_after_edit(logical_y_before, logical_y_before, added);
     }
 
     /// Deletes the text between the current cursor position and `to`.
@@ -2245,21 +5036,33 @@ impl TextBuffer {
         let off = self.active_edit_off;
         let mut out_off = usize::MAX;
 
-        let mut undo = self.undo_stack.back_mut().unwrap().borrow_mut();
-        if self.cursor.logical_pos < undo.cursor {
-            out_off = 0; // Prepend the deleted portion.
-            undo.cursor = self.cursor.logical_pos; // Note the start of the deleted portion.
-        }
+        {
+            let mut undo = self.undo_stack.back_mut().unwrap().borrow_mut();
+            if self.cursor.logical_pos < undo.cursor {
+                out_off = 0; // Prepend the deleted portion.
+                undo.cursor = self.cursor.logical_pos; // Note the start of the deleted portion.
+            }
 
-        // Copy the deleted portion into the undo entry.
-        let deleted = &mut undo.deleted;
-        self.buffer.extract_raw(off, to.offset, deleted, out_off);
+            // Copy the deleted portion into the undo entry.
+            let deleted = &mut undo.deleted;
+            self.buffer.extract_raw(off, to.offset, deleted, out_off);
+        }
 
         // Delete the portion from the buffer by enlarging the gap.
         let count = to.offset - off;
         self.buffer.allocate_gap(off, 0, count);
+        self.auto_closers.retain_mut(|closer| match *closer {
+            c if c < off => true,
+            c if c < to.offset => false,
+            _ => {
+                *closer -= count;
+                true
+            }
+        });
 
-        self.stats.logical_lines += logical_y_before - to.logical_pos.y;
+        let removed = to.logical_pos.y - logical_y_before;
+        self.stats.logical_lines -= removed;
+        self.folds_after_edit(logical_y_before, to.logical_pos.y, -removed);
     }
 
     /// Finalizes the current edit operation
@@ -2324,6 +5127,25 @@ impl TextBuffer {
     }
 
     fn undo_redo(&mut self, undo: bool) {
+        if self.read_only {
+            return;
+        }
+
+        self.extra_cursors.clear();
+        self.auto_closers.clear();
+        self.folds.clear();
+        // Undoing restores the previous generation, which then gets reused by other edits.
+        self.fold_regions_key = None;
+
+        // The edits made at several cursors at once are undone and redone together.
+        // Either way, the next entry of such a group is then at the back of the redo stack.
+        while self.undo_redo_entry(undo)
+            && self.redo_stack.back().is_some_and(|entry| entry.borrow().joined)
+        {}
+    }
+
+    /// Undoes or redoes a single entry of the history. Returns whether there was one.
+    fn undo_redo_entry(&mut self, undo: bool) -> bool {
         // Transfer the last entry from the undo stack to the redo stack or vice versa.
         {
             let (from, to) = if undo {
@@ -2333,7 +5155,7 @@ impl TextBuffer {
             };
 
             let Some(list) = from.cursor_back_mut().remove_current_as_list() else {
-                return;
+                return false;
             };
 
             to.cursor_back_mut().splice_after(list);
@@ -2426,10 +5248,11 @@ impl TextBuffer {
 
         // Also takes care of clearing `cursor_for_rendering`.
         self.reflow(false);
+        true
     }
 
     /// For interfacing with ICU.
-    pub(crate) fn read_backward(&self, off: usize) -> &[u8] {
+    pub fn read_backward(&self, off: usize) -> &[u8] {
         self.buffer.read_backward(off)
     }
 
@@ -2441,11 +5264,14 @@ impl TextBuffer {
     // Smart indentation helper methods
     
     fn should_use_smart_indent(&self) -> bool {
-        // Enable smart indent for supported languages
-        matches!(
-            self.current_file_type,
-            FileType::Python | FileType::Rust | FileType::JavaScript | FileType::TypeScript | FileType::HTML | FileType::CSS
-        )
+        // Enable smart indent for supported languages and those configured by the user.
+        match self.smart_indenter.rule(self.current_file_type) {
+            Some(rule) if rule.user_defined => true,
+            _ => matches!(
+                self.current_file_type,
+                FileType::Python | FileType::Rust | FileType::JavaScript | FileType::TypeScript | FileType::HTML | FileType::CSS
+            ),
+        }
     }
     
     fn calculate_smart_indent_for_newline(&self) -> usize {
@@ -2476,9 +5302,14 @@ impl TextBuffer {
             lines.len(), // We want to calculate indent for the line AFTER the current one
             "", // The new line is empty initially
             self.current_file_type,
-            self.tab_size as usize,
+            self.indent_style(),
         );
         
+        // User-defined rules are trusted as-is.
+        if self.smart_indenter.rule(self.current_file_type).is_some_and(|r| r.user_defined) {
+            return base_indent;
+        }
+
         // Debug: If the current line doesn't end with a colon, brace, etc., just copy the indent
         if !trimmed_line.ends_with(':') && !trimmed_line.ends_with('{') && !trimmed_line.ends_with('}') && !trimmed_line.starts_with("def ") && !trimmed_line.starts_with("class ") && !trimmed_line.starts_with("if ") && !trimmed_line.starts_with("else") && !trimmed_line.starts_with("elif ") && !trimmed_line.starts_with("for ") && !trimmed_line.starts_with("while ") && !trimmed_line.starts_with("try") && !trimmed_line.starts_with("except") && !trimmed_line.starts_with("finally") {
             // Just copy the current line's indentation
@@ -2488,6 +5319,70 @@ impl TextBuffer {
         base_indent
     }
     
+    /// Called after typing a closing token like `}`: If that turned the current line into
+    /// one that closes a block (e.g. `}`, `else:` or `</div>`), its indentation is
+    /// decreased right away instead of waiting for the next newline.
+    /// The dedent is a separate undo step, so that undo can revert just that.
+    fn auto_dedent_current_line(&mut self) {
+        let file_type = self.current_file_type;
+        let Some(rule) = self.smart_indenter.rule(file_type) else {
+            return;
+        };
+
+        let y = self.cursor.logical_pos.y;
+        let line_start = self.goto_line_start(self.cursor, y);
+        let line = self.get_line_content(line_start);
+        let content = line.trim_start_matches([' ', '\t']);
+
+        // Python's decrease patterns include `return`, `pass` and the like,
+        // which end a block but don't close the line they're on.
+        let closes_block = rule.decrease_increase_patterns.iter().any(|p| p.is_match(content))
+            || (file_type != FileType::Python
+                && rule.decrease_patterns.iter().any(|p| p.is_match(content)));
+        if !closes_block {
+            return;
+        }
+
+        let Some(prev_line) = self.previous_nonblank_line(y) else {
+            return;
+        };
+        let tab_size = self.tab_size as usize;
+        let current = self.smart_indenter.get_line_indent(&line, tab_size);
+        let indent = self.smart_indenter.calculate_indent(
+            &[prev_line],
+            1,
+            content,
+            file_type,
+            self.indent_style(),
+        );
+        if indent >= current {
+            return;
+        }
+
+        let scratch = scratch_arena(None);
+        let mut indentation = ArenaString::new_in(&scratch);
+        self.apply_indentation_to_newline_buffer(&mut indentation, indent);
+
+        let whitespace_len = (line.len() - content.len()) as CoordType;
+        let pos = self.cursor.logical_pos;
+        let end = self.cursor_move_to_logical_internal(line_start, Point { x: whitespace_len, y });
+        self.edit_begin(HistoryType::Other, line_start);
+        self.edit_delete(end);
+        self.edit_write(indentation.as_bytes());
+        self.edit_end();
+
+        let x = pos.x - whitespace_len + indentation.len() as CoordType;
+        self.set_cursor_internal(self.cursor_move_to_logical_internal(self.cursor, Point { x, y }));
+    }
+
+    /// Returns the closest line above `y` that isn't blank.
+    fn previous_nonblank_line(&self, y: CoordType) -> Option<String> {
+        (0..y)
+            .rev()
+            .map(|y| self.get_line_content(self.goto_line_start(self.cursor, y)))
+            .find(|line| !line.trim().is_empty())
+    }
+
     fn calculate_simple_indent_for_newline(&self) -> usize {
         // This is the existing indentation logic (copy previous line's indent)
         let tab_size = self.tab_size as usize;
@@ -2560,21 +5455,6 @@ impl TextBuffer {
         
         content
     }
-    
-    fn get_lines_around_cursor(&self, context_lines: usize) -> Vec<String> {
-        let current_y = self.cursor.logical_pos.y as usize;
-        let start_y = current_y.saturating_sub(context_lines);
-        let end_y = (current_y + context_lines + 1).min(self.stats.logical_lines as usize);
-        
-        let mut lines = Vec::new();
-        for y in start_y..end_y {
-            let line_start = self.goto_line_start(self.cursor, y as CoordType);
-            let line_content = self.get_line_content(line_start);
-            lines.push(line_content);
-        }
-        
-        lines
-    }
 }
 
 pub enum Bom {
@@ -2589,6 +5469,89 @@ pub enum Bom {
 
 const BOM_MAX_LEN: usize = 4;
 
+/// The number of edits that can be undone, unless changed with [`TextBuffer::set_undo_limit()`].
+pub const DEFAULT_UNDO_LIMIT: usize = 1000;
+
+/// How much of a file is looked at to detect legacy encodings.
+const LEGACY_SNIFF_LEN: usize = 64 * KIBI;
+
+/// UTF-8 files at least this large are memory-mapped instead of read.
+const MMAP_MIN_LEN: usize = 64 * MEBI;
+
+/// Beyond this size, finding the partner of an HTML tag gets too slow to do on every render.
+const TAG_PAIR_MAX_LEN: usize = 4 * MEBI;
+
+/// Finding the regions that can be folded is skipped for documents with more lines.
+const FOLD_MAX_LINES: CoordType = 20_000;
+
+/// The keys used for jump labels, home row first.
+const JUMP_LABEL_KEYS: &[u8] = b"asdfghjklqwertyuiopzxcvbnm";
+
+/// Returns `count` distinct jump labels. Single keys are used while they suffice.
+/// Beyond that, the last few single keys become prefixes for two-key labels,
+/// so that no label is the prefix of another one.
+fn jump_labels(count: usize) -> Vec<String> {
+    let n = JUMP_LABEL_KEYS.len();
+    let key = |i: usize| JUMP_LABEL_KEYS[i] as char;
+    if count <= n {
+        return (0..count).map(|i| key(i).to_string()).collect();
+    }
+
+    // Use as few prefixes as possible, leaving the remaining keys as single-key labels.
+    let prefixes = (count - n).div_ceil(n - 1).min(n);
+    let singles = n - prefixes;
+    let mut labels: Vec<String> = (0..singles).map(|i| key(i).to_string()).collect();
+    'outer: for p in singles..n {
+        for i in 0..n {
+            if labels.len() >= count {
+                break 'outer;
+            }
+            labels.push([key(p), key(i)].iter().collect());
+        }
+    }
+    labels
+}
+
+/// Guesses the encoding of a file without a BOM from its first [`LEGACY_SNIFF_LEN`] bytes.
+/// Returns `None` if they're valid UTF-8. Otherwise, text that decodes as Shift-JIS and mostly
+/// consists of kana and common kanji is assumed to be Shift-JIS, and anything else Latin-1.
+fn detect_legacy_encoding(bytes: &[u8]) -> Option<&'static str> {
+    // Only a sample that was cut short may end in the middle of a character.
+    let cut = bytes.len() > LEGACY_SNIFF_LEN;
+    let bytes = &bytes[..bytes.len().min(LEGACY_SNIFF_LEN)];
+    match str::from_utf8(bytes) {
+        Ok(_) => return None,
+        Err(e) if cut && e.error_len().is_none() => return None,
+        Err(_) => {}
+    }
+
+    // Lead bytes 0x81-0x9F cover the kana and the JIS level 1 kanji. They're the bulk of
+    // Japanese text, but (unlike 0xE0-0xFC) never an accented letter in Latin-1.
+    let mut pairs = 0;
+    let mut common_pairs = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let lead = bytes[i];
+        match lead {
+            // ASCII and half-width katakana.
+            0x00..=0x7F | 0xA1..=0xDF => i += 1,
+            0x81..=0x9F | 0xE0..=0xFC => {
+                match bytes.get(i + 1) {
+                    Some(0x40..=0x7E | 0x80..=0xFC) => {}
+                    None if cut => break,
+                    _ => return Some("ISO-8859-1"),
+                }
+                pairs += 1;
+                common_pairs += (lead <= 0x9F) as usize;
+                i += 2;
+            }
+            _ => return Some("ISO-8859-1"),
+        }
+    }
+
+    Some(if common_pairs * 2 > pairs { "Shift_JIS" } else { "ISO-8859-1" })
+}
+
 fn detect_bom(bytes: &[u8]) -> Option<&'static str> {
     if bytes.len() >= 4 {
         if bytes.starts_with(b"\xFF\xFE\x00\x00") {
@@ -2614,3 +5577,466 @@ fn detect_bom(bytes: &[u8]) -> Option<&'static str> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> TextBuffer {
+        let mut tb = TextBuffer::new(false).unwrap();
+        tb.set_text(text);
+        tb
+    }
+
+    fn text(tb: &mut TextBuffer) -> String {
+        let mut text = String::new();
+        tb.save_as_string(&mut text);
+        text
+    }
+
+    /// The positions of all cursors, sorted.
+    fn cursors(tb: &TextBuffer) -> Vec<Point> {
+        let mut cursors: Vec<Point> = tb.extra_cursors.iter().map(|c| c.end).collect();
+        cursors.push(tb.cursor_logical_pos());
+        cursors.sort();
+        cursors
+    }
+
+    const REGEX: SearchOptions =
+        SearchOptions { match_case: true, whole_word: false, use_regex: true };
+
+    /// Takes the `steps` through the hits of `pattern` in all lines, like the confirm mode
+    /// of `:s`, and returns the text and whether a hit was left selected after each step.
+    fn replace_steps(
+        tb: &mut TextBuffer,
+        pattern: &str,
+        replacement: &str,
+        every: bool,
+        steps: &[ReplaceStep],
+    ) -> Vec<bool> {
+        let mut lines = 0..tb.logical_line_count();
+        let mut found = Vec::new();
+        for &step in [ReplaceStep::Skip].iter().chain(steps) {
+            found.push(
+                tb.find_and_replace_step(pattern, REGEX, replacement, &mut lines, every, step)
+                    .unwrap(),
+            );
+        }
+        found
+    }
+
+    #[test]
+    fn test_multi_cursor_edit() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("ab\ncd\nef");
+        tb.cursor_move_to_logical(Point { x: 1, y: 0 });
+        assert!(tb.add_cursor_vertical(1, 1));
+        assert!(tb.add_cursor_vertical(1, 1));
+        assert!(!tb.add_cursor_vertical(1, 1));
+
+        // Each edit shifts the cursors after it on the same line, but not those on others.
+        tb.write(b"XY", true);
+        assert_eq!(text(&mut tb), "aXYb\ncXYd\neXYf");
+        let at = |x| vec![Point { x, y: 0 }, Point { x, y: 1 }, Point { x, y: 2 }];
+        assert_eq!(cursors(&tb), at(3));
+
+        tb.delete(CursorMovement::Grapheme, -1);
+        assert_eq!(text(&mut tb), "aXb\ncXd\neXf");
+        assert_eq!(cursors(&tb), at(2));
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 2, y: 2 });
+    }
+
+    #[test]
+    fn test_multi_cursor_merge() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("abcd");
+        tb.cursor_move_to_logical(Point { x: 2, y: 0 });
+        let first = Point { x: 1, y: 0 };
+        tb.extra_cursors.push(TextBufferSelection { beg: first, end: first });
+
+        // Both characters before the cursors go, which leaves the cursors at the same spot.
+        tb.delete(CursorMovement::Grapheme, -1);
+        assert_eq!(text(&mut tb), "cd");
+        assert!(!tb.has_extra_cursors());
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 0, y: 0 });
+        tb.write(b"x", true);
+        assert_eq!(text(&mut tb), "xcd");
+    }
+
+    #[test]
+    fn test_add_cursor_at_next_match() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("foo x foo y foo");
+        tb.cursor_move_to_offset(7);
+
+        // The first press selects the word, the others add cursors, wrapping around.
+        assert!(tb.add_cursor_at_next_match());
+        assert!(!tb.has_extra_cursors());
+        assert!(tb.add_cursor_at_next_match());
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 15, y: 0 });
+        assert!(tb.add_cursor_at_next_match());
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 3, y: 0 });
+        let (beg, _) = tb.selection_range().unwrap();
+        assert_eq!(beg.offset, 0);
+
+        // Every occurrence has a cursor now.
+        assert!(!tb.add_cursor_at_next_match());
+        assert_eq!(cursors(&tb).len(), 3);
+
+        tb.write(b"bar", true);
+        assert_eq!(text(&mut tb), "bar x bar y bar");
+    }
+
+    #[test]
+    fn test_multi_cursor_undo_redo() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("a\nb\nc");
+        tb.cursor_move_to_logical(Point { x: 1, y: 0 });
+        tb.add_cursor_vertical(1, 1);
+        tb.add_cursor_vertical(1, 1);
+        tb.write(b"!", true);
+        assert_eq!(text(&mut tb), "a!\nb!\nc!");
+
+        // The entries after the first one are joined to it.
+        let joined: Vec<bool> = tb.undo_stack.iter().map(|e| e.borrow().joined).collect();
+        assert_eq!(joined, [false, true, true]);
+
+        tb.undo();
+        assert_eq!(text(&mut tb), "a\nb\nc");
+        assert!(tb.undo_stack.is_empty());
+        tb.redo();
+        assert_eq!(text(&mut tb), "a!\nb!\nc!");
+        assert!(tb.redo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_selected_lines() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("a\nb\nc\nd");
+        assert_eq!(tb.selected_lines(), None);
+
+        tb.cursor_move_to_logical(Point { x: 1, y: 2 });
+        tb.selection_update_logical(Point { x: 0, y: 1 });
+        assert_eq!(tb.selected_lines(), Some(1..3));
+
+        // Ending at the start of a line doesn't include that line.
+        tb.selection_update_logical(Point { x: 0, y: 3 });
+        assert_eq!(tb.selected_lines(), Some(2..3));
+        tb.clear_selection();
+        tb.cursor_move_to_logical(Point { x: 1, y: 0 });
+        tb.selection_update_logical(Point { x: 0, y: 1 });
+        assert_eq!(tb.selected_lines(), Some(0..1));
+    }
+
+    #[test]
+    fn test_block_selection() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("abcd\nefgh\nijkl");
+        tb.cursor_move_to_logical(Point { x: 1, y: 0 });
+        tb.block_selection_update_visual(Point { x: 3, y: 2 });
+        assert!(tb.has_block_selection());
+        let column = |x| vec![Point { x, y: 0 }, Point { x, y: 1 }, Point { x, y: 2 }];
+        assert_eq!(cursors(&tb), column(3));
+
+        assert_eq!(tb.extract_selection(false), b"bc\nfg\njk");
+
+        tb.write(b"X", true);
+        assert_eq!(text(&mut tb), "aXd\neXh\niXl");
+        assert_eq!(cursors(&tb), column(2));
+    }
+
+    #[test]
+    fn test_paste_into_cursors() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("a\nb\nc");
+        tb.cursor_move_to_logical(Point { x: 1, y: 0 });
+        tb.add_cursor_vertical(1, 1);
+        tb.add_cursor_vertical(1, 1);
+
+        // As many lines as there are cursors go one to each, whatever the newlines are.
+        tb.write(b"1\n2\n3", false);
+        assert_eq!(text(&mut tb), "a1\nb2\nc3");
+        tb.write(b"4\r5\r\n6\n", false);
+        assert_eq!(text(&mut tb), "a14\nb25\nc36");
+
+        // Any other number of lines goes to every cursor as a whole.
+        tb.write(b"x\ny", false);
+        assert_eq!(text(&mut tb), "a14x\ny\nb25x\ny\nc36x\ny");
+    }
+
+    const FOLDABLE: &str = "fn a() {\n    x;\n    y;\n}\nfn b() {\n    z;\n}\n";
+
+    fn foldable() -> TextBuffer {
+        let mut tb = buffer(FOLDABLE);
+        tb.set_file_type(FileType::Rust);
+        tb
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_fold_rows() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = foldable();
+        tb.cursor_move_to_logical(Point { x: 4, y: 2 });
+        assert!(tb.fold_at_cursor());
+        assert_eq!(tb.folds, [1..4]);
+        // The cursor leaves the fold for its header.
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 4, y: 0 });
+
+        assert_eq!(tb.visual_line_count(), 5);
+        let screen: Vec<_> = (0..5).map(|y| tb.screen_to_row(y)).collect();
+        assert_eq!(screen, [0, 4, 5, 6, 7]);
+        let rows: Vec<_> = (0..8).map(|row| tb.row_to_screen(row)).collect();
+        assert_eq!(rows, [0, 0, 0, 0, 1, 2, 3, 4]);
+
+        assert!(tb.fold_all());
+        assert_eq!(tb.folds, [1..4, 5..7]);
+        assert_eq!(tb.visual_line_count(), 3);
+        assert_eq!(tb.screen_to_row(2), 7);
+        assert!(tb.toggle_fold());
+        assert_eq!(tb.folds, [5..7]);
+        assert!(tb.unfold_all());
+        assert_eq!(tb.visual_line_count(), 8);
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_fold_reveal() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = foldable();
+        tb.fold_all();
+
+        // Going to a line in a fold unfolds it, and only it.
+        tb.cursor_move_to_logical(Point { x: 0, y: 5 });
+        assert_eq!(tb.folds, [1..4]);
+
+        tb.cursor_move_to_logical(Point { x: 0, y: 0 });
+        let options = SearchOptions { match_case: true, whole_word: false, use_regex: false };
+        if tb.find_and_select("y;", options).is_err() {
+            return; // ICU isn't installed.
+        }
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 6, y: 2 });
+        assert!(tb.folds.is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_folds_after_edit() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = foldable();
+        tb.fold_all();
+
+        // Typing on a header keeps its fold.
+        tb.cursor_move_to_logical(Point { x: 4, y: 4 });
+        tb.write(b"c", true);
+        assert_eq!(text(&mut tb), FOLDABLE.replace("fn b", "fn bc"));
+        assert_eq!(tb.folds, [1..4, 5..7]);
+
+        // Lines added above a fold move it along, while those in its region unfold it.
+        tb.cursor_move_to_logical(Point { x: 0, y: 0 });
+        tb.write(b"\n", true);
+        assert_eq!(tb.folds, [6..8]);
+        tb.cursor_move_to_logical(Point { x: CoordType::MAX, y: 5 });
+        tb.write(b"\n", true);
+        assert!(tb.folds.is_empty());
+        assert_eq!(tb.visual_line_count(), 10);
+    }
+
+    #[test]
+    fn test_reload_file_by_diff() {
+        let _arena = crate::arena::init_for_test();
+        let path = std::env::temp_dir().join(format!("edit-buffer-reload-{}", std::process::id()));
+        std::fs::write(&path, "a\nb\nc\nd\n").unwrap();
+        let mut tb = TextBuffer::new(false).unwrap();
+        tb.read_file_with_path(&mut File::open(&path).unwrap(), &path, None).unwrap();
+
+        // An unsaved edit, and a selection on a line that stays the same.
+        tb.cursor_move_to_logical(Point { x: 0, y: 3 });
+        tb.write(b"x", true);
+        tb.cursor_move_to_logical(Point { x: 0, y: 2 });
+        tb.selection_update_logical(Point { x: 1, y: 2 });
+
+        std::fs::write(&path, "new\na\nB\nc\nd\n").unwrap();
+        tb.reload_file_with_path(&mut File::open(&path).unwrap(), &path, None).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert_eq!(text(&mut tb), "new\na\nB\nc\nd\n");
+        assert!(!tb.is_dirty());
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 1, y: 3 });
+        let (beg, end) = tb.selection_range().unwrap();
+        assert_eq!(beg.logical_pos, Point { x: 0, y: 3 });
+        assert_eq!(end.logical_pos, Point { x: 1, y: 3 });
+
+        // The reload is a single undo step, which brings back the unsaved edit.
+        tb.undo();
+        assert_eq!(text(&mut tb), "a\nb\nc\nxd\n");
+    }
+
+    #[test]
+    fn test_jump_labels() {
+        assert_eq!(jump_labels(3), ["a", "s", "d"]);
+        assert_eq!(jump_labels(26).len(), 26);
+        assert!(jump_labels(26).iter().all(|l| l.len() == 1));
+
+        // One more than there are keys takes a single key as a prefix.
+        let labels = jump_labels(27);
+        assert_eq!(labels[24..], ["n", "ma", "ms"]);
+
+        for count in [27, 100, 26 * 26] {
+            let labels = jump_labels(count);
+            assert_eq!(labels.len(), count);
+            for (i, a) in labels.iter().enumerate() {
+                assert!(labels[i + 1..].iter().all(|b| !b.starts_with(a.as_str())));
+            }
+        }
+        assert_eq!(jump_labels(1000).len(), 26 * 26);
+    }
+
+    #[test]
+    fn test_jump_targets_collect() {
+        let mut tb = buffer("ab Ab ab\nab");
+        tb.cursor_move_to_logical(Point { x: 6, y: 0 });
+
+        // The closest hits get the first labels. A lowercase needle ignores case.
+        tb.set_jump_needle(Some("ab"));
+        tb.jump_targets_collect(0, tb.text_length());
+        let targets: Vec<_> =
+            tb.jump_targets.iter().map(|t| (t.label.as_str(), t.offset)).collect();
+        assert_eq!(targets, [("a", 6), ("s", 3), ("d", 9), ("f", 0)]);
+
+        tb.set_jump_needle(Some("Ab"));
+        tb.jump_targets_collect(0, tb.text_length());
+        assert!(tb.has_jump_label("a") && !tb.has_jump_label("s"));
+        assert!(tb.jump_to_label("a"));
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 3, y: 0 });
+        assert!(!tb.has_jump_label("a"));
+    }
+
+    #[test]
+    fn test_reindent() {
+        let _arena = crate::arena::init_for_test();
+        let messy = "fn main() {\nif a {\n      b();\n  \n}\n}\n";
+
+        let mut tb = buffer(messy);
+        tb.set_file_type(FileType::Rust);
+        tb.reindent();
+        assert_eq!(text(&mut tb), "fn main() {\n    if a {\n        b();\n\n    }\n}\n");
+
+        // Only the selected lines, relative to the line above them, with tabs.
+        let mut tb = buffer(messy);
+        tb.set_file_type(FileType::Rust);
+        tb.set_indent_with_tabs(true);
+        tb.cursor_move_to_logical(Point { x: 0, y: 1 });
+        tb.selection_update_logical(Point { x: 0, y: 3 });
+        tb.reindent();
+        assert_eq!(text(&mut tb), "fn main() {\n\tif a {\n\t\tb();\n  \n}\n}\n");
+
+        // The indentation of Python is left alone.
+        let mut tb = buffer("if a:\nb\n");
+        tb.set_file_type(FileType::Python);
+        tb.reindent();
+        assert_eq!(text(&mut tb), "if a:\nb\n");
+    }
+
+    #[test]
+    fn test_find_expand_replacement() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("alice@example, bob@test");
+        if tb.find_and_replace_all(r"(\w+)@(\w+)", REGEX, r"${2}:$1 $$0 \t\\$3").is_err() {
+            return; // ICU isn't installed.
+        }
+        assert_eq!(text(&mut tb), "example:alice $0 \t\\, test:bob $0 \t\\");
+
+        // Without regex, nothing is expanded.
+        let literal = SearchOptions { use_regex: false, ..REGEX };
+        tb.find_and_replace_all("$0", literal, r"$1\n").unwrap();
+        assert_eq!(text(&mut tb), "example:alice $1\\n \t\\, test:bob $1\\n \t\\");
+    }
+
+    #[test]
+    fn test_find_and_replace_step() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("a1 a2\nb1\nc1\n");
+        if tb.find_count("a", REGEX).is_err() {
+            return; // ICU isn't installed.
+        }
+
+        use ReplaceStep::*;
+        let found = replace_steps(&mut tb, r"\d", "#", false, &[Replace, Skip, Replace]);
+        assert_eq!(found, [true, true, true, false]);
+        assert_eq!(text(&mut tb), "a# a2\nb1\nc#\n");
+
+        let mut tb = buffer("a1 a2\nb1\nc1\n");
+        let found = replace_steps(&mut tb, r"\d", "#", true, &[Skip, Replace, ReplaceAll]);
+        assert_eq!(found, [true, true, true, false]);
+        assert_eq!(text(&mut tb), "a1 a#\nb#\nc#\n");
+    }
+
+    #[test]
+    fn test_find_and_replace_empty_hits() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = buffer("a\nb\nc");
+        if tb.find_count("a", REGEX).is_err() {
+            return; // ICU isn't installed.
+        }
+
+        use ReplaceStep::*;
+        let found = replace_steps(&mut tb, "^", "> ", false, &[Replace, Skip, Replace]);
+        assert_eq!(found, [true, true, true, false]);
+        assert_eq!(text(&mut tb), "> a\nb\n> c");
+
+        // Each of them is replaced once, even though the replacement ends where it was.
+        let mut tb = buffer("a\nb\nc");
+        let found = replace_steps(&mut tb, "$", "!", true, &[Replace, Replace, Replace]);
+        assert_eq!(found, [true, true, true, false]);
+        assert_eq!(text(&mut tb), "a!\nb!\nc!");
+
+        let mut tb = buffer("a\nb");
+        tb.find_and_select("$", REGEX).unwrap();
+        tb.find_and_replace("$", REGEX, "!").unwrap();
+        tb.find_and_replace("$", REGEX, "!").unwrap();
+        assert_eq!(text(&mut tb), "a!\nb!");
+    }
+
+    #[test]
+    fn test_move_lines() {
+        let _arena = crate::arena::init_for_test();
+        let cursor = |y| {
+            let pos = Point { x: 0, y };
+            TextBufferSelection { beg: pos, end: pos }
+        };
+
+        // Each cursor's line moves across its own neighbor.
+        let mut tb = buffer("0\n1\n2\n3\n4\n5");
+        tb.cursor_move_to_logical(Point { x: 0, y: 1 });
+        tb.extra_cursors.push(cursor(4));
+        tb.move_lines(-1);
+        assert_eq!(text(&mut tb), "1\n0\n2\n4\n3\n5");
+        assert_eq!(tb.cursor_logical_pos(), Point { x: 0, y: 0 });
+        assert_eq!(tb.extra_cursors[0].end, Point { x: 0, y: 3 });
+
+        // Cursors on adjacent lines move them as one block.
+        let mut tb = buffer("0\n1\n2\n3\n4\n5");
+        tb.cursor_move_to_logical(Point { x: 0, y: 2 });
+        tb.extra_cursors.push(cursor(1));
+        tb.extra_cursors.push(cursor(4));
+        tb.move_lines(1);
+        assert_eq!(text(&mut tb), "0\n3\n1\n2\n5\n4");
+
+        // Nothing moves if one of them can't.
+        tb.move_lines(1);
+        assert_eq!(text(&mut tb), "0\n3\n1\n2\n5\n4");
+    }
+
+    #[test]
+    fn test_detect_legacy_encoding() {
+        assert_eq!(detect_legacy_encoding("café".as_bytes()), None);
+        assert_eq!(detect_legacy_encoding(b"caf\xE9"), Some("ISO-8859-1"));
+        assert_eq!(detect_legacy_encoding(b"\x82\xA0\x82\xA2\x82"), Some("ISO-8859-1"));
+        assert_eq!(detect_legacy_encoding(b"\x82\xA0\x82\xA2"), Some("Shift_JIS"));
+
+        // A sample that was cut short may end in the middle of a character.
+        let mut bytes = vec![b'a'; LEGACY_SNIFF_LEN - 1];
+        bytes.extend_from_slice("é".as_bytes());
+        assert_eq!(detect_legacy_encoding(&bytes), None);
+    }
+}
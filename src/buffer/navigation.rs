@@ -38,15 +38,85 @@ const WORD_CLASSIFIER: [CharClass; 256] =
 
 /// Finds the next word boundary given a document cursor offset.
 /// Returns the offset of the next word boundary.
-pub fn word_forward(doc: &dyn ReadableDocument, offset: usize) -> usize {
+pub fn word_forward<D: ReadableDocument + ?Sized>(doc: &D, offset: usize) -> usize {
     word_navigation(WordForward { doc, offset, chunk: &[], chunk_off: 0 })
 }
 
 /// The backward version of `word_forward`.
-pub fn word_backward(doc: &dyn ReadableDocument, offset: usize) -> usize {
+pub fn word_backward<D: ReadableDocument + ?Sized>(doc: &D, offset: usize) -> usize {
     word_navigation(WordBackward { doc, offset, chunk: &[], chunk_off: 0 })
 }
 
+/// Like `word_forward`, but stops at sub-word boundaries within a word:
+/// Between `camel` and `Case`, `HTTP` and `Server`, before each `_` of `snake_case`
+/// and where letters and digits meet.
+pub fn subword_forward<D: ReadableDocument + ?Sized>(doc: &D, offset: usize) -> usize {
+    let end = word_forward(doc, offset);
+    let text = extract(doc, offset..end);
+
+    // Move by at least one character of the word, not counting leading underscores.
+    let Some(first) = text.iter().position(|&b| is_word_byte(b) && b != b'_') else {
+        return end;
+    };
+    (first + 1..text.len())
+        .find(|&i| is_subword_boundary(&text, i, true))
+        .map_or(end, |i| offset + i)
+}
+
+/// The backward version of `subword_forward`. Stops at the start of each sub-word instead.
+pub fn subword_backward<D: ReadableDocument + ?Sized>(doc: &D, offset: usize) -> usize {
+    let beg = word_backward(doc, offset);
+    let text = extract(doc, beg..offset);
+
+    // Move by at least one character of the word, not counting trailing underscores.
+    let Some(last) = text.iter().rposition(|&b| is_word_byte(b) && b != b'_') else {
+        return beg;
+    };
+    (1..=last).rev().find(|&i| is_subword_boundary(&text, i, false)).map_or(beg, |i| beg + i)
+}
+
+fn extract<D: ReadableDocument + ?Sized>(doc: &D, range: Range<usize>) -> Vec<u8> {
+    let mut text = Vec::with_capacity(range.len());
+    while text.len() < range.len() {
+        let chunk = doc.read_forward(range.start + text.len());
+        if chunk.is_empty() {
+            break;
+        }
+        text.extend_from_slice(&chunk[..chunk.len().min(range.len() - text.len())]);
+    }
+    text
+}
+
+fn is_word_byte(b: u8) -> bool {
+    WORD_CLASSIFIER[b as usize] == CharClass::Word
+}
+
+/// Returns true if there's a sub-word boundary between `text[i - 1]` and `text[i]`.
+/// Runs of underscores belong to the sub-word after them when moving `forward`
+/// and to the one before them otherwise, so that both directions skip over them.
+fn is_subword_boundary(text: &[u8], i: usize, forward: bool) -> bool {
+    let (prev, cur) = (text[i - 1], text[i]);
+    if !is_word_byte(prev) || !is_word_byte(cur) {
+        return false;
+    }
+    if (prev == b'_') != (cur == b'_') {
+        return if forward { cur == b'_' } else { prev == b'_' };
+    }
+    if prev.is_ascii_lowercase() && cur.is_ascii_uppercase() {
+        return true;
+    }
+    // The `S` in `HTTPServer`.
+    if prev.is_ascii_uppercase()
+        && cur.is_ascii_uppercase()
+        && text.get(i + 1).is_some_and(u8::is_ascii_lowercase)
+    {
+        return true;
+    }
+    prev.is_ascii_alphanumeric()
+        && cur.is_ascii_alphanumeric()
+        && prev.is_ascii_digit() != cur.is_ascii_digit()
+}
+
 /// Word navigation implementation. Matches the behavior of VS Code.
 fn word_navigation<T: WordNavigation>(mut nav: T) -> usize {
     // First, fill `self.chunk` with at least 1 grapheme.
@@ -87,14 +157,14 @@ trait WordNavigation {
     fn offset(&self) -> usize;
 }
 
-struct WordForward<'a> {
-    doc: &'a dyn ReadableDocument,
+struct WordForward<'a, D: ?Sized> {
+    doc: &'a D,
     offset: usize,
     chunk: &'a [u8],
     chunk_off: usize,
 }
 
-impl WordNavigation for WordForward<'_> {
+impl<D: ReadableDocument + ?Sized> WordNavigation for WordForward<'_, D> {
     fn read(&mut self) {
         self.chunk = self.doc.read_forward(self.offset);
         self.chunk_off = 0;
@@ -144,14 +214,14 @@ impl WordNavigation for WordForward<'_> {
     }
 }
 
-struct WordBackward<'a> {
-    doc: &'a dyn ReadableDocument,
+struct WordBackward<'a, D: ?Sized> {
+    doc: &'a D,
     offset: usize,
     chunk: &'a [u8],
     chunk_off: usize,
 }
 
-impl WordNavigation for WordBackward<'_> {
+impl<D: ReadableDocument + ?Sized> WordNavigation for WordBackward<'_, D> {
     fn read(&mut self) {
         self.chunk = self.doc.read_backward(self.offset);
         self.chunk_off = self.chunk.len();
@@ -287,4 +357,32 @@ mod test {
         assert_eq!(word_backward(&"Hello   ".as_bytes(), 7), 0);
         assert_eq!(word_backward(&"Hello\n\n".as_bytes(), 7), 6);
     }
+
+    #[test]
+    fn test_subword_navigation() {
+        let forward = |text: &str| {
+            let mut stops = vec![0];
+            while *stops.last().unwrap() < text.len() {
+                stops.push(subword_forward(&text.as_bytes(), *stops.last().unwrap()));
+            }
+            stops
+        };
+        let backward = |text: &str| {
+            let mut stops = vec![text.len()];
+            while *stops.last().unwrap() > 0 {
+                stops.push(subword_backward(&text.as_bytes(), *stops.last().unwrap()));
+            }
+            stops
+        };
+
+        assert_eq!(forward("camelCase"), [0, 5, 9]);
+        assert_eq!(forward("HTTPServer2go"), [0, 4, 10, 11, 13]);
+        assert_eq!(forward("_snake__case x"), [0, 6, 12, 14]);
+        assert_eq!(forward("a.fooBar"), [0, 1, 5, 8]);
+
+        assert_eq!(backward("camelCase"), [9, 5, 0]);
+        assert_eq!(backward("HTTPServer2go"), [13, 11, 10, 4, 0]);
+        assert_eq!(backward("_snake__case x"), [14, 13, 8, 1, 0]);
+        assert_eq!(backward("snake_case_"), [11, 6, 0]);
+    }
 }
@@ -0,0 +1,159 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Converting text between UPPER, lower, Title Case and the identifier styles,
+//! see [`crate::buffer::TextBuffer::convert_case()`].
+
+use std::fmt::Write as _;
+
+/// What to convert the text to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Upper,
+    Lower,
+    /// Capitalizes each word and lowercases the rest.
+    Title,
+    /// `camelCase`.
+    Camel,
+    /// `snake_case`.
+    Snake,
+    /// `kebab-case`.
+    Kebab,
+}
+
+impl Case {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "upper" => Self::Upper,
+            "lower" => Self::Lower,
+            "title" => Self::Title,
+            "camel" => Self::Camel,
+            "snake" => Self::Snake,
+            "kebab" => Self::Kebab,
+            _ => return None,
+        })
+    }
+
+    /// Converts `text`. The identifier styles split each line into words at punctuation,
+    /// whitespace and case changes, like `HTTPServer` into `HTTP` and `Server`,
+    /// but keep the indentation and anything trailing.
+    pub fn convert(self, text: &str) -> String {
+        match self {
+            Self::Upper => text.to_uppercase(),
+            Self::Lower => text.to_lowercase(),
+            Self::Title => title_case(text),
+            Self::Camel | Self::Snake | Self::Kebab => {
+                let mut result = String::with_capacity(text.len());
+                for (i, line) in text.split('\n').enumerate() {
+                    if i > 0 {
+                        result.push('\n');
+                    }
+                    let content = line.trim();
+                    let beg = line.len() - line.trim_start().len();
+                    result.push_str(&line[..beg]);
+                    result.push_str(&self.join(&split_words(content)));
+                    result.push_str(&line[beg + content.len()..]);
+                }
+                result
+            }
+        }
+    }
+
+    fn join(self, words: &[&str]) -> String {
+        let mut result = String::new();
+        for (i, word) in words.iter().enumerate() {
+            let word = word.to_lowercase();
+            match self {
+                Self::Camel if i > 0 => result.push_str(&capitalize(&word)),
+                Self::Snake if i > 0 => _ = write!(result, "_{word}"),
+                Self::Kebab if i > 0 => _ = write!(result, "-{word}"),
+                _ => result.push_str(&word),
+            }
+        }
+        result
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_word = false;
+    for c in text.chars() {
+        if in_word {
+            result.extend(c.to_lowercase());
+        } else {
+            result.extend(c.to_uppercase());
+        }
+        // "don't" is one word.
+        in_word = c.is_alphanumeric() || (in_word && c == '\'');
+    }
+    result
+}
+
+/// Splits an identifier or phrase into its words.
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut beg = None;
+    let mut chars = text.char_indices().peekable();
+    let mut prev: Option<char> = None;
+
+    while let Some((i, c)) = chars.next() {
+        if !c.is_alphanumeric() {
+            if let Some(b) = beg.take() {
+                words.push(&text[b..i]);
+            }
+            prev = None;
+            continue;
+        }
+        if let Some(p) = prev
+            && let Some(b) = beg
+        {
+            let next_lower = chars.peek().is_some_and(|&(_, n)| n.is_lowercase());
+            // `fooBar` and the `S` in `HTTPServer`.
+            if (p.is_lowercase() || p.is_numeric()) && c.is_uppercase()
+                || p.is_uppercase() && c.is_uppercase() && next_lower
+            {
+                words.push(&text[b..i]);
+                beg = Some(i);
+            }
+        }
+        beg.get_or_insert(i);
+        prev = Some(c);
+    }
+    if let Some(b) = beg {
+        words.push(&text[b..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("parseHTTPServer2Config"), ["parse", "HTTP", "Server2", "Config"]);
+        assert_eq!(split_words("max-line_length  x"), ["max", "line", "length", "x"]);
+        assert_eq!(split_words(""), [] as [&str; 0]);
+    }
+
+    #[test]
+    fn test_convert() {
+        let text = "  userID-map";
+        assert_eq!(Case::Upper.convert(text), "  USERID-MAP");
+        assert_eq!(Case::Lower.convert(text), "  userid-map");
+        assert_eq!(Case::Title.convert("the dog's BALL"), "The Dog's Ball");
+        assert_eq!(Case::Camel.convert(text), "  userIdMap");
+        assert_eq!(Case::Snake.convert("FooBar\n\tbaz qux \n"), "foo_bar\n\tbaz_qux \n");
+        assert_eq!(Case::Kebab.convert("MaxLineLength"), "max-line-length");
+        assert_eq!(Case::from_name("snake"), Some(Case::Snake));
+        assert_eq!(Case::from_name("pascal"), None);
+    }
+}
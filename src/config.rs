@@ -0,0 +1,1010 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! User configuration, read from a small subset of TOML.
+//!
+//! Supported are `[section]` / `[section.sub]` headers, `#` comments, and
+//! `key = value` pairs, where the value is a string, integer, boolean, or
+//! a single-line array of those. Keys are stored flattened, e.g. a `wrap`
+//! key in the `[filetype.markdown]` section becomes `filetype.markdown.wrap`.
+//!
+//! The config is loaded once at startup via [`init()`] and can then be
+//! retrieved from anywhere via [`get()`]. If nothing was loaded, [`get()`]
+//! returns an empty config and everyone falls back to their defaults.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use regex::Regex;
+
+use crate::helpers::{CoordType, MEBI};
+#[cfg(feature = "tui")]
+use crate::input::InputKey;
+use crate::syntax::FileType;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Installs the global config. Subsequent calls are ignored.
+pub fn init(config: Config) {
+    _ = CONFIG.set(config);
+}
+
+/// Ignores all config files from now on, so that problems caused by them can be told apart
+/// from those of edit itself. Must be called before the config is loaded.
+pub fn enable_safe_mode() {
+    SAFE_MODE.store(true, Ordering::Relaxed);
+}
+
+pub fn is_safe_mode() -> bool {
+    SAFE_MODE.load(Ordering::Relaxed)
+}
+
+/// Returns the global config, or an empty one if [`init()`] wasn't called.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// A parsed config value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Array(Vec<Value>),
+}
+
+/// A value along with the 1-based line it was defined on.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub value: Value,
+    pub line: usize,
+}
+
+/// A syntax error encountered while parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+/// How much whitespace the UI spends on the line number gutter, the status bar and the tab bar.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Density {
+    Compact,
+    #[default]
+    Normal,
+    Comfortable,
+}
+
+impl Density {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "compact" => Some(Self::Compact),
+            "normal" => Some(Self::Normal),
+            "comfortable" => Some(Self::Comfortable),
+            _ => None,
+        }
+    }
+}
+
+/// Which whitespace gets drawn as visible glyphs, like `·` for spaces and `→` for tabs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RenderWhitespace {
+    #[default]
+    None,
+    /// Only indentation and trailing whitespace.
+    Boundary,
+    /// All of it, including the line endings.
+    All,
+}
+
+impl RenderWhitespace {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(Self::None),
+            "boundary" => Some(Self::Boundary),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with the byte order mark of UTF-8 files when they're opened.
+/// The other Unicode encodings always have one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BomPolicy {
+    /// Keep it if there's one and don't add one otherwise.
+    #[default]
+    Keep,
+    Remove,
+    Add,
+}
+
+impl BomPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "keep" => Some(Self::Keep),
+            "remove" => Some(Self::Remove),
+            "add" => Some(Self::Add),
+            _ => None,
+        }
+    }
+}
+
+/// What the value of a key must be, see [`Config::validate()`].
+#[derive(Clone, Copy)]
+enum Kind {
+    Bool,
+    /// An integer in the given range.
+    Int(i64, i64),
+    Str,
+    /// A string or an array of strings.
+    StrArray,
+    /// One of the given strings.
+    OneOf(&'static [&'static str]),
+    /// A regular expression, or an array of them.
+    Regex,
+    /// A shortcut, see [`InputKey::parse()`].
+    Key,
+    /// An array of file type names.
+    FileTypes,
+}
+
+/// The settings that may be given per file type, both in `[editor]` and `[filetype.<name>]`.
+const FILE_TYPE_KEYS: &[(&str, Kind)] = &[
+    ("wrap", Kind::Bool),
+    ("wrap_column", Kind::Int(0, CoordType::MAX as i64)),
+    ("ruler", Kind::Int(0, CoordType::MAX as i64)),
+    ("trim_trailing_whitespace", Kind::Bool),
+    ("insert_final_newline", Kind::Bool),
+    ("normalize_indentation", Kind::Bool),
+    ("lint_whitespace", Kind::Bool),
+    ("theme", Kind::Str),
+    ("subword_navigation", Kind::Bool),
+    ("auto_pairs", Kind::Bool),
+    ("density", Kind::OneOf(&["compact", "normal", "comfortable"])),
+    ("render_whitespace", Kind::OneOf(&["none", "boundary", "all"])),
+    ("bom", Kind::OneOf(&["keep", "remove", "add"])),
+];
+
+/// All other keys. A `*` stands for the name of a subsection, e.g. `[linter.mylint]`.
+const KEYS: &[(&str, Kind)] = &[
+    ("editor.auto_compact_width", Kind::Int(0, CoordType::MAX as i64)),
+    ("editor.large_file_size", Kind::Int(0, i64::MAX)),
+    ("editor.guard_generated", Kind::Bool),
+    ("ui.alternate_screen", Kind::Bool),
+    ("ui.low_bandwidth", Kind::Bool),
+    ("ui.scroll_indicators", Kind::Bool),
+    ("ui.dim_inactive", Kind::Int(0, 100)),
+    ("ui.scrollbars", Kind::OneOf(&["none", "vertical", "horizontal", "both"])),
+    ("terminal.bold", Kind::Bool),
+    ("terminal.italic", Kind::Bool),
+    ("terminal.underline", Kind::Bool),
+    ("clipboard.osc52_limit", Kind::Int(0, i64::MAX)),
+    ("clipboard.osc52_read", Kind::Bool),
+    ("backup.mode", Kind::OneOf(&["tilde", "bak", "directory"])),
+    ("backup.directory", Kind::Str),
+    ("backup.keep", Kind::Int(1, i64::MAX)),
+    ("macro.*.key", Kind::Key),
+    ("formatter.*.enabled", Kind::Bool),
+    ("formatter.*.command", Kind::StrArray),
+    ("formatter.*.filetypes", Kind::FileTypes),
+    ("formatter.*.scope", Kind::OneOf(&["changes", "file"])),
+    ("formatter.*.timeout", Kind::Int(0, i64::MAX)),
+    ("linter.*.enabled", Kind::Bool),
+    ("linter.*.command", Kind::StrArray),
+    ("linter.*.filetypes", Kind::FileTypes),
+    ("linter.*.format", Kind::OneOf(&["regex", "json"])),
+    ("linter.*.pattern", Kind::Regex),
+    ("linter.*.items", Kind::Str),
+    ("linter.*.line", Kind::Str),
+    ("linter.*.column", Kind::Str),
+    ("linter.*.severity", Kind::Str),
+    ("linter.*.message", Kind::Str),
+    ("indent.*.increase", Kind::Regex),
+    ("indent.*.decrease", Kind::Regex),
+    ("indent.*.decrease_increase", Kind::Regex),
+    ("indent.*.tab_width", Kind::Int(1, i64::MAX)),
+    ("alternate.*.source", Kind::StrArray),
+    ("alternate.*.test", Kind::StrArray),
+    ("alternate.*.template", Kind::Str),
+];
+
+/// Returns the kind of `key`, or `None` if it's unknown.
+fn kind_of(key: &str) -> Option<Kind> {
+    let (section, name) = key.rsplit_once('.')?;
+    let file_type_key = || FILE_TYPE_KEYS.iter().find(|(k, _)| *k == name).map(|&(_, kind)| kind);
+
+    if is_per_file_type(section) && let Some(kind) = file_type_key() {
+        return Some(kind);
+    }
+    KEYS.iter()
+        .find(|(pattern, _)| {
+            let mut parts = pattern.split('.');
+            let mut key_parts = key.splitn(3, '.');
+            parts.all(|p| key_parts.next().is_some_and(|k| p == "*" || p == k))
+                && key_parts.next().is_none()
+        })
+        .map(|&(_, kind)| kind)
+}
+
+/// Returns whether `[section]` takes the [`FILE_TYPE_KEYS`].
+fn is_per_file_type(section: &str) -> bool {
+    section == "editor" || section.matches('.').count() == 1 && section.starts_with("filetype.")
+}
+
+/// Returns how the config `text`, which ends at the cursor, may go on: with a section name
+/// after a `[`, with a key of the current section at the start of a line, and with a value
+/// after `key =`. The candidates replace the returned number of bytes at the end of `text`.
+/// The theme names are passed in, as only the highlighter knows them.
+pub fn completions(text: &str, themes: &[String]) -> (usize, Vec<String>) {
+    let (before, line) = text.rsplit_once('\n').unwrap_or(("", text));
+    let section = before
+        .lines()
+        .rev()
+        .find_map(|l| strip_comment(l).trim().strip_prefix('[')?.strip_suffix(']'))
+        .map(|name| name.split('.').map(|s| unquote(s.trim())).collect::<Vec<_>>().join("."))
+        .unwrap_or_default();
+
+    let (partial, candidates) = if let Some(partial) = line.trim_start().strip_prefix('[') {
+        (partial, section_names())
+    } else if let Some((key, value)) = line.split_once('=') {
+        let key = unquote(key.trim());
+        let full = if section.is_empty() { key } else { format!("{section}.{key}") };
+        let Some(kind) = kind_of(&full) else {
+            return (0, Vec::new());
+        };
+        value_candidates(&full, kind, value.trim_start(), themes)
+    } else {
+        (line.trim_start(), key_names(&section))
+    };
+
+    // Strings may be completed before their opening quote is typed.
+    let typed = partial.trim_start_matches('"');
+    let candidates = candidates
+        .into_iter()
+        .filter(|c| {
+            let c = c.trim_start_matches('"');
+            c.len() > typed.len()
+                && c.get(..typed.len()).is_some_and(|h| h.eq_ignore_ascii_case(typed))
+        })
+        .collect();
+    (partial.len(), candidates)
+}
+
+/// The sections the [`KEYS`] are in. Those named by the user, like `[linter.mylint]`,
+/// are completed up to the dot.
+fn section_names() -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (pattern, _) in KEYS {
+        let Some((section, _)) = pattern.rsplit_once('.') else {
+            continue;
+        };
+        let name = section.strip_suffix('*').unwrap_or(section);
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names.extend(FileType::ALL.iter().map(|ft| format!("filetype.{}", ft.config_name())));
+    names
+}
+
+/// The keys that may be given in `[section]`.
+fn key_names(section: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    if is_per_file_type(section) {
+        names.extend(FILE_TYPE_KEYS.iter().map(|(name, _)| name.to_string()));
+    }
+    for (pattern, _) in KEYS {
+        let Some((pattern_section, name)) = pattern.rsplit_once('.') else {
+            continue;
+        };
+        let mut parts = section.split('.');
+        if pattern_section.split('.').all(|p| parts.next().is_some_and(|s| p == "*" || p == s))
+            && parts.next().is_none()
+        {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// The values `key` may have, and the part of `value` they replace.
+fn value_candidates<'a>(
+    key: &str,
+    kind: Kind,
+    value: &'a str,
+    themes: &[String],
+) -> (&'a str, Vec<String>) {
+    fn quoted<'s>(names: impl Iterator<Item = &'s str>) -> Vec<String> {
+        names.map(|name| format!("\"{name}\"")).collect()
+    }
+
+    match kind {
+        Kind::Bool => (value, vec!["true".to_string(), "false".to_string()]),
+        Kind::OneOf(choices) => (value, quoted(choices.iter().copied())),
+        Kind::Str if key.ends_with(".theme") => (value, quoted(themes.iter().map(String::as_str))),
+        Kind::FileTypes => {
+            // Only the last name of the array is completed.
+            let start = value.rfind(['[', ',']).map_or(0, |i| i + 1);
+            (value[start..].trim_start(), quoted(FileType::ALL.iter().map(|ft| ft.config_name())))
+        }
+        Kind::Key => {
+            // Only the part after the last `+` is, e.g. `"ctrl+al` becomes `"ctrl+alt+`.
+            let start = value.rfind(['"', '+']).map_or(0, |i| i + 1);
+            let used = &value[..start];
+            let modifiers = ["ctrl+", "alt+", "shift+"].into_iter().filter(|m| !used.contains(m));
+            let keys = (1..=12).map(|n| format!("f{n}"));
+            (&value[start..], modifiers.map(str::to_string).chain(keys).collect())
+        }
+        _ => (value, Vec::new()),
+    }
+}
+
+/// Checks `value` against `kind` and describes what's wrong with it.
+fn check_value(value: &Value, kind: Kind) -> Option<String> {
+    let strings = || match value {
+        Value::Str(s) => Some(vec![s.as_str()]),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Str(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    };
+
+    match (kind, value) {
+        (Kind::Bool, Value::Bool(_)) | (Kind::Str, Value::Str(_)) => None,
+        (Kind::Bool, _) => Some("must be `true` or `false`".to_string()),
+        (Kind::Str, _) => Some("must be a string".to_string()),
+        (Kind::Int(min, max), Value::Int(i)) if (min..=max).contains(i) => None,
+        (Kind::Int(min, max), Value::Int(_)) if max == i64::MAX => {
+            Some(format!("must be at least {min}"))
+        }
+        (Kind::Int(min, max), Value::Int(_)) => Some(format!("must be between {min} and {max}")),
+        (Kind::Int(..), _) => Some("must be an integer".to_string()),
+        (Kind::OneOf(choices), Value::Str(s)) if choices.contains(&s.as_str()) => None,
+        (Kind::OneOf(choices), _) => Some(format!("must be one of: {}", choices.join(", "))),
+        #[cfg(feature = "tui")]
+        (Kind::Key, Value::Str(s)) if InputKey::parse(s).is_some() => None,
+        // Without the terminal UI there are no shortcuts to check against.
+        #[cfg(not(feature = "tui"))]
+        (Kind::Key, Value::Str(_)) => None,
+        (Kind::Key, _) => Some("must be a shortcut like \"ctrl+alt+m\" or \"f5\"".to_string()),
+        (Kind::StrArray, _) => match strings() {
+            Some(_) => None,
+            None => Some("must be a string or an array of strings".to_string()),
+        },
+        (Kind::Regex, _) => match strings() {
+            Some(patterns) => patterns
+                .into_iter()
+                .find_map(|p| Regex::new(p).err())
+                .map(|err| format!("has an invalid regex: {err}")),
+            None => Some("must be a regex or an array of regexes".to_string()),
+        },
+        (Kind::FileTypes, _) => match strings() {
+            Some(names) => names
+                .into_iter()
+                .find(|name| FileType::from_config_name(name).is_none())
+                .map(|name| format!("has an unknown file type `{name}`")),
+            None => Some("must be an array of file type names".to_string()),
+        },
+    }
+}
+
+/// Editor settings that depend on the file type. See [`Config::file_type_settings()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTypeSettings {
+    pub word_wrap: bool,
+    /// The column word wrap breaks lines at, or 0 for the width of the editor.
+    pub wrap_column: CoordType,
+    /// The column of the ruler, or 0 if there's none.
+    pub ruler: CoordType,
+    /// Whether to remove trailing whitespace when saving.
+    pub trim_trailing_whitespace: bool,
+    /// Whether to end the file with a newline when saving.
+    pub insert_final_newline: bool,
+    /// Whether to convert mismatched indentation to the document's style when saving.
+    pub normalize_indentation: bool,
+    /// Whether to highlight trailing whitespace and mismatched indentation.
+    pub lint_whitespace: bool,
+    /// The syntax highlighting theme, or `None` for the default one.
+    pub theme: Option<String>,
+    /// Whether word motions stop within words at `camelCase` and `snake_case` boundaries.
+    pub subword_navigation: bool,
+    /// Whether typing a bracket or quote inserts its closing counterpart.
+    pub auto_pairs: bool,
+    pub density: Density,
+    pub render_whitespace: RenderWhitespace,
+    pub bom: BomPolicy,
+}
+
+#[derive(Default, Clone)]
+pub struct Config {
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, Entry>,
+    errors: Vec<ConfigError>,
+}
+
+impl Config {
+    /// Returns the path of the user's config file.
+    ///
+    /// That's `%APPDATA%\edit\config.toml` on Windows and
+    /// `$XDG_CONFIG_HOME/edit/config.toml` (or `~/.config/...`) elsewhere.
+    pub fn default_path() -> Option<PathBuf> {
+        Self::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Returns the directory that holds the config file(s).
+    pub fn config_dir() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        };
+        base.map(|b| b.join("edit"))
+    }
+
+    /// Returns the directory for data that's worth keeping, but can be recreated,
+    /// like search indices. That's `%LOCALAPPDATA%\edit` on Windows and
+    /// `$XDG_STATE_HOME/edit` (or `~/.local/state/edit`) elsewhere.
+    pub fn state_dir() -> Option<PathBuf> {
+        let base = if cfg!(windows) {
+            env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_STATE_HOME")
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+        };
+        base.map(|b| b.join("edit"))
+    }
+
+    /// Loads the config from [`Config::default_path()`].
+    /// A missing or unreadable file results in an empty config, as does the safe mode.
+    pub fn load() -> Self {
+        let Some(path) = Self::default_path().filter(|_| !is_safe_mode()) else {
+            return Self::default();
+        };
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::default(),
+        };
+        config.path = Some(path);
+        config
+    }
+
+    /// Looks for a project config, `.edit/config.toml`, in `dir` and its ancestors.
+    /// Returns the directory that contains `.edit`, the workspace root, along with the config.
+    /// There's none in the safe mode.
+    pub fn load_project(dir: &Path) -> Option<(PathBuf, Self)> {
+        if is_safe_mode() {
+            return None;
+        }
+        for root in dir.ancestors() {
+            let path = root.join(".edit").join("config.toml");
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                let mut config = Self::parse(&text);
+                config.path = Some(path);
+                return Some((root.to_path_buf(), config));
+            }
+        }
+        None
+    }
+
+    /// Adds the entries of `other` to `self`, replacing existing ones.
+    pub fn merge(&mut self, other: &Config) {
+        self.entries.extend(other.entries.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    /// Parses the given config text. Invalid lines are skipped and recorded in [`Config::errors()`].
+    pub fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+        let mut section = String::new();
+
+        for (idx, raw) in text.lines().enumerate() {
+            let line = idx + 1;
+            let trimmed = strip_comment(raw).trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(header) = trimmed.strip_prefix('[') {
+                match header.strip_suffix(']') {
+                    Some(name) if !name.trim().is_empty() => {
+                        section = name
+                            .split('.')
+                            .map(|s| unquote(s.trim()))
+                            .collect::<Vec<_>>()
+                            .join(".");
+                    }
+                    _ => config.error(line, "malformed section header"),
+                }
+                continue;
+            }
+
+            let Some((key, value)) = trimmed.split_once('=') else {
+                config.error(line, "expected `key = value`");
+                continue;
+            };
+
+            let key = unquote(key.trim());
+            if key.is_empty() {
+                config.error(line, "missing key");
+                continue;
+            }
+
+            match parse_value(value.trim()) {
+                Some(value) => {
+                    let full = if section.is_empty() { key } else { format!("{section}.{key}") };
+                    config.entries.insert(full, Entry { value, line });
+                }
+                None => config.error(line, "invalid value"),
+            }
+        }
+
+        config
+    }
+
+    fn error(&mut self, line: usize, message: &str) {
+        self.errors.push(ConfigError { line, message: message.to_string() });
+    }
+
+    /// The path the config was loaded from, if any.
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// Syntax errors encountered during parsing.
+    pub fn errors(&self) -> &[ConfigError] {
+        &self.errors
+    }
+
+    /// Returns the syntax errors along with the keys that edit doesn't know,
+    /// values of the wrong type and invalid values, sorted by line.
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut problems = self.errors.clone();
+
+        for (key, entry) in &self.entries {
+            let message = if let Some(name) = file_type_section(key)
+                && name != "commit"
+                && FileType::from_config_name(name).is_none()
+            {
+                Some(format!("unknown file type `{name}`"))
+            } else {
+                match kind_of(key) {
+                    Some(kind) => check_value(&entry.value, kind).map(|m| format!("`{key}` {m}")),
+                    None => Some(format!("unknown key `{key}`")),
+                }
+            };
+            if let Some(message) = message {
+                problems.push(ConfigError { line: entry.line, message });
+            }
+        }
+
+        problems.sort_by_key(|p| p.line);
+        problems
+    }
+
+    /// Iterates over all flattened keys and their entries.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Entry)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key).map(|e| &e.value)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns an array of strings. A single string is treated as a 1-element array.
+    pub fn get_str_array(&self, key: &str) -> Option<Vec<&str>> {
+        match self.get(key)? {
+            Value::Str(s) => Some(vec![s.as_str()]),
+            Value::Array(a) => a
+                .iter()
+                .map(|v| match v {
+                    Value::Str(s) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    /// Returns the settings for the file type with the given config name (see `FileType::config_name()`).
+    ///
+    /// Each setting is looked up in `[filetype.<name>]` first, then in the built-in
+    /// defaults for that file type, and finally in `[editor]`. `name` may also be
+    /// `"commit"`, which stands for git commit messages.
+    pub fn file_type_settings(&self, name: &str) -> FileTypeSettings {
+        let (word_wrap, ruler, trim) = match name {
+            "markdown" => (Some(true), None, None),
+            "commit" => (Some(true), Some(72), None),
+            "python" => (None, Some(80), None),
+            "rust" => (None, Some(100), None),
+            // Trailing whitespace is significant in patches: It's part of the context lines.
+            "diff" => (None, None, Some(false)),
+            _ => (None, None, None),
+        };
+        let bool_key = |key: &str, builtin: Option<bool>, default: bool| {
+            self.get_bool(&format!("filetype.{name}.{key}"))
+                .or(builtin)
+                .or_else(|| self.get_bool(&format!("editor.{key}")))
+                .unwrap_or(default)
+        };
+        let ruler = self
+            .get_int(&format!("filetype.{name}.ruler"))
+            .or(ruler)
+            .or_else(|| self.get_int("editor.ruler"))
+            .unwrap_or(0);
+        let wrap_column = self
+            .get_int(&format!("filetype.{name}.wrap_column"))
+            .or_else(|| self.get_int("editor.wrap_column"))
+            .unwrap_or(0);
+
+        FileTypeSettings {
+            word_wrap: bool_key("wrap", word_wrap, false),
+            wrap_column: wrap_column.clamp(0, CoordType::MAX as i64) as CoordType,
+            ruler: ruler.clamp(0, CoordType::MAX as i64) as CoordType,
+            trim_trailing_whitespace: bool_key("trim_trailing_whitespace", trim, false),
+            insert_final_newline: bool_key("insert_final_newline", None, false),
+            normalize_indentation: bool_key("normalize_indentation", None, false),
+            // The same goes for highlighting it.
+            lint_whitespace: bool_key("lint_whitespace", trim, true),
+            theme: self
+                .get_str(&format!("filetype.{name}.theme"))
+                .or_else(|| self.get_str("editor.theme"))
+                .map(str::to_string),
+            subword_navigation: bool_key("subword_navigation", None, false),
+            auto_pairs: bool_key("auto_pairs", None, true),
+            density: self
+                .get_str(&format!("filetype.{name}.density"))
+                .or_else(|| self.get_str("editor.density"))
+                .and_then(Density::parse)
+                .unwrap_or_default(),
+            render_whitespace: self
+                .get_str(&format!("filetype.{name}.render_whitespace"))
+                .or_else(|| self.get_str("editor.render_whitespace"))
+                .and_then(RenderWhitespace::parse)
+                .unwrap_or_default(),
+            bom: self
+                .get_str(&format!("filetype.{name}.bom"))
+                .or_else(|| self.get_str("editor.bom"))
+                .and_then(BomPolicy::parse)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns `editor.auto_compact_width`: Terminals narrower than this many columns
+    /// always use [`Density::Compact`]. 0 disables this.
+    pub fn auto_compact_width(&self) -> CoordType {
+        let width = self.get_int("editor.auto_compact_width").unwrap_or(100);
+        width.clamp(0, CoordType::MAX as i64) as CoordType
+    }
+
+    /// Returns `editor.large_file_size`, given in MiB: Files at least this large (50 MiB by
+    /// default) are opened in the large-file mode. 0 disables it, which returns `usize::MAX`.
+    pub fn large_file_size(&self) -> usize {
+        match self.get_int("editor.large_file_size").unwrap_or(50) {
+            ..=0 => usize::MAX,
+            mib => (mib as usize).saturating_mul(MEBI),
+        }
+    }
+
+    /// Returns the distinct names of all subsections of `prefix`.
+    /// For instance, `[indent.python]` and `[indent.lua]` yield `["lua", "python"]` for `"indent"`.
+    pub fn subsections(&self, prefix: &str) -> Vec<&str> {
+        let mut result: Vec<&str> = Vec::new();
+        for key in self.entries.keys() {
+            let Some(rest) = key.strip_prefix(prefix).and_then(|r| r.strip_prefix('.')) else {
+                continue;
+            };
+            if let Some((name, _)) = rest.split_once('.') {
+                result.push(name);
+            }
+        }
+        // The keys are sorted, so duplicates are adjacent.
+        result.dedup();
+        result
+    }
+}
+
+/// Returns the file type name of keys in sections named after file types,
+/// like `[filetype.python]` or `[indent.lua]`.
+fn file_type_section(key: &str) -> Option<&str> {
+    let mut parts = key.splitn(3, '.');
+    match (parts.next()?, parts.next()?, parts.next()) {
+        ("filetype" | "indent" | "alternate", name, Some(_)) => Some(name),
+        _ => None,
+    }
+}
+
+/// Removes a trailing `# comment`, unless the `#` is inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match in_str {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    in_str = None;
+                }
+            }
+            None if c == '"' || c == '\'' => in_str = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+fn unquote(s: &str) -> String {
+    match parse_value(s) {
+        Some(Value::Str(s)) => s,
+        _ => s.to_string(),
+    }
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    if let Some(inner) = s.strip_prefix('[') {
+        let inner = inner.strip_suffix(']')?;
+        let mut items = Vec::new();
+        for item in split_array(inner)? {
+            let item = item.trim();
+            if !item.is_empty() {
+                items.push(parse_value(item)?);
+            }
+        }
+        return Some(Value::Array(items));
+    }
+
+    if let Some(inner) = s.strip_prefix('\'') {
+        // Literal strings don't support escapes. Great for regexes.
+        return inner.strip_suffix('\'').map(|s| Value::Str(s.to_string()));
+    }
+
+    if let Some(inner) = s.strip_prefix('"') {
+        let inner = inner.strip_suffix('"')?;
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            out.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '\\' => '\\',
+                '"' => '"',
+                _ => return None,
+            });
+        }
+        return Some(Value::Str(out));
+    }
+
+    match s {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => s.replace('_', "").parse().ok().map(Value::Int),
+    }
+}
+
+/// Splits the inside of an array at top-level commas.
+fn split_array(s: &str) -> Option<Vec<&str>> {
+    let mut items = Vec::new();
+    let mut beg = 0;
+    let mut in_str = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match in_str {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    in_str = None;
+                }
+            }
+            None if c == '"' || c == '\'' => in_str = Some(c),
+            None if c == ',' => {
+                items.push(&s[beg..i]);
+                beg = i + 1;
+            }
+            None => {}
+        }
+    }
+    if in_str.is_some() {
+        return None;
+    }
+    items.push(&s[beg..]);
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            r##"
+            top = 1
+            [terminal]
+            italic = false # trailing comment
+            [indent.python]
+            increase = ['^\s*def .*:$', "#not a comment"]
+            tab_width = 4
+            [indent.lua]
+            increase = "then$"
+            broken
+            "##,
+        );
+
+        assert_eq!(config.get_int("top"), Some(1));
+        assert_eq!(config.get_bool("terminal.italic"), Some(false));
+        assert_eq!(
+            config.get_str_array("indent.python.increase"),
+            Some(vec![r"^\s*def .*:$", "#not a comment"])
+        );
+        assert_eq!(config.get_str_array("indent.lua.increase"), Some(vec!["then$"]));
+        assert_eq!(config.subsections("indent"), vec!["lua", "python"]);
+        assert_eq!(config.errors().len(), 1);
+        assert_eq!(config.errors()[0].line, 10);
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = Config::parse(
+            r#"
+            [editor]
+            ruler = "80"
+            density = "cozy"
+            wrapp = true
+            [filetype.pyhton]
+            wrap = true
+            [filetype.commit]
+            ruler = 50
+            [linter.mine]
+            command = ["mine", "-"]
+            pattern = '(?P<line>\d+'
+            filetypes = ["python", "cobol"]
+            [macro.wrap-div]
+            key = "ctrl+alt+d"
+            [ui]
+            dim_inactive = 150
+            "#,
+        );
+
+        let problems: Vec<_> =
+            config.validate().into_iter().map(|p| (p.line, p.message)).collect();
+        let lines: Vec<_> = problems.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [3, 4, 5, 7, 12, 13, 17]);
+        assert_eq!(problems[0].1, "`editor.ruler` must be an integer");
+        assert_eq!(problems[1].1, "`editor.density` must be one of: compact, normal, comfortable");
+        assert_eq!(problems[2].1, "unknown key `editor.wrapp`");
+        assert_eq!(problems[3].1, "unknown file type `pyhton`");
+        assert!(problems[4].1.starts_with("`linter.mine.pattern` has an invalid regex"));
+        assert_eq!(problems[5].1, "`linter.mine.filetypes` has an unknown file type `cobol`");
+        assert_eq!(problems[6].1, "`ui.dim_inactive` must be between 0 and 100");
+    }
+
+    #[test]
+    fn test_file_type_settings() {
+        let config = Config::parse(
+            r#"
+            [editor]
+            trim_trailing_whitespace = true
+            [filetype.rust]
+            wrap = true
+            wrap_column = 80
+            subword_navigation = true
+            auto_pairs = false
+            theme = "base16-ocean.dark"
+            insert_final_newline = true
+            [filetype.markdown]
+            density = "comfortable"
+            render_whitespace = "boundary"
+            bom = "remove"
+            "#,
+        );
+
+        let rust = config.file_type_settings("rust");
+        assert_eq!(
+            rust,
+            FileTypeSettings {
+                word_wrap: true,
+                wrap_column: 80,
+                ruler: 100,
+                trim_trailing_whitespace: true,
+                insert_final_newline: true,
+                normalize_indentation: false,
+                lint_whitespace: true,
+                theme: Some("base16-ocean.dark".to_string()),
+                subword_navigation: true,
+                auto_pairs: false,
+                density: Density::Normal,
+                render_whitespace: RenderWhitespace::None,
+                bom: BomPolicy::Keep,
+            }
+        );
+        // The built-in default for diffs beats the `[editor]` section.
+        assert!(!config.file_type_settings("diff").trim_trailing_whitespace);
+        assert!(!config.file_type_settings("diff").lint_whitespace);
+        assert_eq!(config.file_type_settings("commit").ruler, 72);
+        assert_eq!(config.file_type_settings("python").ruler, 80);
+        assert_eq!(config.file_type_settings("plain").ruler, 0);
+        assert!(Config::default().file_type_settings("markdown").word_wrap);
+        assert_eq!(config.file_type_settings("markdown").theme, None);
+        assert_eq!(config.file_type_settings("markdown").density, Density::Comfortable);
+        assert_eq!(
+            config.file_type_settings("markdown").render_whitespace,
+            RenderWhitespace::Boundary
+        );
+        assert_eq!(config.file_type_settings("markdown").bom, BomPolicy::Remove);
+        assert!(config.file_type_settings("markdown").auto_pairs);
+        assert_eq!(config.auto_compact_width(), 100);
+        assert_eq!(config.large_file_size(), 50 * MEBI);
+        assert_eq!(Config::parse("[editor]\nlarge_file_size = 0").large_file_size(), usize::MAX);
+    }
+
+    #[test]
+    fn test_completions() {
+        let themes = ["base16-ocean.dark".to_string(), "InspiredGitHub".to_string()];
+        let complete = |text| completions(text, &themes);
+
+        assert_eq!(complete("[te"), (2, vec!["terminal".to_string()]));
+        assert!(complete("[lin").1.contains(&"linter.".to_string()));
+        assert_eq!(complete("[ui]\nscroll").1, vec!["scroll_indicators", "scrollbars"]);
+        assert_eq!(complete("[filetype.rust]\nsub").1, vec!["subword_navigation"]);
+        assert!(complete("[linter.mylint]\nsub").1.is_empty());
+        assert_eq!(complete("[linter.mylint]\nfor").1, vec!["format"]);
+
+        assert_eq!(complete("[ui]\nalternate_screen = t"), (1, vec!["true".to_string()]));
+        assert_eq!(complete("[backup]\nmode = \"d").1, vec!["\"directory\""]);
+        assert_eq!(complete("[editor]\ntheme = ins"), (3, vec!["\"InspiredGitHub\"".to_string()]));
+        assert_eq!(
+            complete("[linter.x]\nfiletypes = [\"rust\", py"),
+            (2, vec!["\"python\"".to_string()])
+        );
+        assert_eq!(complete("[macro.x]\nkey = \"ctrl+al"), (2, vec!["alt+".to_string()]));
+        assert_eq!(complete("[macro.x]\nkey = \"ctrl+f1").1, vec!["f10", "f11", "f12"]);
+        assert!(complete("[ui]\nunknown = t").1.is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A per-document store of diagnostics, e.g. the results of an external linter.
+//!
+//! Diagnostics are grouped by their source (`"ruff"`, `"mypy"`, ...),
+//! so that each producer can replace its own results without touching the others.
+//!
+//! This module also contains the whitespace lint, which is cheap enough to be
+//! run on the visible lines during rendering instead of going through the store.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::document::IndentStyle;
+use crate::helpers::CoordType;
+
+/// How bad a [`Diagnostic`] is. Ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Parses the severity names and numbers that linters commonly use.
+    /// Unknown values are treated as [`Severity::Warning`].
+    pub fn parse(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "error" | "fatal" | "e" | "2" => Severity::Error,
+            "info" | "information" | "note" | "hint" | "style" | "i" | "0" => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 0-based logical line.
+    pub line: CoordType,
+    /// 0-based column, in characters.
+    pub column: CoordType,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct Diagnostics {
+    by_source: BTreeMap<String, Vec<Diagnostic>>,
+    generation: u32,
+}
+
+impl Diagnostics {
+    /// Replaces all diagnostics of the given source.
+    pub fn set(&mut self, source: &str, mut diagnostics: Vec<Diagnostic>) {
+        diagnostics.sort_by_key(|d| (d.line, d.column));
+        if diagnostics.is_empty() {
+            self.by_source.remove(source);
+        } else {
+            self.by_source.insert(source.to_string(), diagnostics);
+        }
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Removes the diagnostics of all sources.
+    pub fn clear(&mut self) {
+        self.by_source.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Increments whenever the diagnostics change.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_source.is_empty()
+    }
+
+    /// Returns the number of diagnostics across all sources.
+    pub fn len(&self) -> usize {
+        self.by_source.values().map(Vec::len).sum()
+    }
+
+    /// Iterates over all diagnostics as `(source, diagnostic)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Diagnostic)> {
+        self.by_source.iter().flat_map(|(s, list)| list.iter().map(move |d| (s.as_str(), d)))
+    }
+
+    /// Returns the diagnostics on the given logical line, most severe first.
+    pub fn on_line(&self, line: CoordType) -> Vec<(&str, &Diagnostic)> {
+        let mut result: Vec<_> = self
+            .by_source
+            .iter()
+            .flat_map(|(s, list)| {
+                let beg = list.partition_point(|d| d.line < line);
+                let end = list.partition_point(|d| d.line <= line);
+                list[beg..end].iter().map(move |d| (s.as_str(), d))
+            })
+            .collect();
+        result.sort_by_key(|(_, d)| Reverse(d.severity));
+        result
+    }
+
+    /// Returns the highest severity on the given logical line, if any.
+    pub fn severity_on_line(&self, line: CoordType) -> Option<Severity> {
+        self.on_line(line).first().map(|(_, d)| d.severity)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitespaceIssueKind {
+    /// Spaces or tabs at the end of the line.
+    Trailing,
+    /// A tab following a space in the indentation.
+    TabAfterSpace,
+    /// Tabs in a document indented with spaces, or a full level of spaces in one indented with tabs.
+    IndentStyleMismatch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhitespaceIssue {
+    pub kind: WhitespaceIssueKind,
+    /// Byte offsets within the line.
+    pub range: Range<usize>,
+}
+
+/// Checks the whitespace of a single line, without its line ending, against the document's style.
+pub fn whitespace_issues(line: &[u8], style: IndentStyle) -> Vec<WhitespaceIssue> {
+    let mut issues = Vec::new();
+    let indent_end = line.iter().take_while(|&&c| c == b' ' || c == b'\t').count();
+
+    if indent_end == line.len() {
+        if indent_end > 0 {
+            issues.push(WhitespaceIssue {
+                kind: WhitespaceIssueKind::Trailing,
+                range: 0..indent_end,
+            });
+        }
+        return issues;
+    }
+
+    let indent = &line[..indent_end];
+    if let Some(tab) = indent.windows(2).position(|w| w == b" \t") {
+        issues.push(WhitespaceIssue {
+            kind: WhitespaceIssueKind::TabAfterSpace,
+            range: tab..indent_end,
+        });
+    } else if mismatches_style(indent, style) {
+        issues.push(WhitespaceIssue {
+            kind: WhitespaceIssueKind::IndentStyleMismatch,
+            range: 0..indent_end,
+        });
+    }
+
+    let content_end = line.iter().rposition(|&c| c != b' ' && c != b'\t').map_or(0, |i| i + 1);
+    if content_end < line.len() {
+        issues.push(WhitespaceIssue {
+            kind: WhitespaceIssueKind::Trailing,
+            range: content_end..line.len(),
+        });
+    }
+
+    issues
+}
+
+fn mismatches_style(indent: &[u8], style: IndentStyle) -> bool {
+    if style.use_tabs {
+        // Spaces after the tabs are fine for alignment, as long as they're less than a level.
+        let spaces = indent.iter().rev().take_while(|&&c| c == b' ').count();
+        indent[..indent.len() - spaces].contains(&b' ') || spaces >= style.width.max(1) as usize
+    } else {
+        indent.contains(&b'\t')
+    }
+}
+
+/// Returns the line with its trailing whitespace removed and its indentation
+/// converted to `style`, or `None` if it's fine as it is.
+pub fn fix_whitespace(line: &[u8], style: IndentStyle) -> Option<Vec<u8>> {
+    let issues = whitespace_issues(line, style);
+    if issues.is_empty() {
+        return None;
+    }
+
+    let indent_end = line.iter().take_while(|&&c| c == b' ' || c == b'\t').count();
+    let content_end = line.iter().rposition(|&c| c != b' ' && c != b'\t').map_or(0, |i| i + 1);
+    let mut fixed = Vec::with_capacity(line.len());
+
+    if indent_end < content_end {
+        let indent = &line[..indent_end];
+        if issues.iter().any(|i| i.kind != WhitespaceIssueKind::Trailing) {
+            reindent(indent, style, &mut fixed);
+        } else {
+            fixed.extend_from_slice(indent);
+        }
+        fixed.extend_from_slice(&line[indent_end..content_end]);
+    }
+
+    Some(fixed)
+}
+
+/// Returns the line with its indentation converted to `style`,
+/// or `None` if it's fine as it is. Unlike [`fix_whitespace()`], trailing whitespace is kept.
+pub fn normalize_indentation(line: &[u8], style: IndentStyle) -> Option<Vec<u8>> {
+    let issues = whitespace_issues(line, style);
+    if issues.iter().all(|i| i.kind == WhitespaceIssueKind::Trailing) {
+        return None;
+    }
+
+    let indent_end = line.iter().take_while(|&&c| c == b' ' || c == b'\t').count();
+    let mut fixed = Vec::with_capacity(line.len());
+    reindent(&line[..indent_end], style, &mut fixed);
+    fixed.extend_from_slice(&line[indent_end..]);
+    Some(fixed)
+}
+
+/// Appends the indentation `indent` spans, written in `style`, to `out`.
+fn reindent(indent: &[u8], style: IndentStyle, out: &mut Vec<u8>) {
+    let tab_size = style.width.max(1) as usize;
+    let columns = indent.iter().fold(0, |col, &c| {
+        if c == b'\t' { (col / tab_size + 1) * tab_size } else { col + 1 }
+    });
+    if style.use_tabs {
+        out.resize(out.len() + columns / tab_size, b'\t');
+        out.resize(out.len() + columns % tab_size, b' ');
+    } else {
+        out.resize(out.len() + columns, b' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diag(line: CoordType, severity: Severity) -> Diagnostic {
+        Diagnostic { line, column: 0, severity, message: String::new() }
+    }
+
+    #[test]
+    fn test_sources() {
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.set("ruff", vec![diag(3, Severity::Warning), diag(1, Severity::Info)]);
+        diagnostics.set("mypy", vec![diag(3, Severity::Error)]);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics.severity_on_line(3), Some(Severity::Error));
+        assert_eq!(diagnostics.severity_on_line(1), Some(Severity::Info));
+        assert_eq!(diagnostics.severity_on_line(2), None);
+
+        // Each source replaces only its own results.
+        diagnostics.set("mypy", Vec::new());
+        assert_eq!(diagnostics.severity_on_line(3), Some(Severity::Warning));
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn test_whitespace_issues() {
+        use WhitespaceIssueKind::*;
+
+        let spaces = IndentStyle { use_tabs: false, width: 4 };
+        let tabs = IndentStyle { use_tabs: true, width: 4 };
+        let kinds = |line: &str, style| {
+            whitespace_issues(line.as_bytes(), style)
+                .into_iter()
+                .map(|i| (i.kind, i.range))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(kinds("    foo();", spaces), vec![]);
+        assert_eq!(kinds("    foo();  ", spaces), vec![(Trailing, 10..12)]);
+        assert_eq!(kinds("  \t", spaces), vec![(Trailing, 0..3)]);
+        assert_eq!(kinds("  \tfoo", spaces), vec![(TabAfterSpace, 1..3)]);
+        assert_eq!(kinds("\tfoo", spaces), vec![(IndentStyleMismatch, 0..1)]);
+        assert_eq!(kinds("\t  foo", tabs), vec![]);
+        assert_eq!(kinds("\t    foo", tabs), vec![(IndentStyleMismatch, 0..5)]);
+
+        let fix = |line: &str, style| {
+            fix_whitespace(line.as_bytes(), style).map(|v| String::from_utf8(v).unwrap())
+        };
+        assert_eq!(fix("    foo();", spaces), None);
+        assert_eq!(fix("\t  foo \t", spaces).as_deref(), Some("      foo"));
+        assert_eq!(fix("      foo", tabs).as_deref(), Some("\t  foo"));
+        assert_eq!(fix("\t  ", tabs).as_deref(), Some(""));
+        // Only trailing whitespace? The indentation is left alone.
+        assert_eq!(fix("\tfoo ", tabs).as_deref(), Some("\tfoo"));
+
+        let normalize = |line: &str, style| {
+            normalize_indentation(line.as_bytes(), style).map(|v| String::from_utf8(v).unwrap())
+        };
+        assert_eq!(normalize("\tfoo ", tabs), None);
+        assert_eq!(normalize("\t  foo \t", spaces).as_deref(), Some("      foo \t"));
+        assert_eq!(normalize("  \t", spaces), None);
+    }
+}
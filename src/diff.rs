@@ -0,0 +1,232 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Line diffs, e.g. between a document and its file on disk.
+//!
+//! [`diff()`] uses Myers' O(ND) algorithm, which finds the fewest lines to delete and insert.
+//! It keeps the furthest reaching path of every round around for the backtracking, which
+//! costs O(D²) memory, so it gives up and reports a single change if there are too many.
+
+use std::ops::Range;
+
+/// Beyond this many saved path ends, [`diff()`] reports everything between the common
+/// prefix and suffix as one change. That's about 3000 changed lines.
+const MAX_TRACE_LEN: usize = 8 * 1024 * 1024;
+
+/// Lines that differ: `old` were replaced with `new`. One of them may be empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// Splits `text` into lines, each with its line ending.
+pub fn lines(text: &[u8]) -> Vec<&[u8]> {
+    text.split_inclusive(|&b| b == b'\n').collect()
+}
+
+/// Returns the changes that turn `old` into `new`, in order.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    // Usually most lines at the start and end didn't change. They're cheap to skip.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    if a.is_empty() && b.is_empty() {
+        return Vec::new();
+    }
+    let Some(matches) = matching_lines(a, b) else {
+        return vec![Hunk { old: prefix..prefix + a.len(), new: prefix..prefix + b.len() }];
+    };
+
+    // Everything between two matching lines is a change.
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (mx, my) in matches.into_iter().chain([(a.len(), b.len())]) {
+        if mx > x || my > y {
+            hunks.push(Hunk { old: prefix + x..prefix + mx, new: prefix + y..prefix + my });
+        }
+        (x, y) = (mx + 1, my + 1);
+    }
+    hunks
+}
+
+/// Returns the pairs of indices of the lines that `a` and `b` have in common, in order.
+/// Returns `None` if that would take too long.
+fn matching_lines<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<(usize, usize)>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let offset = max;
+    // `v[k + offset]` is how far `x` got on diagonal `k = x - y`.
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    // The relevant part of `v` after each round `d`: the diagonals `-d..=d`.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut trace_len = 0;
+
+    'rounds: for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1] // Down: Insert a line of `b`.
+            } else {
+                v[i - 1] + 1 // Right: Delete a line of `a`.
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+                break 'rounds;
+            }
+        }
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        trace_len += 2 * d as usize + 1;
+        if trace_len > MAX_TRACE_LEN {
+            return None;
+        }
+    }
+
+    // Walk the path back from the end, collecting the diagonal steps.
+    let mut matches = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = &trace[d as usize - 1];
+        let prev_v = |k: isize| prev[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k =
+            if k == -d || (k != d && prev_v(k - 1) < prev_v(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = prev_v(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            matches.push((x as usize, y as usize));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    // The first round starts at the origin.
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        matches.push((x as usize, y as usize));
+    }
+
+    matches.reverse();
+    Some(matches)
+}
+
+/// Formats the `hunks` between the lines `old` and `new` like `diff -u`,
+/// with `context` unchanged lines around the changes.
+pub fn unified(
+    old: &[&[u8]],
+    new: &[&[u8]],
+    hunks: &[Hunk],
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+    let mut i = 0;
+
+    while i < hunks.len() {
+        // Changes that are close together share their context.
+        let mut j = i;
+        while j + 1 < hunks.len() && hunks[j + 1].old.start - hunks[j].old.end <= 2 * context {
+            j += 1;
+        }
+
+        let (first, last) = (&hunks[i], &hunks[j]);
+        let before = context.min(first.old.start).min(first.new.start);
+        let after = context.min(old.len() - last.old.end).min(new.len() - last.new.end);
+        let old_range = first.old.start - before..last.old.end + after;
+        let new_range = first.new.start - before..last.new.end + after;
+
+        // An empty range is given as the line before it.
+        let start = |r: &Range<usize>| if r.is_empty() { r.start } else { r.start + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start(&old_range),
+            old_range.len(),
+            start(&new_range),
+            new_range.len()
+        ));
+
+        let mut pos = old_range.start;
+        for hunk in &hunks[i..=j] {
+            push_lines(&mut out, ' ', &old[pos..hunk.old.start]);
+            push_lines(&mut out, '-', &old[hunk.old.clone()]);
+            push_lines(&mut out, '+', &new[hunk.new.clone()]);
+            pos = hunk.old.end;
+        }
+        push_lines(&mut out, ' ', &old[pos..old_range.end]);
+
+        i = j + 1;
+    }
+    out
+}
+
+fn push_lines(out: &mut String, prefix: char, lines: &[&[u8]]) {
+    for line in lines {
+        out.push(prefix);
+        out.push_str(&String::from_utf8_lossy(line));
+        if !line.ends_with(b"\n") {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let hunk = |old: Range<usize>, new: Range<usize>| Hunk { old, new };
+
+        assert_eq!(diff(b"abc", b"abc"), []);
+        assert_eq!(diff(b"", b"ab"), [hunk(0..0, 0..2)]);
+        assert_eq!(diff(b"abcdef", b"axcdyf"), [hunk(1..2, 1..2), hunk(4..5, 4..5)]);
+        assert_eq!(diff(b"abcabba", b"cbabac"), [
+            hunk(0..2, 0..0),
+            hunk(3..3, 1..2),
+            hunk(5..6, 4..4),
+            hunk(7..7, 5..6),
+        ]);
+
+        // Applying the hunks to the old text must give the new one.
+        let (old, new) = (b"the quick brown fox jumps", b"a quick red fox jumped up");
+        let mut result = Vec::new();
+        let mut pos = 0;
+        for h in diff(old, new) {
+            result.extend_from_slice(&old[pos..h.old.start]);
+            result.extend_from_slice(&new[h.new]);
+            pos = h.old.end;
+        }
+        result.extend_from_slice(&old[pos..]);
+        assert_eq!(result, new);
+    }
+
+    #[test]
+    fn test_unified() {
+        let old = lines(b"1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+        let new = lines(b"1\n2\nthree\n4\n5\n6\n7\n8\n9\n10");
+        let text = unified(&old, &new, &diff(&old, &new), "a", "b", 2);
+        assert_eq!(
+            text,
+            concat!(
+                "--- a\n+++ b\n",
+                "@@ -1,5 +1,5 @@\n 1\n 2\n-3\n+three\n 4\n 5\n",
+                "@@ -8,2 +8,3 @@\n 8\n 9\n+10\n\\ No newline at end of file\n",
+            )
+        );
+    }
+}
@@ -3,29 +3,511 @@
 
 //! Abstractions over reading/writing arbitrary text containers.
 
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell, RefCell};
 use std::ffi::OsString;
+use std::fs::File;
+use std::io::{Read as _, Seek as _, SeekFrom};
 use std::mem;
 use std::ops::Range;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use crate::apperr;
 use crate::arena::{ArenaString, scratch_arena};
-use crate::helpers::ReplaceRange as _;
+use crate::buffer::{self, GapBuffer};
+use crate::diff::{self, Hunk};
+use crate::helpers::{CoordType, KIBI, ReplaceRange as _};
+use crate::line_index::LineIndex;
 use crate::syntax::{SyntaxHighlighter, FileType};
+use crate::unicode;
 
-/// A document with syntax highlighting capabilities
+/// How a document is indented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentStyle {
+    /// Whether lines are indented with tabs rather than spaces.
+    pub use_tabs: bool,
+    /// The number of columns per indentation level. For tabs that's their visual size.
+    pub width: CoordType,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self { use_tabs: false, width: 4 }
+    }
+}
+
+impl IndentStyle {
+    /// Infers the indentation style from the first 1000 lines of `text`.
+    /// Falls back to the default of 4 spaces if there's no indentation to go by.
+    pub fn detect(text: &[u8]) -> Self {
+        let mut offset = 0;
+        let mut lines = 0;
+        // Number of lines starting with a tab.
+        let mut tab_indentations = 0;
+        // Number of lines starting with a space.
+        let mut space_indentations = 0;
+        // Histogram of the indentation depth of lines starting with between 2 and 8 spaces.
+        // In other words, `space_indentation_sizes[0]` is the number of lines starting with 2 spaces.
+        let mut space_indentation_sizes = [0; 7];
+
+        loop {
+            // Check if the line starts with a tab.
+            if offset < text.len() && text[offset] == b'\t' {
+                tab_indentations += 1;
+            } else {
+                // Otherwise, check how many spaces the line starts with. Searching for >8 spaces
+                // allows us to reject lines that have more than 1 level of indentation.
+                let space_indentation =
+                    text[offset..].iter().take(9).take_while(|&&c| c == b' ').count();
+
+                // We'll also reject lines starting with 1 space, because that's too fickle as a heuristic.
+                if (2..=8).contains(&space_indentation) {
+                    space_indentations += 1;
+
+                    // If we encounter an indentation depth of 6, it may either be a 6-space indentation,
+                    // two 3-space indentation or 3 2-space indentations. To make this work, we increment
+                    // all 3 possible histogram slots.
+                    //   2 -> 2
+                    //   3 -> 3
+                    //   4 -> 4 2
+                    //   5 -> 5
+                    //   6 -> 6 3 2
+                    //   7 -> 7
+                    //   8 -> 8 4 2
+                    space_indentation_sizes[space_indentation - 2] += 1;
+                    if space_indentation & 4 != 0 {
+                        space_indentation_sizes[0] += 1;
+                    }
+                    if space_indentation == 6 || space_indentation == 8 {
+                        space_indentation_sizes[space_indentation / 2 - 2] += 1;
+                    }
+                }
+            }
+
+            (offset, lines) = unicode::newlines_forward(text, offset, lines, lines + 1);
+
+            // We'll limit our heuristics to the first 1000 lines.
+            // That should hopefully be enough in practice.
+            if offset >= text.len() || lines >= 1000 {
+                break;
+            }
+        }
+
+        // We'll assume tabs if there are more lines starting with tabs than with spaces.
+        if tab_indentations > space_indentations {
+            // Tabs will get a visual size of 4 spaces by default.
+            return Self { use_tabs: true, width: 4 };
+        }
+
+        // Otherwise, we'll assume the most common indentation depth.
+        // If there are conflicting indentation depths, we'll prefer the maximum, because in the loop
+        // above we incremented the histogram slot for 2-spaces when encountering 4-spaces and so on.
+        let mut max = 1;
+        let mut width = 4;
+        for (i, &count) in space_indentation_sizes.iter().enumerate() {
+            if count >= max {
+                max = count;
+                width = i as CoordType + 2;
+            }
+        }
+        Self { use_tabs: false, width }
+    }
+}
+
+/// A single [`WriteableDocument::replace()`] on a [`Document`], kept for undo and redo.
+struct HistoryEntry {
+    /// Where the replacement happened.
+    offset: usize,
+    /// The text that got replaced.
+    deleted: Vec<u8>,
+    /// The text it was replaced with.
+    added: Vec<u8>,
+}
+
+impl HistoryEntry {
+    fn new(delta: &EditDelta) -> Self {
+        Self {
+            offset: delta.removed_range.start,
+            deleted: delta.old_text.to_vec(),
+            added: delta.new_text.to_vec(),
+        }
+    }
+
+    /// Whether `self` is a single typed character that directly follows `prev`,
+    /// in which case both can be undone at once.
+    fn continues_typing(&self, prev: &Self) -> bool {
+        prev.deleted.is_empty()
+            && self.deleted.is_empty()
+            && self.offset == prev.offset + prev.added.len()
+            && str::from_utf8(&self.added).is_ok_and(|s| s.chars().count() == 1)
+    }
+}
+
+/// A change to a document, as returned by [`WriteableDocument::replace_ext()`] and passed to
+/// the listeners registered via [`Document::on_change()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditDelta<'a> {
+    /// The range that got replaced, in offsets from before the change.
+    pub removed_range: Range<usize>,
+    /// The text that got replaced.
+    pub old_text: Cow<'a, [u8]>,
+    /// The text that's now at `removed_range.start`.
+    pub new_text: Cow<'a, [u8]>,
+}
+
+impl EditDelta<'_> {
+    /// The length of the inserted text.
+    pub fn inserted_len(&self) -> usize {
+        self.new_text.len()
+    }
+
+    /// The range of the inserted text, in offsets from after the change.
+    pub fn inserted_range(&self) -> Range<usize> {
+        self.removed_range.start..self.removed_range.start + self.inserted_len()
+    }
+
+    /// Whether nothing changed, e.g. because the document is read-only.
+    pub fn is_empty(&self) -> bool {
+        self.old_text.is_empty() && self.new_text.is_empty()
+    }
+}
+
+/// Identifies a listener registered via [`Document::on_change()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeListenerId(u32);
+
+type ChangeListener = Box<dyn FnMut(&EditDelta)>;
+
+/// The text of a [`Document`] at some point, see [`Document::snapshot()`].
+/// It never changes, and clones share the text, also with other threads.
+///
+/// The text is kept in chunks that end at a newline, except for the last one,
+/// and snapshots share the chunks that didn't change between them.
+#[derive(Clone)]
+pub struct Snapshot {
+    revision: u64,
+    chunks: Arc<[SnapshotChunk]>,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct SnapshotChunk {
+    /// Where the chunk starts in the snapshot.
+    offset: usize,
+    text: Arc<[u8]>,
+    /// The number of lines in `text`, as counted by [`diff::lines()`].
+    lines: usize,
+}
+
+impl Snapshot {
+    /// Chunks get cut at the first newline after this many bytes.
+    const CHUNK_SIZE: usize = 64 * KIBI;
+
+    /// The length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Copies the text into a `Vec`.
+    pub fn text(&self) -> Vec<u8> {
+        let mut text = Vec::with_capacity(self.len);
+        for chunk in self.chunks.iter() {
+            text.extend_from_slice(&chunk.text);
+        }
+        text
+    }
+
+    /// Splits `text`, which starts at `offset`, into chunks and appends them to `chunks`.
+    fn push_chunks(chunks: &mut Vec<SnapshotChunk>, mut offset: usize, mut text: &[u8]) {
+        while !text.is_empty() {
+            let min = Self::CHUNK_SIZE.min(text.len());
+            let newline = text[min..].iter().position(|&b| b == b'\n');
+            let len = newline.map_or(text.len(), |i| min + i + 1);
+            let (chunk, rest) = text.split_at(len);
+            let lines = diff::lines(chunk).len();
+            chunks.push(SnapshotChunk { offset, text: chunk.into(), lines });
+            offset += len;
+            text = rest;
+        }
+    }
+
+    /// The lines of `chunks`, as [`diff::lines()`] would split their text.
+    fn lines(chunks: &[SnapshotChunk]) -> Vec<&[u8]> {
+        chunks.iter().flat_map(|chunk| diff::lines(&chunk.text)).collect()
+    }
+
+    /// The chunk that contains `off`, if any.
+    fn chunk(&self, off: usize) -> Option<&SnapshotChunk> {
+        let index = self.chunks.partition_point(|c| c.offset + c.text.len() <= off);
+        self.chunks.get(index)
+    }
+}
+
+/// A document with syntax highlighting capabilities.
+///
+/// The text lives in a [`GapBuffer`], the same container that backs the editor's
+/// [`crate::buffer::TextBuffer`], so that edits near the previous one don't move the
+/// rest of the file around. `String` still implements the traits below for tests.
+///
+/// Every [`WriteableDocument::replace()`] is recorded, see [`Document::undo()`],
+/// and reported to the listeners registered via [`Document::on_change()`].
+/// A read-only document ignores them, see [`Document::set_read_only()`].
 pub struct Document {
-    content: String,
+    content: GapBuffer,
+    lines: LineIndex,
     file_type: FileType,
+    indent_style: IndentStyle,
     syntax_highlighter: Option<SyntaxHighlighter>,
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    listeners: Vec<(ChangeListenerId, ChangeListener)>,
+    next_listener_id: u32,
+    read_only: bool,
+    /// Counts the changes to the text, to tell whether a [`Snapshot`] is still current.
+    revision: u64,
+    /// The last snapshot, handed out again until the text changes.
+    snapshot: RefCell<Option<Snapshot>>,
+    /// The range that changed since the last snapshot was taken: its start,
+    /// and its end in the last snapshot and in the current text.
+    unsnapshotted: Cell<Option<(usize, usize, usize)>>,
 }
 
 impl Document {
-    pub fn from_string(content: String, filename: &str) -> Self {
-        Self {
-            content,
+    pub fn from_string(content: String, filename: &str) -> apperr::Result<Self> {
+        let mut buffer = GapBuffer::new(false)?;
+        buffer.replace(0..0, content.as_bytes());
+        Ok(Self {
+            indent_style: IndentStyle::detect(content.as_bytes()),
+            lines: LineIndex::new(content.as_bytes()),
+            content: buffer,
             file_type: SyntaxHighlighter::detect_file_type(filename),
             syntax_highlighter: Some(SyntaxHighlighter::new()),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            listeners: Vec::new(),
+            next_listener_id: 0,
+            read_only: false,
+            revision: 0,
+            snapshot: RefCell::new(None),
+            unsnapshotted: Cell::new(None),
+        })
+    }
+
+    /// The length of the text in bytes.
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.len() == 0
+    }
+
+    /// Copies the text into a `String`.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.content.len());
+        self.content.copy_into(&mut text);
+        text
+    }
+
+    /// The number of lines. A text that ends with a newline has an empty last line.
+    pub fn line_count(&self) -> usize {
+        self.lines.line_count()
+    }
+
+    /// Returns the 0-based line and the byte offset within it of `offset`, in O(log n).
+    pub fn offset_to_line_col(&self, offset: usize) -> (usize, usize) {
+        self.lines.offset_to_line_col(offset)
+    }
+
+    /// Returns the offset at which the 0-based `line` starts, in O(log n).
+    pub fn line_to_offset(&self, line: usize) -> usize {
+        self.lines.line_to_offset(line)
+    }
+
+    /// Registers `listener` to be called after every change to the text, including undo and redo,
+    /// so that highlighting, diff gutters and the like can update without polling.
+    /// Listeners are called in the order they were registered.
+    pub fn on_change(&mut self, listener: impl FnMut(&EditDelta) + 'static) -> ChangeListenerId {
+        let id = ChangeListenerId(self.next_listener_id);
+        self.next_listener_id += 1;
+        self.listeners.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Unregisters a listener. Returns false if it wasn't registered (anymore).
+    pub fn remove_change_listener(&mut self, id: ChangeListenerId) -> bool {
+        let len = self.listeners.len();
+        self.listeners.retain(|(i, _)| *i != id);
+        self.listeners.len() != len
+    }
+
+    /// Replaces `offset..offset + old_text.len()` with `new_text` and tells the listeners.
+    fn apply(&mut self, offset: usize, old_text: &[u8], new_text: &[u8]) {
+        let removed_range = offset..offset + old_text.len();
+        self.content.replace(removed_range.clone(), new_text);
+        self.lines.replace(offset, old_text.len(), new_text);
+        self.revision += 1;
+
+        let end = offset + old_text.len();
+        let changed = match self.unsnapshotted.get() {
+            None => (offset, end, offset + new_text.len()),
+            Some((beg, old_end, new_end)) => {
+                // Beyond the changed range, the text is the same as in the snapshot.
+                let union_end = new_end.max(end);
+                let new_union_end = union_end - end + offset + new_text.len();
+                (beg.min(offset), union_end + old_end - new_end, new_union_end)
+            }
+        };
+        self.unsnapshotted.set(Some(changed));
+
+        let delta = EditDelta {
+            removed_range,
+            old_text: Cow::Borrowed(old_text),
+            new_text: Cow::Borrowed(new_text),
+        };
+        for (_, listener) in &mut self.listeners {
+            listener(&delta);
+        }
+    }
+
+    /// Returns the current text as a [`Snapshot`], e.g. to compare with later via
+    /// [`Document::diff()`]. Only the chunks of lines that changed since the last snapshot
+    /// are copied, the others are shared with it. Until the next change, all snapshots
+    /// are the same.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut cached = self.snapshot.borrow_mut();
+        if let Some(snapshot) = &*cached
+            && snapshot.revision == self.revision
+        {
+            return snapshot.clone();
         }
+
+        let len = self.content.len();
+        let mut chunks = Vec::new();
+        let mut suffix = Vec::new();
+        let mut beg = 0;
+        if let Some(old) = cached.take()
+            && let Some((changed_beg, old_end, new_end)) = self.unsnapshotted.get()
+        {
+            // The chunks before the change can be kept, as long as they end in a newline,
+            // so that the text inserted behind them starts a new line. Those behind the
+            // change can be kept, too, if the newline before them didn't change.
+            for chunk in old.chunks.iter() {
+                let end = chunk.offset + chunk.text.len();
+                if end > changed_beg || chunk.text.last() != Some(&b'\n') {
+                    break;
+                }
+                chunks.push(chunk.clone());
+                beg = end;
+            }
+            for chunk in old.chunks[chunks.len()..].iter().rev() {
+                if chunk.offset <= old_end {
+                    break;
+                }
+                let offset = chunk.offset - old_end + new_end;
+                suffix.push(SnapshotChunk { offset, ..chunk.clone() });
+            }
+        }
+        self.unsnapshotted.set(None);
+
+        let end = suffix.last().map_or(len, |chunk| chunk.offset);
+        let mut text = Vec::with_capacity(end - beg);
+        self.content.extract_raw(beg, end, &mut text, 0);
+        Snapshot::push_chunks(&mut chunks, beg, &text);
+        chunks.extend(suffix.into_iter().rev());
+
+        let snapshot = Snapshot { revision: self.revision, chunks: chunks.into(), len };
+        *cached = Some(snapshot.clone());
+        snapshot
+    }
+
+    /// Returns the current text for background work, like highlighting or searching,
+    /// which can read it on another thread while the document is edited.
+    /// It's a [`Document::snapshot()`], so it only copies what changed, too.
+    pub fn arc_snapshot(&self) -> Arc<dyn ReadableDocument + Send + Sync> {
+        Arc::new(self.snapshot())
+    }
+
+    /// Returns the lines that changed since `base` was taken, as indices into the lines of
+    /// `base` and of the current text. Only the lines of the chunks that the snapshots
+    /// don't share are compared.
+    pub fn diff(&self, base: &Snapshot) -> Vec<Hunk> {
+        let current = self.snapshot();
+        let (old, new) = (&base.chunks[..], &current.chunks[..]);
+        let same = |(a, b): &(&SnapshotChunk, &SnapshotChunk)| Arc::ptr_eq(&a.text, &b.text);
+        let prefix = old.iter().zip(new).take_while(same).count();
+        let suffix =
+            old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(same).count();
+
+        let skipped: usize = old[..prefix].iter().map(|chunk| chunk.lines).sum();
+        let mut hunks = diff::diff(
+            &Snapshot::lines(&old[prefix..old.len() - suffix]),
+            &Snapshot::lines(&new[prefix..new.len() - suffix]),
+        );
+        for hunk in &mut hunks {
+            hunk.old = hunk.old.start + skipped..hunk.old.end + skipped;
+            hunk.new = hunk.new.start + skipped..hunk.new.end + skipped;
+        }
+        hunks
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Makes [`WriteableDocument::replace()`], undo and redo do nothing,
+    /// e.g. for files that can't be written anyway.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Reverts the last replacement. Consecutively typed characters are reverted together.
+    ///
+    /// Returns the offset to put the cursor at, which is where it was before the edit,
+    /// or `None` if there's nothing to undo.
+    pub fn undo(&mut self) -> Option<usize> {
+        if self.read_only {
+            return None;
+        }
+        let entry = self.undo_stack.pop()?;
+        self.apply(entry.offset, &entry.added, &entry.deleted);
+        let cursor = entry.offset + entry.deleted.len();
+        self.redo_stack.push(entry);
+        Some(cursor)
+    }
+
+    /// Reapplies the last replacement reverted by [`Document::undo()`].
+    /// Any other replacement in the meantime clears what there is to redo.
+    ///
+    /// Returns the offset to put the cursor at, which is right after the replacement,
+    /// or `None` if there's nothing to redo.
+    pub fn redo(&mut self) -> Option<usize> {
+        if self.read_only {
+            return None;
+        }
+        let entry = self.redo_stack.pop()?;
+        self.apply(entry.offset, &entry.deleted, &entry.added);
+        let cursor = entry.offset + entry.added.len();
+        self.undo_stack.push(entry);
+        Some(cursor)
+    }
+
+    /// The indentation style detected when the document was created.
+    pub fn indent_style(&self) -> IndentStyle {
+        self.indent_style
     }
 
     pub fn highlight_line<'a>(&'a mut self, line: &'a str, line_number: usize) -> Vec<(syntect::highlighting::Style, &'a str)> {
@@ -53,6 +535,60 @@ impl Document {
     }
 }
 
+/// A file that's read in chunks as they're needed, e.g. to search a file without loading it.
+/// Chunks stay cached until [`FileDocument::clear_cache()`].
+///
+/// The file shouldn't change meanwhile. If it shrinks or can't be read,
+/// a chunk may end early, which readers take for the end of the file.
+pub struct FileDocument {
+    file: RefCell<File>,
+    len: usize,
+    chunks: Box<[OnceCell<Box<[u8]>>]>,
+}
+
+impl FileDocument {
+    const CHUNK_SIZE: usize = 64 * KIBI;
+
+    pub fn new(file: File) -> apperr::Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        let chunks = (0..len.div_ceil(Self::CHUNK_SIZE)).map(|_| OnceCell::new()).collect();
+        Ok(Self { file: RefCell::new(file), len, chunks })
+    }
+
+    pub fn open(path: &Path) -> apperr::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    /// The length of the file when it was opened.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forgets the chunks read so far, to free their memory.
+    pub fn clear_cache(&mut self) {
+        self.chunks.iter_mut().for_each(|chunk| _ = chunk.take());
+    }
+
+    /// Returns the chunk at `index` and the offset it starts at, reading it if needed.
+    fn chunk(&self, index: usize) -> (&[u8], usize) {
+        let beg = index * Self::CHUNK_SIZE;
+        let chunk = self.chunks[index].get_or_init(|| {
+            let mut buf = Vec::new();
+            let mut file = self.file.borrow_mut();
+            if file.seek(SeekFrom::Start(beg as u64)).is_ok() {
+                let len = Self::CHUNK_SIZE.min(self.len - beg);
+                _ = (&mut *file).take(len as u64).read_to_end(&mut buf);
+            }
+            buf.into()
+        });
+        (chunk, beg)
+    }
+}
+
 /// An abstraction over reading from text containers.
 pub trait ReadableDocument {
     /// Read some bytes starting at (including) the given absolute offset.
@@ -78,6 +614,28 @@ pub trait ReadableDocument {
     ///   * You MUST NOT break grapheme clusters across chunks.
     ///   * You MUST NOT return an empty slice unless the offset is zero.
     fn read_backward(&self, off: usize) -> &[u8];
+
+    /// Returns the end of the grapheme cluster starting at `off`,
+    /// or `off` if it's at the end. `off` must be at a cluster boundary.
+    fn grapheme_next(&self, off: usize) -> usize {
+        unicode::grapheme_forward(self, off)
+    }
+
+    /// Returns the start of the grapheme cluster ending at `off`,
+    /// or `off` if it's at the start. `off` must be at a cluster boundary.
+    fn grapheme_prev(&self, off: usize) -> usize {
+        unicode::grapheme_backward(self, off)
+    }
+
+    /// Returns the next word boundary after `off`, like Ctrl+Right stops at.
+    fn word_next(&self, off: usize) -> usize {
+        buffer::word_forward(self, off)
+    }
+
+    /// Returns the previous word boundary before `off`, like Ctrl+Left stops at.
+    fn word_prev(&self, off: usize) -> usize {
+        buffer::word_backward(self, off)
+    }
 }
 
 /// An abstraction over writing to text containers.
@@ -89,22 +647,84 @@ pub trait WriteableDocument: ReadableDocument {
     /// * The given range may be out of bounds and you MUST clamp it.
     /// * The replacement may not be valid UTF8.
     fn replace(&mut self, range: Range<usize>, replacement: &[u8]);
+
+    /// Like [`WriteableDocument::replace()`], but returns what changed: the clamped range
+    /// and the text as it was inserted, e.g. for undo, to tell a language server, or to
+    /// know which lines to highlight again. That takes a copy of both texts.
+    ///
+    /// By default, the replaced text is read before calling [`WriteableDocument::replace()`],
+    /// which must insert `replacement` as is. Documents that change it, for instance to
+    /// make it valid UTF-8, must implement this themselves.
+    fn replace_ext(&mut self, range: Range<usize>, replacement: &[u8]) -> EditDelta<'static> {
+        // A range beyond the end gets clamped to the length, which only reading tells.
+        let mut beg = range.start;
+        if beg > 0 && self.read_forward(beg).is_empty() {
+            beg = 0;
+            loop {
+                let chunk = self.read_forward(beg);
+                if chunk.is_empty() {
+                    break;
+                }
+                beg += chunk.len();
+            }
+        }
+
+        let mut old_text = Vec::new();
+        let mut end = beg;
+        while end < range.end {
+            let chunk = self.read_forward(end);
+            if chunk.is_empty() {
+                break;
+            }
+            let chunk = &chunk[..chunk.len().min(range.end - end)];
+            old_text.extend_from_slice(chunk);
+            end += chunk.len();
+        }
+
+        self.replace(beg..end, replacement);
+        EditDelta {
+            removed_range: beg..end,
+            old_text: old_text.into(),
+            new_text: replacement.to_vec().into(),
+        }
+    }
+}
+
+/// Implements [`WriteableDocument::replace_ext()`] for documents that are a single slice,
+/// but change the replacement when inserting it.
+fn replace_contiguous<D: WriteableDocument>(
+    doc: &mut D,
+    bytes: impl Fn(&D) -> &[u8],
+    range: Range<usize>,
+    replacement: &[u8],
+) -> EditDelta<'static> {
+    let len = bytes(doc).len();
+    let end = range.end.min(len);
+    let beg = range.start.min(end);
+    let old_text = bytes(doc)[beg..end].to_vec();
+
+    doc.replace(beg..end, replacement);
+    let inserted_len = bytes(doc).len() + (end - beg) - len;
+    let new_text = bytes(doc)[beg..beg + inserted_len].to_vec();
+    EditDelta { removed_range: beg..end, old_text: old_text.into(), new_text: new_text.into() }
 }
 
 impl ReadableDocument for Document {
     fn read_forward(&self, off: usize) -> &[u8] {
-        let s = self.content.as_bytes();
-        &s[off.min(s.len())..]
+        self.content.read_forward(off)
     }
 
     fn read_backward(&self, off: usize) -> &[u8] {
-        let s = self.content.as_bytes();
-        &s[..off.min(s.len())]
+        self.content.read_backward(off)
     }
 }
 
 impl WriteableDocument for Document {
     fn replace(&mut self, range: Range<usize>, replacement: &[u8]) {
+        self.replace_ext(range, replacement);
+    }
+
+    fn replace_ext(&mut self, range: Range<usize>, replacement: &[u8]) -> EditDelta<'static> {
         // `replacement` is not guaranteed to be valid UTF-8, so we need to sanitize it.
         let scratch = scratch_arena(None);
         let utf8 = ArenaString::from_utf8_lossy(&scratch, replacement);
@@ -113,8 +733,66 @@ impl WriteableDocument for Document {
             Err(s) => s.as_str(),
         };
 
-        // SAFETY: `range` is guaranteed to be on codepoint boundaries.
-        unsafe { self.content.as_mut_vec() }.replace_range(range, src.as_bytes());
+        let end = range.end.min(self.content.len());
+        let beg = range.start.min(end);
+        if self.read_only || (beg == end && src.is_empty()) {
+            let (old_text, new_text) = (Cow::default(), Cow::default());
+            return EditDelta { removed_range: beg..beg, old_text, new_text };
+        }
+
+        let mut deleted = Vec::new();
+        self.content.extract_raw(beg, end, &mut deleted, 0);
+        self.apply(beg, &deleted, src.as_bytes());
+        let delta = EditDelta {
+            removed_range: beg..end,
+            old_text: deleted.into(),
+            new_text: src.as_bytes().to_vec().into(),
+        };
+
+        let entry = HistoryEntry::new(&delta);
+        self.redo_stack.clear();
+        match self.undo_stack.last_mut() {
+            Some(prev) if entry.continues_typing(prev) => prev.added.extend(entry.added),
+            _ => self.undo_stack.push(entry),
+        }
+        delta
+    }
+}
+
+impl ReadableDocument for FileDocument {
+    fn read_forward(&self, off: usize) -> &[u8] {
+        if off >= self.len {
+            return &[];
+        }
+        let (chunk, beg) = self.chunk(off / Self::CHUNK_SIZE);
+        &chunk[(off - beg).min(chunk.len())..]
+    }
+
+    fn read_backward(&self, off: usize) -> &[u8] {
+        let off = off.min(self.len);
+        if off == 0 {
+            return &[];
+        }
+        let (chunk, beg) = self.chunk((off - 1) / Self::CHUNK_SIZE);
+        &chunk[..(off - beg).min(chunk.len())]
+    }
+}
+
+impl ReadableDocument for Snapshot {
+    fn read_forward(&self, off: usize) -> &[u8] {
+        match self.chunk(off) {
+            Some(chunk) => &chunk.text[off - chunk.offset..],
+            None => &[],
+        }
+    }
+
+    fn read_backward(&self, off: usize) -> &[u8] {
+        let off = off.min(self.len);
+        if off == 0 {
+            return &[];
+        }
+        let chunk = self.chunk(off - 1).unwrap();
+        &chunk.text[..off - chunk.offset]
     }
 }
 
@@ -155,6 +833,10 @@ impl WriteableDocument for String {
         // SAFETY: `range` is guaranteed to be on codepoint boundaries.
         unsafe { self.as_mut_vec() }.replace_range(range, src.as_bytes());
     }
+
+    fn replace_ext(&mut self, range: Range<usize>, replacement: &[u8]) -> EditDelta<'static> {
+        replace_contiguous(self, |s| s.as_bytes(), range, replacement)
+    }
 }
 
 impl ReadableDocument for PathBuf {
@@ -176,3 +858,244 @@ impl WriteableDocument for PathBuf {
         *self = unsafe { Self::from(OsString::from_encoded_bytes_unchecked(vec)) };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_replace() {
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("fn main() {}\n".to_string(), "main.rs").unwrap();
+        doc.replace(11..11, b"\n    println!();\n");
+        doc.replace(0..2, b"pub fn");
+        assert_eq!(doc.text(), "pub fn main() {\n    println!();\n}\n");
+        assert_eq!(doc.len(), doc.text().len());
+        assert_eq!(doc.line_count(), 4);
+        assert_eq!(doc.offset_to_line_col(20), (1, 4));
+        assert_eq!(doc.line_to_offset(2), 32);
+
+        // The same edits on a `String` give the same result.
+        let mut text = "fn main() {}\n".to_string();
+        WriteableDocument::replace(&mut text, 11..11, b"\n    println!();\n");
+        WriteableDocument::replace(&mut text, 0..2, b"pub fn");
+        assert_eq!(doc.text(), text);
+
+        // Invalid UTF-8 gets replaced.
+        doc.replace(0..0, b"\xff");
+        assert!(doc.text().starts_with('\u{FFFD}'));
+    }
+
+    #[test]
+    fn test_document_undo() {
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("fn main() {}\n".to_string(), "main.rs").unwrap();
+        assert_eq!(doc.undo(), None);
+
+        // The typed characters get undone at once, but not the replacement before them.
+        doc.replace(0..2, b"pub fn");
+        for (i, ch) in ["x", "y", "ä"].iter().enumerate() {
+            doc.replace(6 + i..6 + i, ch.as_bytes());
+        }
+        assert_eq!(doc.text(), "pub fnxyä main() {}\n");
+        assert_eq!(doc.undo(), Some(6));
+        assert_eq!(doc.text(), "pub fn main() {}\n");
+        assert_eq!(doc.undo(), Some(2));
+        assert_eq!(doc.text(), "fn main() {}\n");
+        assert!(!doc.can_undo());
+
+        assert_eq!(doc.redo(), Some(6));
+        assert_eq!(doc.redo(), Some(10));
+        assert_eq!(doc.text(), "pub fnxyä main() {}\n");
+        assert_eq!(doc.redo(), None);
+
+        // A new edit discards what there was to redo.
+        doc.undo();
+        doc.replace(0..0, b"// ");
+        assert!(!doc.can_redo());
+        assert_eq!(doc.text(), "// pub fn main() {}\n");
+    }
+
+    #[test]
+    fn test_document_replace_ext() {
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("fn main() {}\n".to_string(), "main.rs").unwrap();
+        let delta = doc.replace_ext(10..100, b"\xff}");
+        assert_eq!(delta.removed_range, 10..13);
+        assert_eq!(&*delta.old_text, b"{}\n");
+        assert_eq!(&*delta.new_text, "\u{FFFD}}".as_bytes());
+        assert_eq!(delta.inserted_range(), 10..14);
+
+        // The delta is what undo reverts.
+        assert_eq!(doc.undo(), Some(13));
+        assert_eq!(doc.text(), "fn main() {}\n");
+
+        doc.set_read_only(true);
+        assert!(doc.replace_ext(0..2, b"pub fn").is_empty());
+
+        // Other documents clamp the same way.
+        let mut text = "fn main() {}\n".to_string();
+        let delta = text.replace_ext(10..100, b"}");
+        assert_eq!((delta.removed_range, &*delta.old_text), (10..13, &b"{}\n"[..]));
+        assert_eq!(text, "fn main() }");
+
+        // So does the default implementation, which paths use.
+        let mut path = PathBuf::from("/etc/hosts");
+        let delta = path.replace_ext(5..100, b"fstab");
+        assert_eq!((delta.removed_range, &*delta.old_text), (5..10, &b"hosts"[..]));
+        assert_eq!(&*delta.new_text, b"fstab");
+        let delta = path.replace_ext(20..30, b"~");
+        assert_eq!((delta.removed_range, &*delta.old_text), (10..10, &b""[..]));
+        assert_eq!(path, Path::new("/etc/fstab~"));
+    }
+
+    #[test]
+    fn test_document_read_only() {
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("fn main() {}\n".to_string(), "main.rs").unwrap();
+        doc.replace(0..2, b"pub fn");
+
+        doc.set_read_only(true);
+        doc.replace(0..0, b"// ");
+        assert_eq!(doc.undo(), None);
+        assert_eq!(doc.text(), "pub fn main() {}\n");
+
+        doc.set_read_only(false);
+        assert_eq!(doc.undo(), Some(2));
+        assert_eq!(doc.text(), "fn main() {}\n");
+    }
+
+    #[test]
+    fn test_document_snapshot() {
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("a\nb\nc\n".to_string(), "main.rs").unwrap();
+        let base = doc.snapshot();
+        assert!(Arc::ptr_eq(&base.chunks, &doc.snapshot().chunks));
+        assert_eq!(doc.diff(&base), []);
+
+        doc.replace(2..3, b"B\nB2");
+        doc.replace(0..0, b"0\n");
+        assert_eq!(base.text(), b"a\nb\nc\n");
+        assert_eq!(doc.diff(&base), [Hunk { old: 0..0, new: 0..1 }, Hunk { old: 1..2, new: 2..4 }]);
+
+        // Undoing everything makes for a new snapshot with the same text.
+        doc.undo();
+        doc.undo();
+        assert!(!Arc::ptr_eq(&base.chunks, &doc.snapshot().chunks));
+        assert_eq!(doc.diff(&base), []);
+    }
+
+    #[test]
+    fn test_document_snapshot_shares_chunks() {
+        let _arena = crate::arena::init_for_test();
+        let text: String = (0..30_000).map(|i| format!("line {i}\n")).collect();
+        let mut doc = Document::from_string(text.clone(), "main.rs").unwrap();
+        let base = doc.snapshot();
+        assert!(base.chunks.len() > 3);
+        assert_eq!(base.text(), text.as_bytes());
+
+        // Only the chunk around the change is copied.
+        let middle = doc.line_to_offset(15_000);
+        doc.replace(middle..middle + 5, b"changed\nline");
+        let current = doc.snapshot();
+        let shared =
+            |i: usize, j: usize| Arc::ptr_eq(&base.chunks[i].text, &current.chunks[j].text);
+        assert!(shared(0, 0));
+        assert!(shared(base.chunks.len() - 1, current.chunks.len() - 1));
+        assert_eq!(current.text(), doc.text().as_bytes());
+        assert_eq!(doc.diff(&base), [Hunk { old: 15_000..15_001, new: 15_000..15_002 }]);
+
+        // Text appended to a chunk without a final newline doesn't end up in its own chunk.
+        let end = doc.len();
+        doc.replace(end - 1..end, b"");
+        doc.snapshot();
+        doc.replace(end - 1..end - 1, b"0\n");
+        let current = doc.snapshot();
+        assert_eq!(current.text(), doc.text().as_bytes());
+        assert!(current.read_backward(current.len()).ends_with(b"\nline 299990\n"));
+        assert_eq!(
+            doc.diff(&base),
+            [
+                Hunk { old: 15_000..15_001, new: 15_000..15_002 },
+                Hunk { old: 29_999..30_000, new: 30_000..30_001 },
+            ]
+        );
+
+        while doc.undo().is_some() {}
+        assert_eq!(doc.snapshot().text(), text.as_bytes());
+        assert_eq!(doc.diff(&base), []);
+    }
+
+    #[test]
+    fn test_document_arc_snapshot() {
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("fn main() {}\n".to_string(), "main.rs").unwrap();
+        let snapshot = doc.arc_snapshot();
+        let worker = std::thread::spawn(move || snapshot.read_forward(3).to_vec());
+
+        doc.replace(0..2, b"pub fn");
+        assert_eq!(worker.join().unwrap(), b"main() {}\n");
+        assert_eq!(doc.arc_snapshot().read_backward(6), b"pub fn");
+    }
+
+    #[test]
+    fn test_file_document() {
+        let path = std::env::temp_dir().join(format!("edit-file-document-{}", std::process::id()));
+        let text: Vec<u8> = (0..3 * FileDocument::CHUNK_SIZE / 8)
+            .flat_map(|i| format!("{:07}\n", i).into_bytes())
+            .collect();
+        std::fs::write(&path, &text).unwrap();
+        let mut doc = FileDocument::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(doc.len(), text.len());
+
+        let mut forward = Vec::new();
+        while forward.len() < doc.len() {
+            forward.extend_from_slice(doc.read_forward(forward.len()));
+        }
+        assert_eq!(forward, text);
+
+        let mut backward = doc.len();
+        while backward > 0 {
+            let chunk = doc.read_backward(backward);
+            assert_eq!(chunk, &text[backward - chunk.len()..backward]);
+            backward -= chunk.len();
+        }
+        assert_eq!(doc.read_forward(doc.len()), b"");
+        assert_eq!(doc.read_backward(0), b"");
+
+        // A line starts at the chunk boundary, which takes the previous chunk to tell.
+        let boundary = FileDocument::CHUNK_SIZE;
+        assert_eq!(doc.word_prev(boundary + 3), boundary);
+        assert_eq!(doc.word_next(boundary - 4), boundary - 1);
+        doc.clear_cache();
+        assert_eq!(doc.read_backward(boundary), &text[..boundary]);
+    }
+
+    #[test]
+    fn test_document_on_change() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let _arena = crate::arena::init_for_test();
+        let mut doc = Document::from_string("fn main() {}\n".to_string(), "main.rs").unwrap();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let id = doc.on_change({
+            let log = log.clone();
+            move |delta| {
+                let old = String::from_utf8_lossy(&delta.old_text).into_owned();
+                log.borrow_mut().push((delta.removed_range.clone(), old, delta.inserted_len()));
+            }
+        });
+
+        doc.replace(0..2, b"pub fn");
+        doc.replace(100..100, b"");
+        doc.undo();
+        assert_eq!(*log.borrow(), [(0..2, "fn".to_string(), 6), (0..6, "pub fn".to_string(), 2)]);
+
+        assert!(doc.remove_change_listener(id));
+        assert!(!doc.remove_change_listener(id));
+        doc.redo();
+        assert_eq!(log.borrow().len(), 2);
+    }
+}
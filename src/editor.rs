@@ -0,0 +1,182 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The editor without the terminal, for embedding it in other programs, like a GUI or a web
+//! frontend.
+//!
+//! [`EditorCore`] holds the open documents. Each one is a [`TextBuffer`], which does the rest:
+//! the cursor and selection ([`TextBuffer::cursor_move_to_logical()`],
+//! [`TextBuffer::selection_update_logical()`]), editing and undo ([`TextBuffer::write()`],
+//! [`TextBuffer::undo()`]), search ([`TextBuffer::find_and_select()`]) and indentation
+//! ([`TextBuffer::set_indent_with_tabs()`], [`TextBuffer::unindent()`]).
+//! Drawing the text and turning key presses into those calls is up to the frontend.
+//! [`EditorCore::highlight_line()`] returns the syntax colors to draw a line with.
+//!
+//! Without the `tui` feature, which is on by default, the crate is built without the terminal
+//! UI, its framebuffer and its input parser. `examples/headless.rs` is a frontend that only
+//! reads commands and prints the text.
+//!
+//! ```
+//! use edit::arena;
+//! use edit::editor::EditorCore;
+//! use edit::helpers::Point;
+//!
+//! arena::init(16 * 1024 * 1024).unwrap();
+//!
+//! let mut editor = EditorCore::new();
+//! let index = editor.new_document().unwrap();
+//! {
+//!     let mut tb = editor.document(index).buffer.borrow_mut();
+//!     tb.write(b"fn main() {}\n", false);
+//!     tb.cursor_move_to_logical(Point { x: 0, y: 0 });
+//!     tb.undo();
+//!     assert_eq!(tb.text_length(), 0);
+//! }
+//! ```
+
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use syntect::highlighting::Style;
+
+use crate::apperr;
+use crate::buffer::{RcTextBuffer, TextBuffer};
+use crate::helpers::CoordType;
+use crate::syntax::{FileType, SyntaxHighlighter};
+
+/// A document of an [`EditorCore`].
+pub struct EditorDocument {
+    pub buffer: RcTextBuffer,
+    path: Option<PathBuf>,
+    file_type: FileType,
+}
+
+impl EditorDocument {
+    fn new(path: Option<PathBuf>) -> apperr::Result<Self> {
+        let buffer = TextBuffer::new_rc(false)?;
+        let mut doc = Self { buffer, path: None, file_type: FileType::Plain };
+        if let Some(path) = path {
+            doc.set_path(path);
+        }
+        Ok(doc)
+    }
+
+    /// The file the document was opened from or last saved to.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Sets the file the document is saved to, and the file type that goes with its name.
+    pub fn set_path(&mut self, path: PathBuf) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.file_type = SyntaxHighlighter::detect_file_type(&name);
+        self.buffer.borrow_mut().set_file_type(self.file_type);
+        self.path = Some(path);
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Whether the document was edited since it was opened or saved.
+    pub fn is_dirty(&self) -> bool {
+        self.buffer.borrow().is_dirty()
+    }
+}
+
+/// The open documents and what they share, like the syntax highlighter.
+///
+/// Documents are referred to by their index, which shifts down when one before them
+/// is closed. The scratch arena must be initialized first, see [`crate::arena::init()`].
+pub struct EditorCore {
+    documents: Vec<EditorDocument>,
+    highlighter: SyntaxHighlighter,
+}
+
+impl EditorCore {
+    pub fn new() -> Self {
+        Self { documents: Vec::new(), highlighter: SyntaxHighlighter::new() }
+    }
+
+    pub fn documents(&self) -> &[EditorDocument] {
+        &self.documents
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn document(&self, index: usize) -> &EditorDocument {
+        &self.documents[index]
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn document_mut(&mut self, index: usize) -> &mut EditorDocument {
+        &mut self.documents[index]
+    }
+
+    /// Adds an empty document without a file and returns its index.
+    pub fn new_document(&mut self) -> apperr::Result<usize> {
+        self.documents.push(EditorDocument::new(None)?);
+        Ok(self.documents.len() - 1)
+    }
+
+    /// Opens the file at `path` and returns the index of its document. A file that's open
+    /// already isn't read again. A file that doesn't exist results in an empty document,
+    /// which saving creates.
+    pub fn open(&mut self, path: &Path) -> apperr::Result<usize> {
+        if let Some(index) = self.documents.iter().position(|doc| doc.path() == Some(path)) {
+            return Ok(index);
+        }
+
+        let doc = EditorDocument::new(Some(path.to_path_buf()))?;
+        match File::open(path) {
+            Ok(mut file) => doc.buffer.borrow_mut().read_file_with_path(&mut file, path, None)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        self.documents.push(doc);
+        Ok(self.documents.len() - 1)
+    }
+
+    /// Saves the document to `path`, or to its own path if that's `None`.
+    /// A document without a path can't be saved without one.
+    pub fn save(&mut self, index: usize, path: Option<PathBuf>) -> apperr::Result<()> {
+        let doc = &mut self.documents[index];
+        if let Some(path) = path {
+            doc.set_path(path);
+        }
+        let Some(path) = doc.path() else {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput).into());
+        };
+
+        let mut tb = doc.buffer.borrow_mut();
+        // Truncating the file would pull the rug out from under a mapping of it.
+        tb.unmap()?;
+        tb.write_file(&mut File::create(path)?)
+    }
+
+    /// Closes the document and returns it, e.g. to ask whether to save it first.
+    pub fn close(&mut self, index: usize) -> EditorDocument {
+        self.documents.remove(index)
+    }
+
+    /// Switches the syntax highlighting to another theme. Returns false if there's none
+    /// with that name, see [`SyntaxHighlighter::available_themes()`].
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        self.highlighter.set_theme(name)
+    }
+
+    /// Returns the pieces of the `line` (0-based) of the document, each with its syntax colors.
+    pub fn highlight_line(&mut self, index: usize, line: CoordType) -> Vec<(Style, String)> {
+        let doc = &self.documents[index];
+        let text = doc.buffer.borrow().lines_text(line..line + 1).pop().unwrap_or_default();
+        self.highlighter
+            .highlight_line(&text, doc.file_type, line as usize)
+            .into_iter()
+            .map(|(style, piece)| (style, piece.to_string()))
+            .collect()
+    }
+}
@@ -5,7 +5,7 @@
 
 use std::cell::Cell;
 use std::fmt::Write;
-use std::ops::{BitOr, BitXor};
+use std::ops::{BitAnd, BitOr, BitXor};
 use std::ptr;
 use std::slice::ChunksExact;
 
@@ -89,6 +89,10 @@ pub struct Framebuffer {
     contrast_colors: [Cell<(u32, u32)>; CACHE_TABLE_SIZE],
     background_fill: u32,
     foreground_fill: u32,
+    /// Attributes that the terminal can display. Others are dropped during [`Framebuffer::render()`].
+    supported_attributes: Attributes,
+    /// Whether the cursor is drawn without blinking.
+    steady_cursor: bool,
 }
 
 impl Framebuffer {
@@ -105,9 +109,25 @@ impl Framebuffer {
             contrast_colors: [const { Cell::new((0, 0)) }; CACHE_TABLE_SIZE],
             background_fill: DEFAULT_THEME[IndexedColor::Background as usize],
             foreground_fill: DEFAULT_THEME[IndexedColor::Foreground as usize],
+            supported_attributes: Attributes::All,
+            steady_cursor: false,
         }
     }
 
+    /// Restricts the attributes that get emitted during rendering.
+    ///
+    /// This is useful for terminals that can't render e.g. italics properly,
+    /// as some of them use reverse video or a different color instead.
+    pub fn set_supported_attributes(&mut self, attr: Attributes) {
+        self.supported_attributes = attr;
+    }
+
+    /// Makes the cursor stop blinking, e.g. because every blink is sent over a slow connection
+    /// by some terminal multiplexers.
+    pub fn set_steady_cursor(&mut self, steady: bool) {
+        self.steady_cursor = steady;
+    }
+
     /// Sets the base color palette.
     ///
     /// If you call this method, [`Framebuffer`] expects that you
@@ -126,6 +146,16 @@ impl Framebuffer {
         }
     }
 
+    /// Makes the next [`Framebuffer::render()`] redraw everything,
+    /// for instance because something else wrote to the terminal.
+    pub fn invalidate(&mut self) {
+        let front = &mut self.buffers[self.frame_counter & 1];
+        // Trigger a full redraw. (Yes, it's a hack.)
+        front.fg_bitmap.fill(1);
+        // Trigger a cursor update as well, just to be sure.
+        front.cursor = Cursor::new_invalid();
+    }
+
     /// Begins a new frame with the given `size`.
     pub fn flip(&mut self, size: Size) {
         if size != self.buffers[0].bg_bitmap.size {
@@ -136,11 +166,7 @@ impl Framebuffer {
                 buffer.attributes = AttributeBuffer::new(size);
             }
 
-            let front = &mut self.buffers[self.frame_counter & 1];
-            // Trigger a full redraw. (Yes, it's a hack.)
-            front.fg_bitmap.fill(1);
-            // Trigger a cursor update as well, just to be sure.
-            front.cursor = Cursor::new_invalid();
+            self.invalidate();
         }
 
         self.frame_counter = self.frame_counter.wrapping_add(1);
@@ -192,50 +218,11 @@ impl Framebuffer {
             return 0;
         }
 
-        let viewport_height = track.height();
-        // The content height is at least the viewport height.
-        let content_height = content_height.max(viewport_height);
-
-        // No need to draw a scrollbar if the content fits in the viewport.
-        let content_offset_max = content_height - viewport_height;
-        if content_offset_max == 0 {
+        let Some((thumb_top, thumb_height)) =
+            Self::scrollbar_thumb(track.height(), content_offset, content_height)
+        else {
             return 0;
-        }
-
-        // The content offset must be at least one viewport height from the bottom.
-        // You don't want to scroll past the end after all...
-        let content_offset = content_offset.clamp(0, content_offset_max);
-
-        // In order to increase the visual resolution of the scrollbar,
-        // we'll use 1/8th blocks to represent the thumb.
-        // First, scale the offsets to get that 1/8th resolution.
-        let viewport_height = viewport_height as i64 * 8;
-        let content_offset_max = content_offset_max as i64 * 8;
-        let content_offset = content_offset as i64 * 8;
-        let content_height = content_height as i64 * 8;
-
-        // The proportional thumb height (0-1) is the fraction of viewport and
-        // content height. The taller the content, the smaller the thumb:
-        // = viewport_height / content_height
-        // We then scale that to the viewport height to get the height in 1/8th units.
-        // = viewport_height * viewport_height / content_height
-        // We add content_height/2 to round the integer division, which results in a numerator of:
-        // = viewport_height * viewport_height + content_height / 2
-        let numerator = viewport_height * viewport_height + content_height / 2;
-        let thumb_height = numerator / content_height;
-        // Ensure the thumb has a minimum size of 1 row.
-        let thumb_height = thumb_height.max(8);
-
-        // The proportional thumb top position (0-1) is:
-        // = content_offset / content_offset_max
-        // The maximum thumb top position is the viewport height minus the thumb height:
-        // = viewport_height - thumb_height
-        // To get the thumb top position in 1/8th units, we multiply both:
-        // = (viewport_height - thumb_height) * content_offset / content_offset_max
-        // We add content_offset_max/2 to round the integer division, which results in a numerator of:
-        // = (viewport_height - thumb_height) * content_offset + content_offset_max / 2
-        let numerator = (viewport_height - thumb_height) * content_offset + content_offset_max / 2;
-        let thumb_top = numerator / content_offset_max;
+        };
         // The thumb bottom position is the thumb top position plus the thumb height.
         let thumb_bottom = thumb_top + thumb_height;
 
@@ -291,11 +278,153 @@ impl Framebuffer {
         ((thumb_height + 4) / 8) as CoordType
     }
 
+    /// Same as [`Framebuffer::draw_scrollbar()`], but for a horizontal `track`
+    /// and content that's `content_width` wide.
+    pub fn draw_scrollbar_horizontal(
+        &mut self,
+        clip_rect: Rect,
+        track: Rect,
+        content_offset: CoordType,
+        content_width: CoordType,
+    ) -> CoordType {
+        let track_clipped = track.intersect(clip_rect);
+        if track_clipped.is_empty() {
+            return 0;
+        }
+
+        let Some((thumb_left, thumb_width)) =
+            Self::scrollbar_thumb(track.width(), content_offset, content_width)
+        else {
+            return 0;
+        };
+        let thumb_right = thumb_left + thumb_width;
+
+        // Shift to absolute coordinates and clamp to the visible area.
+        let thumb_left = (thumb_left + track.left as i64 * 8).max(track_clipped.left as i64 * 8);
+        let thumb_right =
+            (thumb_right + track.left as i64 * 8).min(track_clipped.right as i64 * 8);
+
+        // Calculate the width of the left/right cell of the thumb.
+        let left_fract = (thumb_left % 8) as CoordType;
+        let right_fract = (thumb_right % 8) as CoordType;
+
+        let thumb_left = ((thumb_left + 7) / 8) as CoordType;
+        let thumb_right = (thumb_right / 8) as CoordType;
+
+        self.blend_bg(track_clipped, self.indexed(IndexedColor::BrightBlack));
+        self.blend_fg(track_clipped, self.indexed(IndexedColor::BrightWhite));
+
+        for x in thumb_left..thumb_right {
+            self.replace_text(track_clipped.top, x, x + 1, "█");
+        }
+
+        // U+2589 to U+258F, 7/8th block to 1/8th block elements glyphs: ▉▊▋▌▍▎▏
+        // In UTF8: E2 96 89 to E2 96 8F. They're all left-aligned, which is why
+        // the left cell of the thumb is drawn inverted, like the bottom one above.
+        let mut fract_buf = [0xE2, 0x96, 0x88];
+        if left_fract != 0 {
+            fract_buf[2] = (0x90 - left_fract) as u8;
+            self.replace_text(track_clipped.top, thumb_left - 1, thumb_left, unsafe {
+                std::str::from_utf8_unchecked(&fract_buf)
+            });
+            let rect = Rect {
+                left: thumb_left - 1,
+                top: track_clipped.top,
+                right: thumb_left,
+                bottom: track_clipped.top + 1,
+            };
+            self.blend_bg(rect, self.indexed(IndexedColor::BrightWhite));
+            self.blend_fg(rect, self.indexed(IndexedColor::BrightBlack));
+        }
+        if right_fract != 0 {
+            fract_buf[2] = (0x90 - right_fract) as u8;
+            self.replace_text(track_clipped.top, thumb_right, thumb_right + 1, unsafe {
+                std::str::from_utf8_unchecked(&fract_buf)
+            });
+        }
+
+        ((thumb_width + 4) / 8) as CoordType
+    }
+
+    /// Computes the thumb of a scrollbar with a `track_len` long track in 1/8th cell units:
+    /// Its offset from the start of the track and its length.
+    /// Returns `None` if the content fits into the track and there's nothing to scroll.
+    fn scrollbar_thumb(
+        track_len: CoordType,
+        content_offset: CoordType,
+        content_len: CoordType,
+    ) -> Option<(i64, i64)> {
+        let viewport_height = track_len;
+        // The content height is at least the viewport height.
+        let content_height = content_len.max(viewport_height);
+
+        // No need to draw a scrollbar if the content fits in the viewport.
+        let content_offset_max = content_height - viewport_height;
+        if content_offset_max == 0 {
+            return None;
+        }
+
+        // The content offset must be at least one viewport height from the bottom.
+        // You don't want to scroll past the end after all...
+        let content_offset = content_offset.clamp(0, content_offset_max);
+
+        // In order to increase the visual resolution of the scrollbar,
+        // we'll use 1/8th blocks to represent the thumb.
+        // First, scale the offsets to get that 1/8th resolution.
+        let viewport_height = viewport_height as i64 * 8;
+        let content_offset_max = content_offset_max as i64 * 8;
+        let content_offset = content_offset as i64 * 8;
+        let content_height = content_height as i64 * 8;
+
+        // The proportional thumb height (0-1) is the fraction of viewport and
+        // content height. The taller the content, the smaller the thumb:
+        // = viewport_height / content_height
+        // We then scale that to the viewport height to get the height in 1/8th units.
+        // = viewport_height * viewport_height / content_height
+        // We add content_height/2 to round the integer division, which results in a numerator of:
+        // = viewport_height * viewport_height + content_height / 2
+        let numerator = viewport_height * viewport_height + content_height / 2;
+        let thumb_height = numerator / content_height;
+        // Ensure the thumb has a minimum size of 1 row.
+        let thumb_height = thumb_height.max(8);
+
+        // The proportional thumb top position (0-1) is:
+        // = content_offset / content_offset_max
+        // The maximum thumb top position is the viewport height minus the thumb height:
+        // = viewport_height - thumb_height
+        // To get the thumb top position in 1/8th units, we multiply both:
+        // = (viewport_height - thumb_height) * content_offset / content_offset_max
+        // We add content_offset_max/2 to round the integer division, which results in a numerator of:
+        // = (viewport_height - thumb_height) * content_offset + content_offset_max / 2
+        let numerator = (viewport_height - thumb_height) * content_offset + content_offset_max / 2;
+        let thumb_top = numerator / content_offset_max;
+
+        Some((thumb_top, thumb_height))
+    }
+
     #[inline]
     pub fn indexed(&self, index: IndexedColor) -> u32 {
         self.indexed_colors[index as usize]
     }
 
+    /// Returns a color from the xterm 256-color palette. The first 16 are the theme colors,
+    /// followed by a 6x6x6 color cube and 24 shades of gray.
+    pub fn indexed_256(&self, index: u8) -> u32 {
+        let rgb = match index {
+            0..16 => return self.indexed_colors[index as usize],
+            16..232 => {
+                let level = |i: u8| if i == 0 { 0 } else { 55 + 40 * i as u32 };
+                let i = index - 16;
+                [level(i / 36), level(i / 6 % 6), level(i % 6)]
+            }
+            232.. => {
+                let v = 8 + 10 * (index - 232) as u32;
+                [v, v, v]
+            }
+        };
+        0xff000000 | rgb[2] << 16 | rgb[1] << 8 | rgb[0]
+    }
+
     /// Returns a color from the palette.
     ///
     /// To facilitate constant folding by the compiler,
@@ -455,7 +584,7 @@ impl Framebuffer {
             while {
                 let bg = back_bg[chunk_end];
                 let fg = back_fg[chunk_end];
-                let attr = back_attr[chunk_end];
+                let attr = back_attr[chunk_end] & self.supported_attributes;
 
                 // Chunk into runs of the same color.
                 while {
@@ -463,7 +592,7 @@ impl Framebuffer {
                     chunk_end < back_bg.len()
                         && back_bg[chunk_end] == bg
                         && back_fg[chunk_end] == fg
-                        && back_attr[chunk_end] == attr
+                        && back_attr[chunk_end] & self.supported_attributes == attr
                 } {}
 
                 if last_bg != bg as u64 {
@@ -492,6 +621,13 @@ impl Framebuffer {
                             result.push_str("\x1b[24m");
                         }
                     }
+                    if diff.is(Attributes::Bold) {
+                        if attr.is(Attributes::Bold) {
+                            result.push_str("\x1b[1m");
+                        } else {
+                            result.push_str("\x1b[22m");
+                        }
+                    }
                     last_attr = attr;
                 }
 
@@ -516,7 +652,8 @@ impl Framebuffer {
                     "\x1b[{};{}H\x1b[{} q\x1b[?25h",
                     back.cursor.pos.y + 1,
                     back.cursor.pos.x + 1,
-                    if back.cursor.overtype { 1 } else { 5 }
+                    // The steady variants are the blinking ones + 1.
+                    if back.cursor.overtype { 1 } else { 5 } + self.steady_cursor as u8
                 );
             } else {
                 // DECTCEM to hide the cursor.
@@ -785,7 +922,7 @@ impl Bitmap {
     }
 
     /// Iterates over each row in the bitmap.
-    fn iter(&self) -> ChunksExact<u32> {
+    fn iter(&self) -> ChunksExact<'_, u32> {
         self.data.chunks_exact(self.size.width as usize)
     }
 }
@@ -802,7 +939,8 @@ impl Attributes {
     pub const None: Self = Self(0);
     pub const Italic: Self = Self(0b1);
     pub const Underlined: Self = Self(0b10);
-    pub const All: Self = Self(0b11);
+    pub const Bold: Self = Self(0b100);
+    pub const All: Self = Self(0b111);
 
     pub const fn is(self, attr: Self) -> bool {
         (self.0 & attr.0) == attr.0
@@ -819,6 +957,14 @@ impl BitOr for Attributes {
     }
 }
 
+impl BitAnd for Attributes {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
 impl BitXor for Attributes {
     type Output = Self;
 
@@ -871,7 +1017,7 @@ impl AttributeBuffer {
     }
 
     /// Iterates over each row in the bitmap.
-    fn iter(&self) -> ChunksExact<Attributes> {
+    fn iter(&self) -> ChunksExact<'_, Attributes> {
         self.data.chunks_exact(self.size.width as usize)
     }
 }
@@ -892,3 +1038,31 @@ impl Cursor {
         Self { pos: Point { x: -1, y: -1 }, overtype: false }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::scratch_arena;
+
+    /// Renders "abcd" with "bc" in bold, on a terminal that supports `supported`.
+    fn render_bold(supported: Attributes) -> String {
+        let _arena = crate::arena::init_for_test();
+        let scratch = scratch_arena(None);
+        let mut fb = Framebuffer::new();
+        fb.set_supported_attributes(supported);
+        fb.flip(Size { width: 4, height: 1 });
+        fb.replace_text(0, 0, 4, "abcd");
+        let bc = Rect { left: 1, top: 0, right: 3, bottom: 1 };
+        fb.replace_attr(bc, Attributes::All, Attributes::Bold);
+        fb.render(&scratch).to_string()
+    }
+
+    #[test]
+    fn test_render_bold() {
+        assert!(render_bold(Attributes::All).contains("a\x1b[1mbc\x1b[22md"));
+
+        let output = render_bold(Attributes::Italic | Attributes::Underlined);
+        assert!(output.contains("abcd"));
+        assert!(!output.contains("\x1b[1m") && !output.contains("\x1b[22m"));
+    }
+}
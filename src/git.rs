@@ -0,0 +1,106 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Finds the git repository a file belongs to, without running git.
+//!
+//! Besides regular checkouts, that's a worktree (`git worktree add`) or a submodule,
+//! whose `.git` is a file that points to the actual git directory:
+//!
+//! ```text
+//! gitdir: /src/project/.git/worktrees/feature
+//! gitdir: ../.git/modules/vendor/lib
+//! ```
+//!
+//! A workspace may hold several of them, so each file is attributed to the innermost one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repo {
+    /// The checked out directory, which contains the `.git` directory or file.
+    pub work_tree: PathBuf,
+    /// The directory with the `HEAD` of `work_tree`. For a worktree that's within
+    /// the git directory of the repository it was added to.
+    pub git_dir: PathBuf,
+}
+
+impl Repo {
+    /// Returns the repository the file or directory at `path` belongs to, if any.
+    pub fn find(path: &Path) -> Option<Self> {
+        for dir in path.ancestors() {
+            let dot_git = dir.join(".git");
+            let Ok(metadata) = fs::metadata(&dot_git) else {
+                continue;
+            };
+            let git_dir = if metadata.is_dir() {
+                dot_git
+            } else {
+                let text = fs::read_to_string(&dot_git).ok()?;
+                let target = text.lines().next()?.strip_prefix("gitdir:")?.trim();
+                // Submodules point to their git directory relative to the `.git` file.
+                dir.join(target)
+            };
+            return Some(Self { work_tree: dir.to_path_buf(), git_dir });
+        }
+        None
+    }
+
+    /// The name of the checked out directory, e.g. to tell repositories apart.
+    pub fn name(&self) -> String {
+        self.work_tree.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+
+    /// Returns the checked out branch, or the abbreviated commit if none is.
+    pub fn branch(&self) -> Option<String> {
+        let head = fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        match head.strip_prefix("ref:") {
+            Some(name) => {
+                let name = name.trim();
+                Some(name.strip_prefix("refs/heads/").unwrap_or(name).to_string())
+            }
+            None => Some(head.get(..7)?.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let root = std::env::temp_dir().join(format!("edit-git-test-{}", std::process::id()));
+        let main = root.join("main");
+        let sub = main.join("vendor/lib");
+        let worktree = root.join("feature");
+        fs::create_dir_all(main.join(".git/worktrees/feature")).unwrap();
+        fs::create_dir_all(main.join(".git/modules/vendor/lib")).unwrap();
+        fs::create_dir_all(sub.join("src")).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(main.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(main.join(".git/worktrees/feature/HEAD"), "ref: refs/heads/feature\n").unwrap();
+        fs::write(main.join(".git/modules/vendor/lib/HEAD"), "0123456789abcdef\n").unwrap();
+        fs::write(sub.join(".git"), "gitdir: ../../.git/modules/vendor/lib\n").unwrap();
+        let worktree_git_dir = main.join(".git/worktrees/feature");
+        fs::write(worktree.join(".git"), format!("gitdir: {}\n", worktree_git_dir.display()))
+            .unwrap();
+
+        let repo = Repo::find(&main.join("README.md")).unwrap();
+        assert_eq!(repo.work_tree, main);
+        assert_eq!(repo.name(), "main");
+        assert_eq!(repo.branch().as_deref(), Some("main"));
+
+        let repo = Repo::find(&sub.join("src/lib.rs")).unwrap();
+        assert_eq!(repo.work_tree, sub);
+        assert_eq!(repo.branch().as_deref(), Some("0123456"));
+
+        let repo = Repo::find(&worktree.join("main.rs")).unwrap();
+        assert_eq!(repo.work_tree, worktree);
+        assert_eq!(repo.git_dir, worktree_git_dir);
+        assert_eq!(repo.branch().as_deref(), Some("feature"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
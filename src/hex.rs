@@ -0,0 +1,128 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Hex dumps of binary files, in the layout of `hexdump -C`:
+//!
+//! ```text
+//! 00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|
+//! ```
+//!
+//! The dump can be edited and turned back into bytes. Only the hex columns count for that,
+//! so bytes can be changed, inserted and deleted there, while the offsets and the ASCII
+//! column are ignored. See [`crate::buffer::TextBuffer::set_hex_view()`].
+
+use std::fmt::Write as _;
+
+use crate::helpers::KIBI;
+
+/// How much of a file [`is_binary()`] looks at.
+const SNIFF_LEN: usize = 8 * KIBI;
+const BYTES_PER_LINE: usize = 16;
+
+/// Returns true if `data` looks like binary content rather than text: it contains a NUL
+/// like `git` checks, or many other control characters. Text in legacy encodings isn't
+/// valid UTF-8 either, so that alone doesn't count.
+pub fn is_binary(data: &[u8]) -> bool {
+    let data = &data[..data.len().min(SNIFF_LEN)];
+    if data.contains(&0) {
+        return true;
+    }
+    // Tabs, newlines, form feeds, and the backspaces and escapes of man pages and logs are
+    // all common in text.
+    let controls = data
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x08 | 0x0c | 0x1b))
+        .count();
+    controls * 10 > data.len()
+}
+
+/// Formats `data` as a hex dump.
+pub fn dump(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(BYTES_PER_LINE) * 79);
+
+    for (i, line) in data.chunks(BYTES_PER_LINE).enumerate() {
+        _ = write!(out, "{:08x} ", i * BYTES_PER_LINE);
+        for j in 0..BYTES_PER_LINE {
+            // The two halves of the line are separated by an extra space.
+            if j % 8 == 0 {
+                out.push(' ');
+            }
+            match line.get(j) {
+                Some(b) => _ = write!(out, "{b:02x} "),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str(" |");
+        out.extend(line.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' {
+            b as char
+        } else {
+            '.'
+        }));
+        out.push_str("|\n");
+    }
+    out
+}
+
+/// Turns a hex dump back into bytes. Each line starts with its offset, followed by
+/// the bytes as pairs of hex digits and, optionally, the ASCII column in `|...|`.
+///
+/// Returns the index of the first line that isn't like that as the error.
+pub fn parse(text: &[u8]) -> Result<Vec<u8>, usize> {
+    let mut data = Vec::with_capacity(text.len() / 4);
+
+    for (i, line) in text.split(|&b| b == b'\n').enumerate() {
+        let line = match line.iter().position(|&b| b == b'|') {
+            Some(pos) => &line[..pos],
+            None => line,
+        };
+        // Skip the offset.
+        for word in line.split(u8::is_ascii_whitespace).filter(|w| !w.is_empty()).skip(1) {
+            if word.len() % 2 != 0 {
+                return Err(i);
+            }
+            for pair in word.chunks_exact(2) {
+                let digits = str::from_utf8(pair).map_err(|_| i)?;
+                data.push(u8::from_str_radix(digits, 16).map_err(|_| i)?);
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b""));
+        assert!(!is_binary("fn main() {\n\tprintln!(\"é\");\n}\n".as_bytes()));
+        assert!(!is_binary(b"caf\xe9 cr\xe8me\r\n")); // Latin-1
+        assert!(!is_binary(b"\x1b[1mbold\x1b[0m b\x08bo\x08ol\x08ld\n"));
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(is_binary(b"\x01\x02\x03\x04abc"));
+    }
+
+    #[test]
+    fn test_dump() {
+        assert_eq!(dump(b""), "");
+        assert_eq!(
+            dump(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00Hi!"),
+            concat!(
+                "00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|\n",
+                "00000010  48 69 21                                          |Hi!|\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let data: Vec<u8> = (0..=255).chain([0, 10, 13, b'|']).collect();
+        assert_eq!(parse(dump(&data).as_bytes()), Ok(data));
+
+        // Bytes can be inserted and deleted, and the ASCII column is ignored.
+        assert_eq!(parse(b"00000000  41 4243 44  |A|\n0010  45\n\n"), Ok(b"ABCDE".to_vec()));
+        assert_eq!(parse(b"00000000  41 42\n00000002  4g  |.|"), Err(1));
+        assert_eq!(parse(b"00000000  414"), Err(0));
+    }
+}
@@ -368,6 +368,15 @@ extern "C" fn utext_access(ut: &mut icu_ffi::UText, native_index: i64, forward:
         ut.native_indexing_limit = cache.native_indexing_limit as i32;
         true
     } else {
+        // Out of bounds, e.g. at the end of the text. Leave an empty chunk behind,
+        // because the previous one may be stale, if the text changed in the meantime.
+        // Like ICU expects, it's pinned to the nearest end of the text.
+        let native_index = native_index.clamp(0, utext_native_length(ut));
+        ut.chunk_length = 0;
+        ut.chunk_offset = 0;
+        ut.chunk_native_start = native_index;
+        ut.chunk_native_limit = native_index;
+        ut.native_indexing_limit = 0;
         false
     }
 }
@@ -491,11 +500,7 @@ fn utext_access_impl<'a>(
             }
         }
 
-        loop {
-            let Some(c) = it.next() else {
-                break;
-            };
-
+        while let Some(c) = it.next() {
             // Thanks to our `if utf16_len >= UTF16_LEN_LIMIT` check,
             // we can safely assume that this will fit.
             unsafe {
@@ -648,6 +653,19 @@ impl Regex {
         let mut status = icu_ffi::U_ZERO_ERROR;
         unsafe { (f.uregex_reset64)(self.0, offset as i64, &mut status) };
     }
+
+    /// Returns the range of the capture group `group` in the last match.
+    /// Group 0 is the entire match. `None` if the group doesn't exist or didn't participate.
+    pub fn group(&mut self, group: i32) -> Option<Range<usize>> {
+        let f = assume_loaded();
+        let mut status = icu_ffi::U_ZERO_ERROR;
+        let start = unsafe { (f.uregex_start64)(self.0, group, &mut status) };
+        let end = unsafe { (f.uregex_end64)(self.0, group, &mut status) };
+        if status.is_failure() || start < 0 {
+            return None;
+        }
+        Some(start as usize..end.max(start) as usize)
+    }
 }
 
 impl Iterator for Regex {
@@ -1253,4 +1271,29 @@ mod tests {
         assert_eq!(compare_strings_ascii(b"hallo", b"Hello"), Ordering::Less);
         assert_eq!(compare_strings_ascii(b"Hello", b"hallo"), Ordering::Greater);
     }
+
+    #[test]
+    fn test_utext_access_out_of_bounds() {
+        let _arena = crate::arena::init_for_test();
+        let mut tb = TextBuffer::new(false).unwrap();
+        tb.copy_from_str(&b"hello".as_slice());
+        let Ok(text) = (unsafe { Text::new(&tb) }) else {
+            return; // ICU isn't installed.
+        };
+
+        assert!(utext_access(text.0, 2, true));
+        assert_eq!(text.0.chunk_native_limit, 5);
+
+        // Past the end, no stale chunk must be left behind.
+        assert!(!utext_access(text.0, 7, true));
+        assert_eq!(text.0.chunk_length, 0);
+        assert_eq!(text.0.chunk_native_start, 5);
+        assert_eq!(text.0.chunk_native_limit, 5);
+
+        // Before the start, it's pinned to the start.
+        assert!(!utext_access(text.0, -3, false));
+        assert_eq!(text.0.chunk_length, 0);
+        assert_eq!(text.0.chunk_native_start, 0);
+        assert_eq!(text.0.chunk_native_limit, 0);
+    }
 }
@@ -52,9 +52,31 @@ impl InputKey {
         (self.0 & modifier.0) != 0
     }
 
-    pub(crate) const fn with_modifiers(&self, modifiers: InputKeyMod) -> Self {
+    pub const fn with_modifiers(&self, modifiers: InputKeyMod) -> Self {
         Self(self.0 | modifiers.0)
     }
+
+    /// Parses a shortcut as written in the config, like `ctrl+alt+m`, `alt+1` or `shift+f5`.
+    /// Letters, digits and F1-F12 are supported, along with any of the three modifiers.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (modifiers, key) = s.rsplit_once('+').unwrap_or(("", s));
+        let mut result = Self::from_ascii(key.chars().next()?.to_ascii_lowercase())
+            .filter(|_| key.len() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()))
+            .or_else(|| {
+                let n: u32 = key.strip_prefix(['f', 'F'])?.parse().ok()?;
+                (1..=12).contains(&n).then(|| Self(vk::F1.0 + n - 1))
+            })?;
+
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            result = result.with_modifiers(match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => kbmod::CTRL,
+                "alt" => kbmod::ALT,
+                "shift" => kbmod::SHIFT,
+                _ => return None,
+            });
+        }
+        Some(result)
+    }
 }
 
 /// A keyboard modifier. Ctrl/Alt/Shift.
@@ -200,6 +222,13 @@ pub mod vk {
     pub const F22: InputKey = InputKey::new(0x85);
     pub const F23: InputKey = InputKey::new(0x86);
     pub const F24: InputKey = InputKey::new(0x87);
+
+    /// The `,<` key.
+    pub const OEM_COMMA: InputKey = InputKey::new(0xBC);
+    /// The `.>` key.
+    pub const OEM_PERIOD: InputKey = InputKey::new(0xBE);
+    /// The `/?` key on US keyboards.
+    pub const OEM_2: InputKey = InputKey::new(0xBF);
 }
 
 /// Keyboard modifiers.
@@ -343,6 +372,8 @@ impl<'input> Iterator for Stream<'_, '_, 'input> {
                         let key = ch as u32 | 0x40;
                         return Some(Input::Keyboard(kbmod::CTRL | InputKey::new(key)));
                     }
+                    // Terminals send Ctrl+/ like Ctrl+_.
+                    '\x1f' => return Some(Input::Keyboard(kbmod::CTRL | vk::OEM_2)),
                     '\x7f' => return Some(Input::Keyboard(vk::BACK)),
                     _ => {}
                 },
@@ -350,6 +381,11 @@ impl<'input> Iterator for Stream<'_, '_, 'input> {
                     match ch {
                         '\0' => return Some(Input::Keyboard(vk::ESCAPE)),
                         '\n' => return Some(Input::Keyboard(kbmod::CTRL_ALT | vk::RETURN)),
+                        '0'..='9' => {
+                            return Some(Input::Keyboard(kbmod::ALT | InputKey::new(ch as u32)));
+                        }
+                        ',' => return Some(Input::Keyboard(kbmod::ALT | vk::OEM_COMMA)),
+                        '.' => return Some(Input::Keyboard(kbmod::ALT | vk::OEM_PERIOD)),
                         ' '..='~' => {
                             let ch = ch as u32;
                             let key = ch & !0x20; // Shift a-z to A-Z
@@ -466,7 +502,7 @@ impl<'input> Iterator for Stream<'_, '_, 'input> {
                             mouse.modifiers |=
                                 if (btn & 0x08) != 0 { kbmod::ALT } else { kbmod::NONE };
                             mouse.modifiers |=
-                                if (btn & 0x10f) != 0 { kbmod::CTRL } else { kbmod::NONE };
+                                if (btn & 0x10) != 0 { kbmod::CTRL } else { kbmod::NONE };
 
                             mouse.position.x = csi.params[1] as CoordType - 1;
                             mouse.position.y = csi.params[2] as CoordType - 1;
@@ -584,3 +620,17 @@ impl<'input> Stream<'_, '_, 'input> {
         modifiers
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert!(InputKey::parse("ctrl+alt+d") == Some(kbmod::CTRL_ALT | vk::D));
+        assert!(InputKey::parse("Shift+F5") == Some(kbmod::SHIFT | vk::F5));
+        assert!(InputKey::parse("alt+1") == Some(kbmod::ALT | vk::N1));
+        assert!(InputKey::parse("hyper+x").is_none());
+        assert!(InputKey::parse("ctrl+f13").is_none());
+    }
+}
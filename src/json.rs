@@ -0,0 +1,217 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A small JSON reader, sufficient for consuming the output of command line tools.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Looks up a `/`-separated path of object keys and array indices, e.g. `"0/messages"`.
+    /// An empty path returns `self`.
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        let mut value = self;
+        for key in path.split('/').filter(|k| !k.is_empty()) {
+            value = match value {
+                Value::Object(map) => map.get(key)?,
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns strings as-is and numbers and booleans in their textual form.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a JSON document. Returns `None` if it's malformed.
+pub fn parse(text: &str) -> Option<Value> {
+    let mut parser = Parser { text: text.as_bytes(), off: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    (parser.off == parser.text.len()).then_some(value)
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    off: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.off < self.text.len()
+            && matches!(self.text[self.off], b' ' | b'\t' | b'\r' | b'\n')
+        {
+            self.off += 1;
+        }
+    }
+
+    fn eat(&mut self, ch: u8) -> bool {
+        self.skip_whitespace();
+        let ok = self.text.get(self.off) == Some(&ch);
+        if ok {
+            self.off += 1;
+        }
+        ok
+    }
+
+    fn keyword(&mut self, word: &[u8], value: Value) -> Option<Value> {
+        let ok = self.text[self.off..].starts_with(word);
+        self.off += word.len();
+        ok.then_some(value)
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_whitespace();
+        match *self.text.get(self.off)? {
+            b'{' => {
+                self.off += 1;
+                let mut map = BTreeMap::new();
+                if self.eat(b'}') {
+                    return Some(Value::Object(map));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    if !self.eat(b':') {
+                        return None;
+                    }
+                    map.insert(key, self.value()?);
+                    if !self.eat(b',') {
+                        return self.eat(b'}').then_some(Value::Object(map));
+                    }
+                }
+            }
+            b'[' => {
+                self.off += 1;
+                let mut items = Vec::new();
+                if self.eat(b']') {
+                    return Some(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    if !self.eat(b',') {
+                        return self.eat(b']').then_some(Value::Array(items));
+                    }
+                }
+            }
+            b'"' => self.string().map(Value::String),
+            b't' => self.keyword(b"true", Value::Bool(true)),
+            b'f' => self.keyword(b"false", Value::Bool(false)),
+            b'n' => self.keyword(b"null", Value::Null),
+            _ => {
+                let beg = self.off;
+                while self.off < self.text.len()
+                    && matches!(self.text[self.off], b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+                {
+                    self.off += 1;
+                }
+                let number = std::str::from_utf8(&self.text[beg..self.off]).ok()?;
+                number.parse().ok().map(Value::Number)
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.text.get(self.off) != Some(&b'"') {
+            return None;
+        }
+        self.off += 1;
+
+        let mut out = Vec::new();
+        loop {
+            let ch = *self.text.get(self.off)?;
+            self.off += 1;
+            match ch {
+                b'"' => break,
+                b'\\' => {
+                    let esc = *self.text.get(self.off)?;
+                    self.off += 1;
+                    let ch = match esc {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\x08',
+                        b'f' => '\x0c',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // Surrogate pair?
+                            if (0xD800..0xDC00).contains(&code)
+                                && self.text[self.off..].starts_with(b"\\u")
+                            {
+                                self.off += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000
+                                    + ((code - 0xD800) << 10)
+                                    + (low.wrapping_sub(0xDC00) & 0x3ff);
+                            }
+                            char::from_u32(code).unwrap_or('\u{FFFD}')
+                        }
+                        _ => esc as char,
+                    };
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => out.push(ch),
+            }
+        }
+        String::from_utf8(out).ok()
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.text.get(self.off..self.off + 4)?;
+        self.off += 4;
+        u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = parse(r#" [{"filePath": "a.js", "messages": [{"line": 3, "message": "x\"yé", "ok": true}]}] "#)
+            .unwrap();
+        let message = value.pointer("0/messages/0").unwrap();
+        assert_eq!(message.pointer("line").and_then(Value::as_f64), Some(3.0));
+        assert_eq!(message.pointer("message").and_then(Value::to_text).as_deref(), Some("x\"yé"));
+        assert_eq!(message.pointer("ok"), Some(&Value::Bool(true)));
+        assert_eq!(value.pointer("1"), None);
+
+        assert_eq!(parse("{}"), Some(Value::Object(BTreeMap::new())));
+        assert_eq!(parse("[1, 2"), None);
+        assert_eq!(parse("nul"), None);
+    }
+}
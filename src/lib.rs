@@ -13,25 +13,46 @@
     os_string_truncate
 )]
 #![allow(clippy::missing_transmute_annotations, clippy::new_without_default, stable_features)]
+// Some of the features are only used by the terminal UI.
+#![cfg_attr(not(feature = "tui"), allow(unused_features))]
 
 #[macro_use]
 pub mod arena;
 
+pub mod ansi;
 pub mod apperr;
 pub mod base64;
 pub mod buffer;
+pub mod case;
 pub mod cell;
+pub mod config;
+pub mod diagnostics;
+pub mod diff;
 pub mod document;
+pub mod editor;
+#[cfg(feature = "tui")]
 pub mod framebuffer;
+pub mod fuzzy;
+pub mod git;
 pub mod hash;
 pub mod helpers;
+pub mod hex;
 pub mod icu;
+#[cfg(feature = "tui")]
 pub mod input;
+pub mod json;
+pub mod line_index;
 pub mod oklab;
 pub mod path;
+pub mod search_index;
 pub mod simd;
+pub mod stack_trace;
+pub mod surround;
 pub mod sys;
 pub mod syntax;
+#[cfg(feature = "tui")]
 pub mod tui;
 pub mod unicode;
 pub mod vt;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
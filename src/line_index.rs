@@ -0,0 +1,97 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The offsets at which the lines of a text start, kept up to date as the text changes,
+//! so that converting between offsets and lines is a binary search instead of a scan.
+
+/// The line starts of a text. Lines are separated by `\n`, so a `\r\n` ending
+/// counts towards the line it ends, like everywhere else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The offset of each line's first byte. The first one is always 0.
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(text: &[u8]) -> Self {
+        let mut starts = vec![0];
+        starts.extend(newline_ends(text, 0));
+        Self { starts, len: text.len() }
+    }
+
+    /// Updates the index after `old_len` bytes at `offset` were replaced with `new_text`.
+    pub fn replace(&mut self, offset: usize, old_len: usize, new_text: &[u8]) {
+        let old_end = offset + old_len;
+        // The lines that started within the replaced text are gone.
+        let beg = self.starts.partition_point(|&s| s <= offset);
+        let end = self.starts.partition_point(|&s| s <= old_end);
+
+        for start in &mut self.starts[end..] {
+            *start = *start - old_len + new_text.len();
+        }
+        self.starts.splice(beg..end, newline_ends(new_text, offset));
+        self.len = self.len - old_len + new_text.len();
+    }
+
+    /// The number of lines. A text that ends with a newline has an empty last line.
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Returns the 0-based line and the byte offset within it of `offset`.
+    /// Offsets past the end are clamped to it.
+    pub fn offset_to_line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.len);
+        let line = self.starts.partition_point(|&s| s <= offset) - 1;
+        (line, offset - self.starts[line])
+    }
+
+    /// Returns the offset at which `line` starts. Lines past the end are clamped to the last one.
+    pub fn line_to_offset(&self, line: usize) -> usize {
+        self.starts[line.min(self.starts.len() - 1)]
+    }
+}
+
+/// The offsets just past each `\n` in `text`, which is at `base`.
+fn newline_ends(text: &[u8], base: usize) -> impl Iterator<Item = usize> + '_ {
+    text.iter().enumerate().filter(|&(_, &b)| b == b'\n').map(move |(i, _)| base + i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let index = LineIndex::new(b"ab\r\n\ncd");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.offset_to_line_col(0), (0, 0));
+        assert_eq!(index.offset_to_line_col(3), (0, 3));
+        assert_eq!(index.offset_to_line_col(4), (1, 0));
+        assert_eq!(index.offset_to_line_col(6), (2, 1));
+        assert_eq!(index.offset_to_line_col(100), (2, 2));
+        assert_eq!(index.line_to_offset(2), 5);
+        assert_eq!(index.line_to_offset(100), 5);
+        assert_eq!(LineIndex::new(b"").offset_to_line_col(0), (0, 0));
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut text = b"one\ntwo\nthree\n".to_vec();
+        let mut index = LineIndex::new(&text);
+        let edits: [(usize, usize, &[u8]); 6] = [
+            (4, 0, b"\n\n"),
+            (0, 6, b"x"),
+            (2, 0, b"a\nb\nc"),
+            (3, 5, b""),
+            (0, 0, b"\n"),
+            (1, 11, b"end\n"),
+        ];
+        for (offset, old_len, new_text) in edits {
+            text.splice(offset..offset + old_len, new_text.iter().copied());
+            index.replace(offset, old_len, new_text);
+            assert_eq!(index, LineIndex::new(&text), "{:?}", str::from_utf8(&text));
+        }
+    }
+}
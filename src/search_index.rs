@@ -0,0 +1,485 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! A trigram index of the files in a directory tree, which makes repeated searches
+//! across a large workspace fast: Only files that contain every trigram (3-byte sequence)
+//! of the search term can contain a match, and only those need to be read.
+//!
+//! Building the index reads every file once, so it's meant to happen on a background
+//! thread, see [`SearchIndex::load_or_build()`]. The result is saved in the state
+//! directory and [`SearchIndex::refresh()`] then only rereads the files that changed.
+//! Files whose names aren't valid UTF-8 and hidden files and directories are skipped.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::config::Config;
+use crate::hash::hash;
+
+const MAGIC: &[u8] = b"edit-search-index 1\n";
+
+/// Bounds the time and memory spent on an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexLimits {
+    /// Larger files aren't indexed and are always searched.
+    pub max_file_size: u64,
+    /// Once this many bytes are indexed, the remaining files are always searched.
+    pub max_total_size: u64,
+}
+
+impl Default for IndexLimits {
+    fn default() -> Self {
+        Self { max_file_size: 1024 * 1024, max_total_size: 256 * 1024 * 1024 }
+    }
+}
+
+struct IndexedFile {
+    /// Relative to the root.
+    path: String,
+    len: u64,
+    /// In nanoseconds since the Unix epoch. Together with `len` this tells if the file changed.
+    modified: u64,
+    /// Replaced files are only marked as such, until [`SearchIndex::compact()`] drops them.
+    live: bool,
+}
+
+pub struct SearchIndex {
+    root: PathBuf,
+    limits: IndexLimits,
+    files: Vec<IndexedFile>,
+    /// The live entries of `files` by path.
+    by_path: HashMap<String, u32>,
+    /// For each trigram, the sorted indices into `files` that contain it.
+    postings: HashMap<u32, Vec<u32>>,
+    /// Files that are too large, or that didn't fit within `max_total_size`.
+    unindexed: Vec<String>,
+    indexed_size: u64,
+}
+
+impl SearchIndex {
+    /// Indexes all files below `root`.
+    pub fn build(root: &Path, limits: IndexLimits) -> Self {
+        let mut index = Self::empty(root, limits);
+        index.refresh();
+        index
+    }
+
+    fn empty(root: &Path, limits: IndexLimits) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            limits,
+            files: Vec::new(),
+            by_path: HashMap::new(),
+            postings: HashMap::new(),
+            unindexed: Vec::new(),
+            indexed_size: 0,
+        }
+    }
+
+    /// Loads the index of `root` from the state directory, brings it up to date and saves it
+    /// again, or builds it from scratch if there's none yet. This reads a lot of files,
+    /// so better call it from a background thread.
+    pub fn load_or_build(root: &Path, limits: IndexLimits) -> Self {
+        let cache = Self::cache_path(root);
+        let index = match cache.as_deref().and_then(|c| Self::load(c, root, limits).ok()) {
+            Some(mut index) => {
+                index.refresh();
+                index
+            }
+            None => Self::build(root, limits),
+        };
+        if let Some(cache) = cache {
+            _ = fs::create_dir_all(cache.parent().unwrap()).and_then(|_| index.save(&cache));
+        }
+        index
+    }
+
+    /// Where [`SearchIndex::load_or_build()`] keeps the index of `root`.
+    pub fn cache_path(root: &Path) -> Option<PathBuf> {
+        let id = hash(0, root.as_os_str().as_encoded_bytes());
+        Config::state_dir().map(|dir| dir.join("search-index").join(format!("{id:016x}")))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether the file at `path` (absolute) is known to the index, indexed or not.
+    /// Hidden files, for instance, are not.
+    pub fn contains(&self, path: &Path) -> bool {
+        let Some(rel) = path.strip_prefix(&self.root).ok().and_then(Path::to_str) else {
+            return false;
+        };
+        let rel = rel.replace('\\', "/");
+        self.by_path.contains_key(&rel) || self.unindexed.contains(&rel)
+    }
+
+    /// Rereads the files that changed since they were indexed,
+    /// adds new ones and forgets the deleted ones.
+    pub fn refresh(&mut self) {
+        let mut seen = Vec::new();
+        let mut unindexed = Vec::new();
+        self.walk(&self.root, &mut String::new(), &mut seen, &mut unindexed);
+
+        // Files added below get higher indices and aren't affected by this.
+        let mut current = vec![false; self.files.len()];
+        for (path, len, modified) in seen {
+            if let Some(&id) = self.by_path.get(&path) {
+                let file = &self.files[id as usize];
+                if file.len == len && file.modified == modified {
+                    current[id as usize] = true;
+                    continue;
+                }
+                self.forget(id);
+            }
+            if !self.add(path.clone(), len, modified) {
+                unindexed.push(path);
+            }
+        }
+
+        // Whatever wasn't seen anymore was deleted.
+        for (id, current) in current.into_iter().enumerate() {
+            if !current && self.files[id].live {
+                self.forget(id as u32);
+            }
+        }
+        self.unindexed = unindexed;
+        self.compact();
+    }
+
+    /// Updates a single file, e.g. after it was saved. `path` is absolute.
+    pub fn update_file(&mut self, path: &Path) {
+        let Some(rel) = path.strip_prefix(&self.root).ok().and_then(Path::to_str) else {
+            return;
+        };
+        let rel = rel.replace('\\', "/");
+        if let Some(id) = self.by_path.get(&rel).copied() {
+            self.forget(id);
+        }
+        self.unindexed.retain(|p| *p != rel);
+
+        if let Ok(metadata) = fs::metadata(path)
+            && metadata.is_file()
+            && !self.add(rel.clone(), metadata.len(), modified_nanos(&metadata))
+        {
+            self.unindexed.push(rel);
+        }
+    }
+
+    /// Returns the files that may contain `needle`, or `None` if that can't be narrowed down,
+    /// because it's shorter than 3 bytes. The search should then go through all files.
+    ///
+    /// The trigrams are case-insensitive for ASCII, so this works for case-insensitive
+    /// searches, too. Files that changed since the last refresh may be missing.
+    pub fn candidates(&self, needle: &str) -> Option<Vec<PathBuf>> {
+        let mut trigrams: Vec<u32> = needle
+            .as_bytes()
+            .windows(3)
+            // Non-ASCII letters may have other case variants, maybe with different lengths.
+            .filter(|w| w.is_ascii())
+            .map(trigram)
+            .collect();
+        if trigrams.is_empty() {
+            return None;
+        }
+        trigrams.sort_unstable();
+        trigrams.dedup();
+
+        let mut lists = Vec::with_capacity(trigrams.len());
+        for t in &trigrams {
+            lists.push(self.postings.get(t).map_or(&[][..], Vec::as_slice));
+        }
+        lists.sort_by_key(|l| l.len());
+
+        let mut ids = lists[0].to_vec();
+        for list in &lists[1..] {
+            ids.retain(|id| list.binary_search(id).is_ok());
+        }
+
+        let indexed = ids.into_iter().map(|id| &self.files[id as usize]).filter(|f| f.live);
+        let mut result: Vec<PathBuf> = indexed.map(|f| self.root.join(&f.path)).collect();
+        result.extend(self.unindexed.iter().map(|p| self.root.join(p)));
+        result.sort();
+        Some(result)
+    }
+
+    fn walk(
+        &self,
+        dir: &Path,
+        rel: &mut String,
+        seen: &mut Vec<(String, u64, u64)>,
+        unindexed: &mut Vec<String>,
+    ) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            // Version control, caches and the like.
+            if name.starts_with('.') {
+                continue;
+            }
+            // Symlinks may lead in circles. `DirEntry::file_type()` doesn't follow them.
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            let len = rel.len();
+            if !rel.is_empty() {
+                rel.push('/');
+            }
+            rel.push_str(name);
+
+            if file_type.is_dir() {
+                self.walk(&entry.path(), rel, seen, unindexed);
+            } else if file_type.is_file()
+                && let Ok(metadata) = entry.metadata()
+            {
+                if metadata.len() > self.limits.max_file_size {
+                    unindexed.push(rel.clone());
+                } else {
+                    seen.push((rel.clone(), metadata.len(), modified_nanos(&metadata)));
+                }
+            }
+
+            rel.truncate(len);
+        }
+    }
+
+    /// Indexes the file at `path` (relative to the root). Returns false if it doesn't fit.
+    fn add(&mut self, path: String, len: u64, modified: u64) -> bool {
+        if self.indexed_size + len > self.limits.max_total_size {
+            return false;
+        }
+        let Ok(contents) = fs::read(self.root.join(&path)) else {
+            return true;
+        };
+        self.indexed_size += len;
+
+        let id = self.files.len() as u32;
+        // Binary files are indexed without trigrams, which keeps them out of all results.
+        if !contents[..contents.len().min(8 * 1024)].contains(&0) {
+            let mut trigrams: Vec<u32> = contents.windows(3).map(trigram).collect();
+            trigrams.sort_unstable();
+            trigrams.dedup();
+            for t in trigrams {
+                self.postings.entry(t).or_default().push(id);
+            }
+        }
+
+        self.by_path.insert(path.clone(), id);
+        self.files.push(IndexedFile { path, len, modified, live: true });
+        true
+    }
+
+    fn forget(&mut self, id: u32) {
+        let file = &mut self.files[id as usize];
+        file.live = false;
+        self.indexed_size -= file.len;
+        self.by_path.remove(&file.path);
+    }
+
+    /// Drops the forgotten files from the postings once they make up half of the index.
+    fn compact(&mut self) {
+        let live = self.by_path.len();
+        if self.files.len() < 2 * live.max(1) {
+            return;
+        }
+
+        let mut remap = vec![u32::MAX; self.files.len()];
+        let mut files = Vec::with_capacity(live);
+        for (id, file) in self.files.drain(..).enumerate() {
+            if file.live {
+                remap[id] = files.len() as u32;
+                files.push(file);
+            }
+        }
+        self.files = files;
+
+        self.postings.retain(|_, ids| {
+            ids.retain_mut(|id| {
+                *id = remap[*id as usize];
+                *id != u32::MAX
+            });
+            !ids.is_empty()
+        });
+        self.by_path =
+            self.files.iter().enumerate().map(|(i, f)| (f.path.clone(), i as u32)).collect();
+    }
+
+    /// Writes the index to `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        w.write_all(MAGIC)?;
+        write_str(&mut w, self.root.to_str().unwrap_or_default())?;
+
+        write_u64(&mut w, self.unindexed.len() as u64)?;
+        for path in &self.unindexed {
+            write_str(&mut w, path)?;
+        }
+
+        // Forgotten files are kept, so that the indices in the postings stay valid.
+        write_u64(&mut w, self.files.len() as u64)?;
+        for file in &self.files {
+            write_str(&mut w, &file.path)?;
+            write_u64(&mut w, file.len)?;
+            write_u64(&mut w, file.modified)?;
+            w.write_all(&[file.live as u8])?;
+        }
+
+        write_u64(&mut w, self.postings.len() as u64)?;
+        for (&t, ids) in &self.postings {
+            write_u64(&mut w, t as u64)?;
+            write_u64(&mut w, ids.len() as u64)?;
+            for &id in ids {
+                w.write_all(&id.to_le_bytes())?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Reads an index written by [`SearchIndex::save()`]. Fails if it's not the one of `root`.
+    /// The `limits` apply to subsequent updates.
+    pub fn load(path: &Path, root: &Path, limits: IndexLimits) -> io::Result<Self> {
+        let invalid = || io::Error::from(io::ErrorKind::InvalidData);
+        let mut r = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; MAGIC.len()];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC || Some(read_str(&mut r)?.as_str()) != root.to_str() {
+            return Err(invalid());
+        }
+
+        let mut index = Self::empty(root, limits);
+        for _ in 0..read_u64(&mut r)? {
+            index.unindexed.push(read_str(&mut r)?);
+        }
+
+        for id in 0..read_u64(&mut r)? as u32 {
+            let path = read_str(&mut r)?;
+            let len = read_u64(&mut r)?;
+            let modified = read_u64(&mut r)?;
+            let mut live = [0];
+            r.read_exact(&mut live)?;
+            if live[0] != 0 {
+                index.by_path.insert(path.clone(), id);
+                index.indexed_size += len;
+            }
+            index.files.push(IndexedFile { path, len, modified, live: live[0] != 0 });
+        }
+
+        for _ in 0..read_u64(&mut r)? {
+            let t = read_u64(&mut r)? as u32;
+            let count = read_u64(&mut r)? as usize;
+            let mut ids = Vec::with_capacity(count.min(index.files.len()));
+            for _ in 0..count {
+                let mut id = [0; 4];
+                r.read_exact(&mut id)?;
+                let id = u32::from_le_bytes(id);
+                if id as usize >= index.files.len() {
+                    return Err(invalid());
+                }
+                ids.push(id);
+            }
+            index.postings.insert(t, ids);
+        }
+        Ok(index)
+    }
+}
+
+/// Packs 3 bytes into a trigram, with ASCII letters lowercased.
+fn trigram(w: &[u8]) -> u32 {
+    let b = |i: usize| w[i].to_ascii_lowercase() as u32;
+    b(0) << 16 | b(1) << 8 | b(2)
+}
+
+fn modified_nanos(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+fn write_u64(w: &mut impl Write, v: u64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_u64(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_str(r: &mut impl Read) -> io::Result<String> {
+    let len = read_u64(r)?;
+    let mut buf = Vec::new();
+    r.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buf).map_err(|_| io::ErrorKind::InvalidData.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_index() {
+        let root = std::env::temp_dir().join(format!("edit-search-index-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() { println!(\"Hello\"); }").unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn hello() {}").unwrap();
+        fs::write(root.join(".git/HEAD"), "hello").unwrap();
+        fs::write(root.join("big.txt"), "x".repeat(64)).unwrap();
+
+        let limits = IndexLimits { max_file_size: 63, max_total_size: 1024 };
+        let mut index = SearchIndex::build(&root, limits);
+        // Too large files are always candidates, since they aren't indexed.
+        let candidates = |index: &SearchIndex, needle| {
+            let paths = index.candidates(needle).unwrap();
+            paths.iter().map(|p| p.strip_prefix(&root).unwrap().to_path_buf()).collect::<Vec<_>>()
+        };
+        let expected = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(
+            candidates(&index, "HELLO"),
+            expected(&["big.txt", "src/lib.rs", "src/main.rs"])
+        );
+        assert_eq!(candidates(&index, "println"), expected(&["big.txt", "src/main.rs"]));
+        assert_eq!(index.candidates("fn"), None);
+        assert!(index.contains(&root.join("src/main.rs")));
+        assert!(index.contains(&root.join("big.txt")));
+        assert!(!index.contains(&root.join(".git/HEAD")));
+
+        fs::write(root.join("src/lib.rs"), "pub fn println() {}").unwrap();
+        index.update_file(&root.join("src/lib.rs"));
+        assert_eq!(candidates(&index, "hello"), expected(&["big.txt", "src/main.rs"]));
+
+        let cache = root.join(".git/index");
+        index.save(&cache).unwrap();
+        fs::remove_file(root.join("src/main.rs")).unwrap();
+        let mut index = SearchIndex::load(&cache, &root, limits).unwrap();
+        assert_eq!(
+            candidates(&index, "println"),
+            expected(&["big.txt", "src/lib.rs", "src/main.rs"])
+        );
+        index.refresh();
+        assert_eq!(candidates(&index, "println"), expected(&["big.txt", "src/lib.rs"]));
+        assert!(SearchIndex::load(&cache, &root.join("src"), limits).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
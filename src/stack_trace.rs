@@ -0,0 +1,177 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Recognizes the frames of stack traces: Rust panics and backtraces,
+//! Python tracebacks and Node.js stacks. See [`crate::syntax::FileType::StackTrace`].
+
+use std::ops::Range;
+
+use crate::helpers::CoordType;
+
+/// A line of a stack trace that refers to a location in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub path: &'a str,
+    /// 1-based, like in the trace.
+    pub line: CoordType,
+    /// 1-based, like in the trace. Python doesn't print columns.
+    pub column: Option<CoordType>,
+    /// The byte range of the reference (the path up to the line or column) within the line.
+    pub range: Range<usize>,
+    /// Whether the frame is in the standard library, the runtime or a dependency.
+    pub external: bool,
+}
+
+/// How many lines [`is_stack_trace()`] looks at.
+const SNIFF_LINES: usize = 200;
+
+/// Returns true if `text` contains a stack trace in its first few lines.
+pub fn is_stack_trace(text: &str) -> bool {
+    let mut frames = 0;
+    for line in text.lines().take(SNIFF_LINES) {
+        let line = line.trim_start();
+        if line.starts_with("Traceback (most recent call last):")
+            || line.starts_with("stack backtrace:")
+            || (line.starts_with("thread '") && line.contains("' panicked at "))
+        {
+            return true;
+        }
+        // Node doesn't print a header, just the error message.
+        if line.starts_with("at ") && parse_frame(line).is_some() {
+            frames += 1;
+            if frames >= 2 {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Parses the frame in `line`, if there's one.
+pub fn parse_frame(line: &str) -> Option<Frame<'_>> {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+
+    // Python: `  File "/srv/app.py", line 12, in main`
+    if let Some(rest) = trimmed.strip_prefix("File \"") {
+        let beg = indent + 6;
+        let path_len = rest.find('"')?;
+        let number = rest[path_len..].strip_prefix("\", line ")?;
+        let digits = number.bytes().take_while(u8::is_ascii_digit).count();
+        let end = beg + path_len + 8 + digits;
+        return Some(Frame {
+            path: &rest[..path_len],
+            line: number[..digits].parse().ok()?,
+            column: None,
+            range: beg..end,
+            external: is_external(&rest[..path_len]),
+        });
+    }
+
+    // Rust: `thread 'main' panicked at src/main.rs:2:5:`
+    let (beg, location) = if let Some(pos) = trimmed.find("' panicked at ") {
+        let beg = indent + pos + 14;
+        let location = line[beg..].split_whitespace().next()?;
+        (beg, location.strip_suffix(':').unwrap_or(location))
+    } else {
+        let rest = trimmed.strip_prefix("at ")?;
+        // Node: `at main (/srv/app.js:10:7)` or `at /srv/app.js:10:7`
+        if rest.ends_with(')')
+            && let Some(open) = rest.rfind('(')
+        {
+            let beg = line.len() - rest.len() + open + 1;
+            (beg, &line[beg..line.len() - 1])
+        } else {
+            // Rust backtraces: `at ./src/main.rs:10:5`
+            (line.len() - rest.len(), rest.trim_end())
+        }
+    };
+
+    let (path, line_number, column) = split_location(location)?;
+    Some(Frame {
+        path,
+        line: line_number,
+        column,
+        range: beg..beg + location.len(),
+        external: is_external(path),
+    })
+}
+
+/// Splits `path:line:column` or `path:line`.
+pub fn split_location(location: &str) -> Option<(&str, CoordType, Option<CoordType>)> {
+    let (rest, last) = location.rsplit_once(':')?;
+    let last = last.parse().ok()?;
+    if let Some((path, first)) = rest.rsplit_once(':')
+        && let Ok(first) = first.parse()
+        && !path.is_empty()
+    {
+        return Some((path, first, Some(last)));
+    }
+    (!rest.is_empty()).then_some((rest, last, None))
+}
+
+fn is_external(path: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        // Rust
+        "/rustc/",
+        "/.cargo/registry/",
+        "/.cargo/git/",
+        "/.rustup/",
+        // Python
+        "site-packages/",
+        "dist-packages/",
+        "/lib/python",
+        // Node
+        "node_modules/",
+    ];
+    let path = path.replace('\\', "/");
+    path.starts_with('<')
+        || path.starts_with("node:")
+        || path.starts_with("internal/")
+        || MARKERS.iter().any(|m| path.contains(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frame() {
+        let line = "thread 'main' panicked at src/main.rs:2:5:";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!((frame.path, frame.line, frame.column), ("src/main.rs", 2, Some(5)));
+        assert_eq!(&line[frame.range], "src/main.rs:2:5");
+
+        let line = "             at /rustc/0123/library/std/src/panicking.rs:689:5";
+        let frame = parse_frame(line).unwrap();
+        assert!(frame.external);
+        assert_eq!(&line[frame.range], "/rustc/0123/library/std/src/panicking.rs:689:5");
+
+        let line = r#"  File "/srv/app.py", line 12, in main"#;
+        let frame = parse_frame(line).unwrap();
+        assert_eq!((frame.path, frame.line, frame.column), ("/srv/app.py", 12, None));
+        assert_eq!(&line[frame.range], r#"/srv/app.py", line 12"#);
+        assert!(!frame.external);
+
+        let line = "    at main (/srv/app.js:10:7)";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(&line[frame.range], "/srv/app.js:10:7");
+
+        let line = "    at node:internal/main/run_main_module:28:49";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(frame.path, "node:internal/main/run_main_module");
+        assert!(frame.external);
+
+        assert_eq!(parse_frame("   3: edit::path::normalize"), None);
+        assert_eq!(parse_frame("at least we tried"), None);
+    }
+
+    #[test]
+    fn test_is_stack_trace() {
+        assert!(is_stack_trace("Traceback (most recent call last):\n  File \"a.py\", line 1\n"));
+        assert!(is_stack_trace(
+            "TypeError: x is undefined\n    at f (/a.js:1:2)\n    at /a.js:3:4\n"
+        ));
+        assert!(!is_stack_trace("Just some text\n    at f (/a.js:1:2)\n"));
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! Brackets, quotes and tags around a piece of text,
+//! see [`crate::buffer::TextBuffer::surround()`].
+
+use std::ops::Range;
+
+use crate::syntax::matching_tag;
+
+const BRACKETS: [(u8, u8); 4] = [(b'(', b')'), (b'[', b']'), (b'{', b'}'), (b'<', b'>')];
+
+/// What goes around the text, or is looked for around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Surrounding {
+    /// An opening and a closing bracket, which nest.
+    Brackets(u8, u8),
+    /// The same text on both sides, like quotes or Markdown's `**`.
+    Symmetric(String),
+    /// An HTML tag. The attributes only matter when it's added.
+    Tag { name: String, attributes: String },
+}
+
+impl Surrounding {
+    /// Parses how it's written on the command line: Either bracket stands for the pair,
+    /// `<div class="x">` for a tag and any other punctuation, like `"` or `**`, for itself.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let bytes = spec.as_bytes();
+        if let Some(&(open, close)) = BRACKETS.iter().find(|&&(o, c)| bytes == [o] || bytes == [c])
+        {
+            return Some(Self::Brackets(open, close));
+        }
+        if spec == "<>" {
+            return Some(Self::Brackets(b'<', b'>'));
+        }
+        if let Some(inner) = spec.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            let inner = inner.trim();
+            let (name, attributes) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+            let is_name =
+                |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b':' | b'.');
+            if !name.starts_with(|c: char| c.is_ascii_alphabetic()) || !name.bytes().all(is_name) {
+                return None;
+            }
+            let (name, attributes) = (name.to_string(), attributes.trim().to_string());
+            return Some(Self::Tag { name, attributes });
+        }
+        if !spec.is_empty() && !spec.chars().any(|c| c.is_alphanumeric() || c.is_whitespace()) {
+            return Some(Self::Symmetric(spec.to_string()));
+        }
+        None
+    }
+
+    pub fn open(&self) -> String {
+        match self {
+            Self::Brackets(open, _) => (*open as char).to_string(),
+            Self::Symmetric(text) => text.clone(),
+            Self::Tag { name, attributes } if attributes.is_empty() => format!("<{name}>"),
+            Self::Tag { name, attributes } => format!("<{name} {attributes}>"),
+        }
+    }
+
+    pub fn close(&self) -> String {
+        match self {
+            Self::Brackets(_, close) => (*close as char).to_string(),
+            Self::Symmetric(text) => text.clone(),
+            Self::Tag { name, .. } => format!("</{name}>"),
+        }
+    }
+
+    /// Finds the innermost occurrence in `text` that encloses `range`.
+    /// Returns the byte ranges of the opener and the closer.
+    ///
+    /// Symmetric ones have to be on the same line, and an even number of them before
+    /// the opener, which tells `"a" | "b"` apart from `"a | b"`.
+    pub fn find(&self, text: &[u8], range: Range<usize>) -> Option<[Range<usize>; 2]> {
+        match self {
+            Self::Brackets(open, close) => find_brackets(text, range, *open, *close),
+            Self::Symmetric(needle) => find_symmetric(text, range, needle.as_bytes()),
+            Self::Tag { name, .. } => find_tag(text, range, name.as_bytes()),
+        }
+    }
+}
+
+fn find_brackets(
+    text: &[u8],
+    range: Range<usize>,
+    open: u8,
+    close: u8,
+) -> Option<[Range<usize>; 2]> {
+    let mut depth = 0;
+    let beg = text[..range.start].iter().rposition(|&b| {
+        if b == close {
+            depth += 1;
+        } else if b == open {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+        }
+        false
+    })?;
+
+    depth = 0;
+    let end = range.end
+        + text[range.end..].iter().position(|&b| {
+            if b == open {
+                depth += 1;
+            } else if b == close {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            }
+            false
+        })?;
+
+    Some([beg..beg + 1, end..end + 1])
+}
+
+fn find_symmetric(text: &[u8], range: Range<usize>, needle: &[u8]) -> Option<[Range<usize>; 2]> {
+    let line_beg = text[..range.start].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let line_end =
+        text[range.end..].iter().position(|&b| b == b'\n').map_or(text.len(), |i| range.end + i);
+
+    // The unescaped occurrences on the line before the range.
+    let mut before = Vec::new();
+    let mut i = line_beg;
+    while i + needle.len() <= range.start {
+        if text[i] == b'\\' {
+            i += 2;
+        } else if text[i..].starts_with(needle) {
+            before.push(i);
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    if before.len() % 2 == 0 {
+        return None;
+    }
+    let beg = *before.last()?;
+
+    let mut i = range.end;
+    while i + needle.len() <= line_end {
+        if text[i] == b'\\' {
+            i += 2;
+        } else if text[i..].starts_with(needle) {
+            return Some([beg..beg + needle.len(), i..i + needle.len()]);
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+fn find_tag(text: &[u8], range: Range<usize>, name: &[u8]) -> Option<[Range<usize>; 2]> {
+    let tag_end = |from: usize| text[from..].iter().position(|&b| b == b'>').map(|i| from + i + 1);
+
+    // From the closest opening tag before the range outwards.
+    let mut i = range.start;
+    while let Some(beg) = text[..i].iter().rposition(|&b| b == b'<') {
+        i = beg;
+        let Some([open, close]) = matching_tag(text, beg + 1) else {
+            continue;
+        };
+        // `matching_tag()` also finds the partner of a closing tag.
+        if open.start != beg + 1 || !text[open.clone()].eq_ignore_ascii_case(name) {
+            continue;
+        }
+        let open = beg..tag_end(open.end)?;
+        let close = close.start - 2..tag_end(close.end)?;
+        if open.end <= range.start && close.start >= range.end {
+            return Some([open, close]);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(spec: &str, text: &str, range: Range<usize>) -> Option<[Range<usize>; 2]> {
+        Surrounding::parse(spec).unwrap().find(text.as_bytes(), range)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Surrounding::parse(")"), Some(Surrounding::Brackets(b'(', b')')));
+        assert_eq!(Surrounding::parse("<>"), Some(Surrounding::Brackets(b'<', b'>')));
+        assert_eq!(Surrounding::parse("**"), Some(Surrounding::Symmetric("**".to_string())));
+        let tag = Surrounding::parse("<a href=\"#\">").unwrap();
+        assert_eq!(tag.open(), "<a href=\"#\">");
+        assert_eq!(tag.close(), "</a>");
+        assert_eq!(Surrounding::parse("<1>"), None);
+        assert_eq!(Surrounding::parse("ab"), None);
+    }
+
+    #[test]
+    fn test_find_brackets() {
+        let text = "f(a, g(b), c)";
+        assert_eq!(find("(", text, 7..7), Some([6..7, 8..9]));
+        assert_eq!(find(")", text, 5..5), Some([1..2, 12..13]));
+        // The selection contains a whole pair, so it's the outer one.
+        assert_eq!(find("(", text, 5..9), Some([1..2, 12..13]));
+        assert_eq!(find("[", text, 7..7), None);
+    }
+
+    #[test]
+    fn test_find_symmetric() {
+        let text = r#"x = "a \" b" + "c""#;
+        assert_eq!(find("\"", text, 6..6), Some([4..5, 11..12]));
+        // Between two strings rather than inside one.
+        assert_eq!(find("\"", text, 13..13), None);
+        assert_eq!(find("**", "a **bold** b", 6..6), Some([2..4, 8..10]));
+        assert_eq!(find("\"", "\"a\nb\"", 1..1), None);
+    }
+
+    #[test]
+    fn test_find_tag() {
+        let text = "<div class=\"x\"><p>a</p><p>b</p></div>";
+        assert_eq!(find("<p>", text, 26..27), Some([23..26, 27..31]));
+        let [open, close] = find("<div>", text, 26..27).unwrap();
+        assert_eq!((&text[open], &text[close]), ("<div class=\"x\">", "</div>"));
+        assert_eq!(find("<span>", text, 26..27), None);
+    }
+}
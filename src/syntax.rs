@@ -2,11 +2,18 @@ use std::collections::HashMap;
 use std::path::Path;
 use std::ops::Range;
 use std::ffi::OsStr;
-use syntect::parsing::SyntaxSet;
-use syntect::highlighting::{ThemeSet, Style, Color};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::highlighting::{Theme, ThemeSet, Style, Color, FontStyle, Highlighter};
+use syntect::parsing::Scope;
 use syntect::easy::HighlightLines;
 use regex::Regex;
 
+use crate::config::{self, Config};
+use crate::stack_trace;
+use crate::document::IndentStyle;
+use crate::helpers::CoordType;
+use crate::unicode::MeasurementConfig;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FileType {
     Plain,
@@ -18,14 +25,309 @@ pub enum FileType {
     CSS,
     Dockerfile,
     YAML,
+    /// Unified diffs, patches and git commit messages.
+    Diff,
+    Markdown,
+    Shell,
+    /// Stack traces, see [`crate::stack_trace`]. Detected by content rather than by name.
+    StackTrace,
     // Add more as needed
 }
 
+impl FileType {
+    pub const ALL: [FileType; 13] = [
+        FileType::Plain,
+        FileType::Python,
+        FileType::Rust,
+        FileType::JavaScript,
+        FileType::TypeScript,
+        FileType::HTML,
+        FileType::CSS,
+        FileType::Dockerfile,
+        FileType::YAML,
+        FileType::Diff,
+        FileType::Markdown,
+        FileType::Shell,
+        FileType::StackTrace,
+    ];
+
+    /// The name used to refer to this file type in the config, e.g. `[indent.python]`.
+    pub fn config_name(self) -> &'static str {
+        match self {
+            FileType::Plain => "plain",
+            FileType::Python => "python",
+            FileType::Rust => "rust",
+            FileType::JavaScript => "javascript",
+            FileType::TypeScript => "typescript",
+            FileType::HTML => "html",
+            FileType::CSS => "css",
+            FileType::Dockerfile => "dockerfile",
+            FileType::YAML => "yaml",
+            FileType::Diff => "diff",
+            FileType::Markdown => "markdown",
+            FileType::Shell => "shell",
+            FileType::StackTrace => "stacktrace",
+        }
+    }
+
+    /// The extension files of this type usually have, e.g. to name a scratch document
+    /// that's saved. `None` for those that are recognized otherwise.
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            FileType::Plain => Some("txt"),
+            FileType::Python => Some("py"),
+            FileType::Rust => Some("rs"),
+            FileType::JavaScript => Some("js"),
+            FileType::TypeScript => Some("ts"),
+            FileType::HTML => Some("html"),
+            FileType::CSS => Some("css"),
+            FileType::YAML => Some("yaml"),
+            FileType::Diff => Some("diff"),
+            FileType::Markdown => Some("md"),
+            FileType::Shell => Some("sh"),
+            FileType::Dockerfile | FileType::StackTrace => None,
+        }
+    }
+
+    /// Whether the text is markup whose tags can be paired up, see [`matching_tag()`].
+    /// Markdown may contain inline HTML. XML files aren't recognized as a type of their own.
+    pub fn has_tags(self) -> bool {
+        matches!(self, FileType::HTML | FileType::Markdown)
+    }
+
+    /// The opening and closing characters that get inserted as a pair when typing,
+    /// see [`crate::buffer::TextBuffer::set_auto_pairs()`]. Quotes are listed
+    /// with themselves as the closer.
+    pub fn auto_pairs(self) -> &'static [(u8, u8)] {
+        const PAREN: (u8, u8) = (b'(', b')');
+        const SQUARE: (u8, u8) = (b'[', b']');
+        const CURLY: (u8, u8) = (b'{', b'}');
+        const DOUBLE: (u8, u8) = (b'"', b'"');
+        const SINGLE: (u8, u8) = (b'\'', b'\'');
+        const BACKTICK: (u8, u8) = (b'`', b'`');
+        match self {
+            // The single quote also starts Rust lifetimes, and prose is full of apostrophes.
+            FileType::Plain | FileType::Rust => &[PAREN, SQUARE, CURLY, DOUBLE],
+            FileType::Markdown => &[PAREN, SQUARE, CURLY, DOUBLE, BACKTICK],
+            FileType::JavaScript | FileType::TypeScript => {
+                &[PAREN, SQUARE, CURLY, DOUBLE, SINGLE, BACKTICK]
+            }
+            FileType::Python
+            | FileType::HTML
+            | FileType::CSS
+            | FileType::Dockerfile
+            | FileType::YAML
+            | FileType::Shell => &[PAREN, SQUARE, CURLY, DOUBLE, SINGLE],
+            FileType::Diff | FileType::StackTrace => &[],
+        }
+    }
+
+    /// How comments are written, see [`CommentStyle`].
+    pub fn comment_style(self) -> CommentStyle {
+        let (line, block) = match self {
+            FileType::Rust | FileType::JavaScript | FileType::TypeScript => {
+                (Some("//"), Some(("/*", "*/")))
+            }
+            FileType::CSS => (None, Some(("/*", "*/"))),
+            FileType::HTML | FileType::Markdown => (None, Some(("<!--", "-->"))),
+            FileType::Python | FileType::Dockerfile | FileType::YAML | FileType::Shell => {
+                (Some("#"), None)
+            }
+            FileType::Plain | FileType::Diff | FileType::StackTrace => (None, None),
+        };
+        CommentStyle { line, block }
+    }
+
+    /// The inverse of [`FileType::config_name()`].
+    pub fn from_config_name(name: &str) -> Option<FileType> {
+        Self::ALL.into_iter().find(|ft| ft.config_name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// How comments are written in a file type, see [`FileType::comment_style()`]
+/// and [`crate::buffer::TextBuffer::toggle_line_comment()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentStyle {
+    /// Starts a comment that runs to the end of the line, like `//`.
+    pub line: Option<&'static str>,
+    /// Starts and ends a comment that may span lines, like `/*` and `*/`.
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+impl CommentStyle {
+    /// Comments out `lines`, or uncomments them if all those that aren't blank are
+    /// comments already. Blank lines are left alone. The comment markers go after the
+    /// least indentation among the lines, so that they line up. Without line comments,
+    /// each line becomes a block comment of its own.
+    ///
+    /// Returns for each line the byte offset where it changed and by how much it grew,
+    /// or `None` if the file type has no comments.
+    pub fn toggle_lines(&self, lines: &mut [Vec<u8>]) -> Option<Vec<(usize, isize)>> {
+        let (open, close) = match (self.line, self.block) {
+            (Some(line), _) => (line.as_bytes(), &b""[..]),
+            (None, Some((open, close))) => (open.as_bytes(), close.as_bytes()),
+            (None, None) => return None,
+        };
+        let indent = |line: &[u8]| line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+        let blank = |line: &[u8]| indent(line) == line.len();
+        let commented = |line: &[u8]| {
+            let text = &line[indent(line)..];
+            text.len() >= open.len() + close.len()
+                && text.starts_with(open)
+                && text.ends_with(close)
+        };
+
+        let uncomment =
+            lines.iter().any(|l| !blank(l)) && lines.iter().all(|l| blank(l) || commented(l));
+        let column = lines.iter().filter(|l| !blank(l)).map(|l| indent(l)).min().unwrap_or(0);
+
+        let edits = lines
+            .iter_mut()
+            .map(|line| {
+                let len_before = line.len() as isize;
+                if blank(line) {
+                    return (0, 0);
+                }
+                if uncomment {
+                    // A space after the opener and before the closer goes with them.
+                    let beg = indent(line);
+                    let mut open_end = beg + open.len();
+                    let mut close_beg = line.len() - close.len();
+                    if !close.is_empty() && close_beg > open_end && line[close_beg - 1] == b' ' {
+                        close_beg -= 1;
+                    }
+                    if open_end < close_beg && line[open_end] == b' ' {
+                        open_end += 1;
+                    }
+                    line.truncate(close_beg);
+                    line.drain(beg..open_end);
+                    (beg, line.len() as isize - len_before)
+                } else {
+                    line.splice(column..column, open.iter().chain(b" ").copied());
+                    if !close.is_empty() {
+                        line.push(b' ');
+                        line.extend_from_slice(close);
+                    }
+                    (column, line.len() as isize - len_before)
+                }
+            })
+            .collect();
+        Some(edits)
+    }
+}
+
 pub struct HighlightedText<'a> {
     pub text: &'a str,
     pub styles: Vec<(Style, Range<usize>)>,
 }
 
+/// What an [`OverlaySpan`] marks up.
+///
+/// The order matters: If multiple overlays cover the same column,
+/// the one that sorts last wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OverlayKind {
+    /// Colors from escape sequences, see [`crate::buffer::TextBuffer::set_ansi_view()`].
+    Styled(crate::ansi::Style),
+    /// Text of lesser importance, like comments in git commit messages.
+    Dimmed,
+    /// A glyph standing in for whitespace, which is dimmed, but highlighted as the whitespace
+    /// it replaces. See [`crate::buffer::TextBuffer::set_render_whitespace()`].
+    Whitespace,
+    /// The text selection. Only tints the background, so syntax colors remain visible.
+    Selection,
+    /// The tag at the cursor and its partner, see [`matching_tag()`].
+    MatchingTag,
+    /// Any hit of the active search term.
+    SearchMatch,
+    /// The search hit that is currently selected.
+    CurrentSearchMatch,
+    /// A cursor besides the primary one, which the terminal can't show.
+    /// See [`crate::buffer::TextBuffer::for_each_cursor()`].
+    Caret,
+    /// A label of the jump motion, see [`crate::buffer::TextBuffer::set_jump_needle()`].
+    JumpLabel,
+}
+
+/// A decoration that gets composited on top of the syntax colors.
+///
+/// `line` is the row relative to the top of the rendered viewport and `columns`
+/// are visual columns relative to the left edge of the text (after the margin).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlaySpan {
+    pub line: CoordType,
+    pub columns: Range<CoordType>,
+    pub kind: OverlayKind,
+}
+
+/// A highlighted piece of a line after overlays have been composited onto it.
+pub struct CompositedSegment<'a> {
+    pub style: Style,
+    pub overlay: Option<OverlayKind>,
+    /// Visual columns covered by `text`, relative to the start of the line.
+    pub columns: Range<CoordType>,
+    pub text: &'a str,
+}
+
+/// Splits the syntax `segments` of a single line wherever one of the `overlays` starts
+/// or ends, so that each resulting piece is covered by at most one overlay kind.
+///
+/// The `overlays` are expected to belong to the same line as the `segments`.
+pub fn composite_overlays<'a>(
+    segments: Vec<(Style, &'a str)>,
+    overlays: &[OverlaySpan],
+) -> Vec<CompositedSegment<'a>> {
+    let mut result = Vec::with_capacity(segments.len() + overlays.len() * 2);
+    let mut column = 0;
+
+    for (style, text) in segments {
+        let bytes = text.as_bytes();
+        let seg_beg = column;
+        let seg_end = seg_beg + MeasurementConfig::new(&bytes).goto_offset(bytes.len()).visual_pos.x;
+        let mut cfg = MeasurementConfig::new(&bytes);
+        let mut off = 0;
+
+        while off < bytes.len() {
+            // Find the next overlay boundary within this segment.
+            let next = overlays
+                .iter()
+                .flat_map(|o| [o.columns.start, o.columns.end])
+                .filter(|&c| c > column && c < seg_end)
+                .min()
+                .unwrap_or(seg_end);
+
+            let end = if next >= seg_end {
+                bytes.len()
+            } else {
+                cfg.goto_visual(crate::helpers::Point { x: next - seg_beg, y: 0 }).offset
+            };
+
+            // `goto_visual` stops short of wide glyphs that straddle the boundary.
+            // In that case the glyph simply becomes part of the next piece.
+            if end > off {
+                let overlay = overlays
+                    .iter()
+                    .filter(|o| o.columns.contains(&column))
+                    .map(|o| o.kind)
+                    .max();
+                result.push(CompositedSegment {
+                    style,
+                    overlay,
+                    columns: column..next,
+                    text: &text[off..end],
+                });
+                off = end;
+            }
+
+            column = next;
+        }
+
+        column = seg_end;
+    }
+
+    result
+}
+
 pub struct SyntaxHighlighter {
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
@@ -35,6 +337,8 @@ pub struct SyntaxHighlighter {
 
 impl SyntaxHighlighter {
     const MAX_CACHE_SIZE: usize = 1000;
+    /// The theme used unless the config picks another one.
+    pub const DEFAULT_THEME: &str = "base16-mocha.dark";
 
     pub fn new() -> Self {
         let syntax_set = SyntaxSet::load_defaults_newlines();
@@ -43,7 +347,7 @@ impl SyntaxHighlighter {
         Self {
             syntax_set,
             theme_set,
-            current_theme: "base16-mocha.dark".to_string(), // Use a warmer default theme
+            current_theme: Self::DEFAULT_THEME.to_string(), // Use a warmer default theme
             highlight_cache: HashMap::new(),
         }
     }
@@ -79,9 +383,14 @@ impl SyntaxHighlighter {
 
     pub fn detect_file_type(filename: &str) -> FileType {
         // Special case files
-        if filename == "Dockerfile" {
+        if is_dockerfile(filename) {
             return FileType::Dockerfile;
         }
+
+        // Git opens these in $EDITOR. With `commit -v` they even contain the diff itself.
+        if matches!(filename, "COMMIT_EDITMSG" | "MERGE_MSG" | "SQUASH_MSG" | "TAG_EDITMSG") {
+            return FileType::Diff;
+        }
         
         // Check for common YAML files without extensions
         match filename.to_lowercase().as_str() {
@@ -96,7 +405,7 @@ impl SyntaxHighlighter {
             return FileType::YAML;
         }
 
-        let detected_type = match Path::new(filename)
+        match Path::new(filename)
             .extension()
             .and_then(OsStr::to_str)
         {
@@ -109,10 +418,11 @@ impl SyntaxHighlighter {
             Some("css") => FileType::CSS,
             // Enhanced YAML detection
             Some("yaml") | Some("yml") => FileType::YAML,
+            Some("diff") | Some("patch") => FileType::Diff,
+            Some("md") | Some("markdown") => FileType::Markdown,
+            Some("sh") | Some("bash") | Some("zsh") => FileType::Shell,
             _ => FileType::Plain,
-        };
-        
-        detected_type
+        }
     }
 
     pub fn highlight_line<'a>(
@@ -128,43 +438,23 @@ impl SyntaxHighlighter {
             }
         }
         
+        if file_type == FileType::Dockerfile {
+            return self.highlight_dockerfile_line(line);
+        }
+
+        if file_type == FileType::StackTrace {
+            return self.highlight_stack_trace_line(line);
+        }
+
         // Create cache key
         let cache_key = (line.to_string(), line_number);
         
-        // Get syntax reference based on file type
-        let syntax = match file_type {
-            FileType::Plain => self.syntax_set.find_syntax_plain_text(),
-            FileType::Python => self.syntax_set.find_syntax_by_extension("py").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::Rust => self.syntax_set.find_syntax_by_extension("rs").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::JavaScript => self.syntax_set.find_syntax_by_extension("js").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::TypeScript => {
-                self.syntax_set.find_syntax_by_extension("ts")
-                    .or_else(|| self.syntax_set.find_syntax_by_name("TypeScript"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("TypeScript (JavaScript)"))
-                    .or_else(|| self.syntax_set.find_syntax_by_extension("js"))
-                    .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
-            },
-            FileType::HTML => self.syntax_set.find_syntax_by_extension("html").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::CSS => self.syntax_set.find_syntax_by_extension("css").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::Dockerfile => self.syntax_set.find_syntax_by_extension("Dockerfile").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::YAML => {
-                // First try to find actual YAML syntax
-                self.syntax_set.find_syntax_by_extension("yaml")
-                    .or_else(|| self.syntax_set.find_syntax_by_extension("yml"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("YAML"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("Yaml"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("yaml"))
-                    // If no YAML syntax, use JSON as it's similar structure
-                    .or_else(|| self.syntax_set.find_syntax_by_extension("json"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("JSON"))
-                    .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
-            },
-        };
+        let syntax = self.syntax_for(file_type);
 
         // Perform highlighting
         let mut highlighter = HighlightLines::new(
             syntax,
-            &self.theme_set.themes[&self.current_theme]
+            self.current_theme()
         );
 
         let highlighted = highlighter.highlight_line(line, &self.syntax_set)
@@ -201,8 +491,23 @@ impl SyntaxHighlighter {
         self.highlight_cache.clear();
     }
 
+    /// The name of the current theme.
+    pub fn theme(&self) -> &str {
+        &self.current_theme
+    }
+
+    /// The current theme, or the default one should the current one be unknown.
+    fn current_theme(&self) -> &Theme {
+        let themes = &self.theme_set.themes;
+        themes.get(&self.current_theme).unwrap_or_else(|| &themes[Self::DEFAULT_THEME])
+    }
+
+    pub fn has_theme(&self, theme_name: &str) -> bool {
+        self.theme_set.themes.contains_key(theme_name)
+    }
+
     pub fn set_theme(&mut self, theme_name: &str) -> bool {
-        if self.theme_set.themes.contains_key(theme_name) {
+        if self.has_theme(theme_name) {
             self.current_theme = theme_name.to_string();
             self.clear_cache();
             true
@@ -224,12 +529,102 @@ impl SyntaxHighlighter {
         Ok(())
     }
 
+    /// The selection background of the current theme, if it specifies one.
+    pub fn selection_color(&self) -> Option<Color> {
+        self.current_theme().settings.selection
+    }
+
     pub fn available_themes(&self) -> Vec<String> {
         self.theme_set.themes.keys()
             .map(|k| k.to_string())
             .collect()
     }
 
+    /// Renders `text` as a standalone HTML page with line numbers, colored with the current theme.
+    /// Lines are highlighted the same way the editor does it, so the page looks like the screen.
+    /// Tabs are `tab_size` columns wide.
+    pub fn export_html(
+        &mut self,
+        text: &str,
+        file_type: FileType,
+        tab_size: CoordType,
+        title: &str,
+    ) -> String {
+        let settings = &self.current_theme().settings;
+        let bg = settings.background.unwrap_or(Color::BLACK);
+        let fg = settings.foreground.unwrap_or(Color::WHITE);
+        let gutter = settings.gutter_foreground.unwrap_or(Color { a: 0x80, ..fg });
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+        html_escape(&mut html, title);
+        html.push_str("</title>\n<style>\n");
+        html.push_str(&format!("body {{ margin: 0; background: {}; color: {}; }}\n", css_color(bg), css_color(fg)));
+        html.push_str(&format!(
+            "pre {{ margin: 0; padding: 1em; font-family: monospace; tab-size: {tab_size}; }}\n"
+        ));
+        html.push_str(&format!(".ln {{ color: {}; user-select: none; }}\n", css_color(gutter)));
+        html.push_str("</style>\n</head>\n<body>\n<pre>");
+
+        let line_count = text.lines().count().max(1);
+        let width = line_count.ilog10() as usize + 1;
+        for (i, line) in text.lines().enumerate() {
+            html.push_str(&format!("<span class=\"ln\">{:>width$} </span>", i + 1));
+            for (style, piece) in self.highlight_line(line, file_type, i) {
+                if piece.is_empty() {
+                    continue;
+                }
+                html.push_str(&format!("<span style=\"color: {}", css_color(style.foreground)));
+                if style.font_style.contains(FontStyle::BOLD) {
+                    html.push_str("; font-weight: bold");
+                }
+                if style.font_style.contains(FontStyle::ITALIC) {
+                    html.push_str("; font-style: italic");
+                }
+                if style.font_style.contains(FontStyle::UNDERLINE) {
+                    html.push_str("; text-decoration: underline");
+                }
+                html.push_str("\">");
+                html_escape(&mut html, piece);
+                html.push_str("</span>");
+            }
+            html.push('\n');
+        }
+
+        html.push_str("</pre>\n</body>\n</html>\n");
+        html
+    }
+
+    /// Renders `text` with 24-bit ANSI colors for printing to a terminal.
+    /// The background is left alone, so it blends in with the terminal.
+    pub fn export_ansi(&mut self, text: &str, file_type: FileType) -> String {
+        let mut out = String::with_capacity(text.len() * 2);
+        for (i, line) in text.lines().enumerate() {
+            for (style, piece) in self.highlight_line(line, file_type, i) {
+                if piece.is_empty() {
+                    continue;
+                }
+                let c = style.foreground;
+                out.push_str(&format!("\x1b[38;2;{};{};{}m", c.r, c.g, c.b));
+                if style.font_style.contains(FontStyle::BOLD) {
+                    out.push_str("\x1b[1m");
+                }
+                if style.font_style.contains(FontStyle::ITALIC) {
+                    out.push_str("\x1b[3m");
+                }
+                if style.font_style.contains(FontStyle::UNDERLINE) {
+                    out.push_str("\x1b[4m");
+                }
+                out.push_str(piece);
+                if !style.font_style.is_empty() {
+                    out.push_str("\x1b[22;23;24m");
+                }
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
     /// Debug function to list all available syntax definitions
     pub fn list_available_syntaxes(&self) -> Vec<String> {
         self.syntax_set.syntaxes().iter()
@@ -244,37 +639,118 @@ impl SyntaxHighlighter {
     
     /// Debug method to check what syntax is being used for a file type
     pub fn debug_syntax_for_filetype(&self, file_type: FileType) -> String {
-        let syntax = match file_type {
-            FileType::Plain => self.syntax_set.find_syntax_plain_text(),
-            FileType::Python => self.syntax_set.find_syntax_by_extension("py").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::Rust => self.syntax_set.find_syntax_by_extension("rs").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::JavaScript => self.syntax_set.find_syntax_by_extension("js").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::TypeScript => {
-                self.syntax_set.find_syntax_by_extension("ts")
-                    .or_else(|| self.syntax_set.find_syntax_by_name("TypeScript"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("TypeScript (JavaScript)"))
-                    .or_else(|| self.syntax_set.find_syntax_by_extension("js"))
-                    .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
-            },
-            FileType::HTML => self.syntax_set.find_syntax_by_extension("html").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::CSS => self.syntax_set.find_syntax_by_extension("css").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::Dockerfile => self.syntax_set.find_syntax_by_extension("Dockerfile").unwrap_or_else(|| self.syntax_set.find_syntax_plain_text()),
-            FileType::YAML => {
-                self.syntax_set.find_syntax_by_extension("yaml")
-                    .or_else(|| self.syntax_set.find_syntax_by_extension("yml"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("YAML"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("Yaml"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("yaml"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("YML"))
-                    .or_else(|| self.syntax_set.find_syntax_by_name("Yet Another Markup Language"))
-                    .or_else(|| self.syntax_set.find_syntax_by_extension("json").or_else(|| self.syntax_set.find_syntax_by_name("JSON")))
-                    .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
-            },
-        };
-        
+        let syntax = self.syntax_for(file_type);
         format!("FileType: {:?} -> Syntax: {}", file_type, syntax.name)
     }
     
+    /// Picks the syntect syntax definition for the given file type.
+    fn syntax_for(&self, file_type: FileType) -> &SyntaxReference {
+        let ss = &self.syntax_set;
+        let syntax = match file_type {
+            FileType::Plain => None,
+            FileType::Python => ss.find_syntax_by_extension("py"),
+            FileType::Rust => ss.find_syntax_by_extension("rs"),
+            FileType::JavaScript => ss.find_syntax_by_extension("js"),
+            FileType::TypeScript => ss
+                .find_syntax_by_extension("ts")
+                .or_else(|| ss.find_syntax_by_name("TypeScript"))
+                .or_else(|| ss.find_syntax_by_name("TypeScript (JavaScript)"))
+                .or_else(|| ss.find_syntax_by_extension("js")),
+            FileType::HTML => ss.find_syntax_by_extension("html"),
+            FileType::CSS => ss.find_syntax_by_extension("css"),
+            FileType::Dockerfile => ss.find_syntax_by_extension("Dockerfile"),
+            FileType::YAML => ss
+                .find_syntax_by_extension("yaml")
+                .or_else(|| ss.find_syntax_by_extension("yml"))
+                .or_else(|| ss.find_syntax_by_name("YAML"))
+                .or_else(|| ss.find_syntax_by_name("Yaml"))
+                .or_else(|| ss.find_syntax_by_name("yaml"))
+                // If no YAML syntax, use JSON as it's similar structure
+                .or_else(|| ss.find_syntax_by_extension("json"))
+                .or_else(|| ss.find_syntax_by_name("JSON")),
+            FileType::Diff => ss.find_syntax_by_extension("diff"),
+            FileType::Markdown => ss.find_syntax_by_extension("md"),
+            FileType::Shell => ss.find_syntax_by_extension("sh"),
+            FileType::StackTrace => None,
+        };
+        syntax.unwrap_or_else(|| ss.find_syntax_plain_text())
+    }
+
+    /// Colors the file reference of stack frames like a link, so they stand out
+    /// as something Enter can follow. External frames are dimmed by the text buffer.
+    fn highlight_stack_trace_line<'a>(&self, line: &'a str) -> Vec<(Style, &'a str)> {
+        let theme = self.current_theme();
+        let highlighter = Highlighter::new(theme);
+        let default = highlighter.get_default();
+
+        let Some(frame) = stack_trace::parse_frame(line) else {
+            return vec![(default, line)];
+        };
+        let link = match Scope::new("markup.underline.link") {
+            Ok(scope) => highlighter.style_for_stack(&[scope]),
+            Err(_) => default,
+        };
+        let link = Style { font_style: link.font_style | FontStyle::UNDERLINE, ..link };
+        vec![
+            (default, &line[..frame.range.start]),
+            (link, &line[frame.range.clone()]),
+            (default, &line[frame.range.end..]),
+        ]
+    }
+
+    /// syntect doesn't ship a Dockerfile grammar, so we color the instruction keyword
+    /// and comments ourselves and hand the command of `RUN` lines to the shell grammar.
+    /// Lines are highlighted in isolation, so indented lines without an instruction
+    /// are assumed to be `\`-continuations of a `RUN` command.
+    fn highlight_dockerfile_line<'a>(&self, line: &'a str) -> Vec<(Style, &'a str)> {
+        let theme = self.current_theme();
+        let style_for = |scope: &str| {
+            let highlighter = Highlighter::new(theme);
+            match Scope::new(scope) {
+                Ok(scope) => highlighter.style_for_stack(&[scope]),
+                Err(_) => highlighter.get_default(),
+            }
+        };
+        let default = style_for("source.dockerfile");
+
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        if trimmed.starts_with('#') {
+            return vec![(default, indent), (style_for("comment.line.dockerfile"), trimmed)];
+        }
+
+        let word_len = trimmed.find(|c: char| c.is_ascii_whitespace()).unwrap_or(trimmed.len());
+        let (word, rest) = trimmed.split_at(word_len);
+        let is_instruction = !word.is_empty()
+            && word.bytes().all(|b| b.is_ascii_alphabetic())
+            && DOCKERFILE_INSTRUCTIONS.iter().any(|i| i.eq_ignore_ascii_case(word));
+
+        let (mut result, shell) = if is_instruction {
+            let keyword = style_for("keyword.other.dockerfile");
+            let mut result = vec![(default, indent), (keyword, word)];
+            if !word.eq_ignore_ascii_case("RUN") {
+                result.push((default, rest));
+                return result;
+            }
+            (result, rest)
+        } else if !indent.is_empty() {
+            (Vec::new(), line)
+        } else {
+            return vec![(default, line)];
+        };
+
+        let Some(syntax) = self.syntax_set.find_syntax_by_extension("sh") else {
+            result.push((default, shell));
+            return result;
+        };
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        match highlighter.highlight_line(shell, &self.syntax_set) {
+            Ok(spans) => result.extend(spans),
+            Err(_) => result.push((default, shell)),
+        }
+        result
+    }
+
     /// Custom YAML highlighting when syntect doesn't have YAML support
     fn custom_yaml_highlight<'a>(&self, line: &'a str) -> Option<Vec<(Style, &'a str)>> {
         // Check if native YAML highlighting is available
@@ -345,6 +821,314 @@ impl SyntaxHighlighter {
     }
 } 
 
+const DOCKERFILE_INSTRUCTIONS: &[&str] = &[
+    "ADD", "ARG", "CMD", "COPY", "ENTRYPOINT", "ENV", "EXPOSE", "FROM", "HEALTHCHECK", "LABEL",
+    "MAINTAINER", "ONBUILD", "RUN", "SHELL", "STOPSIGNAL", "USER", "VOLUME", "WORKDIR",
+];
+
+/// The CSS named colors, sorted by name for binary search.
+const CSS_NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4), ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4),
+    ("black", 0x000000), ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a), ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e), ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c), ("cyan", 0x00ffff), ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b), ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc), ("darkred", 0x8b0000), ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b), ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22), ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700), ("goldenrod", 0xdaa520), ("gray", 0x808080),
+    ("green", 0x008000), ("greenyellow", 0xadff2f), ("grey", 0x808080), ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c), ("indigo", 0x4b0082), ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa), ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3), ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a), ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32), ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa), ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371), ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5), ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000), ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500),
+    ("orchid", 0xda70d6), ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093), ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb), ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399), ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347), ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00), ("yellowgreen", 0x9acd32),
+];
+
+/// Finds the color literals in a line of CSS (or CSS embedded in HTML):
+/// `#rgb`, `#rrggbb` (with optional alpha), `rgb()`/`rgba()` and named colors.
+/// Returns the byte range of each literal and its color.
+///
+/// Named colors are only recognized after a `:`, so that property names,
+/// selectors and prose in HTML don't light up.
+pub fn color_literals(line: &str) -> Vec<(Range<usize>, Color)> {
+    let bytes = line.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'_';
+    let value_start = line.find(':').map_or(usize::MAX, |i| i + 1);
+    let mut result = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if i > 0 && is_word(bytes[i - 1]) {
+            i += 1;
+            continue;
+        }
+
+        let rest = &line[i..];
+        let word_len = rest.bytes().position(|b| !is_word(b)).unwrap_or(rest.len());
+        let word = &rest[..word_len];
+
+        let found = if bytes[i] == b'#' && (i == 0 || bytes[i - 1] != b'&') {
+            let hex = &rest[1..];
+            let len = hex.bytes().position(|b| !is_word(b)).unwrap_or(hex.len());
+            parse_hex_color(&hex[..len]).map(|c| (1 + len, c))
+        } else if (word.eq_ignore_ascii_case("rgb") || word.eq_ignore_ascii_case("rgba"))
+            && let Some(args) = rest[word_len..].strip_prefix('(')
+        {
+            args.find(')')
+                .and_then(|end| parse_rgb_args(&args[..end]).map(|c| (word_len + 1 + end + 1, c)))
+        } else if word_len > 0 && i >= value_start {
+            let word = word.to_ascii_lowercase();
+            let idx = CSS_NAMED_COLORS.binary_search_by(|(name, _)| (*name).cmp(&word)).ok();
+            idx.map(|idx| {
+                let [_, r, g, b] = CSS_NAMED_COLORS[idx].1.to_be_bytes();
+                (word_len, Color { r, g, b, a: 0xff })
+            })
+        } else {
+            None
+        };
+
+        match found {
+            Some((len, color)) => {
+                result.push((i..i + len, color));
+                i += len;
+            }
+            None => i += word_len.max(1),
+        }
+    }
+
+    result
+}
+
+/// Parses the digits of `#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa`.
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    match hex.len() {
+        3 | 4 => Some(Color {
+            r: digit(0) * 0x11,
+            g: digit(1) * 0x11,
+            b: digit(2) * 0x11,
+            a: if hex.len() == 4 { digit(3) * 0x11 } else { 0xff },
+        }),
+        6 | 8 => Some(Color {
+            r: pair(0),
+            g: pair(2),
+            b: pair(4),
+            a: if hex.len() == 8 { pair(6) } else { 0xff },
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the arguments of `rgb()`, in either the legacy `1, 2, 3` or the `1 2 3 / 50%` syntax.
+/// Channels may be given as numbers or percentages.
+fn parse_rgb_args(args: &str) -> Option<Color> {
+    let (channels, alpha) = match args.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha)),
+        None => (args, None),
+    };
+    let mut parts =
+        channels.split(|c: char| c == ',' || c.is_ascii_whitespace()).filter(|s| !s.is_empty());
+
+    let parse = |s: &str, scale: f32| -> Option<u8> {
+        let value = match s.strip_suffix('%') {
+            Some(p) => p.parse::<f32>().ok()? / 100.0 * scale,
+            None => s.parse::<f32>().ok()?,
+        };
+        Some(value.round().clamp(0.0, 255.0) as u8)
+    };
+
+    let r = parse(parts.next()?, 255.0)?;
+    let g = parse(parts.next()?, 255.0)?;
+    let b = parse(parts.next()?, 255.0)?;
+    // The legacy `rgba(r, g, b, a)` syntax passes the alpha as a 4th value.
+    let alpha = alpha.map(str::trim).or(parts.next());
+    if parts.next().is_some() {
+        return None;
+    }
+    let a = match alpha {
+        Some(a) => match a.strip_suffix('%') {
+            Some(p) => p.parse::<f32>().ok()? / 100.0,
+            None => a.parse::<f32>().ok()?,
+        },
+        None => 1.0,
+    };
+    Some(Color { r, g, b, a: (a.clamp(0.0, 1.0) * 255.0).round() as u8 })
+}
+
+/// Elements that never have a closing tag in HTML, like `<br>`.
+const HTML_VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Finds the HTML/XML tag that contains `offset` and the tag it pairs with.
+/// Returns the byte ranges of both tag names, the one at `offset` first.
+///
+/// Tags nest, so in `<b><b></b></b>` the outer two pair up. A closing tag pairs with
+/// the innermost open element of the same name, which tolerates unclosed elements like `<p>`.
+/// Void elements, self-closing tags, comments, CDATA and the contents of `<script>`
+/// and `<style>` never pair with anything.
+pub fn matching_tag(text: &[u8], offset: usize) -> Option<[Range<usize>; 2]> {
+    let find = |from: usize, needle: &[u8]| {
+        text[from..].windows(needle.len()).position(|w| w.eq_ignore_ascii_case(needle))
+    };
+    let is_name = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b':' | b'.');
+    // The tag and name ranges of the currently open elements.
+    let mut stack: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    let mut i = 0;
+
+    while let Some(pos) = find(i, b"<") {
+        let beg = i + pos;
+        if beg > offset && !stack.iter().any(|(tag, _)| tag.contains(&offset)) {
+            // We're past `offset` and it's not in an element that's still open.
+            return None;
+        }
+
+        let rest = &text[beg..];
+        let skip_past = |end: &[u8]| find(beg, end).map_or(text.len(), |p| beg + p + end.len());
+        if rest.starts_with(b"<!--") {
+            i = skip_past(b"-->");
+            continue;
+        }
+        if rest.starts_with(b"<![CDATA[") {
+            i = skip_past(b"]]>");
+            continue;
+        }
+        if rest.starts_with(b"<!") || rest.starts_with(b"<?") {
+            i = skip_past(b">");
+            continue;
+        }
+
+        let closing = rest.get(1) == Some(&b'/');
+        let name_beg = beg + 1 + closing as usize;
+        if !text.get(name_beg).is_some_and(u8::is_ascii_alphabetic) {
+            i = beg + 1;
+            continue;
+        }
+        let name_len = text[name_beg..].iter().position(|&b| !is_name(b));
+        let name = name_beg..name_len.map_or(text.len(), |len| name_beg + len);
+
+        // Find the closing `>`, skipping over quoted attribute values.
+        let mut end = name.end;
+        let mut quote = None;
+        while end < text.len() {
+            match (quote, text[end]) {
+                (Some(q), b) if b == q => quote = None,
+                (None, b'"' | b'\'') => quote = Some(text[end]),
+                (None, b'>') => break,
+                _ => {}
+            }
+            end += 1;
+        }
+        let self_closing = text[end - 1] == b'/';
+        let tag = beg..(end + 1).min(text.len());
+        let tag_name = &text[name.clone()];
+        i = tag.end;
+
+        if closing {
+            let open =
+                stack.iter().rposition(|(_, n)| text[n.clone()].eq_ignore_ascii_case(tag_name));
+            let Some(idx) = open else {
+                if tag.contains(&offset) {
+                    return None;
+                }
+                continue;
+            };
+            // Elements opened after the partner are implicitly closed.
+            let (open_tag, open_name) = stack[idx].clone();
+            if stack[idx + 1..].iter().any(|(t, _)| t.contains(&offset)) {
+                return None;
+            }
+            stack.truncate(idx);
+            if open_tag.contains(&offset) {
+                return Some([open_name, name]);
+            }
+            if tag.contains(&offset) {
+                return Some([name, open_name]);
+            }
+        } else if self_closing
+            || HTML_VOID_ELEMENTS.iter().any(|v| v.as_bytes().eq_ignore_ascii_case(tag_name))
+        {
+            if tag.contains(&offset) {
+                return None;
+            }
+        } else {
+            if tag_name.eq_ignore_ascii_case(b"script") || tag_name.eq_ignore_ascii_case(b"style") {
+                // Raw text elements: Their contents are not markup.
+                let mut needle = b"</".to_vec();
+                needle.extend_from_slice(tag_name);
+                i = find(i, &needle).map_or(text.len(), |p| i + p);
+            }
+            stack.push((tag, name));
+        }
+    }
+
+    None
+}
+
+/// Matches `Dockerfile`, `Containerfile`, variants like `Dockerfile.dev`, and `*.dockerfile`.
+fn is_dockerfile(filename: &str) -> bool {
+    let name = Path::new(filename).file_name().and_then(OsStr::to_str).unwrap_or(filename);
+    let name = name.to_ascii_lowercase();
+    ["dockerfile", "containerfile"]
+        .iter()
+        .any(|base| name == *base || name.strip_prefix(base).is_some_and(|r| r.starts_with('.')))
+        || name.ends_with(".dockerfile")
+}
+
+fn css_color(c: Color) -> String {
+    if c.a == 0xff {
+        format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a)
+    }
+}
+
+fn html_escape(dst: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => dst.push_str("&amp;"),
+            '<' => dst.push_str("&lt;"),
+            '>' => dst.push_str("&gt;"),
+            '"' => dst.push_str("&quot;"),
+            _ => dst.push(ch),
+        }
+    }
+}
+
 /// Smart indentation rule for a language
 #[derive(Debug)]
 pub struct IndentRule {
@@ -354,6 +1138,13 @@ pub struct IndentRule {
     pub decrease_patterns: Vec<Regex>,
     /// Patterns that both decrease current line and increase next line
     pub decrease_increase_patterns: Vec<Regex>,
+    /// Columns per indentation level. `None` uses the buffer's tab size.
+    pub tab_width: Option<usize>,
+    /// Whether the decrease patterns match lines that close a block, like `}`.
+    /// Not so in Python, where e.g. `return` merely ends the statements of one.
+    pub closing_lines: bool,
+    /// Whether the user's config defined (some of) the patterns of the rule.
+    pub user_defined: bool,
 }
 
 impl IndentRule {
@@ -369,6 +1160,9 @@ impl IndentRule {
             decrease_increase_patterns: vec![
                 Regex::new(r"^\s*(elif|else|except|finally).*:\s*(?:#.*)?$").unwrap(),
             ],
+            tab_width: None,
+            user_defined: false,
+            closing_lines: false,
         }
     }
     
@@ -384,6 +1178,9 @@ impl IndentRule {
             decrease_increase_patterns: vec![
                 Regex::new(r"^\s*\}\s*else\s*\{").unwrap(),     // } else {
             ],
+            tab_width: None,
+            user_defined: false,
+            closing_lines: true,
         }
     }
     
@@ -401,6 +1198,9 @@ impl IndentRule {
                 Regex::new(r"^\s*\}\s*catch\s*\(").unwrap(),    // } catch (
                 Regex::new(r"^\s*\}\s*finally\s*\{").unwrap(),  // } finally {
             ],
+            tab_width: None,
+            user_defined: false,
+            closing_lines: true,
         }
     }
     
@@ -415,6 +1215,9 @@ impl IndentRule {
                 Regex::new(r"^\s*</").unwrap(),                 // closing tags
             ],
             decrease_increase_patterns: vec![],
+            tab_width: None,
+            user_defined: false,
+            closing_lines: true,
         }
     }
 
@@ -427,6 +1230,9 @@ impl IndentRule {
                 Regex::new(r"^\s*\}").unwrap(),                 // closing brace
             ],
             decrease_increase_patterns: vec![],
+            tab_width: None,
+            user_defined: false,
+            closing_lines: true,
         }
     }
 
@@ -443,7 +1249,104 @@ impl IndentRule {
                 // YAML doesn't typically have decrease patterns like braces
             ],
             decrease_increase_patterns: vec![],
+            tab_width: None,
+            user_defined: false,
+            closing_lines: false,
+        }
+    }
+}
+
+impl IndentRule {
+    /// Applies the `[indent.<filetype>]` section of the config on top of `self`.
+    /// Each key replaces the built-in value, e.g. `increase = ['then$', '\bdo$']`.
+    /// Invalid regexes are skipped.
+    fn apply_config(&mut self, config: &Config, section: &str) {
+        let patterns = |key: &str| {
+            config.get_str_array(&format!("{section}.{key}")).map(|pats| {
+                pats.into_iter().filter_map(|p| Regex::new(p).ok()).collect::<Vec<_>>()
+            })
+        };
+
+        // Only the patterns make the rule the user's. A `tab_width` alone merely adjusts it.
+        if let Some(p) = patterns("increase") {
+            self.increase_patterns = p;
+            self.user_defined = true;
+        }
+        if let Some(p) = patterns("decrease") {
+            self.decrease_patterns = p;
+            self.user_defined = true;
+        }
+        if let Some(p) = patterns("decrease_increase") {
+            self.decrease_increase_patterns = p;
+            self.user_defined = true;
         }
+        if let Some(w) = config.get_int(&format!("{section}.tab_width")) {
+            self.tab_width = usize::try_from(w).ok().filter(|&w| w > 0);
+        }
+    }
+
+    /// Returns the foldable regions of `lines` as ranges of line indices.
+    ///
+    /// A region starts at a line matching one of the increase patterns (a function header,
+    /// `{`, a YAML `key:`, a Python `if ...:`) and spans all following lines that are
+    /// indented deeper. A closing line at the header's indentation, like `}` or `</div>`,
+    /// is included. Lines that both close and open a block, like `} else {`, aren't;
+    /// they start a region of their own. The header line is the first line of each range.
+    /// The ranges are ordered by their start and may be nested.
+    pub fn fold_ranges(&self, lines: &[String]) -> Vec<Range<usize>> {
+        let tab_size = self.tab_width.unwrap_or(4);
+        let indent_of = |line: &str| {
+            line.chars()
+                .take_while(|c| c.is_whitespace())
+                .map(|c| if c == '\t' { tab_size } else { 1 })
+                .sum::<usize>()
+        };
+        let is_blank = |line: &str| line.trim().is_empty();
+        let mut ranges = Vec::new();
+
+        for (start, header) in lines.iter().enumerate() {
+            if is_blank(header)
+                || !self
+                    .increase_patterns
+                    .iter()
+                    .chain(&self.decrease_increase_patterns)
+                    .any(|p| p.is_match(header))
+            {
+                continue;
+            }
+
+            let indent = indent_of(header);
+            let mut end = start + 1;
+            // The end of the last non-blank line of the region.
+            let mut last = end;
+            while end < lines.len() {
+                let line = &lines[end];
+                if !is_blank(line) {
+                    if indent_of(line) <= indent {
+                        break;
+                    }
+                    last = end + 1;
+                }
+                end += 1;
+            }
+
+            if last - start < 2 {
+                // Nothing indented below the header.
+                continue;
+            }
+            // `end` is the line that ended the region, if any.
+            if self.closing_lines
+                && let Some(line) = lines.get(end)
+                && indent_of(line) == indent
+                && self.decrease_patterns.iter().any(|p| p.is_match(line))
+                && !self.decrease_increase_patterns.iter().any(|p| p.is_match(line))
+            {
+                last = end + 1;
+            }
+            ranges.push(start..last);
+        }
+
+        ranges
     }
 }
 
@@ -453,7 +1356,12 @@ pub struct SmartIndenter {
 }
 
 impl SmartIndenter {
+    /// Creates an indenter with the built-in rules and the user's `[indent.*]` config.
     pub fn new() -> Self {
+        Self::with_config(config::get())
+    }
+
+    pub fn with_config(config: &Config) -> Self {
         let mut rules = HashMap::new();
         rules.insert(FileType::Python, IndentRule::python());
         rules.insert(FileType::Rust, IndentRule::rust());
@@ -462,19 +1370,44 @@ impl SmartIndenter {
         rules.insert(FileType::HTML, IndentRule::html());
         rules.insert(FileType::CSS, IndentRule::css());
         rules.insert(FileType::YAML, IndentRule::yaml()); // Add YAML support
-        
+
+        for name in config.subsections("indent") {
+            let Some(file_type) = FileType::from_config_name(name) else {
+                continue;
+            };
+            rules
+                .entry(file_type)
+                .or_insert_with(|| IndentRule {
+                    increase_patterns: Vec::new(),
+                    decrease_patterns: Vec::new(),
+                    decrease_increase_patterns: Vec::new(),
+                    tab_width: None,
+                    user_defined: false,
+                    closing_lines: true,
+                })
+                .apply_config(config, &format!("indent.{name}"));
+        }
+
         Self { rules }
     }
+
+    /// Returns the rule for the given file type, if there is one.
+    pub fn rule(&self, file_type: FileType) -> Option<&IndentRule> {
+        self.rules.get(&file_type)
+    }
     
-    /// Calculate the indent for a new line based on the previous lines
+    /// Calculate the indent for a new line based on the previous lines.
+    /// `indent` is the document's (detected) style, which determines the width of
+    /// tabs and of one indentation level, unless the rule overrides the latter.
     pub fn calculate_indent(
         &self,
         lines: &[String],
         current_line_idx: usize,
         current_line_content: &str,
         file_type: FileType,
-        tab_size: usize,
+        indent: IndentStyle,
     ) -> usize {
+        let tab_size = indent.width.max(1) as usize;
         let rule = match self.rules.get(&file_type) {
             Some(rule) => rule,
             None => return self.get_previous_indent(lines, current_line_idx, tab_size), // fallback
@@ -496,15 +1429,16 @@ impl SmartIndenter {
         
         let prev_line = &lines[prev_line_idx];
         let prev_indent = self.get_line_indent(prev_line, tab_size);
+        let tab_size = rule.tab_width.unwrap_or(tab_size);
         
-        // Check if current line should decrease indent
-        if rule.decrease_patterns.iter().any(|pattern| pattern.is_match(current_line_content)) {
-            return prev_indent.saturating_sub(tab_size);
-        }
-        
-        // Check if current line should both decrease and increase
-        if rule.decrease_increase_patterns.iter().any(|pattern| pattern.is_match(current_line_content)) {
-            return prev_indent; // Same as previous
+        let prev_increases = rule.increase_patterns.iter().any(|pattern| pattern.is_match(prev_line));
+
+        // Check if current line should decrease indent. Lines that both decrease and
+        // increase, like `} else {`, are dedented as well. The increase is picked up by
+        // the next line via the increase patterns.
+        if rule.decrease_patterns.iter().chain(&rule.decrease_increase_patterns).any(|pattern| pattern.is_match(current_line_content)) {
+            // An empty block, like `{` directly followed by `}`, stays at the same level.
+            return if prev_increases { prev_indent } else { prev_indent.saturating_sub(tab_size) };
         }
         
         // Special case for Python: if __name__ == '__main__' at top level should stay at top level
@@ -513,7 +1447,7 @@ impl SmartIndenter {
         }
         
         // Check if previous line should increase indent
-        if rule.increase_patterns.iter().any(|pattern| pattern.is_match(prev_line)) {
+        if prev_increases {
             return prev_indent + tab_size;
         }
         
@@ -547,4 +1481,335 @@ impl SyntaxHighlighter {
     pub fn create_smart_indenter() -> SmartIndenter {
         SmartIndenter::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACES_4: IndentStyle = IndentStyle { use_tabs: false, width: 4 };
+
+    fn span(columns: Range<CoordType>, kind: OverlayKind) -> OverlaySpan {
+        OverlaySpan { line: 0, columns, kind }
+    }
+
+    #[test]
+    fn test_detect_diff() {
+        assert_eq!(SyntaxHighlighter::detect_file_type("fix.patch"), FileType::Diff);
+        assert_eq!(SyntaxHighlighter::detect_file_type("a.diff"), FileType::Diff);
+        assert_eq!(SyntaxHighlighter::detect_file_type("COMMIT_EDITMSG"), FileType::Diff);
+        assert_eq!(SyntaxHighlighter::detect_file_type("patch.txt"), FileType::Plain);
+    }
+
+    #[test]
+    fn test_highlight_diff() {
+        let mut hl = SyntaxHighlighter::new();
+        let fg = |hl: &mut SyntaxHighlighter, line: &str| {
+            hl.highlight_line(line, FileType::Diff, 0)[0].0.foreground
+        };
+        let added = fg(&mut hl, "+let a = 1;");
+        let removed = fg(&mut hl, "-let a = 1;");
+        let context = fg(&mut hl, " let a = 1;");
+        assert_ne!(added, removed);
+        assert_ne!(added, context);
+        assert_ne!(removed, context);
+    }
+
+    #[test]
+    fn test_set_theme() {
+        let mut hl = SyntaxHighlighter::new();
+        assert_eq!(hl.theme(), SyntaxHighlighter::DEFAULT_THEME);
+
+        assert!(hl.set_theme("Solarized (light)"));
+        assert_eq!(hl.theme(), "Solarized (light)");
+        let light = hl.selection_color();
+        assert!(light.is_some());
+
+        assert!(!hl.set_theme("no such theme"));
+        assert_eq!(hl.theme(), "Solarized (light)");
+        assert_eq!(hl.selection_color(), light);
+    }
+
+    #[test]
+    fn test_unknown_theme_falls_back() {
+        let mut hl = SyntaxHighlighter::new();
+        let default = hl.selection_color();
+        let styles = hl.highlight_line("let a = 1;", FileType::Rust, 0);
+        let default_fg = styles[0].0.foreground;
+
+        hl.current_theme = "no such theme".to_string();
+        hl.clear_cache();
+        assert_eq!(hl.selection_color(), default);
+        let styles = hl.highlight_line("let a = 1;", FileType::Rust, 0);
+        assert_eq!(styles[0].0.foreground, default_fg);
+        assert!(hl.export_html("a\n", FileType::Plain, 4, "a").starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn test_auto_pairs() {
+        assert!(FileType::Python.auto_pairs().contains(&(b'\'', b'\'')));
+        assert!(!FileType::Rust.auto_pairs().contains(&(b'\'', b'\'')));
+        assert!(FileType::TypeScript.auto_pairs().contains(&(b'`', b'`')));
+        assert!(FileType::Diff.auto_pairs().is_empty());
+    }
+
+    #[test]
+    fn test_toggle_comment_lines() {
+        let toggle = |file_type: FileType, lines: &[&str]| {
+            let mut lines: Vec<_> = lines.iter().map(|l| l.as_bytes().to_vec()).collect();
+            let edits = file_type.comment_style().toggle_lines(&mut lines);
+            let lines: Vec<_> = lines.into_iter().map(|l| String::from_utf8(l).unwrap()).collect();
+            edits.map(|edits| (lines, edits))
+        };
+
+        // Mixed lines get commented, lined up after the least indentation.
+        let (lines, edits) = toggle(FileType::Rust, &["    a", "", "  // b"]).unwrap();
+        assert_eq!(lines, ["  //   a", "", "  // // b"]);
+        assert_eq!(edits, [(2, 3), (0, 0), (2, 3)]);
+
+        let (lines, edits) = toggle(FileType::Python, &["  # a", "  #b", "   "]).unwrap();
+        assert_eq!(lines, ["  a", "  b", "   "]);
+        assert_eq!(edits, [(2, -2), (2, -1), (0, 0)]);
+
+        let (lines, _) = toggle(FileType::HTML, &["<p>", "  <!-- x -->"]).unwrap();
+        assert_eq!(lines, ["<!-- <p> -->", "<!--   <!-- x --> -->"]);
+        let (lines, _) = toggle(FileType::HTML, &["<!-- <p> -->", "<!---->"]).unwrap();
+        assert_eq!(lines, ["<p>", ""]);
+
+        assert_eq!(toggle(FileType::Plain, &["a"]), None);
+    }
+
+    #[test]
+    fn test_detect_dockerfile() {
+        for name in ["Dockerfile", "Containerfile", "Dockerfile.dev", "build/api.dockerfile"] {
+            assert_eq!(SyntaxHighlighter::detect_file_type(name), FileType::Dockerfile, "{name}");
+        }
+        assert_eq!(SyntaxHighlighter::detect_file_type("Dockerfiles.txt"), FileType::Plain);
+        assert_eq!(SyntaxHighlighter::detect_file_type(".dockerfile"), FileType::Dockerfile);
+    }
+
+    #[test]
+    fn test_highlight_dockerfile_run() {
+        let mut hl = SyntaxHighlighter::new();
+        let spans = hl.highlight_line("RUN echo \"hi\" && ls", FileType::Dockerfile, 0);
+        let text: String = spans.iter().map(|(_, s)| *s).collect();
+        assert_eq!(text, "RUN echo \"hi\" && ls");
+        // The shell grammar splits the command into multiple differently colored spans.
+        let colors: Vec<_> = spans.iter().map(|(s, _)| s.foreground).collect();
+        assert!(colors.windows(2).filter(|w| w[0] != w[1]).count() >= 2);
+
+        let spans = hl.highlight_line("FROM rust:latest", FileType::Dockerfile, 0);
+        assert_eq!(spans.iter().map(|(_, s)| *s).collect::<Vec<_>>(), ["", "FROM", " rust:latest"]);
+    }
+
+    #[test]
+    fn test_export_html() {
+        let mut hl = SyntaxHighlighter::new();
+        let html =
+            hl.export_html("fn main() {\n    a < b && c;\n}\n", FileType::Rust, 8, "<main.rs>");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("tab-size: 8;"));
+        assert!(html.contains("<title>&lt;main.rs&gt;</title>"));
+        assert!(html.contains("<span class=\"ln\">1 </span>"));
+        assert!(html.contains("<span class=\"ln\">3 </span>"));
+        assert!(!html.contains("<span class=\"ln\">4 </span>"));
+        assert!(html.contains("&lt;"));
+        assert!(html.contains("&amp;"));
+        assert!(!html.contains("a < b") && !html.contains(" && "));
+    }
+
+    #[test]
+    fn test_export_ansi() {
+        let mut hl = SyntaxHighlighter::new();
+        let ansi = hl.export_ansi("fn main() {}\nlet a = 1;\n", FileType::Rust);
+        assert!(ansi.starts_with("\x1b[38;2;"));
+        assert_eq!(ansi.matches("\x1b[0m\n").count(), 2);
+
+        // Without the escape sequences, the text is unchanged.
+        let plain = Regex::new("\x1b\\[[0-9;]*m").unwrap().replace_all(&ansi, "");
+        assert_eq!(plain, "fn main() {}\nlet a = 1;\n");
+    }
+
+    #[test]
+    fn test_color_literals() {
+        assert!(CSS_NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let rgb = |r, g, b| Color { r, g, b, a: 0xff };
+        let line = "a { color: #f80; background: #00ff0080; border: 1px solid Tomato; }";
+        assert_eq!(
+            color_literals(line),
+            vec![
+                (11..15, rgb(0xff, 0x88, 0x00)),
+                (29..38, Color { r: 0, g: 0xff, b: 0, a: 0x80 }),
+                (58..64, rgb(0xff, 0x63, 0x47)),
+            ]
+        );
+
+        let line = "color: rgb(255, 0, 0); fill: rgba(0 0 255 / 50%);";
+        assert_eq!(
+            color_literals(line),
+            vec![(7..21, rgb(255, 0, 0)), (29..48, Color { r: 0, g: 0, b: 255, a: 128 })]
+        );
+
+        // Not colors: selectors, property names, entities, bad hex lengths, identifiers.
+        assert_eq!(color_literals("#header .red-text { color: var(--red) }"), vec![]);
+        assert_eq!(color_literals("<p>&#123; red</p>"), vec![]);
+        assert_eq!(color_literals("color: #12345; x: #ggg;"), vec![]);
+    }
+
+    #[test]
+    fn test_matching_tag() {
+        // Returns where the names of the tag at `offset` and its partner start.
+        let pair = |text: &str, offset: usize| {
+            matching_tag(text.as_bytes(), offset).map(|[a, b]| (a.start, b.start))
+        };
+
+        let text = r#"<div class="a>b"><div></div><br><img/></div>"#;
+        assert_eq!(pair(text, 0), Some((1, 40)));
+        assert_eq!(pair(text, 12), Some((1, 40)));
+        assert_eq!(pair(text, 16), Some((1, 40)));
+        assert_eq!(pair(text, 17), Some((18, 24)));
+        assert_eq!(pair(text, 27), Some((24, 18)));
+        assert_eq!(pair(text, 43), Some((40, 1)));
+        // Void elements and self-closing tags have no partner.
+        assert_eq!(pair(text, 29), None);
+        assert_eq!(pair(text, 33), None);
+
+        // Unclosed elements get closed implicitly. Text isn't a tag.
+        let text = "<ul><li>a<li>b</ul>";
+        assert_eq!(pair(text, 0), Some((1, 16)));
+        assert_eq!(pair(text, 5), None);
+        assert_eq!(pair(text, 8), None);
+
+        // Comments, CDATA and raw text don't count. Names are case-insensitive.
+        let text = "<a><!-- </a> --><![CDATA[</a>]]><script>if (a</b) {}</script></A>";
+        assert_eq!(pair(text, 1), Some((1, 63)));
+        assert_eq!(pair(text, 33), Some((33, 54)));
+        assert_eq!(pair(text, 8), None);
+
+        // Inline HTML in Markdown, next to generics in a code span.
+        let text = "Use `Vec<u8>` and press <kbd>Ctrl</kbd>.";
+        assert!(FileType::Markdown.has_tags());
+        assert_eq!(pair(text, 25), Some((25, 35)));
+        assert_eq!(pair(text, 9), None);
+    }
+
+    #[test]
+    fn test_indent_rules_from_config() {
+        let config = Config::parse(
+            r"
+            [indent.plain]
+            increase = ['\bthen$', '\bdo$']
+            decrease = '^\s*end\b'
+            tab_width = 3
+            [indent.rust]
+            tab_width = 2
+            [indent.cobol]
+            increase = '.'
+            ",
+        );
+        let indenter = SmartIndenter::with_config(&config);
+        let lines = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let plain = lines(&["if x then"]);
+        assert_eq!(indenter.calculate_indent(&plain, 1, "", FileType::Plain, SPACES_4), 3);
+        let plain = lines(&["   foo()"]);
+        assert_eq!(indenter.calculate_indent(&plain, 1, "end", FileType::Plain, SPACES_4), 0);
+
+        // Keys that aren't set keep the built-in patterns.
+        let rust = lines(&["fn main() {"]);
+        assert_eq!(indenter.calculate_indent(&rust, 1, "", FileType::Rust, SPACES_4), 2);
+        assert!(indenter.rule(FileType::Plain).unwrap().user_defined);
+        assert!(!indenter.rule(FileType::Rust).unwrap().user_defined);
+        assert!(!indenter.rule(FileType::Python).unwrap().user_defined);
+    }
+
+    #[test]
+    fn test_indent_closing_lines() {
+        let indenter = SmartIndenter::with_config(&Config::default());
+        let lines = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let rust = lines(&["        foo();"]);
+        assert_eq!(indenter.calculate_indent(&rust, 1, "}", FileType::Rust, SPACES_4), 4);
+        assert_eq!(indenter.calculate_indent(&rust, 1, "} else {", FileType::Rust, SPACES_4), 4);
+        // Empty blocks don't dedent.
+        let rust = lines(&["    if a {"]);
+        assert_eq!(indenter.calculate_indent(&rust, 1, "}", FileType::Rust, SPACES_4), 4);
+
+        let python = lines(&["        pass"]);
+        assert_eq!(indenter.calculate_indent(&python, 1, "else:", FileType::Python, SPACES_4), 4);
+    }
+
+    #[test]
+    fn test_composite_overlays() {
+        let segments = vec![(Style::default(), "let "), (Style::default(), "foo = foo;")];
+        let overlays = [
+            span(4..7, OverlayKind::SearchMatch),
+            span(10..13, OverlayKind::CurrentSearchMatch),
+        ];
+        let result: Vec<_> = composite_overlays(segments, &overlays)
+            .into_iter()
+            .map(|s| (s.text, s.overlay, s.columns))
+            .collect();
+
+        assert_eq!(
+            result,
+            vec![
+                ("let ", None, 0..4),
+                ("foo", Some(OverlayKind::SearchMatch), 4..7),
+                (" = ", None, 7..10),
+                ("foo", Some(OverlayKind::CurrentSearchMatch), 10..13),
+                (";", None, 13..14),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_composite_overlays_priority() {
+        let segments = vec![(Style::default(), "abcdef")];
+        let overlays =
+            [span(0..6, OverlayKind::SearchMatch), span(2..4, OverlayKind::CurrentSearchMatch)];
+        let result: Vec<_> = composite_overlays(segments, &overlays)
+            .into_iter()
+            .map(|s| (s.text, s.overlay))
+            .collect();
+
+        assert_eq!(
+            result,
+            vec![
+                ("ab", Some(OverlayKind::SearchMatch)),
+                ("cd", Some(OverlayKind::CurrentSearchMatch)),
+                ("ef", Some(OverlayKind::SearchMatch)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fold_ranges() {
+        let lines = |text: &str| text.lines().map(str::to_string).collect::<Vec<_>>();
+
+        let rust = lines(
+            "fn main() {\n    if a {\n        b();\n    } else {\n        c();\n    }\n\n    d();\n}\nfn empty() {}\n",
+        );
+        assert_eq!(IndentRule::rust().fold_ranges(&rust), vec![0..9, 1..3, 3..6]);
+
+        let python = lines("@cache\ndef f(x):\n    if x:\n        return 1\n\n    return 2\n\nprint(f(1))\n");
+        assert_eq!(IndentRule::python().fold_ranges(&python), vec![1..6, 2..4]);
+
+        let yaml = lines("jobs:\n  build:\n    steps:\n      - run: make\nname: x\n");
+        assert_eq!(IndentRule::yaml().fold_ranges(&yaml), vec![0..4, 1..4, 2..4]);
+    }
+
+    #[test]
+    fn test_detect_indent_style() {
+        let style = IndentStyle::detect(b"fn main() {\n  let a = 1;\n  if a {\n    b();\n  }\n}\n");
+        assert_eq!(style, IndentStyle { use_tabs: false, width: 2 });
+        let style = IndentStyle::detect(b"fn main() {\n\tlet a = 1;\n\tb();\n}\n");
+        assert!(style.use_tabs);
+
+        // Indentation from the file, not a global tab size, drives the smart indenter.
+        let indenter = SmartIndenter::new();
+        let lines = vec!["fn main() {".to_string()];
+        let two = IndentStyle { use_tabs: false, width: 2 };
+        assert_eq!(indenter.calculate_indent(&lines, 1, "", FileType::Rust, two), 2);
+    }
+}
@@ -5,6 +5,8 @@
 
 #[cfg(unix)]
 mod unix;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
 #[cfg(windows)]
 mod windows;
 
@@ -13,5 +15,7 @@ pub use std::fs::canonicalize;
 
 #[cfg(unix)]
 pub use unix::*;
+#[cfg(target_arch = "wasm32")]
+pub use wasm::*;
 #[cfg(windows)]
 pub use windows::*;
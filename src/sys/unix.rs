@@ -12,6 +12,8 @@ use std::mem::{self, ManuallyDrop, MaybeUninit};
 use std::os::fd::{AsRawFd as _, FromRawFd as _};
 use std::path::Path;
 use std::ptr::{self, NonNull, null_mut};
+use std::sync::Once;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{thread, time};
 
 use crate::arena::{Arena, ArenaString, scratch_arena};
@@ -92,7 +94,7 @@ pub fn switch_modes() -> apperr::Result<()> {
     unsafe {
         // Set STATE.inject_resize to true whenever we get a SIGWINCH.
         let mut sigwinch_action: libc::sigaction = mem::zeroed();
-        sigwinch_action.sa_sigaction = sigwinch_handler as libc::sighandler_t;
+        sigwinch_action.sa_sigaction = sigwinch_handler as *const () as libc::sighandler_t;
         check_int_return(libc::sigaction(libc::SIGWINCH, &sigwinch_action, null_mut()))?;
 
         // Get the original terminal modes so we can disable raw mode on exit.
@@ -152,6 +154,26 @@ pub fn switch_modes() -> apperr::Result<()> {
     }
 }
 
+/// Runs `f` with the terminal in the modes it had before [`switch_modes()`],
+/// e.g. so that a child process can prompt for a password.
+pub fn with_initial_modes<T>(f: impl FnOnce() -> T) -> T {
+    unsafe {
+        let mut raw = MaybeUninit::<libc::termios>::uninit();
+        let raw = (libc::tcgetattr(STATE.stdin, raw.as_mut_ptr()) == 0).then(|| raw.assume_init());
+        #[allow(static_mut_refs)]
+        if let Some(termios) = &STATE.stdout_initial_termios {
+            libc::tcsetattr(STATE.stdout, libc::TCSANOW, termios);
+        }
+
+        let result = f();
+
+        if let Some(termios) = &raw {
+            libc::tcsetattr(STATE.stdin, libc::TCSANOW, termios);
+        }
+        result
+    }
+}
+
 pub fn inject_window_size_into_stdin() {
     unsafe {
         STATE.inject_resize = true;
@@ -381,6 +403,122 @@ pub fn file_id(file: Option<&File>, path: &Path) -> apperr::Result<FileId> {
     }
 }
 
+/// Copies the permissions, the ownership and the extended attributes of the file
+/// at `src` onto `dst`, as far as we're allowed to. Used when saving via a temporary file.
+///
+/// Returns false if the owner or the group of `src` couldn't be kept,
+/// in which case nothing else was copied either.
+pub fn copy_file_attributes(src: &Path, dst: &File) -> bool {
+    use std::os::unix::fs::{MetadataExt as _, PermissionsExt as _, fchown};
+
+    let Ok(metadata) = fs::metadata(src) else {
+        return true;
+    };
+
+    // Only root may give away files, so this fails for other people's files.
+    // This must happen before the chmod, because a chown clears the setuid/setgid bits.
+    if fchown(dst, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        return false;
+    }
+    _ = dst.set_permissions(fs::Permissions::from_mode(metadata.mode() & 0o7777));
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+    copy_xattrs(src, dst);
+    true
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+fn copy_xattrs(src: &Path, dst: &File) {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt as _;
+
+    #[cfg(not(target_os = "macos"))]
+    unsafe fn list(path: &CStr, buf: *mut u8, size: usize) -> isize {
+        unsafe { libc::listxattr(path.as_ptr(), buf.cast(), size) }
+    }
+    #[cfg(not(target_os = "macos"))]
+    unsafe fn get(path: &CStr, name: &CStr, buf: *mut u8, size: usize) -> isize {
+        unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf.cast(), size) }
+    }
+    #[cfg(not(target_os = "macos"))]
+    unsafe fn set(fd: c_int, name: &CStr, value: &[u8]) {
+        unsafe { libc::fsetxattr(fd, name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+    }
+    #[cfg(target_os = "macos")]
+    unsafe fn list(path: &CStr, buf: *mut u8, size: usize) -> isize {
+        unsafe { libc::listxattr(path.as_ptr(), buf.cast(), size, 0) }
+    }
+    #[cfg(target_os = "macos")]
+    unsafe fn get(path: &CStr, name: &CStr, buf: *mut u8, size: usize) -> isize {
+        unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf.cast(), size, 0, 0) }
+    }
+    #[cfg(target_os = "macos")]
+    unsafe fn set(fd: c_int, name: &CStr, value: &[u8]) {
+        unsafe { libc::fsetxattr(fd, name.as_ptr(), value.as_ptr().cast(), value.len(), 0, 0) };
+    }
+
+    let Ok(src) = CString::new(src.as_os_str().as_bytes()) else {
+        return;
+    };
+
+    unsafe {
+        // The names are a list of NUL-terminated strings.
+        let len = list(&src, null_mut(), 0);
+        if len <= 0 {
+            return;
+        }
+        let mut names = vec![0u8; len as usize];
+        let len = list(&src, names.as_mut_ptr(), names.len());
+        if len <= 0 {
+            return;
+        }
+
+        for name in names[..len as usize].split_inclusive(|&b| b == 0) {
+            let Ok(name) = CStr::from_bytes_with_nul(name) else {
+                continue;
+            };
+            let size = get(&src, name, null_mut(), 0);
+            if size < 0 {
+                continue;
+            }
+            let mut value = vec![0u8; size as usize];
+            let size = get(&src, name, value.as_mut_ptr(), value.len());
+            // Some, like `security.selinux`, may not be ours to set. That's fine.
+            if size >= 0 {
+                set(dst.as_raw_fd(), name, &value[..size as usize]);
+            }
+        }
+    }
+}
+
+/// Returns true if the file at `path` has more than one hard link.
+/// Replacing it with a new file would break them.
+pub fn has_hard_links(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt as _;
+    fs::metadata(path).is_ok_and(|m| m.nlink() > 1)
+}
+
+/// Returns true if the file at `path` exists, but we may not write to it. Unlike its
+/// permission bits, this accounts for who we are, and for read-only file systems.
+pub fn is_read_only(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt as _;
+
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let denied = unsafe { libc::access(path.as_ptr(), libc::W_OK) != 0 };
+    denied && std::io::Error::last_os_error().raw_os_error() != Some(libc::ENOENT)
+}
+
+/// Returns true if a process with the given ID is running.
+pub fn process_exists(pid: u32) -> bool {
+    // Signal 0 only checks whether the process could be signaled.
+    // EPERM means that it exists, but belongs to someone else.
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || errno() == libc::EPERM
+}
+
 /// Reserves a virtual memory region of the given size.
 /// To commit the memory, use `virtual_commit`.
 /// To release the memory, use `virtual_release`.
@@ -433,6 +571,111 @@ pub unsafe fn virtual_commit(base: NonNull<u8>, size: usize) -> apperr::Result<(
     }
 }
 
+/// The address ranges of the mappings made by `file_map`, as `start..end`.
+/// A start of 0 marks a free slot. See `handle_sigbus`.
+static FILE_MAPPINGS: [(AtomicUsize, AtomicUsize); 16] =
+    [const { (AtomicUsize::new(0), AtomicUsize::new(0)) }; 16];
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Reading a page of a mapping beyond the end of its file raises SIGBUS, which happens if
+/// another process truncates the file. The missing pages of our mappings are replaced with
+/// zeros then, instead of crashing. Other faults crash as usual.
+extern "C" fn handle_sigbus(_: c_int, info: *mut libc::siginfo_t, _: *mut c_void) {
+    unsafe {
+        let addr = (*info).si_addr() as usize;
+        let ours = FILE_MAPPINGS.iter().any(|(start, end)| {
+            let start = start.load(Ordering::Acquire);
+            start != 0 && (start..end.load(Ordering::Acquire)).contains(&addr)
+        });
+        if ours {
+            let page_size = PAGE_SIZE.load(Ordering::Relaxed);
+            let page = addr & !(page_size - 1);
+            let ptr = libc::mmap(
+                page as *mut c_void,
+                page_size,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED,
+                -1,
+                0,
+            );
+            if !ptr::eq(ptr, libc::MAP_FAILED) {
+                return;
+            }
+        }
+        // Faulting again without a handler crashes.
+        libc::signal(libc::SIGBUS, libc::SIG_DFL);
+    }
+}
+
+/// Maps the first `len` bytes of `file` into memory, read-only.
+/// Changes that others make to the file may or may not show up in the mapping.
+/// If the file gets truncated, the part that's gone reads as zeros.
+///
+/// # Safety
+///
+/// This function is unsafe because it uses raw pointers.
+/// Don't forget to release the mapping with `file_unmap` when you're done with it.
+pub unsafe fn file_map(file: &File, len: usize) -> apperr::Result<NonNull<u8>> {
+    static INSTALL_HANDLER: Once = Once::new();
+
+    unsafe {
+        INSTALL_HANDLER.call_once(|| {
+            PAGE_SIZE.store(libc::sysconf(libc::_SC_PAGESIZE) as usize, Ordering::Relaxed);
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handle_sigbus as *const () as usize;
+            action.sa_flags = libc::SA_SIGINFO;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGBUS, &action, null_mut());
+        });
+
+        // Without a free slot, a truncation would crash us.
+        // A slot is claimed by its end, which makes it used before its start is known.
+        let claim = |end: &AtomicUsize| {
+            end.compare_exchange(0, 1, Ordering::AcqRel, Ordering::Relaxed).is_ok()
+        };
+        let Some((start, end)) = FILE_MAPPINGS.iter().find(|(_, end)| claim(end)) else {
+            return Err(errno_to_apperr(libc::ENOMEM));
+        };
+
+        let ptr = libc::mmap(
+            null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr.is_null() || ptr::eq(ptr, libc::MAP_FAILED) {
+            let err = errno_to_apperr(errno());
+            end.store(0, Ordering::Release);
+            Err(err)
+        } else {
+            end.store(ptr as usize + len, Ordering::Release);
+            start.store(ptr as usize, Ordering::Release);
+            Ok(NonNull::new_unchecked(ptr as *mut u8))
+        }
+    }
+}
+
+/// Releases a mapping of the given size.
+///
+/// # Safety
+///
+/// This function is unsafe because it uses raw pointers.
+/// Make sure to only pass pointers acquired from `file_map`.
+pub unsafe fn file_unmap(base: NonNull<u8>, len: usize) {
+    unsafe {
+        libc::munmap(base.cast().as_ptr(), len);
+    }
+    let base = base.as_ptr() as usize;
+    if let Some((start, end)) =
+        FILE_MAPPINGS.iter().find(|(start, _)| start.load(Ordering::Acquire) == base)
+    {
+        start.store(0, Ordering::Release);
+        end.store(0, Ordering::Release);
+    }
+}
+
 unsafe fn load_library(name: &CStr) -> apperr::Result<NonNull<c_void>> {
     unsafe {
         NonNull::new(libc::dlopen(name.as_ptr(), libc::RTLD_LAZY))
@@ -603,6 +846,10 @@ pub fn apperr_is_not_found(err: apperr::Error) -> bool {
     err == errno_to_apperr(libc::ENOENT)
 }
 
+pub fn apperr_is_permission_denied(err: apperr::Error) -> bool {
+    err == errno_to_apperr(libc::EACCES) || err == errno_to_apperr(libc::EPERM)
+}
+
 const fn errno_to_apperr(no: c_int) -> apperr::Error {
     apperr::Error::new_sys(if no < 0 { 0 } else { no as u32 })
 }
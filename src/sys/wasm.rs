@@ -0,0 +1,94 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! The little a browser offers: memory. There are no files to map and no ICU to load,
+//! so those fail with [`ERROR_UNSUPPORTED`], which makes the callers fall back.
+//!
+//! WebAssembly can't reserve memory without committing it, so [`virtual_reserve()`]
+//! allocates it all at once. Browsers zero-fill linear memory lazily, which keeps that
+//! from costing more than the address space.
+
+use std::alloc::{self, Layout};
+use std::ffi::{CStr, c_void};
+use std::fs::File;
+use std::ptr::NonNull;
+
+use crate::apperr;
+
+/// What the functions that need an operating system fail with.
+pub const ERROR_UNSUPPORTED: apperr::Error = apperr::Error::new_sys(1);
+pub const ERROR_OUT_OF_MEMORY: apperr::Error = apperr::Error::new_sys(2);
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, PAGE_SIZE).unwrap()
+}
+
+/// Reserves a memory region of the given size. It's usable right away,
+/// but `virtual_commit` must still be called, as on the other platforms.
+///
+/// # Safety
+///
+/// This function is unsafe because it uses raw pointers.
+/// Don't forget to release the memory when you're done with it or you'll leak it.
+pub unsafe fn virtual_reserve(size: usize) -> apperr::Result<NonNull<u8>> {
+    let ptr = unsafe { alloc::alloc(layout(size)) };
+    NonNull::new(ptr).ok_or(ERROR_OUT_OF_MEMORY)
+}
+
+/// Releases a memory region of the given size.
+///
+/// # Safety
+///
+/// This function is unsafe because it uses raw pointers.
+/// Make sure to only pass pointers acquired from `virtual_reserve`.
+pub unsafe fn virtual_release(base: NonNull<u8>, size: usize) {
+    unsafe { alloc::dealloc(base.as_ptr(), layout(size)) };
+}
+
+/// Does nothing, as `virtual_reserve` committed the memory already.
+///
+/// # Safety
+///
+/// Make sure to only pass pointers acquired from `virtual_reserve`
+/// and to pass a size less than or equal to the size passed to `virtual_reserve`.
+pub unsafe fn virtual_commit(_base: NonNull<u8>, _size: usize) -> apperr::Result<()> {
+    Ok(())
+}
+
+/// Fails, so that files are read into memory instead.
+///
+/// # Safety
+///
+/// Safe, but `unsafe` like on the other platforms.
+pub unsafe fn file_map(_file: &File, _len: usize) -> apperr::Result<NonNull<u8>> {
+    Err(ERROR_UNSUPPORTED)
+}
+
+/// # Safety
+///
+/// Never called, as `file_map` never succeeds.
+pub unsafe fn file_unmap(_base: NonNull<u8>, _len: usize) {}
+
+/// # Safety
+///
+/// Never called, as there are no libraries to load.
+pub unsafe fn get_proc_address<T>(_handle: NonNull<c_void>, _name: &CStr) -> apperr::Result<T> {
+    Err(ERROR_UNSUPPORTED)
+}
+
+pub fn load_libicuuc() -> apperr::Result<NonNull<c_void>> {
+    Err(ERROR_UNSUPPORTED)
+}
+
+pub fn load_libicui18n() -> apperr::Result<NonNull<c_void>> {
+    Err(ERROR_UNSUPPORTED)
+}
+
+pub(crate) fn io_error_to_apperr(err: std::io::Error) -> apperr::Error {
+    match err.kind() {
+        std::io::ErrorKind::OutOfMemory => ERROR_OUT_OF_MEMORY,
+        _ => ERROR_UNSUPPORTED,
+    }
+}
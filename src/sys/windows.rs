@@ -195,6 +195,27 @@ pub fn inject_window_size_into_stdin() {
     }
 }
 
+/// Runs `f` with the console in the modes it had before [`switch_modes()`],
+/// e.g. so that a child process can prompt for a password.
+pub fn with_initial_modes<T>(f: impl FnOnce() -> T) -> T {
+    unsafe {
+        if STATE.stdin_mode_old == INVALID_CONSOLE_MODE {
+            return f();
+        }
+        let (mut stdin_mode, mut stdout_mode): (u32, u32) = (0, 0);
+        Console::GetConsoleMode(STATE.stdin, &mut stdin_mode);
+        Console::GetConsoleMode(STATE.stdout, &mut stdout_mode);
+        Console::SetConsoleMode(STATE.stdin, STATE.stdin_mode_old);
+        Console::SetConsoleMode(STATE.stdout, STATE.stdout_mode_old);
+
+        let result = f();
+
+        Console::SetConsoleMode(STATE.stdin, stdin_mode);
+        Console::SetConsoleMode(STATE.stdout, stdout_mode);
+        result
+    }
+}
+
 fn get_console_size() -> Option<Size> {
     unsafe {
         let mut info: Console::CONSOLE_SCREEN_BUFFER_INFOEX = mem::zeroed();
@@ -459,6 +480,51 @@ fn file_id_from_handle(file: &File) -> apperr::Result<FileId> {
     }
 }
 
+/// Copies the read-only attribute of the file at `src` onto `dst`.
+/// Used when saving via a temporary file, which inherits its ACLs from the directory.
+///
+/// Always returns true, as there's no owner to keep.
+pub fn copy_file_attributes(src: &Path, dst: &File) -> bool {
+    if let Ok(metadata) = fs::metadata(src) {
+        _ = dst.set_permissions(metadata.permissions());
+    }
+    true
+}
+
+/// Returns true if the file at `path` exists, but has the read-only attribute.
+pub fn is_read_only(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.permissions().readonly())
+}
+
+/// Returns true if the file at `path` has more than one hard link.
+/// Replacing it with a new file would break them.
+pub fn has_hard_links(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    unsafe {
+        let mut info = MaybeUninit::<FileSystem::BY_HANDLE_FILE_INFORMATION>::uninit();
+        FileSystem::GetFileInformationByHandle(file.as_raw_handle(), info.as_mut_ptr()) != 0
+            && info.assume_init().nNumberOfLinks > 1
+    }
+}
+
+/// Returns true if a process with the given ID is running.
+pub fn process_exists(pid: u32) -> bool {
+    unsafe {
+        let handle = Threading::OpenProcess(Threading::PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            // Access is denied to processes of other users, but they do exist.
+            return Foundation::GetLastError() == Foundation::ERROR_ACCESS_DENIED;
+        }
+        let mut code = 0;
+        let running = Threading::GetExitCodeProcess(handle, &mut code) != 0
+            && code == Foundation::STILL_ACTIVE as u32;
+        Foundation::CloseHandle(handle);
+        running
+    }
+}
+
 /// Canonicalizes the given path.
 ///
 /// This differs from [`fs::canonicalize`] in that it strips the `\\?\` UNC
@@ -540,6 +606,48 @@ pub unsafe fn virtual_commit(base: NonNull<u8>, size: usize) -> apperr::Result<(
     }
 }
 
+/// Maps the first `len` bytes of `file` into memory, read-only.
+/// Changes that others make to the file may or may not show up in the mapping.
+/// Unlike on Unix, the file can't be truncated while it's mapped.
+///
+/// # Safety
+///
+/// This function is unsafe because it uses raw pointers.
+/// Don't forget to release the mapping with [`file_unmap`] when you're done with it.
+pub unsafe fn file_map(file: &File, len: usize) -> apperr::Result<NonNull<u8>> {
+    unsafe {
+        let mapping = Memory::CreateFileMappingW(
+            file.as_raw_handle() as _,
+            null(),
+            Memory::PAGE_READONLY,
+            0,
+            0,
+            null(),
+        );
+        if mapping.is_null() {
+            return Err(get_last_error());
+        }
+        // The view keeps the mapping alive on its own.
+        let view = Memory::MapViewOfFile(mapping, Memory::FILE_MAP_READ, 0, 0, len);
+        Foundation::CloseHandle(mapping);
+        check_ptr_return(view.Value as *mut u8)
+    }
+}
+
+/// Releases a mapping of the given size.
+///
+/// # Safety
+///
+/// This function is unsafe because it uses raw pointers.
+/// Make sure to only pass pointers acquired from [`file_map`].
+pub unsafe fn file_unmap(base: NonNull<u8>, _len: usize) {
+    unsafe {
+        Memory::UnmapViewOfFile(Memory::MEMORY_MAPPED_VIEW_ADDRESS {
+            Value: base.as_ptr() as *mut _,
+        });
+    }
+}
+
 unsafe fn get_module(name: *const u16) -> apperr::Result<NonNull<c_void>> {
     unsafe { check_ptr_return(LibraryLoader::GetModuleHandleW(name)) }
 }
@@ -699,6 +807,10 @@ pub fn apperr_is_not_found(err: apperr::Error) -> bool {
     err == gle_to_apperr(Foundation::ERROR_FILE_NOT_FOUND)
 }
 
+pub fn apperr_is_permission_denied(err: apperr::Error) -> bool {
+    err == gle_to_apperr(Foundation::ERROR_ACCESS_DENIED)
+}
+
 fn check_bool_return(ret: Foundation::BOOL) -> apperr::Result<()> {
     if ret == 0 { Err(get_last_error()) } else { Ok(()) }
 }
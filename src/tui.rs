@@ -93,7 +93,6 @@
 //! use edit::input::Input;
 //! use edit::tui::*;
 //! use edit::{arena, arena_format};
-//! use crate::syntax::SyntaxHighlighter;
 //!
 //! struct State {
 //!     counter: i32,
@@ -158,8 +157,11 @@ use crate::framebuffer::{Attributes, Framebuffer, INDEXED_COLORS_COUNT, IndexedC
 use crate::hash::*;
 use crate::helpers::*;
 use crate::input::{InputKeyMod, kbmod, vk};
-use crate::syntax::{SyntaxHighlighter, FileType};
-use crate::{apperr, arena_format, input, unicode};
+use crate::oklab::oklab_blend;
+use crate::syntax::{
+    FileType, OverlayKind, OverlaySpan, SyntaxHighlighter, color_literals, composite_overlays,
+};
+use crate::{ansi, apperr, arena_format, input, stack_trace, unicode};
 
 const ROOT_ID: u64 = 0x14057B7EF767814F; // Knuth's MMIX constant
 const SHIFT_TAB: InputKey = vk::TAB.with_modifiers(kbmod::SHIFT);
@@ -195,6 +197,14 @@ pub struct ModifierTranslations {
     pub shift: &'static str,
 }
 
+/// Which scrollbars multi-line textareas show. See [`Tui::set_textarea_scrollbars()`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Scrollbars {
+    pub vertical: bool,
+    /// Only shown if word wrap is disabled.
+    pub horizontal: bool,
+}
+
 /// Controls to which node the floater is anchored.
 #[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub enum Anchor {
@@ -323,6 +333,13 @@ pub struct Tui {
     floater_default_fg: u32,
     modal_default_bg: u32,
     modal_default_fg: u32,
+    /// Modals fill the entire screen if the terminal is narrower or shorter than this.
+    modal_fullscreen_below: Size,
+    textarea_scrollbars: Scrollbars,
+    /// How much inactive textareas are dimmed, in percent.
+    inactive_dim: u32,
+    /// See [`Tui::set_low_bandwidth()`].
+    low_bandwidth: bool,
 
     /// Last known terminal size.
     ///
@@ -403,6 +420,10 @@ impl Tui {
             floater_default_fg: 0,
             modal_default_bg: 0,
             modal_default_fg: 0,
+            modal_fullscreen_below: Size { width: 0, height: 0 },
+            textarea_scrollbars: Scrollbars { vertical: true, horizontal: false },
+            inactive_dim: 25,
+            low_bandwidth: false,
 
             size: Size { width: 0, height: 0 },
             mouse_position: Point::MIN,
@@ -438,6 +459,12 @@ impl Tui {
         self.framebuffer.set_indexed_colors(colors);
     }
 
+    /// Restricts the text attributes (bold, italic, ...) emitted to the terminal.
+    /// See [`Framebuffer::set_supported_attributes()`].
+    pub fn setup_supported_attributes(&mut self, attr: Attributes) {
+        self.framebuffer.set_supported_attributes(attr);
+    }
+
     /// Set up translations for Ctrl/Alt/Shift modifiers.
     pub fn setup_modifier_translations(&mut self, translations: ModifierTranslations) {
         self.modifier_translations = translations;
@@ -463,6 +490,29 @@ impl Tui {
         self.modal_default_fg = color;
     }
 
+    /// Makes modals fill the entire screen if the terminal is narrower
+    /// or shorter than `size`, instead of overflowing it.
+    pub fn set_modal_fullscreen_below(&mut self, size: Size) {
+        self.modal_fullscreen_below = size;
+    }
+
+    /// Sets which scrollbars multi-line textareas show. By default only the vertical one.
+    pub fn set_textarea_scrollbars(&mut self, scrollbars: Scrollbars) {
+        self.textarea_scrollbars = scrollbars;
+    }
+
+    /// Set by how much (0-100%) textareas marked via [`Context::attr_inactive()`] are dimmed.
+    pub fn set_inactive_dim(&mut self, percent: u32) {
+        self.inactive_dim = percent.min(100);
+    }
+
+    /// Cuts down on what's sent to the terminal, for slow connections: The cursor stops
+    /// blinking and dragging a selection past the edge scrolls in fewer, larger steps.
+    pub fn set_low_bandwidth(&mut self, low_bandwidth: bool) {
+        self.low_bandwidth = low_bandwidth;
+        self.framebuffer.set_steady_cursor(low_bandwidth);
+    }
+
     /// If the TUI is currently running animations, etc.,
     /// this will return a timeout smaller than [`time::Duration::MAX`].
     pub fn read_timeout(&mut self) -> time::Duration {
@@ -507,6 +557,14 @@ impl Tui {
         self.clipboard_generation
     }
 
+    /// Sets the clipboard contents, e.g. to what was copied outside of the application.
+    pub fn set_clipboard(&mut self, data: Vec<u8>) {
+        if !data.is_empty() {
+            self.clipboard = data;
+            self.clipboard_generation = self.clipboard_generation.wrapping_add(1);
+        }
+    }
+
     /// Starts a new frame and returns a [`Context`] for it.
     pub fn create_context<'a, 'input>(
         &'a mut self,
@@ -956,38 +1014,85 @@ impl Tui {
                     bottom: inner_clipped.bottom,
                 };
 
-                if !tc.single_line {
-                    // Account for the scrollbar.
-                    destination.right -= 1;
-                }
-
-                if let Some(res) =
-                    tb.render(tc.scroll_offset, destination, tc.has_focus, &mut self.framebuffer)
-                {
+                // Account for the scrollbars.
+                destination.right -= tc.scrollbar_vertical as CoordType;
+                destination.bottom -= tc.scrollbar_horizontal as CoordType;
+
+                let mut overlays = Vec::new();
+                let mut wrapped_rows = Vec::new();
+                if let Some(res) = tb.render(
+                    tc.scroll_offset,
+                    destination,
+                    tc.has_focus,
+                    tc.scrollbar_horizontal,
+                    &mut self.framebuffer,
+                ) {
                     tc.scroll_offset_x_max = res.visual_pos_x_max;
+                    tc.line_width_max = res.line_width_max;
+                    overlays = res.overlays;
+                    wrapped_rows = res.wrapped_rows;
                 }
 
-                // Apply basic syntax highlighting by coloring keywords
                 if !tc.single_line {
-                    // Detect file type - you might want to pass this as a parameter
-                    // or store it in the TextareaContent for better accuracy
-                    // let file_type = FileType::Python; // Default, or detect based on context
-                    self.apply_syntax_highlighting(destination, tc.file_type);
-                }                
+                    // The highlighter is shared by all textareas, but each buffer may have its own theme.
+                    let theme = tb
+                        .theme()
+                        .filter(|t| self.syntax_highlighter.has_theme(t))
+                        .unwrap_or(SyntaxHighlighter::DEFAULT_THEME);
+                    if self.syntax_highlighter.theme() != theme {
+                        self.syntax_highlighter.set_theme(theme);
+                    }
 
-                if !tc.single_line {
+                    let text_left = destination.left + tb.margin_width();
+                    self.apply_syntax_highlighting(
+                        destination,
+                        text_left,
+                        tc.file_type,
+                        &overlays,
+                        &wrapped_rows,
+                        tc.has_focus,
+                        node.attributes.inactive,
+                    );
+                }
+
+                if node.attributes.inactive && self.inactive_dim != 0 {
+                    let dim = self.framebuffer.indexed_alpha(
+                        IndexedColor::Background,
+                        self.inactive_dim,
+                        100,
+                    );
+                    self.framebuffer.blend_bg(inner_clipped, dim);
+                    self.framebuffer.blend_fg(inner_clipped, dim);
+                }
+
+                if tc.scrollbar_vertical {
                     // Render the scrollbar.
                     let track = Rect {
                         left: inner_clipped.right - 1,
                         top: inner_clipped.top,
                         right: inner_clipped.right,
-                        bottom: inner_clipped.bottom,
+                        bottom: destination.bottom,
                     };
                     tc.thumb_height = self.framebuffer.draw_scrollbar(
                         inner_clipped,
                         track,
                         tc.scroll_offset.y,
-                        tb.visual_line_count() + inner.height() - 1,
+                        tb.visual_line_count() + track.height() - 1,
+                    );
+                }
+                if tc.scrollbar_horizontal {
+                    let track = Rect {
+                        left: destination.left + tb.margin_width(),
+                        top: destination.bottom,
+                        right: destination.right,
+                        bottom: destination.bottom + 1,
+                    };
+                    let content_width = tc.content_width(track.width());
+                    tc.thumb_width = self.framebuffer.draw_scrollbar_horizontal(
+                        inner_clipped,
+                        track,
+                        tc.scroll_offset.x,
+                        content_width,
                     );
                 }
             }
@@ -1181,7 +1286,7 @@ impl Tui {
                 match &node.content {
                     NodeContent::Text(content) => {
                         result.push_repeat(' ', depth * 2);
-                        _ = write!(result, "  text:         \"{}\"\r\n", &content.text);
+                        _ = write!(result, "  text:         \"{}\"\r\n", content.text);
                     }
                     NodeContent::Textarea(content) => {
                         let tb = content.buffer.borrow();
@@ -1298,85 +1403,320 @@ impl Tui {
         true
     }
 
-    /// Applies basic syntax highlighting to the framebuffer by coloring common keywords and patterns.
-    fn apply_syntax_highlighting(&mut self, destination: Rect, file_type: FileType) {
-        // Collect all the text lines first to avoid borrowing conflicts
-        let mut lines_with_positions = Vec::new();
-        for y in destination.top..destination.bottom {
-            if let Some(line) = self.framebuffer.get_line_text(y) {
-                lines_with_positions.push((y, line.to_string()));
-            }
-        }
-        
-        // Now apply highlighting without borrowing conflicts
-        for (y, line) in lines_with_positions {
-            // Find the margin width by looking for the "│" character
-            let text_start_chars = if let Some(_pos) = line.find('│') {
-                // Find the character position after "│ " (skip the border and space)
-                line.char_indices()
-                    .position(|(_, ch)| ch == '│')
-                    .map(|pos| pos + 2) // Skip '│' and ' '
-                    .unwrap_or(0)
-            } else {
-                0
-            };
-            
-            // Convert character position to byte position for slicing
-            let text_start_bytes = line.char_indices()
-                .nth(text_start_chars)
-                .map(|(byte_pos, _)| byte_pos)
-                .unwrap_or(line.len());
-            
-            // Work with the text portion only
-            if text_start_bytes >= line.len() {
-                continue;
-            }
-            let text_portion = &line[text_start_bytes..];
-            
-            // Use syntect for highlighting
-            let highlighted = self.syntax_highlighter.highlight_line(
-                text_portion, 
-                file_type, 
-                (y - destination.top) as usize
-            );
-            
-            let mut char_offset = text_start_chars;
-            for (style, text_segment) in highlighted {
-                let segment_len = text_segment.chars().count();
-                
-                if segment_len > 0 {
-                    let highlight_rect = Rect {
-                        left: destination.left + char_offset as CoordType,
-                        top: y,
-                        right: destination.left + (char_offset + segment_len) as CoordType,
-                        bottom: y + 1,
+    /// Applies syntax highlighting to the text portion of a rendered textarea and
+    /// composites the given `overlays` (e.g. search hits) on top of it.
+    /// See [`crate::buffer::RenderResult`] for the `wrapped_rows`.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_syntax_highlighting(
+        &mut self,
+        destination: Rect,
+        text_left: CoordType,
+        file_type: FileType,
+        overlays: &[OverlaySpan],
+        wrapped_rows: &[(CoordType, CoordType)],
+        focused: bool,
+        inactive: bool,
+    ) {
+        // The buffer already drew a generic selection. If the theme has its own selection
+        // color, we repaint the background with it. Either way, the foreground of the
+        // selected text is restored to the syntax colors below.
+        let selection_bg = self.syntax_highlighter.selection_color().map(|color| {
+            let background = self.framebuffer.indexed(IndexedColor::Background);
+            let mut bg = oklab_blend(background, Self::convert_syntect_color_to_u32(color));
+            if !focused {
+                bg =
+                    oklab_blend(bg, self.framebuffer.indexed_alpha(IndexedColor::Background, 1, 2));
+            }
+            bg
+        });
+
+        let wrap_width = |row| {
+            let i = wrapped_rows.binary_search_by_key(&row, |&(row, _)| row).ok()?;
+            Some(wrapped_rows[i].1)
+        };
+        let mut top = destination.top;
+
+        while top < destination.bottom {
+            // The rows of a line that word wrap broke up are highlighted as one,
+            // so that e.g. a string that continues on the next row stays one.
+            let mut bottom = top + 1;
+            while bottom < destination.bottom && wrap_width(bottom - 1 - destination.top).is_some()
+            {
+                bottom += 1;
+            }
+
+            let texts: Vec<String> = (top..bottom)
+                .map(|y| {
+                    let Some(line) = self.framebuffer.get_line_text(y) else {
+                        return String::new();
                     };
-                    
-                    // Convert syntect::highlighting::Style to framebuffer colors
-                    let fg_color = Self::convert_syntect_color_to_u32(style.foreground);
-                    self.framebuffer.blend_fg(highlight_rect, fg_color);
-                    
-                    // Apply text attributes if needed
-                    if style.font_style.contains(syntect::highlighting::FontStyle::UNDERLINE) {
-                        self.framebuffer.replace_attr(highlight_rect, Attributes::Underlined, Attributes::Underlined);
+                    // The framebuffer contents are laid out in columns, so we can find
+                    // the text portion (right of the margin) via its visual position.
+                    let right = match wrap_width(y - destination.top) {
+                        Some(width) => text_left + width,
+                        None => destination.right,
+                    };
+                    let bytes = line.as_bytes();
+                    let mut cfg = unicode::MeasurementConfig::new(&bytes);
+                    let beg = cfg.goto_visual(Point { x: text_left, y: 0 }).offset;
+                    let end = cfg.goto_visual(Point { x: right, y: 0 }).offset;
+                    if beg >= end {
+                        return String::new();
                     }
-                    if style.font_style.contains(syntect::highlighting::FontStyle::ITALIC) {
-                        self.framebuffer.replace_attr(highlight_rect, Attributes::Italic, Attributes::Italic);
+
+                    // Whitespace glyphs get highlighted as the spaces they stand for.
+                    let text = &line[beg..end];
+                    let bytes = text.as_bytes();
+                    let mut cfg = unicode::MeasurementConfig::new(&bytes);
+                    let mut restored = String::with_capacity(text.len());
+                    let mut copied = 0;
+                    let row = y - destination.top;
+                    for o in overlays.iter().filter(|o| o.line == row) {
+                        if o.kind != OverlayKind::Whitespace {
+                            continue;
+                        }
+                        for x in o.columns.clone() {
+                            let off = cfg.goto_visual(Point { x, y: 0 }).offset;
+                            if off >= copied
+                                && let Some(c) = text[off..].chars().next()
+                            {
+                                restored.push_str(&text[copied..off]);
+                                restored.push(' ');
+                                copied = off + c.len_utf8();
+                            }
+                        }
                     }
-                    if style.font_style.contains(syntect::highlighting::FontStyle::UNDERLINE) {
-                        self.framebuffer.replace_attr(highlight_rect, Attributes::Underlined, Attributes::Underlined);
+                    restored.push_str(&text[copied..]);
+                    restored
+                })
+                .collect();
+            let joined = texts.concat();
+            let mut off = 0;
+            let highlighted: Vec<_> = self
+                .syntax_highlighter
+                .highlight_line(&joined, file_type, (top - destination.top) as usize)
+                .into_iter()
+                .map(|(style, text)| {
+                    off += text.len();
+                    (style, off - text.len()..off)
+                })
+                .collect();
+
+            let mut text_beg = 0;
+            for (y, text) in (top..bottom).zip(&texts) {
+                let text_range = text_beg..text_beg + text.len();
+                text_beg = text_range.end;
+                if text.is_empty() {
+                    continue;
+                }
+                let highlighted: Vec<_> = highlighted
+                    .iter()
+                    .filter_map(|(style, range)| {
+                        let beg = range.start.max(text_range.start);
+                        let end = range.end.min(text_range.end);
+                        (beg < end).then(|| (*style, &joined[beg..end]))
+                    })
+                    .collect();
+
+                self.apply_syntax_highlighting_row(
+                    y - destination.top,
+                    y,
+                    text_left,
+                    text,
+                    highlighted,
+                    file_type,
+                    overlays,
+                    selection_bg,
+                    inactive,
+                );
+            }
+
+            top = bottom;
+        }
+    }
+
+    /// Paints one row of text in its `highlighted` colors, composited with its `overlays`.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_syntax_highlighting_row(
+        &mut self,
+        row: CoordType,
+        y: CoordType,
+        text_left: CoordType,
+        text: &str,
+        highlighted: Vec<(syntect::highlighting::Style, &str)>,
+        file_type: FileType,
+        overlays: &[OverlaySpan],
+        selection_bg: Option<u32>,
+        inactive: bool,
+    ) {
+        // The overlays are sorted by line, because they're generated top to bottom.
+        let overlays_beg = overlays.partition_point(|o| o.line < row);
+        let overlays_end = overlays.partition_point(|o| o.line <= row);
+        let line_overlays = &overlays[overlays_beg..overlays_end];
+
+        // The selection may extend past the end of the text, which is why
+        // its background gets painted separately from the text segments.
+        if let Some(bg) = selection_bg {
+            for o in line_overlays.iter().filter(|o| o.kind == OverlayKind::Selection) {
+                let rect = Rect {
+                    left: text_left + o.columns.start,
+                    top: y,
+                    right: text_left + o.columns.end,
+                    bottom: y + 1,
+                };
+                self.framebuffer.blend_bg(rect, bg);
+            }
+        }
+
+        for segment in composite_overlays(highlighted, line_overlays) {
+            let rect = Rect {
+                left: text_left + segment.columns.start,
+                top: y,
+                right: text_left + segment.columns.end,
+                bottom: y + 1,
+            };
+            let style = segment.style;
+
+            match segment.overlay {
+                Some(OverlayKind::JumpLabel) => {
+                    let bg = self.framebuffer.indexed(IndexedColor::BrightMagenta);
+                    self.framebuffer.blend_bg(rect, bg);
+                    self.framebuffer.blend_fg(rect, self.framebuffer.contrasted(bg));
+                    self.framebuffer.replace_attr(rect, Attributes::Bold, Attributes::Bold);
+                    continue;
+                }
+                Some(OverlayKind::Caret) => {
+                    let bg = self.framebuffer.indexed(IndexedColor::Foreground);
+                    self.framebuffer.blend_bg(rect, bg);
+                    self.framebuffer.blend_fg(rect, self.framebuffer.contrasted(bg));
+                    continue;
+                }
+                Some(OverlayKind::CurrentSearchMatch) if !inactive => {
+                    let bg = self.framebuffer.indexed(IndexedColor::BrightYellow);
+                    self.framebuffer.blend_bg(rect, bg);
+                    self.framebuffer.blend_fg(rect, self.framebuffer.contrasted(bg));
+                }
+                Some(OverlayKind::Styled(ansi)) => {
+                    let syntax_fg = Self::convert_syntect_color_to_u32(style.foreground);
+                    let color = |c| Self::ansi_color(&self.framebuffer, c);
+                    let mut fg = ansi.fg.map_or(syntax_fg, color);
+                    let mut bg = ansi.bg.map(color);
+                    if ansi.reverse {
+                        let background = self.framebuffer.indexed(IndexedColor::Background);
+                        (fg, bg) = (bg.unwrap_or(background), Some(fg));
+                    }
+                    if let Some(bg) = bg {
+                        self.framebuffer.blend_bg(rect, bg);
+                    }
+                    self.framebuffer.blend_fg(rect, fg);
+                    if ansi.faint {
+                        self.framebuffer.blend_fg(
+                            rect,
+                            self.framebuffer.indexed_alpha(IndexedColor::Background, 1, 2),
+                        );
+                    }
+
+                    let mut attr = Attributes::None;
+                    if ansi.bold {
+                        attr = attr | Attributes::Bold;
+                    }
+                    if ansi.italic {
+                        attr = attr | Attributes::Italic;
+                    }
+                    if ansi.underline {
+                        attr = attr | Attributes::Underlined;
+                    }
+                    self.framebuffer.replace_attr(rect, attr, attr);
+                }
+                overlay => {
+                    // Inactive panes get a muted variant that keeps the syntax colors legible.
+                    let tint = match overlay {
+                        Some(OverlayKind::CurrentSearchMatch) => {
+                            Some((IndexedColor::Yellow, 1, 2))
+                        }
+                        Some(OverlayKind::SearchMatch) if inactive => {
+                            Some((IndexedColor::Yellow, 1, 4))
+                        }
+                        Some(OverlayKind::SearchMatch) => {
+                            Some((IndexedColor::BrightYellow, 1, 3))
+                        }
+                        Some(OverlayKind::MatchingTag) => {
+                            Some((IndexedColor::BrightBlue, 1, 3))
+                        }
+                        _ => None,
+                    };
+                    if let Some((color, num, denom)) = tint {
+                        self.framebuffer
+                            .blend_bg(rect, self.framebuffer.indexed_alpha(color, num, denom));
+                    }
+                    let fg_color = Self::convert_syntect_color_to_u32(style.foreground);
+                    self.framebuffer.blend_fg(rect, fg_color);
+                    if let Some(OverlayKind::Dimmed | OverlayKind::Whitespace) = overlay {
+                        self.framebuffer.blend_fg(
+                            rect,
+                            self.framebuffer.indexed_alpha(IndexedColor::Background, 1, 2),
+                        );
                     }
                 }
-                
-                char_offset += segment_len;
             }
+
+            let attr = Self::convert_syntect_font_style(style.font_style);
+            if attr != Attributes::None {
+                self.framebuffer.replace_attr(rect, attr, attr);
+            }
+        }
+
+        // Color literals get their own color as the background, like a swatch.
+        if matches!(file_type, FileType::CSS | FileType::HTML) {
+            let bytes = text.as_bytes();
+            for (range, color) in color_literals(text) {
+                let mut cfg = unicode::MeasurementConfig::new(&bytes);
+                let beg = cfg.goto_offset(range.start).visual_pos.x;
+                let end = cfg.goto_offset(range.end).visual_pos.x;
+                let rect = Rect {
+                    left: text_left + beg,
+                    top: y,
+                    right: text_left + end,
+                    bottom: y + 1,
+                };
+                let opaque = syntect::highlighting::Color { a: 0xff, ..color };
+                let bg = Self::convert_syntect_color_to_u32(opaque);
+                self.framebuffer.blend_bg(rect, bg);
+                self.framebuffer.blend_fg(rect, self.framebuffer.contrasted(bg));
+            }
+        }
+    }
+
+    fn convert_syntect_font_style(font_style: syntect::highlighting::FontStyle) -> Attributes {
+        use syntect::highlighting::FontStyle;
+
+        let mut attr = Attributes::None;
+        if font_style.contains(FontStyle::BOLD) {
+            attr = attr | Attributes::Bold;
+        }
+        if font_style.contains(FontStyle::ITALIC) {
+            attr = attr | Attributes::Italic;
+        }
+        if font_style.contains(FontStyle::UNDERLINE) {
+            attr = attr | Attributes::Underlined;
         }
+        attr
     }
-    
+
     // Helper method to convert syntect colors to u32
     fn convert_syntect_color_to_u32(color: syntect::highlighting::Color) -> u32 {
-        // Convert RGBA to u32 (assuming RGBA format)
-        ((color.a as u32) << 24) | ((color.r as u32) << 16) | ((color.g as u32) << 8) | (color.b as u32)
+        // The framebuffer stores colors as 0xAABBGGRR.
+        ((color.a as u32) << 24)
+            | ((color.b as u32) << 16)
+            | ((color.g as u32) << 8)
+            | (color.r as u32)
+    }
+
+    fn ansi_color(fb: &Framebuffer, color: ansi::Color) -> u32 {
+        match color {
+            ansi::Color::Indexed(index) => fb.indexed_256(index),
+            ansi::Color::Rgb(rgb) => rgb,
+        }
     }
 }
 
@@ -1463,12 +1803,18 @@ impl<'a> Context<'a, '_> {
     /// Sets the clipboard contents.
     pub fn set_clipboard(&mut self, data: Vec<u8>) {
         if !data.is_empty() {
-            self.tui.clipboard = data;
-            self.tui.clipboard_generation = self.tui.clipboard_generation.wrapping_add(1);
+            self.tui.set_clipboard(data);
             self.needs_rerender();
         }
     }
 
+    /// Tell the UI framework that something else wrote to the terminal,
+    /// and that the next frame needs to be drawn from scratch.
+    pub fn invalidate_screen(&mut self) {
+        self.tui.framebuffer.invalidate();
+        self.needs_rerender();
+    }
+
     /// Tell the UI framework that your state changed and you need another layout pass.
     pub fn needs_rerender(&mut self) {
         // If this hits, the call stack is responsible is trying to deadlock you.
@@ -1659,6 +2005,13 @@ impl<'a> Context<'a, '_> {
         last_node.attributes.focus_well = true;
     }
 
+    /// Marks the current textarea as an inactive pane, i.e. one the user isn't working in.
+    /// It gets dimmed (see [`Tui::set_inactive_dim()`]) and its search matches are muted.
+    pub fn attr_inactive(&mut self) {
+        let mut last_node = self.tree.last_node.borrow_mut();
+        last_node.attributes.inactive = true;
+    }
+
     /// Explicitly sets the intrinsic size of the current node.
     /// The intrinsic size is the size the node ideally wants to be.
     pub fn attr_intrinsic_size(&mut self, size: Size) {
@@ -1754,12 +2107,71 @@ impl<'a> Context<'a, '_> {
         }
     }
 
+    /// Formats a shortcut for display, e.g. "Ctrl+Shift+S" or "Alt+←",
+    /// using the translations from [`Tui::setup_modifier_translations()`].
+    pub fn shortcut_text(&self, shortcut: InputKey) -> ArenaString<'a> {
+        let mut text = ArenaString::new_in(self.arena());
+        if shortcut.modifiers_contains(kbmod::CTRL) {
+            text.push_str(self.tui.modifier_translations.ctrl);
+            text.push('+');
+        }
+        if shortcut.modifiers_contains(kbmod::ALT) {
+            text.push_str(self.tui.modifier_translations.alt);
+            text.push('+');
+        }
+        if shortcut.modifiers_contains(kbmod::SHIFT) {
+            text.push_str(self.tui.modifier_translations.shift);
+            text.push('+');
+        }
+
+        let key = shortcut.key();
+        let name = match key {
+            vk::BACK => "Backspace",
+            vk::TAB => "Tab",
+            vk::RETURN => "Enter",
+            vk::ESCAPE => "Esc",
+            vk::SPACE => "Space",
+            vk::PRIOR => "PgUp",
+            vk::NEXT => "PgDn",
+            vk::END => "End",
+            vk::HOME => "Home",
+            vk::LEFT => "←",
+            vk::UP => "↑",
+            vk::RIGHT => "→",
+            vk::DOWN => "↓",
+            vk::INSERT => "Ins",
+            vk::DELETE => "Del",
+            vk::OEM_COMMA => ",",
+            vk::OEM_PERIOD => ".",
+            vk::OEM_2 => "/",
+            _ if (vk::F1.value()..=vk::F24.value()).contains(&key.value()) => {
+                _ = write!(text, "F{}", key.value() - vk::F1.value() + 1);
+                return text;
+            }
+            _ => {
+                text.push(char::from_u32(key.value()).unwrap_or('?'));
+                return text;
+            }
+        };
+        text.push_str(name);
+        text
+    }
+
     /// Returns current keyboard input, if any.
     /// Returns None if the input was already consumed.
     pub fn keyboard_input(&self) -> Option<InputKey> {
         if self.input_consumed { None } else { self.input_keyboard }
     }
 
+    /// Returns the current text input, if any. Pasted text is not included.
+    /// Returns None if the input was already consumed.
+    pub fn text_input(&self) -> Option<&str> {
+        match &self.input_text {
+            Some(input) if !self.input_consumed && !input.bracketed => Some(input.text),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn set_input_consumed(&mut self) {
         debug_assert!(!self.input_consumed);
@@ -1813,6 +2225,13 @@ impl<'a> Context<'a, '_> {
             offset_y: self.tui.size.height as f32 * 0.5,
         });
         self.attr_border();
+        if self.is_modal_fullscreen() {
+            // The border is drawn around the intrinsic size.
+            self.attr_intrinsic_size(Size {
+                width: (self.tui.size.width - 2).max(0),
+                height: (self.tui.size.height - 2).max(0),
+            });
+        }
         self.attr_background_rgba(self.tui.modal_default_bg);
         self.attr_foreground_rgba(self.tui.modal_default_fg);
         self.inherit_focus();
@@ -1828,6 +2247,13 @@ impl<'a> Context<'a, '_> {
         self.last_modal = Some(self.tree.last_node);
     }
 
+    /// Whether modals currently fill the entire screen.
+    /// See [`Tui::set_modal_fullscreen_below()`].
+    pub fn is_modal_fullscreen(&self) -> bool {
+        let below = self.tui.modal_fullscreen_below;
+        self.tui.size.width < below.width || self.tui.size.height < below.height
+    }
+
     /// Ends the current modal window block.
     /// Returns true if the user pressed Escape (a request to close).
     pub fn modal_end(&mut self) -> bool {
@@ -2140,7 +2566,12 @@ impl<'a> Context<'a, '_> {
         self.textarea_internal(classname, TextBufferPayload::Editline(text), None)
     }
 
-    pub fn textarea_with_file_type(&mut self, classname: &'static str, tb: RcTextBuffer, file_type: FileType) {
+    pub fn textarea_with_file_type(
+        &mut self,
+        classname: &'static str,
+        tb: RcTextBuffer,
+        file_type: FileType,
+    ) {
         self.textarea_internal(classname, TextBufferPayload::Textarea(tb), Some(file_type));
     }
 
@@ -2149,7 +2580,12 @@ impl<'a> Context<'a, '_> {
         self.textarea_internal(classname, TextBufferPayload::Textarea(tb), None);
     }
 
-    fn textarea_internal(&mut self, classname: &'static str, payload: TextBufferPayload, file_type: Option<FileType>) -> bool {
+    fn textarea_internal(
+        &mut self,
+        classname: &'static str,
+        payload: TextBufferPayload,
+        file_type: Option<FileType>,
+    ) -> bool {
         self.block_begin(classname);
         self.block_end();
 
@@ -2195,10 +2631,15 @@ impl<'a> Context<'a, '_> {
             buffer,
             scroll_offset: Default::default(),
             scroll_offset_y_drag_start: CoordType::MIN,
+            scroll_offset_x_drag_start: CoordType::MIN,
             scroll_offset_x_max: 0,
+            line_width_max: 0,
             thumb_height: 0,
+            thumb_width: 0,
             preferred_column: 0,
             single_line,
+            scrollbar_vertical: !single_line && self.tui.textarea_scrollbars.vertical,
+            scrollbar_horizontal: false,
             has_focus: self.tui.is_node_focused(node.id),
             file_type: file_type.unwrap_or(FileType::Plain),
         });
@@ -2211,18 +2652,24 @@ impl<'a> Context<'a, '_> {
         if let TextBufferPayload::Editline(text) = &payload {
             content.buffer.borrow_mut().copy_from_str(*text);
         }
+        content.scrollbar_horizontal = !single_line
+            && self.tui.textarea_scrollbars.horizontal
+            && !content.buffer.borrow().is_word_wrap_enabled();
 
         if let Some(node_prev) = self.tui.prev_node_map.get(node.id) {
             let node_prev = node_prev.borrow();
             if let NodeContent::Textarea(content_prev) = &node_prev.content {
                 content.scroll_offset = content_prev.scroll_offset;
                 content.scroll_offset_y_drag_start = content_prev.scroll_offset_y_drag_start;
+                content.scroll_offset_x_drag_start = content_prev.scroll_offset_x_drag_start;
                 content.scroll_offset_x_max = content_prev.scroll_offset_x_max;
+                content.line_width_max = content_prev.line_width_max;
                 content.thumb_height = content_prev.thumb_height;
+                content.thumb_width = content_prev.thumb_width;
                 content.preferred_column = content_prev.preferred_column;
 
                 let mut text_width = node_prev.inner.width();
-                if !single_line {
+                if content.scrollbar_vertical {
                     // Subtract -1 to account for the scrollbar.
                     text_width -= 1;
                 }
@@ -2300,15 +2747,27 @@ impl<'a> Context<'a, '_> {
                 let text_rect = Rect {
                     left: inner.left + tb.margin_width(),
                     top: inner.top,
-                    right: inner.right - !single_line as CoordType,
-                    bottom: inner.bottom,
+                    right: inner.right - tc.scrollbar_vertical as CoordType,
+                    bottom: inner.bottom - tc.scrollbar_horizontal as CoordType,
                 };
                 let track_rect = Rect {
                     left: text_rect.right,
                     top: inner.top,
                     right: inner.right,
+                    bottom: text_rect.bottom,
+                };
+                let track_rect_horizontal = Rect {
+                    left: text_rect.left,
+                    top: text_rect.bottom,
+                    right: text_rect.right,
                     bottom: inner.bottom,
                 };
+                let margin_rect = Rect {
+                    left: inner.left,
+                    top: inner.top,
+                    right: text_rect.left,
+                    bottom: text_rect.bottom,
+                };
                 let pos = Point {
                     x: mouse.x - inner.left - tb.margin_width() + tc.scroll_offset.x,
                     y: mouse.y - inner.top + tc.scroll_offset.y,
@@ -2316,7 +2775,15 @@ impl<'a> Context<'a, '_> {
 
                 if text_rect.contains(self.tui.mouse_down_position) {
                     if self.tui.mouse_is_drag {
-                        tb.selection_update_visual(pos);
+                        // The modifiers are only known for the frames with mouse input.
+                        if (self.input_mouse_modifiers.contains(kbmod::ALT)
+                            || tb.has_block_selection())
+                            && !single_line
+                        {
+                            tb.block_selection_update_visual(pos);
+                        } else {
+                            tb.selection_update_visual(pos);
+                        }
                         tc.preferred_column = tb.cursor_visual_pos().x;
 
                         let height = inner.height();
@@ -2344,14 +2811,17 @@ impl<'a> Context<'a, '_> {
                                 SPEEDS[idx]
                             }
 
-                            let delta_x = calc(text_rect.left, text_rect.right, mouse.x);
-                            let delta_y = calc(text_rect.top, text_rect.bottom, mouse.y);
+                            // Scrolling 4x as far every 4x as long keeps the speed the same.
+                            let factor = if self.tui.low_bandwidth { 4 } else { 1 };
+                            let delta_x = calc(text_rect.left, text_rect.right, mouse.x) * factor;
+                            let delta_y = calc(text_rect.top, text_rect.bottom, mouse.y) * factor;
 
                             tc.scroll_offset.x += delta_x;
                             tc.scroll_offset.y += delta_y;
 
                             if delta_x != 0 || delta_y != 0 {
-                                self.tui.read_timeout = time::Duration::from_millis(25);
+                                self.tui.read_timeout =
+                                    time::Duration::from_millis(25 * factor as u64);
                             }
                         }
                     } else {
@@ -2375,6 +2845,11 @@ impl<'a> Context<'a, '_> {
                             },
                         }
                     }
+                } else if margin_rect.contains(self.tui.mouse_down_position) {
+                    // Clicking the line number of a foldable line folds or unfolds it.
+                    if !self.tui.mouse_is_drag && self.tui.mouse_state == InputMouseState::Left {
+                        tb.toggle_fold_at_visual(pos.y);
+                    }
                 } else if track_rect.contains(self.tui.mouse_down_position) {
                     if self.tui.mouse_state == InputMouseState::Release {
                         tc.scroll_offset_y_drag_start = CoordType::MIN;
@@ -2395,6 +2870,26 @@ impl<'a> Context<'a, '_> {
                                     as CoordType;
                         }
                     }
+                } else if track_rect_horizontal.contains(self.tui.mouse_down_position) {
+                    if self.tui.mouse_state == InputMouseState::Release {
+                        tc.scroll_offset_x_drag_start = CoordType::MIN;
+                    } else if self.tui.mouse_is_drag {
+                        if tc.scroll_offset_x_drag_start == CoordType::MIN {
+                            tc.scroll_offset_x_drag_start = tc.scroll_offset.x;
+                        }
+
+                        let track_width = track_rect_horizontal.width();
+                        let scrollable_width =
+                            tc.content_width(track_width) - track_width;
+
+                        if scrollable_width > 0 {
+                            let trackable = (track_width - tc.thumb_width).max(1);
+                            let delta_x = mouse.x - self.tui.mouse_down_position.x;
+                            tc.scroll_offset.x = tc.scroll_offset_x_drag_start
+                                + (delta_x as i64 * scrollable_width as i64 / trackable as i64)
+                                    as CoordType;
+                        }
+                    }
                 }
 
                 self.set_input_consumed();
@@ -2411,6 +2906,10 @@ impl<'a> Context<'a, '_> {
         let mut write_raw = false;
 
         if let Some(input) = &self.input_text {
+            if tb.is_read_only() {
+                // Leave the text for the caller, who may use it for shortcuts (e.g. a pager).
+                return false;
+            }
             write = input.text.as_bytes();
             write_raw = input.bracketed;
             tc.preferred_column = tb.cursor_visual_pos().x;
@@ -2419,6 +2918,11 @@ impl<'a> Context<'a, '_> {
             let key = input.key();
             let modifiers = input.modifiers();
 
+            if tb.is_read_only() && matches!(key, vk::RETURN | vk::BACK) {
+                // These would only edit. Leave them for the caller, like the text input above.
+                return false;
+            }
+
             make_cursor_visible = true;
 
             match key {
@@ -2446,11 +2950,19 @@ impl<'a> Context<'a, '_> {
                         // If this is just a simple input field, don't consume Enter (= early return).
                         return false;
                     }
+                    if tc.file_type == FileType::StackTrace
+                        && stack_trace::parse_frame(&tb.line_text_at_cursor().0).is_some()
+                    {
+                        // Enter on a stack frame opens its file. That's up to the caller.
+                        return false;
+                    }
                     write = b"\n";
                 }
                 vk::ESCAPE => {
-                    // If there was a selection, clear it and show the cursor (= fallthrough).
-                    if !tb.clear_selection() {
+                    // If there were more cursors or a selection, clear them
+                    // and show the cursor (= fallthrough).
+                    let had_extra_cursors = tb.clear_extra_cursors();
+                    if !tb.clear_selection() && !had_extra_cursors {
                         if single_line {
                             // If this is just a simple input field, don't consume the escape key
                             // (early return) and don't show the cursor (= return false).
@@ -2508,6 +3020,20 @@ impl<'a> Context<'a, '_> {
                         tc.preferred_column = tb.cursor_visual_pos().x;
                     }
                 }
+                vk::HOME | vk::END
+                    if tb.has_extra_cursors() && !modifiers.contains(kbmod::CTRL) =>
+                {
+                    // With several cursors, each one goes to the start or end of its line.
+                    let x = if key == vk::HOME { 0 } else { CoordType::MAX };
+                    tb.for_each_cursor(|tb| {
+                        let pos = Point { x, y: tb.cursor_logical_pos().y };
+                        if modifiers.contains(kbmod::SHIFT) {
+                            tb.selection_update_logical(pos);
+                        } else {
+                            tb.cursor_move_to_logical(pos);
+                        }
+                    });
+                }
                 vk::END => {
                     let logical_before = tb.cursor_logical_pos();
                     let destination = if modifiers.contains(kbmod::CTRL) {
@@ -2596,30 +3122,51 @@ impl<'a> Context<'a, '_> {
                         }
                     }
                 }
+                vk::LEFT | vk::UP | vk::RIGHT | vk::DOWN
+                    if modifiers == kbmod::ALT_SHIFT && !single_line =>
+                {
+                    let delta = match key {
+                        vk::LEFT => Point { x: -1, y: 0 },
+                        vk::UP => Point { x: 0, y: -1 },
+                        vk::RIGHT => Point { x: 1, y: 0 },
+                        _ => Point { x: 0, y: 1 },
+                    };
+                    tb.block_selection_update_delta(delta);
+                }
                 vk::LEFT => {
-                    let granularity = if modifiers.contains(KBMOD_FOR_WORD_NAV) {
+                    let granularity = if modifiers.contains(kbmod::CTRL)
+                        && modifiers.contains(kbmod::ALT)
+                    {
+                        CursorMovement::SubWord
+                    } else if modifiers.contains(KBMOD_FOR_WORD_NAV) {
                         CursorMovement::Word
                     } else {
                         CursorMovement::Grapheme
                     };
-                    if modifiers.contains(kbmod::SHIFT) {
-                        tb.selection_update_delta(granularity, -1);
-                    } else if let Some((beg, _)) = tb.selection_range() {
-                        unsafe { tb.set_cursor(beg) };
-                    } else {
-                        tb.cursor_move_delta(granularity, -1);
-                    }
+                    tb.for_each_cursor(|tb| {
+                        if modifiers.contains(kbmod::SHIFT) {
+                            tb.selection_update_delta(granularity, -1);
+                        } else if let Some((beg, _)) = tb.selection_range() {
+                            unsafe { tb.set_cursor(beg) };
+                        } else {
+                            tb.cursor_move_delta(granularity, -1);
+                        }
+                    });
                 }
                 vk::UP => {
                     match modifiers {
+                        kbmod::NONE | kbmod::SHIFT if tb.has_extra_cursors() => {
+                            Self::textarea_move_cursors_vertical(tb, modifiers, -1);
+                        }
                         kbmod::NONE => {
                             let mut x = tc.preferred_column;
                             let mut y = tb.cursor_visual_pos().y - 1;
 
                             // If there's a selection we put the cursor above it.
                             if let Some((beg, _)) = tb.selection_range() {
-                                x = beg.visual_pos.x;
-                                y = beg.visual_pos.y - 1;
+                                unsafe { tb.set_cursor(beg) };
+                                x = tb.cursor_visual_pos().x;
+                                y = tb.cursor_visual_pos().y - 1;
                                 tc.preferred_column = x;
                             }
 
@@ -2649,34 +3196,45 @@ impl<'a> Context<'a, '_> {
                             });
                         }
                         kbmod::CTRL_ALT => {
-                            // TODO: Add cursor above
+                            tb.add_cursor_vertical(tc.preferred_column, -1);
                         }
+                        kbmod::ALT if !single_line => tb.move_lines(-1),
                         _ => return false,
                     }
                 }
                 vk::RIGHT => {
-                    let granularity = if modifiers.contains(KBMOD_FOR_WORD_NAV) {
+                    let granularity = if modifiers.contains(kbmod::CTRL)
+                        && modifiers.contains(kbmod::ALT)
+                    {
+                        CursorMovement::SubWord
+                    } else if modifiers.contains(KBMOD_FOR_WORD_NAV) {
                         CursorMovement::Word
                     } else {
                         CursorMovement::Grapheme
                     };
-                    if modifiers.contains(kbmod::SHIFT) {
-                        tb.selection_update_delta(granularity, 1);
-                    } else if let Some((_, end)) = tb.selection_range() {
-                        unsafe { tb.set_cursor(end) };
-                    } else {
-                        tb.cursor_move_delta(granularity, 1);
-                    }
+                    tb.for_each_cursor(|tb| {
+                        if modifiers.contains(kbmod::SHIFT) {
+                            tb.selection_update_delta(granularity, 1);
+                        } else if let Some((_, end)) = tb.selection_range() {
+                            unsafe { tb.set_cursor(end) };
+                        } else {
+                            tb.cursor_move_delta(granularity, 1);
+                        }
+                    });
                 }
                 vk::DOWN => match modifiers {
+                    kbmod::NONE | kbmod::SHIFT if tb.has_extra_cursors() => {
+                        Self::textarea_move_cursors_vertical(tb, modifiers, 1);
+                    }
                     kbmod::NONE => {
                         let mut x = tc.preferred_column;
                         let mut y = tb.cursor_visual_pos().y + 1;
 
                         // If there's a selection we put the cursor below it.
                         if let Some((_, end)) = tb.selection_range() {
-                            x = end.visual_pos.x;
-                            y = end.visual_pos.y + 1;
+                            unsafe { tb.set_cursor(end) };
+                            x = tb.cursor_visual_pos().x;
+                            y = tb.cursor_visual_pos().y + 1;
                             tc.preferred_column = x;
                         }
 
@@ -2715,8 +3273,9 @@ impl<'a> Context<'a, '_> {
                         }
                     }
                     kbmod::CTRL_ALT => {
-                        // TODO: Add cursor above
+                        tb.add_cursor_vertical(tc.preferred_column, 1);
                     }
+                    kbmod::ALT if !single_line => tb.move_lines(1),
                     _ => return false,
                 },
                 vk::INSERT => match modifiers {
@@ -2752,6 +3311,12 @@ impl<'a> Context<'a, '_> {
                     }
                     _ => return false,
                 },
+                vk::D => match modifiers {
+                    kbmod::CTRL if !single_line => {
+                        tb.add_cursor_at_next_match();
+                    }
+                    _ => return false,
+                },
                 vk::H => match modifiers {
                     kbmod::CTRL => tb.delete(CursorMovement::Word, -1),
                     _ => return false,
@@ -2806,6 +3371,23 @@ impl<'a> Context<'a, '_> {
         make_cursor_visible
     }
 
+    /// Moves each of several cursors by a visual line, with Shift to select.
+    fn textarea_move_cursors_vertical(
+        tb: &mut TextBuffer,
+        modifiers: InputKeyMod,
+        delta: CoordType,
+    ) {
+        tb.for_each_cursor(|tb| {
+            let pos = tb.cursor_visual_pos();
+            let pos = Point { x: pos.x, y: pos.y + delta };
+            if modifiers == kbmod::SHIFT {
+                tb.selection_update_visual(pos);
+            } else {
+                tb.cursor_move_to_visual(pos);
+            }
+        });
+    }
+
     fn textarea_make_cursor_visible(&self, tc: &mut TextareaContent, node_prev: &Node) {
         let tb = tc.buffer.borrow();
         let mut scroll_x = tc.scroll_offset.x;
@@ -2816,7 +3398,7 @@ impl<'a> Context<'a, '_> {
         scroll_x = scroll_x.min(cursor_x - 10);
         scroll_x = scroll_x.max(cursor_x - text_width + 10);
 
-        let viewport_height = node_prev.inner.height();
+        let viewport_height = node_prev.inner.height() - tc.scrollbar_horizontal as CoordType;
         let cursor_y = tb.cursor_visual_pos().y;
         // Scroll up if the cursor is above the visible area.
         scroll_y = scroll_y.min(cursor_y);
@@ -2832,7 +3414,8 @@ impl<'a> Context<'a, '_> {
         let mut scroll_x = tc.scroll_offset.x;
         let mut scroll_y = tc.scroll_offset.y;
 
-        scroll_x = scroll_x.min(tc.scroll_offset_x_max.max(tb.cursor_visual_pos().x) - 10);
+        let scroll_x_max = tc.scroll_offset_x_max.max(tc.line_width_max);
+        scroll_x = scroll_x.min(scroll_x_max.max(tb.cursor_visual_pos().x) - 10);
         scroll_x = scroll_x.max(0);
         scroll_y = scroll_y.clamp(0, tb.visual_line_count() - 1);
 
@@ -2947,12 +3530,10 @@ impl<'a> Context<'a, '_> {
                     InputMouseState::Release => {
                         sc.scroll_offset_y_drag_start = CoordType::MIN;
                     }
-                    InputMouseState::Scroll => {
-                        if container_rect.contains(self.tui.mouse_position) {
-                            sc.scroll_offset.x += self.input_scroll_delta.x;
-                            sc.scroll_offset.y += self.input_scroll_delta.y;
-                            self.set_input_consumed();
-                        }
+                    InputMouseState::Scroll if container_rect.contains(self.tui.mouse_position) => {
+                        sc.scroll_offset.x += self.input_scroll_delta.x;
+                        sc.scroll_offset.y += self.input_scroll_delta.y;
+                        self.set_input_consumed();
                     }
                     _ => {}
                 }
@@ -3382,21 +3963,7 @@ impl<'a> Context<'a, '_> {
     fn menubar_shortcut(&mut self, shortcut: InputKey) {
         let shortcut_letter = shortcut.value() as u8 as char;
         if shortcut_letter.is_ascii_uppercase() {
-            let mut shortcut_text = ArenaString::new_in(self.arena());
-            if shortcut.modifiers_contains(kbmod::CTRL) {
-                shortcut_text.push_str(self.tui.modifier_translations.ctrl);
-                shortcut_text.push('+');
-            }
-            if shortcut.modifiers_contains(kbmod::ALT) {
-                shortcut_text.push_str(self.tui.modifier_translations.alt);
-                shortcut_text.push('+');
-            }
-            if shortcut.modifiers_contains(kbmod::SHIFT) {
-                shortcut_text.push_str(self.tui.modifier_translations.shift);
-                shortcut_text.push('+');
-            }
-            shortcut_text.push(shortcut_letter);
-
+            let shortcut_text = self.shortcut_text(shortcut);
             self.label("shortcut", &shortcut_text);
         } else {
             self.block_begin("shortcut");
@@ -3691,6 +4258,7 @@ struct NodeAttributes {
     focusable: bool,
     focus_well: bool, // Prevents focus from leaving via Tab
     focus_void: bool, // Prevents focus from entering via Tab
+    inactive: bool,
 }
 
 /// NOTE: Must not contain items that require drop().
@@ -3730,15 +4298,28 @@ struct TextareaContent<'a> {
     // Carries over between frames.
     scroll_offset: Point,
     scroll_offset_y_drag_start: CoordType,
+    scroll_offset_x_drag_start: CoordType,
     scroll_offset_x_max: CoordType,
+    line_width_max: CoordType,
     thumb_height: CoordType,
+    thumb_width: CoordType,
     preferred_column: CoordType,
 
     single_line: bool,
+    scrollbar_vertical: bool,
+    scrollbar_horizontal: bool,
     has_focus: bool,
     file_type: FileType,
 }
 
+impl TextareaContent<'_> {
+    /// The width of the horizontally scrollable content, given a `text_width` wide viewport.
+    /// Includes the room for scrolling beyond the end of the lines.
+    fn content_width(&self, text_width: CoordType) -> CoordType {
+        (self.line_width_max + 1).max(self.scroll_offset.x + text_width)
+    }
+}
+
 /// NOTE: Must not contain items that require drop().
 #[derive(Clone)]
 struct ScrollareaContent {
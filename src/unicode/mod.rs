@@ -4,8 +4,10 @@
 //! Everything related to Unicode lives here.
 
 mod measurement;
+mod segmentation;
 mod tables;
 mod utf8;
 
 pub use measurement::*;
+pub use segmentation::*;
 pub use utf8::*;
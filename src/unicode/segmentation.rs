@@ -0,0 +1,76 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+use super::Utf8Chars;
+use super::tables::*;
+use crate::document::ReadableDocument;
+use crate::simd::memrchr2;
+
+/// Returns the end of the grapheme cluster that starts at `offset`,
+/// or `offset` itself if it's at the end of the document.
+pub fn grapheme_forward<D: ReadableDocument + ?Sized>(doc: &D, offset: usize) -> usize {
+    // `read_forward` doesn't split grapheme clusters, so the chunk contains all of it.
+    let chunk = doc.read_forward(offset);
+    let mut chars = Utf8Chars::new(chunk, 0);
+    let Some(ch) = chars.next() else {
+        return offset;
+    };
+    let mut props_last_char = ucd_grapheme_cluster_lookup(ch);
+    let mut state = 0;
+
+    loop {
+        let end = chars.offset();
+        let Some(ch) = chars.next() else {
+            return offset + end;
+        };
+        let props = ucd_grapheme_cluster_lookup(ch);
+        state = ucd_grapheme_cluster_joins(state, props_last_char, props);
+        if ucd_grapheme_cluster_joins_done(state) {
+            return offset + end;
+        }
+        props_last_char = props;
+    }
+}
+
+/// Returns the start of the grapheme cluster that ends at `offset`,
+/// or `offset` itself if it's at the start of the document.
+pub fn grapheme_backward<D: ReadableDocument + ?Sized>(doc: &D, offset: usize) -> usize {
+    // Clusters can only be segmented front to back. Both the start of a chunk and the start
+    // of a line are cluster boundaries, so segmenting starts at whichever is closer.
+    let chunk = doc.read_backward(offset);
+    if chunk.is_empty() {
+        return offset;
+    }
+    let chunk_beg = offset - chunk.len();
+    let mut beg = match memrchr2(b'\n', b'\n', chunk, chunk.len() - 1) {
+        Some(i) => chunk_beg + i + 1,
+        None => chunk_beg,
+    };
+
+    loop {
+        let end = grapheme_forward(doc, beg);
+        if end >= offset || end == beg {
+            return beg;
+        }
+        beg = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grapheme_navigation() {
+        // "a", a woman facepalming with skin tone, "\r\n", "e" with an acute accent.
+        let text = "a🤦🏼‍♀️\r\ne\u{301}".as_bytes();
+        let stops = [0, 1, 18, 20, 23];
+
+        for w in stops.windows(2) {
+            assert_eq!(grapheme_forward(&text, w[0]), w[1]);
+            assert_eq!(grapheme_backward(&text, w[1]), w[0]);
+        }
+        assert_eq!(grapheme_forward(&text, 23), 23);
+        assert_eq!(grapheme_backward(&text, 0), 0);
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT License.
+
+//! JavaScript bindings for the [`EditorCore`], for a web page to edit one document with
+//! the editor's highlighting, indentation and undo. `web/index.html` is an example.
+//!
+//! It's built for the `wasm32-unknown-unknown` target without the `tui` feature and turned
+//! into a module with `wasm-bindgen`, see `web/README.md`.
+//!
+//! ```js
+//! const editor = new WebEditor("fn main() {}\n", "main.rs");
+//! editor.key("End", false, false);
+//! editor.insert(" // TODO");
+//! JSON.parse(editor.lines(0, 10)); // [[["#b48ead", "fn"], ["#c0c5ce", " "], ...], ...]
+//! ```
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use wasm_bindgen::prelude::*;
+
+use crate::buffer::CursorMovement;
+use crate::editor::EditorCore;
+use crate::helpers::{CoordType, Point};
+use crate::{arena, helpers};
+
+#[wasm_bindgen]
+pub struct WebEditor {
+    core: EditorCore,
+    index: usize,
+}
+
+#[wasm_bindgen]
+impl WebEditor {
+    /// Opens `text` as a document. The `filename` picks the syntax highlighting.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str, filename: &str) -> Result<WebEditor, JsError> {
+        static INIT: Once = Once::new();
+        let mut result = Ok(());
+        INIT.call_once(|| result = arena::init(16 * helpers::MEBI));
+        result.map_err(|err| JsError::new(&format!("{err:?}")))?;
+
+        let mut core = EditorCore::new();
+        let index = core.new_document().map_err(|err| JsError::new(&format!("{err:?}")))?;
+        let doc = core.document_mut(index);
+        doc.set_path(PathBuf::from(filename));
+        doc.buffer.borrow_mut().set_text(text);
+        Ok(Self { core, index })
+    }
+
+    /// Applies a key press, named like `KeyboardEvent.key`. Returns false for keys it
+    /// doesn't handle, which the page may then handle itself.
+    pub fn key(&mut self, key: &str, ctrl: bool, shift: bool) -> bool {
+        let mut tb = self.core.document(self.index).buffer.borrow_mut();
+        let granularity = if ctrl { CursorMovement::Word } else { CursorMovement::Grapheme };
+        let line = tb.cursor_logical_pos().y;
+        let move_to = |tb: &mut crate::buffer::TextBuffer, pos: Point| {
+            if shift { tb.selection_update_logical(pos) } else { tb.cursor_move_to_logical(pos) }
+        };
+
+        match key {
+            "Backspace" => tb.delete(granularity, -1),
+            "Delete" => tb.delete(granularity, 1),
+            "Enter" => tb.write(b"\n", false),
+            "Tab" if shift => tb.unindent(),
+            "Tab" => tb.write(b"\t", false),
+            "ArrowLeft" | "ArrowRight" => {
+                let delta = if key == "ArrowLeft" { -1 } else { 1 };
+                if shift {
+                    tb.selection_update_delta(granularity, delta);
+                } else {
+                    tb.clear_selection();
+                    tb.cursor_move_delta(granularity, delta);
+                }
+            }
+            "ArrowUp" | "ArrowDown" => {
+                let y = if key == "ArrowUp" { line - 1 } else { line + 1 };
+                let x = tb.cursor_logical_pos().x;
+                move_to(&mut tb, Point { x, y: y.max(0) });
+            }
+            "Home" => move_to(&mut tb, Point { x: 0, y: line }),
+            "End" => move_to(&mut tb, Point { x: CoordType::MAX, y: line }),
+            "a" if ctrl => tb.select_all(),
+            "z" if ctrl => tb.undo(),
+            "y" | "Z" if ctrl => tb.redo(),
+            _ if ctrl => return false,
+            // Printable characters are a single one, named keys like "Shift" are longer.
+            _ if key.chars().count() == 1 => tb.write(key.as_bytes(), false),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Types `text`, e.g. what was pasted, replacing the selection.
+    pub fn insert(&mut self, text: &str) {
+        self.core.document(self.index).buffer.borrow_mut().write(text.as_bytes(), true);
+    }
+
+    /// Moves the cursor to the 0-based `line` and `column`, e.g. where the page was clicked.
+    pub fn click(&mut self, line: CoordType, column: CoordType) {
+        let mut tb = self.core.document(self.index).buffer.borrow_mut();
+        tb.cursor_move_to_logical(Point { x: column, y: line });
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.core.document(self.index).buffer.borrow_mut().save_as_string(&mut text);
+        text
+    }
+
+    pub fn line_count(&self) -> CoordType {
+        self.core.document(self.index).buffer.borrow().logical_line_count()
+    }
+
+    /// The 0-based line of the cursor.
+    pub fn cursor_line(&self) -> CoordType {
+        self.core.document(self.index).buffer.borrow().cursor_logical_pos().y
+    }
+
+    /// The 0-based column of the cursor, in characters.
+    pub fn cursor_column(&self) -> CoordType {
+        self.core.document(self.index).buffer.borrow().cursor_logical_pos().x
+    }
+
+    /// Switches the syntax highlighting to another theme. Returns false if there's none
+    /// with that name.
+    pub fn set_theme(&mut self, name: &str) -> bool {
+        self.core.set_theme(name)
+    }
+
+    /// Returns `count` lines from the 0-based `first` one as a JSON array. Each line is an
+    /// array of `[color, text]` pairs, with the color as `#rrggbb`.
+    pub fn lines(&mut self, first: CoordType, count: CoordType) -> String {
+        let last = (first + count).min(self.line_count());
+        let mut json = String::from("[");
+        for line in first.max(0)..last {
+            if json.len() > 1 {
+                json.push(',');
+            }
+            json.push('[');
+            for (i, (style, text)) in self.core.highlight_line(self.index, line).iter().enumerate()
+            {
+                let c = style.foreground;
+                let comma = if i == 0 { "" } else { "," };
+                _ = write!(json, "{comma}[\"#{:02x}{:02x}{:02x}\",", c.r, c.g, c.b);
+                json_string(&mut json, text.trim_end_matches(['\r', '\n']));
+                json.push(']');
+            }
+            json.push(']');
+        }
+        json.push(']');
+        json
+    }
+}
+
+fn json_string(dst: &mut String, text: &str) {
+    dst.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => dst.push_str("\\\""),
+            '\\' => dst.push_str("\\\\"),
+            c if c < ' ' => _ = write!(dst, "\\u{:04x}", c as u32),
+            c => dst.push(c),
+        }
+    }
+    dst.push('"');
+}